@@ -0,0 +1,13 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 color;
+
+uniform mat4 view;
+uniform mat4 pers;
+
+out vec3 line_color;
+
+void main() {
+    line_color = color;
+    gl_Position = pers * view * vec4(position, 1.0);
+}
@@ -9,23 +9,33 @@ struct DirectionalLight {
 };
 
 uniform DirectionalLight dir_light;
-uniform vec3 color;
+uniform float ambient_strength;
+uniform vec3 view_position;
+
+//Material, the defaults match Material::default()
+uniform vec3 color = vec3(0.7, 0.7, 0.7);
+uniform float specular_strength = 0.5;
+uniform float shininess = 32.0;
+uniform float opacity = 1.0;
 
 vec3 calculate_dir_light(DirectionalLight light, vec3 normal) {
     vec3 light_dir = normalize(light.direction);
-    float diff = max(dot(frag_norm, light_dir), 0.0);
+    float diff = max(dot(normal, light_dir), 0.0);
     vec3 diffuse = diff * light.color;
 
-    return diffuse;
+    //Blinn-Phong highlight
+    vec3 view_dir = normalize(view_position - frag_position);
+    vec3 halfway = normalize(light_dir + view_dir);
+    float spec = diff > 0.0 ? pow(max(dot(normal, halfway), 0.0), shininess) : 0.0;
+    vec3 specular = specular_strength * spec * light.color;
+
+    return diffuse + specular;
 }
 
 void main() {
-    //vec3 object_color = vec3(0.7, 0.7, 0.7);
-
-    float ambient_strength = 0.4;
     vec3 ambient = ambient_strength * color;
 
-    vec3 dir_light = calculate_dir_light(dir_light, frag_norm);
+    vec3 dir_light = calculate_dir_light(dir_light, normalize(frag_norm));
     vec3 result = ( dir_light + ambient) * color;
-    gl_FragColor = vec4(result, 1.0);
+    gl_FragColor = vec4(result, opacity);
 }
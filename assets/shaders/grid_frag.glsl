@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec3 line_color;
+in vec3 frag_position;
+
+uniform vec3 view_position;
+//Matches the scene clear color, far lines blend into it instead of flickering
+uniform vec3 background = vec3(0.1, 0.1, 0.1);
+uniform float fade_start = 10.0;
+uniform float fade_end = 45.0;
+
+out vec4 frag_color;
+
+void main() {
+    float distance = length(frag_position.xz - view_position.xz);
+    float fade = smoothstep(fade_start, fade_end, distance);
+    frag_color = vec4(mix(line_color, background, fade), 1.0);
+}
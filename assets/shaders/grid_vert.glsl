@@ -0,0 +1,15 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 color;
+
+uniform mat4 view;
+uniform mat4 pers;
+
+out vec3 line_color;
+out vec3 frag_position;
+
+void main() {
+    line_color = color;
+    frag_position = position;
+    gl_Position = pers * view * vec4(position, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+layout (location = 3) in mat4 instance_model;
+
+uniform mat4 view;
+uniform mat4 pers;
+
+out vec3 frag_norm;
+out vec3 frag_position;
+
+void main() {
+
+    frag_norm = mat3(transpose(inverse(instance_model))) * normal;
+    frag_position = vec3(instance_model * vec4(position, 1.0));
+
+    gl_Position = (pers * view * instance_model) * vec4(position, 1.0);
+}
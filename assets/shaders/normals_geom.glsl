@@ -0,0 +1,36 @@
+#version 330 core
+layout (triangles) in;
+layout (line_strip, max_vertices = 8) out;
+
+in vec3 vertex_normal[];
+
+uniform mat4 view;
+uniform mat4 pers;
+uniform float normal_length;
+
+out vec3 line_color;
+
+const vec3 VERTEX_NORMAL_COLOR = vec3(1.0, 0.9, 0.2);
+const vec3 FACE_NORMAL_COLOR = vec3(0.2, 0.9, 1.0);
+
+void emit_line(vec4 from, vec3 direction, vec3 color) {
+    line_color = color;
+    gl_Position = pers * view * from;
+    EmitVertex();
+
+    gl_Position = pers * view * (from + vec4(direction * normal_length, 0.0));
+    EmitVertex();
+    EndPrimitive();
+}
+
+void main() {
+    for (int i = 0; i < 3; i++) {
+        emit_line(gl_in[i].gl_Position, vertex_normal[i], VERTEX_NORMAL_COLOR);
+    }
+
+    //Note(teddy) Counter clockwise winding, the same faces the culling keeps
+    vec3 edge_a = gl_in[1].gl_Position.xyz - gl_in[0].gl_Position.xyz;
+    vec3 edge_b = gl_in[2].gl_Position.xyz - gl_in[0].gl_Position.xyz;
+    vec4 center = (gl_in[0].gl_Position + gl_in[1].gl_Position + gl_in[2].gl_Position) / 3.0;
+    emit_line(center, normalize(cross(edge_a, edge_b)), FACE_NORMAL_COLOR);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+
+uniform mat4 model;
+
+out vec3 vertex_normal;
+
+//Note(teddy) Stays in world space, the geometry shader grows the lines before projecting them
+void main() {
+    vertex_normal = normalize(mat3(transpose(inverse(model))) * normal);
+    gl_Position = model * vec4(position, 1.0);
+}
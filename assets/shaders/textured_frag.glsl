@@ -0,0 +1,43 @@
+#version 330 core
+
+in vec3 frag_position;
+in vec3 frag_norm;
+in vec2 frag_text_cords;
+
+struct DirectionalLight {
+    vec3 color;
+    vec3 direction;
+};
+
+uniform DirectionalLight dir_light;
+uniform float ambient_strength;
+uniform vec3 view_position;
+uniform sampler2D diffuse_map;
+
+//Material, the defaults match Material::default()
+uniform float specular_strength = 0.5;
+uniform float shininess = 32.0;
+uniform float opacity = 1.0;
+
+vec3 calculate_dir_light(DirectionalLight light, vec3 normal, vec3 color) {
+    vec3 light_dir = normalize(light.direction);
+    float diff = max(dot(normal, light_dir), 0.0);
+    vec3 diffuse = diff * light.color * color;
+
+    //Blinn-Phong highlight, it takes the light's color and not the texture's
+    vec3 view_dir = normalize(view_position - frag_position);
+    vec3 halfway = normalize(light_dir + view_dir);
+    float spec = diff > 0.0 ? pow(max(dot(normal, halfway), 0.0), shininess) : 0.0;
+    vec3 specular = specular_strength * spec * light.color;
+
+    return diffuse + specular;
+}
+
+void main() {
+    vec3 color = texture(diffuse_map, frag_text_cords).rgb;
+
+    vec3 ambient = ambient_strength * color;
+
+    vec3 dir_light = calculate_dir_light(dir_light, normalize(frag_norm), color);
+    gl_FragColor = vec4(dir_light + ambient, opacity);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+layout (location = 0) in vec4 position;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in vec2 text_cords;
+
+uniform mat4 view;
+uniform mat4 pers;
+uniform mat4 model;
+
+out vec3 frag_norm;
+out vec3 frag_position;
+out vec2 frag_text_cords;
+
+void main() {
+    frag_norm = mat3(transpose(inverse(model))) * normal;
+    frag_position = vec3(model * position);
+    frag_text_cords = text_cords;
+
+    gl_Position = (pers * view * model) * position;
+}
@@ -0,0 +1,10 @@
+#version 330
+in vec2 image_cords;
+
+out vec4 color;
+
+uniform sampler2D image;
+
+void main() {
+    color = texture(image, image_cords);
+}
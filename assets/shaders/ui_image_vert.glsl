@@ -0,0 +1,11 @@
+#version 330
+layout (location=0) in vec3 vertex;
+layout (location=1) in vec2 uv;
+out vec2 image_cords;
+
+uniform mat4 projection;
+
+void main() {
+    image_cords = uv;
+    gl_Position = projection * vec4(vertex, 1.0);
+}
@@ -1,8 +1,37 @@
 #version 330
+in vec2 frag_position;
 out vec4 color;
 
-uniform vec3 quad_color;
+//rgb and the alpha the whole quad is blended with
+uniform vec4 quad_color;
+
+//x, y of the bottom left corner then width, height, in the same cords as the vertices
+uniform vec4 quad_rect;
+uniform float corner_radius;
+uniform vec3 border_color;
+uniform float border_width;
+
+//Signed distance to the edge of a box with rounded corners, negative inside
+float rounded_box(vec2 p, vec2 half_size, float radius) {
+    vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
 
 void main() {
-    color = vec4(quad_color, 1.0);
-}
\ No newline at end of file
+    vec2 half_size = quad_rect.zw * 0.5;
+    float radius = clamp(corner_radius, 0.0, min(half_size.x, half_size.y));
+    float distance = rounded_box(frag_position - (quad_rect.xy + half_size), half_size, radius);
+
+    float coverage = clamp(0.5 - distance, 0.0, 1.0);
+    if (coverage <= 0.0) {
+        //Keeps the cut off corners out of the depth buffer
+        discard;
+    }
+
+    vec3 fill = quad_color.rgb;
+    if (border_width > 0.0) {
+        fill = mix(quad_color.rgb, border_color, clamp(distance + border_width + 0.5, 0.0, 1.0));
+    }
+
+    color = vec4(fill, quad_color.a * coverage);
+}
@@ -3,6 +3,9 @@ layout (location=0) in vec3 vertex;
 
 uniform mat4 projection;
 
+out vec2 frag_position;
+
 void main() {
+    frag_position = vertex.xy;
     gl_Position = projection * vec4(vertex, 1.0);
 }
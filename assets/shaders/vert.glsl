@@ -12,7 +12,7 @@ out vec3 frag_position;
 void main() {
     
     frag_norm = mat3(transpose(inverse(model))) * normal;
-    frag_position = position;
+    frag_position = vec3(model * vec4(position, 1.0));
 
     gl_Position = (pers * view * model) * vec4(position, 1.0);
 }
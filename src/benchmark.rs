@@ -0,0 +1,390 @@
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use nalgebra::Vector3;
+
+use crate::core::Engine;
+use crate::game_world::level_meta::unix_now;
+use crate::game_world::world::{EntitySpec, World};
+use crate::utils::{write_atomic, SeededRng};
+
+pub const BENCHMARK_OUTPUT_DIR: &'static str = "./benchmarks/";
+const BENCHMARK_SEED: u64 = 0x1248;
+const WARMUP_FRAMES: u32 = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BenchmarkPreset {
+    ///5k small instances of 3 meshes on a jittered grid
+    Forest,
+    ///1k larger meshes with unique transforms
+    City,
+}
+
+impl BenchmarkPreset {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "forest" => Ok(BenchmarkPreset::Forest),
+            "city" => Ok(BenchmarkPreset::City),
+            _ => Err(format!("Unknown benchmark preset {}, expected forest or city", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchmarkPreset::Forest => "forest",
+            BenchmarkPreset::City => "city",
+        }
+    }
+
+    fn meshes(&self) -> [&'static str; 3] {
+        match self {
+            BenchmarkPreset::Forest => ["cube.obj", "sphere.obj", "suzanne.obj"],
+            BenchmarkPreset::City => ["nyatiti.obj", "suzanne.obj", "cube.obj"],
+        }
+    }
+
+    ///Same specs on every run, everything random comes from a fixed seed
+    pub fn scene(&self, shader_label: &str) -> Vec<EntitySpec> {
+        let mut rng = SeededRng::new(BENCHMARK_SEED);
+        let meshes = self.meshes();
+        let spec = |mesh: &str, position: Vector3<f32>, scale: f32| EntitySpec {
+            mesh_label: String::from(mesh),
+            shader_label: String::from(shader_label),
+            position,
+            scale,
+        };
+
+        match self {
+            BenchmarkPreset::Forest => {
+                let (count, columns, spacing) = (5000, 71, 2.0);
+                (0..count)
+                    .map(|i| {
+                        let jitter = Vector3::new(rng.range(-0.5, 0.5), 0.0, rng.range(-0.5, 0.5));
+                        let cell = Vector3::new((i % columns) as f32, 0.0, (i / columns) as f32) * spacing;
+                        spec(meshes[rng.index(meshes.len())], cell + jitter, rng.range(0.5, 1.0))
+                    })
+                    .collect()
+            }
+            BenchmarkPreset::City => (0..1000)
+                .map(|_| {
+                    let position = Vector3::new(rng.range(0.0, 140.0), 0.0, rng.range(0.0, 140.0));
+                    spec(meshes[rng.index(meshes.len())], position, rng.range(2.0, 5.0))
+                })
+                .collect(),
+        }
+    }
+
+    ///Both presets cover roughly 140 x 140 units, the camera circles the edge looking at the middle
+    pub fn camera_path(&self) -> CameraPath {
+        let center = Vector3::new(70.0, 0.0, 70.0);
+        let corner = |x: f32, z: f32, time: f32| CameraKey {
+            time,
+            position: Vector3::new(x, 25.0, z),
+            target: center,
+        };
+
+        CameraPath {
+            keys: vec![
+                corner(-20.0, -20.0, 0.0),
+                corner(160.0, -20.0, 5.0),
+                corner(160.0, 160.0, 10.0),
+                corner(-20.0, 160.0, 15.0),
+                corner(-20.0, -20.0, 20.0),
+            ],
+        }
+    }
+}
+
+///Pulls `--benchmark <preset>` out of the arguments, the rest go to the light settings parser
+pub fn split_benchmark_arg<I: Iterator<Item = String>>(args: I) -> (Option<String>, Vec<String>) {
+    let mut benchmark = None;
+    let mut rest = vec![];
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        if arg == "--benchmark" {
+            benchmark = Some(args.next().unwrap_or_default());
+        } else {
+            rest.push(arg);
+        }
+    }
+
+    (benchmark, rest)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraKey {
+    ///Seconds from the start of the path
+    pub time: f32,
+    pub position: Vector3<f32>,
+    pub target: Vector3<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    pub keys: Vec<CameraKey>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |key| key.time)
+    }
+
+    ///Position and target at the time, linear between keys and clamped at both ends
+    pub fn sample(&self, time: f32) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let first = self.keys.first()?;
+        if time <= first.time {
+            return Some((first.position, first.target));
+        }
+
+        for pair in self.keys.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if time <= to.time {
+                let t = (time - from.time) / (to.time - from.time).max(std::f32::EPSILON);
+                return Some((from.position.lerp(&to.position, t), from.target.lerp(&to.target, t)));
+            }
+        }
+
+        self.keys.last().map(|key| (key.position, key.target))
+    }
+
+    pub fn apply(&self, time: f32, engine: &mut Engine) {
+        if let Some((position, target)) = self.sample(time) {
+            engine.camera.position = position;
+            //Note(teddy) The camera sees down -camera_front, see Camera::view_direction
+            if let Some(direction) = (target - position).try_normalize(std::f32::EPSILON) {
+                engine.camera.camera_front = -direction;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FrameSample {
+    pub frame_time: Duration,
+    pub draw_calls: u32,
+    ///Scene and ui passes on the cpu, what RENDER_SYSTEM logs
+    pub render_time: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct FrameRecorder {
+    pub samples: Vec<FrameSample>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkSummary {
+    pub preset: String,
+    pub frames: usize,
+    pub average_ms: f32,
+    pub p95_ms: f32,
+    pub p99_ms: f32,
+    pub average_draw_calls: f32,
+    pub average_render_ms: f32,
+}
+
+impl fmt::Display for BenchmarkSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "benchmark {}: {} frames, avg {:.2} ms, 95th {:.2} ms, 99th {:.2} ms, {:.0} draw calls, render system avg {:.2} ms",
+            self.preset, self.frames, self.average_ms, self.p95_ms, self.p99_ms, self.average_draw_calls, self.average_render_ms
+        )
+    }
+}
+
+impl FrameRecorder {
+    pub fn record(&mut self, frame_time: Duration, draw_calls: u32, render_time: Duration) {
+        self.samples.push(FrameSample { frame_time, draw_calls, render_time });
+    }
+
+    pub fn summary(&self, preset: &str) -> BenchmarkSummary {
+        let mut times: Vec<f32> = self.samples.iter().map(|sample| milliseconds(sample.frame_time)).collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let frames = times.len().max(1) as f32;
+
+        BenchmarkSummary {
+            preset: String::from(preset),
+            frames: times.len(),
+            average_ms: times.iter().sum::<f32>() / frames,
+            p95_ms: percentile(&times, 0.95),
+            p99_ms: percentile(&times, 0.99),
+            average_draw_calls: self.samples.iter().map(|sample| sample.draw_calls as f32).sum::<f32>() / frames,
+            average_render_ms: self.samples.iter().map(|sample| milliseconds(sample.render_time)).sum::<f32>() / frames,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,frame_time_ms,draw_calls,render_system_ms\n");
+        for (i, sample) in self.samples.iter().enumerate() {
+            csv.push_str(&format!("{},{:.4},{},{:.4}\n", i, milliseconds(sample.frame_time), sample.draw_calls, milliseconds(sample.render_time)));
+        }
+        csv
+    }
+}
+
+#[inline]
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+///Nearest rank percentile of already sorted values
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = (fraction * sorted.len() as f32).ceil() as usize;
+    sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+enum BenchmarkState {
+    ///Waiting for the loader thread to make every preset mesh resident
+    Loading,
+    Warmup(u32),
+    Running(Instant),
+}
+
+pub struct Benchmark {
+    preset: BenchmarkPreset,
+    path: CameraPath,
+    recorder: FrameRecorder,
+    state: BenchmarkState,
+}
+
+impl Benchmark {
+    pub fn new(preset: BenchmarkPreset) -> Self {
+        Self {
+            preset,
+            path: preset.camera_path(),
+            recorder: FrameRecorder::default(),
+            state: BenchmarkState::Loading,
+        }
+    }
+
+    pub fn populate(&self, world: &mut World, shader_label: &str) {
+        for spec in self.preset.scene(shader_label).iter() {
+            world.spawn(spec);
+        }
+    }
+
+    ///Call once per frame with the time the last frame took, returns the summary once the path has finished
+    pub fn update(&mut self, engine: &mut Engine, world: &World, frame_time: Duration) -> Option<BenchmarkSummary> {
+        self.path.apply(0.0, engine);
+
+        match self.state {
+            BenchmarkState::Loading => {
+                let loaded = match world.resources.try_read() {
+                    Ok(resources) => self
+                        .preset
+                        .meshes()
+                        .iter()
+                        .all(|mesh| resources.mesh(mesh).map_or(false, |mesh| mesh.mesh_type.is_some())),
+                    Err(_) => false,
+                };
+
+                if loaded {
+                    self.state = BenchmarkState::Warmup(WARMUP_FRAMES);
+                }
+                None
+            }
+
+            BenchmarkState::Warmup(0) => {
+                self.state = BenchmarkState::Running(Instant::now());
+                None
+            }
+
+            BenchmarkState::Warmup(frames) => {
+                self.state = BenchmarkState::Warmup(frames - 1);
+                None
+            }
+
+            BenchmarkState::Running(start) => {
+                let elapsed = start.elapsed().as_secs_f32();
+                let stats = &engine.render_stats;
+                self.recorder.record(frame_time, engine.frame_draw_calls, stats.scene.time + stats.ui.time);
+
+                if elapsed >= self.path.duration() {
+                    return Some(self.recorder.summary(self.preset.name()));
+                }
+
+                self.path.apply(elapsed, engine);
+                None
+            }
+        }
+    }
+
+    ///Writes the per frame timings, one CSV per run so branches can be compared side by side
+    pub fn write_csv(&self, directory: &Path) -> Result<String, String> {
+        let path = directory.join(format!("{}_{}.csv", self.preset.name(), unix_now()));
+        write_atomic(&path, self.recorder.to_csv().as_bytes())?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_deterministic() {
+        let forest = BenchmarkPreset::Forest.scene("default");
+        assert_eq!(forest.len(), 5000);
+        assert_eq!(forest, BenchmarkPreset::Forest.scene("default"));
+
+        let mut meshes: Vec<&str> = forest.iter().map(|spec| spec.mesh_label.as_str()).collect();
+        meshes.sort();
+        meshes.dedup();
+        assert_eq!(meshes, vec!["cube.obj", "sphere.obj", "suzanne.obj"]);
+
+        let city = BenchmarkPreset::City.scene("default");
+        assert_eq!(city.len(), 1000);
+        assert!(city.iter().all(|spec| spec.scale >= 2.0));
+        assert!(BenchmarkPreset::from_name("desert").is_err());
+    }
+
+    #[test]
+    fn test_camera_path_sampling() {
+        let path = BenchmarkPreset::Forest.camera_path();
+        assert_eq!(path.duration(), 20.0);
+
+        let (start, target) = path.sample(0.0).unwrap();
+        assert_eq!(start, Vector3::new(-20.0, 25.0, -20.0));
+        assert_eq!(target, Vector3::new(70.0, 0.0, 70.0));
+
+        let (halfway, _) = path.sample(2.5).unwrap();
+        assert!((halfway - Vector3::new(70.0, 25.0, -20.0)).norm() < 1e-4);
+        assert_eq!(path.sample(100.0).unwrap().0, start);
+    }
+
+    #[test]
+    fn test_summary_and_csv() {
+        let mut recorder = FrameRecorder::default();
+        for i in 1..=100 {
+            recorder.record(Duration::from_millis(i), 10, Duration::from_millis(2));
+        }
+
+        let summary = recorder.summary("forest");
+        assert_eq!(summary.frames, 100);
+        assert!((summary.average_ms - 50.5).abs() < 1e-3);
+        assert!((summary.p95_ms - 95.0).abs() < 1e-3);
+        assert!((summary.p99_ms - 99.0).abs() < 1e-3);
+        assert_eq!(summary.average_draw_calls, 10.0);
+        assert!((summary.average_render_ms - 2.0).abs() < 1e-3);
+
+        let csv = recorder.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 101);
+        assert_eq!(lines[0], "frame,frame_time_ms,draw_calls,render_system_ms");
+        assert_eq!(lines[1], "0,1.0000,10,2.0000");
+    }
+
+    #[test]
+    fn test_split_benchmark_arg() {
+        let args = vec!["--ambient", "0.2", "--benchmark", "city"].into_iter().map(String::from);
+        let (benchmark, rest) = split_benchmark_arg(args);
+        assert_eq!(benchmark, Some(String::from("city")));
+        assert_eq!(rest, vec![String::from("--ambient"), String::from("0.2")]);
+    }
+}
@@ -1,28 +1,66 @@
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::ffi::{c_void, CString};
 use std::hash::{Hash, Hasher};
 use std::ptr::null;
+use std::rc::Rc;
 use std::convert::TryInto;
 use std::time::Instant;
 
 use freetype::freetype;
-use glfw::{Action, FlushedMessages, Key, MouseButton, WindowEvent};
+use glfw::{Action, Cursor, FlushedMessages, Key, MouseButton, StandardCursor, WindowEvent};
 use nalgebra::{Matrix4, Point2, Point3, Vector3, Vector4};
-use ncollide3d::query::Ray;
+use ncollide3d::query::{Proximity, Ray};
+use nphysics3d::object::ActivationStatus;
 
-use crate::game_world::world::{EntityID, World, FONT_ASSETS_DIR};
+use crate::game_world::world::{EntityID, JointID, World, FONT_ASSETS_DIR};
 use crate::gl_bindings::Display;
+use crate::systems::physics::PhysicsStats;
 use crate::systems::system::SystemType;
-use crate::ui::ui::{propagate_button_click, propagate_cursor_pos_to_ui, UITree, View};
-use crate::utils::Cords;
+use crate::ui::ui::{propagate_button_click, propagate_char, propagate_cursor_pos_to_ui, propagate_key_stroke, propagate_scroll, CursorIcon, UITree, UiResources, View, ViewDimens, ViewPosition, ViewRect};
+use crate::utils::{framing_distance, Aabb, Cords, ViewportRect};
 use crate::logs::LogManager;
+use crate::renderer::debug_draw::DebugDraw;
+use crate::renderer::image_writer::{ImageWriter, ShutdownPolicy};
+use crate::renderer::stats::{self, RenderStats};
+use crate::ui::tween::Easing;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum EventType {
     EntityCreated(EntityID),
     EntityRemoved(EntityID),
     CastRay(CastRayDat),
     RayCasted(CastedRay),
+    Ui(UiEvent),
+    ///Two entities' colliders started touching, a is the smaller id
+    CollisionStarted { a: EntityID, b: EntityID },
+    CollisionEnded { a: EntityID, b: EntityID },
+    ///Overlap of colliders that don't push each other apart, like sensors
+    ProximityChanged { a: EntityID, b: EntityID, state: Proximity },
+    ///Sent by the World's apply_impulse and friends, held pending until the entity's body is built
+    Physics { entity: EntityID, command: PhysicsCommand },
+    JointAdded(JointID),
+    JointRemoved(JointID),
+}
+
+///Runtime pushes on an entity's rigid body, carried out by the physics system
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhysicsCommand {
+    ApplyImpulse(Vector3<f32>),
+    ///Only acts on the next step, send it every frame to keep pushing
+    ApplyForce(Vector3<f32>),
+    SetLinearVelocity(Vector3<f32>),
+    SetAngularVelocity(Vector3<f32>),
+    WakeUp,
+}
+
+///Ui input that lands outside every view, for the game or the editor to act on
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiEvent {
+    ///A dragged view was let go over no view, cords are window cords
+    DroppedOnWorld { payload: String, cords: Cords<f32> },
+    ///A button went down over no view
+    ClickedWorld { button: MouseButton, cords: Cords<f32> },
 }
 
 ///Some events will be locked to routine running in a seperate thread like loading assets.
@@ -31,6 +69,35 @@ pub enum EventType {
 
 static mut EVENT_IDS: u64 = 0;
 
+///CastRay id used by mouse picking, tools use their own ids to tell their results apart
+pub const PICKING_RAY_ID: usize = 0;
+
+//Note(teddy) A few full frames at most, each job holds a copy of the framebuffer
+const IMAGE_WRITER_QUEUE_SIZE: usize = 4;
+///Tiny or flat meshes are framed as if they were this big, the camera would end up almost inside them
+const MIN_FRAMING_RADIUS: f32 = 0.5;
+const PERSPECTIVE_NEAR: f32 = 0.1;
+const PERSPECTIVE_FAR: f32 = 100000.0;
+//Note(teddy) The orthographic volume reaches this far both ways from the camera, where it sits along
+//the view direction doesn't hide anything
+const ORTHOGRAPHIC_DEPTH: f32 = 10000.0;
+const DEFAULT_ORTHOGRAPHIC_HEIGHT: f32 = 20.0;
+///Degrees turned per pixel the cursor moves, mouse look and orbiting alike
+const LOOK_SENSITIVITY: f32 = 0.5;
+///Switching to orbit turns around a point this far in front of the camera
+const DEFAULT_ORBIT_DISTANCE: f32 = 10.0;
+const MIN_ORBIT_DISTANCE: f32 = 0.1;
+const MAX_ORBIT_DISTANCE: f32 = 10000.0;
+///Each scroll step moves this much of the way to the target
+const ORBIT_ZOOM_STEP: f32 = 0.1;
+///Panning moves the target this much of the orbit distance per pixel
+const ORBIT_PAN_SPEED: f32 = 0.002;
+///WASD speed in units a second, what the old 0.05 a frame came to at 60 fps
+const FLY_SPEED: f32 = 3.0;
+
+const UI_SCALE_MIN: f32 = 0.5;
+const UI_SCALE_MAX: f32 = 4.0;
+
 #[derive(Debug, Clone)]
 pub struct Event {
     pub id: u64,
@@ -86,16 +153,35 @@ impl Event {
     }
 }
 
+///Which collision groups a ray is allowed to hit, see the group constants in the physics system
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayFilter {
+    All,
+    ///Level geometry only, for tools that place things on the floor
+    StaticOnly,
+    ///Skips editor helpers and sensors, so picking reaches what is behind a trigger
+    ExcludeHelpers,
+    ///Bit mask of collision group ids
+    Groups(u32),
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct CastRayDat {
     pub id: usize,
     pub ray: Ray<f32>,
+    pub filter: RayFilter,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct CastedRay {
     pub id: usize,
     pub entity: Option<EntityID>,
+    ///World space hit point of the closest entity
+    pub point: Option<Point3<f32>>,
+    ///Surface normal at the hit point, pointing out of the entity's collider
+    pub normal: Option<Vector3<f32>>,
+    ///How far along the ray the hit is in multiples of its direction, infinite on a miss
+    pub toi: f32,
 }
 
 #[repr(C)]
@@ -103,14 +189,80 @@ pub struct CastedRay {
 pub struct Light {
     pub color: [f32; 3],
     pub direction: [f32; 3],
+    pub ambient: f32,
+}
+
+
+pub const DEFAULT_MSAA_SAMPLES: i32 = 4;
+
+///Multisampled color and depth/stencil the scene is drawn into, resolved into the frame's texture
+#[derive(Debug, Copy, Clone)]
+pub struct MultisampleTarget {
+    pub frame_buffer: u32,
+    color_rbo: u32,
+    depth_rbo: u32,
+    pub samples: i32,
 }
 
+impl MultisampleTarget {
+    ///None when the driver can't do more than one sample or the framebuffer can't be completed
+    unsafe fn new(viewport: ViewPortDimensions, samples: i32, enable_alpha: bool) -> Option<Self> {
+        let mut max_samples = 0;
+        gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+
+        let samples = samples.min(max_samples);
+        if samples <= 1 {
+            return None;
+        }
+
+        let ViewPortDimensions { width, height } = viewport;
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+
+        let mut renderbuffers = [0; 2];
+        gl::GenRenderbuffers(2, renderbuffers.as_mut_ptr());
+        let [color_rbo, depth_rbo] = renderbuffers;
+
+        let color_format = if enable_alpha { gl::RGBA8 } else { gl::RGB8 };
+        gl::BindRenderbuffer(gl::RENDERBUFFER, color_rbo);
+        gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, color_format, width, height);
+        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, color_rbo);
+
+        //Note(teddy) The highlight outlines need the stencil, it's multisampled with the depth
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth_rbo);
+        gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, gl::DEPTH24_STENCIL8, width, height);
+        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_rbo);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+        let complete = gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+        let target = Self { frame_buffer: fbo, color_rbo, depth_rbo, samples };
+        if !complete {
+            target.dispose();
+            return None;
+        }
+        Some(target)
+    }
+
+    unsafe fn dispose(&self) {
+        gl::DeleteFramebuffers(1, &self.frame_buffer);
+        gl::DeleteRenderbuffers(2, [self.color_rbo, self.depth_rbo].as_ptr());
+    }
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct FrameRenderObject {
     pub frame_buffer: u32,
     pub texture: u32,
     pub rbo: u32,
+    pub size: ViewPortDimensions,
+    enable_alpha: bool,
+    ///Drawn into instead of frame_buffer when set, resolve copies it into the texture
+    multisample: Option<MultisampleTarget>,
+    ///Sample count asked for, the target may have fewer when the driver can't do it
+    requested_samples: i32,
 }
 
 impl FrameRenderObject {
@@ -130,7 +282,8 @@ impl FrameRenderObject {
         let mut texture_color_buffer = 0;
         gl::GenTextures(1, &mut texture_color_buffer);
         gl::BindTexture(gl::TEXTURE_2D, texture_color_buffer);
-        gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGB.try_into().unwrap(), width, height, 0, gl::RGB, gl::UNSIGNED_BYTE, null());
+        let format = if enable_alpha { gl::RGBA } else { gl::RGB };
+        gl::TexImage2D(gl::TEXTURE_2D, 0, format.try_into().unwrap(), width, height, 0, format, gl::UNSIGNED_BYTE, null());
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR.try_into().unwrap());
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR.try_into().unwrap());
         //
@@ -161,19 +314,80 @@ impl FrameRenderObject {
         dbg!(Self {
             frame_buffer: fbo,
             texture: texture_color_buffer,
-            rbo
+            rbo,
+            size: viewport,
+            enable_alpha,
+            multisample: None,
+            requested_samples: 1,
         })
     }
 
-    unsafe fn dispose(&mut self) {
-        gl::DeleteFramebuffers(1, [self.frame_buffer].as_ptr());
-        gl::DeleteRenderbuffers(1, [self.rbo].as_ptr());
-        gl::DeleteTextures(1, [self.texture].as_ptr());
+    pub fn requested_samples(&self) -> i32 {
+        self.requested_samples
+    }
+
+    ///Samples actually in use, 1 without multisampling
+    pub fn samples(&self) -> i32 {
+        self.multisample.map_or(1, |target| target.samples)
+    }
+
+    ///Framebuffer the frame's passes draw into
+    pub fn draw_frame_buffer(&self) -> u32 {
+        self.multisample.map_or(self.frame_buffer, |target| target.frame_buffer)
+    }
+
+    ///Rebuilds the multisampled target, 1 or less goes back to drawing straight into the frame
+    pub unsafe fn set_samples(&mut self, samples: i32) {
+        if let Some(target) = self.multisample.take() {
+            target.dispose();
+        }
+
+        self.requested_samples = samples;
+        self.multisample = MultisampleTarget::new(self.size, samples, self.enable_alpha);
     }
 
-    unsafe fn resize(mut self, width: i32, height: i32) -> Self {
-        self.dispose();
-        FrameRenderObject::new(ViewPortDimensions{width, height}, true)
+    ///Copies the multisampled color into the texture, after this frame_buffer is bound.
+    ///Nothing to do without multisampling.
+    pub unsafe fn resolve(&self) {
+        if let Some(target) = self.multisample {
+            let ViewPortDimensions { width, height } = self.size;
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target.frame_buffer);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.frame_buffer);
+            gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.frame_buffer);
+    }
+
+    ///Reallocates the color texture and the depth/stencil storage at the new size, the framebuffer and
+    ///the attachment ids stay the same so nothing holding them has to change
+    pub unsafe fn resize(&mut self, size: ViewPortDimensions) {
+        use std::convert::TryInto;
+
+        if self.size.width == size.width && self.size.height == size.height {
+            return;
+        }
+
+        let ViewPortDimensions { width, height } = size;
+        let format = if self.enable_alpha { gl::RGBA } else { gl::RGB };
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        gl::TexImage2D(gl::TEXTURE_2D, 0, format.try_into().unwrap(), width, height, 0, format, gl::UNSIGNED_BYTE, null());
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        gl::BindRenderbuffer(gl::RENDERBUFFER, self.rbo);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.frame_buffer);
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            eprintln!("Error: Framebuffer:: Framebuffer is not complete after resizing to {}x{}", width, height);
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+        self.size = size;
+        if self.multisample.is_some() {
+            self.set_samples(self.requested_samples);
+        }
     }
 }
 
@@ -184,6 +398,7 @@ pub unsafe fn bind_texture(object: &FrameRenderObject, index: u32, program: u32,
     gl::Uniform1i(uniform_location, index.try_into().unwrap());
     gl::ActiveTexture(gl::TEXTURE0 + index); 
     gl::BindTexture(gl::TEXTURE_2D, object.texture);
+    stats::record_texture_bind();
 }
 
 
@@ -200,8 +415,15 @@ pub struct Engine {
     pub pressed_keys: Vec<Key>,
     pub mouse_button_keys: Vec<MouseButton>,
     pub select_mode: bool,
-    pub font_face: FontFace,
-    view_toggle: bool,
+    ///The default face, views without a font size of their own use it
+    pub font_face: Rc<FontFace>,
+    pub fonts: FontCache,
+    ///What views are built and drawn with, handed to their constructors
+    pub ui: UiResources,
+    ///L held last frame, the wireframe toggles once per press
+    wireframe_key_down: bool,
+    ///A middle press in the scene turns or pans the orbit camera until it's released
+    orbit_dragging: bool,
     cursor_mode_toggle: bool,
 
     pub ui_view: Vec<Box<dyn View>>,
@@ -209,6 +431,64 @@ pub struct Engine {
     pub ui_render_object: Option<FrameRenderObject>,
     pub scene_render_object: FrameRenderObject,
     pub log_manager: LogManager,
+
+    ///Window size in screen cordinates, the space cursor positions are reported in
+    pub window_size: ViewPortDimensions,
+    ///Size in pixels, differs from the window size on HiDPI displays
+    pub framebuffer_size: ViewPortDimensions,
+    ///Space taken by docked panels around the 3D viewport, in window cordinates
+    pub viewport_insets: ViewportInsets,
+
+    pub image_writer: ImageWriter,
+    ///Set by F12, the renderer captures the next finished scene frame
+    pub screenshot_requested: bool,
+    ///Path of a level thumbnail the renderer should capture from the next scene frame
+    pub thumbnail_request: Option<String>,
+    ///World space lines drawn on top of the scene, whoever fills them owns clearing them
+    pub overlay_lines: Option<OverlayLines>,
+    ///Lines queued this frame, the renderer draws them after the entities and clears them
+    pub debug_draw: DebugDraw,
+    ///Physics option, outlines every collider with the debug lines
+    pub show_colliders: bool,
+    ///Physics option, kinetic energy a dynamic body falls asleep under. None keeps every body awake
+    pub sleep_threshold: Option<f32>,
+    ///Awake and sleeping dynamic bodies after the last physics step
+    pub physics_stats: PhysicsStats,
+    ///Editor option, the ground grid and origin axes
+    pub show_grid: bool,
+    ///Framebuffer size the render targets still have to be resized to, the renderer applies it once a frame
+    pub pending_resize: Option<ViewPortDimensions>,
+    ///MSAA samples for the scene, 1 turns it off. The renderer falls back to fewer when the driver can't do it.
+    pub msaa_samples: i32,
+    ///Times the render passes on the gpu with timer queries as well, they cost a little so it starts off
+    pub gpu_timing: bool,
+    ///Set while an editor tool uses escape, the key no longer quits
+    pub escape_captured: bool,
+    ///Draw calls issued by the renderer for the last scene frame
+    pub frame_draw_calls: u32,
+    ///Counts and timings of the last whole frame, scene, ui and the screen pass
+    pub render_stats: RenderStats,
+    ///Editor option, every entity is drawn as wireframe
+    pub wireframe: bool,
+    ///Window cordinates per ui pixel, the ui lays out and takes input in ui pixels
+    ui_scale: f32,
+    cursor_icon: CursorIcon,
+    ///Standard cursors made so far, the one the window shows is held by the window
+    cursors: HashMap<CursorIcon, Cursor>,
+}
+
+pub struct OverlayLines {
+    pub shader_label: String,
+    pub thickness: f32,
+    pub segments: Vec<(Point3<f32>, Point3<f32>)>,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ViewportInsets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
 }
 
 #[inline(always)]
@@ -232,54 +512,173 @@ fn check_button(button: &MouseButton, action: &Action, buttons: &mut Vec<MouseBu
 
 //TODO(teddy) have an init routine
 impl Engine {
+    ///The face for a view's font size, the default face when it has none or the size fails to load
+    pub fn font(&self, font_size: Option<u32>) -> Rc<FontFace> {
+        match font_size.map(|size| self.fonts.get(size)) {
+            Some(Ok(face)) => face,
+            Some(Err(e)) => {
+                println!("Unable to load the font at size {:?}: {:?}", font_size, e);
+                self.font_face.clone()
+            }
+            None => self.font_face.clone(),
+        }
+    }
+
     pub fn new(display: Display, font_face: FontFace) -> Self {
+        let font_face = Rc::new(font_face);
+        let (window_width, window_height) = display.window.get_size();
+        let (framebuffer_width, framebuffer_height) = display.window.get_framebuffer_size();
+        let window_size = ViewPortDimensions { width: window_width, height: window_height };
+        let framebuffer_size = ViewPortDimensions { width: framebuffer_width, height: framebuffer_height };
+
+        let mut camera = Camera::new();
+        camera.scene_rect = ViewportRect::new(0.0, 0.0, window_width as f32, window_height as f32);
 
-        let camera = Camera::new();
         let scene_render_obj = unsafe {
-            FrameRenderObject::new(camera.view_port, true)
+            FrameRenderObject::new(framebuffer_size, true)
         };
 
-        Self {
+        let mut engine = Self {
             display,
             camera,
-            view_toggle: true,
+            wireframe_key_down: false,
+            orbit_dragging: false,
             pressed_keys: vec![],
             mouse_button_keys: vec![],
             dir_lights: Light {
                 color: [1.0, 1.0, 1.0],
                 direction: [10.0, 30.0, 0.0],
+                ambient: 0.4,
             },
             select_mode: false,
             cursor_mode_toggle: true,
+            fonts: FontCache::new(font_face.clone()),
+            ui: UiResources::new(font_face.clone()),
             font_face,
             ui_view: vec![],
             ui_render_object: None,
             ui_tree: None,
             scene_render_object: scene_render_obj,
-            log_manager: LogManager::new()
+            log_manager: LogManager::new(),
+            window_size,
+            framebuffer_size,
+            viewport_insets: ViewportInsets::default(),
+            image_writer: ImageWriter::new(IMAGE_WRITER_QUEUE_SIZE, ShutdownPolicy::Drain),
+            screenshot_requested: false,
+            thumbnail_request: None,
+            overlay_lines: None,
+            debug_draw: DebugDraw::new(),
+            show_colliders: false,
+            sleep_threshold: Some(ActivationStatus::<f32>::default_threshold()),
+            physics_stats: PhysicsStats::default(),
+            show_grid: true,
+            pending_resize: None,
+            msaa_samples: DEFAULT_MSAA_SAMPLES,
+            gpu_timing: false,
+            escape_captured: false,
+            wireframe: false,
+            frame_draw_calls: 0,
+            render_stats: RenderStats::default(),
+            ui_scale: 1.0,
+            cursor_icon: CursorIcon::Arrow,
+            cursors: HashMap::new(),
+        };
+
+        engine.reset_ui_scale();
+        engine
+    }
+
+    ///Goes back to the scale of the monitor the window is on
+    pub fn reset_ui_scale(&mut self) {
+        //Note(teddy) Where the framebuffer is already bigger than the window (macOS) the content scale is covered by it
+        let content_scale = self.display.window.get_content_scale().0;
+        self.set_ui_scale(content_scale * self.window_size.width as f32 / self.framebuffer_size.width.max(1) as f32);
+    }
+
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    ///Resizes the whole ui, the fonts are rendered again and the tree is laid out for the new size
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        self.ui_scale = scale.max(UI_SCALE_MIN).min(UI_SCALE_MAX);
+        self.fonts.set_pixel_ratio(self.ui_pixel_ratio());
+
+        let viewport = self.ui_viewport();
+        if let Some(ui_tree) = self.ui_tree.and_then(|tree| unsafe { tree.as_mut() }) {
+            ui_tree.layout(viewport);
+        }
+    }
+
+    ///The window in ui pixels, what the ui is projected and hit tested in. It follows the window,
+    ///not the camera, so the ui is right from the first frame whatever size the window opened at.
+    pub fn ui_size(&self) -> ViewPortDimensions {
+        ViewPortDimensions {
+            width: (self.window_size.width as f32 / self.ui_scale).round() as i32,
+            height: (self.window_size.height as f32 / self.ui_scale).round() as i32,
         }
     }
 
+    ///Framebuffer pixels per ui pixel, what the glyphs are rendered at
+    fn ui_pixel_ratio(&self) -> f32 {
+        self.ui_scale * self.framebuffer_size.width as f32 / self.window_size.width.max(1) as f32
+    }
+
+    ///Window cordinates from glfw to the ui pixels the views work in
+    pub fn to_ui_cords(&self, cords: Cords<f32>) -> Cords<f32> {
+        Cords { x: cords.x / self.ui_scale, y: cords.y / self.ui_scale }
+    }
+
+    ///Ui pixels back to the window cordinates the scene viewport is in
+    pub fn from_ui_cords(&self, cords: Cords<f32>) -> Cords<f32> {
+        Cords { x: cords.x * self.ui_scale, y: cords.y * self.ui_scale }
+    }
+
+    ///The part of the window the scene is drawn into, everything else belongs to docked panels
+    pub fn scene_viewport(&self) -> ViewportRect {
+        let ViewportInsets { left, right, top, bottom } = self.viewport_insets;
+        let width = self.window_size.width as f32 - left - right;
+        let height = self.window_size.height as f32 - top - bottom;
+
+        ViewportRect::new(left, top, width.max(1.0), height.max(1.0))
+    }
+
+    ///The scene viewport in framebuffer pixels, with OpenGL's bottom left origin
+    pub fn scene_viewport_pixels(&self) -> (i32, i32, i32, i32) {
+        self.scene_viewport()
+            .to_framebuffer_pixels(self.window_size, self.framebuffer_size)
+    }
+
+    ///The whole window in ui pixels, what the ui anchors the root against
+    pub fn ui_viewport(&self) -> ViewRect {
+        let ViewPortDimensions { width, height } = self.ui_size();
+        ViewRect::new(ViewPosition::zerod(), ViewDimens::new(width, height), 0)
+    }
+
     pub fn get_ui_tree(&mut self) -> Option<&mut UITree> {
         unsafe { self.ui_tree.as_ref().unwrap().as_mut() }
     }
 
     pub fn update(&mut self, event_manager: &mut EventManager) {
         let eve_ptr: *mut EventManager = event_manager;
+        self.camera.scene_rect = self.scene_viewport();
 
         for event in event_manager.window_events.iter() {
             match event {
                 WindowEvent::Size(width, height) => {
-                    self.camera.view_port = ViewPortDimensions{  width: *width, height: *height };
-
-                    unsafe {
-                        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-                        gl::Viewport(0, 0, *width, *height);
+                    self.window_size = ViewPortDimensions{  width: *width, height: *height };
+                    self.camera.scene_rect = self.scene_viewport();
+                    self.set_ui_scale(self.ui_scale);
+                }
 
-                        self.scene_render_object = self.scene_render_object.resize(*width, *height);
-                        self.ui_render_object = Some(self.ui_render_object.unwrap().resize(*width, *height));
-                    }
+                //Note(teddy) A minimized window reports 0 by 0, the frame buffers can't be made that small
+                WindowEvent::FramebufferSize(width, height) if *width > 0 && *height > 0 => {
+                    self.framebuffer_size = ViewPortDimensions{  width: *width, height: *height };
+                    //Note(teddy) Moving to a monitor with another density changes the ratio, not the ui size
+                    self.fonts.set_pixel_ratio(self.ui_pixel_ratio());
 
+                    //Note(teddy) A drag sends a burst of these, only the last size of the frame gets reallocated
+                    self.pending_resize = Some(self.framebuffer_size);
                 }
 
                 WindowEvent::CursorPos(x, y) => {
@@ -291,8 +690,18 @@ impl Engine {
                             y: *y as f32,
                         };
 
+                        if self.orbit_dragging {
+                            let (dx, dy) = (cords.x - self.camera.new_cords.x, cords.y - self.camera.new_cords.y);
+                            let shift = self.pressed_keys.contains(&Key::LeftShift) || self.pressed_keys.contains(&Key::RightShift);
+                            if shift {
+                                self.camera.pan(dx, dy);
+                            } else {
+                                self.camera.turn(dx, dy);
+                            }
+                        }
+
                         self.camera.new_cords = cords;
-                        propagate_cursor_pos_to_ui(self, cords)
+                        propagate_cursor_pos_to_ui(self, self.to_ui_cords(cords))
                     }
                 }
 
@@ -310,6 +719,9 @@ impl Engine {
 
                         MouseButton::Button3 => {
                             check_button(button, action, &mut self.mouse_button_keys);
+                            if *action == Action::Release {
+                                self.orbit_dragging = false;
+                            }
                         }
 
                         //TODO(teddy) will make the other buttons remappable for the next projects
@@ -317,21 +729,30 @@ impl Engine {
                     }
 
                     //TODO(teddy) Move the ui to its own system
-                    if !propagate_button_click(self, &self.mouse_button_keys, self.camera.new_cords)
+                    let at = Instant::now();
+                    if !propagate_button_click(self, *button, *action, at, self.to_ui_cords(self.camera.new_cords), unsafe { &mut *eve_ptr })
                     {
-                        let direction = compute_ray_from_mouse_cords(
-                            (self.camera.new_cords.x, self.camera.new_cords.y),
-                            self.camera.view_port,
+                        //Note(teddy) Clicks outside the scene viewport land on docked panels
+                        let ndc = match self.scene_viewport().to_ndc(&self.camera.new_cords) {
+                            Some(ndc) => ndc,
+                            None => continue,
+                        };
+
+                        if *button == MouseButton::Button3 && *action == Action::Press && self.camera.mode == CameraMode::Orbit {
+                            self.orbit_dragging = true;
+                        }
+
+                        let ray = compute_ray_from_mouse_cords(
+                            ndc,
                             self.camera.perspective(),
                             self.camera.view(),
                         );
 
-                        // dbg!(&direction);
+                        // dbg!(&ray);
                         // dbg!(&self.camera.camera_front);
-                        let ray = Ray::new(Point3::from(self.camera.position), direction);
 
                         let ray_cast_event =
-                            Event::new(EventType::CastRay(CastRayDat { id: 0, ray }));
+                            Event::new(EventType::CastRay(CastRayDat { id: PICKING_RAY_ID, ray, filter: RayFilter::ExcludeHelpers }));
 
                         // dbg!(&ray_cast_event);
                         unsafe {
@@ -340,7 +761,25 @@ impl Engine {
                     }
                 }
 
+                WindowEvent::Char(c) => {
+                    propagate_char(self, *c);
+                }
+
+                //Note(teddy) Scrolling over the scene zooms the orbit camera, panels scroll themselves first
+                WindowEvent::Scroll(_, y_offset) => {
+                    let scrolled = propagate_scroll(self, self.to_ui_cords(self.camera.new_cords), *y_offset as f32);
+                    let over_scene = self.scene_viewport().to_ndc(&self.camera.new_cords).is_some();
+                    if !scrolled && over_scene && self.camera.mode == CameraMode::Orbit {
+                        self.camera.zoom(*y_offset as f32);
+                    }
+                }
+
                 WindowEvent::Key(key, _, action, _modifier) => {
+                    //Note(teddy) Keys the UI takes (Tab, Escape with focus, anything typed into a focused view) never reach the camera or shortcuts
+                    if *action != Action::Release && propagate_key_stroke(self, *key) {
+                        continue;
+                    }
+
                     if self.pressed_keys.contains(key) && *action == Action::Release {
                         self.pressed_keys.retain(|s| s != key);
                     } else if *action == Action::Press {
@@ -351,6 +790,38 @@ impl Engine {
                 _ => (),
             }
         }
+
+        self.update_cursor_icon();
+    }
+
+    ///Shows the cursor of the view under it, the window's cursor is only set when the icon changes
+    fn update_cursor_icon(&mut self) {
+        let cords = self.to_ui_cords(self.camera.new_cords);
+        let looking = !self.cursor_mode_toggle;
+        let icon = match self.ui_tree.and_then(|tree| unsafe { tree.as_ref() }) {
+            Some(tree) if !looking => tree.cursor_icon_at(&cords),
+            _ => CursorIcon::Arrow,
+        };
+
+        if icon == self.cursor_icon {
+            return;
+        }
+
+        let cursor = self.cursors.remove(&icon).unwrap_or_else(|| Cursor::standard(standard_cursor(icon)));
+        if let Some(previous) = self.display.window.set_cursor(Some(cursor)) {
+            self.cursors.insert(self.cursor_icon, previous);
+        }
+        self.cursor_icon = icon;
+    }
+}
+
+fn standard_cursor(icon: CursorIcon) -> StandardCursor {
+    match icon {
+        CursorIcon::Arrow => StandardCursor::Arrow,
+        CursorIcon::IBeam => StandardCursor::IBeam,
+        CursorIcon::Hand => StandardCursor::Hand,
+        CursorIcon::HResize => StandardCursor::HResize,
+        CursorIcon::VResize => StandardCursor::VResize,
     }
 }
 
@@ -499,17 +970,86 @@ pub struct ViewPortDimensions {
     pub height: i32,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProjectionMode {
+    ///Vertical field of view in radians
+    Perspective { fov: f32 },
+    ///World units the view covers top to bottom, the width follows the aspect ratio
+    Orthographic { height: f32 },
+}
+
+///Axis aligned views for the editor, the numpad keys like most modelling tools
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraView {
+    ///Looking down y
+    Top,
+    ///Looking down z
+    Front,
+    ///Looking down x
+    Side,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraMode {
+    ///WASD and mouse look, the camera goes where it's steered
+    Fly,
+    ///Turns around, pans and zooms towards a target, for editing
+    Orbit,
+}
+
+///What the orbit camera turns around, yaw and pitch are the camera's own
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OrbitState {
+    pub target: Point3<f32>,
+    pub distance: f32,
+}
+
+///Where the camera is and which way it faces, what a move_to goes between
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CameraPose {
+    position: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl CameraPose {
+    ///Eased part of the way to the other pose, t is the linear progress. The yaw turns the short way round.
+    fn interpolate(&self, to: &CameraPose, t: f32) -> CameraPose {
+        let t = Easing::EaseInOut.apply(t);
+
+        CameraPose {
+            position: self.position.lerp(&to.position, t),
+            yaw: self.yaw + shortest_turn(self.yaw, to.yaw) * t,
+            pitch: self.pitch + (to.pitch - self.pitch) * t,
+        }
+    }
+}
+
+///A move_to on its way, elapsed and duration in seconds
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CameraAnimation {
+    from: CameraPose,
+    to: CameraPose,
+    duration: f32,
+    elapsed: f32,
+}
+
 pub struct Camera {
     pub position: Vector3<f32>,
     pub previous_cords: (f32, f32),
     pub new_cords: Cords<f32>,
     pub camera_front: Vector3<f32>,
     pub first_move: bool,
-    pub fov: f32,
+    pub projection: ProjectionMode,
+    pub mode: CameraMode,
+    ///Kept up to date in fly mode as well, it's where the orbit picks up from
+    pub orbit: OrbitState,
     camera_up: Vector3<f32>,
     yaw: f32,
     pitch: f32,
-    pub view_port: ViewPortDimensions,
+    animation: Option<CameraAnimation>,
+    ///Region of the window the scene is rendered into, drives the aspect ratio
+    pub scene_rect: ViewportRect,
 }
 
 impl Camera {
@@ -519,23 +1059,114 @@ impl Camera {
             camera_front: Vector3::new(1.0, 0.0, 0.0),
             camera_up: Vector3::new(0.0, 1.0, 0.0),
             first_move: true,
-            // fov: 0.785398 std::f64::consts::FRAC_PI_4,
-            fov: std::f32::consts::FRAC_PI_4,
+            projection: ProjectionMode::Perspective { fov: std::f32::consts::FRAC_PI_4 },
+            mode: CameraMode::Fly,
+            orbit: OrbitState { target: Point3::new(-1.0 - DEFAULT_ORBIT_DISTANCE, 0.0, 0.0), distance: DEFAULT_ORBIT_DISTANCE },
             yaw: -90.0,
             pitch: 0.0,
+            animation: None,
             previous_cords: (0.0, 0.0),
             new_cords: Cords { x: 0.0, y: 0.0 },
-            view_port: ViewPortDimensions{ width: 1000, height: 600 },
+            scene_rect: ViewportRect::new(0.0, 0.0, 1000.0, 600.0),
         }
     }
 
+    ///Projection matrix for whichever mode the camera is in
     pub fn perspective(&self) -> Matrix4<f32> {
-        Matrix4::new_perspective(
-            self.view_port.width as f32 / self.view_port.height as f32,
-            self.fov,
-            0.1,
-            100000.0,
-        )
+        let aspect = self.scene_rect.width / self.scene_rect.height;
+
+        match self.projection {
+            ProjectionMode::Perspective { fov } => Matrix4::new_perspective(aspect, fov, PERSPECTIVE_NEAR, PERSPECTIVE_FAR),
+            ProjectionMode::Orthographic { height } => {
+                let (half_width, half_height) = (height * aspect / 2.0, height / 2.0);
+                Matrix4::new_orthographic(-half_width, half_width, -half_height, half_height, -ORTHOGRAPHIC_DEPTH, ORTHOGRAPHIC_DEPTH)
+            }
+        }
+    }
+
+    ///Switches between perspective and orthographic, each starts from its default
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            ProjectionMode::Perspective { .. } => ProjectionMode::Orthographic { height: DEFAULT_ORTHOGRAPHIC_HEIGHT },
+            ProjectionMode::Orthographic { .. } => ProjectionMode::Perspective { fov: std::f32::consts::FRAC_PI_4 },
+        };
+    }
+
+    ///Where the scene is seen along. The view matrix is left handed and the projection right handed, so
+    ///what ends up on screen is behind camera_front.
+    pub fn view_direction(&self) -> Vector3<f32> {
+        -self.camera_front.normalize()
+    }
+
+    ///Turns the camera to look down an axis. Mouse look carries on from the new direction, an orbiting
+    ///camera swings around its target to it.
+    pub fn look_from(&mut self, view: CameraView) {
+        self.stop_moving();
+        let (yaw, pitch) = match view {
+            CameraView::Top => (-90.0, 90.0),
+            CameraView::Front => (90.0, 0.0),
+            CameraView::Side => (0.0, 0.0),
+        };
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.camera_front = front_from_angles(yaw, pitch);
+
+        //Note(teddy) Straight down is parallel to y, the top of the screen points down -z instead
+        self.camera_up = if view == CameraView::Top { Vector3::new(0.0, 0.0, -1.0) } else { Vector3::y() };
+        self.follow_orbit();
+    }
+
+    ///Orbiting starts around the point the camera is looking at, flying carries on from where the orbit left it
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.mode == CameraMode::Fly {
+            self.orbit.target = Point3::from(self.position + self.view_direction() * self.orbit.distance);
+        }
+        self.mode = mode;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.set_mode(if self.mode == CameraMode::Fly { CameraMode::Orbit } else { CameraMode::Fly });
+    }
+
+    ///Turns by the cursor movement in pixels, around the target while orbiting
+    pub fn turn(&mut self, dx: f32, dy: f32) {
+        self.stop_moving();
+        self.yaw += dx * LOOK_SENSITIVITY;
+        self.pitch = (self.pitch + dy * LOOK_SENSITIVITY).max(-89.0).min(89.0);
+
+        self.camera_front = front_from_angles(self.yaw, self.pitch);
+        self.camera_up = Vector3::y();
+        self.follow_orbit();
+    }
+
+    ///Slides the target across the view with the cursor, further targets slide further
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.stop_moving();
+        let left = self.camera_front.cross(&self.camera_up).normalize();
+        let up = (self.camera_up - self.camera_front * self.camera_up.dot(&self.camera_front)).normalize();
+
+        self.orbit.target += (left * dx + up * dy) * self.orbit.distance * ORBIT_PAN_SPEED;
+        self.follow_orbit();
+    }
+
+    ///Positive steps move in towards the target. An orthographic view shrinks with it, moving closer doesn't change it.
+    pub fn zoom(&mut self, steps: f32) {
+        self.stop_moving();
+        let scale = (1.0 - ORBIT_ZOOM_STEP).powf(steps);
+        self.orbit.distance = (self.orbit.distance * scale).max(MIN_ORBIT_DISTANCE).min(MAX_ORBIT_DISTANCE);
+
+        if let ProjectionMode::Orthographic { height } = &mut self.projection {
+            *height = (*height * scale).max(MIN_ORBIT_DISTANCE);
+        }
+        self.follow_orbit();
+    }
+
+    ///Puts an orbiting camera back on its orbit, a flying one just moves the target along in front of it
+    fn follow_orbit(&mut self) {
+        match self.mode {
+            CameraMode::Orbit => self.position = self.orbit.target.coords - self.view_direction() * self.orbit.distance,
+            CameraMode::Fly => self.orbit.target = Point3::from(self.position + self.view_direction() * self.orbit.distance),
+        }
     }
 
     pub fn view(&self) -> Matrix4<f32> {
@@ -546,85 +1177,153 @@ impl Camera {
         )
     }
 
+    ///Moves back along the view direction until the whole box is in view, the camera keeps looking the same way.
+    ///Flies there over duration seconds, zero jumps straight to it.
+    pub fn focus(&mut self, bounds: &Aabb, duration: f32) {
+        let aspect = if self.scene_rect.height > 0.0 { self.scene_rect.width / self.scene_rect.height } else { 1.0 };
+        let radius = (bounds.diagonal() / 2.0).max(MIN_FRAMING_RADIUS);
+
+        let distance = match &mut self.projection {
+            ProjectionMode::Perspective { fov } => framing_distance(radius, *fov, aspect),
+            //Note(teddy) Distance doesn't change the size, the box is fit by the height instead
+            ProjectionMode::Orthographic { height } => {
+                *height = radius * 2.0 / aspect.min(1.0);
+                radius * 2.0
+            }
+        };
+
+        let center = bounds.center();
+        self.move_to(center - self.view_direction() * distance, center, duration);
+    }
+
+    ///Flies to position while turning to face look_at, eased in and out over duration seconds of advance.
+    ///Any manual movement stops it where it is. The orbit ends up around look_at in either mode.
+    pub fn move_to(&mut self, position: Vector3<f32>, look_at: Vector3<f32>, duration: f32) {
+        let from = CameraPose { position: self.position, yaw: self.yaw, pitch: self.pitch };
+        let mut to = CameraPose { position, ..from };
+
+        //Note(teddy) Already facing that way keeps the angles, an axis view keeps its own up
+        let offset = look_at - position;
+        if let Some(direction) = offset.try_normalize(std::f32::EPSILON) {
+            if direction.dot(&self.view_direction()) < 1.0 - 1e-5 {
+                let (yaw, pitch) = angles_from_front(&-direction);
+                to.yaw = yaw;
+                to.pitch = pitch.max(-89.0).min(89.0);
+            }
+        }
+
+        self.orbit = OrbitState { target: Point3::from(look_at), distance: offset.norm().max(MIN_ORBIT_DISTANCE) };
+        self.animation = Some(CameraAnimation { from, to, duration, elapsed: 0.0 });
+        self.advance(0.0);
+    }
+
+    ///Steps a move_to on by dt seconds
+    pub fn advance(&mut self, dt: f32) {
+        let (pose, done) = match self.animation.as_mut() {
+            Some(animation) => {
+                animation.elapsed += dt;
+                let t = if animation.duration > 0.0 { animation.elapsed / animation.duration } else { 1.0 };
+                (animation.from.interpolate(&animation.to, t), t >= 1.0)
+            }
+            None => return,
+        };
+
+        if done {
+            self.animation = None;
+        }
+
+        self.position = pose.position;
+        if (pose.yaw, pose.pitch) != (self.yaw, self.pitch) {
+            self.yaw = pose.yaw;
+            self.pitch = pose.pitch;
+            self.camera_front = front_from_angles(pose.yaw, pose.pitch);
+            self.camera_up = Vector3::y();
+        }
+    }
+
+    ///Cuts a move_to short, the orbit carries on around the point in front of where it stopped
+    fn stop_moving(&mut self) {
+        if self.animation.take().is_some() {
+            self.orbit.target = Point3::from(self.position + self.view_direction() * self.orbit.distance);
+        }
+    }
+
     fn update_look(&mut self, x: f64, y: f64) {
         if self.first_move {
             self.previous_cords = (x as f32, y as f32);
             self.first_move = false;
         }
 
-        let mut offset = (
+        let offset = (
             x as f32 - self.previous_cords.0,
             y as f32 - self.previous_cords.1,
         );
         self.previous_cords.0 = x as f32;
         self.previous_cords.1 = y as f32;
 
-        let sensitivity = 0.5;
-        offset = (offset.0 * sensitivity, offset.1 * sensitivity);
+        self.turn(offset.0, offset.1);
+    }
 
-        self.yaw += offset.0;
-        self.pitch += offset.1;
+    fn update_position(&mut self, motion: CameraMovement, speed: Option<f32>) {
+        self.stop_moving();
+        let camera_speed = 2.5;
 
-        if self.pitch > 89.0 {
-            self.pitch = 89.0;
-        }
+        let offset = match motion {
+            CameraMovement::Up => -speed.unwrap_or(camera_speed) * self.camera_front,
+            CameraMovement::Down => speed.unwrap_or(camera_speed) * self.camera_front,
+            CameraMovement::Left => speed.unwrap_or(camera_speed) * self.camera_front.cross(&self.camera_up).normalize(),
+            CameraMovement::Right => -speed.unwrap_or(camera_speed) * self.camera_front.cross(&self.camera_up).normalize(),
+        };
 
-        if self.pitch < -89.0 {
-            self.pitch = -89.0
+        //Note(teddy) An orbiting camera takes its target along
+        self.position += offset;
+        match self.mode {
+            CameraMode::Orbit => self.orbit.target += offset,
+            CameraMode::Fly => self.follow_orbit(),
         }
-
-        let x_dir = self.yaw.to_radians().cos() * self.pitch.to_radians().cos();
-        let y_dir = self.pitch.to_radians().sin();
-        let z_dir = self.yaw.to_radians().sin() * self.pitch.to_radians().cos();
-
-        self.camera_front = Vector3::new(x_dir, y_dir, z_dir).normalize();
     }
+}
 
-    fn update_position(&mut self, motion: CameraMovement, speed: Option<f32>) {
-        let camera_speed = 2.5;
-
-        match motion {
-            CameraMovement::Up => {
-                self.position -= speed.unwrap_or(camera_speed) * self.camera_front;
-            }
+///Yaw and pitch in degrees facing the camera_front, the other way from front_from_angles
+fn angles_from_front(front: &Vector3<f32>) -> (f32, f32) {
+    let front = front.normalize();
+    (front.z.atan2(front.x).to_degrees(), front.y.max(-1.0).min(1.0).asin().to_degrees())
+}
 
-            CameraMovement::Down => {
-                self.position += speed.unwrap_or(camera_speed) * self.camera_front;
-            }
+///Degrees from one angle to the other, going the short way round
+fn shortest_turn(from: f32, to: f32) -> f32 {
+    let turn = (to - from).rem_euclid(360.0);
+    if turn > 180.0 { turn - 360.0 } else { turn }
+}
 
-            CameraMovement::Left => {
-                self.position += speed.unwrap_or(camera_speed)
-                    * self.camera_front.cross(&self.camera_up).normalize();
-            }
+fn front_from_angles(yaw: f32, pitch: f32) -> Vector3<f32> {
+    let x_dir = yaw.to_radians().cos() * pitch.to_radians().cos();
+    let y_dir = pitch.to_radians().sin();
+    let z_dir = yaw.to_radians().sin() * pitch.to_radians().cos();
 
-            CameraMovement::Right => {
-                self.position -= speed.unwrap_or(camera_speed)
-                    * self.camera_front.cross(&self.camera_up).normalize();
-            }
-        }
-    }
+    Vector3::new(x_dir, y_dir, z_dir).normalize()
 }
 
+///Takes the cursor already mapped into the scene viewport's normalized device cordinates. The ray runs from
+///the near plane to the far plane under the cursor, perspective rays spread out from the camera and
+///orthographic ones all point the same way from different origins.
 #[inline]
-fn compute_ray_from_mouse_cords(
-    cords: (f32, f32),
-    screen_cords: ViewPortDimensions,
+pub fn compute_ray_from_mouse_cords(
+    ndc: Cords<f32>,
     projection_matrix: Matrix4<f32>,
     view_matrix: Matrix4<f32>,
-) -> Vector3<f32> {
-    //Normalize the device cordinates
-    let x = (2.0 * cords.0) / screen_cords.width as f32 - 1.0;
-    let y = 1.0 - (2.0 * cords.1) / screen_cords.height as f32;
-
-    let ray_normalized_devices_cords: Vector4<f32> = Vector4::new(x, y, 1.0, 1.0);
-
+) -> Ray<f32> {
     //FIXME(teddy) Inverse computation should be handled incase it fails
-    let map_to_camera_space: Matrix4<f32> =
+    let map_to_world_space: Matrix4<f32> =
         (projection_matrix * view_matrix).try_inverse().unwrap();
 
-    let mut mapped_direction: Vector4<f32> = map_to_camera_space * ray_normalized_devices_cords;
-    mapped_direction /= mapped_direction.w;
-    mapped_direction.xyz().normalize()
+    let unproject = |depth: f32| {
+        let point: Vector4<f32> = map_to_world_space * Vector4::new(ndc.x, ndc.y, depth, 1.0);
+        Point3::from(point.xyz() / point.w)
+    };
+
+    let (near, far) = (unproject(-1.0), unproject(1.0));
+    Ray::new(near, (far - near).normalize())
 }
 
 macro_rules! contains_key {
@@ -633,33 +1332,46 @@ macro_rules! contains_key {
     };
 }
 
-static mut L_CLICKED: bool = false;
 static mut M_CLICKED: bool = false;
+static mut F12_CLICKED: bool = false;
+
+///dt is the real time the last frame took in seconds
+pub fn camera_behaviour(engine: &mut Engine, dt: f32) {
+    let step = Some(FLY_SPEED * dt);
 
-pub fn camera_behaviour(engine: &mut Engine) {
     if contains_key!(engine, Key::W) {
         engine
             .camera
-            .update_position(CameraMovement::Up, Some(0.05));
+            .update_position(CameraMovement::Up, step);
     }
 
     if contains_key!(engine, Key::S) {
         engine
             .camera
-            .update_position(CameraMovement::Down, Some(0.05));
+            .update_position(CameraMovement::Down, step);
     }
 
     if contains_key!(engine, Key::A) {
         engine
             .camera
-            .update_position(CameraMovement::Left, Some(0.05));
+            .update_position(CameraMovement::Left, step);
     }
 
     if contains_key!(engine, Key::D) {
         engine
             .camera
-            .update_position(CameraMovement::Right, Some(0.05));
+            .update_position(CameraMovement::Right, step);
+    }
+
+    //Note(teddy) After the keys so moving this frame has already cut a move_to short
+    engine.camera.advance(dt);
+
+    //Note(teddy) Same option as the editor's wireframe checkbox, the renderer sets the polygon mode per entity
+    let wireframe_key_down = contains_key!(engine, Key::L);
+    if wireframe_key_down && !engine.wireframe_key_down {
+        engine.wireframe = !engine.wireframe;
     }
+    engine.wireframe_key_down = wireframe_key_down;
 
     unsafe {
         if contains_key!(engine, Key::M) {
@@ -684,32 +1396,402 @@ pub fn camera_behaviour(engine: &mut Engine) {
             M_CLICKED = false;
         }
 
-        if contains_key!(engine, Key::L) {
-            if !L_CLICKED {
-                engine.view_toggle = !engine.view_toggle;
-
-                if engine.view_toggle {
-                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
-                } else {
-                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-                }
-
-                L_CLICKED = true;
+        if contains_key!(engine, Key::F12) {
+            if !F12_CLICKED {
+                engine.screenshot_requested = true;
+                F12_CLICKED = true;
             }
         } else {
-            L_CLICKED = false;
+            F12_CLICKED = false;
         }
     }
-    if contains_key!(engine, Key::Escape) {
-        std::process::exit(0);
+    if contains_key!(engine, Key::Escape) && !engine.escape_captured {
+        quit(engine);
+    }
+}
+
+pub fn quit(engine: &mut Engine) -> ! {
+    //Note(teddy) process::exit skips destructors, finish the pending screenshots first
+    engine.image_writer.shutdown();
+    std::process::exit(0);
+}
+
+///The freetype face a FontFace loads glyphs from after the atlas is built
+struct FreetypeFace {
+    library: freetype::FT_Library,
+    face: freetype::FT_Face,
+}
+
+impl Drop for FreetypeFace {
+    fn drop(&mut self) {
+        unsafe {
+            freetype::FT_Done_Face(self.face);
+            freetype::FT_Done_FreeType(self.library);
+        }
     }
 }
 
-#[derive(Debug)]
 pub struct FontFace {
     font_name: String,  //TODO(teddy) Get the name of the font from the ttf files
     pub font_size: u32, //Similar to the font-size
-    pub chars: HashMap<char, FontChar>,
+    chars: RefCell<HashMap<char, FontChar>>,
+    ///Every loaded glyph's pixels, the glyphs hold their uv rect in it
+    atlas: RefCell<FontAtlas>,
+    ///Characters freetype couldn't load, they are drawn with the fallback glyph without asking again
+    missing: RefCell<HashSet<char>>,
+    freetype: Option<FreetypeFace>,
+    ///Screen pixels per ui pixel, the glyphs are rendered this much bigger and drawn back down
+    pixel_ratio: Cell<f32>,
+}
+
+///Pixel size freetype renders a font_size face at for the pixel ratio
+fn raster_size(font_size: u32, pixel_ratio: f32) -> u32 {
+    (font_size as f32 * pixel_ratio).round().max(1.0) as u32
+}
+
+//Note(teddy) Drawn in place of characters the font doesn't have
+const FALLBACK_GLYPH: char = '?';
+
+impl FontFace {
+    ///A face that only knows the given glyphs, anything else is drawn with the fallback glyph
+    pub fn new(font_name: String, font_size: u32, chars: HashMap<char, FontChar>) -> Self {
+        Self {
+            font_name,
+            font_size,
+            chars: RefCell::new(chars),
+            atlas: RefCell::new(FontAtlas::new(ATLAS_WIDTH, ATLAS_INITIAL_HEIGHT)),
+            missing: RefCell::new(HashSet::new()),
+            freetype: None,
+            pixel_ratio: Cell::new(1.0),
+        }
+    }
+
+    pub fn pixel_ratio(&self) -> f32 {
+        self.pixel_ratio.get()
+    }
+
+    ///Renders every glyph again at the new ratio, font_size and the measured widths stay in ui pixels
+    pub fn set_pixel_ratio(&self, pixel_ratio: f32) {
+        if self.pixel_ratio.get() == pixel_ratio {
+            return;
+        }
+        self.pixel_ratio.set(pixel_ratio);
+
+        //Note(teddy) The test faces have no freetype, their glyphs are just drawn at another ratio
+        let freetype = match &self.freetype {
+            Some(freetype) => freetype,
+            None => return,
+        };
+
+        self.chars.borrow_mut().clear();
+        self.missing.borrow_mut().clear();
+        self.atlas.borrow_mut().clear();
+
+        unsafe {
+            freetype::FT_Set_Pixel_Sizes(freetype.face, 0, raster_size(self.font_size, pixel_ratio));
+            for c in (0..128u8).map(char::from) {
+                match load_glyph(freetype.face, c) {
+                    Ok(bitmap) => {
+                        self.insert_glyph(c, &bitmap);
+                    }
+                    Err(_) => {
+                        self.missing.borrow_mut().insert(c);
+                    }
+                }
+            }
+
+            self.atlas.borrow_mut().upload();
+        }
+    }
+
+    ///The texture every glyph of the face is drawn from
+    pub fn atlas_texture(&self) -> u32 {
+        self.atlas.borrow().texture
+    }
+
+    ///Changes when the atlas grows, uvs taken from glyphs before that are stale
+    pub fn atlas_generation(&self) -> u32 {
+        self.atlas.borrow().generation
+    }
+
+    ///Packs the bitmap into the atlas, growing it when it's full, and keeps the glyph
+    fn insert_glyph(&self, c: char, bitmap: &GlyphBitmap) -> FontChar {
+        let mut atlas = self.atlas.borrow_mut();
+        let height = atlas.height;
+        let (x, y) = atlas.allocate(bitmap.width, bitmap.height);
+
+        //Note(teddy) Growing keeps the pixels where they are, only the v of the older glyphs shrinks
+        if atlas.height != height {
+            let scale = height as f32 / atlas.height as f32;
+            for glyph in self.chars.borrow_mut().values_mut() {
+                glyph.uv[1] *= scale;
+                glyph.uv[3] *= scale;
+            }
+        }
+
+        atlas.blit(x, y, bitmap);
+        let glyph = FontChar {
+            uv: atlas.uv(x, y, bitmap.width, bitmap.height),
+            size: Point2::new(bitmap.width, bitmap.height),
+            bearing: bitmap.bearing,
+            advance: bitmap.advance,
+        };
+
+        self.chars.borrow_mut().insert(c, glyph);
+        glyph
+    }
+
+    ///Glyph for the character, loaded the first time it is asked for.
+    ///The fallback glyph when the font doesn't cover it.
+    pub fn glyph(&self, c: char) -> Option<FontChar> {
+        if let Some(glyph) = self.chars.borrow().get(&c) {
+            return Some(*glyph);
+        }
+
+        if let Some(freetype) = &self.freetype {
+            if !self.missing.borrow().contains(&c) {
+                match unsafe { load_glyph(freetype.face, c) } {
+                    Ok(bitmap) => {
+                        let glyph = self.insert_glyph(c, &bitmap);
+                        unsafe { self.atlas.borrow_mut().upload() };
+                        return Some(glyph);
+                    }
+                    Err(_) => {
+                        self.missing.borrow_mut().insert(c);
+                    }
+                }
+            }
+        }
+
+        self.chars.borrow().get(&FALLBACK_GLYPH).copied()
+    }
+
+    ///Whether the font has its own glyph for the character, without loading it
+    pub fn has_glyph(&self, c: char) -> bool {
+        match &self.freetype {
+            Some(freetype) => unsafe { freetype::FT_Get_Char_Index(freetype.face, c as freetype::FT_ULong) != 0 },
+            None => self.chars.borrow().contains_key(&c),
+        }
+    }
+
+    ///Width of the text in ui pixels at scale 1.0
+    pub fn measure_text(&self, text: &str) -> u32 {
+        let width: u32 = text.chars()
+            .filter_map(|c| self.glyph(c))
+            .map(|glyph| (glyph.advance >> 6) as u32)
+            .sum();
+
+        (width as f32 / self.pixel_ratio()).round() as u32
+    }
+}
+
+impl std::fmt::Debug for FontFace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontFace")
+            .field("font_name", &self.font_name)
+            .field("font_size", &self.font_size)
+            .field("loaded_glyphs", &self.chars.borrow().len())
+            .field("atlas_height", &self.atlas.borrow().height)
+            .field("pixel_ratio", &self.pixel_ratio.get())
+            .finish()
+    }
+}
+
+//Note(teddy) The atlas texture belongs to the face, the test faces never make one and never touch gl
+impl Drop for FontFace {
+    fn drop(&mut self) {
+        let texture = self.atlas.borrow().texture;
+        if texture != 0 {
+            unsafe { gl::DeleteTextures(1, &texture) };
+        }
+    }
+}
+
+const ATLAS_WIDTH: i32 = 512;
+const ATLAS_INITIAL_HEIGHT: i32 = 128;
+///Empty pixels around each glyph so linear filtering doesn't pull in the neighbours
+const ATLAS_GLYPH_GAP: i32 = 1;
+
+///One red channel texture holding every glyph of a face, packed in shelves left to right, top to bottom.
+///A copy of the pixels stays here so the texture can be uploaded again after the atlas grows.
+pub struct FontAtlas {
+    texture: u32,
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+    ///Where the next glyph goes on the open shelf
+    cursor_x: i32,
+    shelf_y: i32,
+    ///Height of the tallest glyph on the open shelf
+    shelf_height: i32,
+    ///Bumped every time the atlas grows
+    generation: u32,
+    ///Rows changed since the last upload
+    dirty: Option<(i32, i32)>,
+    ///Height of the texture as it was last made, a taller atlas is uploaded whole
+    uploaded_height: i32,
+}
+
+impl FontAtlas {
+    fn new(width: i32, height: i32) -> Self {
+        Self {
+            texture: 0,
+            width,
+            height,
+            pixels: vec![0; (width * height) as usize],
+            cursor_x: ATLAS_GLYPH_GAP,
+            shelf_y: ATLAS_GLYPH_GAP,
+            shelf_height: 0,
+            generation: 0,
+            dirty: None,
+            uploaded_height: 0,
+        }
+    }
+
+    ///Top left of a free width by height rect, the atlas doubles in height until it fits
+    fn allocate(&mut self, width: i32, height: i32) -> (i32, i32) {
+        //Note(teddy) Glyphs wider than the atlas don't exist at the sizes the ui uses, they get cut off
+        let width = width.min(self.width - ATLAS_GLYPH_GAP * 2);
+
+        if self.cursor_x + width + ATLAS_GLYPH_GAP > self.width {
+            self.shelf_y += self.shelf_height + ATLAS_GLYPH_GAP;
+            self.cursor_x = ATLAS_GLYPH_GAP;
+            self.shelf_height = 0;
+        }
+
+        while self.shelf_y + height + ATLAS_GLYPH_GAP > self.height {
+            self.grow();
+        }
+
+        let position = (self.cursor_x, self.shelf_y);
+        self.cursor_x += width + ATLAS_GLYPH_GAP;
+        self.shelf_height = self.shelf_height.max(height);
+        position
+    }
+
+    ///Drops every glyph, the texture is kept and made again on the next upload
+    fn clear(&mut self) {
+        *self = Self { texture: self.texture, generation: self.generation + 1, ..Self::new(self.width, ATLAS_INITIAL_HEIGHT) };
+    }
+
+    fn grow(&mut self) {
+        self.height *= 2;
+        self.pixels.resize((self.width * self.height) as usize, 0);
+        self.generation += 1;
+    }
+
+    ///Copies the glyph's rows in at (x, y)
+    fn blit(&mut self, x: i32, y: i32, bitmap: &GlyphBitmap) {
+        let width = bitmap.width.min(self.width - x);
+
+        for row in 0..bitmap.height {
+            let source = (row * bitmap.width) as usize;
+            let target = ((y + row) * self.width + x) as usize;
+            self.pixels[target..target + width as usize].copy_from_slice(&bitmap.pixels[source..source + width as usize]);
+        }
+
+        self.dirty = match self.dirty {
+            Some((top, bottom)) => Some((top.min(y), bottom.max(y + bitmap.height))),
+            None => Some((y, y + bitmap.height)),
+        };
+    }
+
+    ///[u0, v0, u1, v1] with v0 the glyph's top row
+    fn uv(&self, x: i32, y: i32, width: i32, height: i32) -> [f32; 4] {
+        let (atlas_width, atlas_height) = (self.width as f32, self.height as f32);
+        [
+            x as f32 / atlas_width,
+            y as f32 / atlas_height,
+            (x + width) as f32 / atlas_width,
+            (y + height) as f32 / atlas_height,
+        ]
+    }
+
+    ///Sends the changed pixels to the texture, made on the first upload
+    unsafe fn upload(&mut self) {
+        let (top, bottom) = match self.dirty.take() {
+            Some(rows) => rows,
+            None => return,
+        };
+
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1); //Note(teddY) Disable byte-alignment restriction
+
+        if self.texture == 0 {
+            gl::GenTextures(1, &mut self.texture);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+
+            //Set the texture paramaters
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        } else {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        }
+
+        if self.uploaded_height == self.height {
+            let offset = (top * self.width) as usize;
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                top,
+                self.width,
+                bottom - top,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                self.pixels[offset..].as_ptr() as *const c_void,
+            );
+        } else {
+            self.uploaded_height = self.height;
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RED as i32,
+                self.width,
+                self.height,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                self.pixels.as_ptr() as *const c_void,
+            );
+        }
+
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+}
+
+///Font faces by pixel size, a size is loaded the first time something asks for it
+pub struct FontCache {
+    faces: RefCell<HashMap<u32, Rc<FontFace>>>,
+    pixel_ratio: Cell<f32>,
+}
+
+impl FontCache {
+    pub fn new(default: Rc<FontFace>) -> Self {
+        let pixel_ratio = Cell::new(default.pixel_ratio());
+        let mut faces = HashMap::new();
+        faces.insert(default.font_size, default);
+        Self { faces: RefCell::new(faces), pixel_ratio }
+    }
+
+    pub fn get(&self, font_size: u32) -> Result<Rc<FontFace>, FontError> {
+        if let Some(face) = self.faces.borrow().get(&font_size) {
+            return Ok(face.clone());
+        }
+
+        let face = Rc::new(unsafe { load_fonts(font_size, self.pixel_ratio.get())? });
+        self.faces.borrow_mut().insert(font_size, face.clone());
+        Ok(face)
+    }
+
+    ///Renders the loaded faces again for the new ratio, the views holding them pick it up on their next draw
+    pub fn set_pixel_ratio(&self, pixel_ratio: f32) {
+        self.pixel_ratio.set(pixel_ratio);
+        for face in self.faces.borrow().values() {
+            face.set_pixel_ratio(pixel_ratio);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -719,20 +1801,54 @@ pub enum FontError {
     FailedToLoadGlyph,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct FontChar {
-    pub texture: u32,
+    ///Rect of the glyph in its face's atlas, [u0, v0, u1, v1] with v0 the top row
+    pub uv: [f32; 4],
     pub size: Point2<i32>,
     pub bearing: Point2<i32>,
     pub advance: i32,
 }
 
-//TODO(teddy) Return the font-face loaded
-//Reuse the font-face incase the ui will require different font sizes
+///A rendered glyph before it goes into the atlas
+struct GlyphBitmap {
+    width: i32,
+    height: i32,
+    ///Tightly packed rows, top row first
+    pixels: Vec<u8>,
+    bearing: Point2<i32>,
+    advance: i32,
+}
+
+//Note(teddy) Renders one glyph, the face has to have its pixel size set
+unsafe fn load_glyph(font_face: freetype::FT_Face, c: char) -> Result<GlyphBitmap, FontError> {
+    if freetype::FT_Load_Char(font_face, c as freetype::FT_ULong, freetype::FT_LOAD_RENDER as i32) != 0 {
+        return Err(FontError::FailedToLoadGlyph);
+    }
+
+    let glyph = &*(*font_face).glyph;
+    let width = glyph.bitmap.width as i32;
+    let height = glyph.bitmap.rows as i32;
+
+    //Note(teddy) Rows can be padded past the width, the atlas wants them packed
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for row in 0..height {
+        let start = glyph.bitmap.buffer.offset((row * glyph.bitmap.pitch) as isize);
+        pixels.extend_from_slice(std::slice::from_raw_parts(start, width as usize));
+    }
 
-//Note(teddy) Caller can generate fonts for different sizes depending on their needs
-//The unnecessary fonts should be freed accordingly
-pub unsafe fn load_fonts(font_size: u32) -> Result<FontFace, FontError> {
+    Ok(GlyphBitmap {
+        width,
+        height,
+        pixels,
+        bearing: Point2::new(glyph.bitmap_left, glyph.bitmap_top),
+        advance: glyph.advance.x as i32,
+    })
+}
+
+//Note(teddy) Caller can generate fonts for different sizes depending on their needs, the FontCache keeps them.
+//ASCII is loaded up front, every other character the first time it is drawn or measured.
+pub unsafe fn load_fonts(font_size: u32, pixel_ratio: f32) -> Result<FontFace, FontError> {
     let mut ft_lib: freetype::FT_Library = std::ptr::null_mut();
     if freetype::FT_Init_FreeType(&mut ft_lib) != 0 {
         return Err(FontError::FailedToLoadFontLib);
@@ -742,62 +1858,171 @@ pub unsafe fn load_fonts(font_size: u32) -> Result<FontFace, FontError> {
         CString::new(format!("{}{}", FONT_ASSETS_DIR, "Roboto-Regular.ttf").as_str()).unwrap();
     let mut font_face: freetype::FT_Face = std::ptr::null_mut();
     if freetype::FT_New_Face(ft_lib, font_path.as_ptr(), 0, &mut font_face) != 0 {
+        freetype::FT_Done_FreeType(ft_lib);
         return Err(FontError::UnableToLoadFont);
     }
 
-    freetype::FT_Set_Pixel_Sizes(font_face, 0, font_size);
+    //Note(teddy) From here the face and library are released when the FontFace drops
+    let freetype = FreetypeFace { library: ft_lib, face: font_face };
+    freetype::FT_Set_Pixel_Sizes(font_face, 0, raster_size(font_size, pixel_ratio));
+
+    let mut face = FontFace::new(String::from(""), font_size, HashMap::new());
+    face.pixel_ratio.set(pixel_ratio);
+    for c in (0..128u8).map(char::from) {
+        face.insert_glyph(c, &load_glyph(font_face, c)?);
+    }
+
+    //Note(teddy) The whole ASCII set goes up in one upload
+    face.atlas.get_mut().upload();
+    face.freetype = Some(freetype);
+    Ok(face)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(width: i32, height: i32) -> GlyphBitmap {
+        GlyphBitmap { width, height, pixels: vec![255; (width * height) as usize], bearing: Point2::new(0, height), advance: width << 6 }
+    }
+
+    #[test]
+    fn test_glyphs_pack_into_shelves_and_grow_the_atlas() {
+        let face = FontFace::new(String::from("test"), 12, HashMap::new());
 
-    let mut characters = HashMap::new();
+        let a = face.insert_glyph('a', &bitmap(100, 20));
+        let b = face.insert_glyph('b', &bitmap(100, 30));
+        assert_eq!(a.uv, [1.0 / 512.0, 1.0 / 128.0, 101.0 / 512.0, 21.0 / 128.0]);
+        assert_eq!(b.uv[0], 102.0 / 512.0);
 
-    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1); //Note(teddY) Disable byte-alignment restriction
-    for c in 0..128 {
-        if freetype::FT_Load_Char(font_face, c, freetype::FT_LOAD_RENDER as i32) != 0 {
-            return Err(FontError::FailedToLoadGlyph);
+        //Note(teddy) Past the right edge a new shelf opens under the tallest glyph of the last one
+        for c in ['c', 'd', 'e'].iter() {
+            face.insert_glyph(*c, &bitmap(100, 10));
         }
+        let f = face.insert_glyph('f', &bitmap(100, 10));
+        assert_eq!(f.uv[1], 32.0 / 128.0);
+        assert_eq!(face.atlas.borrow().pixels[(32 * 512 + 1) as usize], 255);
+
+        //Note(teddy) Growing moves no pixels, the glyphs packed before keep their place with a smaller v
+        assert_eq!(face.atlas_generation(), 0);
+        face.insert_glyph('g', &bitmap(600, 100));
+        assert_eq!(face.atlas_generation(), 1);
+        assert_eq!(face.glyph('a').unwrap().uv, [1.0 / 512.0, 1.0 / 256.0, 101.0 / 512.0, 21.0 / 256.0]);
+        assert_eq!(face.glyph('f').unwrap().uv[1], 32.0 / 256.0);
+    }
 
-        let width = (*(&*font_face).glyph).bitmap.width as i32;
-        let height = (*(&*font_face).glyph).bitmap.rows as i32;
+    #[test]
+    fn test_scaled_faces_measure_in_ui_pixels() {
+        let face = FontFace::new(String::from("test"), 12, HashMap::new());
+        face.insert_glyph('a', &bitmap(20, 24));
+        assert_eq!(face.measure_text("aaa"), 60);
 
-        let mut texture: u32 = 0;
-        gl::GenTextures(1, &mut texture);
-        gl::BindTexture(gl::TEXTURE_2D, texture);
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RED as i32,
-            width,
-            height,
-            0,
-            gl::RED,
-            gl::UNSIGNED_BYTE,
-            (*(*font_face).glyph).bitmap.buffer as *const c_void,
-        );
+        //Note(teddy) At twice the density the glyphs are rendered twice as big, the text still takes the same room
+        face.set_pixel_ratio(2.0);
+        assert_eq!(face.measure_text("aaa"), 30);
+        assert_eq!(raster_size(12, 1.5), 18);
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let mut camera = Camera::new();
+        camera.look_from(CameraView::Front);
+        let cursor = |camera: &Camera, x: f32, y: f32| compute_ray_from_mouse_cords(Cords { x, y }, camera.perspective(), camera.view());
+
+        //Note(teddy) Perspective rays leave from next to the camera and spread out
+        let (center, corner) = (cursor(&camera, 0.0, 0.0), cursor(&camera, 0.9, 0.9));
+        assert!((center.origin - Point3::from(camera.position)).norm() < 0.2);
+        assert!((corner.origin - center.origin).norm() < 0.2);
+        assert!((center.dir - corner.dir).norm() > 0.1);
+
+        camera.toggle_projection();
+        let (center, corner) = (cursor(&camera, 0.0, 0.0), cursor(&camera, 1.0, 1.0));
+        assert!((center.dir - corner.dir).norm() < 1e-4);
+        assert!((center.dir.cross(&camera.camera_front)).norm() < 1e-4);
+
+        //Note(teddy) Half the view height up from the center, the scene rect is 1000 by 600
+        let offset = corner.origin - center.origin;
+        assert!((offset.y - DEFAULT_ORTHOGRAPHIC_HEIGHT / 2.0).abs() < 1e-2);
+        assert!((offset.x.abs() - DEFAULT_ORTHOGRAPHIC_HEIGHT / 2.0 * 1000.0 / 600.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_orbit_keeps_the_target_in_view() {
+        let mut camera = Camera::new();
+        camera.look_from(CameraView::Top);
+        assert!((camera.view_direction() - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-4);
 
-        //Set the texture paramaters
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-        let character = FontChar {
-            texture,
-            size: Point2::new(width, height),
-            bearing: Point2::new(
-                (*(*font_face).glyph).bitmap_left,
-                (*(*font_face).glyph).bitmap_top,
-            ),
-            advance: (*(*font_face).glyph).advance.x as i32,
+        camera.set_mode(CameraMode::Orbit);
+        let on_orbit = |camera: &Camera| {
+            let expected = camera.orbit.target.coords - camera.view_direction() * camera.orbit.distance;
+            (camera.position - expected).norm() < 1e-3
         };
 
-        characters.insert(c as u8 as char, character);
+        let target = camera.orbit.target;
+        camera.turn(40.0, -20.0);
+        assert!(on_orbit(&camera));
+        assert!((camera.orbit.target - target).norm() < 1e-4);
+
+        camera.zoom(3.0);
+        assert!(on_orbit(&camera));
+        assert!(camera.orbit.distance < DEFAULT_ORBIT_DISTANCE);
+
+        //Note(teddy) Panning slides the target across the view, never towards the camera
+        camera.pan(100.0, 50.0);
+        assert!(on_orbit(&camera));
+        assert!((camera.orbit.target - target).dot(&camera.view_direction()).abs() < 1e-4);
+        assert!((camera.orbit.target - target).norm() > 1e-3);
+
+        camera.zoom(-1000.0);
+        assert_eq!(camera.orbit.distance, MAX_ORBIT_DISTANCE);
     }
 
-    freetype::FT_Done_Face(font_face);
-    freetype::FT_Done_FreeType(ft_lib);
+    #[test]
+    fn test_camera_pose_interpolation() {
+        let from = CameraPose { position: Vector3::new(0.0, 0.0, 0.0), yaw: 170.0, pitch: -20.0 };
+        let to = CameraPose { position: Vector3::new(10.0, -4.0, 2.0), yaw: -170.0, pitch: 40.0 };
+
+        assert_eq!(from.interpolate(&to, 0.0), from);
+        let end = from.interpolate(&to, 1.0);
+        assert_eq!(end.position, to.position);
+        assert_eq!((end.yaw.rem_euclid(360.0), end.pitch), (190.0, 40.0));
+
+        //Note(teddy) Eased, slow at both ends and halfway at the middle, turning through 180 instead of 0
+        let middle = from.interpolate(&to, 0.5);
+        assert!((middle.position - Vector3::new(5.0, -2.0, 1.0)).norm() < 1e-5);
+        assert!((middle.yaw - 180.0).abs() < 1e-4 && (middle.pitch - 10.0).abs() < 1e-4);
+        assert!(from.interpolate(&to, 0.1).position.x < 1.0);
+        assert!(from.interpolate(&to, 0.9).position.x > 9.0);
+
+        assert_eq!((shortest_turn(10.0, 350.0), shortest_turn(-90.0, 90.0)), (-20.0, 180.0));
+        let (yaw, pitch) = angles_from_front(&front_from_angles(-135.0, 30.0));
+        assert!((yaw + 135.0).abs() < 1e-3 && (pitch - 30.0).abs() < 1e-3);
+    }
 
-    Ok(FontFace {
-        font_name: String::from(""),
-        font_size,
-        chars: characters,
-    })
+    #[test]
+    fn test_move_to_runs_on_elapsed_time() {
+        let mut camera = Camera::new();
+        let (destination, look_at) = (Vector3::new(0.0, 10.0, 10.0), Vector3::new(0.0, 0.0, 0.0));
+        camera.move_to(destination, look_at, 1.0);
+
+        camera.advance(0.25);
+        camera.advance(0.25);
+        assert!((camera.position - (Vector3::new(-1.0, 0.0, 0.0) + destination) / 2.0).norm() < 1e-4);
+
+        camera.advance(0.6);
+        assert_eq!(camera.position, destination);
+        assert!((camera.view_direction() - (look_at - destination).normalize()).norm() < 1e-4);
+        assert_eq!(camera.orbit.target, Point3::from(look_at));
+
+        //Note(teddy) Moving by hand stops a move where it is
+        camera.move_to(Vector3::new(50.0, 0.0, 0.0), look_at, 1.0);
+        camera.advance(0.5);
+        camera.update_position(CameraMovement::Up, Some(0.0));
+        let stopped = camera.position;
+        camera.advance(0.5);
+        assert_eq!(camera.position, stopped);
+
+        camera.move_to(destination, look_at, 0.0);
+        assert_eq!(camera.position, destination);
+    }
 }
@@ -0,0 +1,366 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_world::world::World;
+use crate::utils::write_atomic;
+
+pub const AUTOSAVE_DIR: &'static str = "./autosave/";
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+const AUTOSAVE_EXTENSION: &'static str = "autosave";
+const AUTOSAVE_INFO_EXTENSION: &'static str = "autosave.json";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DocumentKind {
+    Level,
+    Prefab,
+    ImportSettings,
+    EditorSettings,
+}
+
+impl DocumentKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentKind::Level => "level",
+            DocumentKind::Prefab => "prefab",
+            DocumentKind::ImportSettings => "import settings",
+            DocumentKind::EditorSettings => "editor settings",
+        }
+    }
+}
+
+pub type DocumentId = usize;
+///Serializes the current state of a document, called for autosaves and for real saves
+pub type SnapshotFn = dyn FnMut(&World) -> Result<Vec<u8>, String>;
+pub type DocumentWriteFn = dyn FnMut(&Path, &[u8]) -> Result<(), String>;
+
+struct Document {
+    kind: DocumentKind,
+    name: String,
+    path: PathBuf,
+    dirty: bool,
+    snapshot: Box<SnapshotFn>,
+}
+
+///Written next to every autosave so recovery knows what the data is and where it belongs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveredDocument {
+    pub kind: DocumentKind,
+    pub name: String,
+    ///Where the document is saved normally
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub autosave: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct SaveResult {
+    pub kind: DocumentKind,
+    pub name: String,
+    pub result: Result<(), String>,
+}
+
+///Every document the editor can persist. Autosaves and Save All go through the same snapshots.
+pub struct DocumentRegistry {
+    documents: Vec<Document>,
+    autosave_dir: PathBuf,
+    pub autosave_interval: Duration,
+    last_autosave: Option<Instant>,
+    write: Box<DocumentWriteFn>,
+}
+
+impl DocumentRegistry {
+    pub fn new(autosave_dir: &Path, autosave_interval: Duration) -> Self {
+        Self::with_writer(autosave_dir, autosave_interval, Box::new(|path: &Path, bytes: &[u8]| write_atomic(path, bytes)))
+    }
+
+    pub fn with_writer(autosave_dir: &Path, autosave_interval: Duration, write: Box<DocumentWriteFn>) -> Self {
+        Self {
+            documents: vec![],
+            autosave_dir: autosave_dir.to_path_buf(),
+            autosave_interval,
+            last_autosave: None,
+            write,
+        }
+    }
+
+    pub fn register(&mut self, kind: DocumentKind, name: &str, path: &Path, snapshot: Box<SnapshotFn>) -> DocumentId {
+        if let Some(id) = self.find(kind, name) {
+            self.documents[id].snapshot = snapshot;
+            self.documents[id].path = path.to_path_buf();
+            return id;
+        }
+
+        self.documents.push(Document {
+            kind,
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            dirty: false,
+            snapshot,
+        });
+        self.documents.len() - 1
+    }
+
+    pub fn find(&self, kind: DocumentKind, name: &str) -> Option<DocumentId> {
+        self.documents.iter().position(|document| document.kind == kind && document.name == name)
+    }
+
+    pub fn mark_dirty(&mut self, id: DocumentId) {
+        if let Some(document) = self.documents.get_mut(id) {
+            document.dirty = true;
+        }
+    }
+
+    ///For documents that were saved outside the registry, e.g. the level's own save button
+    pub fn mark_saved(&mut self, id: DocumentId) {
+        if let Some(document) = self.documents.get_mut(id) {
+            document.dirty = false;
+            let _ = remove_autosave(&self.autosave_dir, document.kind, &document.name);
+        }
+    }
+
+    pub fn is_dirty(&self, id: DocumentId) -> bool {
+        self.documents.get(id).map_or(false, |document| document.dirty)
+    }
+
+    pub fn unsaved(&self) -> Vec<(DocumentKind, String)> {
+        self.documents
+            .iter()
+            .filter(|document| document.dirty)
+            .map(|document| (document.kind, document.name.clone()))
+            .collect()
+    }
+
+    ///Writes every dirty document into the autosave directory once the interval has passed.
+    ///Documents stay dirty, an autosave is not a save.
+    pub fn autosave_tick(&mut self, now: Instant, world: &World) -> Vec<SaveResult> {
+        let last = *self.last_autosave.get_or_insert(now);
+        if now.duration_since(last) < self.autosave_interval {
+            return vec![];
+        }
+
+        self.last_autosave = Some(now);
+        let autosave_dir = self.autosave_dir.clone();
+        let write = &mut self.write;
+
+        self.documents
+            .iter_mut()
+            .filter(|document| document.dirty)
+            .map(|document| {
+                let result = (document.snapshot)(world).and_then(|bytes| {
+                    let info = RecoveredDocument {
+                        kind: document.kind,
+                        name: document.name.clone(),
+                        path: document.path.clone(),
+                        autosave: PathBuf::new(),
+                    };
+                    let info = serde_json::to_vec_pretty(&info).map_err(|e| format!("{}", e))?;
+
+                    write(&autosave_file(&autosave_dir, document.kind, &document.name, AUTOSAVE_EXTENSION), &bytes)?;
+                    write(&autosave_file(&autosave_dir, document.kind, &document.name, AUTOSAVE_INFO_EXTENSION), &info)
+                });
+
+                SaveResult { kind: document.kind, name: document.name.clone(), result }
+            })
+            .collect()
+    }
+
+    ///Saves every dirty document to its real path and drops its autosave
+    pub fn save_all(&mut self, world: &World) -> Vec<SaveResult> {
+        let autosave_dir = self.autosave_dir.clone();
+        let write = &mut self.write;
+
+        self.documents
+            .iter_mut()
+            .filter(|document| document.dirty)
+            .map(|document| {
+                let result = (document.snapshot)(world).and_then(|bytes| write(&document.path, &bytes));
+
+                if result.is_ok() {
+                    document.dirty = false;
+                    let _ = remove_autosave(&autosave_dir, document.kind, &document.name);
+                }
+                SaveResult { kind: document.kind, name: document.name.clone(), result }
+            })
+            .collect()
+    }
+
+    ///Autosaves are left behind by a crash, a clean quit removes them
+    pub fn discard_autosaves(&self) {
+        for document in self.documents.iter() {
+            let _ = remove_autosave(&self.autosave_dir, document.kind, &document.name);
+        }
+    }
+}
+
+fn autosave_file(directory: &Path, kind: DocumentKind, name: &str, extension: &str) -> PathBuf {
+    //Note(teddy) Names can be paths, keep the autosave directory flat
+    let name: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+    directory.join(format!("{}_{}.{}", kind.label().replace(' ', "_"), name, extension))
+}
+
+fn remove_autosave(directory: &Path, kind: DocumentKind, name: &str) -> Result<(), String> {
+    fs::remove_file(autosave_file(directory, kind, name, AUTOSAVE_EXTENSION)).map_err(|e| format!("{}", e))?;
+    fs::remove_file(autosave_file(directory, kind, name, AUTOSAVE_INFO_EXTENSION)).map_err(|e| format!("{}", e))
+}
+
+///Documents autosaved by a session that never quit cleanly, sorted by kind then name
+pub fn recovered_documents(autosave_dir: &Path) -> Vec<RecoveredDocument> {
+    let entries = match fs::read_dir(autosave_dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut recovered: Vec<RecoveredDocument> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(AUTOSAVE_INFO_EXTENSION))
+        .filter_map(|info_path| {
+            let contents = fs::read_to_string(&info_path).ok()?;
+            let mut document: RecoveredDocument = serde_json::from_str(&contents).ok()?;
+            document.autosave = autosave_file(autosave_dir, document.kind, &document.name, AUTOSAVE_EXTENSION);
+
+            if document.autosave.exists() {
+                Some(document)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    recovered.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.name.cmp(&b.name)));
+    recovered
+}
+
+///Moves the autosaved data over the document's real path
+pub fn restore_document(document: &RecoveredDocument) -> Result<(), String> {
+    let bytes = fs::read(&document.autosave).map_err(|e| format!("{}", e))?;
+    write_atomic(&document.path, &bytes)?;
+    discard_recovered(document)
+}
+
+pub fn discard_recovered(document: &RecoveredDocument) -> Result<(), String> {
+    let directory = document.autosave.parent().unwrap_or(Path::new(""));
+    remove_autosave(directory, document.kind, &document.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("imara_documents_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn fake_document(calls: &Rc<RefCell<Vec<String>>>, name: &str) -> Box<SnapshotFn> {
+        let calls = Rc::clone(calls);
+        let name = name.to_string();
+        Box::new(move |_world: &World| {
+            calls.borrow_mut().push(name.clone());
+            Ok(name.clone().into_bytes())
+        })
+    }
+
+    #[test]
+    fn test_autosave_runs_once_per_dirty_document() {
+        let world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let directory = temp_dir("autosave");
+        let calls = Rc::new(RefCell::new(vec![]));
+        let writes = Rc::new(RefCell::new(vec![]));
+
+        let recorded = Rc::clone(&writes);
+        let mut registry = DocumentRegistry::with_writer(
+            &directory.join("autosave"),
+            Duration::from_secs(60),
+            Box::new(move |path: &Path, bytes: &[u8]| {
+                recorded.borrow_mut().push(path.to_path_buf());
+                write_atomic(path, bytes)
+            }),
+        );
+
+        let level = registry.register(DocumentKind::Level, "arena", &directory.join("arena"), fake_document(&calls, "arena"));
+        let lamp = registry.register(DocumentKind::Prefab, "lamp", &directory.join("lamp.json"), fake_document(&calls, "lamp"));
+        registry.register(DocumentKind::EditorSettings, "editor", &directory.join("editor.json"), fake_document(&calls, "editor"));
+        registry.register(DocumentKind::ImportSettings, "crate.obj", &directory.join("crate.import.json"), fake_document(&calls, "crate.obj"));
+
+        registry.mark_dirty(level);
+        registry.mark_dirty(lamp);
+
+        let start = Instant::now();
+        assert!(registry.autosave_tick(start, &world).is_empty());
+        assert!(registry.autosave_tick(start + Duration::from_secs(30), &world).is_empty());
+
+        let saved = registry.autosave_tick(start + Duration::from_secs(61), &world);
+        assert_eq!(saved.len(), 2);
+        assert!(saved.iter().all(|save| save.result.is_ok()));
+        assert_eq!(*calls.borrow(), vec![String::from("arena"), String::from("lamp")]);
+
+        //Note(teddy) Data and info file for each document, all through the atomic writer and no temp files left over
+        assert_eq!(writes.borrow().len(), 4);
+        assert!(writes.borrow().iter().all(|path| path.starts_with(directory.join("autosave"))));
+        let leftovers = fs::read_dir(directory.join("autosave")).unwrap().filter(|entry| {
+            entry.as_ref().unwrap().path().to_string_lossy().ends_with(".tmp")
+        });
+        assert_eq!(leftovers.count(), 0);
+
+        //Note(teddy) The autosave leaves the documents dirty, recovery lists both kinds
+        assert_eq!(registry.unsaved(), vec![(DocumentKind::Level, String::from("arena")), (DocumentKind::Prefab, String::from("lamp"))]);
+        let recovered = recovered_documents(&directory.join("autosave"));
+        assert_eq!(recovered.iter().map(|document| document.kind).collect::<Vec<_>>(), vec![DocumentKind::Level, DocumentKind::Prefab]);
+        assert_eq!(recovered[0].path, directory.join("arena"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_save_all_clears_dirty_and_autosaves() {
+        let world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let directory = temp_dir("save_all");
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut registry = DocumentRegistry::new(&directory.join("autosave"), Duration::from_secs(0));
+
+        let level = registry.register(DocumentKind::Level, "arena", &directory.join("arena"), fake_document(&calls, "arena"));
+        let lamp = registry.register(DocumentKind::Prefab, "lamp", &directory.join("lamp.json"), fake_document(&calls, "lamp"));
+        registry.mark_dirty(level);
+
+        registry.autosave_tick(Instant::now(), &world);
+        assert_eq!(recovered_documents(&directory.join("autosave")).len(), 1);
+
+        let saved = registry.save_all(&world);
+        assert_eq!(saved.len(), 1);
+        assert_eq!(fs::read_to_string(directory.join("arena")).unwrap(), "arena");
+        assert!(!registry.is_dirty(level) && !registry.is_dirty(lamp));
+        assert!(registry.unsaved().is_empty());
+        assert!(recovered_documents(&directory.join("autosave")).is_empty());
+        assert!(registry.save_all(&world).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_restore_recovered_document() {
+        let world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let directory = temp_dir("restore");
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut registry = DocumentRegistry::new(&directory.join("autosave"), Duration::from_secs(0));
+
+        let lamp = registry.register(DocumentKind::Prefab, "lamp", &directory.join("lamp.json"), fake_document(&calls, "lamp"));
+        registry.mark_dirty(lamp);
+        registry.autosave_tick(Instant::now(), &world);
+
+        let recovered = recovered_documents(&directory.join("autosave"));
+        restore_document(&recovered[0]).unwrap();
+        assert_eq!(fs::read_to_string(directory.join("lamp.json")).unwrap(), "lamp");
+        assert!(recovered_documents(&directory.join("autosave")).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
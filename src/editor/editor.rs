@@ -1,27 +1,225 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
-use glfw::MouseButton;
-use nalgebra::{Matrix4, Point3, Vector3};
+use glfw::{Key, MouseButton};
+use nalgebra::{Isometry3, Point3, UnitQuaternion, Vector3};
+use ncollide3d::query::{Proximity, Ray};
+use ncollide3d::shape::Cuboid;
 use nphysics3d::material::{BasicMaterial, MaterialHandle};
 use nphysics3d::object::{BodyStatus, DefaultBodyHandle, DefaultColliderHandle};
 
 use crate::{core::{
-    mouse_clicked, CastRayDat, CastedRay, Engine, Event, EventManager, EventType,
-    ViewPortDimensions,
+    compute_ray_from_mouse_cords, mouse_clicked, quit, CameraMode, CameraView, CastRayDat, CastedRay, Engine, DEFAULT_MSAA_SAMPLES, Event, EventManager, EventType,
+    OverlayLines, RayFilter, UiEvent,
 }, ui::ui::View};
 use crate::game_world::components::*;
-use crate::game_world::world::{AssetSource, ObjType, OBJ_ASSETS_DIR};
-use crate::game_world::world::{ResourceResult, World};
+use crate::game_world::prefab::{
+    apply_instance_to_prefab, instantiate, load_prefabs, prefab_bytes, prefab_path, revert_field, set_instance_field,
+    update_instances, Prefab, PrefabField, PrefabInstance, PrefabValue, PREFAB_ASSETS_DIR,
+};
+use crate::game_world::mesh_dedup::merge_duplicate_labels;
+use crate::game_world::level_meta::{format_age, list_levels, thumbnail_path, unix_now, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+use crate::game_world::world::{mesh_material, AssetSource, ObjType, OBJ_ASSETS_DIR, WORLD_LEVELS_DIR};
+use crate::game_world::world::{ResourceResult, World, WorldError};
+use crate::ui::hot_reload::{asset_file_watcher, is_shader_source, reload_all_shaders, reload_shaders, FileWatcher};
+use crate::ui::loader::{load_ui, LoaderError};
+use crate::ui::tween::{fade, slide_in};
 use crate::ui::ui::{
-    Orientation, SimpleUIContainer, TextView, UITree, ViewContainer, ViewPosition,
+    Anchor, CheckboxView, CollapsibleSection, ColorPickerView, ConfirmDialog, Dimensions, DropdownView, ImageView, Orientation, Overlay, ScrollView, SimpleUIContainer, SliderView, TextAlign, TextInputView, TextOverflow, TextView, ProgressBarView, TreeNode, TreeView, UITree, MenuAction, UiCommand, UiContext, UiResources, ViewContainer, ViewDimens,
+    ViewPosition, SplitPane,
 };
+use crate::logs::Logable;
+use crate::renderer::png_decoder::decode_png;
+use crate::renderer::stats::RenderStatsLogObject;
+use crate::renderer::textures::{upload_texture, TextureFilter, TextureParams, TextureWrap};
+use crate::systems::physics::PhysicsStatsLogObject;
+use crate::settings::parse_console_command;
 use crate::utils::compute_world_space_to_screen_space;
+use super::documents::{
+    discard_recovered, recovered_documents, restore_document, DocumentId, DocumentKind, DocumentRegistry, AUTOSAVE_DIR,
+    DEFAULT_AUTOSAVE_INTERVAL,
+};
+use super::gizmo::{draw_gizmo, gizmo_length, pick_axis, GizmoDrag, GizmoMode};
+use super::measure::{
+    intersect_ground_plane, snap_to_grid, snap_to_origin, Measurement, MEASURE_GRID_SIZE, MEASURE_SNAP_RADIUS,
+};
+use super::undo::{TransformDelta, EditKind, TransformState, UndoStack, DEFAULT_UNDO_MEMORY_BUDGET};
+
+const NUDGE_STEP: f32 = 0.1;
+///Units a second the arrow keys walk a character
+const CHARACTER_WALK_SPEED: f32 = 3.0;
+///Upward impulse from the launch key, a unit mass leaves at this many units a second
+const LAUNCH_IMPULSE: f32 = 8.0;
+///Force G pushes the selection away from the camera with, for every frame it's held
+const PUSH_FORCE: f32 = 20.0;
+///How much ctrl+= and ctrl+- change the ui scale by
+const UI_SCALE_STEP: f32 = 0.25;
+const SNAP_RAY_ID: usize = 1;
+const MEASURE_RAY_ID: usize = 2;
+const DROP_RAY_ID: usize = 3;
+///How far in front of the camera a focused entity ends up
+const FOCUS_DISTANCE: f32 = 15.0;
+///How far below the entity snapping to the floor looks
+const SNAP_MAX_DROP: f32 = 1000.0;
+///Seconds the camera takes to fly to a focused entity
+const FOCUS_DURATION: f32 = 0.4;
+///Duplicates land this far along x from the original so both stay visible
+const DUPLICATE_OFFSET: f32 = 2.0;
+///Size in world units of a terrain added from the editor, y is the height of a white pixel
+const TERRAIN_SCALE: [f32; 3] = [256.0, 30.0, 256.0];
+const MEASURE_LINE_THICKNESS: f32 = 0.02;
+const MEASURE_LABEL_ID: &'static str = "measure_label";
+const QUIT_DIALOG_ID: &'static str = "quit_dialog";
+const DELETE_DIALOG_ID: &'static str = "confirm_delete";
+///F3 shows it over the top right of the scene
+const RENDER_STATS_ID: &'static str = "render_stats_overlay";
+const RENDER_STATS_MARGIN: i32 = 10;
+///Past this the asset list scrolls, about a dozen entries
+const ASSET_LIST_HEIGHT: i32 = 400;
+///Gap between asset rows, outside the rows so their hover boxes don't grow
+const ASSET_LIST_SPACING: i32 = 4;
+///Wide labels like the separator are cut off here so they stay inside the panel
+const PANEL_TEXT_MAX_WIDTH: i32 = 360;
+const SECTION_HEADER_FONT_SIZE: u32 = 16;
+const LIGHT_DIRECTION_SLIDERS: [&'static str; 3] = ["light_dir_x", "light_dir_y", "light_dir_z"];
+const LIGHT_DIRECTION_RANGE: (f32, f32, f32) = (-50.0, 50.0, 1.0);
+const LIGHT_AMBIENT_SLIDER: &'static str = "light_ambient";
+const LIGHT_COLOR_PICKER: &'static str = "light_color";
+const BODY_STATUS_DROPDOWN: &'static str = "body_status";
+const BODY_STATUS_OPTIONS: [(&'static str, BodyStatus); 3] =
+    [("Static", BodyStatus::Static), ("Kinematic", BodyStatus::Kinematic), ("Dynamic", BodyStatus::Dynamic)];
+const SHADER_DROPDOWN: &'static str = "entity_shader";
+const SPAWN_COLLIDER_DROPDOWN: &'static str = "spawn_collider";
+const SPAWN_COLLIDER_OPTIONS: [(&'static str, ColliderShape); 3] = [
+    ("Mesh collider", ColliderShape::TriMesh),
+    ("Convex hull collider", ColliderShape::ConvexHull),
+    ("Box collider", ColliderShape::AutoFromAabb),
+];
+const DEBUG_VIEW_DROPDOWN: &'static str = "entity_debug_view";
+const DEBUG_VIEW_OPTIONS: [(&'static str, DebugViewMode); 3] =
+    [("Default", DebugViewMode::Default), ("Wireframe", DebugViewMode::Wireframe), ("Normals", DebugViewMode::Normals)];
+const ENTITY_COLOR_PICKER: &'static str = "entity_color";
+const ENTITY_OPACITY_SLIDER: &'static str = "entity_opacity";
+const ENTITY_FLAG_CHECKBOXES: [(&'static str, &'static str, RenderFlags); 2] = [
+    ("entity_wireframe", "Draw as wireframe", RenderFlags::WIREFRAME),
+    ("entity_shadow_caster", "Casts shadows", RenderFlags::SHADOW_CASTER),
+];
+///Inspector for the selected rope, slider id, label and range of segments, length, iterations and thickness
+const ROPE_SLIDERS: [(&'static str, &'static str, (f32, f32, f32)); 4] = [
+    ("rope_segments", "Rope segments", (1.0, 64.0, 1.0)),
+    ("rope_length", "Rope length", (0.5, 30.0, 0.5)),
+    ("rope_iterations", "Rope iterations", (1.0, 32.0, 1.0)),
+    ("rope_thickness", "Rope thickness", (0.01, 0.5, 0.01)),
+];
+const EDITOR_UI_PATH: &'static str = "./assets/ui/editor.json";
+const ASSET_SECTION_ID: &'static str = "assets";
+const ASSET_LIST_ID: &'static str = "asset_list";
+///Asset rows are this followed by the asset's file name
+const ASSET_ROW_PREFIX: &'static str = "text_";
+const ASSET_BORDER_COLOR: [f32; 3] = [0.35, 0.35, 0.35];
+const COLLISION_HIGHLIGHT_COLOR: [f32; 3] = [1.0, 0.2, 0.2];
+const ASSET_ROW_COLOR: [f32; 3] = [0.2, 0.2, 0.2];
+const ASSET_SELECTED_COLOR: [f32; 3] = [0.25, 0.35, 0.25];
+const LOG_SECTION_ID: &'static str = "ui_log";
+const RESOURCE_PROGRESS_ID: &'static str = "resource_progress";
+const RESOURCE_PROGRESS_WIDTH: i32 = 300;
+const OUTLINER_SECTION_ID: &'static str = "outliner_section";
+const OUTLINER_ID: &'static str = "outliner";
+///Outliner groups take their ids counting down from here so they never clash with an entity id
+const OUTLINER_GROUP_ID: usize = usize::MAX;
+///Dark grey that lets what is under the logs show through
+const LOG_BACKGROUND: [f32; 4] = [0.12, 0.12, 0.12, 0.6];
+///Seconds
+const PANEL_SLIDE_DURATION: f32 = 0.35;
+///Seconds a flashed row takes to fade back to its own color
+const FLASH_DURATION: f32 = 0.8;
+const SAVED_FLASH_COLOR: [f32; 3] = [0.2, 0.6, 0.2];
+const RECOVERED_FLASH_COLOR: [f32; 3] = [0.7, 0.5, 0.1];
+///Filled when the editor starts, from the autosaves and the levels on disk
+const RECOVERY_CONTAINER_ID: &'static str = "recovery";
+const RECOVERY_ROW_IDS: [&'static str; 3] = ["recovered_label", "restore_recovered", "discard_recovered"];
+const LEVELS_CONTAINER_ID: &'static str = "levels";
+///Quarter of the captured thumbnail
+const LEVEL_THUMBNAIL_SIZE: ViewDimens = ViewDimens { x: (THUMBNAIL_WIDTH / 4) as i32, y: (THUMBNAIL_HEIGHT / 4) as i32 };
+///Splits the window between the panel and the scene
+const EDITOR_SPLIT_ID: &'static str = "editor_split";
+///Placeholder for the scene side of the split, hidden so the clicks and drops on it reach the world
+const SCENE_AREA_ID: &'static str = "scene_area";
+const PANEL_SPLIT_RATIO: f32 = 0.4;
+const PANEL_MIN_WIDTH: i32 = 250;
+const SCENE_MIN_WIDTH: i32 = 300;
+const PREFAB_COMMANDS: [(&'static str, &'static str, fn(usize, &mut World)); 5] = [
+    ("make_prefab", "Make prefab", make_prefab),
+    ("place_prefab", "Place another instance", place_instance),
+    ("apply_prefab", "Apply instance to prefab", apply_to_prefab),
+    ("update_prefab", "Update instances from prefab", update_from_prefab),
+    ("revert_prefab", "Revert to prefab", revert_to_prefab),
+];
+///One row per PrefabField, filled in for the selected instance by sync_selection_views
+const PREFAB_OVERRIDES_ID: &'static str = "prefab_overrides";
+const OVERRIDDEN_COLOR: [f32; 3] = [1.0, 0.8, 0.2];
+
+pub enum EditorTool {
+    Select,
+    Measure(Measurement),
+}
 
 pub struct Editor {
     pub ui_tree: UITree,
     pub shader_label: String,
     pub selected_entity: Option<usize>,
+    pub undo_stack: UndoStack,
+    pub tool: EditorTool,
+    pub documents: DocumentRegistry,
+    level_document: DocumentId,
+    quit_dialog_open: bool,
+    previous_keys: Vec<Key>,
+    previous_buttons: Vec<MouseButton>,
+    snap_requested: bool,
+    ///Carried into every new measurement
+    measure_snap: bool,
+    ///Whether the camera flies over to entities spawned from the asset panel
+    fly_to_spawned: bool,
+    ///Whether assets dropped on the scene are tilted to the surface they land on
+    align_drops: bool,
+    ///Character the arrow keys walked last, stopped when the selection moves on
+    driven_character: Option<usize>,
+    ///Collider given to entities spawned from the asset panel
+    spawn_collider: ColliderShape,
+    ///Pairs of entities touching or overlapping, smaller id first, kept from the physics events
+    touching: HashSet<(usize, usize)>,
+    ///Asset dropped on the scene and the ray cast for it, spawned once the hit comes back
+    pending_drop: Option<(String, Ray<f32>)>,
+    ///Id of the highlighted asset row
+    selected_asset: Option<Box<str>>,
+    ///Entities the outliner was last filled with
+    outliner_entities: Vec<usize>,
+    ///Layouts and ui shader sources, rebuilt or recompiled when they change on disk
+    file_watcher: FileWatcher,
+    ///Where the panel divider was dragged to, put back when the ui is reloaded
+    panel_split_ratio: Option<f32>,
+    ///Gizmo axis held down on the selected entity
+    gizmo_drag: Option<GizmoDrag>,
+    ///Textures of the level picker thumbnails, the views don't own them
+    level_thumbnails: Vec<u32>,
+    ///Rows the level picker was last filled with
+    level_rows: Vec<Box<str>>,
+    ///Entity the delete confirmation is open for
+    deleting_entity: Option<usize>,
+}
+
+struct EditorLogObject {
+    text: String,
+}
+
+impl Logable for EditorLogObject {
+    fn to_string(&self) -> String {
+        self.text.clone()
+    }
 }
 
 impl Editor {
@@ -30,138 +228,840 @@ impl Editor {
             ui_tree: UITree::new(),
             shader_label,
             selected_entity: None,
+            undo_stack: UndoStack::new(DEFAULT_UNDO_MEMORY_BUDGET),
+            tool: EditorTool::Select,
+            documents: DocumentRegistry::new(Path::new(AUTOSAVE_DIR), DEFAULT_AUTOSAVE_INTERVAL),
+            level_document: 0,
+            quit_dialog_open: false,
+            previous_keys: vec![],
+            previous_buttons: vec![],
+            snap_requested: false,
+            measure_snap: false,
+            fly_to_spawned: false,
+            align_drops: false,
+            driven_character: None,
+            spawn_collider: ColliderShape::TriMesh,
+            touching: HashSet::new(),
+            pending_drop: None,
+            selected_asset: None,
+            outliner_entities: vec![],
+            file_watcher: asset_file_watcher(),
+            panel_split_ratio: None,
+            gizmo_drag: None,
+            level_thumbnails: vec![],
+            level_rows: vec![],
+            deleting_entity: None,
+        }
+    }
+
+    ///Highlights the asset row and clears the one picked before it
+    fn select_asset(&mut self, row_id: Box<str>) {
+        if let Some(previous) = self.selected_asset.take() {
+            if let Ok(row) = self.ui_tree.find_view_mut(&previous) {
+                row.set_background_color(ASSET_ROW_COLOR);
+            }
+        }
+
+        if let Ok(row) = self.ui_tree.find_view_mut(&row_id) {
+            row.set_background_color(ASSET_SELECTED_COLOR);
+            self.selected_asset = Some(row_id);
         }
     }
 
+    pub fn mark_level_dirty(&mut self) {
+        self.documents.mark_dirty(self.level_document);
+    }
+
     pub fn init_editor_ui(&mut self, engine: &mut Engine, world: &mut World) {
-        let simpe_container_position = ViewPosition::new(0, 0);
-        let mut simple_container = Box::new(SimpleUIContainer::new(
-            String::from("simple_container").into_boxed_str(),
-            None,
-            simpe_container_position,
-            Orientation::Vertical,
-            10,
-            1.0
-        ));
+        self.level_document = track_level(&mut self.documents, world);
+        for name in world.prefabs.keys() {
+            track_prefab(&mut self.documents, name);
+        }
 
-        let log_container = Box::new(SimpleUIContainer::new(
-            String::from("ui_log").into_boxed_str(),
-            None,
-            ViewPosition::new(0, 0),
-            Orientation::Vertical,
-            10,
-            1.0
-        ));
+        //Note(teddy) The layout comes from the ui file, the callbacks and the views that depend on the world are added here
+        let root = match load_ui(Path::new(EDITOR_UI_PATH), engine) {
+            Ok(root) => root,
+            Err(error) => {
+                println!("Unable to load {}, using the built in editor ui: {}", EDITOR_UI_PATH, error);
+                build_editor_ui(engine)
+            }
+        };
+        self.set_editor_root(root, engine, world);
 
-        let mut text_view = Box::new(TextView::new(
-            String::from("text_1").into_boxed_str(),
-            String::from("Objects"),
-            ViewPosition { x: 10, y: 10 },
-            1.0,
-            10,
-        ));
-        text_view.get_view_object_mut().background_color = Box::new([0.6, 0.2, 0.2]);
+        self.ui_tree.overlays.push(Overlay {
+            visible: false,
+            view: Box::new(TextView::new(MEASURE_LABEL_ID.to_owned().into_boxed_str(), String::new(), ViewPosition::zerod(), 1.0, 4, &engine.ui)),
+        });
+        self.ui_tree.overlays.push(Overlay {
+            visible: false,
+            view: Box::new(TextView::new(QUIT_DIALOG_ID.to_owned().into_boxed_str(), String::new(), ViewPosition::zerod(), 1.0, 10, &engine.ui)),
+        });
+        self.ui_tree.overlays.push(Overlay {
+            visible: false,
+            view: Box::new(TextView::new(RENDER_STATS_ID.to_owned().into_boxed_str(), String::new(), ViewPosition::zerod(), 1.0, 10, &engine.ui)),
+        });
+        self.ui_tree.layout(engine.ui_viewport());
 
-        text_view.on_hover = Some(Box::new(|view: *mut TextView| unsafe {
-            let view_ref = view.as_mut().unwrap();
-            view_ref.color = Some(Vector3::new(1.0, 1.0, 1.0));
-            view_ref.get_view_object_mut().background_color = Box::new([0.0, 0.4, 0.0])
-        }));
-        text_view.on_mouse_leave = Some(Box::new(|view: *mut TextView| unsafe {
-            let view_ref = view.as_mut().unwrap();
-            view_ref.color = Some(Vector3::new(1.0, 1.0, 1.0));
-            view_ref.get_view_object_mut().background_color = Box::new([0.2, 0.2, 0.0])
-        }));
+        //Note(teddy) The panel slides in from past the left edge of the window, then recovered documents are pointed out
+        let width = panel_width(&mut self.ui_tree);
+        if let Some(root) = &self.ui_tree.root {
+            let object = root.get_view_object();
+            let slide = slide_in(&object.id, object.position.x - width, object.position.x, PANEL_SLIDE_DURATION)
+                .on_complete(|ctx| queue(ctx, |editor, _, _| flash(&mut editor.ui_tree, RECOVERY_ROW_IDS[0], RECOVERED_FLASH_COLOR)));
+            self.ui_tree.animate(slide);
+        }
+    }
+
+    ///Puts the root in the tree and fills it, the callbacks find their views by id
+    fn set_editor_root(&mut self, root: Box<dyn View>, engine: &Engine, world: &World) {
+        self.ui_tree.root = Some(root);
+        //Note(teddy) The rows of the old root are gone, the sync functions fill the new one on the next frame
+        self.selected_asset = None;
+        self.outliner_entities.clear();
+
+        self.fill_editor_ui(engine, world);
+        self.attach_editor_callbacks();
+    }
+
+    ///Rebuilds the panel from the ui file, the current one stays up when the file is broken
+    fn reload_editor_ui(&mut self, engine: &Engine, world: &World) -> Result<(), LoaderError> {
+        let root = load_ui(Path::new(EDITOR_UI_PATH), engine)?;
+        self.set_editor_root(root, engine, world);
+        self.ui_tree.layout(engine.ui_viewport());
+        Ok(())
+    }
+
+    ///Lists the levels again, a save changes the sidecar and thumbnail of its level
+    fn refresh_level_picker(&mut self, engine: &Engine) {
+        if let Some(container) = self.ui_tree.find_view_mut(LEVELS_CONTAINER_ID).ok().and_then(|view| view.as_container_mut()) {
+            for id in self.level_rows.drain(..) {
+                let _ = container.remove_child(&id);
+            }
+        }
+        for texture in self.level_thumbnails.drain(..) {
+            unsafe { gl::DeleteTextures(1, &texture) };
+        }
+
+        let rows = level_picker_views(&engine.ui, &mut self.level_thumbnails);
+        self.level_rows = rows.iter().map(|row| Box::from(row.get_id())).collect();
+        add_children(&mut self.ui_tree, LEVELS_CONTAINER_ID, rows);
+    }
+
+    ///Views and values that come from the disk, the engine or the world
+    fn fill_editor_ui(&mut self, engine: &Engine, world: &World) {
+        //Note(teddy) The old rows went with the old root
+        self.level_rows.clear();
+        self.refresh_level_picker(engine);
+
+        let tree = &mut self.ui_tree;
+
+        let assets = asset_entry_views(&self.shader_label, &engine.ui);
+        let asset_rows: Vec<Box<str>> = assets.iter().map(|view| Box::from(view.get_id())).collect();
+        add_children(tree, ASSET_LIST_ID, assets);
+        add_children(tree, RECOVERY_CONTAINER_ID, recovery_views(&engine.ui));
+        add_children(tree, PREFAB_OVERRIDES_ID, prefab_override_views(&engine.ui));
+
+        if let Ok(scene_area) = tree.find_view_mut(SCENE_AREA_ID) {
+            scene_area.set_visible(false);
+        }
+        if let Some(ratio) = self.panel_split_ratio {
+            with_view(tree, EDITOR_SPLIT_ID, |split: &mut SplitPane| split.set_ratio(ratio));
+        }
+
+        with_view(tree, "wireframe", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.wireframe));
+        with_view(tree, "show_colliders", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.show_colliders));
+        with_view(tree, "show_grid", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.show_grid));
+        with_view(tree, "msaa", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.msaa_samples > 1));
+        with_view(tree, "gpu_timing", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.gpu_timing));
+        with_view(tree, "orbit_camera", |checkbox: &mut CheckboxView| checkbox.set_checked(engine.camera.mode == CameraMode::Orbit));
+        with_view(tree, "level_author", |input: &mut TextInputView| input.state.set_text(world.metadata.author.clone()));
+        with_view(tree, "level_description", |input: &mut TextInputView| input.state.set_text(world.metadata.description.clone()));
+        with_view(tree, LOG_SECTION_ID, |section: &mut CollapsibleSection| {
+            section.set_background(Some(LOG_BACKGROUND));
+
+            //Note(teddy) Hidden until the loading thread has queued assets
+            let mut progress = ProgressBarView::new(RESOURCE_PROGRESS_ID.to_owned().into_boxed_str(), RESOURCE_PROGRESS_WIDTH, ViewPosition::zerod(), 10, &engine.ui);
+            progress.set_show_percentage(true, &engine.ui);
+            progress.set_visible(false);
+            section.add_child(Box::new(progress));
+        });
+
+        //Note(teddy) Arrowing through the assets only previews the path, Enter picks the asset like a click
+        with_view(tree, ASSET_LIST_ID, |list: &mut ScrollView| {
+            list.selection.wrap = true;
+            list.selection.on_selection_changed = Some(Box::new(move |ctx: &mut UiContext, index: usize| {
+                if let Some(name) = asset_rows.get(index).and_then(|row| row.strip_prefix(ASSET_ROW_PREFIX)) {
+                    ctx.commands.push(UiCommand::SetText { id: Box::from("asset_path"), text: name.to_string() });
+                }
+            }));
+        });
+    }
+
+    fn attach_editor_callbacks(&mut self) {
+        let tree = &mut self.ui_tree;
+
+        with_view(tree, EDITOR_SPLIT_ID, |split: &mut SplitPane| {
+            split.on_ratio_changed = Some(Box::new(|ctx: &mut UiContext, _view: &mut SplitPane, ratio: f32| {
+                queue(ctx, move |editor, _, _| editor.panel_split_ratio = Some(ratio));
+            }));
+        });
+
+        //Note(teddy) Group rows only expand, picking an entity row selects the entity
+        with_view(tree, OUTLINER_ID, |outliner: &mut TreeView| {
+            outliner.on_node_selected = Some(Box::new(|ctx: &mut UiContext, _view: &mut TreeView, id: usize| {
+                if ctx.world.entities.contains(&id) {
+                    queue(ctx, move |editor, _, _| editor.selected_entity = Some(id));
+                }
+            }));
+        });
+
+        with_view(tree, ASSET_SECTION_ID, |section: &mut CollapsibleSection| {
+            let header = section.header_mut();
+            header.on_hover = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+                view.color = Some(Vector3::new(1.0, 1.0, 1.0));
+                view.get_view_object_mut().set_background_color([0.0, 0.4, 0.0])
+            }));
+            header.on_mouse_leave = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+                view.color = Some(Vector3::new(1.0, 1.0, 1.0));
+                view.get_view_object_mut().set_background_color([0.2, 0.2, 0.0])
+            }));
+        });
+
+        with_view(tree, "save", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                //Note(teddy) Saving overwrites the world file on disk, ask first
+                let mut dialog = ConfirmDialog::new(
+                    "confirm_save".to_owned().into_boxed_str(),
+                    format!("Overwrite the saved world with the one in the editor?"),
+                    format!("Save"),
+                    format!("Cancel"),
+                    &ctx.engine.ui,
+                );
 
-        simple_container.add_child(text_view);
+                dialog.on_confirm = Some(Box::new(|ctx: &mut UiContext, _dialog: &mut ConfirmDialog| {
+                    ctx.world.save();
+                    //Note(teddy) The thumbnail comes from the next rendered frame of the editor camera
+                    ctx.engine.thumbnail_request = ctx.world.metadata.thumbnail.clone();
+                    queue(ctx, |editor, _, engine| {
+                        editor.documents.mark_saved(editor.level_document);
+                        editor.refresh_level_picker(engine);
+                        flash(&mut editor.ui_tree, "save", SAVED_FLASH_COLOR);
+                    });
+                }));
 
-        let objs = load_list_of_obj_assets();
+                queue(ctx, move |editor, _, _| editor.ui_tree.push_modal(Box::new(dialog)));
+            }));
+        });
+
+        with_view(tree, "load", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                match ctx.world.load_world() {
+                    Ok(_) => queue(ctx, |editor, _, _| editor.documents.mark_saved(editor.level_document)),
+                    Err(error) => log_level_load_error(ctx.engine, &error),
+                }
+            }));
+        });
+
+        with_view(tree, "save_all", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                queue(ctx, |editor, world, engine| {
+                    if save_all_documents(editor, world, engine) {
+                        flash(&mut editor.ui_tree, "save_all", SAVED_FLASH_COLOR);
+                    }
+                    editor.refresh_level_picker(engine);
+                });
+            }));
+        });
+
+        let shader = self.shader_label.clone();
+        with_view(tree, "add_rope", |view: &mut TextView| {
+            view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                let shader = shader.clone();
+                queue(ctx, move |editor, world, _| {
+                    //Note(teddy) Selected so the rope sliders show it right away
+                    editor.selected_entity = Some(create_rope(world, editor.selected_entity, shader));
+                    editor.mark_level_dirty();
+                });
+            }));
+        });
+
+        for (field, (id, _, _)) in ROPE_SLIDERS.iter().enumerate() {
+            with_view(tree, id, |slider: &mut SliderView| {
+                slider.on_change = Some(Box::new(move |ctx: &mut UiContext, _view: &mut SliderView, value: f32| {
+                    queue(ctx, move |editor, world, _| {
+                        if let Some(Some(rope)) = editor.selected_entity.and_then(|id| world.components.ropes.get_mut(id)) {
+                            set_rope_field(rope, field, value);
+                            editor.mark_level_dirty();
+                        }
+                    });
+                }));
+            });
+        }
+
+        let shader = self.shader_label.clone();
+        with_view(tree, "add_trigger", |view: &mut TextView| {
+            view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                create_trigger_volume(ctx.world, shader.clone());
+                queue(ctx, |editor, _, _| editor.mark_level_dirty());
+            }));
+        });
+
+        let shader = self.shader_label.clone();
+        with_view(tree, "add_character", |view: &mut TextView| {
+            view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                let id = create_character(ctx.world, shader.clone());
+                queue(ctx, move |editor, _, _| {
+                    editor.selected_entity = Some(id);
+                    editor.mark_level_dirty();
+                });
+            }));
+        });
+
+        with_view(tree, "snap_to_floor", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                queue(ctx, |editor, _, _| editor.snap_requested = true);
+            }));
+        });
+
+        with_view(tree, "merge_meshes", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                queue(ctx, |editor, world, _| {
+                    for (alias, canonical) in world.resources.read().unwrap().mesh_aliases.duplicates() {
+                        println!("Rewriting {} to {}", alias, canonical);
+                    }
+
+                    let changes = merge_duplicate_labels(world);
+                    if !changes.is_empty() {
+                        editor.mark_level_dirty();
+                    }
+                    editor.undo_stack.push_relabel(changes, Instant::now());
+                });
+            }));
+        });
+
+        for (id, _, command) in PREFAB_COMMANDS.iter() {
+            let command = *command;
+
+            with_view(tree, id, |view: &mut TextView| {
+                view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                    queue(ctx, move |editor, world, _| {
+                        if let Some(entity) = editor.selected_entity {
+                            command(entity, world);
+                            editor.mark_level_dirty();
+
+                            //Note(teddy) Prefab edits are written by save all or autosaved like the level
+                            if let Some(instance) = &world.components.prefabs[entity] {
+                                let document = track_prefab(&mut editor.documents, &instance.prefab);
+                                editor.documents.mark_dirty(document);
+                            }
+                        }
+                    });
+                }));
+            });
+        }
+
+        for field in PrefabField::ALL.iter().copied() {
+            with_view(tree, &prefab_override_id("revert", field), |view: &mut TextView| {
+                view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                    queue(ctx, move |editor, world, _| {
+                        let (entity, name) = match editor.selected_entity.and_then(|entity| Some((entity, world.components.prefabs[entity].as_ref()?.prefab.clone()))) {
+                            Some(selected) => selected,
+                            None => return,
+                        };
+                        if let Some(prefab) = world.prefabs.get(&name) {
+                            revert_field(&mut world.components, entity, field, prefab);
+                            editor.mark_level_dirty();
+                        }
+                    });
+                }));
+            });
+        }
+
+        with_view(tree, "reload_shaders", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                queue(ctx, |_, world, engine| {
+                    let result = reload_all_shaders(engine, world);
+                    log_shader_reload(engine, "the shaders folder", result);
+                });
+            }));
+        });
+
+        with_view(tree, "measure", |view: &mut TextView| {
+            view.on_click = Some(Box::new(|ctx: &mut UiContext, _view: &mut TextView| {
+                queue(ctx, |editor, _, _| {
+                    editor.tool = match editor.tool {
+                        EditorTool::Measure(_) => EditorTool::Select,
+                        EditorTool::Select => EditorTool::Measure(Measurement { snap: editor.measure_snap, ..Measurement::new() }),
+                    };
+                });
+            }));
+        });
+
+        with_view(tree, "measure_snap", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                queue(ctx, move |editor, _, _| {
+                    editor.measure_snap = checked;
+                    if let EditorTool::Measure(measurement) = &mut editor.tool {
+                        measurement.snap = checked;
+                    }
+                });
+            });
+        });
+
+        with_view(tree, "fly_to_spawned", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                queue(ctx, move |editor, _, _| editor.fly_to_spawned = checked);
+            });
+        });
+
+        with_view(tree, "align_drops", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                queue(ctx, move |editor, _, _| editor.align_drops = checked);
+            });
+        });
+
+        with_view(tree, "wireframe", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.wireframe = checked;
+            });
+        });
 
-        for (i, name) in objs.into_iter().filter(|s| s.ends_with(".obj")).enumerate() {
-            let mut asset_name_text_view = Box::new(TextView::new(
-                format!("text_{}", name).into_boxed_str(),
-                name.clone(),
-                ViewPosition { x: 10, y: 10 },
-                1.0,
-                10,
-            ));
+        with_view(tree, "show_colliders", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.show_colliders = checked;
+            });
+        });
 
-            asset_name_text_view.get_view_object_mut().background_color = Box::new([0.2, 0.2, 0.2]);
+        with_view(tree, "show_grid", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.show_grid = checked;
+            });
+        });
 
-            asset_name_text_view.on_hover = Some(Box::new(|view: *mut TextView| unsafe {
-                let view_ref = view.as_mut().unwrap();
-                view_ref.color = Some(Vector3::new(1.0, 1.0, 1.0));
-                view_ref.get_view_object_mut().background_color = Box::new([0.0, 0.4, 0.0]);
+        //Note(teddy) The render system log line shows the frame time and the samples in use to compare
+        with_view(tree, "msaa", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.msaa_samples = if checked { DEFAULT_MSAA_SAMPLES } else { 1 };
+            });
+        });
+
+        //Note(teddy) Middle drag turns around the target, with shift it pans, scrolling zooms
+        with_view(tree, "orbit_camera", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.camera.set_mode(if checked { CameraMode::Orbit } else { CameraMode::Fly });
+            });
+        });
+
+        //Note(teddy) The gpu times show in the render stats, F3 or the log
+        with_view(tree, "gpu_timing", |checkbox: &mut CheckboxView| {
+            checkbox.on_toggle(|ctx: &mut UiContext, checked: bool| {
+                ctx.engine.gpu_timing = checked;
+                if checked {
+                    ctx.commands.push(UiCommand::SetVisible { id: Box::from(RENDER_STATS_ID), visible: true });
+                }
+            });
+        });
+
+        let spawn_collider = self.spawn_collider;
+        let spawn_collider = SPAWN_COLLIDER_OPTIONS.iter().position(|(_, shape)| *shape == spawn_collider);
+        with_view(tree, SPAWN_COLLIDER_DROPDOWN, |dropdown: &mut DropdownView| {
+            dropdown.set_selected(spawn_collider);
+            dropdown.on_select = Some(Box::new(|ctx: &mut UiContext, _view: &mut DropdownView, index: usize| {
+                queue(ctx, move |editor, _, _| editor.spawn_collider = SPAWN_COLLIDER_OPTIONS[index].1);
+            }));
+        });
+
+        //Note(teddy) Edit the selected entity, sync_selection_views points them at the current selection
+        with_view(tree, BODY_STATUS_DROPDOWN, |dropdown: &mut DropdownView| {
+            dropdown.on_select = Some(Box::new(|ctx: &mut UiContext, _view: &mut DropdownView, index: usize| {
+                queue(ctx, move |editor, world, _| {
+                    if let Some(Some(physics)) = editor.selected_entity.and_then(|id| world.components.physics.get_mut(id)) {
+                        physics.status = BODY_STATUS_OPTIONS[index].1;
+                        editor.mark_level_dirty();
+                    }
+                });
+            }));
+        });
+
+        with_view(tree, SHADER_DROPDOWN, |dropdown: &mut DropdownView| {
+            dropdown.on_select = Some(Box::new(|ctx: &mut UiContext, view: &mut DropdownView, index: usize| {
+                let label = view.options()[index].clone();
+                queue(ctx, move |editor, world, _| edit_selected(editor, world, PrefabValue::Shader(label)));
+            }));
+        });
+
+        //Note(teddy) Only for looking at the entity, the level isn't dirtied
+        with_view(tree, DEBUG_VIEW_DROPDOWN, |dropdown: &mut DropdownView| {
+            dropdown.on_select = Some(Box::new(|ctx: &mut UiContext, _view: &mut DropdownView, index: usize| {
+                queue(ctx, move |editor, world, _| {
+                    if let Some(Some(render)) = editor.selected_entity.and_then(|id| world.components.renderables.get_mut(id)) {
+                        render.debug_view = DEBUG_VIEW_OPTIONS[index].1;
+                    }
+                });
+            }));
+        });
+
+        with_view(tree, ENTITY_COLOR_PICKER, |picker: &mut ColorPickerView| {
+            picker.on_change = Some(Box::new(|ctx: &mut UiContext, _view: &mut ColorPickerView, color: [f32; 3]| {
+                queue(ctx, move |editor, world, _| edit_selected(editor, world, PrefabValue::Color(color)));
+            }));
+        });
+
+        with_view(tree, ENTITY_OPACITY_SLIDER, |slider: &mut SliderView| {
+            slider.on_change = Some(Box::new(|ctx: &mut UiContext, _view: &mut SliderView, value: f32| {
+                queue(ctx, move |editor, world, _| edit_selected(editor, world, PrefabValue::Opacity(value)));
+            }));
+        });
+
+        for (id, _, flag) in ENTITY_FLAG_CHECKBOXES.iter() {
+            let flag = *flag;
+            with_view(tree, id, |checkbox: &mut CheckboxView| {
+                checkbox.on_toggle(move |ctx: &mut UiContext, checked: bool| {
+                    queue(ctx, move |editor, world, _| {
+                        let render = editor.selected_entity.and_then(|id| world.components.renderables.get(id)).and_then(|render| render.as_ref());
+                        if let Some(mut flags) = render.map(|render| render.flags) {
+                            flags.set(flag, checked);
+                            edit_selected(editor, world, PrefabValue::Flags(flags));
+                        }
+                    });
+                });
+            });
+        }
+
+        //Note(teddy) Same commands as the stdin console, e.g. `light dir 0.5 -1 0.2`
+        with_view(tree, "console_input", |input: &mut TextInputView| {
+            input.on_submit = Some(Box::new(|ctx: &mut UiContext, view: &mut TextInputView| {
+                match parse_console_command(view.state.text()) {
+                    Ok(command) => command.apply(&mut ctx.engine.dir_lights),
+                    Err(error) => println!("Console: {:?}", error),
+                }
+                view.state.set_text(String::new());
+            }));
+        });
+
+        //Note(teddy) Kept in sync with the console every frame by sync_light_sliders
+        for (axis, id) in LIGHT_DIRECTION_SLIDERS.iter().enumerate() {
+            with_view(tree, id, |slider: &mut SliderView| {
+                slider.on_change = Some(Box::new(move |ctx: &mut UiContext, _view: &mut SliderView, value: f32| {
+                    ctx.engine.dir_lights.direction[axis] = value;
+                }));
+            });
+        }
+
+        with_view(tree, LIGHT_AMBIENT_SLIDER, |slider: &mut SliderView| {
+            slider.on_change = Some(Box::new(|ctx: &mut UiContext, _view: &mut SliderView, value: f32| {
+                ctx.engine.dir_lights.ambient = value;
+            }));
+        });
+
+        with_view(tree, LIGHT_COLOR_PICKER, |picker: &mut ColorPickerView| {
+            picker.on_change = Some(Box::new(|ctx: &mut UiContext, _view: &mut ColorPickerView, color: [f32; 3]| {
+                ctx.engine.dir_lights.color = color;
+            }));
+        });
+
+        //Note(teddy) World settings, stored in the level sidecar on the next save
+        with_view(tree, "level_author", |input: &mut TextInputView| {
+            input.on_submit = Some(Box::new(|ctx: &mut UiContext, view: &mut TextInputView| {
+                ctx.world.metadata.author = String::from(view.state.text());
+                queue(ctx, |editor, _, _| editor.mark_level_dirty());
             }));
-            asset_name_text_view.on_mouse_leave = Some(Box::new(|view: *mut TextView| unsafe {
-                let view_ref = view.as_mut().unwrap();
-                view_ref.color = Some(Vector3::new(1.0, 1.0, 1.0));
-                view_ref.get_view_object_mut().background_color = Box::new([0.2, 0.2, 0.2]);
+        });
+
+        with_view(tree, "level_description", |input: &mut TextInputView| {
+            input.on_submit = Some(Box::new(|ctx: &mut UiContext, view: &mut TextInputView| {
+                ctx.world.metadata.description = String::from(view.state.text());
+                queue(ctx, |editor, _, _| editor.mark_level_dirty());
             }));
+        });
 
-            let shader = self.shader_label.clone();
-            let world_ptr: *mut World = world;
-            let engine_ptr: *mut Engine = engine;
-            let self_ptr: *mut Self = self;
+        //Note(teddy) Spawns the mesh at the path, same as double clicking it in the asset list
+        let shader = self.shader_label.clone();
+        with_view(tree, "asset_path", |input: &mut TextInputView| {
+            input.on_submit = Some(Box::new(move |ctx: &mut UiContext, view: &mut TextInputView| {
+                let path = view.state.text().trim().to_string();
+                if path.is_empty() {
+                    return;
+                }
+
+                let id = create_entity(ctx.world, path, shader.clone());
+                queue(ctx, move |editor, world, engine| finish_spawn(editor, engine, world, id));
+                view.state.set_text(String::new());
+            }));
+        });
 
-            asset_name_text_view.on_click = Some(Box::new(move |view: *mut TextView| {
-                let self_ref = unsafe { self_ptr.as_mut().unwrap() };
-                //Move the camera closer to the entity
+        //Note(teddy) Builds a terrain from a png in the textures folder, centered on the origin
+        let shader = self.shader_label.clone();
+        with_view(tree, "terrain_heightmap", |input: &mut TextInputView| {
+            input.on_submit = Some(Box::new(move |ctx: &mut UiContext, view: &mut TextInputView| {
+                let image = view.state.text().trim().to_string();
+                if image.is_empty() {
+                    return;
+                }
 
-                println!("ON CLICK CLICKED");
-                let id = create_entity(world_ptr, engine_ptr, name.clone(), shader.clone());
-                self_ref.selected_entity = Some(id);
+                let name = Path::new(&image).file_stem().and_then(|stem| stem.to_str()).unwrap_or("terrain").to_string();
+                let result = ctx.world.add_terrain(&name, &image, Vector3::from(TERRAIN_SCALE), Vector3::zeros(), &shader);
+                if result.is_ok() {
+                    view.state.set_text(String::new());
+                }
+                queue(ctx, move |editor, _, engine| log_terrain(editor, engine, &image, result));
             }));
+        });
+    }
+}
 
-            simple_container.add_child(asset_name_text_view);
+fn log_terrain(editor: &mut Editor, engine: &mut Engine, image: &str, result: Result<Vec<usize>, String>) {
+    let text = match result {
+        Ok(chunks) => {
+            editor.mark_level_dirty();
+            format!("TERRAIN: added {} chunks from {}, they aren't saved with the level", chunks.len(), image)
         }
+        Err(error) => format!("TERRAIN: couldn't load {}: {}", image, error),
+    };
+    engine.log_manager.add_log((String::from("terrain"), Box::new(EditorLogObject { text })));
+}
+
+///Lights the view's background up and fades it back. Views without a background are left alone, and so are
+///ones still fading, their background isn't their own color yet.
+fn flash(tree: &mut UITree, id: &str, color: [f32; 3]) {
+    if tree.is_animating(id) {
+        return;
+    }
+
+    let background = match tree.find_view_mut(id).ok().and_then(|view| view.get_view_object().background) {
+        Some(background) => [background[0], background[1], background[2]],
+        None => return,
+    };
+    tree.animate(fade(id, color, background, FLASH_DURATION));
+}
+
+///Runs attach on the editor view with that id, the ui file may have left it out
+fn with_view<T: View + 'static, F: FnOnce(&mut T)>(tree: &mut UITree, id: &str, attach: F) {
+    match tree.find_view_as::<T>(id) {
+        Some(view) => attach(view),
+        None => println!("The editor ui has no {} view of the expected type", id),
+    }
+}
+
+///Width from the left edge to where the scene starts, the whole root when the ui file has no split
+fn panel_width(tree: &mut UITree) -> i32 {
+    if let Some(split) = tree.find_view_as::<SplitPane>(EDITOR_SPLIT_ID) {
+        let (_, scene) = split.pane_sizes();
+        return split.get_view_object().size.unwrap_or(ViewDimens::zerod()).x - scene;
+    }
+
+    tree.root.as_ref().and_then(|root| root.get_view_object().size).unwrap_or(ViewDimens::zerod()).x
+}
+
+fn add_children(tree: &mut UITree, id: &str, views: Vec<Box<dyn View>>) {
+    match tree.find_view_mut(id).ok().and_then(|view| view.as_container_mut()) {
+        Some(container) => views.into_iter().for_each(|view| container.add_child(view)),
+        None => println!("The editor ui has no {} container", id),
+    }
+}
+
+fn asset_entry_views(shader_label: &str, resources: &UiResources) -> Vec<Box<dyn View>> {
+    let mut views: Vec<Box<dyn View>> = vec![];
+
+    for name in load_list_of_obj_assets().into_iter().filter(|s| s.ends_with(".obj")) {
+        let mut asset_name_text_view = Box::new(TextView::new(
+            format!("{}{}", ASSET_ROW_PREFIX, name).into_boxed_str(),
+            name.clone(),
+            ViewPosition { x: 10, y: 10 },
+            1.0,
+            10,
+            resources,
+        ));
+
+        asset_name_text_view.get_view_object_mut().set_background_color(ASSET_ROW_COLOR);
+        asset_name_text_view.set_max_width(Some(PANEL_TEXT_MAX_WIDTH), TextOverflow::Ellipsis);
+        asset_name_text_view.set_tooltip(name.clone());
+        asset_name_text_view.set_draggable(name.clone());
 
-        let mut save_world = TextView::new("save".to_owned().into_boxed_str(), format!("Save world"), ViewPosition::zerod(), 1.0, 10);
-        let mut load_world = TextView::new("load".to_owned().into_boxed_str(), format!("Load world"), ViewPosition::zerod(), 1.0, 10);
+        asset_name_text_view.set_border(ASSET_BORDER_COLOR, 1);
+        asset_name_text_view.set_corner_radius(4);
 
-        let world_ptr: *mut World = world;
-        let engine_ptr: *const Engine = engine;
+        //Note(teddy) Only the border lights up, the fill stays the same
+        asset_name_text_view.on_hover = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+            view.set_border_color([0.0, 0.7, 0.0]);
+        }));
+        asset_name_text_view.on_mouse_leave = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+            view.set_border_color(ASSET_BORDER_COLOR);
+        }));
 
-        save_world.on_click = Some(Box::new( move |view: *mut TextView| unsafe {
-            let world_ref = world_ptr.as_mut().unwrap();
-            let engine_ref = engine_ptr.as_ref().unwrap();
-            world_ref.save();
+        let shader = shader_label.to_string();
+
+        //Note(teddy) A single click only picks the asset, spawning takes a double click so stray clicks don't
+        let path = name.clone();
+        asset_name_text_view.on_click = Some(Box::new(move |ctx: &mut UiContext, view: &mut TextView| {
+            ctx.commands.push(UiCommand::SetText { id: Box::from("asset_path"), text: path.clone() });
+            let row = Box::<str>::from(view.get_id());
+            queue(ctx, move |editor, _, _| editor.select_asset(row));
         }));
 
-        load_world.on_click = Some(Box::new( move |view: *mut TextView| unsafe {
-            let world_ref = world_ptr.as_mut().unwrap();
-            world_ref.load_world();
+        asset_name_text_view.on_double_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+            //Move the camera closer to the entity
+            let id = create_entity(ctx.world, name.clone(), shader.clone());
+            queue(ctx, move |editor, world, engine| finish_spawn(editor, engine, world, id));
         }));
 
-        let sep = TextView::new("logs".to_owned().into_boxed_str(), format!("------------------------------------------------------------------------"), ViewPosition::zerod(), 1.0, 10);
-        let text_view = TextView::new("logs".to_owned().into_boxed_str(), format!("Logs"), ViewPosition::zerod(), 1.0, 10);
-        simple_container.add_child(Box::new(save_world));
-        simple_container.add_child(Box::new(load_world));
-        simple_container.add_child(Box::new(sep));
-        simple_container.add_child(Box::new(text_view));
-        simple_container.add_child(log_container);
-        self.ui_tree.root = Some(simple_container);
+        views.push(asset_name_text_view);
     }
+
+    views
 }
 
-static mut COUNTER: f32 = 0.0;
+///Same layout as assets/ui/editor.json, used when the file can't be loaded
+fn build_editor_ui(engine: &Engine) -> Box<dyn View> {
+    let resources = &engine.ui;
+    let text = |id: &str, text: &str| Box::new(TextView::new(id.to_owned().into_boxed_str(), text.to_string(), ViewPosition::zerod(), 1.0, 10, resources));
+    let input = |id: &str| Box::new(TextInputView::new(id.to_owned().into_boxed_str(), 300, ViewPosition::zerod(), 10, resources));
+    let container = |id: &str| Box::new(SimpleUIContainer::new(id.to_owned().into_boxed_str(), None, ViewPosition::zerod(), Orientation::Vertical, 0, 1.0, false));
+
+    let mut simple_container = Box::new(SimpleUIContainer::new(
+        String::from("simple_container").into_boxed_str(),
+        None,
+        ViewPosition::new(0, 0),
+        Orientation::Vertical,
+        10,
+        1.0,
+        false,
+    ));
+
+    let mut asset_section = Box::new(CollapsibleSection::new(ASSET_SECTION_ID.to_owned().into_boxed_str(), format!("Objects"), ViewPosition::zerod(), 10, resources));
+    asset_section.header_mut().get_view_object_mut().set_background_color([0.6, 0.2, 0.2]);
+    asset_section.header_mut().set_font_size(Some(SECTION_HEADER_FONT_SIZE), engine);
+
+    let mut outliner_section = Box::new(CollapsibleSection::new(OUTLINER_SECTION_ID.to_owned().into_boxed_str(), format!("Scene"), ViewPosition::zerod(), 10, resources));
+    outliner_section.header_mut().set_font_size(Some(SECTION_HEADER_FONT_SIZE), engine);
+    outliner_section.add_child(Box::new(TreeView::new(OUTLINER_ID.to_owned().into_boxed_str(), ViewPosition::zerod(), 0, resources)));
+
+    let mut asset_list = Box::new(ScrollView::new(ASSET_LIST_ID.to_owned().into_boxed_str(), ASSET_LIST_HEIGHT, ViewPosition::zerod(), 0));
+    asset_list.set_spacing(ASSET_LIST_SPACING);
+    asset_list.set_selectable(true);
+    asset_section.add_child(asset_list);
+    simple_container.add_child(asset_section);
+    simple_container.add_child(outliner_section);
 
-fn create_entity(
-    world_ptr: *mut World,
-    engine_ptr: *mut Engine,
-    file_path: String,
-    shader_label: String,
-) -> usize {
-    let world = unsafe { world_ptr.as_mut().unwrap() };
-    let engine = unsafe { engine_ptr.as_mut().unwrap() };
+    simple_container.add_child(text("save", "Save world"));
+    simple_container.add_child(text("load", "Load world"));
+    simple_container.add_child(text("save_all", "Save all"));
+    simple_container.add_child(text("add_rope", "Add rope"));
+    for (id, label, range) in ROPE_SLIDERS.iter() {
+        simple_container.add_child(text(&format!("{}_label", id), label));
+        simple_container.add_child(Box::new(SliderView::new(id.to_string().into_boxed_str(), 200, *range, range.0, ViewPosition::zerod(), 10, resources)));
+    }
+    for (id, label, _) in PREFAB_COMMANDS.iter() {
+        simple_container.add_child(text(id, label));
+    }
+    simple_container.add_child(container(PREFAB_OVERRIDES_ID));
+    simple_container.add_child(text("add_trigger", "Add trigger volume"));
+    simple_container.add_child(text("add_character", "Add character"));
+    simple_container.add_child(text("snap_to_floor", "Snap to floor"));
+    simple_container.add_child(text("merge_meshes", "Merge duplicate meshes"));
+    simple_container.add_child(text("reload_shaders", "Reload shaders"));
+    simple_container.add_child(text("measure", "Measure"));
+    simple_container.add_child(Box::new(CheckboxView::new("measure_snap".to_owned().into_boxed_str(), Some(format!("Measure snapping")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("fly_to_spawned".to_owned().into_boxed_str(), Some(format!("Fly to new entities")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("align_drops".to_owned().into_boxed_str(), Some(format!("Align drops to surface")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("wireframe".to_owned().into_boxed_str(), Some(format!("Wireframe")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("show_colliders".to_owned().into_boxed_str(), Some(format!("Show colliders")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("show_grid".to_owned().into_boxed_str(), Some(format!("Show grid")), true, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("msaa".to_owned().into_boxed_str(), Some(format!("Anti-aliasing")), true, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("gpu_timing".to_owned().into_boxed_str(), Some(format!("GPU timing")), false, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(CheckboxView::new("orbit_camera".to_owned().into_boxed_str(), Some(format!("Orbit camera")), false, ViewPosition::zerod(), 10, resources)));
+
+    simple_container.add_child(Box::new(DropdownView::new(
+        SPAWN_COLLIDER_DROPDOWN.to_owned().into_boxed_str(),
+        SPAWN_COLLIDER_OPTIONS.iter().map(|(name, _)| name.to_string()).collect(),
+        Some(0),
+        ViewPosition::zerod(),
+        10,
+        resources,
+    )));
+    simple_container.add_child(Box::new(DropdownView::new(
+        BODY_STATUS_DROPDOWN.to_owned().into_boxed_str(),
+        BODY_STATUS_OPTIONS.iter().map(|(name, _)| name.to_string()).collect(),
+        None,
+        ViewPosition::zerod(),
+        10,
+        resources,
+    )));
+    simple_container.add_child(Box::new(DropdownView::new(SHADER_DROPDOWN.to_owned().into_boxed_str(), vec![], None, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(DropdownView::new(
+        DEBUG_VIEW_DROPDOWN.to_owned().into_boxed_str(),
+        DEBUG_VIEW_OPTIONS.iter().map(|(name, _)| name.to_string()).collect(),
+        None,
+        ViewPosition::zerod(),
+        10,
+        resources,
+    )));
+    simple_container.add_child(Box::new(ColorPickerView::new(ENTITY_COLOR_PICKER.to_owned().into_boxed_str(), Material::default().base_color, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(SliderView::new(ENTITY_OPACITY_SLIDER.to_owned().into_boxed_str(), 200, (0.0, 1.0, 0.05), 1.0, ViewPosition::zerod(), 10, resources)));
+    for (id, label, _) in ENTITY_FLAG_CHECKBOXES.iter() {
+        simple_container.add_child(Box::new(CheckboxView::new(id.to_string().into_boxed_str(), Some(label.to_string()), false, ViewPosition::zerod(), 10, resources)));
+    }
+    simple_container.add_child(input("console_input"));
+
+    for id in LIGHT_DIRECTION_SLIDERS.iter() {
+        simple_container.add_child(Box::new(SliderView::new(id.to_string().into_boxed_str(), 200, LIGHT_DIRECTION_RANGE, 0.0, ViewPosition::zerod(), 10, resources)));
+    }
+    simple_container.add_child(Box::new(SliderView::new(LIGHT_AMBIENT_SLIDER.to_owned().into_boxed_str(), 200, (0.0, 1.0, 0.05), 0.0, ViewPosition::zerod(), 10, resources)));
+    simple_container.add_child(Box::new(ColorPickerView::new(LIGHT_COLOR_PICKER.to_owned().into_boxed_str(), [1.0; 3], ViewPosition::zerod(), 10, resources)));
+
+    simple_container.add_child(text("asset_path_label", "Asset path"));
+    simple_container.add_child(input("asset_path"));
+    simple_container.add_child(text("author_label", "Author"));
+    simple_container.add_child(input("level_author"));
+    simple_container.add_child(text("description_label", "Description"));
+    simple_container.add_child(input("level_description"));
+    simple_container.add_child(text("terrain_heightmap_label", "Terrain heightmap"));
+    simple_container.add_child(input("terrain_heightmap"));
+    simple_container.add_child(container(RECOVERY_CONTAINER_ID));
+
+    let mut levels_label = text("levels_label", "Levels");
+    levels_label.set_font_size(Some(SECTION_HEADER_FONT_SIZE), engine);
+    simple_container.add_child(levels_label);
+    simple_container.add_child(container(LEVELS_CONTAINER_ID));
+
+    let mut sep = text("logs_separator", "------------------------------------------------------------------------");
+    sep.set_max_width(Some(PANEL_TEXT_MAX_WIDTH), TextOverflow::Ellipsis);
+    simple_container.add_child(sep);
+
+    let mut log_section = Box::new(CollapsibleSection::new(LOG_SECTION_ID.to_owned().into_boxed_str(), format!("Logs"), ViewPosition::zerod(), 10, resources));
+    log_section.header_mut().align = TextAlign::Center;
+    log_section.header_mut().set_font_size(Some(SECTION_HEADER_FONT_SIZE), engine);
+    log_section.set_clip_children(true);
+    simple_container.add_child(log_section);
+
+    simple_container.get_view_object_mut().anchor = Some(Anchor::StretchVertical);
+
+    let mut split = Box::new(SplitPane::new(EDITOR_SPLIT_ID.to_owned().into_boxed_str(), Orientation::Horizontal, simple_container, container(SCENE_AREA_ID), ViewPosition::zerod()));
+    split.set_ratio(PANEL_SPLIT_RATIO);
+    split.set_min_sizes(PANEL_MIN_WIDTH, SCENE_MIN_WIDTH);
+    split
+}
+
+///Editor work queued by a ui callback, update_editor runs it once the tree is done dispatching
+struct EditorCommand(Box<dyn FnOnce(&mut Editor, &mut World, &mut Engine)>);
+
+fn queue<F: FnOnce(&mut Editor, &mut World, &mut Engine) + 'static>(ctx: &mut UiContext, command: F) {
+    ctx.commands.push(EditorCommand(Box::new(command)));
+}
 
+static mut COUNTER: f32 = 0.0;
+
+fn create_entity(world: &mut World, file_path: String, shader_label: String) -> usize {
     let id = world.create_entity();
 
     let words: Vec<&str> = file_path.split("/").collect();
     let mesh_id = String::from(words[words.len() - 1]);
-    world.add_resource(AssetSource::Mesh(ObjType::Normal, String::from(words[words.len() - 1])));
+    let material = mesh_material(&mesh_id);
+    world.add_mesh(&mesh_id, &material);
 
-    world.components.renderables[id] = Some(RenderComponent::new(mesh_id, shader_label));
+    let mut render_component = RenderComponent::new(mesh_id, shader_label);
+    render_component.material = material;
+    world.components.renderables[id] = Some(render_component);
     world.components.positionable[id] = Some(TransformComponent::new(
         Vector3::new(0.0 + (5.0 * unsafe { COUNTER }), 0.0, 10.0),
         Vector3::new(0.0, 1.0, 0.0),
@@ -184,73 +1084,1435 @@ fn create_entity(
     id
 }
 
-fn load_list_of_obj_assets() -> Vec<String> {
-    let mut output = vec![];
-    let directory = fs::read_dir(dbg!(Path::new(OBJ_ASSETS_DIR))).unwrap();
-    for entry in directory {
-        let dir = entry.unwrap().path();
-        output.push(String::from(dir.to_str().unwrap()));
+//Note(teddy) Runs before the physics system sees the new entity, its body is built with the chosen collider
+fn finish_spawn(editor: &mut Editor, engine: &mut Engine, world: &mut World, id: usize) {
+    if let Some(physics) = world.components.physics[id].as_mut() {
+        physics.shape = editor.spawn_collider;
     }
 
-    output
+    editor.selected_entity = Some(id);
+    editor.mark_level_dirty();
+    if editor.fly_to_spawned {
+        frame_entity(engine, world, id);
+    }
 }
 
-pub fn update_editor(
-    editor: &mut Editor,
-    engine: &mut Engine,
-    world: &mut World,
-    event_manager: &mut EventManager,
-) {
-    //TODO(teddy)
-    //1. get the selected_entity and add higlight component
-    //
+//Note(teddy) Trigger volumes are drawn as wireframe boxes on top of the scene and never picked
+//Note(teddy) Drawn as a sphere, there's no capsule mesh. Turn on collider outlines to see the capsule
+fn create_character(world: &mut World, shader_label: String) -> usize {
+    let id = world.create_entity();
+    let mesh_label = String::from("sphere.obj");
 
-    if mouse_clicked(engine, &MouseButton::Button3) {
-        println!("Button event captured");
-    }
+    world.add_resource(AssetSource::Mesh(ObjType::Normal, mesh_label.clone()));
+    world.components.renderables[id] = Some(RenderComponent::new(mesh_label, shader_label));
+    world.components.positionable[id] = Some(TransformComponent::new(Vector3::new(0.0, 2.0, 10.0), Vector3::zeros(), 0.5));
 
-    if let Some(id) = editor.selected_entity {
-        let component = world.components.positionable[id].as_ref().unwrap();
+    let mut physics = PhysicsComponent::new(
+        1.0,
+        false,
+        BodyStatus::Kinematic,
+        Vector3::zeros(),
+        MaterialHandle::new(BasicMaterial::new(0.0, 0.0)),
+    );
+    physics.shape = ColliderShape::Capsule { half_height: 0.5, radius: 0.5 };
+    world.components.physics[id] = Some(physics);
+    world.components.characters[id] = Some(CharacterController::new(0.3, 45.0f32.to_radians()));
 
-        let camera = &engine.camera;
-        let ViewPortDimensions{  width, height } = camera.view_port;
+    id
+}
 
-        //TODO(Teddy) fix tomorrow
-        let result = compute_world_space_to_screen_space(
-            ViewPortDimensions { width, height },
-            &component.position.translation.vector,
-            &camera.view(),
-            &camera.perspective(),
-        );
+fn create_trigger_volume(world: &mut World, shader_label: String) -> usize {
+    let id = world.create_entity();
+    let mesh_label = String::from("cube.obj");
 
-        if (result.x > 0.0 && result.x < width as f32)
-            && (result.y > 0.0 && result.y < height as f32)
-        {
-            //TODO(teddy):
-        }
+    world.add_resource(AssetSource::Mesh(ObjType::Normal, mesh_label.clone()));
+    world.components.renderables[id] = Some(RenderComponent::editor_helper(mesh_label, shader_label));
+    world.components.positionable[id] = Some(TransformComponent::new(
+        Vector3::new(0.0, 0.0, 10.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+    ));
+    let mut physics = PhysicsComponent::new(
+        1.0,
+        false,
+        BodyStatus::Static,
+        Vector3::new(0.0, 0.0, 0.0),
+        MaterialHandle::new(BasicMaterial::new(0.0, 0.0)),
+    );
+    physics.layer = CollisionLayer::Trigger;
+    physics.is_sensor = true;
+    world.components.physics[id] = Some(physics);
 
-        //Note(teddy) Draw a quad at that position
-        handle_world_events(editor, engine, world, event_manager);
-        unsafe { draw_transform_guides(&Vector3::new(0.0, 0.0, 0.0)) };
-    }
+    id
 }
 
-fn handle_world_events(
-    editor: &mut Editor,
-    engine: &Engine,
-    world: &mut World,
-    event_manager: &mut EventManager,
-) {
-    for event in event_manager.get_engine_events() {
-        match event.event_type {
-            EventType::RayCasted(CastedRay { id: _, entity }) if entity.is_some() => {
-                //world.components.renderables[entity.unwrap()] = Some(HighlightComponent {
-                 //   color: [0.0, 1.0, 0.0],
-                //});
-            }
-            _ => (),
-        }
-    }
+//Note(teddy) Hangs a rope from the selected entity, or between two fixed points when nothing is selected
+fn create_rope(world: &mut World, anchor: Option<usize>, shader_label: String) -> usize {
+    let id = world.create_entity();
+
+    let origin = match anchor.and_then(|entity| world.components.positionable[entity].as_ref()) {
+        Some(transform) => Point3::from(transform.position.translation.vector),
+        None => Point3::new(0.0, 4.0, 10.0),
+    };
+
+    let start = match anchor {
+        Some(entity) if world.components.positionable[entity].is_some() => RopeAnchor::Entity(entity),
+        _ => RopeAnchor::Point(origin),
+    };
+    let end = RopeAnchor::Point(origin + Vector3::new(4.0, 0.0, 0.0));
+
+    world.components.ropes[id] = Some(RopeComponent::new(start, end, 16, 6.0, 8, 0.1, shader_label));
+    id
+}
+
+//Note(teddy) The prefab is named after the mesh, the entity becomes its first instance
+fn make_prefab(entity: usize, world: &mut World) {
+    let (render, transform) = match (&world.components.renderables[entity], &world.components.positionable[entity]) {
+        (Some(render), Some(transform)) => (render, transform),
+        _ => return,
+    };
+
+    let name = render.mesh_label.trim_end_matches(".obj").to_string();
+    let prefab = Prefab {
+        name: name.clone(),
+        mesh_label: render.mesh_label.clone(),
+        shader_label: render.shader_label.clone(),
+        scale: transform.scale,
+        flags: render.flags.0,
+        base_color: render.material.base_color,
+        opacity: render.material.opacity,
+    };
+
+    world.components.prefabs[entity] = Some(PrefabInstance::new(name.clone()));
+    world.prefabs.insert(name, prefab);
 }
 
-unsafe fn draw_transform_guides(position: &Vector3<f32>) {}
+//Note(teddy) The new instance lands next to the selected one, with the collider the selected one has
+fn place_instance(entity: usize, world: &mut World) {
+    let prefab = match world.components.prefabs[entity].as_ref().and_then(|instance| world.prefabs.get(&instance.prefab)) {
+        Some(prefab) => prefab.clone(),
+        None => return,
+    };
+    let position = match world.components.positionable[entity].as_ref() {
+        Some(transform) => transform.position.translation.vector + Vector3::new(DUPLICATE_OFFSET, 0.0, 0.0),
+        None => return,
+    };
+    let shape = world.components.physics[entity].as_ref().map(|physics| physics.shape);
+
+    let material = mesh_material(&prefab.mesh_label);
+    world.add_mesh(&prefab.mesh_label, &material);
+
+    let id = world.create_entity();
+    instantiate(&mut world.components, id, &prefab, position);
+    if let Some(render) = world.components.renderables[id].as_mut() {
+        render.material.diffuse_map = material.diffuse_map;
+    }
+
+    if let Some(shape) = shape {
+        let mut physics = PhysicsComponent::new(
+            1.0,
+            false,
+            BodyStatus::Static,
+            Vector3::new(0.0, 0.0, 0.0),
+            MaterialHandle::new(BasicMaterial::new(0.3, 0.8)),
+        );
+        physics.shape = shape;
+        world.components.physics[id] = Some(physics);
+    }
+}
+
+fn apply_to_prefab(entity: usize, world: &mut World) {
+    let name = match &world.components.prefabs[entity] {
+        Some(instance) => instance.prefab.clone(),
+        None => return,
+    };
+
+    if let Some(prefab) = world.prefabs.get_mut(&name) {
+        apply_instance_to_prefab(&mut world.components, entity, prefab);
+    }
+    update_from_prefab(entity, world);
+}
+
+fn update_from_prefab(entity: usize, world: &mut World) {
+    let name = match &world.components.prefabs[entity] {
+        Some(instance) => instance.prefab.clone(),
+        None => return,
+    };
+    let prefab = match world.prefabs.get(&name) {
+        Some(prefab) => prefab,
+        None => return,
+    };
+
+    update_instances(&mut world.components, world.entities.iter().copied(), prefab);
+}
+
+fn revert_to_prefab(entity: usize, world: &mut World) {
+    let (fields, prefab) = match world.components.prefabs[entity].as_ref() {
+        Some(instance) => match world.prefabs.get(&instance.prefab) {
+            Some(prefab) => (instance.overridden_fields(), prefab),
+            None => return,
+        },
+        None => return,
+    };
+
+    for field in fields {
+        revert_field(&mut world.components, entity, field, prefab);
+    }
+}
+
+fn prefab_override_id(part: &str, field: PrefabField) -> String {
+    format!("prefab_{}_{}", part, field.name().to_lowercase())
+}
+
+///A field name and a revert button per PrefabField, hidden until an instance is selected
+fn prefab_override_views(resources: &UiResources) -> Vec<Box<dyn View>> {
+    PrefabField::ALL
+        .iter()
+        .map(|field| {
+            let text = |id: String, text: &str| Box::new(TextView::new(id.into_boxed_str(), text.to_string(), ViewPosition::zerod(), 1.0, 5, resources));
+
+            let mut row = SimpleUIContainer::new(prefab_override_id("row", *field).into_boxed_str(), None, ViewPosition::zerod(), Orientation::Horizontal, 0, 1.0, false);
+            row.add_child(text(prefab_override_id("field", *field), field.name()));
+            row.add_child(text(prefab_override_id("revert", *field), "Revert"));
+            row.set_visible(false);
+            Box::new(row) as Box<dyn View>
+        })
+        .collect()
+}
+
+///Inspector edit of the selected entity, on a prefab instance the field becomes an override
+fn edit_selected(editor: &mut Editor, world: &mut World, value: PrefabValue) {
+    let entity = match editor.selected_entity {
+        Some(entity) if world.components.renderables.get(entity).map_or(false, |render| render.is_some()) => entity,
+        _ => return,
+    };
+
+    set_instance_field(&mut world.components, entity, value);
+    editor.mark_level_dirty();
+}
+
+//Note(teddy) Only the sidecars and thumbnails are read here, a level is loaded once it's picked.
+fn level_picker_views(resources: &UiResources, thumbnails: &mut Vec<u32>) -> Vec<Box<dyn View>> {
+    let now = unix_now();
+
+    list_levels(Path::new(WORLD_LEVELS_DIR))
+        .into_iter()
+        .map(|level| {
+            let (entities, outdated) = match &level.metadata {
+                Some(metadata) => (format!("{} entities", metadata.entity_count), metadata.is_outdated()),
+                None => (String::from("no metadata"), false),
+            };
+
+            let mut text = format!("{} - {} - {}", level.name, entities, format_age(now.saturating_sub(level.modified)));
+            if outdated {
+                text.push_str(" (!) old format");
+            }
+
+            let mut view = TextView::new(format!("level_{}", level.name).into_boxed_str(), text, ViewPosition::zerod(), 1.0, 10, resources);
+            if outdated {
+                view.color = Some(Vector3::new(1.0, 0.8, 0.2));
+            }
+            let path = level.path.clone();
+            view.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                let path = path.clone();
+                queue(ctx, move |editor, world, engine| open_level(editor, engine, world, path));
+            }));
+
+            let mut row = SimpleUIContainer::new(format!("level_row_{}", level.name).into_boxed_str(), None, ViewPosition::zerod(), Orientation::Horizontal, 0, 1.0, false);
+            //Note(teddy) Levels saved before thumbnails were written only get the text
+            if let Ok(texture) = load_thumbnail(&thumbnail_path(&level.path)) {
+                thumbnails.push(texture);
+                let mut image = ImageView::new(format!("level_thumb_{}", level.name).into_boxed_str(), texture, LEVEL_THUMBNAIL_SIZE, ViewPosition::zerod(), 5);
+                image.flip_vertical = true;
+                let path = level.path.clone();
+                image.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut ImageView| {
+                    let path = path.clone();
+                    queue(ctx, move |editor, world, engine| open_level(editor, engine, world, path));
+                }));
+                row.add_child(Box::new(image));
+            }
+            row.add_child(Box::new(view));
+            Box::new(row) as Box<dyn View>
+        })
+        .collect()
+}
+
+//Note(teddy) A picked level replaces the scene, unsaved changes are only dropped once that's confirmed
+fn open_level(editor: &mut Editor, engine: &mut Engine, world: &mut World, path: PathBuf) {
+    if !editor.documents.is_dirty(editor.level_document) {
+        replace_level(editor, engine, world, &path);
+        return;
+    }
+    if editor.ui_tree.has_modal() {
+        return;
+    }
+
+    let mut dialog = ConfirmDialog::new(
+        "confirm_open_level".to_owned().into_boxed_str(),
+        format!("Discard the unsaved changes and open {}?", path.display()),
+        format!("Open"),
+        format!("Cancel"),
+        &engine.ui,
+    );
+    dialog.on_confirm = Some(Box::new(move |ctx: &mut UiContext, _dialog: &mut ConfirmDialog| {
+        let path = path.clone();
+        queue(ctx, move |editor, world, engine| replace_level(editor, engine, world, &path));
+    }));
+    editor.ui_tree.push_modal(Box::new(dialog));
+}
+
+//Note(teddy) The current entities only go once the new level has loaded, a broken file leaves the scene alone
+fn replace_level(editor: &mut Editor, engine: &mut Engine, world: &mut World, path: &Path) {
+    let previous: Vec<usize> = world.entities.iter().copied().collect();
+    if let Err(error) = world.load_level(path) {
+        log_level_load_error(engine, &error);
+        return;
+    }
+
+    for id in previous {
+        delete_entity(editor, world, id);
+    }
+    editor.documents.mark_saved(editor.level_document);
+    editor.level_document = track_level(&mut editor.documents, world);
+    editor.documents.mark_saved(editor.level_document);
+}
+
+///Uploads the png as a texture, top row first
+fn load_thumbnail(path: &Path) -> Result<u32, String> {
+    let bytes = fs::read(path).map_err(|error| format!("{}", error))?;
+    let image = decode_png(&bytes)?;
+    let params = TextureParams { wrap: TextureWrap::ClampToEdge, filter: TextureFilter::Linear, mipmaps: false };
+    Ok(unsafe { upload_texture(&image, &params) })
+}
+
+fn load_list_of_obj_assets() -> Vec<String> {
+    let mut output = vec![];
+    let directory = fs::read_dir(dbg!(Path::new(OBJ_ASSETS_DIR))).unwrap();
+    for entry in directory {
+        let dir = entry.unwrap().path();
+        output.push(String::from(dir.to_str().unwrap()));
+    }
+
+    output
+}
+
+///A level's new thumbnail shows in the picker once the image writer has it on disk
+pub fn image_written(editor: &mut Editor, engine: &Engine, world: &World, path: &str) {
+    if world.metadata.thumbnail.as_deref() == Some(path) {
+        editor.refresh_level_picker(engine);
+    }
+}
+
+pub fn update_editor(
+    editor: &mut Editor,
+    engine: &mut Engine,
+    world: &mut World,
+    event_manager: &mut EventManager,
+) {
+    //TODO(teddy)
+    //1. get the selected_entity and add higlight component
+    //
+
+    //Note(teddy) Ui callbacks run here, the editor work they queue runs once the tree is released
+    let mut ctx = UiContext::new(world, engine);
+    editor.ui_tree.dispatch(&mut ctx);
+    for EditorCommand(command) in ctx.commands.drain::<EditorCommand>() {
+        command(editor, world, engine);
+    }
+
+    if mouse_clicked(engine, &MouseButton::Button3) {
+        println!("Button event captured");
+    }
+
+    //Note(teddy) The editor panel is docked on the left, the scene gets the rest of the window
+    engine.viewport_insets.left = panel_width(&mut editor.ui_tree) as f32 * engine.ui_scale();
+
+    //Note(teddy) Runs before the shortcuts, they record this frame's keys as the previous ones
+    update_documents(editor, engine, world);
+    close_stale_delete_dialog(editor, world);
+    update_transform_gizmo(editor, engine, world);
+    update_measure_tool(editor, engine, world, event_manager);
+    update_asset_drops(editor, engine, world, event_manager);
+    update_entity_menu(editor, engine, event_manager);
+    update_collision_highlights(editor, engine, world, event_manager);
+    handle_ui_scale_shortcuts(editor, engine);
+    handle_camera_shortcuts(editor, engine);
+    update_render_stats_overlay(editor, engine);
+    handle_transform_shortcuts(editor, engine, world);
+    reload_changed_files(editor, engine, world);
+    sync_light_sliders(editor, engine);
+    sync_render_options(editor, engine);
+    sync_selection_views(editor, world);
+    sync_outliner(editor, world);
+    sync_resource_progress(editor, world);
+
+    if editor.snap_requested {
+        editor.snap_requested = false;
+        request_snap_to_floor(editor, world, event_manager);
+    }
+
+    if let Some(id) = editor.selected_entity {
+        let component = world.components.positionable[id].as_ref().unwrap();
+
+        let camera = &engine.camera;
+        let viewport = engine.scene_viewport();
+
+        //TODO(Teddy) fix tomorrow
+        let result = compute_world_space_to_screen_space(
+            viewport,
+            &component.position.translation.vector,
+            &camera.view(),
+            &camera.perspective(),
+        );
+
+        if viewport.contains(&result) {
+            //TODO(teddy):
+        }
+
+        handle_world_events(editor, engine, world, event_manager);
+    }
+}
+
+#[inline]
+fn key_pressed_once(editor: &Editor, engine: &Engine, key: Key) -> bool {
+    engine.pressed_keys.contains(&key) && !editor.previous_keys.contains(&key)
+}
+
+//Note(teddy) The stats are the renderer's last frame, the editor runs after it has drawn
+fn update_render_stats_overlay(editor: &mut Editor, engine: &Engine) {
+    let toggled = key_pressed_once(editor, engine, Key::F3);
+    let viewport = engine.scene_viewport();
+    let overlay = match editor.ui_tree.find_overlay(RENDER_STATS_ID) {
+        Some(overlay) => overlay,
+        None => return,
+    };
+
+    if toggled {
+        overlay.visible = !overlay.visible;
+    }
+    if !overlay.visible {
+        return;
+    }
+
+    if let Some(view) = overlay.view.as_any().downcast_mut::<TextView>() {
+        let render = RenderStatsLogObject { stats: engine.render_stats }.to_string();
+        let physics = PhysicsStatsLogObject { stats: engine.physics_stats }.to_string();
+        view.set_text(format!("{}\n{}", render, physics));
+
+        let view_object = view.get_view_object_mut();
+        let width = view_object.size.map_or(0, |size| size.x);
+        view_object.position = ViewPosition::new(
+            (viewport.x + viewport.width) as i32 - width - RENDER_STATS_MARGIN,
+            viewport.y as i32 + RENDER_STATS_MARGIN,
+        );
+    }
+}
+
+//Note(teddy) Numpad 7, 1 and 3 look down y, z and x, 5 switches between perspective and orthographic
+fn handle_camera_shortcuts(editor: &Editor, engine: &mut Engine) {
+    let views = [(Key::Kp7, CameraView::Top), (Key::Kp1, CameraView::Front), (Key::Kp3, CameraView::Side)];
+    for (key, view) in views.iter() {
+        if key_pressed_once(editor, engine, *key) {
+            engine.camera.look_from(*view);
+        }
+    }
+
+    if key_pressed_once(editor, engine, Key::Kp5) {
+        engine.camera.toggle_projection();
+    }
+
+    if key_pressed_once(editor, engine, Key::Kp0) {
+        engine.camera.toggle_mode();
+    }
+
+    //Note(teddy) Not a camera key, but F4 for the collider outlines sits next to F3 for the stats
+    if key_pressed_once(editor, engine, Key::F4) {
+        engine.show_colliders = !engine.show_colliders;
+    }
+}
+
+//Note(teddy) ctrl+= and ctrl+- grow and shrink the whole ui, ctrl+0 goes back to the window's own scale
+fn handle_ui_scale_shortcuts(editor: &Editor, engine: &mut Engine) {
+    let control = engine.pressed_keys.contains(&Key::LeftControl) || engine.pressed_keys.contains(&Key::RightControl);
+    if !control {
+        return;
+    }
+
+    if key_pressed_once(editor, engine, Key::Equal) {
+        engine.set_ui_scale(engine.ui_scale() + UI_SCALE_STEP);
+    } else if key_pressed_once(editor, engine, Key::Minus) {
+        engine.set_ui_scale(engine.ui_scale() - UI_SCALE_STEP);
+    } else if key_pressed_once(editor, engine, Key::Num0) {
+        engine.reset_ui_scale();
+    }
+}
+
+//Note(teddy) Held arrow keys walk a selected character instead of nudging it. Returns false for anything else
+fn drive_character(editor: &mut Editor, engine: &Engine, world: &mut World, entity: usize) -> bool {
+    if let Some(previous) = editor.driven_character.filter(|previous| *previous != entity) {
+        if let Some(controller) = world.components.characters.get_mut(previous).and_then(|controller| controller.as_mut()) {
+            controller.desired_velocity = Vector3::zeros();
+        }
+        editor.driven_character = None;
+    }
+
+    let controller = match world.components.characters[entity].as_mut() {
+        Some(controller) => controller,
+        None => return false,
+    };
+
+    let directions = [
+        (Key::Left, -Vector3::x()),
+        (Key::Right, Vector3::x()),
+        (Key::Up, -Vector3::z()),
+        (Key::Down, Vector3::z()),
+    ];
+    let direction: Vector3<f32> = directions
+        .iter()
+        .filter(|(key, _)| engine.pressed_keys.contains(key))
+        .map(|(_, direction)| *direction)
+        .sum();
+
+    controller.desired_velocity = match direction.try_normalize(std::f32::EPSILON) {
+        Some(direction) => direction * CHARACTER_WALK_SPEED,
+        None => Vector3::zeros(),
+    };
+    editor.driven_character = Some(entity);
+    true
+}
+
+//Note(teddy) Arrow keys nudge the selected entity, space launches it, G pushes it, X stops it, ctrl+z / ctrl+y walk the undo stack
+fn handle_transform_shortcuts(editor: &mut Editor, engine: &mut Engine, world: &mut World) {
+    let control = engine.pressed_keys.contains(&Key::LeftControl) || engine.pressed_keys.contains(&Key::RightControl);
+
+    if let (Some(entity), true) = (editor.selected_entity, key_pressed_once(editor, engine, Key::F)) {
+        frame_entity(engine, world, entity);
+    }
+
+    //Note(teddy) Delete also edits text, a focused field keeps it
+    if let (Some(entity), true) = (editor.selected_entity, key_pressed_once(editor, engine, Key::Delete)) {
+        if editor.ui_tree.focused_view.is_none() {
+            confirm_delete(editor, engine, world, entity);
+        }
+    }
+
+    //Note(teddy) Space throws the selection up to check it falls right, static bodies don't move
+    if let (Some(entity), true) = (editor.selected_entity, key_pressed_once(editor, engine, Key::Space)) {
+        if editor.ui_tree.focused_view.is_none() {
+            world.apply_impulse(entity, Vector3::new(0.0, LAUNCH_IMPULSE, 0.0));
+        }
+    }
+
+    if let (Some(entity), true) = (editor.selected_entity, engine.pressed_keys.contains(&Key::G)) {
+        if editor.ui_tree.focused_view.is_none() {
+            world.apply_force(entity, engine.camera.view_direction() * PUSH_FORCE);
+        }
+    }
+
+    //Note(teddy) X stops the selection where it is, moving and spinning
+    if let (Some(entity), true) = (editor.selected_entity, key_pressed_once(editor, engine, Key::X)) {
+        if editor.ui_tree.focused_view.is_none() {
+            world.set_linear_velocity(entity, Vector3::zeros());
+            world.set_angular_velocity(entity, Vector3::zeros());
+        }
+    }
+
+    if control && key_pressed_once(editor, engine, Key::Z) {
+        if editor.undo_stack.undo(world) {
+            editor.mark_level_dirty();
+        }
+    } else if control && key_pressed_once(editor, engine, Key::Y) {
+        if editor.undo_stack.redo(world) {
+            editor.mark_level_dirty();
+        }
+    }
+
+    let nudges = [
+        (Key::Left, Vector3::new(-NUDGE_STEP, 0.0, 0.0)),
+        (Key::Right, Vector3::new(NUDGE_STEP, 0.0, 0.0)),
+        (Key::Up, Vector3::new(0.0, 0.0, -NUDGE_STEP)),
+        (Key::Down, Vector3::new(0.0, 0.0, NUDGE_STEP)),
+    ];
+
+    if let Some(entity) = editor.selected_entity.filter(|entity| !drive_character(editor, engine, world, *entity)) {
+        for (key, offset) in nudges.iter() {
+            if !key_pressed_once(editor, engine, *key) {
+                continue;
+            }
+
+            if let Some(component) = world.components.positionable[entity].as_mut() {
+                let before = TransformState::from_component(component);
+                component.position.translation.vector += offset;
+                let after = TransformState::from_component(component);
+
+                editor
+                    .undo_stack
+                    .record_nudge(vec![TransformDelta { entity, before, after }], Instant::now());
+                editor.mark_level_dirty();
+            }
+        }
+    }
+
+    let dropped = editor.undo_stack.take_dropped();
+    let mut text = format!(
+        "UNDO: {} entries, {:.1} KB",
+        editor.undo_stack.depth(),
+        editor.undo_stack.memory_used() as f32 / 1024.0
+    );
+    if dropped > 0 {
+        text.push_str(&format!(", dropped {} oldest", dropped));
+    }
+    engine.log_manager.add_log((String::from("undo"), Box::new(EditorLogObject { text })));
+
+    editor.previous_keys = engine.pressed_keys.clone();
+}
+
+fn handle_world_events(
+    editor: &mut Editor,
+    engine: &Engine,
+    world: &mut World,
+    event_manager: &mut EventManager,
+) {
+    for event in event_manager.get_engine_events() {
+        match event.event_type {
+            //Note(teddy) Same reach as the swept snap
+            EventType::RayCasted(CastedRay { id: SNAP_RAY_ID, point: Some(point), toi, .. }) if toi <= SNAP_MAX_DROP => {
+                snap_selected_entity(editor, world, point);
+            }
+            EventType::RayCasted(CastedRay { id: _, entity, .. }) if entity.is_some() => {
+                //world.components.renderables[entity.unwrap()] = Some(HighlightComponent {
+                 //   color: [0.0, 1.0, 0.0],
+                //});
+            }
+            _ => (),
+        }
+    }
+}
+
+//Note(teddy) Assets dragged from the panel spawn where the cursor's ray hits the scene, or on the ground plane
+fn update_asset_drops(editor: &mut Editor, engine: &mut Engine, world: &mut World, event_manager: &mut EventManager) {
+    for event in event_manager.get_engine_events() {
+        match event.event_type {
+            EventType::Ui(UiEvent::DroppedOnWorld { payload, cords }) => {
+                let ndc = match engine.scene_viewport().to_ndc(&engine.from_ui_cords(cords)) {
+                    Some(ndc) => ndc,
+                    None => continue,
+                };
+
+                let ray = compute_ray_from_mouse_cords(ndc, engine.camera.perspective(), engine.camera.view());
+
+                editor.pending_drop = Some((payload, ray));
+                event_manager.add_engine_event(Event::new(EventType::CastRay(CastRayDat {
+                    id: DROP_RAY_ID,
+                    ray,
+                    filter: RayFilter::ExcludeHelpers,
+                })));
+            }
+
+            EventType::RayCasted(CastedRay { id: DROP_RAY_ID, point, normal, .. }) => {
+                let (path, ray) = match editor.pending_drop.take() {
+                    Some(drop) => drop,
+                    None => continue,
+                };
+
+                let point = match point.or_else(|| intersect_ground_plane(&ray)) {
+                    Some(point) => point,
+                    None => {
+                        println!("Nothing under the cursor to place {} on", path);
+                        continue;
+                    }
+                };
+
+                let id = create_entity(world, path, editor.shader_label.clone());
+                if let Some(component) = world.components.positionable[id].as_mut() {
+                    component.position.translation.vector = point.coords;
+
+                    //Note(teddy) Up goes along the normal, a drop on the ground plane has none and stays upright
+                    if let (true, Some(normal)) = (editor.align_drops, normal) {
+                        let tilt = UnitQuaternion::rotation_between(&Vector3::y(), &normal).unwrap_or_else(UnitQuaternion::identity);
+                        component.position.rotation = tilt * component.position.rotation;
+                    }
+                }
+
+                finish_spawn(editor, engine, world, id);
+            }
+
+            _ => (),
+        }
+    }
+}
+
+//Note(teddy) With the colliders shown, entities touching something get a red box around them
+fn update_collision_highlights(editor: &mut Editor, engine: &mut Engine, world: &World, event_manager: &mut EventManager) {
+    for event in event_manager.get_engine_events() {
+        match event.event_type {
+            EventType::CollisionStarted { a, b } | EventType::ProximityChanged { a, b, state: Proximity::Intersecting } => {
+                editor.touching.insert((a, b));
+            }
+            EventType::CollisionEnded { a, b } | EventType::ProximityChanged { a, b, .. } => {
+                editor.touching.remove(&(a, b));
+            }
+            EventType::EntityRemoved(id) => editor.touching.retain(|(a, b)| *a != id && *b != id),
+            _ => (),
+        }
+    }
+
+    if !engine.show_colliders {
+        return;
+    }
+
+    let entities: HashSet<usize> = editor.touching.iter().flat_map(|&(a, b)| std::iter::once(a).chain(std::iter::once(b))).collect();
+    for entity in entities {
+        if let Some(bounds) = world.entity_world_aabb(entity) {
+            engine.debug_draw.scene.queue_aabb(Point3::from(bounds.min), Point3::from(bounds.max), COLLISION_HIGHLIGHT_COLOR);
+        }
+    }
+}
+
+//Note(teddy) Right clicking the scene with an entity selected opens its menu, the scene list will open the same one
+fn update_entity_menu(editor: &mut Editor, engine: &Engine, event_manager: &mut EventManager) {
+    for event in event_manager.get_engine_events() {
+        let cords = match event.event_type {
+            EventType::Ui(UiEvent::ClickedWorld { button: MouseButton::Button2, cords }) => cords,
+            _ => continue,
+        };
+
+        let id = match editor.selected_entity {
+            Some(id) if engine.scene_viewport().contains(&engine.from_ui_cords(cords)) => id,
+            _ => continue,
+        };
+
+        editor.ui_tree.open_context_menu(ViewPosition::new(cords.x as i32, cords.y as i32), entity_menu_items(id), &engine.ui);
+    }
+}
+
+fn entity_menu_items(id: usize) -> Vec<(String, MenuAction)> {
+    let delete: MenuAction = Box::new(move |ctx: &mut UiContext| {
+        queue(ctx, move |editor, world, engine| confirm_delete(editor, engine, world, id));
+    });
+
+    let duplicate: MenuAction = Box::new(move |ctx: &mut UiContext| {
+        queue(ctx, move |editor, world, _| {
+            let (mesh_label, shader_label) = match world.components.renderables[id].as_ref() {
+                Some(render) => (render.mesh_label.clone(), render.shader_label.clone()),
+                None => return,
+            };
+            let shape = world.components.physics[id].as_ref().map(|physics| physics.shape);
+
+            let original = world.components.positionable[id].as_ref().map(|transform| (transform.position, transform.scale));
+            let copy = create_entity(world, mesh_label, shader_label);
+            if let (Some((position, scale)), Some(transform)) = (original, world.components.positionable[copy].as_mut()) {
+                transform.position = position;
+                transform.position.translation.vector.x += DUPLICATE_OFFSET;
+                transform.scale = scale;
+            }
+            if let (Some(shape), Some(physics)) = (shape, world.components.physics[copy].as_mut()) {
+                physics.shape = shape;
+            }
+
+            editor.selected_entity = Some(copy);
+            editor.mark_level_dirty();
+        });
+    });
+
+    let focus: MenuAction = Box::new(move |ctx: &mut UiContext| {
+        queue(ctx, move |_, world, engine| frame_entity(engine, world, id));
+    });
+
+    vec![(String::from("Delete"), delete), (String::from("Duplicate"), duplicate), (String::from("Focus camera"), focus)]
+}
+
+//Note(teddy) The context menu and the Delete key both ask first, the entity is deleted by the dialog's confirm
+fn confirm_delete(editor: &mut Editor, engine: &Engine, world: &World, id: usize) {
+    if editor.ui_tree.has_modal() {
+        return;
+    }
+
+    let mut dialog = ConfirmDialog::new(
+        DELETE_DIALOG_ID.to_owned().into_boxed_str(),
+        format!("Delete entity {} ({})?", id, outliner_group(world, id)),
+        format!("Delete"),
+        format!("Cancel"),
+        &engine.ui,
+    );
+    dialog.on_confirm = Some(Box::new(move |ctx: &mut UiContext, _dialog: &mut ConfirmDialog| {
+        queue(ctx, move |editor, world, _| {
+            editor.deleting_entity = None;
+            delete_entity(editor, world, id);
+        });
+    }));
+    dialog.on_cancel = Some(Box::new(|ctx: &mut UiContext, _dialog: &mut ConfirmDialog| {
+        queue(ctx, |editor, _, _| editor.deleting_entity = None);
+    }));
+
+    editor.deleting_entity = Some(id);
+    editor.ui_tree.push_modal(Box::new(dialog));
+}
+
+//Note(teddy) Nothing is left to confirm once the entity went away some other way
+fn close_stale_delete_dialog(editor: &mut Editor, world: &World) {
+    if let Some(id) = editor.deleting_entity {
+        if !world.entities.contains(&id) {
+            editor.ui_tree.pop_modal();
+            editor.deleting_entity = None;
+        }
+    }
+}
+
+fn delete_entity(editor: &mut Editor, world: &mut World, id: usize) {
+    world.delete_entity(id);
+    //Note(teddy) The id goes to the next created entity, undo must not move that one
+    editor.undo_stack.forget_entity(id);
+    if editor.selected_entity == Some(id) {
+        editor.selected_entity = None;
+    }
+    editor.mark_level_dirty();
+}
+
+//Note(teddy) Meshes that haven't loaded have no bounds yet, the camera just backs off a fixed distance
+fn frame_entity(engine: &mut Engine, world: &World, id: usize) {
+    let camera = &mut engine.camera;
+    match world.entity_world_aabb(id) {
+        Some(bounds) => camera.focus(&bounds, FOCUS_DURATION),
+        None => {
+            if let Some(transform) = world.components.positionable[id].as_ref() {
+                let target = transform.position.translation.vector;
+                camera.move_to(target - camera.view_direction() * FOCUS_DISTANCE, target, FOCUS_DURATION);
+            }
+        }
+    }
+}
+
+//Note(teddy) Only static geometry counts as floor, props and helpers under the entity are ignored. Once the mesh
+//has loaded its bounds are swept down so the entity rests on the floor, before that its origin is dropped onto it.
+fn request_snap_to_floor(editor: &mut Editor, world: &mut World, event_manager: &mut EventManager) {
+    let transform = match editor.selected_entity.and_then(|id| world.components.positionable[id].as_ref()) {
+        Some(transform) => transform,
+        None => return,
+    };
+
+    let bounds = editor.selected_entity.and_then(|id| world.entity_world_aabb(id));
+    if let (Some(queries), Some(bounds)) = (world.physics.as_ref(), bounds) {
+        let center = bounds.center();
+        let hit = queries.shape_cast(
+            &Cuboid::new((bounds.max - bounds.min) * 0.5),
+            &Isometry3::translation(center.x, center.y, center.z),
+            &-Vector3::y(),
+            SNAP_MAX_DROP,
+            RayFilter::StaticOnly,
+        );
+
+        if let Some(hit) = hit {
+            let resting = transform.position.translation.vector - Vector3::y() * hit.toi;
+            snap_selected_entity(editor, world, Point3::from(resting));
+        }
+        return;
+    }
+
+    let ray = Ray::new(Point3::from(transform.position.translation.vector), -Vector3::y());
+    event_manager.add_engine_event(Event::new(EventType::CastRay(CastRayDat {
+        id: SNAP_RAY_ID,
+        ray,
+        filter: RayFilter::StaticOnly,
+    })));
+}
+
+fn snap_selected_entity(editor: &mut Editor, world: &mut World, point: Point3<f32>) {
+    let entity = match editor.selected_entity {
+        Some(entity) => entity,
+        None => return,
+    };
+
+    if let Some(component) = world.components.positionable[entity].as_mut() {
+        let before = TransformState::from_component(component);
+        component.position.translation.vector = point.coords;
+        let after = TransformState::from_component(component);
+
+        editor
+            .undo_stack
+            .push(EditKind::Drag, vec![TransformDelta { entity, before, after }], Instant::now());
+        editor.mark_level_dirty();
+    }
+}
+
+fn track_level(documents: &mut DocumentRegistry, world: &World) -> DocumentId {
+    documents.register(
+        DocumentKind::Level,
+        &world.level_path().to_string_lossy(),
+        world.level_path(),
+        Box::new(|world: &World| Ok(world.level_bytes())),
+    )
+}
+
+fn track_prefab(documents: &mut DocumentRegistry, name: &str) -> DocumentId {
+    let prefab_name = name.to_string();
+    documents.register(
+        DocumentKind::Prefab,
+        name,
+        &prefab_path(Path::new(PREFAB_ASSETS_DIR), name),
+        Box::new(move |world: &World| match world.prefabs.get(&prefab_name) {
+            Some(prefab) => prefab_bytes(prefab),
+            None => Err(format!("Prefab {} no longer exists", prefab_name)),
+        }),
+    )
+}
+
+fn save_all_documents(editor: &mut Editor, world: &mut World, engine: &mut Engine) -> bool {
+    let mut saved_everything = true;
+
+    for saved in editor.documents.save_all(world) {
+        match saved.result {
+            Ok(_) if saved.kind == DocumentKind::Level => world.save_metadata(),
+            Ok(_) => (),
+            Err(error) => {
+                log_document_error(engine, format!("not saved {} {}: {}", saved.kind.label(), saved.name, error));
+                saved_everything = false;
+            }
+        }
+    }
+
+    saved_everything
+}
+
+fn describe_documents(documents: &[(DocumentKind, String)]) -> String {
+    documents
+        .iter()
+        .map(|(kind, name)| format!("{} {}", kind.label(), name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+//Note(teddy) Autosaves left behind by a crash, the user picks whether they replace the saved files
+fn recovery_views(resources: &UiResources) -> Vec<Box<dyn View>> {
+    let recovered = recovered_documents(Path::new(AUTOSAVE_DIR));
+    if recovered.is_empty() {
+        return vec![];
+    }
+
+    let summary = recovered.iter().map(|document| (document.kind, document.name.clone())).collect::<Vec<_>>();
+    let message = TextView::new(
+        RECOVERY_ROW_IDS[0].to_owned().into_boxed_str(),
+        format!("Recovered: {}", describe_documents(&summary)),
+        ViewPosition::zerod(),
+        1.0,
+        10,
+        resources,
+    );
+
+    let recovered = Rc::new(RefCell::new(recovered));
+    let mut restore = TextView::new(RECOVERY_ROW_IDS[1].to_owned().into_boxed_str(), format!("Restore recovered"), ViewPosition::zerod(), 1.0, 10, resources);
+    let mut discard = TextView::new(RECOVERY_ROW_IDS[2].to_owned().into_boxed_str(), format!("Discard recovered"), ViewPosition::zerod(), 1.0, 10, resources);
+
+    let to_restore = Rc::clone(&recovered);
+    restore.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+        let world = &mut *ctx.world;
+
+        for document in to_restore.borrow_mut().drain(..) {
+            match restore_document(&document) {
+                Ok(_) if document.kind == DocumentKind::Level => {
+                    if let Err(error) = world.load_world() {
+                        log_level_load_error(ctx.engine, &error);
+                    }
+                }
+                Ok(_) => (),
+                Err(error) => log_document_error(ctx.engine, format!("not restored {} {}: {}", document.kind.label(), document.name, error)),
+            }
+        }
+        world.prefabs = load_prefabs(Path::new(PREFAB_ASSETS_DIR));
+        remove_recovery_rows(ctx);
+    }));
+
+    discard.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+        for document in recovered.borrow_mut().drain(..) {
+            if let Err(error) = discard_recovered(&document) {
+                log_document_error(ctx.engine, format!("not discarded {} {}: {}", document.kind.label(), document.name, error));
+            }
+        }
+        remove_recovery_rows(ctx);
+    }));
+
+    vec![Box::new(message), Box::new(restore), Box::new(discard)]
+}
+
+///Nothing is left to restore or discard once either row is clicked
+fn remove_recovery_rows(ctx: &mut UiContext) {
+    for id in RECOVERY_ROW_IDS.iter() {
+        ctx.commands.push(UiCommand::RemoveView { id: Box::from(*id) });
+    }
+}
+
+//Note(teddy) Escape with unsaved documents opens the quit dialog instead of quitting
+fn update_documents(editor: &mut Editor, engine: &mut Engine, world: &mut World) {
+    for saved in editor.documents.autosave_tick(Instant::now(), world) {
+        let text = match saved.result {
+            Ok(_) => format!("AUTOSAVE: {} {}", saved.kind.label(), saved.name),
+            Err(error) => format!("AUTOSAVE FAILED: {} {}: {}", saved.kind.label(), saved.name, error),
+        };
+        engine.log_manager.add_log((String::from("autosave"), Box::new(EditorLogObject { text })));
+    }
+
+    let unsaved = editor.documents.unsaved();
+    let measuring = match editor.tool {
+        EditorTool::Measure(_) => true,
+        EditorTool::Select => false,
+    };
+    engine.escape_captured = measuring || !unsaved.is_empty();
+
+    let escape = key_pressed_once(editor, engine, Key::Escape);
+    if editor.quit_dialog_open {
+        if key_pressed_once(editor, engine, Key::Enter) && save_all_documents(editor, world, engine) {
+            quit(engine);
+        } else if key_pressed_once(editor, engine, Key::Q) {
+            editor.documents.discard_autosaves();
+            quit(engine);
+        } else if escape {
+            editor.quit_dialog_open = false;
+        }
+    } else if escape && !measuring && !unsaved.is_empty() {
+        editor.quit_dialog_open = true;
+    }
+
+    let viewport = engine.scene_viewport();
+    let overlay = match editor.ui_tree.find_overlay(QUIT_DIALOG_ID) {
+        Some(overlay) => overlay,
+        None => return,
+    };
+
+    overlay.visible = editor.quit_dialog_open;
+    if editor.quit_dialog_open {
+        if let Some(view) = overlay.view.as_any().downcast_mut::<TextView>() {
+            let text = format!(
+                "Unsaved: {}. Enter saves all and quits, Q quits without saving, Escape cancels",
+                describe_documents(&unsaved)
+            );
+            let width = engine.font_face.measure_text(&text) as i32;
+            view.set_text(text);
+
+            let view_object = view.get_view_object_mut();
+            view_object.size = Some(Dimensions::new(width, engine.font_face.font_size as i32));
+            view_object.position = ViewPosition::new(
+                (viewport.x + viewport.width / 2.0) as i32 - width / 2,
+                (viewport.y + viewport.height / 2.0) as i32,
+            );
+        }
+    }
+}
+
+//Note(teddy) The console can change the light too, the sliders follow whatever is current
+fn sync_light_sliders(editor: &mut Editor, engine: &Engine) {
+    let values = LIGHT_DIRECTION_SLIDERS
+        .iter()
+        .zip(engine.dir_lights.direction.iter().cloned())
+        .chain(std::iter::once((&LIGHT_AMBIENT_SLIDER, engine.dir_lights.ambient)));
+
+    for (id, value) in values {
+        if let Ok(view) = editor.ui_tree.find_view_mut(id) {
+            if let Some(slider) = view.as_any().downcast_mut::<SliderView>() {
+                slider.set_value(value);
+            }
+        }
+    }
+
+    if let Some(picker) = editor.ui_tree.find_view_as::<ColorPickerView>(LIGHT_COLOR_PICKER) {
+        picker.set_color(engine.dir_lights.color);
+    }
+}
+
+//Note(teddy) L flips the wireframe option outside the ui, the checkbox follows it
+fn sync_render_options(editor: &mut Editor, engine: &Engine) {
+    if let Some(checkbox) = editor.ui_tree.find_view_as::<CheckboxView>("wireframe") {
+        checkbox.set_checked(engine.wireframe);
+    }
+    if let Some(checkbox) = editor.ui_tree.find_view_as::<CheckboxView>("orbit_camera") {
+        checkbox.set_checked(engine.camera.mode == CameraMode::Orbit);
+    }
+    if let Some(checkbox) = editor.ui_tree.find_view_as::<CheckboxView>("show_colliders") {
+        checkbox.set_checked(engine.show_colliders);
+    }
+}
+
+fn shader_labels(world: &World) -> Vec<String> {
+    let mut labels: Vec<String> = world.resources.read().unwrap().shaders.keys().cloned().collect();
+    labels.sort();
+    labels
+}
+
+fn sync_selection_views(editor: &mut Editor, world: &World) {
+    let selected = editor.selected_entity;
+
+    if let Ok(view) = editor.ui_tree.find_view_mut(BODY_STATUS_DROPDOWN) {
+        if let Some(dropdown) = view.as_any().downcast_mut::<DropdownView>() {
+            let status = selected.and_then(|id| world.components.physics.get(id)).and_then(|physics| physics.as_ref()).map(|physics| physics.status);
+            dropdown.set_selected(status.and_then(|status| BODY_STATUS_OPTIONS.iter().position(|(_, option)| *option == status)));
+        }
+    }
+
+    if let Ok(view) = editor.ui_tree.find_view_mut(SHADER_DROPDOWN) {
+        if let Some(dropdown) = view.as_any().downcast_mut::<DropdownView>() {
+            //Note(teddy) Shaders finish loading after the ui is built, pick up the late ones
+            let labels = shader_labels(world);
+            if dropdown.options() != labels.as_slice() {
+                dropdown.set_options(labels);
+            }
+
+            let label = selected.and_then(|id| world.components.renderables.get(id)).and_then(|render| render.as_ref()).map(|render| render.shader_label.as_str());
+            dropdown.set_selected(label.and_then(|label| dropdown.options().iter().position(|option| option == label)));
+        }
+    }
+
+    if let Some(dropdown) = editor.ui_tree.find_view_as::<DropdownView>(DEBUG_VIEW_DROPDOWN) {
+        let mode = selected.and_then(|id| world.components.renderables.get(id)).and_then(|render| render.as_ref()).map(|render| render.debug_view);
+        dropdown.set_selected(mode.and_then(|mode| DEBUG_VIEW_OPTIONS.iter().position(|(_, option)| *option == mode)));
+    }
+
+    let material = selected.and_then(|id| world.components.renderables.get(id)).and_then(|render| render.as_ref()).map(|render| &render.material);
+    if let (Some(picker), Some(material)) = (editor.ui_tree.find_view_as::<ColorPickerView>(ENTITY_COLOR_PICKER), material) {
+        picker.set_color(material.base_color);
+    }
+    if let (Some(slider), Some(material)) = (editor.ui_tree.find_view_as::<SliderView>(ENTITY_OPACITY_SLIDER), material) {
+        slider.set_value(material.opacity);
+    }
+
+    let flags = selected.and_then(|id| world.components.renderables.get(id)).and_then(|render| render.as_ref()).map(|render| render.flags);
+    for (id, _, flag) in ENTITY_FLAG_CHECKBOXES.iter() {
+        if let (Some(checkbox), Some(flags)) = (editor.ui_tree.find_view_as::<CheckboxView>(id), flags) {
+            checkbox.set_checked(flags.contains(*flag));
+        }
+    }
+
+    let instance = selected.and_then(|id| world.components.prefabs.get(id)).and_then(|instance| instance.as_ref());
+    for field in PrefabField::ALL.iter() {
+        let overridden = instance.map(|instance| instance.is_overridden(*field));
+
+        if let Ok(row) = editor.ui_tree.find_view_mut(&prefab_override_id("row", *field)) {
+            row.set_visible(overridden.is_some());
+        }
+        if let Some(text) = editor.ui_tree.find_view_as::<TextView>(&prefab_override_id("field", *field)) {
+            let label = match overridden {
+                Some(true) => format!("{} (overridden)", field.name()),
+                _ => format!("{} (from prefab)", field.name()),
+            };
+            if text.text() != label {
+                text.set_text(label);
+            }
+            text.color = if overridden == Some(true) { Some(Vector3::from(OVERRIDDEN_COLOR)) } else { None };
+        }
+        if let Ok(revert) = editor.ui_tree.find_view_mut(&prefab_override_id("revert", *field)) {
+            revert.set_visible(overridden == Some(true));
+        }
+    }
+
+    let rope = selected.and_then(|id| world.components.ropes.get(id)).and_then(|rope| rope.as_ref());
+    for (field, (id, _, _)) in ROPE_SLIDERS.iter().enumerate() {
+        if let (Some(slider), Some(rope)) = (editor.ui_tree.find_view_as::<SliderView>(id), rope) {
+            slider.set_value(rope_field(rope, field));
+        }
+    }
+}
+
+///Value of the ROPE_SLIDERS field, in the slider's units
+fn rope_field(rope: &RopeComponent, field: usize) -> f32 {
+    match field {
+        0 => rope.segments as f32,
+        1 => rope.length,
+        2 => rope.iterations as f32,
+        _ => rope.thickness,
+    }
+}
+
+//Note(teddy) A new segment count lays the rope out again the next time it's simulated
+fn set_rope_field(rope: &mut RopeComponent, field: usize, value: f32) {
+    match field {
+        0 => rope.segments = value.round().max(1.0) as usize,
+        1 => rope.length = value.max(0.01),
+        2 => rope.iterations = value.round().max(1.0) as u32,
+        _ => rope.thickness = value.max(0.001),
+    }
+}
+
+///What an entity is listed under in the outliner, its prefab or else its mesh
+fn outliner_group(world: &World, id: usize) -> String {
+    let prefab = world.components.prefabs.get(id).and_then(|prefab| prefab.as_ref());
+    let render = world.components.renderables.get(id).and_then(|render| render.as_ref());
+    let rope = world.components.ropes.get(id).and_then(|rope| rope.as_ref());
+
+    match (prefab, render, rope) {
+        (Some(prefab), _, _) => format!("Prefab {}", prefab.prefab),
+        (_, _, Some(_)) => String::from("Ropes"),
+        (_, Some(render), _) => Path::new(&render.mesh_label).file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or(render.mesh_label.clone()),
+        _ => String::from("Other"),
+    }
+}
+
+///The world's entities grouped by prefab or mesh, groups are listed in the order they are first seen
+fn outliner_nodes(world: &World) -> Vec<TreeNode> {
+    let mut groups: Vec<TreeNode> = vec![];
+
+    for &id in world.entities.iter() {
+        let group = outliner_group(world, id);
+        let index = match groups.iter().position(|node| node.label == group) {
+            Some(index) => index,
+            None => {
+                groups.push(TreeNode::new(group, OUTLINER_GROUP_ID - groups.len()));
+                groups.len() - 1
+            }
+        };
+
+        groups[index].children.push(TreeNode::new(format!("Entity {}", id), id));
+    }
+
+    groups
+}
+
+///Refills the outliner when entities came or went, and shows the selected entity in it
+fn sync_outliner(editor: &mut Editor, world: &World) {
+    let entities: Vec<usize> = world.entities.iter().cloned().collect();
+    let refill = entities != editor.outliner_entities;
+    let selected = editor.selected_entity;
+
+    if let Some(outliner) = editor.ui_tree.find_view_as::<TreeView>(OUTLINER_ID) {
+        if refill {
+            outliner.set_nodes(outliner_nodes(world));
+        }
+
+        if outliner.selected() != selected {
+            outliner.set_selected(selected);
+            if let Some(id) = selected {
+                outliner.expand_to(id);
+            }
+        }
+    }
+
+    editor.outliner_entities = entities;
+}
+
+//Note(teddy) Edits to the layout or a shader show up without a restart, a broken file is reported
+//in the log panel and what was on screen stays
+fn reload_changed_files(editor: &mut Editor, engine: &mut Engine, world: &World) {
+    for path in editor.file_watcher.poll() {
+        if is_shader_source(&path) {
+            let result = reload_shaders(&path, engine, world);
+            log_shader_reload(engine, &path.display().to_string(), result);
+            continue;
+        }
+
+        if path != Path::new(EDITOR_UI_PATH) {
+            continue;
+        }
+
+        let text = match editor.reload_editor_ui(engine, world) {
+            Ok(()) => format!("UI: reloaded {}", path.display()),
+            Err(error) => format!("UI: {} was not reloaded, {}", path.display(), error),
+        };
+        engine.log_manager.add_log((String::from("ui_reload"), Box::new(EditorLogObject { text })));
+    }
+}
+
+fn log_shader_reload(engine: &mut Engine, source: &str, result: Result<Vec<String>, String>) {
+    let text = match result {
+        Ok(shaders) => format!("SHADERS: reloaded {} from {}", shaders.join(", "), source),
+        Err(error) => format!("SHADERS: {} kept the old programs, {}", source, error),
+    };
+    engine.log_manager.add_log((String::from("shader_reload"), Box::new(EditorLogObject { text })));
+}
+
+fn log_document_error(engine: &mut Engine, error: String) {
+    let text = format!("DOCUMENTS: {}", error);
+    engine.log_manager.add_log((String::from("documents"), Box::new(EditorLogObject { text })));
+}
+
+///The level stays as it was when the file is refused, says why in the log panel
+fn log_level_load_error(engine: &mut Engine, error: &WorldError) {
+    println!("Failed to load the level: {}", error);
+    let text = format!("LEVEL: not loaded, {}", error);
+    engine.log_manager.add_log((String::from("level_load"), Box::new(EditorLogObject { text })));
+}
+
+///Shows the loading thread's progress in the log panel while it has assets left to load
+fn sync_resource_progress(editor: &mut Editor, world: &World) {
+    let (loaded, total) = world.resource_queue_progress();
+
+    if let Some(progress) = editor.ui_tree.find_view_as::<ProgressBarView>(RESOURCE_PROGRESS_ID) {
+        progress.set_visible(total > loaded);
+        if total > 0 {
+            progress.set_fraction(loaded as f32 / total as f32);
+        }
+    }
+}
+
+fn update_measure_tool(editor: &mut Editor, engine: &mut Engine, world: &World, event_manager: &mut EventManager) {
+    let clicked = mouse_clicked(engine, &MouseButton::Button1) && !editor.previous_buttons.contains(&MouseButton::Button1);
+    let escape = key_pressed_once(editor, engine, Key::Escape);
+    editor.previous_buttons = engine.mouse_button_keys.clone();
+
+    let measurement = match &mut editor.tool {
+        EditorTool::Measure(measurement) => measurement,
+        EditorTool::Select => {
+            engine.overlay_lines = None;
+            if let Some(label) = editor.ui_tree.find_overlay(MEASURE_LABEL_ID) {
+                label.visible = false;
+            }
+            return;
+        }
+    };
+
+    //Note(teddy) Escape clears the measurement instead of quitting while the tool is active
+    if escape {
+        measurement.clear();
+    }
+
+    let viewport = engine.scene_viewport();
+    if clicked {
+        if let Some(ndc) = viewport.to_ndc(&engine.camera.new_cords) {
+            let ray = compute_ray_from_mouse_cords(ndc, engine.camera.perspective(), engine.camera.view());
+
+            measurement.pending_ray = Some(ray);
+            measurement.pending_cursor = Some(engine.camera.new_cords);
+            event_manager.add_engine_event(Event::new(EventType::CastRay(CastRayDat {
+                id: MEASURE_RAY_ID,
+                ray,
+                filter: RayFilter::StaticOnly,
+            })));
+        }
+    }
+
+    for event in event_manager.get_engine_events() {
+        let hit = match event.event_type {
+            EventType::RayCasted(CastedRay { id: MEASURE_RAY_ID, point, .. }) => point,
+            _ => continue,
+        };
+
+        let ray = match measurement.pending_ray.take() {
+            Some(ray) => ray,
+            None => continue,
+        };
+
+        let point = match hit.or_else(|| intersect_ground_plane(&ray)) {
+            Some(point) => point,
+            None => continue,
+        };
+
+        let point = match (measurement.snap, measurement.pending_cursor.take()) {
+            (true, Some(cursor)) => {
+                let origins = world
+                    .entities
+                    .iter()
+                    .filter_map(|entity| world.components.positionable[*entity].as_ref())
+                    .map(|transform| Point3::from(transform.position.translation.vector));
+
+                snap_to_origin(cursor, origins, viewport, &engine.camera.view(), &engine.camera.perspective(), MEASURE_SNAP_RADIUS)
+                    .unwrap_or_else(|| snap_to_grid(point, MEASURE_GRID_SIZE))
+            }
+            _ => point,
+        };
+
+        measurement.add_point(point);
+    }
+
+    let label = measurement.label();
+    engine.overlay_lines = Some(OverlayLines {
+        shader_label: editor.shader_label.clone(),
+        thickness: MEASURE_LINE_THICKNESS,
+        segments: measurement.overlay_segments(),
+    });
+
+    if let Some(text) = &label {
+        engine.log_manager.add_log((String::from("measure"), Box::new(EditorLogObject { text: format!("MEASURE: {}", text) })));
+    }
+
+    let anchor = measurement.label_anchor();
+    let overlay = match editor.ui_tree.find_overlay(MEASURE_LABEL_ID) {
+        Some(overlay) => overlay,
+        None => return,
+    };
+
+    overlay.visible = false;
+    if let (Some(text), Some(anchor)) = (label, anchor) {
+        let screen = compute_world_space_to_screen_space(viewport, &anchor.coords, &engine.camera.view(), &engine.camera.perspective());
+        if !viewport.contains(&screen) {
+            return;
+        }
+
+        if let Some(view) = overlay.view.as_any().downcast_mut::<TextView>() {
+            let width = engine.font_face.measure_text(&text) as i32;
+            view.set_text(text);
+
+            let view_object = view.get_view_object_mut();
+            view_object.size = Some(Dimensions::new(width, engine.font_face.font_size as i32));
+            view_object.position = ViewPosition::new(screen.x as i32 - width / 2, screen.y as i32);
+            overlay.visible = true;
+        }
+    }
+}
+
+//Note(teddy) Runs before the measure tool, it records this frame's buttons as the previous ones.
+//Shift held on the press scales instead of moving.
+fn update_transform_gizmo(editor: &mut Editor, engine: &mut Engine, world: &mut World) {
+    let held = mouse_clicked(engine, &MouseButton::Button1);
+    let pressed = held && !editor.previous_buttons.contains(&MouseButton::Button1);
+
+    let entity = match (editor.selected_entity, &editor.tool) {
+        (Some(entity), EditorTool::Select) if world.components.positionable[entity].is_some() => entity,
+        _ => {
+            if editor.gizmo_drag.take().is_some() {
+                editor.undo_stack.end_drag(world);
+            }
+            return;
+        }
+    };
+
+    let ray = engine.scene_viewport().to_ndc(&engine.camera.new_cords).map(|ndc| {
+        compute_ray_from_mouse_cords(ndc, engine.camera.perspective(), engine.camera.view())
+    });
+
+    let shift = engine.pressed_keys.contains(&Key::LeftShift) || engine.pressed_keys.contains(&Key::RightShift);
+    let mode = match &editor.gizmo_drag {
+        Some(drag) => drag.mode,
+        None if shift => GizmoMode::Scale,
+        None => GizmoMode::Translate,
+    };
+
+    let transform = world.components.positionable[entity].as_ref().unwrap();
+    let origin = Point3::from(transform.position.translation.vector);
+    let length = gizmo_length(origin, Point3::from(engine.camera.position));
+
+    if pressed && editor.gizmo_drag.is_none() {
+        if let Some(drag) = ray.as_ref().and_then(|ray| GizmoDrag::begin(ray, origin, transform.scale, length, mode)) {
+            editor.undo_stack.begin_drag(world, &[entity]);
+            editor.gizmo_drag = Some(drag);
+        }
+    }
+
+    //Note(teddy) A body that fell asleep would hang where it was dropped
+    if !held {
+        if editor.gizmo_drag.take().is_some() {
+            editor.undo_stack.end_drag(world);
+            world.wake_up(entity);
+        }
+    }
+
+    let moved = match (&editor.gizmo_drag, &ray) {
+        (Some(drag), Some(ray)) => drag.update(ray),
+        _ => None,
+    };
+
+    if let (Some((position, scale)), Some(component)) = (moved, world.components.positionable[entity].as_mut()) {
+        if component.position.translation.vector != position.coords || component.scale != scale {
+            component.position.translation.vector = position.coords;
+            let rescaled = component.scale != scale;
+            //Note(teddy) The position is the instance's own, the scale comes from the prefab until it's changed
+            if rescaled {
+                set_instance_field(&mut world.components, entity, PrefabValue::Scale(scale));
+            }
+            editor.mark_level_dirty();
+        }
+    }
+
+    let active = match &editor.gizmo_drag {
+        Some(drag) => Some(drag.axis),
+        None => ray.as_ref().and_then(|ray| pick_axis(ray, origin, length)),
+    };
+
+    let origin = Point3::from(world.components.positionable[entity].as_ref().unwrap().position.translation.vector);
+    draw_gizmo(&mut engine.debug_draw, origin, length, mode, active);
+}
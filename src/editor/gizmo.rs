@@ -0,0 +1,186 @@
+use nalgebra::{Point3, Vector3};
+use ncollide3d::query::Ray;
+
+use crate::renderer::debug_draw::DebugDraw;
+
+///Arrow length per unit of distance from the camera, keeps the gizmo about the same size on screen
+const GIZMO_SCREEN_SCALE: f32 = 0.15;
+///Pick radius around an arrow as a fraction of its length
+const GIZMO_PICK_RADIUS: f32 = 0.08;
+const GIZMO_HEAD_SIZE: f32 = 0.15;
+const GIZMO_HOVER_COLOR: [f32; 3] = [1.0, 0.9, 0.2];
+
+//Note(teddy) A single drag can't throw the entity further than this, a ray nearly parallel to the
+//axis would otherwise send it off to infinity
+pub const GIZMO_MAX_OFFSET: f32 = 100.0;
+pub const GIZMO_MIN_SCALE: f32 = 0.05;
+pub const GIZMO_MAX_SCALE: f32 = 50.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn direction(&self) -> Vector3<f32> {
+        match self {
+            GizmoAxis::X => Vector3::x(),
+            GizmoAxis::Y => Vector3::y(),
+            GizmoAxis::Z => Vector3::z(),
+        }
+    }
+
+    fn color(&self) -> [f32; 3] {
+        match self {
+            GizmoAxis::X => [1.0, 0.2, 0.2],
+            GizmoAxis::Y => [0.2, 1.0, 0.2],
+            GizmoAxis::Z => [0.2, 0.4, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GizmoMode {
+    Translate,
+    ///Uniform, the transform only has the one scale
+    Scale,
+}
+
+///An axis being dragged, everything is measured from where the press happened
+#[derive(Debug, Copy, Clone)]
+pub struct GizmoDrag {
+    pub axis: GizmoAxis,
+    pub mode: GizmoMode,
+    origin: Point3<f32>,
+    start_scale: f32,
+    ///Where the press landed along the axis
+    start_param: f32,
+    length: f32,
+}
+
+impl GizmoDrag {
+    ///Starts a drag if the ray passes close enough to one of the arrows
+    pub fn begin(ray: &Ray<f32>, origin: Point3<f32>, scale: f32, length: f32, mode: GizmoMode) -> Option<Self> {
+        let axis = pick_axis(ray, origin, length)?;
+        let (start_param, _) = closest_on_axis(ray, origin, axis.direction())?;
+
+        Some(Self { axis, mode, origin, start_scale: scale, start_param, length })
+    }
+
+    ///Position and scale for the entity with the cursor on this ray, None while the ray runs along the axis
+    pub fn update(&self, ray: &Ray<f32>) -> Option<(Point3<f32>, f32)> {
+        let (param, _) = closest_on_axis(ray, self.origin, self.axis.direction())?;
+        let offset = (param - self.start_param).max(-GIZMO_MAX_OFFSET).min(GIZMO_MAX_OFFSET);
+
+        match self.mode {
+            GizmoMode::Translate => Some((self.origin + self.axis.direction() * offset, self.start_scale)),
+            GizmoMode::Scale => {
+                let scale = self.start_scale * (1.0 + offset / self.length);
+                Some((self.origin, scale.max(GIZMO_MIN_SCALE).min(GIZMO_MAX_SCALE)))
+            }
+        }
+    }
+}
+
+pub fn gizmo_length(origin: Point3<f32>, camera_position: Point3<f32>) -> f32 {
+    ((camera_position - origin).norm() * GIZMO_SCREEN_SCALE).max(0.1)
+}
+
+///Closest arrow the ray passes within the pick radius of
+pub fn pick_axis(ray: &Ray<f32>, origin: Point3<f32>, length: f32) -> Option<GizmoAxis> {
+    let radius = length * GIZMO_PICK_RADIUS;
+
+    GizmoAxis::ALL
+        .iter()
+        .filter_map(|axis| {
+            let (param, distance) = closest_on_axis(ray, origin, axis.direction())?;
+            if param < 0.0 || param > length || distance > radius {
+                return None;
+            }
+            Some((*axis, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(axis, _)| axis)
+}
+
+///Distance along the axis line of the point closest to the ray, and how far the ray passes from it.
+///Same as hitting the plane through the axis that faces the ray. None when the ray runs along the
+///axis or the closest point is behind the camera.
+fn closest_on_axis(ray: &Ray<f32>, origin: Point3<f32>, axis: Vector3<f32>) -> Option<(f32, f32)> {
+    let to_ray = ray.origin - origin;
+    let (dd, da, aa) = (ray.dir.dot(&ray.dir), ray.dir.dot(&axis), axis.dot(&axis));
+    let (dw, aw) = (ray.dir.dot(&to_ray), axis.dot(&to_ray));
+
+    let denominator = dd * aa - da * da;
+    if denominator.abs() <= 1e-6 {
+        return None;
+    }
+
+    let ray_param = (da * aw - aa * dw) / denominator;
+    let axis_param = (dd * aw - da * dw) / denominator;
+    if ray_param < 0.0 {
+        return None;
+    }
+
+    let distance = (ray.point_at(ray_param) - (origin + axis * axis_param)).norm();
+    Some((axis_param, distance))
+}
+
+///Arrows for translation, boxes on the ends for scale. The active axis is highlighted.
+pub fn draw_gizmo(debug_draw: &mut DebugDraw, origin: Point3<f32>, length: f32, mode: GizmoMode, active: Option<GizmoAxis>) {
+    let head = length * GIZMO_HEAD_SIZE;
+
+    for axis in GizmoAxis::ALL.iter() {
+        let color = if active == Some(*axis) { GIZMO_HOVER_COLOR } else { axis.color() };
+        let direction = axis.direction();
+        let tip = origin + direction * length;
+        debug_draw.overlay.queue_line(origin, tip, color);
+
+        match mode {
+            GizmoMode::Translate => {
+                //Note(teddy) Four lines back from the tip, a pair along each of the other two axes
+                for side in GizmoAxis::ALL.iter().filter(|side| *side != axis) {
+                    for sign in [-1.0, 1.0].iter() {
+                        let base = tip - direction * head + side.direction() * head * 0.5 * *sign;
+                        debug_draw.overlay.queue_line(tip, base, color);
+                    }
+                }
+            }
+            GizmoMode::Scale => {
+                let half = Vector3::repeat(head * 0.5);
+                debug_draw.overlay.queue_aabb(tip - half, tip + half, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dragging_the_x_arrow() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let camera = Point3::new(1.0, 0.0, 10.0);
+        let ray_to = |target: Point3<f32>| Ray::new(camera, (target - camera).normalize());
+
+        //Note(teddy) Pressing half way along x picks x, empty space picks nothing
+        assert_eq!(pick_axis(&ray_to(Point3::new(0.5, 0.0, 0.0)), origin, 1.0), Some(GizmoAxis::X));
+        assert_eq!(pick_axis(&ray_to(Point3::new(0.0, 0.5, 0.0)), origin, 1.0), Some(GizmoAxis::Y));
+        assert_eq!(pick_axis(&ray_to(Point3::new(0.6, 0.6, 0.0)), origin, 1.0), None);
+
+        let drag = GizmoDrag::begin(&ray_to(Point3::new(0.5, 0.0, 0.0)), origin, 1.0, 1.0, GizmoMode::Translate).unwrap();
+        let (position, scale) = drag.update(&ray_to(Point3::new(3.5, 0.0, 0.0))).unwrap();
+        assert!((position - Point3::new(3.0, 0.0, 0.0)).norm() < 1e-4);
+        assert_eq!(scale, 1.0);
+
+        let drag = GizmoDrag::begin(&ray_to(Point3::new(0.5, 0.0, 0.0)), origin, 1.0, 1.0, GizmoMode::Scale).unwrap();
+        let (position, scale) = drag.update(&ray_to(Point3::new(-100.0, 0.0, 0.0))).unwrap();
+        assert_eq!(position, origin);
+        assert_eq!(scale, GIZMO_MIN_SCALE);
+    }
+}
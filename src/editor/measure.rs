@@ -0,0 +1,190 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+use ncollide3d::query::Ray;
+
+use crate::utils::{compute_world_space_to_screen_space, Cords, ViewportRect};
+
+pub const MEASURE_GRID_SIZE: f32 = 0.5;
+///How close, in window pixels, a click has to land to an entity origin to snap to it
+pub const MEASURE_SNAP_RADIUS: f32 = 12.0;
+const TICK_SPACING: f32 = 1.0;
+const TICK_LENGTH: f32 = 0.15;
+
+///Points clicked with the measure tool, every point after the first chains a new segment
+#[derive(Debug, Default)]
+pub struct Measurement {
+    pub points: Vec<Point3<f32>>,
+    pub snap: bool,
+    ///Ray of the click that is waiting for its ray cast, used for the ground plane fallback
+    pub pending_ray: Option<Ray<f32>>,
+    ///Cursor position of the pending click, entity origins are snapped in screen space
+    pub pending_cursor: Option<Cords<f32>>,
+}
+
+impl Measurement {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.pending_ray = None;
+        self.pending_cursor = None;
+    }
+
+    pub fn add_point(&mut self, point: Point3<f32>) {
+        self.points.push(point);
+    }
+
+    pub fn segments(&self) -> Vec<(Point3<f32>, Point3<f32>)> {
+        self.points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+
+    pub fn total_length(&self) -> f32 {
+        self.segments().iter().map(|(a, b)| (b - a).norm()).sum()
+    }
+
+    ///Per axis delta of the last segment
+    pub fn last_delta(&self) -> Option<Vector3<f32>> {
+        self.segments().last().map(|(a, b)| b - a)
+    }
+
+    pub fn label(&self) -> Option<String> {
+        let delta = self.last_delta()?;
+        let mut text = format!("{:.2} (dx {:.2} dy {:.2} dz {:.2})", delta.norm(), delta.x, delta.y, delta.z);
+
+        if self.points.len() > 2 {
+            text.push_str(&format!(" total {:.2}", self.total_length()));
+        }
+        Some(text)
+    }
+
+    pub fn label_anchor(&self) -> Option<Point3<f32>> {
+        self.segments().last().map(|(a, b)| Point3::from((a.coords + b.coords) * 0.5))
+    }
+
+    ///Segments plus a tick mark every unit, ready for the overlay pass
+    pub fn overlay_segments(&self) -> Vec<(Point3<f32>, Point3<f32>)> {
+        let mut lines = vec![];
+
+        for (a, b) in self.segments() {
+            lines.push((a, b));
+            lines.extend(tick_marks(a, b, TICK_SPACING, TICK_LENGTH));
+        }
+
+        lines
+    }
+}
+
+///Where the ray meets the y = 0 ground plane, used when the click hits no static geometry
+pub fn intersect_ground_plane(ray: &Ray<f32>) -> Option<Point3<f32>> {
+    if ray.dir.y.abs() <= std::f32::EPSILON {
+        return None;
+    }
+
+    let toi = -ray.origin.y / ray.dir.y;
+    if toi < 0.0 {
+        return None;
+    }
+
+    Some(ray.point_at(toi))
+}
+
+pub fn snap_to_grid(point: Point3<f32>, grid_size: f32) -> Point3<f32> {
+    point.map(|value| (value / grid_size).round() * grid_size)
+}
+
+///The origin closest to the cursor on screen, if one is within the radius
+pub fn snap_to_origin<I>(
+    cursor: Cords<f32>,
+    origins: I,
+    viewport: ViewportRect,
+    view: &Matrix4<f32>,
+    perspective: &Matrix4<f32>,
+    radius: f32,
+) -> Option<Point3<f32>>
+where
+    I: Iterator<Item = Point3<f32>>,
+{
+    origins
+        .filter_map(|origin| {
+            let screen = compute_world_space_to_screen_space(viewport, &origin.coords, view, perspective);
+            let distance = ((screen.x - cursor.x).powi(2) + (screen.y - cursor.y).powi(2)).sqrt();
+
+            if distance <= radius && viewport.contains(&screen) {
+                Some((distance, origin))
+            } else {
+                None
+            }
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, origin)| origin)
+}
+
+fn tick_marks(a: Point3<f32>, b: Point3<f32>, spacing: f32, length: f32) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let segment = b - a;
+    let distance = segment.norm();
+    let direction = match segment.try_normalize(std::f32::EPSILON) {
+        Some(direction) => direction,
+        None => return vec![],
+    };
+
+    //Note(teddy) Ticks stand up from horizontal segments, vertical segments tick along x
+    let side = if direction.dot(&Vector3::y()).abs() > 0.99 { Vector3::x() } else { Vector3::y() };
+    let half = side * (length * 0.5);
+
+    (0..=(distance / spacing) as usize)
+        .map(|i| a + direction * (i as f32 * spacing))
+        .map(|tick| (tick - half, tick + half))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Perspective3, Vector3};
+
+    #[test]
+    fn test_crates_five_units_apart() {
+        let viewport = ViewportRect::new(0.0, 0.0, 800.0, 600.0);
+        let view = Matrix4::look_at_rh(&Point3::new(2.5, 5.0, 10.0), &Point3::new(2.5, 0.0, 0.0), &Vector3::y());
+        let perspective = Perspective3::new(800.0 / 600.0, 0.8, 0.1, 100.0).to_homogeneous();
+        let crates = [Point3::new(0.0, 0.5, 0.0), Point3::new(5.0, 0.5, 0.0)];
+
+        let mut measurement = Measurement::new();
+        for crate_origin in crates.iter() {
+            //Note(teddy) Click a few pixels off the origin, the snap pulls the point onto it
+            let mut cursor = compute_world_space_to_screen_space(viewport, &crate_origin.coords, &view, &perspective);
+            cursor.x += 4.0;
+            cursor.y -= 3.0;
+
+            let point = snap_to_origin(cursor, crates.iter().cloned(), viewport, &view, &perspective, MEASURE_SNAP_RADIUS);
+            measurement.add_point(point.unwrap());
+        }
+
+        assert!((measurement.total_length() - 5.0).abs() < 1e-4);
+        assert!(measurement.label().unwrap().starts_with("5.00 (dx 5.00 dy 0.00 dz 0.00)"));
+        assert_eq!(measurement.label_anchor(), Some(Point3::new(2.5, 0.5, 0.0)));
+        //Note(teddy) The line plus a tick at every unit including both ends
+        assert_eq!(measurement.overlay_segments().len(), 1 + 6);
+    }
+
+    #[test]
+    fn test_chained_segments_and_ground_plane() {
+        let mut measurement = Measurement::new();
+        measurement.add_point(Point3::origin());
+        measurement.add_point(Point3::new(3.0, 0.0, 0.0));
+        measurement.add_point(Point3::new(3.0, 0.0, 4.0));
+
+        assert!((measurement.total_length() - 7.0).abs() < 1e-5);
+        assert!(measurement.label().unwrap().ends_with("total 7.00"));
+
+        let ray = Ray::new(Point3::new(1.0, 10.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(intersect_ground_plane(&ray), Some(Point3::new(1.0, 0.0, 0.0)));
+        assert_eq!(intersect_ground_plane(&Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::y())), None);
+
+        assert_eq!(snap_to_grid(Point3::new(1.2, 0.1, -0.8), MEASURE_GRID_SIZE), Point3::new(1.0, 0.0, -1.0));
+
+        measurement.clear();
+        assert!(measurement.label().is_none());
+    }
+}
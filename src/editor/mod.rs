@@ -1 +1,5 @@
+pub mod documents;
 pub mod editor;
+pub mod gizmo;
+pub mod measure;
+pub mod undo;
@@ -0,0 +1,390 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+use nalgebra::Isometry3;
+
+use crate::game_world::components::TransformComponent;
+use crate::game_world::mesh_dedup::{apply_label, MeshLabelChange};
+use crate::game_world::world::{EntityID, World};
+
+pub const DEFAULT_UNDO_MEMORY_BUDGET: usize = 4 * 1024 * 1024;
+pub const DEFAULT_NUDGE_MERGE_WINDOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransformState {
+    pub position: Isometry3<f32>,
+    pub scale: f32,
+}
+
+impl TransformState {
+    pub fn from_component(component: &TransformComponent) -> Self {
+        Self { position: component.position, scale: component.scale }
+    }
+}
+
+///Before and after state of one entity, intermediate drag states are never stored
+#[derive(Debug, Copy, Clone)]
+pub struct TransformDelta {
+    pub entity: EntityID,
+    pub before: TransformState,
+    pub after: TransformState,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EditKind {
+    Drag,
+    Nudge,
+    ///Mesh labels rewritten by the merge duplicate meshes command
+    Relabel,
+}
+
+#[derive(Debug)]
+pub struct UndoEntry {
+    pub kind: EditKind,
+    pub deltas: Vec<TransformDelta>,
+    pub labels: Vec<MeshLabelChange>,
+    last_edit: Instant,
+}
+
+impl UndoEntry {
+    ///Rough heap + inline size, used to keep the stack under its budget
+    pub fn memory_size(&self) -> usize {
+        size_of::<UndoEntry>()
+            + self.deltas.capacity() * size_of::<TransformDelta>()
+            + self
+                .labels
+                .iter()
+                .map(|change| size_of::<MeshLabelChange>() + change.before.capacity() + change.after.capacity())
+                .sum::<usize>()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.deltas.is_empty() && self.labels.is_empty()
+    }
+
+    fn same_entities(&self, deltas: &[TransformDelta]) -> bool {
+        self.deltas.len() == deltas.len()
+            && self.deltas.iter().zip(deltas.iter()).all(|(a, b)| a.entity == b.entity)
+    }
+}
+
+pub struct UndoStack {
+    undo: VecDeque<UndoEntry>,
+    redo: Vec<UndoEntry>,
+    drag: Option<Vec<(EntityID, TransformState)>>,
+    memory_used: usize,
+    pub memory_budget: usize,
+    pub nudge_merge_window: Duration,
+    ///Entries that were dropped to stay under the budget since the last call to take_dropped
+    dropped: usize,
+}
+
+impl UndoStack {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            drag: None,
+            memory_used: 0,
+            memory_budget,
+            nudge_merge_window: DEFAULT_NUDGE_MERGE_WINDOW,
+            dropped: 0,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn take_dropped(&mut self) -> usize {
+        std::mem::replace(&mut self.dropped, 0)
+    }
+
+    ///Remember where the dragged entities started, nothing is pushed until the drag ends
+    pub fn begin_drag(&mut self, world: &World, entities: &[EntityID]) {
+        self.drag = Some(capture_states(world, entities));
+    }
+
+    ///Pushes a single entry for the whole drag, or nothing if the entities did not move
+    pub fn end_drag(&mut self, world: &World) {
+        let started = match self.drag.take() {
+            Some(started) => started,
+            None => return,
+        };
+
+        let deltas = started
+            .into_iter()
+            .filter_map(|(entity, before)| {
+                let after = TransformState::from_component(world.components.positionable[entity].as_ref()?);
+                Some(TransformDelta { entity, before, after })
+            })
+            .filter(|delta| delta.before != delta.after)
+            .collect();
+
+        self.push(EditKind::Drag, deltas, Instant::now());
+    }
+
+    ///Record a nudge that was already applied to the world.
+    ///Nudges of the same entities inside the merge window extend the previous entry.
+    pub fn record_nudge(&mut self, deltas: Vec<TransformDelta>, now: Instant) {
+        self.push(EditKind::Nudge, deltas, now);
+    }
+
+    pub fn push(&mut self, kind: EditKind, mut deltas: Vec<TransformDelta>, now: Instant) {
+        if deltas.is_empty() {
+            return;
+        }
+
+        deltas.sort_by_key(|delta| delta.entity);
+        self.redo.clear();
+
+        if kind == EditKind::Nudge {
+            if let Some(last) = self.undo.back_mut() {
+                if last.kind == EditKind::Nudge
+                    && now.duration_since(last.last_edit) <= self.nudge_merge_window
+                    && last.same_entities(&deltas)
+                {
+                    //Note(teddy) Keep the original before state, only the end of the nudge run moves
+                    for (merged, delta) in last.deltas.iter_mut().zip(deltas.iter()) {
+                        merged.after = delta.after;
+                    }
+                    last.last_edit = now;
+                    return;
+                }
+            }
+        }
+
+        deltas.shrink_to_fit();
+        self.push_entry(UndoEntry { kind, deltas, labels: vec![], last_edit: now });
+    }
+
+    ///Record mesh label changes that were already applied to the world
+    pub fn push_relabel(&mut self, mut labels: Vec<MeshLabelChange>, now: Instant) {
+        if labels.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        labels.shrink_to_fit();
+        self.push_entry(UndoEntry { kind: EditKind::Relabel, deltas: vec![], labels, last_edit: now });
+    }
+
+    fn push_entry(&mut self, entry: UndoEntry) {
+        self.memory_used += entry.memory_size();
+        self.undo.push_back(entry);
+        self.enforce_budget();
+    }
+
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        let entry = match self.undo.pop_back() {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        self.memory_used -= entry.memory_size();
+        for delta in entry.deltas.iter() {
+            apply_state(world, delta.entity, &delta.before);
+        }
+        for change in entry.labels.iter() {
+            apply_label(world, change.entity, &change.before);
+        }
+        self.redo.push(entry);
+        true
+    }
+
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        let entry = match self.redo.pop() {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        for delta in entry.deltas.iter() {
+            apply_state(world, delta.entity, &delta.after);
+        }
+        for change in entry.labels.iter() {
+            apply_label(world, change.entity, &change.after);
+        }
+        self.memory_used += entry.memory_size();
+        self.undo.push_back(entry);
+        self.enforce_budget();
+        true
+    }
+
+    ///Drops every edit of a deleted entity, create_entity hands its id out again
+    pub fn forget_entity(&mut self, entity: EntityID) {
+        for entry in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            entry.deltas.retain(|delta| delta.entity != entity);
+            entry.labels.retain(|change| change.entity != entity);
+        }
+        self.undo.retain(|entry| !entry.is_empty());
+        self.redo.retain(|entry| !entry.is_empty());
+        self.memory_used = self.undo.iter().map(|entry| entry.memory_size()).sum();
+
+        if let Some(started) = self.drag.as_mut() {
+            started.retain(|(id, _)| *id != entity);
+        }
+    }
+
+    fn enforce_budget(&mut self) {
+        //Note(teddy) The newest entry is always kept even when it alone exceeds the budget
+        while self.memory_used > self.memory_budget && self.undo.len() > 1 {
+            let oldest = self.undo.pop_front().unwrap();
+            self.memory_used -= oldest.memory_size();
+            self.dropped += 1;
+        }
+    }
+}
+
+fn capture_states(world: &World, entities: &[EntityID]) -> Vec<(EntityID, TransformState)> {
+    entities
+        .iter()
+        .filter_map(|entity| {
+            let component = world.components.positionable.get(*entity)?.as_ref()?;
+            Some((*entity, TransformState::from_component(component)))
+        })
+        .collect()
+}
+
+fn apply_state(world: &mut World, entity: EntityID, state: &TransformState) {
+    if let Some(Some(component)) = world.components.positionable.get_mut(entity) {
+        component.position = state.position;
+        component.scale = state.scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    fn world_with_entities(count: usize) -> (World, Vec<EntityID>) {
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        //Note(teddy) create_entity hands out ids from a global counter, tests run in parallel so index directly
+        let entities: Vec<EntityID> = (0..count)
+            .map(|i| {
+                world.components.create_entry();
+                let id = world.components.positionable.len() - 1;
+                world.components.positionable[id] =
+                    Some(TransformComponent::new(Vector3::new(i as f32, 0.0, 0.0), Vector3::y(), 1.0));
+                id
+            })
+            .collect();
+        (world, entities)
+    }
+
+    fn translate(world: &mut World, entities: &[EntityID], offset: Vector3<f32>) {
+        for entity in entities {
+            let component = world.components.positionable[*entity].as_mut().unwrap();
+            component.position.translation.vector += offset;
+        }
+    }
+
+    fn nudge(world: &mut World, stack: &mut UndoStack, entity: EntityID, now: Instant) {
+        let before = TransformState::from_component(world.components.positionable[entity].as_ref().unwrap());
+        translate(world, &[entity], Vector3::new(0.1, 0.0, 0.0));
+        let after = TransformState::from_component(world.components.positionable[entity].as_ref().unwrap());
+        stack.record_nudge(vec![TransformDelta { entity, before, after }], now);
+    }
+
+    #[test]
+    fn test_group_drag_is_one_bounded_entry() {
+        let (mut world, entities) = world_with_entities(500);
+        let mut stack = UndoStack::new(DEFAULT_UNDO_MEMORY_BUDGET);
+
+        stack.begin_drag(&world, &entities);
+        for _ in 0..120 {
+            translate(&mut world, &entities, Vector3::new(0.01, 0.02, 0.0));
+        }
+        stack.end_drag(&world);
+
+        assert_eq!(stack.depth(), 1);
+        let bound = size_of::<UndoEntry>() + 500 * size_of::<TransformDelta>();
+        assert!(stack.memory_used() <= bound, "{} > {}", stack.memory_used(), bound);
+
+        assert!(stack.undo(&mut world));
+        for (i, entity) in entities.iter().enumerate() {
+            let position = world.components.positionable[*entity].as_ref().unwrap().position.translation.vector;
+            assert!((position - Vector3::new(i as f32, 0.0, 0.0)).norm() < 1e-5);
+        }
+        assert_eq!(stack.memory_used(), 0);
+
+        assert!(stack.redo(&mut world));
+        let moved = world.components.positionable[entities[0]].as_ref().unwrap().position.translation.vector;
+        assert!((moved - Vector3::new(1.2, 2.4, 0.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_rapid_nudges_merge() {
+        let (mut world, entities) = world_with_entities(2);
+        let mut stack = UndoStack::new(DEFAULT_UNDO_MEMORY_BUDGET);
+        let start = Instant::now();
+
+        for i in 0..10 {
+            nudge(&mut world, &mut stack, entities[0], start + Duration::from_millis(i * 100));
+        }
+        assert_eq!(stack.depth(), 1);
+
+        //Note(teddy) A pause longer than the window or a different entity starts a new entry
+        nudge(&mut world, &mut stack, entities[0], start + Duration::from_secs(5));
+        nudge(&mut world, &mut stack, entities[1], start + Duration::from_millis(5100));
+        assert_eq!(stack.depth(), 3);
+
+        stack.undo(&mut world);
+        stack.undo(&mut world);
+        let position = world.components.positionable[entities[0]].as_ref().unwrap().position.translation.vector;
+        assert!((position.x - 1.0).abs() < 1e-5, "{}", position.x);
+
+        stack.undo(&mut world);
+        let position = world.components.positionable[entities[0]].as_ref().unwrap().position.translation.vector;
+        assert!(position.x.abs() < 1e-5, "{}", position.x);
+    }
+
+    #[test]
+    fn test_budget_drops_oldest_entries() {
+        let (mut world, entities) = world_with_entities(4);
+        let entry_size = size_of::<UndoEntry>() + size_of::<TransformDelta>();
+        let mut stack = UndoStack::new(entry_size * 3);
+
+        for i in 0..6 {
+            stack.begin_drag(&world, &entities[i % 4..i % 4 + 1]);
+            translate(&mut world, &entities[i % 4..i % 4 + 1], Vector3::new(1.0, 0.0, 0.0));
+            stack.end_drag(&world);
+        }
+
+        assert_eq!(stack.depth(), 3);
+        assert_eq!(stack.take_dropped(), 3);
+        assert!(stack.memory_used() <= stack.memory_budget);
+    }
+
+    #[test]
+    fn test_deleted_entities_are_forgotten() {
+        let (mut world, entities) = world_with_entities(2);
+        let mut stack = UndoStack::new(DEFAULT_UNDO_MEMORY_BUDGET);
+
+        stack.begin_drag(&world, &entities);
+        translate(&mut world, &entities, Vector3::new(1.0, 0.0, 0.0));
+        stack.end_drag(&world);
+        stack.begin_drag(&world, &entities[1..]);
+        translate(&mut world, &entities[1..], Vector3::new(1.0, 0.0, 0.0));
+        stack.end_drag(&world);
+
+        stack.forget_entity(entities[1]);
+        assert_eq!(stack.depth(), 1);
+
+        //Note(teddy) Stands in for a new entity that was given the deleted id
+        world.components.positionable[entities[1]] = Some(TransformComponent::new(Vector3::new(7.0, 0.0, 0.0), Vector3::y(), 1.0));
+        assert!(stack.undo(&mut world));
+        assert!(!stack.undo(&mut world));
+
+        let position = world.components.positionable[entities[0]].as_ref().unwrap().position.translation.vector;
+        assert!(position.x.abs() < 1e-5, "{}", position.x);
+        let position = world.components.positionable[entities[1]].as_ref().unwrap().position.translation.vector;
+        assert_eq!(position.x, 7.0);
+    }
+}
@@ -1,12 +1,17 @@
-use nalgebra::{Isometry3, Vector3};
+use nalgebra::{Isometry3, Point3, Vector3};
 use nphysics3d::material::MaterialHandle;
 use nphysics3d::object::{BodyStatus, DefaultBodyHandle, DefaultColliderHandle};
 
+use super::prefab::PrefabInstance;
+
 
 pub struct Components {
     pub renderables: Vec<Option<RenderComponent>>,
     pub positionable: Vec<Option<TransformComponent>>,
     pub physics: Vec<Option<PhysicsComponent>>,
+    pub ropes: Vec<Option<RopeComponent>>,
+    pub prefabs: Vec<Option<PrefabInstance>>,
+    pub characters: Vec<Option<CharacterController>>,
 }
 
 impl Components {
@@ -15,6 +20,9 @@ impl Components {
             renderables: Vec::with_capacity(capacity),
             positionable: Vec::with_capacity(capacity),
             physics: Vec::with_capacity(capacity),
+            ropes: Vec::with_capacity(capacity),
+            prefabs: Vec::with_capacity(capacity),
+            characters: Vec::with_capacity(capacity),
         }
     }
 
@@ -22,6 +30,106 @@ impl Components {
         self.renderables.push(None);
         self.positionable.push(None);
         self.physics.push(None);
+        self.ropes.push(None);
+        self.prefabs.push(None);
+        self.characters.push(None);
+    }
+
+    ///Empties every slot of the entity so a recycled id starts out bare
+    pub fn clear_entry(&mut self, id: usize) {
+        self.renderables[id] = None;
+        self.positionable[id] = None;
+        self.physics[id] = None;
+        self.ropes[id] = None;
+        self.prefabs[id] = None;
+        self.characters[id] = None;
+    }
+}
+
+///Per entity render behaviour, stored as a bitfield so it can be written straight into the save file
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderFlags(pub u32);
+
+impl RenderFlags {
+    ///Can be selected by the picking ray
+    pub const PICKABLE: RenderFlags = RenderFlags(1 << 0);
+    ///Drawn as lines regardless of the global polygon mode
+    pub const WIREFRAME: RenderFlags = RenderFlags(1 << 1);
+    ///Drawn in the overlay pass, on top of everything else
+    pub const OVERLAY: RenderFlags = RenderFlags(1 << 2);
+    pub const SHADOW_CASTER: RenderFlags = RenderFlags(1 << 3);
+
+    pub const EDITOR_HELPER: RenderFlags = RenderFlags(Self::WIREFRAME.0 | Self::OVERLAY.0);
+
+    pub fn contains(&self, flags: RenderFlags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub fn set(&mut self, flags: RenderFlags, enabled: bool) {
+        if enabled {
+            self.0 |= flags.0;
+        } else {
+            self.0 &= !flags.0;
+        }
+    }
+}
+
+impl Default for RenderFlags {
+    fn default() -> Self {
+        RenderFlags(Self::PICKABLE.0 | Self::SHADOW_CASTER.0)
+    }
+}
+
+///Editor visualization of a single entity, a debugging aid that isn't saved with the level
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugViewMode {
+    Default,
+    ///Drawn as lines, same as the WIREFRAME flag
+    Wireframe,
+    ///Drawn as usual with the vertex and face normals on top
+    Normals,
+}
+
+impl Default for DebugViewMode {
+    fn default() -> Self {
+        DebugViewMode::Default
+    }
+}
+
+///How a surface is lit, texture names are files in the textures folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub base_color: [f32; 3],
+    pub specular_strength: f32,
+    pub shininess: f32,
+    ///Below 1 the entity is drawn after the opaque ones, blended and sorted back to front
+    pub opacity: f32,
+    pub diffuse_map: Option<String>,
+    ///Loaded with the diffuse map, the shaders don't sample it until meshes carry tangents
+    pub normal_map: Option<String>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: [0.7, 0.7, 0.7],
+            specular_strength: 0.5,
+            shininess: 32.0,
+            opacity: 1.0,
+            diffuse_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+impl Material {
+    ///Texture names the material needs loaded
+    pub fn textures(&self) -> Vec<String> {
+        self.diffuse_map.iter().chain(self.normal_map.iter()).cloned().collect()
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
     }
 }
 
@@ -30,8 +138,10 @@ pub struct RenderComponent {
     pub should_update: bool,
     pub mesh_label: String,
     pub shader_label: String,
-    pub textures: Vec<String>,
-    pub highlight: Option<[f32; 3]>
+    pub material: Material,
+    pub highlight: Option<[f32; 3]>,
+    pub flags: RenderFlags,
+    pub debug_view: DebugViewMode,
 }
 
 impl RenderComponent {
@@ -42,11 +152,28 @@ impl RenderComponent {
             should_update: true,
             mesh_label,
             shader_label,
-            textures: vec![],
-            highlight: None
+            material: Material::default(),
+            highlight: None,
+            flags: RenderFlags::default(),
+            debug_view: DebugViewMode::default(),
         }
     }
 
+    ///Geometry the editor draws for itself, it shouldn't steal clicks from the props behind it
+    pub fn editor_helper(mesh_label: String, shader_label: String) -> Self {
+        let mut component = Self::new(mesh_label, shader_label);
+        component.flags = RenderFlags::EDITOR_HELPER;
+        component
+    }
+
+    pub fn is_pickable(&self) -> bool {
+        self.flags.contains(RenderFlags::PICKABLE)
+    }
+
+    pub fn is_wireframe(&self) -> bool {
+        self.flags.contains(RenderFlags::WIREFRAME) || self.debug_view == DebugViewMode::Wireframe
+    }
+
     //TODO(teddy): To be move the render system
 }
 
@@ -65,6 +192,44 @@ impl TransformComponent {
     }
 }
 
+///Picks the collision group of an entity together with its body status
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CollisionLayer {
+    ///Static environment or dynamic prop, decided by the body status
+    World,
+    ///Small dynamic pieces that hit the world but pass through each other
+    Debris,
+    Trigger,
+    EditorHelper,
+}
+
+///What the collider is built from. Sizes are in the entity's model space, like the mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColliderShape {
+    ///Every triangle of the render mesh, exact but slow to build for big meshes
+    TriMesh,
+    ///Convex hull around the mesh vertices
+    ConvexHull,
+    Box { half_extents: Vector3<f32> },
+    Sphere { radius: f32 },
+    ///Upright along y, half_height is the straight part between the two caps
+    Capsule { half_height: f32, radius: f32 },
+    ///Box around the mesh bounds
+    AutoFromAabb,
+    ///Height grid of a terrain chunk, built along with the chunk's mesh
+    HeightField,
+}
+
+impl ColliderShape {
+    ///Whether the shape can only be built once the entity's mesh has loaded
+    pub fn needs_mesh(&self) -> bool {
+        match self {
+            ColliderShape::TriMesh | ColliderShape::ConvexHull | ColliderShape::AutoFromAabb | ColliderShape::HeightField => true,
+            ColliderShape::Box { .. } | ColliderShape::Sphere { .. } | ColliderShape::Capsule { .. } => false,
+        }
+    }
+}
+
 pub struct PhysicsComponent {
     pub rigid_handle: Option<DefaultBodyHandle>,
     pub collider_handle: Option<DefaultColliderHandle>,
@@ -72,7 +237,17 @@ pub struct PhysicsComponent {
     pub mass: f32,
     pub gravity: bool,
     pub status: BodyStatus,
+    ///Velocity the body is built with, kept up to date by the physics system so saves capture it
     pub velocity: Vector3<f32>,
+    ///Same as velocity, around the body's center
+    pub angular_velocity: Vector3<f32>,
+    pub layer: CollisionLayer,
+    ///Bit mask of the collision groups this collider touches, everything by default
+    pub mask: u32,
+    ///Only read when the body is built, changing it afterwards needs the entity recreated
+    pub shape: ColliderShape,
+    ///Reports overlaps as proximity events without pushing anything, read when the body is built like the shape
+    pub is_sensor: bool,
 }
 
 impl PhysicsComponent {
@@ -91,6 +266,120 @@ impl PhysicsComponent {
             gravity,
             status,
             velocity: initial_velocity,
+            angular_velocity: Vector3::zeros(),
+            layer: CollisionLayer::World,
+            mask: u32::MAX,
+            shape: ColliderShape::TriMesh,
+            is_sensor: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RopeAnchor {
+    Entity(usize),
+    Point(Point3<f32>),
+}
+
+///How a joint holds its two bodies together
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JointKind {
+    ///Keeps the bodies turned the way they were to each other when the joint was made
+    Fixed,
+    ///Free to turn any way around the anchors
+    Ball,
+    ///Turns around the axis only, given in the first body's local space
+    Revolute { axis: Vector3<f32> },
+}
+
+///Anchors are in each body's local space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct JointSpec {
+    pub kind: JointKind,
+    pub anchor_a: Point3<f32>,
+    pub anchor_b: Point3<f32>,
+}
+
+///Two entities held together, kept on the World and built into a constraint once both bodies exist
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Joint {
+    pub a: usize,
+    pub b: usize,
+    pub spec: JointSpec,
+}
+
+///Walks an entity around on its capsule instead of leaving it to the solver. The entity needs a kinematic
+///PhysicsComponent with a capsule shape, the physics system moves the body every frame.
+#[derive(Debug, Copy, Clone)]
+pub struct CharacterController {
+    ///Horizontal velocity to walk with in units a second, the y part is ignored
+    pub desired_velocity: Vector3<f32>,
+    ///Tallest ledge walked up without jumping
+    pub step_height: f32,
+    ///Steepest ground still walked on, in radians
+    pub max_slope: f32,
+    ///Downward acceleration while in the air
+    pub gravity: f32,
+    ///Positive going up, zeroed once the character lands
+    pub vertical_speed: f32,
+    pub is_grounded: bool,
+}
+
+impl CharacterController {
+    pub fn new(step_height: f32, max_slope: f32) -> Self {
+        Self {
+            desired_velocity: Vector3::zeros(),
+            step_height,
+            max_slope,
+            gravity: 9.81,
+            vertical_speed: 0.0,
+            is_grounded: false,
         }
     }
 }
+
+///Chain of verlet particles hanging between two anchors.
+///Particles are simulated in world space, so the entity holding the rope doesn't need a transform
+#[derive(Debug)]
+pub struct RopeComponent {
+    pub start: RopeAnchor,
+    pub end: RopeAnchor,
+    pub segments: usize,
+    pub length: f32,
+    pub iterations: u32,
+    pub thickness: f32,
+    pub collide: bool,
+    pub shader_label: String,
+
+    pub particles: Vec<Point3<f32>>,
+    pub previous: Vec<Point3<f32>>,
+}
+
+impl RopeComponent {
+    pub fn new(
+        start: RopeAnchor,
+        end: RopeAnchor,
+        segments: usize,
+        length: f32,
+        iterations: u32,
+        thickness: f32,
+        shader_label: String,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            segments,
+            length,
+            iterations,
+            thickness,
+            collide: false,
+            shader_label,
+            particles: vec![],
+            previous: vec![],
+        }
+    }
+
+    pub fn segment_length(&self) -> f32 {
+        self.length / self.segments.max(1) as f32
+    }
+}
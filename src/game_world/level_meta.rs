@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_world::world::SAVE_FILE_VERSION;
+
+pub const THUMBNAIL_WIDTH: u32 = 256;
+pub const THUMBNAIL_HEIGHT: u32 = 144;
+
+const METADATA_EXTENSION: &'static str = "meta.json";
+const THUMBNAIL_EXTENSION: &'static str = "thumb.png";
+
+///Sidecar written next to a level so the picker can list levels without loading them.
+///Every field has a default so sidecars from older builds still parse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelMetadata {
+    pub format_version: u32,
+    pub entity_count: u32,
+    ///Seconds since the unix epoch
+    pub saved_at: u64,
+    pub author: String,
+    pub description: String,
+    pub thumbnail: Option<String>,
+}
+
+impl Default for LevelMetadata {
+    fn default() -> Self {
+        Self {
+            format_version: 0,
+            entity_count: 0,
+            saved_at: 0,
+            author: String::new(),
+            description: String::new(),
+            thumbnail: None,
+        }
+    }
+}
+
+impl LevelMetadata {
+    pub fn is_outdated(&self) -> bool {
+        self.format_version < SAVE_FILE_VERSION
+    }
+}
+
+fn with_suffix(level_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = level_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    level_path.with_file_name(name)
+}
+
+pub fn metadata_path(level_path: &Path) -> PathBuf {
+    with_suffix(level_path, METADATA_EXTENSION)
+}
+
+pub fn thumbnail_path(level_path: &Path) -> PathBuf {
+    with_suffix(level_path, THUMBNAIL_EXTENSION)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+///Ok(None) when the level has no sidecar, levels saved before metadata existed
+pub fn read_metadata(level_path: &Path) -> Result<Option<LevelMetadata>, String> {
+    let path = metadata_path(level_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|e| format!("{}", e))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn write_metadata(level_path: &Path, metadata: &LevelMetadata) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(metadata).map_err(|e| format!("{}", e))?;
+    fs::write(metadata_path(level_path), contents).map_err(|e| format!("{}", e))
+}
+
+///Refresh the sidecar after a save, author and description carry over from the previous sidecar
+pub fn update_metadata_on_save(
+    level_path: &Path,
+    previous: &LevelMetadata,
+    entity_count: u32,
+    saved_at: u64,
+) -> Result<LevelMetadata, String> {
+    let metadata = LevelMetadata {
+        format_version: SAVE_FILE_VERSION,
+        entity_count,
+        saved_at,
+        author: previous.author.clone(),
+        description: previous.description.clone(),
+        thumbnail: Some(thumbnail_path(level_path).to_string_lossy().into_owned()),
+    };
+
+    write_metadata(level_path, &metadata)?;
+    Ok(metadata)
+}
+
+#[derive(Debug)]
+pub struct LevelEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub metadata: Option<LevelMetadata>,
+    ///Save time from the sidecar, or the file's modification time for levels without one
+    pub modified: u64,
+}
+
+///Levels in the directory, most recently modified first. Only sidecars are read, never the levels.
+pub fn list_levels(directory: &Path) -> Vec<LevelEntry> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut levels: Vec<LevelEntry> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            !name.ends_with(METADATA_EXTENSION) && !name.ends_with(THUMBNAIL_EXTENSION)
+        })
+        .map(|path| {
+            //Note(teddy) A broken sidecar shouldn't hide the level, list it as if it had none
+            let metadata = read_metadata(&path).unwrap_or(None);
+            let file_modified = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_secs())
+                .unwrap_or(0);
+
+            LevelEntry {
+                name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                modified: metadata.as_ref().map_or(file_modified, |meta| meta.saved_at),
+                metadata,
+                path,
+            }
+        })
+        .collect();
+
+    levels.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+    levels
+}
+
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_level_dir(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("imara_level_meta_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_sidecar_round_trip() {
+        let directory = temp_level_dir("round_trip");
+        let level = directory.join("arena");
+
+        let metadata = LevelMetadata {
+            format_version: SAVE_FILE_VERSION,
+            entity_count: 12,
+            saved_at: 1_600_000_000,
+            author: String::from("teddy"),
+            description: String::from("Test arena"),
+            thumbnail: Some(String::from("arena.thumb.png")),
+        };
+        write_metadata(&level, &metadata).unwrap();
+
+        assert_eq!(metadata_path(&level), directory.join("arena.meta.json"));
+        assert_eq!(read_metadata(&level).unwrap(), Some(metadata.clone()));
+
+        //Note(teddy) Sidecars from older builds miss fields and are flagged as outdated
+        fs::write(metadata_path(&level), r#"{"entity_count": 3}"#).unwrap();
+        let old = read_metadata(&level).unwrap().unwrap();
+        assert_eq!(old.entity_count, 3);
+        assert!(old.is_outdated());
+
+        //Note(teddy) Written by builds that never bumped the sidecar version as the entity layout changed
+        let stale = LevelMetadata { format_version: 2, ..metadata };
+        assert!(stale.is_outdated());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_save_updates_timestamp_and_thumbnail() {
+        let directory = temp_level_dir("save");
+        let level = directory.join("arena");
+
+        let mut previous = LevelMetadata::default();
+        previous.author = String::from("teddy");
+
+        let saved = update_metadata_on_save(&level, &previous, 4, 100).unwrap();
+        assert_eq!(saved.saved_at, 100);
+        assert_eq!(saved.entity_count, 4);
+        assert_eq!(saved.author, "teddy");
+        assert_eq!(saved.thumbnail, Some(thumbnail_path(&level).to_string_lossy().into_owned()));
+        assert!(!saved.is_outdated());
+
+        let resaved = update_metadata_on_save(&level, &saved, 5, 200).unwrap();
+        assert_eq!(read_metadata(&level).unwrap(), Some(resaved));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_list_levels_without_metadata() {
+        let directory = temp_level_dir("list");
+        fs::write(directory.join("old_level"), b"").unwrap();
+        fs::write(directory.join("new_level"), b"").unwrap();
+        update_metadata_on_save(&directory.join("new_level"), &LevelMetadata::default(), 7, unix_now() + 60).unwrap();
+
+        let levels = list_levels(&directory);
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].name, "new_level");
+        assert_eq!(levels[0].metadata.as_ref().unwrap().entity_count, 7);
+        assert_eq!(levels[1].name, "old_level");
+        assert!(levels[1].metadata.is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(120), "2m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(3 * 86400), "3d ago");
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use crate::obj_parser::NormalObj;
+use super::world::{EntityID, World};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[inline]
+fn fnv_write(hash: u64, value: u32) -> u64 {
+    value.to_le_bytes().iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+///Hash of the vertex, normal and index data.
+///Floats are hashed by their bits so only bit identical meshes match, -0.0 and 0.0 differ on purpose.
+pub fn hash_normal_obj(obj: &NormalObj) -> u64 {
+    //Note(teddy) Lengths go in first so data shifted between the buffers can't collide
+    let mut hash = FNV_OFFSET;
+    hash = fnv_write(hash, obj.vertices.len() as u32);
+    hash = fnv_write(hash, obj.normals.len() as u32);
+    hash = fnv_write(hash, obj.indices.len() as u32);
+
+    for vertex in obj.vertices.iter() {
+        for value in vertex.coords.iter() {
+            hash = fnv_write(hash, value.to_bits());
+        }
+    }
+
+    for normal in obj.normals.iter() {
+        for value in normal.coords.iter() {
+            hash = fnv_write(hash, value.to_bits());
+        }
+    }
+
+    obj.indices.iter().fold(hash, |hash, index| fnv_write(hash, *index))
+}
+
+fn same_data(a: &NormalObj, b: &NormalObj) -> bool {
+    let bits = |values: &[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<u32>>();
+
+    a.indices == b.indices
+        && a.vertices.len() == b.vertices.len()
+        && a.normals.len() == b.normals.len()
+        && a.vertices.iter().zip(b.vertices.iter()).all(|(x, y)| bits(x.coords.as_slice()) == bits(y.coords.as_slice()))
+        && a.normals.iter().zip(b.normals.iter()).all(|(x, y)| bits(x.coords.as_slice()) == bits(y.coords.as_slice()))
+}
+
+///Label aliases found by content hashing, alias label -> canonical label
+#[derive(Debug, Default)]
+pub struct MeshAliases {
+    by_hash: HashMap<u64, String>,
+    aliases: HashMap<String, String>,
+}
+
+impl MeshAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Records the mesh under its hash. Returns the canonical label when an identical mesh is already resident.
+    ///`resident` looks up the data of a previously registered label so a hash collision is never taken as a match.
+    pub fn register<'a, F>(&mut self, label: &str, obj: &NormalObj, resident: F) -> Option<String>
+    where
+        F: Fn(&str) -> Option<&'a NormalObj>,
+    {
+        let hash = hash_normal_obj(obj);
+
+        match self.by_hash.get(&hash) {
+            Some(canonical) if canonical != label => match resident(canonical) {
+                Some(existing) if same_data(existing, obj) => {
+                    let canonical = canonical.clone();
+                    self.aliases.insert(label.to_string(), canonical.clone());
+                    Some(canonical)
+                }
+                _ => None,
+            },
+            Some(_) => None,
+            None => {
+                self.by_hash.insert(hash, label.to_string());
+                None
+            }
+        }
+    }
+
+    pub fn canonical<'a>(&'a self, label: &'a str) -> &'a str {
+        self.aliases.get(label).map_or(label, |canonical| canonical.as_str())
+    }
+
+    pub fn is_alias(&self, label: &str) -> bool {
+        self.aliases.contains_key(label)
+    }
+
+    ///(alias, canonical) pairs sorted by alias
+    pub fn duplicates(&self) -> Vec<(String, String)> {
+        let mut duplicates: Vec<(String, String)> =
+            self.aliases.iter().map(|(alias, canonical)| (alias.clone(), canonical.clone())).collect();
+        duplicates.sort();
+        duplicates
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshLabelChange {
+    pub entity: EntityID,
+    pub before: String,
+    pub after: String,
+}
+
+///The suggested cleanup, points every render component using an alias at its canonical label.
+///The returned changes are what the editor pushes on its undo stack.
+pub fn merge_duplicate_labels(world: &mut World) -> Vec<MeshLabelChange> {
+    let resources = world.resources.read().unwrap();
+    let mut changes = vec![];
+
+    for (entity, component) in world.components.renderables.iter_mut().enumerate() {
+        if let Some(component) = component {
+            if resources.mesh_aliases.is_alias(&component.mesh_label) {
+                let after = resources.mesh_aliases.canonical(&component.mesh_label).to_string();
+                let before = std::mem::replace(&mut component.mesh_label, after.clone());
+                changes.push(MeshLabelChange { entity, before, after });
+            }
+        }
+    }
+
+    changes
+}
+
+pub fn apply_label(world: &mut World, entity: EntityID, label: &str) {
+    if let Some(Some(component)) = world.components.renderables.get_mut(entity) {
+        component.mesh_label = label.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Point4};
+
+    use crate::game_world::components::RenderComponent;
+    use crate::game_world::world::{Mesh, MeshType};
+
+    fn fixture(offset: f32) -> NormalObj {
+        NormalObj {
+            vertices: vec![
+                Point4::new(0.0, 0.0, 0.0, 1.0),
+                Point4::new(1.0 + offset, 0.0, 0.0, 1.0),
+                Point4::new(0.0, 1.0, 0.0, 1.0),
+            ],
+            normals: vec![Point3::new(0.0, 0.0, 1.0); 3],
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn test_only_bit_identical_meshes_alias() {
+        let crate_a = fixture(0.0);
+        let crate_b = fixture(0.0);
+        let nearly_a = fixture(f32::EPSILON);
+        let nearly_b = NormalObj { indices: vec![0, 2, 1], ..fixture(0.0) };
+
+        assert_eq!(hash_normal_obj(&crate_a), hash_normal_obj(&crate_b));
+        assert_ne!(hash_normal_obj(&crate_a), hash_normal_obj(&nearly_a));
+
+        let meshes: HashMap<&str, &NormalObj> =
+            vec![("crate_final.obj", &crate_a), ("crate_final2.obj", &crate_b), ("nearly.obj", &nearly_a), ("winding.obj", &nearly_b)]
+                .into_iter()
+                .collect();
+        let resident = |label: &str| meshes.get(label).map(|obj| *obj);
+
+        let mut aliases = MeshAliases::new();
+        assert_eq!(aliases.register("crate_final.obj", &crate_a, resident), None);
+        assert_eq!(aliases.register("crate_final2.obj", &crate_b, resident), Some(String::from("crate_final.obj")));
+        assert_eq!(aliases.register("nearly.obj", &nearly_a, resident), None);
+        assert_eq!(aliases.register("winding.obj", &nearly_b, resident), None);
+
+        assert_eq!(aliases.duplicates(), vec![(String::from("crate_final2.obj"), String::from("crate_final.obj"))]);
+        assert_eq!(aliases.canonical("crate_final2.obj"), "crate_final.obj");
+        assert_eq!(aliases.canonical("nearly.obj"), "nearly.obj");
+    }
+
+    #[test]
+    fn test_aliased_labels_resolve_and_merge() {
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        {
+            let mut resources = world.resources.write().unwrap();
+            resources.dedup_meshes = true;
+            resources.insert_mesh("crate_final.obj", fixture(0.0));
+            resources.insert_mesh("crate_final2.obj", fixture(0.0));
+
+            //Note(teddy) The alias keeps no copy of its own, the renderer gets the canonical data
+            assert!(resources.mesh_data["crate_final2.obj"].mesh_type.is_none());
+            match resources.mesh("crate_final2.obj") {
+                Some(Mesh { mesh_type: Some(MeshType::Normal(obj)), .. }) => assert_eq!(obj.indices, vec![0, 1, 2]),
+                _ => panic!("alias did not resolve to the canonical mesh"),
+            }
+        }
+
+        world.components.create_entry();
+        let entity = world.components.renderables.len() - 1;
+        world.components.renderables[entity] =
+            Some(RenderComponent::new(String::from("crate_final2.obj"), String::from("default")));
+
+        let changes = merge_duplicate_labels(&mut world);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(world.components.renderables[entity].as_ref().unwrap().mesh_label, "crate_final.obj");
+
+        apply_label(&mut world, changes[0].entity, &changes[0].before);
+        assert_eq!(world.components.renderables[entity].as_ref().unwrap().mesh_label, "crate_final2.obj");
+    }
+}
@@ -1,2 +1,6 @@
 pub mod components;
+pub mod level_meta;
+pub mod mesh_dedup;
+pub mod prefab;
+pub mod terrain;
 pub mod world;
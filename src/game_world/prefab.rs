@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use super::components::{Components, Material, RenderComponent, RenderFlags, TransformComponent};
+use super::world::EntityID;
+
+pub const PREFAB_ASSETS_DIR: &'static str = "./assets/prefabs/";
+
+///Fields an instance can override, the discriminant is the bit used in the override mask
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PrefabField {
+    Mesh = 0,
+    Shader = 1,
+    Scale = 2,
+    Flags = 3,
+    Color = 4,
+    Opacity = 5,
+}
+
+impl PrefabField {
+    pub const ALL: [PrefabField; 6] = [
+        PrefabField::Mesh,
+        PrefabField::Shader,
+        PrefabField::Scale,
+        PrefabField::Flags,
+        PrefabField::Color,
+        PrefabField::Opacity,
+    ];
+
+    fn bit(&self) -> u32 {
+        1 << (*self as u32)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrefabField::Mesh => "Mesh",
+            PrefabField::Shader => "Shader",
+            PrefabField::Scale => "Scale",
+            PrefabField::Flags => "Flags",
+            PrefabField::Color => "Color",
+            PrefabField::Opacity => "Opacity",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefabValue {
+    Mesh(String),
+    Shader(String),
+    Scale(f32),
+    Flags(RenderFlags),
+    Color([f32; 3]),
+    Opacity(f32),
+}
+
+impl PrefabValue {
+    pub fn field(&self) -> PrefabField {
+        match self {
+            PrefabValue::Mesh(_) => PrefabField::Mesh,
+            PrefabValue::Shader(_) => PrefabField::Shader,
+            PrefabValue::Scale(_) => PrefabField::Scale,
+            PrefabValue::Flags(_) => PrefabField::Flags,
+            PrefabValue::Color(_) => PrefabField::Color,
+            PrefabValue::Opacity(_) => PrefabField::Opacity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    pub name: String,
+    pub mesh_label: String,
+    pub shader_label: String,
+    pub scale: f32,
+    pub flags: u32,
+    ///Prefabs saved before colors were part of them take the default material's
+    #[serde(default = "default_color")]
+    pub base_color: [f32; 3],
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+}
+
+fn default_color() -> [f32; 3] {
+    Material::default().base_color
+}
+
+fn default_opacity() -> f32 {
+    Material::default().opacity
+}
+
+impl Prefab {
+    pub fn value(&self, field: PrefabField) -> PrefabValue {
+        match field {
+            PrefabField::Mesh => PrefabValue::Mesh(self.mesh_label.clone()),
+            PrefabField::Shader => PrefabValue::Shader(self.shader_label.clone()),
+            PrefabField::Scale => PrefabValue::Scale(self.scale),
+            PrefabField::Flags => PrefabValue::Flags(RenderFlags(self.flags)),
+            PrefabField::Color => PrefabValue::Color(self.base_color),
+            PrefabField::Opacity => PrefabValue::Opacity(self.opacity),
+        }
+    }
+
+    pub fn set_value(&mut self, value: PrefabValue) {
+        match value {
+            PrefabValue::Mesh(mesh) => self.mesh_label = mesh,
+            PrefabValue::Shader(shader) => self.shader_label = shader,
+            PrefabValue::Scale(scale) => self.scale = scale,
+            PrefabValue::Flags(flags) => self.flags = flags.0,
+            PrefabValue::Color(color) => self.base_color = color,
+            PrefabValue::Opacity(opacity) => self.opacity = opacity,
+        }
+    }
+}
+
+///Provenance of an entity created from a prefab
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefabInstance {
+    pub prefab: String,
+    ///Bit mask of PrefabFields the instance changed, those are skipped when the prefab updates
+    pub overrides: u32,
+}
+
+impl PrefabInstance {
+    pub fn new(prefab: String) -> Self {
+        Self { prefab, overrides: 0 }
+    }
+
+    pub fn is_overridden(&self, field: PrefabField) -> bool {
+        self.overrides & field.bit() != 0
+    }
+
+    pub fn set_overridden(&mut self, field: PrefabField, overridden: bool) {
+        if overridden {
+            self.overrides |= field.bit();
+        } else {
+            self.overrides &= !field.bit();
+        }
+    }
+
+    pub fn overridden_fields(&self) -> Vec<PrefabField> {
+        PrefabField::ALL.iter().copied().filter(|field| self.is_overridden(*field)).collect()
+    }
+}
+
+///Current value of a field on an entity, None when the entity is missing the component
+pub fn entity_value(components: &Components, entity: EntityID, field: PrefabField) -> Option<PrefabValue> {
+    match field {
+        PrefabField::Scale => components.positionable[entity]
+            .as_ref()
+            .map(|transform| PrefabValue::Scale(transform.scale)),
+        _ => components.renderables[entity].as_ref().map(|render| match field {
+            PrefabField::Mesh => PrefabValue::Mesh(render.mesh_label.clone()),
+            PrefabField::Shader => PrefabValue::Shader(render.shader_label.clone()),
+            PrefabField::Color => PrefabValue::Color(render.material.base_color),
+            PrefabField::Opacity => PrefabValue::Opacity(render.material.opacity),
+            _ => PrefabValue::Flags(render.flags),
+        }),
+    }
+}
+
+fn write_entity_value(components: &mut Components, entity: EntityID, value: PrefabValue) {
+    match value {
+        PrefabValue::Scale(scale) => {
+            if let Some(transform) = components.positionable[entity].as_mut() {
+                transform.scale = scale;
+            }
+        }
+        value => {
+            if let Some(render) = components.renderables[entity].as_mut() {
+                match value {
+                    PrefabValue::Mesh(mesh) => render.mesh_label = mesh,
+                    PrefabValue::Shader(shader) => render.shader_label = shader,
+                    PrefabValue::Flags(flags) => render.flags = flags,
+                    PrefabValue::Color(color) => render.material.base_color = color,
+                    PrefabValue::Opacity(opacity) => render.material.opacity = opacity,
+                    PrefabValue::Scale(_) => (),
+                }
+            }
+        }
+    }
+}
+
+///Fill an existing entity slot from the prefab and link it back
+pub fn instantiate(components: &mut Components, entity: EntityID, prefab: &Prefab, position: Vector3<f32>) {
+    let mut render = RenderComponent::new(prefab.mesh_label.clone(), prefab.shader_label.clone());
+    render.flags = RenderFlags(prefab.flags);
+    render.material.base_color = prefab.base_color;
+    render.material.opacity = prefab.opacity;
+
+    components.renderables[entity] = Some(render);
+    components.positionable[entity] = Some(TransformComponent::new(position, Vector3::zeros(), prefab.scale));
+    components.prefabs[entity] = Some(PrefabInstance::new(prefab.name.clone()));
+}
+
+///Editing a field of an instance marks it as overridden
+pub fn set_instance_field(components: &mut Components, entity: EntityID, value: PrefabValue) {
+    if let Some(instance) = components.prefabs[entity].as_mut() {
+        instance.set_overridden(value.field(), true);
+    }
+    write_entity_value(components, entity, value);
+}
+
+pub fn revert_field(components: &mut Components, entity: EntityID, field: PrefabField, prefab: &Prefab) {
+    match components.prefabs[entity].as_mut() {
+        Some(instance) if instance.prefab == prefab.name => instance.set_overridden(field, false),
+        _ => return,
+    }
+    write_entity_value(components, entity, prefab.value(field));
+}
+
+///Push the prefab to every linked instance, overridden fields are left alone
+pub fn update_instances<I: Iterator<Item = EntityID>>(components: &mut Components, entities: I, prefab: &Prefab) -> usize {
+    let mut updated = 0;
+
+    for entity in entities {
+        let instance = match components.prefabs[entity].as_ref() {
+            Some(instance) if instance.prefab == prefab.name => instance.clone(),
+            _ => continue,
+        };
+
+        for field in PrefabField::ALL.iter() {
+            if !instance.is_overridden(*field) {
+                write_entity_value(components, entity, prefab.value(*field));
+            }
+        }
+        updated += 1;
+    }
+
+    updated
+}
+
+///Copy the instance's overrides into the prefab, the instance then follows the prefab again
+pub fn apply_instance_to_prefab(components: &mut Components, entity: EntityID, prefab: &mut Prefab) {
+    let overridden = match components.prefabs[entity].as_ref() {
+        Some(instance) if instance.prefab == prefab.name => instance.overridden_fields(),
+        _ => return,
+    };
+
+    for field in overridden {
+        if let Some(value) = entity_value(components, entity, field) {
+            prefab.set_value(value);
+        }
+    }
+
+    if let Some(instance) = components.prefabs[entity].as_mut() {
+        instance.overrides = 0;
+    }
+}
+
+///Prefabs are stored one json file per prefab in the prefab assets dir
+pub fn load_prefabs(directory: &Path) -> HashMap<String, Prefab> {
+    let mut prefabs = HashMap::new();
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return prefabs,
+    };
+
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().map_or(true, |extension| extension != "json") {
+            continue;
+        }
+
+        match fs::read_to_string(&path).map(|contents| serde_json::from_str::<Prefab>(&contents)) {
+            Ok(Ok(prefab)) => {
+                prefabs.insert(prefab.name.clone(), prefab);
+            }
+            _ => println!("Failed to load prefab {}", path.display()),
+        }
+    }
+
+    prefabs
+}
+
+pub fn prefab_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.json", name))
+}
+
+///Prefab file contents, the editor's document registry decides when they are written
+pub fn prefab_bytes(prefab: &Prefab) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(prefab).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lamp() -> Prefab {
+        Prefab {
+            name: String::from("lamp"),
+            mesh_label: String::from("lamp.obj"),
+            shader_label: String::from("default"),
+            scale: 1.0,
+            flags: RenderFlags::default().0,
+            base_color: [1.0, 0.9, 0.6],
+            opacity: 1.0,
+        }
+    }
+
+    fn place(count: usize, prefab: &Prefab) -> (Components, Vec<EntityID>) {
+        let mut components = Components::new(count);
+        let entities = (0..count)
+            .map(|i| {
+                components.create_entry();
+                instantiate(&mut components, i, prefab, Vector3::new(i as f32, 0.0, 0.0));
+                i
+            })
+            .collect();
+        (components, entities)
+    }
+
+    fn scale(components: &Components, entity: EntityID) -> f32 {
+        components.positionable[entity].as_ref().unwrap().scale
+    }
+
+    fn shader(components: &Components, entity: EntityID) -> &str {
+        &components.renderables[entity].as_ref().unwrap().shader_label
+    }
+
+    fn color(components: &Components, entity: EntityID) -> [f32; 3] {
+        components.renderables[entity].as_ref().unwrap().material.base_color
+    }
+
+    #[test]
+    fn test_update_skips_overridden_fields() {
+        let mut prefab = lamp();
+        let (mut components, lamps) = place(5, &prefab);
+
+        set_instance_field(&mut components, lamps[2], PrefabValue::Color([1.0, 0.0, 0.0]));
+        set_instance_field(&mut components, lamps[3], PrefabValue::Shader(String::from("red")));
+        assert!(components.prefabs[lamps[2]].as_ref().unwrap().is_overridden(PrefabField::Color));
+        assert!(!components.prefabs[lamps[0]].as_ref().unwrap().is_overridden(PrefabField::Color));
+
+        prefab.scale = 2.0;
+        prefab.shader_label = String::from("lamp_glow");
+        prefab.base_color = [0.2, 0.2, 1.0];
+        assert_eq!(update_instances(&mut components, lamps.iter().copied(), &prefab), 5);
+
+        for lamp in lamps.iter() {
+            assert_eq!(scale(&components, *lamp), 2.0);
+        }
+        assert_eq!(color(&components, lamps[2]), [1.0, 0.0, 0.0]);
+        assert_eq!(color(&components, lamps[0]), [0.2, 0.2, 1.0]);
+        assert_eq!(shader(&components, lamps[3]), "red");
+        assert_eq!(shader(&components, lamps[0]), "lamp_glow");
+        //Note(teddy) Placement is per instance and never touched by the prefab
+        assert_eq!(components.positionable[lamps[4]].as_ref().unwrap().position.translation.x, 4.0);
+    }
+
+    #[test]
+    fn test_revert_field() {
+        let prefab = lamp();
+        let (mut components, lamps) = place(1, &prefab);
+
+        set_instance_field(&mut components, lamps[0], PrefabValue::Scale(3.0));
+        set_instance_field(&mut components, lamps[0], PrefabValue::Mesh(String::from("cube.obj")));
+        assert_eq!(
+            components.prefabs[lamps[0]].as_ref().unwrap().overridden_fields(),
+            vec![PrefabField::Mesh, PrefabField::Scale]
+        );
+
+        revert_field(&mut components, lamps[0], PrefabField::Scale, &prefab);
+        assert_eq!(scale(&components, lamps[0]), 1.0);
+        assert_eq!(components.prefabs[lamps[0]].as_ref().unwrap().overridden_fields(), vec![PrefabField::Mesh]);
+        assert_eq!(components.renderables[lamps[0]].as_ref().unwrap().mesh_label, "cube.obj");
+    }
+
+    #[test]
+    fn test_apply_instance_to_prefab() {
+        let mut prefab = lamp();
+        let (mut components, lamps) = place(3, &prefab);
+
+        set_instance_field(&mut components, lamps[1], PrefabValue::Flags(RenderFlags::EDITOR_HELPER));
+        set_instance_field(&mut components, lamps[0], PrefabValue::Scale(0.5));
+
+        apply_instance_to_prefab(&mut components, lamps[1], &mut prefab);
+        assert_eq!(prefab.flags, RenderFlags::EDITOR_HELPER.0);
+        assert_eq!(prefab.scale, 1.0);
+        assert_eq!(components.prefabs[lamps[1]].as_ref().unwrap().overrides, 0);
+
+        update_instances(&mut components, lamps.iter().copied(), &prefab);
+        for lamp in lamps.iter() {
+            assert_eq!(components.renderables[*lamp].as_ref().unwrap().flags, RenderFlags::EDITOR_HELPER);
+        }
+        assert_eq!(scale(&components, lamps[0]), 0.5);
+        assert_eq!(scale(&components, lamps[2]), 1.0);
+    }
+
+    #[test]
+    fn test_prefabs_without_colors_still_load() {
+        let prefab: Prefab = serde_json::from_str(
+            r#"{"name": "lamp", "mesh_label": "lamp.obj", "shader_label": "default", "scale": 1.0, "flags": 1}"#,
+        )
+        .unwrap();
+
+        assert_eq!(prefab.base_color, Material::default().base_color);
+        assert_eq!(prefab.opacity, 1.0);
+    }
+
+    #[test]
+    fn test_other_prefabs_are_left_alone() {
+        let lamp = lamp();
+        let mut crate_prefab = lamp.clone();
+        crate_prefab.name = String::from("crate");
+        crate_prefab.scale = 4.0;
+
+        let (mut components, entities) = place(2, &lamp);
+        instantiate(&mut components, entities[1], &crate_prefab, Vector3::zeros());
+
+        let mut bigger_lamp = lamp.clone();
+        bigger_lamp.scale = 2.0;
+        assert_eq!(update_instances(&mut components, entities.iter().copied(), &bigger_lamp), 1);
+        assert_eq!(scale(&components, entities[1]), 4.0);
+
+        revert_field(&mut components, entities[1], PrefabField::Scale, &bigger_lamp);
+        assert_eq!(scale(&components, entities[1]), 4.0);
+    }
+}
@@ -0,0 +1,167 @@
+use std::ops::RangeInclusive;
+
+use nalgebra::{DMatrix, Point3, Point4, Vector3};
+use ncollide3d::shape::HeightField;
+
+use crate::obj_parser::NormalObj;
+use crate::renderer::png_decoder::DecodedImage;
+
+///Cells along each side of a chunk, every chunk is its own mesh so the culling can skip the ones out of view
+pub const TERRAIN_CHUNK_CELLS: usize = 64;
+
+///Grid of heights from 0 to 1, rows along z and columns along x
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    pub width: usize,
+    pub depth: usize,
+    pub heights: Vec<f32>,
+    ///Size of the whole terrain along x and z, y is the height of a white pixel
+    pub scale: Vector3<f32>,
+}
+
+///One chunk of a terrain, its mesh and collider are centered on Heightmap::chunk_offset
+pub struct TerrainChunk {
+    pub label: String,
+    pub mesh: NormalObj,
+    pub heightfield: HeightField<f32>,
+}
+
+///Mesh label of a chunk, the chunks of a terrain all start with its name
+pub fn terrain_chunk_label(name: &str, x: usize, z: usize) -> String {
+    format!("{}#{}_{}", name, x, z)
+}
+
+///Chunks along x and z for a heightmap with that many samples
+pub fn terrain_chunk_grid(width: usize, depth: usize) -> (usize, usize) {
+    let chunks = |samples: usize| ((samples.max(2) - 1) + TERRAIN_CHUNK_CELLS - 1) / TERRAIN_CHUNK_CELLS;
+    (chunks(width), chunks(depth))
+}
+
+impl Heightmap {
+    ///Reads the red channel, greyscale images decode with the same value in all three
+    pub fn from_image(image: &DecodedImage, scale: Vector3<f32>) -> Result<Self, String> {
+        let (width, depth) = (image.width as usize, image.height as usize);
+        if width < 2 || depth < 2 {
+            return Err(format!("A heightmap needs at least 2x2 pixels, got {}x{}", width, depth));
+        }
+
+        let heights = image.pixels.chunks_exact(4).map(|pixel| pixel[0] as f32 / 255.0).collect();
+        Ok(Self { width, depth, heights, scale })
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> f32 {
+        self.heights[z.min(self.depth - 1) * self.width + x.min(self.width - 1)]
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        (self.scale.x / (self.width - 1) as f32, self.scale.z / (self.depth - 1) as f32)
+    }
+
+    ///Where a sample sits, from the terrain's center
+    fn sample_position(&self, x: usize, z: usize) -> Vector3<f32> {
+        let (cell_x, cell_z) = self.cell_size();
+        Vector3::new(
+            x as f32 * cell_x - self.scale.x * 0.5,
+            self.height(x, z) * self.scale.y,
+            z as f32 * cell_z - self.scale.z * 0.5,
+        )
+    }
+
+    //Note(teddy) Central differences over the whole map, chunk edges share their normals and the seams don't show
+    fn normal(&self, x: usize, z: usize) -> Vector3<f32> {
+        let (cell_x, cell_z) = self.cell_size();
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+
+        let slope_x = (self.height(right, z) - self.height(left, z)) * self.scale.y / ((right - left) as f32 * cell_x);
+        let slope_z = (self.height(x, front) - self.height(x, back)) * self.scale.y / ((front - back) as f32 * cell_z);
+        Vector3::new(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    ///First and last sample of the chunk along x and z
+    fn chunk_samples(&self, chunk_x: usize, chunk_z: usize) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+        let (x0, z0) = (chunk_x * TERRAIN_CHUNK_CELLS, chunk_z * TERRAIN_CHUNK_CELLS);
+        (x0..=(x0 + TERRAIN_CHUNK_CELLS).min(self.width - 1), z0..=(z0 + TERRAIN_CHUNK_CELLS).min(self.depth - 1))
+    }
+
+    ///Center of the chunk from the terrain's center, at height 0
+    pub fn chunk_offset(&self, chunk_x: usize, chunk_z: usize) -> Vector3<f32> {
+        let (xs, zs) = self.chunk_samples(chunk_x, chunk_z);
+        let (cell_x, cell_z) = self.cell_size();
+        let center = |first: usize, last: usize, cell: f32, size: f32| (first + last) as f32 * 0.5 * cell - size * 0.5;
+
+        Vector3::new(center(*xs.start(), *xs.end(), cell_x, self.scale.x), 0.0, center(*zs.start(), *zs.end(), cell_z, self.scale.z))
+    }
+
+    pub fn chunks(&self, name: &str) -> Vec<TerrainChunk> {
+        let (chunks_x, chunks_z) = terrain_chunk_grid(self.width, self.depth);
+        (0..chunks_z)
+            .flat_map(|chunk_z| (0..chunks_x).map(move |chunk_x| (chunk_x, chunk_z)))
+            .map(|(chunk_x, chunk_z)| self.chunk(name, chunk_x, chunk_z))
+            .collect()
+    }
+
+    fn chunk(&self, name: &str, chunk_x: usize, chunk_z: usize) -> TerrainChunk {
+        let (xs, zs) = self.chunk_samples(chunk_x, chunk_z);
+        let (x0, x1, z0, z1) = (*xs.start(), *xs.end(), *zs.start(), *zs.end());
+        let (columns, rows) = (x1 - x0 + 1, z1 - z0 + 1);
+        let offset = self.chunk_offset(chunk_x, chunk_z);
+
+        let mut vertices = Vec::with_capacity(columns * rows);
+        let mut normals = Vec::with_capacity(columns * rows);
+        for z in zs.clone() {
+            for x in xs.clone() {
+                let position = self.sample_position(x, z) - offset;
+                vertices.push(Point4::new(position.x, position.y, position.z, 1.0));
+                normals.push(Point3::from(self.normal(x, z)));
+            }
+        }
+
+        //Note(teddy) Two triangles a cell, wound to face up
+        let mut indices = Vec::with_capacity((columns - 1) * (rows - 1) * 6);
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let corner = (row * columns + column) as u32;
+                let (right, below) = (corner + 1, corner + columns as u32);
+                indices.extend_from_slice(&[corner, below, right, right, below, below + 1]);
+            }
+        }
+
+        let heights = DMatrix::from_fn(rows, columns, |row, column| self.height(x0 + column, z0 + row));
+        let (cell_x, cell_z) = self.cell_size();
+        let size = Vector3::new((columns - 1) as f32 * cell_x, self.scale.y, (rows - 1) as f32 * cell_z);
+
+        TerrainChunk {
+            label: terrain_chunk_label(name, chunk_x, chunk_z),
+            mesh: NormalObj { vertices, normals, indices },
+            heightfield: HeightField::new(heights, size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_share_their_edges() {
+        let heightmap = Heightmap {
+            width: 130,
+            depth: 3,
+            heights: (0..130 * 3).map(|i| (i % 130) as f32 / 129.0).collect(),
+            scale: Vector3::new(129.0, 10.0, 2.0),
+        };
+
+        let chunks = heightmap.chunks("hill");
+        assert_eq!(chunks.iter().map(|chunk| chunk.label.as_str()).collect::<Vec<_>>(), vec!["hill#0_0", "hill#1_0", "hill#2_0"]);
+        assert_eq!(chunks[2].mesh.vertices.len(), 2 * 3);
+
+        //Note(teddy) The last column of one chunk is the first of the next
+        let edge = |chunk: usize, column: usize| chunks[chunk].mesh.vertices[column].xyz().coords + heightmap.chunk_offset(chunk, 0);
+        assert!((edge(0, TERRAIN_CHUNK_CELLS) - edge(1, 0)).norm() < 1e-4);
+        assert!((edge(2, 1) - Vector3::new(64.5, 10.0, -1.0)).norm() < 1e-4, "{:?}", edge(2, 1));
+
+        let slope = Vector3::new(-10.0 / 129.0, 1.0, 0.0).normalize();
+        assert!(chunks.iter().flat_map(|chunk| chunk.mesh.normals.iter()).all(|normal| (normal.coords - slope).norm() < 1e-4));
+    }
+}
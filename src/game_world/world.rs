@@ -1,35 +1,56 @@
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom};
 use std::sync::{Arc, Condvar, RwLock, Mutex};
-use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
-use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::{
-    collections::{HashMap, LinkedList},
+    collections::{HashMap, HashSet, LinkedList},
     ops::{Deref, DerefMut},
 };
 
-use nalgebra::{Isometry3, Vector3};
+use nalgebra::{Isometry3, Point3, Point4, Vector3};
+use ncollide3d::shape::HeightField;
 use ncollide3d::simba::scalar::SupersetOf;
 use nphysics3d::material::{BasicMaterial, MaterialHandle};
 use nphysics3d::object::BodyStatus;
 use serde::{Deserialize, Serialize};
 
 use super::components::*;
-use crate::core::{Engine, Event, EventManager, EventType};
-use crate::obj_parser::{load_obj, NormalObj, TexturedObj};
-use crate::renderer::shaders::create_shader;
+use super::prefab::{load_prefabs, Prefab, PrefabInstance, PREFAB_ASSETS_DIR};
+use super::mesh_dedup::MeshAliases;
+use super::terrain::{terrain_chunk_grid, terrain_chunk_label, Heightmap};
+use super::level_meta::{read_metadata, unix_now, update_metadata_on_save, LevelMetadata};
+use crate::core::{Engine, Event, EventManager, EventType, PhysicsCommand};
+use crate::systems::physics::PhysicsQueries;
+use crate::obj_parser::{load_obj, material_diffuse_maps, NormalObj, TexturedObj};
+use crate::renderer::draw::model_matrix;
+use crate::renderer::image_writer::{flip_rows, PixelFormat};
+use crate::renderer::png_decoder::{decode_png, png_size, DecodedImage};
+use crate::renderer::shaders::{create_shader, ShaderError, ShaderProgram};
+use crate::renderer::textures::{upload_texture, TextureParams};
 use crate::logs::LogManager;
 use crate::logs::Logable;
+use crate::utils::{crc32, write_atomic, Aabb};
 
-const WORLD_LEVELS_DIR: &'static str = "./assets/levels/";
+pub const WORLD_LEVELS_DIR: &'static str = "./assets/levels/";
 pub const OBJ_ASSETS_DIR: &'static str = "./assets/objects/";
-const SHADER_ASSETS_DIR: &'static str = "./assets/shaders/";
+pub const SHADER_ASSETS_DIR: &'static str = "./assets/shaders/";
 pub const FONT_ASSETS_DIR: &'static str = "./assets/fonts/";
+pub const TEXTURE_ASSETS_DIR: &'static str = "./assets/textures/";
+
+///Everything needed to create a renderable entity without going through the editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySpec {
+    pub mesh_label: String,
+    pub shader_label: String,
+    pub position: Vector3<f32>,
+    pub scale: f32,
+}
 
-static mut ENTITY_ID: usize = 0;
 pub const ENTITY_SIZE: usize = 100_000;
 pub type EntityID = usize;
+///Index into World::joints, never handed out twice
+pub type JointID = usize;
 
 pub enum MeshType {
     Textured(TexturedObj),
@@ -47,7 +68,32 @@ pub enum AssetSource {
 
     /// Name of shader, Vert, Frag, Option<Geo>
     Shader(String, String, String, Option<String>),
-    Texture(String),
+    ///File name in the textures folder and how it's sampled
+    Texture(String, TextureParams),
+    ///Terrain name, greyscale PNG in the textures folder and the terrain's size, see Heightmap
+    Heightmap(String, String, Vector3<f32>),
+}
+
+///Files in the shaders folder a shader is compiled from, kept so it can be compiled again when they change
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderSource {
+    pub vertex: String,
+    pub fragment: String,
+    pub geometry: Option<String>,
+}
+
+impl ShaderSource {
+    pub fn uses(&self, file_name: &str) -> bool {
+        self.vertex == file_name || self.fragment == file_name || self.geometry.as_deref() == Some(file_name)
+    }
+
+    pub unsafe fn compile(&self) -> Result<u32, ShaderError> {
+        create_shader(
+            format!("{}{}", SHADER_ASSETS_DIR, self.vertex),
+            format!("{}{}", SHADER_ASSETS_DIR, self.fragment),
+            self.geometry.as_ref().map(|geometry| format!("{}{}", SHADER_ASSETS_DIR, geometry)),
+        )
+    }
 }
 
 ///Enum used by add resource function
@@ -60,6 +106,10 @@ pub enum ResourceResult {
 
 pub struct Mesh {
     pub mesh_type: Option<MeshType>,
+    ///Model space bounds, used to cull the entities drawing the mesh
+    pub bounds: Option<Aabb>,
+    ///Collision shape of a terrain chunk, None for meshes loaded from obj files
+    pub heightfield: Option<HeightField<f32>>,
     is_loaded: bool,
 }
 
@@ -67,11 +117,17 @@ impl Mesh {
     fn new() -> Self {
         Self {
             mesh_type: None,
+            bounds: None,
+            heightfield: None,
             is_loaded: false,
         }
     }
 }
 
+pub fn mesh_bounds(vertices: &[Point4<f32>]) -> Option<Aabb> {
+    Aabb::from_points(vertices.iter().map(|vertex| vertex.xyz().coords))
+}
+
 struct ResourceLogs {
 
 }
@@ -89,11 +145,21 @@ impl Logable for ResourceLogs {
 }
 
 type MeshDataContainer = HashMap<String, Mesh>;
-type ShaderContainer = HashMap<String, Option<u32>>;
+///Programs by name, None while the shader is still being compiled
+type ShaderContainer = HashMap<String, Option<ShaderProgram>>;
+///Texture ids by file name, None until the main thread has uploaded it or when it failed to load
+type TextureContainer = HashMap<String, Option<u32>>;
 //Render component will hold the mesh id and a copy of the mesh's vertex data
 pub struct Resources {
     pub mesh_data: MeshDataContainer,
     pub shaders: ShaderContainer,
+    pub shader_sources: HashMap<String, ShaderSource>,
+    pub textures: TextureContainer,
+    ///Decoded by the loading thread, waiting for the main thread to upload them
+    pending_textures: Vec<(String, DecodedImage, TextureParams)>,
+    ///Opt-in, hash meshes as they load and alias labels whose data is identical to a resident mesh
+    pub dedup_meshes: bool,
+    pub mesh_aliases: MeshAliases,
 }
 
 impl Resources {
@@ -102,75 +168,172 @@ impl Resources {
         Self {
             mesh_data: HashMap::new(),
             shaders: HashMap::new(),
+            shader_sources: HashMap::new(),
+            textures: HashMap::new(),
+            pending_textures: vec![],
+            dedup_meshes: false,
+            mesh_aliases: MeshAliases::new(),
         }
     }
 
+    ///Mesh for the label, aliases resolve to the canonical mesh
+    pub fn mesh(&self, label: &str) -> Option<&Mesh> {
+        self.mesh_data.get(self.mesh_aliases.canonical(label))
+    }
 
-    pub fn add_resource(&mut self, resource: AssetSource, threaded: bool) {
+    ///Stores a loaded mesh. With dedup on, a duplicate of a resident mesh is stored as an alias without its data.
+    pub fn insert_mesh(&mut self, label: &str, mesh: NormalObj) {
+        let mesh_data = &self.mesh_data;
+        let canonical = if self.dedup_meshes {
+            self.mesh_aliases.register(label, &mesh, |resident| match mesh_data.get(resident) {
+                Some(Mesh { mesh_type: Some(MeshType::Normal(obj)), .. }) => Some(obj),
+                _ => None,
+            })
+        } else {
+            None
+        };
 
+        let bounds = mesh_bounds(&mesh.vertices);
+        let mesh_type = match canonical {
+            Some(canonical) => {
+                println!("Mesh {} is a duplicate of {}, merge the labels to drop it", label, canonical);
+                None
+            }
+            None => Some(MeshType::Normal(mesh)),
+        };
 
-        match resource {
-            AssetSource::Mesh(obj_type, location) => match obj_type {
-                ObjType::Normal => {
-                    let result = location.clone();
+        let entry = self.mesh_data.entry(label.to_string()).or_insert_with(Mesh::new);
+        entry.mesh_type = mesh_type;
+        entry.bounds = bounds;
+        entry.is_loaded = true;
+    }
 
 
-                    match self.mesh_data.get_mut(&result) {
-                        Some(mesh) if mesh.is_loaded => {
-                            return;
-                        }
+    ///Stores the mesh and heightfield of every chunk under the chunk's label
+    pub fn insert_terrain(&mut self, name: &str, heightmap: &Heightmap) {
+        for chunk in heightmap.chunks(name) {
+            self.insert_mesh(&chunk.label, chunk.mesh);
+            if let Some(mesh) = self.mesh_data.get_mut(&chunk.label) {
+                mesh.heightfield = Some(chunk.heightfield);
+            }
+        }
+    }
 
-                        None => {
-                            //Note(teddy) Mesh is not created
-                            self.mesh_data.insert(location.clone(), Mesh::new());
-                        }
+    pub fn texture(&self, name: &str) -> Option<u32> {
+        self.textures.get(name).copied().flatten()
+    }
 
-                        _ => unreachable!(),
-                    }
+    ///Uploads the textures the loading thread has decoded, gl calls can only happen on the main thread
+    pub unsafe fn upload_pending_textures(&mut self) {
+        for (name, image, params) in self.pending_textures.drain(..) {
+            let texture = upload_texture(&image, &params);
+            self.textures.insert(name, Some(texture));
+        }
+    }
 
-                    // drop(mesh_container); //Release lock
+    pub fn add_resource(&mut self, resource: AssetSource, threaded: bool) {
 
-                    //Note(teddy) Check whether the mesh already exists so that we can use the cached data
-                    let mesh: NormalObj =
-                        load_obj(format!("{}{}", OBJ_ASSETS_DIR, location).as_str()).unwrap();
 
-                    let mesh_type_ref = self.mesh_data.get_mut(&location).unwrap();
-                    mesh_type_ref.mesh_type = Some(MeshType::Normal(mesh));
-                    mesh_type_ref.is_loaded = true;
+        match resource {
+            AssetSource::Mesh(obj_type, location) => {
+                match self.mesh_data.get_mut(&location) {
+                    Some(mesh) if mesh.is_loaded => {
+                        return;
+                    }
+
+                    None => {
+                        //Note(teddy) Mesh is not created
+                        self.mesh_data.insert(location.clone(), Mesh::new());
+                    }
+
+                    _ => unreachable!(),
                 }
 
-                ObjType::Textured => (),
-            },
+                //Note(teddy) Check whether the mesh already exists so that we can use the cached data
+                let source = format!("{}{}", OBJ_ASSETS_DIR, location);
+                match obj_type {
+                    ObjType::Normal => {
+                        let mesh: NormalObj = load_obj(source.as_str()).unwrap();
+                        self.insert_mesh(&location, mesh);
+                    }
+
+                    //Note(teddy) Textured meshes skip the dedup, it only compares normal meshes
+                    ObjType::Textured => {
+                        let mesh: TexturedObj = load_obj(source.as_str()).unwrap();
+                        let entry = self.mesh_data.get_mut(&location).unwrap();
+                        entry.bounds = mesh_bounds(&mesh.vertices);
+                        entry.mesh_type = Some(MeshType::Textured(mesh));
+                        entry.is_loaded = true;
+                    }
+                }
+            }
 
             AssetSource::Shader(name, vertex, fragment, geo) => {
-                let copy_for_result = name.clone();
+                let source = ShaderSource { vertex, fragment, geometry: geo };
                 self.shaders.insert(name.clone(), None);
 
                 //TODO(teddy) Handle this error gracefully
-                let geometry_shader = match geo {
-                    Some(source) => Some(format!("{}{}", SHADER_ASSETS_DIR, source)),
-                    None => None,
-                };
-
-                let shader = unsafe {
-                    create_shader(
-                        format!("{}{}", SHADER_ASSETS_DIR, vertex),
-                        format!("{}{}", SHADER_ASSETS_DIR, fragment),
-                        geometry_shader,
-                    )
-                    .unwrap()
-                };
-
-                self.shaders.insert(name.clone(), Some(shader));
+                let shader = unsafe { source.compile().unwrap() };
+
+                self.shaders.insert(name.clone(), Some(ShaderProgram::new(shader)));
+                self.shader_sources.insert(name, source);
+            }
 
+            AssetSource::Texture(name, params) => {
+                if self.textures.contains_key(&name) {
+                    return;
+                }
+
+                self.textures.insert(name.clone(), None);
+                match load_texture_image(&name) {
+                    Ok(image) => self.pending_textures.push((name, image, params)),
+                    Err(error) => println!("Unable to load the texture {}: {}", name, error),
+                }
             }
 
-            AssetSource::Texture(_) => (),
+            AssetSource::Heightmap(name, image_name, scale) => {
+                let heightmap = std::fs::read(Path::new(TEXTURE_ASSETS_DIR).join(&image_name))
+                    .map_err(|error| format!("{}", error))
+                    .and_then(|bytes| decode_png(&bytes))
+                    .and_then(|image| Heightmap::from_image(&image, scale));
+
+                match heightmap {
+                    Ok(heightmap) => self.insert_terrain(&name, &heightmap),
+                    Err(error) => println!("Unable to load the heightmap {}: {}", image_name, error),
+                }
+            }
         }
 
     }
 }
 
+///Default material with the diffuse map the mesh's mtl files point at, if any
+pub fn mesh_material(mesh_label: &str) -> Material {
+    let diffuse_map = material_diffuse_maps(&format!("{}{}", OBJ_ASSETS_DIR, mesh_label)).into_iter().next();
+    Material { diffuse_map, ..Material::default() }
+}
+
+///Decodes a texture to RGBA with the bottom row first, the way gl expects it
+fn load_texture_image(name: &str) -> Result<DecodedImage, String> {
+    let path = Path::new(TEXTURE_ASSETS_DIR).join(name);
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+    if extension == "jpg" || extension == "jpeg" {
+        return Err(String::from("JPEG textures are not supported yet, save the texture as a PNG"));
+    }
+
+    let bytes = std::fs::read(&path).map_err(|error| format!("{}", error))?;
+    let mut image = decode_png(&bytes)?;
+    image.pixels = flip_rows(&image.pixels, image.width, image.height, PixelFormat::Rgba8);
+    Ok(image)
+}
+
+///Assets handed to the loading thread and how many of them it has loaded, both go back to 0 once it catches up
+#[derive(Default)]
+struct ResourceProgress {
+    queued: AtomicUsize,
+    loaded: AtomicUsize,
+}
+
 const GAME_WORLD_FILE_NAME: &'static str = "game_world";
 pub struct World {
     event_manager: *mut EventManager,
@@ -179,7 +342,20 @@ pub struct World {
     pub components: Components,
     pub entities: LinkedList<EntityID>,
     pub deleted_entities: LinkedList<EntityID>,
-    pub resource_queue: Arc<(Mutex<LinkedList<AssetSource>>, Condvar)>
+    pub resource_queue: Arc<(Mutex<LinkedList<AssetSource>>, Condvar)>,
+    resource_progress: Arc<ResourceProgress>,
+    ///Sidecar of the level that was last saved or loaded, author and description are kept across saves
+    pub metadata: LevelMetadata,
+    ///Where save writes the level and its sidecars, the level picker lists this directory
+    level_path: PathBuf,
+    pub prefabs: HashMap<String, Prefab>,
+    ///Removed joints leave a None behind so the ids in flight stay valid
+    pub joints: Vec<Option<Joint>>,
+    ///Overlap tests and shape casts against the physics colliders, None until the physics system has started
+    pub physics: Option<PhysicsQueries>,
+    ///Chunk entities made by add_terrain. Their meshes are built from the heightmap and can't be loaded
+    ///back by label, so they're left out of the level file.
+    terrain_chunks: HashSet<EntityID>,
 }
 
 impl World {
@@ -191,7 +367,14 @@ impl World {
             components: Components::new(ENTITY_SIZE),
             entities: LinkedList::new(),
             deleted_entities: LinkedList::new(),
-            resource_queue: Arc::new((Mutex::new(LinkedList::new()), Condvar::new()))
+            resource_queue: Arc::new((Mutex::new(LinkedList::new()), Condvar::new())),
+            resource_progress: Arc::new(ResourceProgress::default()),
+            metadata: LevelMetadata::default(),
+            level_path: Path::new(WORLD_LEVELS_DIR).join(GAME_WORLD_FILE_NAME),
+            prefabs: load_prefabs(Path::new(PREFAB_ASSETS_DIR)),
+            joints: vec![],
+            physics: None,
+            terrain_chunks: HashSet::new(),
         }
     }
 
@@ -204,8 +387,8 @@ impl World {
             }
 
             None => {
-                let new_id = unsafe { ENTITY_ID };
-                unsafe { ENTITY_ID += 1 };
+                //Note(teddy) Every id ever handed out has an entry, the next one is the end of the component arrays
+                let new_id = self.components.physics.len();
                 self.entities.push_back(new_id);
                 self.components.create_entry();
                 new_id
@@ -217,45 +400,194 @@ impl World {
         id
     }
 
+    ///Takes the entity out of the world and empties its components, the id is handed out again by
+    ///create_entity. The systems let go of its mesh and body when they see EntityRemoved.
+    pub fn delete_entity(&mut self, id: EntityID) {
+        let count = self.entities.len();
+        self.entities = std::mem::take(&mut self.entities).into_iter().filter(|entity| *entity != id).collect();
+        if self.entities.len() == count {
+            return;
+        }
+
+        //Note(teddy) Cleared right away, the systems keep their own handles to free what the entity had
+        self.components.clear_entry(id);
+        self.deleted_entities.push_back(id);
+        self.terrain_chunks.remove(&id);
+
+        let event_manager = unsafe { self.event_manager.as_mut().unwrap() };
+        event_manager.add_event(Event::new(EventType::EntityRemoved(id)));
+
+        let attached: Vec<JointID> = self
+            .joints
+            .iter()
+            .enumerate()
+            .filter(|(_, joint)| joint.map_or(false, |joint| joint.a == id || joint.b == id))
+            .map(|(joint_id, _)| joint_id)
+            .collect();
+        for joint_id in attached {
+            self.remove_joint(joint_id);
+        }
+    }
+
+    ///The physics system builds the constraint once both entities have a body
+    pub fn add_joint(&mut self, a: EntityID, b: EntityID, spec: JointSpec) -> JointID {
+        let id = self.joints.len();
+        self.joints.push(Some(Joint { a, b, spec }));
+
+        let event_manager = unsafe { self.event_manager.as_mut().unwrap() };
+        event_manager.add_event(Event::new(EventType::JointAdded(id)));
+        id
+    }
+
+    pub fn remove_joint(&mut self, id: JointID) {
+        if self.joints.get_mut(id).and_then(|joint| joint.take()).is_none() {
+            return;
+        }
+
+        let event_manager = unsafe { self.event_manager.as_mut().unwrap() };
+        event_manager.add_event(Event::new(EventType::JointRemoved(id)));
+    }
+
+    pub fn apply_impulse(&mut self, id: EntityID, impulse: Vector3<f32>) {
+        self.send_physics_command(id, PhysicsCommand::ApplyImpulse(impulse));
+    }
+
+    ///The force only lasts for the next physics step
+    pub fn apply_force(&mut self, id: EntityID, force: Vector3<f32>) {
+        self.send_physics_command(id, PhysicsCommand::ApplyForce(force));
+    }
+
+    pub fn set_linear_velocity(&mut self, id: EntityID, velocity: Vector3<f32>) {
+        self.send_physics_command(id, PhysicsCommand::SetLinearVelocity(velocity));
+    }
+
+    pub fn set_angular_velocity(&mut self, id: EntityID, velocity: Vector3<f32>) {
+        self.send_physics_command(id, PhysicsCommand::SetAngularVelocity(velocity));
+    }
+
+    pub fn wake_up(&mut self, id: EntityID) {
+        self.send_physics_command(id, PhysicsCommand::WakeUp);
+    }
+
+    fn send_physics_command(&mut self, entity: EntityID, command: PhysicsCommand) {
+        let event_manager = unsafe { self.event_manager.as_mut().unwrap() };
+        event_manager.add_event(Event::new(EventType::Physics { entity, command }));
+    }
+
+
+    ///Creates an entity from a spec and queues its mesh for loading
+    pub fn spawn(&mut self, spec: &EntitySpec) -> EntityID {
+        let id = self.create_entity();
+        let material = mesh_material(&spec.mesh_label);
+        self.add_mesh(&spec.mesh_label, &material);
+
+        let mut render_component = RenderComponent::new(spec.mesh_label.clone(), spec.shader_label.clone());
+        render_component.material = material;
+        self.components.renderables[id] = Some(render_component);
+        self.components.positionable[id] = Some(TransformComponent::new(spec.position, Vector3::y(), spec.scale));
+        id
+    }
+
+    ///Queues the mesh and the material's textures, a mesh with a diffuse map is loaded with its texture coordinates
+    pub fn add_mesh(&mut self, mesh_label: &str, material: &Material) {
+        let obj_type = if material.diffuse_map.is_some() { ObjType::Textured } else { ObjType::Normal };
+        self.add_resource(AssetSource::Mesh(obj_type, mesh_label.to_string()));
+
+        for texture in material.textures().iter() {
+            self.add_resource(AssetSource::Texture(texture.clone(), TextureParams::default()));
+        }
+    }
+
+    ///Creates a static entity for every chunk of the heightmap, centered on position, and queues their meshes.
+    ///Only the image size is read here, the loading thread builds the chunks.
+    pub fn add_terrain(
+        &mut self,
+        name: &str,
+        image_name: &str,
+        scale: Vector3<f32>,
+        position: Vector3<f32>,
+        shader_label: &str,
+    ) -> Result<Vec<EntityID>, String> {
+        let bytes = std::fs::read(Path::new(TEXTURE_ASSETS_DIR).join(image_name)).map_err(|error| format!("{}", error))?;
+        let (width, depth) = png_size(&bytes)?;
+        if width < 2 || depth < 2 {
+            return Err(format!("A heightmap needs at least 2x2 pixels, got {}x{}", width, depth));
+        }
+
+        //Note(teddy) Where the chunk centers end up depends on the heights only through y, which stays 0
+        let layout = Heightmap { width: width as usize, depth: depth as usize, heights: vec![0.0; (width * depth) as usize], scale };
+        self.add_resource(AssetSource::Heightmap(name.to_string(), image_name.to_string(), scale));
+
+        let (chunks_x, chunks_z) = terrain_chunk_grid(layout.width, layout.depth);
+        let ids = (0..chunks_z)
+            .flat_map(|z| (0..chunks_x).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let offset = layout.chunk_offset(x, z);
+                let id = self.create_entity();
+                self.components.renderables[id] = Some(RenderComponent::new(terrain_chunk_label(name, x, z), shader_label.to_string()));
+                self.components.positionable[id] = Some(TransformComponent::new(position + offset, Vector3::zeros(), 1.0));
+
+                let mut physics = PhysicsComponent::new(
+                    0.0,
+                    false,
+                    BodyStatus::Static,
+                    Vector3::zeros(),
+                    MaterialHandle::new(BasicMaterial::new(0.0, 0.5)),
+                );
+                physics.shape = ColliderShape::HeightField;
+                self.components.physics[id] = Some(physics);
+                id
+            })
+            .collect::<Vec<EntityID>>();
+        self.terrain_chunks.extend(ids.iter().copied());
+
+        Ok(ids)
+    }
+
+    ///World space bounds of the entity's mesh, None until the mesh has loaded
+    pub fn entity_world_aabb(&self, id: EntityID) -> Option<Aabb> {
+        let render = self.components.renderables.get(id)?.as_ref()?;
+        let transform = self.components.positionable.get(id)?.as_ref()?;
+        let bounds = self.resources.read().unwrap().mesh(&render.mesh_label)?.bounds?;
+
+        Some(bounds.transformed(&model_matrix(transform)))
+    }
 
     pub fn add_resource(&mut self, resource: AssetSource) {
         match resource {
             AssetSource::Shader(name, vertex, fragment, geo) => {
                 let mut resource_manager = self.resources.write().unwrap();
-                let copy_for_result = name.clone();
+                let source = ShaderSource { vertex, fragment, geometry: geo };
                 resource_manager.shaders.insert(name.clone(), None);
 
                 //TODO(teddy) Handle this error gracefully
-                let geometry_shader = match geo {
-                    Some(source) => Some(format!("{}{}", SHADER_ASSETS_DIR, source)),
-                    None => None,
-                };
-
-                let shader = unsafe {
-                    create_shader(
-                        format!("{}{}", SHADER_ASSETS_DIR, vertex),
-                        format!("{}{}", SHADER_ASSETS_DIR, fragment),
-                        geometry_shader,
-                    )
-                    .unwrap()
-                };
-
-                resource_manager.shaders.insert(name.clone(), Some(shader));
+                let shader = unsafe { source.compile().unwrap() };
 
+                resource_manager.shaders.insert(name.clone(), Some(ShaderProgram::new(shader)));
+                resource_manager.shader_sources.insert(name, source);
             }
 
             _ => {
                 let (mutex, cond) = &*self.resource_queue;
                 let mut resource_queue = mutex.lock().unwrap();
                 resource_queue.push_back(resource);
+                self.resource_progress.queued.fetch_add(1, Ordering::SeqCst);
                 cond.notify_one();
             }
         }
     }
 
+    ///Loaded and total counts of the assets queued since the loading thread last caught up
+    pub fn resource_queue_progress(&self) -> (usize, usize) {
+        let loaded = self.resource_progress.loaded.load(Ordering::SeqCst);
+        let queued = self.resource_progress.queued.load(Ordering::SeqCst);
+        (loaded.min(queued), queued)
+    }
+
     pub fn init_resource_loading_thread(&self) {
         let resource_queue_ref = Arc::clone(&self.resource_queue);
         let resources_ref = Arc::clone(&self.resources);
+        let progress = Arc::clone(&self.resource_progress);
 
         std::thread::spawn(move || {
             let (mutex, cond) = &*resource_queue_ref;
@@ -266,21 +598,41 @@ impl World {
                 let mut resource_manager = resources_ref.write().unwrap();
             
                 while let Some(item) = resource_queue.pop_front() {
-                    resource_manager.add_resource(item, false)
+                    resource_manager.add_resource(item, false);
+                    progress.loaded.fetch_add(1, Ordering::SeqCst);
                 }
+
+                //Note(teddy) Still holding the queue, nothing can be queued between the two stores
+                progress.queued.store(0, Ordering::SeqCst);
+                progress.loaded.store(0, Ordering::SeqCst);
             }
         });
     }
 
     pub fn save(&mut self) {
-        let mut world_entities = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(GAME_WORLD_FILE_NAME)
-            .unwrap();
-        let header = StorageFileHeader{ total_entities: self.entities.len() as u32 };
-        let entity_objects = self.entities.iter().map(|entity_id| {
+        if let Err(error) = write_atomic(&self.level_path, &self.level_bytes()) {
+            println!("Failed to save the level: {}", error);
+            return;
+        }
+        println!("Entities written to the disk");
+        self.save_metadata();
+    }
+
+    pub fn level_path(&self) -> &Path {
+        &self.level_path
+    }
+
+    pub fn save_metadata(&mut self) {
+        match update_metadata_on_save(&self.level_path, &self.metadata, self.saved_entities().len() as u32, unix_now()) {
+            Ok(metadata) => self.metadata = metadata,
+            Err(error) => println!("Failed to write the level metadata: {}", error),
+        }
+    }
+
+    ///The level file contents, header followed by the raw entity records and then the joint section
+    pub fn level_bytes(&self) -> Vec<u8> {
+        let saved = self.saved_entities();
+        let entity_objects = saved.iter().map(|entity_id| {
             Entity {
                 transform: if let Some(transform_component) = &self.components.positionable[*entity_id] {
                     TransformData {
@@ -305,131 +657,257 @@ impl World {
                     assert!(render_component.shader_label.len() <= 1024,"{}", true);
                     assert!(render_component.shader_label.len() <= 1024, "{}", true);
 
-                    let textures_labels: Vec<[u8; 1024]> =  render_component.textures
-                        .iter()
-                        .map(|a| {
-                            copy_string_to_bytes(a)
-                        }).collect();
-
+                    let material = &render_component.material;
                     let mut textures: [[u8; 1024]; 8] = [[0; 1024]; 8];
-                    for (i, label) in textures_labels.iter().enumerate() {
-                        textures[i] = *label
+                    for (slot, label) in [(DIFFUSE_MAP_SLOT, &material.diffuse_map), (NORMAL_MAP_SLOT, &material.normal_map)].iter() {
+                        if let Some(label) = label {
+                            textures[*slot] = copy_string_to_bytes(label);
+                        }
                     }
 
                     RenderData {
                         is_present: 1,
                         mesh: copy_string_to_bytes(&render_component.mesh_label),
                         shader: copy_string_to_bytes(&render_component.shader_label),
-                        textures: textures
+                        textures: textures,
+                        flags: render_component.flags.0,
+                        base_color: material.base_color,
+                        specular_strength: material.specular_strength,
+                        shininess: material.shininess,
+                        opacity: material.opacity,
                     }
                 } else {
                     RenderData::default()
                 },
 
-                // physics: if let Some(physics_data) = &self.components.physics[*entity_id] {
-                //     PhysicsData::default()
-                // } else {
-                //     PhysicsData::default()
-                // }
+                rope: if let Some(rope_component) = &self.components.ropes[*entity_id] {
+                    let (start_kind, start_entity, start_point) = self.rope_anchor_to_data(&rope_component.start);
+                    let (end_kind, end_entity, end_point) = self.rope_anchor_to_data(&rope_component.end);
+
+                    RopeData {
+                        is_present: 1,
+                        start_kind,
+                        end_kind,
+                        collide: rope_component.collide as u8,
+                        start_entity,
+                        end_entity,
+                        start_point,
+                        end_point,
+                        segments: rope_component.segments as u32,
+                        length: rope_component.length,
+                        iterations: rope_component.iterations,
+                        thickness: rope_component.thickness,
+                        shader: copy_string_to_bytes(&rope_component.shader_label),
+                    }
+                } else {
+                    RopeData::default()
+                },
+
+                prefab: match &self.components.prefabs[*entity_id] {
+                    Some(instance) => PrefabData {
+                        is_present: 1,
+                        name: copy_string_to_bytes(&instance.prefab),
+                        overrides: instance.overrides,
+                    },
+                    None => PrefabData::default(),
+                },
+
+                physics: match &self.components.physics[*entity_id] {
+                    Some(physics) => PhysicsData::from_component(physics),
+                    None => PhysicsData::default(),
+                },
             }
         }).collect();
-        write_entity_to_disk(&mut world_entities, header, entity_objects);
 
+        let mut payload = entities_to_bytes(entity_objects);
+        payload.extend(self.joints_to_bytes(&saved));
+        level_file_bytes(saved.len() as u32, payload)
     }
 
-    pub fn load_world(&mut self) { 
-        let SIZE_OF_HEADER: usize = std::mem::size_of::<StorageFileHeader>();
-        let SIZE_OF_ENTITY: usize = std::mem::size_of::<Entity>();
-
-        let world_entities_file = File::open(GAME_WORLD_FILE_NAME).unwrap();
-        let mut buffered_reader = BufReader::new(world_entities_file);
+    ///Entities written to the level file, in the order they're written
+    fn saved_entities(&self) -> Vec<EntityID> {
+        self.entities.iter().copied().filter(|id| !self.terrain_chunks.contains(id)).collect()
+    }
 
-        //Read the entire file to buffer
-        let mut temp_buffer = vec![];
+    ///Joint count followed by the JointData records, joints to entities that aren't saved are left out
+    fn joints_to_bytes(&self, saved: &[EntityID]) -> Vec<u8> {
+        let index = |id: EntityID| saved.iter().position(|entity| *entity == id).map(|index| index as u32);
+        let joints: Vec<JointData> = self
+            .joints
+            .iter()
+            .flatten()
+            .filter_map(|joint| Some(JointData::from_joint(joint, index(joint.a)?, index(joint.b)?)))
+            .collect();
 
-        let write_ref = unsafe {
-            (&buffered_reader as *const BufReader<File> as *mut BufReader<File>)
-                    .as_mut()
-                    .unwrap()
-        };
-        while let Ok(buf) = buffered_reader.fill_buf() {
-            temp_buffer.extend_from_slice(&buf);
-            if buf.len() == 0 {
-                break;
-            }
-            write_ref.consume(buf.len());
+        let mut bytes = (joints.len() as u32).to_ne_bytes().to_vec();
+        for joint in joints.iter() {
+            bytes.extend_from_slice(unsafe { any_as_u8_slice(joint) });
         }
+        bytes
+    }
 
+    ///Recreates the joints of a joint section, levels saved before joints existed have none
+    fn load_joints(&mut self, bytes: &[u8], loaded_ids: &Vec<EntityID>) {
+        const SIZE_OF_COUNT: usize = std::mem::size_of::<u32>();
+        const SIZE_OF_JOINT: usize = std::mem::size_of::<JointData>();
 
-        let file_header_buffer = &temp_buffer[0..SIZE_OF_HEADER];
-        //Loading the file header to obtain configurations for the world
+        let count = match bytes.get(0..SIZE_OF_COUNT) {
+            Some(count) => u32::from_ne_bytes([count[0], count[1], count[2], count[3]]) as usize,
+            None => return,
+        };
 
-        let file_header: MaybeUninit<StorageFileHeader> = MaybeUninit::zeroed();
-        let mut storage_header = unsafe { file_header.assume_init() };
-        let storage_header_ptr: *mut StorageFileHeader = &mut storage_header;
+        for record in bytes[SIZE_OF_COUNT..].chunks_exact(SIZE_OF_JOINT).take(count) {
+            //Note(teddy) The section starts right after the entities, nothing keeps it aligned
+            let data = unsafe { std::ptr::read_unaligned(record.as_ptr() as *const JointData) };
+            match (loaded_ids.get(data.a as usize), loaded_ids.get(data.b as usize)) {
+                (Some(a), Some(b)) => {
+                    self.add_joint(*a, *b, data.to_spec());
+                }
+                _ => println!("Skipping a joint to a missing entity: {:?}", data),
+            }
+        }
+    }
 
+    ///Loads the level file on top of the current entities, nothing is created when the file doesn't check out
+    pub fn load_world(&mut self) -> Result<(), WorldError> {
+        let path = self.level_path.clone();
+        self.load_level(&path)
+    }
 
-        unsafe {
-            std::ptr::copy(
-                file_header_buffer.as_ptr(), 
-                storage_header_ptr as *mut u8, 
-                std::mem::size_of_val(&file_header_buffer)
-            )
-        };
-        buffered_reader.consume(SIZE_OF_HEADER);
+    ///Same as load_world for another level file, later saves go to that file
+    pub fn load_level(&mut self, path: &Path) -> Result<(), WorldError> {
+        let bytes = std::fs::read(path).map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => WorldError::LevelNotFound,
+            _ => WorldError::FailedToOpenLevel,
+        })?;
 
-        //Loading the entities
-        //
+        self.load_level_bytes(&bytes)?;
+        self.metadata = read_metadata(path).unwrap_or(None).unwrap_or_default();
+        self.level_path = path.to_path_buf();
+        Ok(())
+    }
 
+    ///Recreates the entities and joints of a level file's contents, the counterpart of level_bytes
+    pub fn load_level_bytes(&mut self, bytes: &[u8]) -> Result<(), WorldError> {
+        let SIZE_OF_ENTITY: usize = std::mem::size_of::<Entity>();
+        let (storage_header, payload) = read_level_header(bytes)?;
+
+        //Note(teddy) The payload is only byte aligned, every record is copied out before it's read
+        let loaded_entities: Vec<Entity> = payload
+            .chunks_exact(SIZE_OF_ENTITY)
+            .take(storage_header.total_entities as usize)
+            .map(|record| unsafe { std::ptr::read_unaligned(record.as_ptr() as *const Entity) })
+            .collect();
+
+        //Note(teddy) A broken record takes the entities loaded before it along, the level is left as it was
+        let mut loaded_ids = vec![];
+        let mut rope_shaders = vec![];
+        for entity in loaded_entities.iter() {
+            let loaded = self.create_loaded_entity(entity).and_then(|id| {
+                loaded_ids.push(id);
+                match entity.rope.is_present {
+                    1 => label_from_bytes(&entity.rope.shader).map(Some),
+                    _ => Ok(None),
+                }
+            });
 
+            match loaded {
+                Ok(shader) => rope_shaders.push(shader),
+                Err(error) => {
+                    loaded_ids.into_iter().for_each(|id| self.delete_entity(id));
+                    return Err(WorldError::UnableToParseFile(error));
+                }
+            }
+        }
 
-        let entities_data_buffer = &temp_buffer[SIZE_OF_HEADER..];
+        //Note(teddy) Rope anchors reference other entities by their index in the file,
+        //so they can only be resolved once every entity has been recreated
+        for ((entity, new_entity), shader_label) in loaded_entities.iter().zip(loaded_ids.iter()).zip(rope_shaders) {
+            let (rope, shader_label) = match shader_label {
+                Some(shader_label) => (&entity.rope, shader_label),
+                None => continue,
+            };
 
-        dbg!(entities_data_buffer.len());
-        dbg!(storage_header.total_entities);
-        dbg!(entities_data_buffer.len() as f32 / SIZE_OF_ENTITY as f32);
+            let start = rope_anchor_from_data(rope.start_kind, rope.start_entity, rope.start_point, &loaded_ids);
+            let end = rope_anchor_from_data(rope.end_kind, rope.end_entity, rope.end_point, &loaded_ids);
+
+            let mut rope_component = RopeComponent::new(
+                start,
+                end,
+                rope.segments as usize,
+                rope.length,
+                rope.iterations,
+                rope.thickness,
+                shader_label,
+            );
+            rope_component.collide = rope.collide == 1;
+            self.components.ropes[*new_entity] = Some(rope_component);
+        }
 
-        let loaded_entities: &[Entity] = unsafe { std::mem::transmute::<&[u8], &[Entity]>(entities_data_buffer) };
+        let joints_offset = storage_header.total_entities as usize * SIZE_OF_ENTITY;
+        self.load_joints(&payload[joints_offset..], &loaded_ids);
+        Ok(())
+    }
 
-        for i in 0..storage_header.total_entities {
-            // dbg!(&loaded_entities[i as usize].transform);
-            println!("{:?}", loaded_entities[i as usize]);
-            self.create_loaded_entity(&loaded_entities[i as usize]).unwrap();
+    fn rope_anchor_to_data(&self, anchor: &RopeAnchor) -> (u8, u32, [f32; 3]) {
+        match anchor {
+            RopeAnchor::Point(point) => (0, 0, [point.x, point.y, point.z]),
+            RopeAnchor::Entity(id) => {
+                let index = self.entities.iter().position(|e| e == id).unwrap_or(0);
+                (1, index as u32, [0.0; 3])
+            }
         }
     }
 
-    fn create_loaded_entity(&mut self, entity: &Entity) -> Result<(), String> {
+    fn create_loaded_entity(&mut self, entity: &Entity) -> Result<EntityID, String> {
 
-        let new_entity = self.create_entity();
+        //Note(teddy) Labels are read before the entity is made, a broken one leaves nothing behind
+        let render_labels = match entity.render.is_present {
+            1 => Some((label_from_bytes(&entity.render.mesh)?, label_from_bytes(&entity.render.shader)?)),
+            _ => None,
+        };
+        let prefab_name = match entity.prefab.is_present {
+            1 => Some(label_from_bytes(&entity.prefab.name)?),
+            _ => None,
+        };
 
-        if entity.render.is_present == 1 {
-            let truncate_zeros = |it:[u8; 1024]|  {
-                it.into_iter()
-                    .filter(|c| **c != 0)
-                    .map(|c| *c)
-                    .collect::<Vec<u8>>()
-            };
+        let new_entity = self.create_entity();
 
-            let mesh_label_bytes = truncate_zeros(entity.render.mesh);
-            let shader_label_bytes = truncate_zeros(entity.render.shader);
+        if let Some((mesh_label, shader_label)) = render_labels {
+            //TODO(teddy) Not sure about how the mesh ids work
 
-            let mesh_label = unsafe {
-                String::from_utf8(mesh_label_bytes).unwrap()
+            let texture = |slot: usize| {
+                label_from_bytes(&entity.render.textures[slot])
+                    .ok()
+                    .filter(|label| !label.is_empty())
             };
-            self.add_resource(AssetSource::Mesh(ObjType::Normal, mesh_label.clone()));
-            let shader_label = unsafe {
-                String::from_utf8(shader_label_bytes).unwrap()
+            let material = Material {
+                base_color: entity.render.base_color,
+                specular_strength: entity.render.specular_strength,
+                shininess: entity.render.shininess,
+                opacity: entity.render.opacity,
+                diffuse_map: texture(DIFFUSE_MAP_SLOT),
+                normal_map: texture(NORMAL_MAP_SLOT),
             };
-            //TODO(teddy) Not sure about how the mesh ids work
+            self.add_mesh(&mesh_label, &material);
 
             println!("Reached here");
-            self.components.renderables[new_entity] = Some(
-                RenderComponent::new(
-                    mesh_label.clone(),
-                    shader_label,
-                )
-            );
+            let mut render_component = RenderComponent::new(mesh_label.clone(), shader_label);
+            render_component.flags = RenderFlags(entity.render.flags);
+            render_component.material = material;
+            self.components.renderables[new_entity] = Some(render_component);
+
+        }
 
+        if entity.physics.is_present == 1 {
+            self.components.physics[new_entity] = Some(entity.physics.to_component());
+        }
+
+        if let Some(name) = prefab_name {
+            self.components.prefabs[new_entity] = Some(PrefabInstance {
+                prefab: name,
+                overrides: entity.prefab.overrides,
+            });
         }
 
         if entity.transform.is_present == 1 {
@@ -445,10 +923,23 @@ impl World {
             )
         }
 
-        Ok(())
+        Ok(new_entity)
+    }
+}
+
+fn rope_anchor_from_data(kind: u8, index: u32, point: [f32; 3], loaded_ids: &Vec<EntityID>) -> RopeAnchor {
+    match loaded_ids.get(index as usize) {
+        Some(id) if kind == 1 => RopeAnchor::Entity(*id),
+        _ => RopeAnchor::Point(Point3::new(point[0], point[1], point[2])),
     }
 }
 
+///The label stored in a fixed size field, with the zero padding dropped
+fn label_from_bytes(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.iter().filter(|c| **c != 0).copied().collect())
+        .map_err(|error| format!("label isn't valid utf-8, {}", error))
+}
+
 fn copy_string_to_bytes(string: &String) -> [u8; 1024] {
 
     let mut mesh_data_output: [u8; 1024] = [0; 1024];
@@ -460,32 +951,72 @@ fn copy_string_to_bytes(string: &String) -> [u8; 1024] {
 }
 
 #[inline(always)]
-fn write_entity_to_disk(
-    file: &mut File,
-    header: StorageFileHeader,
-    entities: Vec<Entity>
-) {
-
-    //Note(teddy) writing the file headers
-    let file_header_data = unsafe { std::slice::from_raw_parts( &header as *const _ as *const u8, std::mem::size_of::<StorageFileHeader>()) };
-
-    let entity_data = unsafe { 
+fn entities_to_bytes(entities: Vec<Entity>) -> Vec<u8> {
+    unsafe { 
         entities
             .iter()
             .flat_map(|e: &Entity| any_as_u8_slice(e))
             .map(|byte| *byte)
             .collect::<Vec<u8>>() 
+    }
+}
+
+///Puts the header in front of the payload, the entity records followed by the joint section
+fn level_file_bytes(total_entities: u32, payload: Vec<u8>) -> Vec<u8> {
+    let header = StorageFileHeader {
+        magic: SAVE_FILE_MAGIC,
+        version: SAVE_FILE_VERSION,
+        entity_size: std::mem::size_of::<Entity>() as u32,
+        total_entities,
+        payload_size: payload.len() as u32,
+        checksum: crc32(&payload),
     };
 
-    let file_data = file_header_data
-        .into_iter()
-        .chain(entity_data.iter())
-        .map(|byte| *byte)
-        .collect::<Vec<u8>>();
-    file.write_all(file_data.as_slice()).unwrap();
-    println!("Entities written to the disk");
+    let mut bytes = unsafe { any_as_u8_slice(&header) }.to_vec();
+    bytes.extend(payload);
+    bytes
 }
 
+///Checks the header against this build and the payload against the header, before anything is read from it
+fn read_level_header(bytes: &[u8]) -> Result<(StorageFileHeader, &[u8]), WorldError> {
+    let SIZE_OF_HEADER: usize = std::mem::size_of::<StorageFileHeader>();
+    let header_bytes = bytes.get(0..SIZE_OF_HEADER).ok_or(WorldError::Truncated { expected: SIZE_OF_HEADER, found: bytes.len() })?;
+    if header_bytes[..4] != SAVE_FILE_MAGIC {
+        return Err(WorldError::NotALevelFile);
+    }
+
+    let header = unsafe { std::ptr::read_unaligned(header_bytes.as_ptr() as *const StorageFileHeader) };
+    if header.version != SAVE_FILE_VERSION {
+        return Err(WorldError::UnsupportedVersion { found: header.version, expected: SAVE_FILE_VERSION });
+    }
+
+    let entity_size = std::mem::size_of::<Entity>();
+    if header.entity_size as usize != entity_size {
+        return Err(WorldError::EntitySizeMismatch { found: header.entity_size as usize, expected: entity_size });
+    }
+
+    //Note(teddy) The payload has to hold every entity record the header counts, the joint section comes after
+    let payload = &bytes[SIZE_OF_HEADER..];
+    let entities_size = (header.total_entities as usize).checked_mul(entity_size);
+    let payload_size = header.payload_size as usize;
+    match entities_size {
+        Some(entities_size) if entities_size <= payload_size => (),
+        _ => {
+            let expected = SIZE_OF_HEADER.saturating_add(entities_size.unwrap_or(usize::MAX));
+            return Err(WorldError::Truncated { expected, found: SIZE_OF_HEADER + payload_size });
+        }
+    }
+    if payload.len() < payload_size {
+        return Err(WorldError::Truncated { expected: SIZE_OF_HEADER + payload_size, found: bytes.len() });
+    }
+
+    let payload = &payload[..header.payload_size as usize];
+    if crc32(payload) != header.checksum {
+        return Err(WorldError::ChecksumMismatch);
+    }
+
+    Ok((header, payload))
+}
 
 unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
     std::slice::from_raw_parts(
@@ -494,9 +1025,24 @@ unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
         )
 }
 
+///"IMRW" at the start of every level file
+const SAVE_FILE_MAGIC: [u8; 4] = *b"IMRW";
+//Note(teddy) Bump it whenever Entity or the data structs in it change, the records are read back as they are.
+//The level sidecars record it too so the picker can flag old levels. 2 added the entity size, payload size
+//and checksum to the header, 3 is past the 2 that sidecars kept writing while the entity layout changed
+pub const SAVE_FILE_VERSION: u32 = 3;
+
 #[repr(C)]
 pub struct StorageFileHeader {
+    magic: [u8; 4],
+    version: u32,
+    ///Size of an Entity record in the build that wrote the file
+    entity_size: u32,
     total_entities: u32,
+    ///Bytes after the header, the entity records and the joint section
+    payload_size: u32,
+    ///crc32 of the payload
+    checksum: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -520,7 +1066,9 @@ pub struct Level {
 struct Entity {
     transform: TransformData,
     render: RenderData,
-    // physics: PhysicsData,
+    rope: RopeData,
+    prefab: PrefabData,
+    physics: PhysicsData,
 }
 
 
@@ -540,7 +1088,9 @@ enum Body {
     Dynamic = 2,
 }
 
-
+//Note(teddy) layer: 0 = world, 1 = trigger, 2 = editor helper, 3 = debris. mask is the component's group mask as is
+//shape: 0 = trimesh, 1 = convex hull, 2 = box, 3 = sphere, 4 = capsule, 5 = box around the mesh bounds, 6 = heightfield.
+//shape_params holds the box half extents, the sphere radius or the capsule half height and radius, in that order
 #[repr(C)]
 #[derive(Debug)]
 struct PhysicsData {
@@ -551,6 +1101,12 @@ struct PhysicsData {
     velocity: [f32; 3],
     restitution: f32,
     friction: f32,
+    layer: u8,
+    shape: u8,
+    shape_params: [f32; 3],
+    is_sensor: bool,
+    mask: u32,
+    angular_velocity: [f32; 3],
 }
 
 impl PhysicsData {
@@ -562,12 +1118,108 @@ impl PhysicsData {
             body: 0,
             velocity: [0.0; 3],
             restitution: 0.0,
-            friction: 0.0
+            friction: 0.0,
+            layer: 0,
+            shape: 0,
+            shape_params: [0.0; 3],
+            is_sensor: false,
+            mask: 0,
+            angular_velocity: [0.0; 3],
         }
     }
+
+    fn from_component(component: &PhysicsComponent) -> Self {
+        //Note(teddy) Every material the editor makes is a BasicMaterial
+        let (restitution, friction) = match component.material_handle.downcast_ref::<BasicMaterial<f32>>() {
+            Some(material) => (material.restitution, material.friction),
+            None => (0.0, 0.0),
+        };
+        let (shape, shape_params) = collider_shape_to_data(&component.shape);
+
+        Self {
+            is_present: 1,
+            mass: component.mass,
+            gravity: component.gravity,
+            body: match component.status {
+                BodyStatus::Kinematic => Body::Kinematic as u8,
+                BodyStatus::Dynamic => Body::Dynamic as u8,
+                _ => Body::Static as u8,
+            },
+            velocity: [component.velocity.x, component.velocity.y, component.velocity.z],
+            restitution,
+            friction,
+            layer: match component.layer {
+                CollisionLayer::World => 0,
+                CollisionLayer::Trigger => 1,
+                CollisionLayer::EditorHelper => 2,
+                CollisionLayer::Debris => 3,
+            },
+            shape,
+            shape_params,
+            is_sensor: component.is_sensor,
+            mask: component.mask,
+            angular_velocity: [component.angular_velocity.x, component.angular_velocity.y, component.angular_velocity.z],
+        }
+    }
+
+    fn to_component(&self) -> PhysicsComponent {
+        let status = match self.body {
+            1 => BodyStatus::Kinematic,
+            2 => BodyStatus::Dynamic,
+            _ => BodyStatus::Static,
+        };
+        let [x, y, z] = self.velocity;
+
+        let mut component = PhysicsComponent::new(
+            self.mass,
+            self.gravity,
+            status,
+            Vector3::new(x, y, z),
+            MaterialHandle::new(BasicMaterial::new(self.restitution, self.friction)),
+        );
+        component.layer = match self.layer {
+            1 => CollisionLayer::Trigger,
+            2 => CollisionLayer::EditorHelper,
+            3 => CollisionLayer::Debris,
+            _ => CollisionLayer::World,
+        };
+        component.shape = collider_shape_from_data(self.shape, self.shape_params);
+        component.is_sensor = self.is_sensor;
+        component.mask = self.mask;
+        component.angular_velocity = Vector3::from(self.angular_velocity);
+        component
+    }
+}
+
+fn collider_shape_to_data(shape: &ColliderShape) -> (u8, [f32; 3]) {
+    match shape {
+        ColliderShape::TriMesh => (0, [0.0; 3]),
+        ColliderShape::ConvexHull => (1, [0.0; 3]),
+        ColliderShape::Box { half_extents } => (2, [half_extents.x, half_extents.y, half_extents.z]),
+        ColliderShape::Sphere { radius } => (3, [*radius, 0.0, 0.0]),
+        ColliderShape::Capsule { half_height, radius } => (4, [*half_height, *radius, 0.0]),
+        ColliderShape::AutoFromAabb => (5, [0.0; 3]),
+        ColliderShape::HeightField => (6, [0.0; 3]),
+    }
+}
+
+fn collider_shape_from_data(kind: u8, params: [f32; 3]) -> ColliderShape {
+    match kind {
+        1 => ColliderShape::ConvexHull,
+        2 => ColliderShape::Box { half_extents: Vector3::new(params[0], params[1], params[2]) },
+        3 => ColliderShape::Sphere { radius: params[0] },
+        4 => ColliderShape::Capsule { half_height: params[0], radius: params[1] },
+        5 => ColliderShape::AutoFromAabb,
+        6 => ColliderShape::HeightField,
+        _ => ColliderShape::TriMesh,
+    }
 }
 
 
+//Note(teddy) Slots of the material's textures in RenderData.textures, the rest are unused
+const DIFFUSE_MAP_SLOT: usize = 0;
+const NORMAL_MAP_SLOT: usize = 1;
+
 //Note(teddy) have a fixed size for the strings
 #[repr(C)]
 #[derive(Debug)]
@@ -576,26 +1228,378 @@ struct RenderData {
     textures: [[u8; 1024]; 8],
     mesh: [u8; 1024],
     shader: [u8; 1024],
+    flags: u32,
+    base_color: [f32; 3],
+    specular_strength: f32,
+    shininess: f32,
+    opacity: f32,
 }
 
 impl RenderData {
     fn default() -> Self {
+        let material = Material::default();
         Self {
             is_present: 0,
             textures: [[0; 1024]; 8],
             mesh: [0; 1024],
-            shader: [0; 1024]
+            shader: [0; 1024],
+            flags: RenderFlags::default().0,
+            base_color: material.base_color,
+            specular_strength: material.specular_strength,
+            shininess: material.shininess,
+            opacity: material.opacity,
+        }
+    }
+}
+
+//Note(teddy) start_kind/end_kind: 0 = fixed point, 1 = entity index in the saved entity array
+#[repr(C)]
+#[derive(Debug)]
+struct RopeData {
+    is_present: u8,
+    start_kind: u8,
+    end_kind: u8,
+    collide: u8,
+    start_entity: u32,
+    end_entity: u32,
+    start_point: [f32; 3],
+    end_point: [f32; 3],
+    segments: u32,
+    length: f32,
+    iterations: u32,
+    thickness: f32,
+    shader: [u8; 1024],
+}
+
+impl RopeData {
+    fn default() -> Self {
+        Self {
+            is_present: 0,
+            start_kind: 0,
+            end_kind: 0,
+            collide: 0,
+            start_entity: 0,
+            end_entity: 0,
+            start_point: [0.0; 3],
+            end_point: [0.0; 3],
+            segments: 0,
+            length: 0.0,
+            iterations: 0,
+            thickness: 0.0,
+            shader: [0; 1024],
         }
     }
 }
 
+//Note(teddy) Link back to the prefab an entity was created from, overrides is the PrefabField mask
+#[repr(C)]
+#[derive(Debug)]
+struct PrefabData {
+    is_present: u8,
+    name: [u8; 1024],
+    overrides: u32,
+}
+
+impl PrefabData {
+    fn default() -> Self {
+        Self {
+            is_present: 0,
+            name: [0; 1024],
+            overrides: 0,
+        }
+    }
+}
+
+//Note(teddy) kind: 0 = fixed, 1 = ball, 2 = revolute. a and b are indices in the saved entity array, the axis is
+//only used by revolute joints
+#[repr(C)]
+#[derive(Debug)]
+struct JointData {
+    a: u32,
+    b: u32,
+    kind: u8,
+    anchor_a: [f32; 3],
+    anchor_b: [f32; 3],
+    axis: [f32; 3],
+}
+
+impl JointData {
+    fn from_joint(joint: &Joint, a: u32, b: u32) -> Self {
+        let (kind, axis) = match joint.spec.kind {
+            JointKind::Fixed => (0, Vector3::zeros()),
+            JointKind::Ball => (1, Vector3::zeros()),
+            JointKind::Revolute { axis } => (2, axis),
+        };
+        let (anchor_a, anchor_b) = (joint.spec.anchor_a, joint.spec.anchor_b);
+
+        Self {
+            a,
+            b,
+            kind,
+            anchor_a: [anchor_a.x, anchor_a.y, anchor_a.z],
+            anchor_b: [anchor_b.x, anchor_b.y, anchor_b.z],
+            axis: [axis.x, axis.y, axis.z],
+        }
+    }
+
+    fn to_spec(&self) -> JointSpec {
+        let kind = match self.kind {
+            0 => JointKind::Fixed,
+            2 => JointKind::Revolute { axis: Vector3::from(self.axis) },
+            _ => JointKind::Ball,
+        };
+
+        JointSpec {
+            kind,
+            anchor_a: Point3::from(self.anchor_a),
+            anchor_b: Point3::from(self.anchor_b),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum WorldError {
     LevelNotFound,
     FailedToOpenLevel,
-    UnableToParseFile,
+    UnableToParseFile(String),
+    ///Doesn't start with the level file magic number, files from before the header had one land here too
+    NotALevelFile,
+    UnsupportedVersion { found: u32, expected: u32 },
+    EntitySizeMismatch { found: usize, expected: usize },
+    ///Shorter than its header says, sizes in bytes
+    Truncated { expected: usize, found: usize },
+    ChecksumMismatch,
+}
+
+impl std::fmt::Display for WorldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WorldError::LevelNotFound => write!(f, "There is no saved level to load"),
+            WorldError::FailedToOpenLevel => write!(f, "The level file couldn't be opened"),
+            WorldError::UnableToParseFile(error) => write!(f, "The level file has a broken entity: {}", error),
+            WorldError::NotALevelFile => write!(f, "The file isn't a level, or was saved before level files had a header"),
+            WorldError::UnsupportedVersion { found, expected } => {
+                write!(f, "The level was saved in format version {}, this build reads version {}", found, expected)
+            }
+            WorldError::EntitySizeMismatch { found, expected } => {
+                write!(f, "The level's entity records are {} bytes, this build's are {}", found, expected)
+            }
+            WorldError::Truncated { expected, found } => write!(f, "The level file is cut short, {} of {} bytes", found, expected),
+            WorldError::ChecksumMismatch => write!(f, "The level file is corrupted, its checksum doesn't match"),
+        }
+    }
 }
 
 
 fn load_game_world() -> Vec<Entity>{
     unimplemented!()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deleted_ids_are_recycled_empty() {
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+
+        let id = world.create_entity();
+        world.components.positionable[id] = Some(TransformComponent::new(Vector3::zeros(), Vector3::y(), 1.0));
+        world.components.renderables[id] = Some(RenderComponent::new(String::from("cube"), String::from("default")));
+
+        world.delete_entity(id);
+        assert!(!world.entities.contains(&id));
+        assert!(world.components.positionable[id].is_none() && world.components.renderables[id].is_none());
+        let removed = event_manager.get_engine_events().iter().any(|event| matches!(event.event_type, EventType::EntityRemoved(removed) if removed == id));
+        assert!(removed);
+
+        //Note(teddy) Deleting twice doesn't hand the id out twice
+        world.delete_entity(id);
+        assert_eq!(world.deleted_entities.len(), 1);
+
+        let recycled = world.create_entity();
+        assert_eq!(recycled, id);
+        assert!(world.entities.contains(&id));
+        assert!(world.components.positionable[id].is_none() && world.components.physics[id].is_none());
+    }
+
+    #[test]
+    fn test_physics_data_round_trip() {
+        let shapes = [
+            ColliderShape::TriMesh,
+            ColliderShape::ConvexHull,
+            ColliderShape::Box { half_extents: Vector3::new(1.0, 2.0, 3.0) },
+            ColliderShape::Sphere { radius: 0.5 },
+            ColliderShape::Capsule { half_height: 1.5, radius: 0.25 },
+            ColliderShape::AutoFromAabb,
+        ];
+
+        for shape in shapes.iter() {
+            let mut component = PhysicsComponent::new(2.0, true, BodyStatus::Dynamic, Vector3::x(), MaterialHandle::new(BasicMaterial::new(0.3, 0.8)));
+            component.layer = CollisionLayer::Debris;
+            component.mask = 0b1011;
+            component.is_sensor = true;
+            component.shape = *shape;
+
+            let loaded = PhysicsData::from_component(&component).to_component();
+            assert_eq!(loaded.shape, *shape);
+            assert_eq!((loaded.mass, loaded.gravity, loaded.status, loaded.velocity), (2.0, true, BodyStatus::Dynamic, Vector3::x()));
+            assert_eq!((loaded.layer, loaded.mask), (CollisionLayer::Debris, 0b1011));
+            assert!(loaded.is_sensor);
+
+            let material = loaded.material_handle.downcast_ref::<BasicMaterial<f32>>().unwrap();
+            assert_eq!((material.restitution, material.friction), (0.3, 0.8));
+        }
+    }
+
+    #[test]
+    fn test_joints_round_trip() {
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        let (a, unsaved, b) = (world.create_entity(), world.create_entity(), world.create_entity());
+
+        let hinge = JointSpec {
+            kind: JointKind::Revolute { axis: Vector3::z() },
+            anchor_a: Point3::new(0.5, 0.0, 0.0),
+            anchor_b: Point3::new(-0.5, 0.25, 0.0),
+        };
+        let weld = JointSpec { kind: JointKind::Fixed, ..hinge };
+        world.add_joint(a, b, hinge);
+        world.add_joint(b, unsaved, weld);
+        let removed = world.add_joint(b, a, weld);
+        world.remove_joint(removed);
+        world.entities = world.entities.iter().copied().filter(|id| *id != unsaved).collect();
+
+        //Note(teddy) Only the hinge is left between saved entities, loaded against fresh ids
+        let bytes = world.joints_to_bytes(&world.saved_entities());
+        let mut loaded = World::new(&mut event_manager, std::ptr::null_mut());
+        let loaded_ids = vec![10, 20];
+        loaded.load_joints(&bytes, &loaded_ids);
+        assert_eq!(loaded.joints, vec![Some(Joint { a: 10, b: 20, spec: hinge })]);
+
+        //Note(teddy) Files from before the joint section
+        loaded.load_joints(&[], &loaded_ids);
+        assert_eq!(loaded.joints.len(), 1);
+    }
+
+    #[test]
+    fn test_physics_components_survive_save_and_load() {
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+
+        let floor = world.create_entity();
+        let mut floor_physics = PhysicsComponent::new(0.0, false, BodyStatus::Static, Vector3::zeros(), MaterialHandle::new(BasicMaterial::new(0.1, 0.9)));
+        floor_physics.shape = ColliderShape::Box { half_extents: Vector3::new(10.0, 0.5, 10.0) };
+        world.components.physics[floor] = Some(floor_physics);
+
+        let ball = world.create_entity();
+        let mut ball_physics = PhysicsComponent::new(2.5, true, BodyStatus::Dynamic, Vector3::new(0.0, -1.0, 3.0), MaterialHandle::new(BasicMaterial::new(0.6, 0.2)));
+        ball_physics.shape = ColliderShape::Sphere { radius: 0.5 };
+        ball_physics.layer = CollisionLayer::Debris;
+        world.components.physics[ball] = Some(ball_physics);
+        for (id, height) in [(floor, 0.0), (ball, 3.0)].iter() {
+            world.components.positionable[*id] = Some(TransformComponent::new(Vector3::y() * *height, Vector3::zeros(), 1.0));
+        }
+
+        let mut loaded_events = EventManager::new();
+        let mut loaded = World::new(&mut loaded_events, std::ptr::null_mut());
+        loaded.load_level_bytes(&world.level_bytes()).unwrap();
+        assert_eq!(loaded.entities.len(), 2);
+
+        for (saved, loaded_id) in world.entities.iter().zip(loaded.entities.iter()) {
+            let (saved, loaded_component) = (world.components.physics[*saved].as_ref().unwrap(), loaded.components.physics[*loaded_id].as_ref().unwrap());
+            assert_eq!((loaded_component.mass, loaded_component.gravity, loaded_component.status), (saved.mass, saved.gravity, saved.status));
+            assert_eq!((loaded_component.velocity, loaded_component.shape, loaded_component.layer), (saved.velocity, saved.shape, saved.layer));
+            assert!(loaded_component.rigid_handle.is_none() && loaded_component.collider_handle.is_none());
+
+            let material = |component: &PhysicsComponent| {
+                let material = component.material_handle.downcast_ref::<BasicMaterial<f32>>().unwrap();
+                (material.restitution, material.friction)
+            };
+            assert_eq!(material(loaded_component), material(saved));
+        }
+
+        //Note(teddy) A file from before the magic number starts with its entity count
+        let mut old_file = world.level_bytes();
+        old_file[..4].copy_from_slice(&2u32.to_ne_bytes());
+        let mut rejected = World::new(&mut loaded_events, std::ptr::null_mut());
+        assert_eq!(rejected.load_level_bytes(&old_file), Err(WorldError::NotALevelFile));
+        assert!(rejected.entities.is_empty());
+    }
+
+    #[test]
+    fn test_damaged_level_files_are_refused() {
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        for height in [0.0, 2.0, 4.0].iter() {
+            let id = world.create_entity();
+            world.components.positionable[id] = Some(TransformComponent::new(Vector3::y() * *height, Vector3::zeros(), 1.0));
+        }
+
+        let file = world.level_bytes();
+        let header_size = std::mem::size_of::<StorageFileHeader>();
+        let load = |bytes: &[u8]| {
+            let mut loaded_events = EventManager::new();
+            let mut loaded = World::new(&mut loaded_events, std::ptr::null_mut());
+            let result = loaded.load_level_bytes(bytes);
+            (result, loaded.entities.len())
+        };
+
+        assert_eq!(load(&file), (Ok(()), 3));
+
+        let mut flipped = file.clone();
+        flipped[header_size + 5] ^= 0xFF;
+        assert_eq!(load(&flipped), (Err(WorldError::ChecksumMismatch), 0));
+
+        let cut = &file[..file.len() - 1];
+        assert_eq!(load(cut), (Err(WorldError::Truncated { expected: file.len(), found: file.len() - 1 }), 0));
+        assert_eq!(load(&file[..6]), (Err(WorldError::Truncated { expected: header_size, found: 6 }), 0));
+
+        let mut newer = file.clone();
+        newer[4..8].copy_from_slice(&(SAVE_FILE_VERSION + 1).to_ne_bytes());
+        assert_eq!(load(&newer), (Err(WorldError::UnsupportedVersion { found: SAVE_FILE_VERSION + 1, expected: SAVE_FILE_VERSION }), 0));
+
+        let mut resized = file.clone();
+        resized[8..12].copy_from_slice(&1u32.to_ne_bytes());
+        let expected = std::mem::size_of::<Entity>();
+        assert_eq!(load(&resized), (Err(WorldError::EntitySizeMismatch { found: 1, expected }), 0));
+
+        assert_eq!(load(&[0x42; 64]), (Err(WorldError::NotALevelFile), 0));
+
+        //Note(teddy) A header that checks out but says the payload ends inside the entity records
+        let entity_size = std::mem::size_of::<Entity>();
+        let mut short = file[..header_size + entity_size].to_vec();
+        short[16..20].copy_from_slice(&(entity_size as u32).to_ne_bytes());
+        let checksum = crc32(&short[header_size..]);
+        short[20..24].copy_from_slice(&checksum.to_ne_bytes());
+        assert_eq!(load(&short), (Err(WorldError::Truncated { expected: header_size + 3 * entity_size, found: header_size + entity_size }), 0));
+
+        let mut counted = file.clone();
+        counted[12..16].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert!(matches!(load(&counted), (Err(WorldError::Truncated { .. }), 0)));
+    }
+
+    #[test]
+    fn test_broken_labels_leave_the_level_as_it_was() {
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        world.create_entity();
+        let rope = world.create_entity();
+        let anchor = |x: f32| RopeAnchor::Point(Point3::new(x, 0.0, 0.0));
+        world.components.ropes[rope] = Some(RopeComponent::new(anchor(0.0), anchor(1.0), 4, 1.0, 4, 0.1, String::from("rope_shader")));
+
+        //Note(teddy) The checksum is rewritten so only the label is wrong
+        let mut file = world.level_bytes();
+        let header_size = std::mem::size_of::<StorageFileHeader>();
+        let label = file.windows(11).position(|bytes| bytes == b"rope_shader").unwrap();
+        file[label] = 0xFF;
+        let checksum = crc32(&file[header_size..]);
+        file[20..24].copy_from_slice(&checksum.to_ne_bytes());
+
+        let mut loaded_events = EventManager::new();
+        let mut loaded = World::new(&mut loaded_events, std::ptr::null_mut());
+        assert!(matches!(loaded.load_level_bytes(&file), Err(WorldError::UnableToParseFile(_))));
+        assert!(loaded.entities.is_empty());
+    }
+}
@@ -31,15 +31,21 @@ pub fn init_gl_window_context(initial_size: (u32, u32), window_name: &str) -> Di
     //window.set_cursor_mode(glfw::CursorMode::Disabled);
     window.make_current();
     window.set_key_polling(true);
+    window.set_char_polling(true);
     window.set_cursor_pos_polling(true);
     window.set_mouse_button_polling(true);
+    window.set_scroll_polling(true);
     window.set_size_polling(true);
+    window.set_framebuffer_size_polling(true);
 
     gl::load_with(|s| window.get_proc_address(s) as *const _);
     gl::Viewport::load_with(|s| window.get_proc_address(s));
 
+    //Note(teddy) On HiDPI displays the framebuffer is larger than the window
+    let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+
     unsafe { 
-        gl::Viewport(0, 0, initial_size.0 as i32, initial_size.1 as i32);
+        gl::Viewport(0, 0, framebuffer_width, framebuffer_height);
         gl::Enable(gl::DEBUG_OUTPUT);
         gl::DebugMessageCallback(Some(message_callback), 0 as *const c_void);
     };
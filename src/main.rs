@@ -7,6 +7,7 @@ extern crate memoffset;
 extern crate freetype;
 extern crate serde_json;
 
+mod benchmark;
 #[macro_use]
 mod core;
 mod logs;
@@ -15,32 +16,59 @@ mod game_world;
 mod gl_bindings;
 mod obj_parser;
 mod renderer;
+mod settings;
 
 mod systems;
 mod ui;
 mod utils;
 
+use std::path::Path;
 use std::time::Instant;
 
 use glfw::Context;
 
-use crate::core::{camera_behaviour, load_fonts, Engine, EventManager};
-use editor::editor::{update_editor, Editor};
+use crate::core::{camera_behaviour, load_fonts, quit, Engine, EventManager};
+use benchmark::{split_benchmark_arg, Benchmark, BenchmarkPreset, BENCHMARK_OUTPUT_DIR};
+use editor::editor::{image_written, update_editor, Editor};
 use game_world::world::{AssetSource, World};
 use gl_bindings::Display;
 use systems::physics::Physics;
-use systems::render_system::Renderer;
+use systems::render_system::{instanced_shader_label, Renderer};
+use systems::rope::RopeSystem;
 use logs::Logable;
+use settings::{parse_cli_args, Console, LightSettings};
 
 #[macro_use]
 use systems::system::{System, Systems};
 use ui::ui::init_ui;
 
 fn main() {
+    let (benchmark, args) = split_benchmark_arg(std::env::args().skip(1));
+    let light_settings = match parse_cli_args(args.into_iter()) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("Invalid arguments: {:?}", error);
+            eprintln!("Usage: imara [--light-dir x y z] [--light-color r g b] [--ambient a] [--benchmark forest|city]");
+            std::process::exit(1);
+        }
+    };
+
+    let benchmark = match benchmark.map(|name| BenchmarkPreset::from_name(&name)) {
+        Some(Ok(preset)) => Some(preset),
+        Some(Err(error)) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
     let display = gl_bindings::init_gl_window_context((1000, 600), "Imara");
-    run(display);
+    run(display, light_settings, benchmark);
 }
 
+///A frame longer than this, loading or a breakpoint, only moves things along this much
+const MAX_FRAME_DELTA: f32 = 0.1;
+
 macro_rules! default_shader {
     () => {
         String::from("default")
@@ -48,14 +76,19 @@ macro_rules! default_shader {
 }
 
 
-fn run(display: Display) {
-    let fonts = unsafe { load_fonts(12).unwrap() };
+fn run(display: Display, light_settings: LightSettings, benchmark: Option<BenchmarkPreset>) {
+    let fonts = unsafe { load_fonts(12, 1.0).unwrap() };
 
     let mut engine = Engine::new(display, fonts);
+    light_settings.apply(&mut engine.dir_lights);
+    let console = Console::spawn();
+
     let mut event_manager = EventManager::new();
     let mut world = World::new(&mut event_manager, &mut engine.log_manager);
     let mut systems = Systems::new();
 
+    //Note(teddy) Opt-in while the content hashing is new, duplicates are only reported and aliased
+    world.resources.write().unwrap().dedup_meshes = std::env::var("IMARA_DEDUP_MESHES").is_ok();
     world.init_resource_loading_thread();
     world.add_resource(
         AssetSource::Shader(
@@ -66,6 +99,15 @@ fn run(display: Display) {
         ),
     );
 
+    world.add_resource(
+        AssetSource::Shader(
+            instanced_shader_label(&default_shader!()),
+            String::from("instanced_vert.glsl"),
+            String::from("frag.glsl"),
+            None,
+        ),
+    );
+
     world.add_resource(
         AssetSource::Shader(
             String::from("highlight_shader"),
@@ -76,6 +118,42 @@ fn run(display: Display) {
     );
 
 
+    world.add_resource(
+        AssetSource::Shader(
+            TEXTURED_SHADER!(),
+            String::from("textured_vert.glsl"),
+            String::from("textured_frag.glsl"),
+            None,
+        ),
+    );
+
+    world.add_resource(
+        AssetSource::Shader(
+            DEBUG_LINE_SHADER!(),
+            String::from("debug_line_vert.glsl"),
+            String::from("debug_line_frag.glsl"),
+            None,
+        ),
+    );
+
+    world.add_resource(
+        AssetSource::Shader(
+            NORMALS_SHADER!(),
+            String::from("normals_vert.glsl"),
+            String::from("debug_line_frag.glsl"),
+            Some(String::from("normals_geom.glsl")),
+        ),
+    );
+
+    world.add_resource(
+        AssetSource::Shader(
+            GRID_SHADER!(),
+            String::from("grid_vert.glsl"),
+            String::from("grid_frag.glsl"),
+            None,
+        ),
+    );
+
     world.add_resource(
         AssetSource::Shader(
             SCREEN_SHADER!(),
@@ -94,19 +172,27 @@ fn run(display: Display) {
 
     let render_system: Box<dyn System> = Box::new(Renderer::new());
     let physics_system: Box<dyn System> = Box::new(Physics::new());
+    let rope_system: Box<dyn System> = Box::new(RopeSystem::new());
 
     systems.systems.push_front(render_system);
     systems.systems.push_front(physics_system);
+    systems.systems.push_front(rope_system);
 
     {
         for system in systems.systems.iter_mut() {
             system.init(&mut world, &mut engine).unwrap();
         }
     }
+    //Note(teddy) The benchmark scene replaces the interactive session, it quits once the camera path is done
+    let mut benchmark = benchmark.map(Benchmark::new);
+    if let Some(benchmark) = &benchmark {
+        benchmark.populate(&mut world, &default_shader!());
+    }
+
     // I have to create and load a mesh
     //world.components.(RenderComponent::new())
-    let mut frame_time: u128 = 0;
-    let mut ticks: u128 = 0;
+    let mut frame_time: f32 = 0.0;
+    let mut ticks: u32 = 0;
 
     unsafe {
         gl::Enable(gl::STENCIL_TEST);
@@ -116,29 +202,61 @@ fn run(display: Display) {
 
     engine.log_manager.add_log((String::from("main"), Box::new(MainLoopLogObject{text: String::new()})));
 
+    let mut last_frame = Instant::now();
     while !engine.display.window.should_close() {
         let time = Instant::now();
+        let dt = time.duration_since(last_frame).as_secs_f32().min(MAX_FRAME_DELTA);
+        last_frame = time;
+
         engine.display.glfw.poll_events();
         event_manager.handle_events(glfw::flush_messages(&engine.display.events_receiver));
         engine.update(&mut event_manager);
 
-        camera_behaviour(&mut engine);
+        for command in console.poll() {
+            match command {
+                Ok(command) => command.apply(&mut engine.dir_lights),
+                Err(error) => eprintln!("Console: {:?}", error),
+            }
+        }
+
+        camera_behaviour(&mut engine, dt);
         for system in systems.systems.iter_mut() {
-            system.update(&mut world, &mut event_manager, &mut engine, 16.0);
+            system.update(&mut world, &mut event_manager, &mut engine, dt);
         }
 
         update_editor(&mut editor, &mut engine, &mut world, &mut event_manager);
 
+        for written in engine.image_writer.poll_results() {
+            if written.result.is_ok() {
+                image_written(&mut editor, &engine, &world, &written.path);
+            }
+            let text = match written.result {
+                Ok(_) => format!("Saved {}", written.path),
+                Err(error) => format!("Failed to save {}: {}", written.path, error),
+            };
+            engine.log_manager.add_log((String::from("image_writer"), Box::new(MainLoopLogObject{text})));
+        }
+
         engine.display.window.swap_buffers();
         event_manager.clear();
-        frame_time += time.elapsed().as_nanos();
+
+        if let Some(summary) = benchmark.as_mut().and_then(|benchmark| benchmark.update(&mut engine, &world, time.elapsed())) {
+            match benchmark.as_ref().unwrap().write_csv(Path::new(BENCHMARK_OUTPUT_DIR)) {
+                Ok(path) => println!("Frame timings written to {}", path),
+                Err(error) => eprintln!("Failed to write the frame timings: {}", error),
+            }
+            println!("{}", summary);
+            quit(&mut engine);
+        }
+
+        frame_time += dt;
         ticks += 1;
 
-        if frame_time >= 1000000000 {
+        if frame_time >= 1.0 {
             //println!("{} : Frames per second", ticks);
-            let main_log = format!("Avg. Frame Time {} ms", frame_time / (ticks * 1_000_000));
+            let main_log = format!("Avg. Frame Time {:.2} ms", frame_time * 1000.0 / ticks as f32);
             engine.log_manager.add_log((String::from("main"), Box::new(MainLoopLogObject{text: main_log})));
-            frame_time = 0;
+            frame_time = 0.0;
             ticks = 0;
         }
     }
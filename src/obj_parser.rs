@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::path::Path;
 
 use nalgebra::{Point2, Point3, Point4};
 
@@ -39,22 +41,44 @@ impl Obj for NormalObj {
 }
 
 impl Obj for TexturedObj {
+    //Note(teddy) A position is shared by faces with different uvs and normals, so every distinct
+    //corner becomes its own vertex instead of reusing the position's index
     fn from(data: Data) -> Self {
-        Self {
-            vertices: data.vertices,
-            normals: data.normals,
-            indices: data.indices,
-            text_cords: data.text_cords,
+        let mut obj = Self { vertices: vec![], normals: vec![], text_cords: vec![], indices: vec![] };
+        let mut corner_indices: HashMap<[u32; 3], u32> = HashMap::new();
+
+        for corner in data.corners.iter() {
+            let index = *corner_indices.entry(*corner).or_insert_with(|| {
+                let [vertex, text_cord, normal] = *corner;
+                obj.vertices.push(data.vertices[(vertex - 1) as usize]);
+                obj.text_cords.push(match text_cord {
+                    0 => Point2::origin(),
+                    i => data.raw_text_cords[(i - 1) as usize],
+                });
+                obj.normals.push(match normal {
+                    0 => Point3::origin(),
+                    i => data.raw_normals[(i - 1) as usize],
+                });
+
+                (obj.vertices.len() - 1) as u32
+            });
+
+            obj.indices.push(index);
         }
+
+        obj
     }
 }
 
 #[derive(Debug)]
 pub struct Data {
     vertices: Vec<Point4<f32>>,
-    text_cords: Vec<Point2<f32>>,
     normals: Vec<Point3<f32>>,
     indices: Vec<u32>,
+    ///1 based position, texture coordinate and normal of every face corner, 0 when the face leaves one out
+    corners: Vec<[u32; 3]>,
+    raw_text_cords: Vec<Point2<f32>>,
+    raw_normals: Vec<Point3<f32>>,
 }
 
 pub fn load_obj<T>(source: &str) -> Result<T, ParseError>
@@ -176,17 +200,12 @@ fn parse_file<T: BufRead>(file_content: T) -> Result<Data, ParseError> {
 
     // dbg!(&raw_normals);
     //Process the mesh
-    let mut text_cords: Vec<Point2<f32>> = vec![Point2::origin(); vertices.len()];
     let mut normals: Vec<Point3<f32>> = vec![Point3::origin(); vertices.len()];
     let mut indices: Vec<u32> = vec![];
 
-    for indice in raw_indices {
+    for indice in raw_indices.iter() {
         indices.push(indice[0]);
 
-        if indice[1] != 0 && !text_cords.is_empty() {
-            text_cords[(indice[0] - 1) as usize] = raw_texture_cords[(indice[1] - 1) as usize];
-        }
-
         if indice[2] != 0 {
             normals[(indice[0] - 1) as usize] = raw_normals[(indice[2] - 1) as usize]
         }
@@ -194,12 +213,53 @@ fn parse_file<T: BufRead>(file_content: T) -> Result<Data, ParseError> {
 
     Ok(Data {
         vertices,
-        text_cords,
         normals,
         indices: indices.into_iter().map(|x| x - 1).collect(),
+        corners: raw_indices,
+        raw_text_cords: raw_texture_cords,
+        raw_normals,
     })
 }
 
+///Diffuse maps of the materials the obj uses, the file names are looked up in the textures folder
+pub fn material_diffuse_maps(source: &str) -> Vec<String> {
+    let libraries = match File::open(source) {
+        Ok(file) => statements(BufReader::new(file), "mtllib"),
+        Err(_) => return vec![],
+    };
+
+    let folder = Path::new(source).parent().unwrap_or(Path::new(""));
+    let mut maps = vec![];
+    for library in libraries {
+        if let Ok(file) = File::open(folder.join(&library)) {
+            for map in statements(BufReader::new(file), "map_Kd") {
+                //Note(teddy) Exporters write the path the texture had on their machine, only the name is kept
+                let name = Path::new(&map).file_name().and_then(|name| name.to_str()).map(String::from);
+                if let Some(name) = name.filter(|name| !maps.contains(name)) {
+                    maps.push(name);
+                }
+            }
+        }
+    }
+
+    maps
+}
+
+///Last argument of every line starting with the prefix, options before it are skipped
+fn statements<T: BufRead>(content: T, prefix: &str) -> Vec<String> {
+    let mut found = vec![];
+    let _ = lex(content, |line_prefix, args| {
+        if line_prefix == prefix {
+            if let Some(arg) = args.last() {
+                found.push(arg.to_string());
+            }
+        }
+        Ok(())
+    });
+
+    found
+}
+
 fn lex<T, F>(content: T, mut callback: F) -> Result<(), ParseError>
 where
     T: BufRead,
@@ -212,8 +272,8 @@ where
             Err(err) => return Err(ParseError::IOError(err)),
         };
 
-        if line_content.starts_with("#") {
-            //Ignore a comment
+        if line_content.starts_with("#") || (multi_line.is_empty() && line_content.trim().is_empty()) {
+            //Ignore a comment or a blank line
             continue;
         }
 
@@ -236,3 +296,22 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_textured_obj_splits_corners_and_finds_its_texture() {
+        let cube: TexturedObj = load_obj("./assets/objects/textured_cube.obj").unwrap();
+
+        //Note(teddy) Each of the 6 faces has its own normal, so the 8 positions become 4 corners per face
+        assert_eq!(cube.vertices.len(), 24);
+        assert_eq!(cube.text_cords.len(), 24);
+        assert_eq!(cube.indices.len(), 36);
+        assert_eq!(cube.text_cords[cube.indices[0] as usize], Point2::new(0.875, 0.5));
+
+        assert_eq!(material_diffuse_maps("./assets/objects/textured_cube.obj"), vec![String::from("textured_cube.png")]);
+        assert!(material_diffuse_maps("./assets/objects/cube.obj").is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use std::ffi::c_void;
+
+use nalgebra::{Isometry3, Point3, Vector3};
+
+use crate::core::Camera;
+use crate::game_world::world::World;
+use crate::renderer::draw::{scene_shader, DrawError};
+use crate::renderer::stats;
+
+//Note(teddy) Lines are queued from anywhere during the frame and thrown away once the renderer has
+//drawn them, anything that should stay on screen has to be queued again every frame
+
+const SPHERE_SEGMENTS: usize = 24;
+///Every this many grid lines one is drawn brighter
+const GRID_MAJOR_EVERY: i32 = 10;
+const GRID_MINOR_COLOR: [f32; 3] = [0.25, 0.25, 0.25];
+const GRID_MAJOR_COLOR: [f32; 3] = [0.4, 0.4, 0.4];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+///Line segments waiting for the next flush, every two vertices are one line
+#[derive(Debug, Default)]
+pub struct DebugLines {
+    vertices: Vec<DebugVertex>,
+}
+
+impl DebugLines {
+    pub fn queue_line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 3]) {
+        self.vertices.push(DebugVertex { position: [from.x, from.y, from.z], color });
+        self.vertices.push(DebugVertex { position: [to.x, to.y, to.z], color });
+    }
+
+    ///The twelve edges of the box
+    pub fn queue_aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: [f32; 3]) {
+        let half_extents = (max - min) * 0.5;
+        let center = min + half_extents;
+        self.queue_box(&Isometry3::translation(center.x, center.y, center.z), half_extents, color);
+    }
+
+    ///The twelve edges of a box centered on the position and turned with it
+    pub fn queue_box(&mut self, position: &Isometry3<f32>, half_extents: Vector3<f32>, color: [f32; 3]) {
+        let corner = |i: usize| {
+            position
+                * Point3::new(
+                    if i & 1 == 0 { -half_extents.x } else { half_extents.x },
+                    if i & 2 == 0 { -half_extents.y } else { half_extents.y },
+                    if i & 4 == 0 { -half_extents.z } else { half_extents.z },
+                )
+        };
+
+        //Note(teddy) Corners one bit apart share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4].iter() {
+                if i & bit == 0 {
+                    self.queue_line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    ///A circle around each axis
+    pub fn queue_sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 3]) {
+        let circle_point = |axis: usize, angle: f32| {
+            let (sin, cos) = angle.sin_cos();
+            let offset = match axis {
+                0 => Vector3::new(0.0, cos, sin),
+                1 => Vector3::new(cos, 0.0, sin),
+                _ => Vector3::new(cos, sin, 0.0),
+            };
+            center + offset * radius
+        };
+
+        let step = std::f32::consts::PI * 2.0 / SPHERE_SEGMENTS as f32;
+        for axis in 0..3 {
+            for segment in 0..SPHERE_SEGMENTS {
+                let from = circle_point(axis, segment as f32 * step);
+                let to = circle_point(axis, (segment + 1) as f32 * step);
+                self.queue_line(from, to, color);
+            }
+        }
+    }
+
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+///Immediate mode lines for the systems and the editor, drawn after the entities
+#[derive(Debug, Default)]
+pub struct DebugDraw {
+    ///Hidden behind scene geometry like any other object
+    pub scene: DebugLines,
+    ///Drawn over everything, for gizmos that must stay visible
+    pub overlay: DebugLines,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.scene.clear();
+        self.overlay.clear();
+    }
+}
+
+///Lines on the y = 0 plane around the center plus the red, green and blue axes through the origin.
+///The center is snapped to the spacing so the lines stay put in the world as the camera moves.
+pub fn ground_grid(center: Point3<f32>, extent: f32, spacing: f32) -> DebugLines {
+    let mut lines = DebugLines::default();
+    let count = (extent / spacing).ceil() as i32;
+    let (center_x, center_z) = ((center.x / spacing).round() as i32, (center.z / spacing).round() as i32);
+    let (min_x, max_x) = ((center_x - count) as f32 * spacing, (center_x + count) as f32 * spacing);
+    let (min_z, max_z) = ((center_z - count) as f32 * spacing, (center_z + count) as f32 * spacing);
+
+    let color = |line: i32| if line % GRID_MAJOR_EVERY == 0 { GRID_MAJOR_COLOR } else { GRID_MINOR_COLOR };
+
+    //Note(teddy) The lines through the origin are left to the axes, both at the same depth would fight
+    for line in (center_x - count)..=(center_x + count) {
+        if line != 0 {
+            let x = line as f32 * spacing;
+            lines.queue_line(Point3::new(x, 0.0, min_z), Point3::new(x, 0.0, max_z), color(line));
+        }
+    }
+
+    for line in (center_z - count)..=(center_z + count) {
+        if line != 0 {
+            let z = line as f32 * spacing;
+            lines.queue_line(Point3::new(min_x, 0.0, z), Point3::new(max_x, 0.0, z), color(line));
+        }
+    }
+
+    lines.queue_line(Point3::new(min_x, 0.0, 0.0), Point3::new(max_x, 0.0, 0.0), [0.9, 0.2, 0.2]);
+    lines.queue_line(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, extent, 0.0), [0.2, 0.9, 0.2]);
+    lines.queue_line(Point3::new(0.0, 0.0, min_z), Point3::new(0.0, 0.0, max_z), [0.2, 0.4, 0.9]);
+    lines
+}
+
+#[derive(Debug)]
+pub struct DebugLinesObject {
+    pub vertex_array_object: u32,
+    pub vertex_buffer: u32,
+}
+
+pub unsafe fn init_debug_lines_object() -> DebugLinesObject {
+    let mut vao = 0;
+    let mut vbo = 0;
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+    let stride = std::mem::size_of::<DebugVertex>() as i32;
+    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, offset_of!(DebugVertex, position) as *const c_void);
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, offset_of!(DebugVertex, color) as *const c_void);
+    gl::EnableVertexAttribArray(1);
+
+    gl::BindVertexArray(0);
+    DebugLinesObject { vertex_array_object: vao, vertex_buffer: vbo }
+}
+
+///Uploads the queued lines and draws them with GL_LINES, depth_test false draws them over the scene
+pub unsafe fn draw_debug_lines(
+    world: &World,
+    shader_label: &String,
+    camera: &Camera,
+    object: &DebugLinesObject,
+    lines: &DebugLines,
+    depth_test: bool,
+) -> Result<(), DrawError> {
+    let resources = world.resources.read().unwrap();
+    let shader = scene_shader(&resources, shader_label)?;
+
+    shader.bind();
+    shader.set_mat4("view", &camera.view());
+    shader.set_mat4("pers", &camera.perspective());
+    shader.set_vec3("view_position", &[camera.position.x, camera.position.y, camera.position.z]);
+
+    let vertices = lines.vertices();
+    gl::BindVertexArray(object.vertex_array_object);
+    gl::BindBuffer(gl::ARRAY_BUFFER, object.vertex_buffer);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (vertices.len() * std::mem::size_of::<DebugVertex>()) as isize,
+        vertices.as_ptr().cast(),
+        gl::STREAM_DRAW,
+    );
+
+    if depth_test {
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+    } else {
+        gl::Disable(gl::DEPTH_TEST);
+    }
+
+    gl::DrawArrays(gl::LINES, 0, vertices.len() as i32);
+    stats::record_draw(0);
+    gl::BindVertexArray(0);
+    gl::Enable(gl::DEPTH_TEST);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes_queue_whole_lines() {
+        let mut lines = DebugLines::default();
+
+        lines.queue_aabb(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0), [1.0, 0.0, 0.0]);
+        assert_eq!(lines.vertices().len(), 12 * 2);
+
+        //Note(teddy) Every edge runs along a single axis
+        for edge in lines.vertices().chunks(2) {
+            let length: f32 = (0..3).map(|i| (edge[0].position[i] - edge[1].position[i]).abs()).sum();
+            assert_eq!(length, 2.0);
+        }
+
+        lines.clear();
+        lines.queue_sphere(Point3::new(0.0, 2.0, 0.0), 0.5, [0.0, 1.0, 0.0]);
+        assert_eq!(lines.vertices().len(), 3 * SPHERE_SEGMENTS * 2);
+        for vertex in lines.vertices() {
+            let offset = Vector3::new(vertex.position[0], vertex.position[1] - 2.0, vertex.position[2]);
+            assert!((offset.norm() - 0.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_ground_grid_follows_the_camera_in_whole_cells() {
+        let grid = ground_grid(Point3::new(12.4, 5.0, -3.6), 2.0, 1.0);
+        let vertices = grid.vertices();
+
+        //Note(teddy) Five lines each way around x 12 and z -4, the z = 0 line is out of range so all three axes follow
+        assert_eq!(vertices.len(), (5 + 5 + 3) * 2);
+        assert_eq!(vertices[0].position, [10.0, 0.0, -6.0]);
+        assert_eq!(vertices[1].position, [10.0, 0.0, -2.0]);
+
+        //Note(teddy) Around the origin the lines through it give way to the axes
+        let grid = ground_grid(Point3::new(0.0, 5.0, 0.0), 2.0, 1.0);
+        assert_eq!(grid.vertices().len(), (4 + 4 + 3) * 2);
+    }
+}
@@ -1,14 +1,16 @@
 use std::convert::TryInto;
 use std::ffi::c_void;
-use std::ffi::CString;
 
 use nalgebra::{Matrix4, Point3, Point4, Vector3};
 
-use crate::core::{Camera, Engine, Light, ViewPortDimensions};
-use crate::game_world::components::{TransformComponent};
-use crate::game_world::world::World;
+use crate::core::{Camera, Engine, FontFace, Light, ViewPortDimensions};
+use crate::game_world::components::{Material, TransformComponent};
+use crate::game_world::world::{mesh_bounds, Resources, World};
 use crate::obj_parser::{NormalObj, TexturedObj};
-use crate::utils::get_at_index;
+use crate::renderer::shaders::ShaderProgram;
+use crate::renderer::stats;
+use crate::renderer::textures::bind_diffuse_map;
+use crate::utils::Aabb;
 
 #[derive(Debug)]
 pub enum DrawError {
@@ -54,6 +56,8 @@ pub struct RenderObject {
     pub element_buffer: u32,
     pub vertex_array_object: u32,
     pub size_of_elements: i32,
+    ///Model space bounds of the uploaded vertices
+    pub bounds: Option<Aabb>,
 }
 
 pub unsafe fn init_normal_object(object: &NormalObj) -> RenderObject {
@@ -117,12 +121,99 @@ pub unsafe fn init_normal_object(object: &NormalObj) -> RenderObject {
         vertex_buffer: vbo,
         element_buffer: ebo,
         size_of_elements: indices.len() as i32,
+        bounds: mesh_bounds(&object.vertices),
     }
 }
 
+///Re-upload the vertex and index data of an existing object, used for meshes generated every frame
+pub unsafe fn update_normal_object(object: &mut RenderObject, mesh: &NormalObj) {
+    let (vertices, indices) = process_normal_mesh(&mesh);
+
+    gl::BindVertexArray(object.vertex_array_object);
+    gl::BindBuffer(gl::ARRAY_BUFFER, object.vertex_buffer);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (vertices.len() * std::mem::size_of::<NormalVertex>()) as isize,
+        vertices.as_ptr().cast(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, object.element_buffer);
+    gl::BufferData(
+        gl::ELEMENT_ARRAY_BUFFER,
+        (indices.len() * std::mem::size_of::<u32>()) as isize,
+        indices.as_ptr().cast(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    gl::BindVertexArray(0);
+    object.size_of_elements = indices.len() as i32;
+    object.bounds = mesh_bounds(&mesh.vertices);
+}
+
 pub unsafe fn init_textured_object(object: &TexturedObj) -> RenderObject {
-    let (_vertices, _indices) = process_textured_mesh(&object);
-    unimplemented!()
+    let (vertices, indices) = process_textured_mesh(&object);
+
+    let mut vao = 0;
+    let mut vbo = 0;
+    let mut ebo = 0;
+
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+    gl::GenBuffers(1, &mut ebo);
+
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (vertices.len() * std::mem::size_of::<TexturedVertex>()) as isize,
+        vertices.as_ptr().cast(),
+        gl::STATIC_DRAW,
+    );
+
+    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+    gl::BufferData(
+        gl::ELEMENT_ARRAY_BUFFER,
+        (indices.len() * std::mem::size_of::<u32>()) as isize,
+        indices.as_ptr().cast(),
+        gl::STATIC_DRAW,
+    );
+
+    let stride: i32 = std::mem::size_of::<TexturedVertex>().try_into().unwrap();
+
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, stride, 0 as *const c_void);
+
+    gl::EnableVertexAttribArray(1);
+    gl::VertexAttribPointer(
+        1,
+        3,
+        gl::FLOAT,
+        gl::FALSE,
+        stride,
+        offset_of!(TexturedVertex, normal) as *const c_void,
+    );
+
+    gl::EnableVertexAttribArray(2);
+    gl::VertexAttribPointer(
+        2,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        stride,
+        offset_of!(TexturedVertex, text_cords) as *const c_void,
+    );
+
+    //Note(teddy) break the vertex array binding
+    gl::BindVertexArray(0);
+
+    RenderObject {
+        vertex_array_object: vao,
+        vertex_buffer: vbo,
+        element_buffer: ebo,
+        size_of_elements: indices.len() as i32,
+        bounds: mesh_bounds(&object.vertices),
+    }
 }
 
 pub fn remove_normal_object(_id: usize, _object: RenderObject) {}
@@ -195,173 +286,331 @@ pub unsafe fn draw_normal_object<T>(
     object: &RenderObject,
     transform: &TransformComponent,
     light: &Light,
+    material: &Material,
     draw_params: T,
 ) -> Result<(), DrawError>
 where
     T: FnOnce(),
 {
-    let resources = &world.resources.read().unwrap().shaders;
-
-    let shader = match resources.get(shader_label) {
-        Some(id) => {
-            if let Some(shader_id) = id {
-                *shader_id
-            } else {
-                //Shader is not available skip
-                return Err(DrawError::ShaderNotAvailable(shader_label.clone()));
-            }
-        }
-        None => return Err(DrawError::ShaderNotFound(shader_label.clone())),
-    };
-
-    let view_matrix: Matrix4<f32> = camera.view();
-    let perspective_matrix: Matrix4<f32> = camera.perspective();
-    let scale = transform.scale;
-    let scale_matrix = Matrix4::new(
-        scale, 0.0, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, 0.0, 1.0,
-    );
-    let model_matrix: Matrix4<f32> = transform.position.to_homogeneous() * scale_matrix;
+    draw_scene_object(world, shader_label, camera, object, transform, light, material, |_| draw_params())
+}
 
-    //TODO(teddy) precompute the transformation matrices then send
+unsafe fn draw_scene_object<T>(
+    world: &World,
+    shader_label: &String,
+    camera: &Camera,
+    object: &RenderObject,
+    transform: &TransformComponent,
+    light: &Light,
+    material: &Material,
+    draw_params: T,
+) -> Result<(), DrawError>
+where
+    T: FnOnce(&ShaderProgram),
+{
+    let resources = world.resources.read().unwrap();
+    let shader = scene_shader(&resources, shader_label)?;
+    use_scene_shader(shader, camera, light, material);
 
-    let uniform_name = CString::new("view").unwrap();
-    let perspective_name = CString::new("pers").unwrap();
-    let model_name = CString::new("model").unwrap();
-    let dir_light_direction_name = CString::new("dir_light.direction").unwrap();
-    let dir_light_color_name = CString::new("dir_light.color").unwrap();
-    let object_color_name = CString::new("color").unwrap();
+    shader.set_mat4("model", &model_matrix(transform));
+    gl::BindVertexArray(object.vertex_array_object);
 
-    let view_mat_location = gl::GetUniformLocation(shader, uniform_name.as_ptr());
-    let pers_mat_location = gl::GetUniformLocation(shader, perspective_name.as_ptr());
-    let model_mat_location = gl::GetUniformLocation(shader, model_name.as_ptr());
-    let dir_light_location = gl::GetUniformLocation(shader, dir_light_direction_name.as_ptr());
-    let dir_light_color_location = gl::GetUniformLocation(shader, dir_light_color_name.as_ptr());
-    let object_color_location = gl::GetUniformLocation(shader, object_color_name.as_ptr());
+    draw_params(shader);
+    gl::DrawElements(
+        gl::TRIANGLES,
+        object.size_of_elements,
+        gl::UNSIGNED_INT,
+        0 as *const c_void,
+    );
+    stats::record_draw(object.size_of_elements as u64 / 3);
+    gl::BindVertexArray(0);
+    Ok(())
+}
 
-    gl::UseProgram(shader);
+///First attribute of the per instance model matrix, a mat4 takes this and the next three locations
+pub const INSTANCE_MODEL_LOCATION: u32 = 3;
 
-    gl::UniformMatrix4fv(
-        view_mat_location,
-        1,
-        gl::FALSE,
-        view_matrix.as_slice().as_ptr(),
-    );
-    gl::UniformMatrix4fv(
-        pers_mat_location,
-        1,
-        gl::FALSE,
-        perspective_matrix.as_slice().as_ptr(),
-    );
-    gl::UniformMatrix4fv(
-        model_mat_location,
-        1,
-        gl::FALSE,
-        model_matrix.as_slice().as_ptr(),
-    );
+///Draws the object once for every model matrix with a single call, the shader reads the model from
+///the instance attributes instead of the model uniform
+pub unsafe fn draw_instanced_objects<T>(
+    world: &World,
+    shader_label: &String,
+    camera: &Camera,
+    object: &RenderObject,
+    instance_buffer: u32,
+    transforms: &[&TransformComponent],
+    light: &Light,
+    material: &Material,
+    draw_params: T,
+) -> Result<(), DrawError>
+where
+    T: FnOnce(),
+{
+    let resources = world.resources.read().unwrap();
+    use_scene_shader(scene_shader(&resources, shader_label)?, camera, light, material);
 
-    gl::Uniform3fv(dir_light_location, 1, light.direction.as_ptr());
-    gl::Uniform3fv(dir_light_color_location, 1, light.color.as_ptr());
+    let models: Vec<Matrix4<f32>> = transforms.iter().map(|transform| model_matrix(transform)).collect();
+    let matrix_size = std::mem::size_of::<Matrix4<f32>>();
+    let column_size = matrix_size / 4;
 
-    //TODO(use objects color)
-    let default_color = [0.7, 0.7, 0.7];
-    gl::Uniform3fv(object_color_location, 1, default_color.as_ptr());
     gl::BindVertexArray(object.vertex_array_object);
+    gl::BindBuffer(gl::ARRAY_BUFFER, instance_buffer);
+    //Note(teddy) The buffer is refilled every frame, the old storage is dropped instead of waited on
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (models.len() * matrix_size) as isize,
+        models.as_ptr().cast(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    for column in 0..4 {
+        let location = INSTANCE_MODEL_LOCATION + column as u32;
+        gl::EnableVertexAttribArray(location);
+        gl::VertexAttribPointer(
+            location,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            matrix_size as i32,
+            (column * column_size) as *const c_void,
+        );
+        gl::VertexAttribDivisor(location, 1);
+    }
 
     draw_params();
-    gl::DrawElements(
+    gl::DrawElementsInstanced(
         gl::TRIANGLES,
         object.size_of_elements,
         gl::UNSIGNED_INT,
         0 as *const c_void,
+        models.len() as i32,
     );
+    stats::record_draw(object.size_of_elements as u64 / 3 * models.len() as u64);
+
+    //Note(teddy) The vao is the entity's own, leave it the way the single draws expect it
+    for column in 0..4 {
+        gl::DisableVertexAttribArray(INSTANCE_MODEL_LOCATION + column);
+    }
     gl::BindVertexArray(0);
     Ok(())
 }
 
-//TODO(teddy) Remove the scale, A wrapper function will be use to load the specified font sizes
-//Replace the scale with the font's pixel height
+pub fn model_matrix(transform: &TransformComponent) -> Matrix4<f32> {
+    let scale = transform.scale;
+    let scale_matrix = Matrix4::new(
+        scale, 0.0, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, 0.0, 1.0,
+    );
+    transform.position.to_homogeneous() * scale_matrix
+}
+
+pub fn scene_shader<'a>(resources: &'a Resources, shader_label: &String) -> Result<&'a ShaderProgram, DrawError> {
+    match resources.shaders.get(shader_label) {
+        Some(Some(shader)) => Ok(shader),
+        //Shader is not available skip
+        Some(None) => Err(DrawError::ShaderNotAvailable(shader_label.clone())),
+        None => Err(DrawError::ShaderNotFound(shader_label.clone())),
+    }
+}
+
+///Puts the shader in use with the camera, light and material uniforms every scene draw sets
+unsafe fn use_scene_shader(shader: &ShaderProgram, camera: &Camera, light: &Light, material: &Material) {
+    //TODO(teddy) precompute the transformation matrices then send
+    shader.bind();
+    shader.set_mat4("view", &camera.view());
+    shader.set_mat4("pers", &camera.perspective());
+
+    shader.set_vec3("dir_light.direction", &light.direction);
+    shader.set_vec3("dir_light.color", &light.color);
+    shader.set_f32("ambient_strength", light.ambient);
+    shader.set_vec3("view_position", &[camera.position.x, camera.position.y, camera.position.z]);
+
+    shader.set_vec3("color", &material.base_color);
+    shader.set_f32("specular_strength", material.specular_strength);
+    shader.set_f32("shininess", material.shininess);
+    shader.set_f32("opacity", material.opacity);
+}
+
+///Same as draw_normal_object with the texture bound as the diffuse_map, the shader has to sample it
+pub unsafe fn draw_textured_object<T>(
+    world: &World,
+    shader_label: &String,
+    camera: &Camera,
+    object: &RenderObject,
+    transform: &TransformComponent,
+    light: &Light,
+    material: &Material,
+    diffuse_map: u32,
+    draw_params: T,
+) -> Result<(), DrawError>
+where
+    T: FnOnce(),
+{
+    let bind_params = |shader: &ShaderProgram| {
+        draw_params();
+        bind_diffuse_map(shader, diffuse_map);
+    };
+
+    draw_scene_object(world, shader_label, camera, object, transform, light, material, bind_params)
+}
+
+///Lines along the vertex and face normals of the object's triangles, the shader needs a geometry stage
+///that takes triangles. Length is in world units.
+pub unsafe fn draw_normals(
+    world: &World,
+    shader_label: &String,
+    camera: &Camera,
+    object: &RenderObject,
+    transform: &TransformComponent,
+    length: f32,
+) -> Result<(), DrawError> {
+    let resources = world.resources.read().unwrap();
+    let shader = scene_shader(&resources, shader_label)?;
+
+    shader.bind();
+    shader.set_mat4("model", &model_matrix(transform));
+    shader.set_mat4("view", &camera.view());
+    shader.set_mat4("pers", &camera.perspective());
+    shader.set_f32("normal_length", length);
+
+    gl::Enable(gl::DEPTH_TEST);
+    gl::DepthFunc(gl::LEQUAL);
+    gl::BindVertexArray(object.vertex_array_object);
+    gl::DrawElements(gl::TRIANGLES, object.size_of_elements, gl::UNSIGNED_INT, 0 as *const c_void);
+    stats::record_draw(object.size_of_elements as u64 / 3);
+    gl::BindVertexArray(0);
+    gl::DepthFunc(gl::LESS);
+    Ok(())
+}
+
+//TODO(teddy) Remove the scale, sizes come from the FontCache now
 pub unsafe fn draw_text(
     text_vao: u32,
     text_vbo: u32,
     engine: &Engine,
-    shader_id: u32,
+    font_face: &FontFace,
+    shader: &ShaderProgram,
     text: &str,
-    mut x: f32,
-    mut y: f32,
+    x: f32,
+    y: f32,
     scale: f32,
     color: &Vector3<f32>,
 ) {
-    gl::Enable(gl::BLEND);
-    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    let vertices = glyph_quads(font_face, text, x, y, scale, engine.ui_size().height);
 
-    gl::UseProgram(shader_id);
+    upload_text_vertices(text_vbo, &vertices);
+    draw_text_vertices(text_vao, engine, shader, font_face.atlas_texture(), vertices.len() as i32, color);
+}
 
+///Two triangles for every character of the text with the glyph's atlas uvs, x and y are window cords of the top left
+pub fn glyph_quads(font_face: &FontFace, text: &str, mut x: f32, y: f32, scale: f32, viewport_height: i32) -> Vec<[f32; 4]> {
     //Note(teddy) Since opengl's origin cords are at the bottom. We decrement the y with font_size
     //to accurately map the font cords to the screen
-    let ViewPortDimensions { width, height } = engine.camera.view_port;
+    let y = viewport_height as f32 - y - font_face.font_size as f32;
+    //Note(teddy) Glyphs are rendered pixel_ratio times bigger than the ui pixels they are drawn in
+    let scale = scale / font_face.pixel_ratio();
+    let mut vertices = Vec::with_capacity(text.len() * 6);
 
-    y = height as f32 - y - engine.font_face.font_size as f32;
+    for c in text.chars() {
+        let character = match font_face.glyph(c) {
+            Some(character) => character,
+            None => continue,
+        };
+        let xposition = x + character.bearing.x as f32 * scale;
+        let yposition = y - (character.size.y - character.bearing.y) as f32 * scale;
 
-    let projection: Matrix4<f32> =
-        Matrix4::new_orthographic(0.0, width as f32, 0.0, height as f32, -1.0, 1.0);
+        let w: f32 = character.size.x as f32 * scale;
+        let h: f32 = character.size.y as f32 * scale;
+        let [u0, v0, u1, v1] = character.uv;
 
-    //dbg!(projection);
-    let projection_uniform_name = CString::new("projection").unwrap();
-    let text_color_name = CString::new("text_color").unwrap();
+        vertices.extend_from_slice(&[
+            [xposition, yposition + h, u0, v0],
+            [xposition, yposition, u0, v1],
+            [xposition + w, yposition, u1, v1],
+            [xposition, yposition + h, u0, v0],
+            [xposition + w, yposition, u1, v1],
+            [xposition + w, yposition + h, u1, v0],
+        ]);
 
-    let projection_uniform_location =
-        gl::GetUniformLocation(shader_id, projection_uniform_name.as_ptr());
+        x += (character.advance >> 6) as f32 * scale;
+    }
 
-    let text_color_uniform_location = gl::GetUniformLocation(shader_id, text_color_name.as_ptr());
+    vertices
+}
 
-    gl::UniformMatrix4fv(
-        projection_uniform_location,
-        1,
-        gl::FALSE,
-        projection.as_slice().as_ptr(),
+///Replaces what the text buffer holds, the buffer grows to fit
+pub unsafe fn upload_text_vertices(text_vbo: u32, vertices: &[[f32; 4]]) {
+    gl::BindBuffer(gl::ARRAY_BUFFER, text_vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (vertices.len() * 4 * std::mem::size_of::<f32>()) as isize,
+        vertices.as_ptr() as *const c_void,
+        gl::DYNAMIC_DRAW,
     );
-    gl::Uniform3f(text_color_uniform_location, color.x, color.y, color.z);
-    gl::ActiveTexture(gl::TEXTURE0);
-    gl::BindVertexArray(text_vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+}
 
-    for c in text.chars() {
-        let character = &engine.font_face.chars[&c];
-        let xposition = x + character.bearing.x as f32 * scale;
-        let yposition = y - (character.size.y - character.bearing.y) as f32 * scale;
+///Draws the glyph quads already uploaded to the text buffer with the font's atlas, a single draw call
+pub unsafe fn draw_text_vertices(text_vao: u32, engine: &Engine, shader: &ShaderProgram, atlas_texture: u32, vertex_count: i32, color: &Vector3<f32>) {
+    gl::Enable(gl::BLEND);
+    blend_ui();
 
-        let w: f32 = character.size.x as f32 * scale;
-        let h: f32 = character.size.y as f32 * scale;
+    shader.bind();
 
-        let vertices: [[f32; 4]; 6] = [
-            [xposition, yposition + h, 0.0, 0.0],
-            [xposition, yposition, 0.0, 1.0],
-            [xposition + w, yposition, 1.0, 1.0],
-            [xposition, yposition + h, 0.0, 0.0],
-            [xposition + w, yposition, 1.0, 1.0],
-            [xposition + w, yposition + h, 1.0, 0.0],
-        ];
-
-        gl::BindTexture(gl::TEXTURE_2D, character.texture);
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, text_vbo);
-        gl::BufferSubData(
-            gl::ARRAY_BUFFER,
-            0,
-            (vertices.len() * 4 * std::mem::size_of::<f32>()) as isize,
-            vertices.as_ptr() as *const c_void,
-        );
+    let ViewPortDimensions { width, height } = engine.ui_size();
 
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::DrawArrays(gl::TRIANGLES, 0, 6);
+    let projection: Matrix4<f32> =
+        Matrix4::new_orthographic(0.0, width as f32, 0.0, height as f32, -1.0, 1.0);
 
-        x += (character.advance >> 6) as f32 * scale;
-    }
+    shader.set_mat4("projection", &projection);
+    shader.set_vec3("text_color", &[color.x, color.y, color.z]);
+    gl::ActiveTexture(gl::TEXTURE0);
+    gl::BindVertexArray(text_vao);
+
+    gl::BindTexture(gl::TEXTURE_2D, atlas_texture);
+    gl::DrawArrays(gl::TRIANGLES, 0, vertex_count);
+    stats::record_texture_bind();
+    stats::record_draw(vertex_count as u64 / 3);
 
     gl::BindVertexArray(0);
     gl::BindTexture(gl::TEXTURE_2D, 0);
 }
 
 //Note(teddy) Draw any quad
+///Two triangles covering (x, y) to (x + w, y + h) in opengl cords
+pub fn quad_vertices(z_position: f32, (x, y): (f32, f32), (h, w): (f32, f32)) -> [[f32; 3]; 6] {
+    [
+        [x, y + h, z_position],
+        [x, y, z_position],
+        [x + w, y, z_position],
+        [x, y + h, z_position],
+        [x + w, y, z_position],
+        [x + w, y + h, z_position],
+    ]
+}
+
+///quad_vertices with uv cords appended, v runs up like opengl's texture origin unless flipped
+pub fn textured_quad_vertices(
+    z_position: f32,
+    (x, y): (f32, f32),
+    (h, w): (f32, f32),
+    flip_vertical: bool,
+) -> [[f32; 5]; 6] {
+    let uvs: [[f32; 2]; 6] = [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+    let mut vertices = [[0.0; 5]; 6];
+
+    for (vertex, (position, uv)) in vertices.iter_mut().zip(quad_vertices(z_position, (x, y), (h, w)).iter().zip(uvs.iter())) {
+        let v = if flip_vertical { 1.0 - uv[1] } else { uv[1] };
+        *vertex = [position[0], position[1], position[2], uv[0], v];
+    }
+
+    vertices
+}
+
+///Window cords have their origin at the top, opengl's is at the bottom
+pub fn window_to_gl_y(viewport_height: f32, y: f32) -> f32 {
+    viewport_height - y
+}
+
 pub unsafe fn draw_quad(
     quad_vao: u32,
     quad_vbo: u32,
@@ -369,14 +618,7 @@ pub unsafe fn draw_quad(
     (x, y): (f32, f32),
     (h, w): (f32, f32),
 ) {
-    let vertices: [[f32; 3]; 6] = [
-        [x, y + h, z_position],
-        [x, y, z_position],
-        [x + w, y, z_position],
-        [x, y + h, z_position],
-        [x + w, y, z_position],
-        [x + w, y + h, z_position],
-    ];
+    let vertices = quad_vertices(z_position, (x, y), (h, w));
 
     gl::BindVertexArray(quad_vao);
     gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
@@ -390,10 +632,18 @@ pub unsafe fn draw_quad(
     //Note(teddy) Unbinding the quad_vbo
     gl::BindBuffer(gl::ARRAY_BUFFER, 0);
     gl::DrawArrays(gl::TRIANGLES, 0, 6);
+    stats::record_draw(2);
 
     gl::BindVertexArray(0);
 }
 
+///Ui alpha blending. The ui framebuffer starts out transparent and keeps the coverage in its alpha,
+///the screen shader uses it to put the ui over the scene.
+#[inline(always)]
+unsafe fn blend_ui() {
+    gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+}
+
 pub unsafe fn draw_quad_with_default_shader(
     engine: &Engine,
     quad_vao: u32,
@@ -403,31 +653,79 @@ pub unsafe fn draw_quad_with_default_shader(
     (h, w): (f32, f32),
     color: &[f32; 3],
 ) {
-    use crate::ui::ui::UI_QUAD_SHADER_ID;
+    let [r, g, b] = *color;
+    draw_ui_quad(engine, quad_vao, quad_vbo, z_position, (x, y), (h, w), &[r, g, b, 1.0], &QuadStyle::default());
+}
+
+///Border and rounded corners of a ui quad, the default is a flat quad
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QuadStyle {
+    ///Color and width in pixels, drawn inside the quad's edges over the fill
+    pub border: Option<([f32; 3], f32)>,
+    pub corner_radius: f32,
+}
+
+//Note(teddy) The border and the corners come from a distance function in ui_quad_frag.glsl, the quad stays one draw
+pub unsafe fn draw_styled_quad(
+    engine: &Engine,
+    quad_vao: u32,
+    quad_vbo: u32,
+    z_position: f32,
+    (x, y): (f32, f32),
+    (h, w): (f32, f32),
+    color: &[f32; 4],
+    style: &QuadStyle,
+) {
+    draw_ui_quad(engine, quad_vao, quad_vbo, z_position, (x, y), (h, w), color, style);
+}
+
+//Note(teddy) Blends the quad over what is already drawn, used for the backdrop behind modals
+pub unsafe fn draw_translucent_quad(
+    engine: &Engine,
+    quad_vao: u32,
+    quad_vbo: u32,
+    z_position: f32,
+    (x, y): (f32, f32),
+    (h, w): (f32, f32),
+    color: &[f32; 3],
+    alpha: f32,
+) {
+    let [r, g, b] = *color;
+    draw_ui_quad(engine, quad_vao, quad_vbo, z_position, (x, y), (h, w), &[r, g, b, alpha], &QuadStyle::default());
+}
 
-    let program = UI_QUAD_SHADER_ID;
-    gl::UseProgram(program);
+unsafe fn draw_ui_quad(
+    engine: &Engine,
+    quad_vao: u32,
+    quad_vbo: u32,
+    z_position: f32,
+    (x, y): (f32, f32),
+    (h, w): (f32, f32),
+    color: &[f32; 4],
+    style: &QuadStyle,
+) {
+    let program = &engine.ui.quad_shader;
+    program.bind();
 
-    let ViewPortDimensions {width, height} = engine.camera.view_port;
+    let ViewPortDimensions {width, height} = engine.ui_size();
 
     let projection: Matrix4<f32> =
         Matrix4::new_orthographic(0.0, width as f32, 0.0, height as f32, -1.0, 1.0);
-    let color_uniform_name = CString::new("quad_color").unwrap();
-    let projection_name = CString::new("projection").unwrap();
 
-    let color_uniform_location = gl::GetUniformLocation(program, color_uniform_name.as_ptr());
-    let projection_location = gl::GetUniformLocation(program, projection_name.as_ptr());
+    program.set_vec4("quad_color", color);
+    program.set_mat4("projection", &projection);
 
-    gl::Uniform3fv(color_uniform_location, 1, color.as_ptr());
-    gl::UniformMatrix4fv(
-        projection_location,
-        1,
-        gl::FALSE,
-        projection.as_slice().as_ptr(),
-    );
+    let gl_y = window_to_gl_y(height as f32, y);
+    let (border_color, border_width) = style.border.unwrap_or(([0.0; 3], 0.0));
+
+    //Note(teddy) Every quad sets these, the uniforms outlive the draw call
+    program.set_vec4("quad_rect", &[x, gl_y, w, h]);
+    program.set_f32("corner_radius", style.corner_radius);
+    program.set_vec3("border_color", &border_color);
+    program.set_f32("border_width", border_width);
 
     gl::Enable(gl::BLEND);
-    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    blend_ui();
 
     gl::Enable(gl::DEPTH_TEST);
     gl::DepthFunc(gl::LESS);
@@ -436,7 +734,84 @@ pub unsafe fn draw_quad_with_default_shader(
         quad_vao,
         quad_vbo,
         z_position,
-        (x, height as f32 - y),
+        (x, gl_y),
         (h, w),
     );
 }
+
+//Note(teddy) Same cords as draw_quad_with_default_shader, the quad is filled with the texture instead of a color
+pub unsafe fn draw_textured_quad(
+    engine: &Engine,
+    quad_vao: u32,
+    quad_vbo: u32,
+    texture: u32,
+    z_position: f32,
+    (x, y): (f32, f32),
+    (h, w): (f32, f32),
+    flip_vertical: bool,
+) {
+    let program = &engine.ui.image_shader;
+    program.bind();
+
+    let ViewPortDimensions {width, height} = engine.ui_size();
+
+    let projection: Matrix4<f32> =
+        Matrix4::new_orthographic(0.0, width as f32, 0.0, height as f32, -1.0, 1.0);
+
+    program.set_mat4("projection", &projection);
+    program.set_i32("image", 0);
+
+    gl::Enable(gl::BLEND);
+    blend_ui();
+
+    gl::Enable(gl::DEPTH_TEST);
+    gl::DepthFunc(gl::LESS);
+
+    let vertices = textured_quad_vertices(z_position, (x, window_to_gl_y(height as f32, y)), (h, w), flip_vertical);
+
+    gl::ActiveTexture(gl::TEXTURE0);
+    gl::BindTexture(gl::TEXTURE_2D, texture);
+    stats::record_texture_bind();
+    gl::BindVertexArray(quad_vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
+    gl::BufferSubData(
+        gl::ARRAY_BUFFER,
+        0,
+        (vertices.len() * 5 * std::mem::size_of::<f32>()) as isize,
+        vertices.as_ptr() as *const c_void,
+    );
+
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    gl::DrawArrays(gl::TRIANGLES, 0, 6);
+    stats::record_draw(2);
+
+    gl::BindVertexArray(0);
+    gl::BindTexture(gl::TEXTURE_2D, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use nalgebra::Point2;
+
+    use crate::core::FontChar;
+
+    #[test]
+    fn test_glyph_quads_use_the_atlas_uvs() {
+        let mut chars = HashMap::new();
+        for (c, uv) in [('a', [0.0, 0.0, 0.25, 0.5]), ('b', [0.25, 0.0, 0.5, 0.5]), ('?', [0.5, 0.0, 0.75, 0.5])].iter() {
+            chars.insert(*c, FontChar { uv: *uv, size: Point2::new(8, 12), bearing: Point2::new(0, 12), advance: 8 << 6 });
+        }
+        let font_face = FontFace::new(String::from("test"), 12, chars);
+
+        let vertices = glyph_quads(&font_face, "ab", 0.0, 0.0, 1.0, 100);
+        assert_eq!(vertices.len(), 12);
+
+        //Note(teddy) Each character moves on by its advance, the y is flipped into opengl cords
+        assert_eq!(vertices[0], [0.0, 100.0, 0.0, 0.0]);
+        assert_eq!(vertices[7], [8.0, 88.0, 0.25, 0.5]);
+        assert_eq!(vertices[11], [16.0, 100.0, 0.5, 0.0]);
+    }
+}
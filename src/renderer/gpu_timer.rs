@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use crate::renderer::stats::RenderPass;
+
+//Note(teddy) Asking for a query's result right away waits for the gpu to catch up with the cpu. Every
+//frame gets its own set of queries and they're only read GPU_TIMER_LATENCY frames later, when the gpu
+//is long done with them.
+
+const GPU_TIMER_LATENCY: usize = 2;
+const GPU_TIMER_FRAMES: usize = GPU_TIMER_LATENCY + 1;
+const PASS_COUNT: usize = RenderPass::ALL.len();
+
+///GL_TIME_ELAPSED queries around the render passes
+pub struct GpuTimer {
+    queries: [[u32; PASS_COUNT]; GPU_TIMER_FRAMES],
+    ///Passes begun in each frame's set, a pass that didn't run that frame has nothing to read
+    issued: [[bool; PASS_COUNT]; GPU_TIMER_FRAMES],
+    frame: usize,
+    active: Option<RenderPass>,
+    ///Latest times read back, GPU_TIMER_LATENCY frames old
+    results: [Option<Duration>; PASS_COUNT],
+}
+
+///Set of queries written in the frame
+fn write_slot(frame: usize) -> usize {
+    frame % GPU_TIMER_FRAMES
+}
+
+///Set of queries read at the end of the frame, the one written GPU_TIMER_LATENCY frames before. It's
+///the next frame's write slot so nothing is overwritten before it's read.
+fn read_slot(frame: usize) -> usize {
+    (frame + GPU_TIMER_FRAMES - GPU_TIMER_LATENCY) % GPU_TIMER_FRAMES
+}
+
+impl GpuTimer {
+    ///None when the driver has no timer queries
+    pub unsafe fn new() -> Option<Self> {
+        if !gl::GenQueries::is_loaded() || !gl::GetQueryObjectui64v::is_loaded() {
+            return None;
+        }
+
+        while gl::GetError() != gl::NO_ERROR {}
+
+        //Note(teddy) Zero bits means the query type is there but never counts anything
+        let mut counter_bits = 0;
+        gl::GetQueryiv(gl::TIME_ELAPSED, gl::QUERY_COUNTER_BITS, &mut counter_bits);
+        if gl::GetError() != gl::NO_ERROR || counter_bits == 0 {
+            return None;
+        }
+
+        let mut queries = [[0; PASS_COUNT]; GPU_TIMER_FRAMES];
+        for slot in queries.iter_mut() {
+            gl::GenQueries(PASS_COUNT as i32, slot.as_mut_ptr());
+        }
+
+        Some(Self {
+            queries,
+            issued: [[false; PASS_COUNT]; GPU_TIMER_FRAMES],
+            frame: 0,
+            active: None,
+            results: [None; PASS_COUNT],
+        })
+    }
+
+    ///Only one time elapsed query can run at a time, the pass before is ended
+    pub unsafe fn begin_pass(&mut self, pass: RenderPass) {
+        self.end_pass();
+
+        let slot = write_slot(self.frame);
+        gl::BeginQuery(gl::TIME_ELAPSED, self.queries[slot][pass.index()]);
+        self.issued[slot][pass.index()] = true;
+        self.active = Some(pass);
+    }
+
+    pub unsafe fn end_pass(&mut self) {
+        if self.active.take().is_some() {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+    }
+
+    ///Reads back the oldest set and moves on to the next frame's
+    pub unsafe fn end_frame(&mut self) {
+        self.end_pass();
+
+        let slot = read_slot(self.frame);
+        for pass in RenderPass::ALL.iter() {
+            let index = pass.index();
+            if !self.issued[slot][index] {
+                continue;
+            }
+            self.issued[slot][index] = false;
+
+            //Note(teddy) Still not in after the wait means the gpu is badly behind, the last time stays
+            let query = self.queries[slot][index];
+            let mut available = 0;
+            gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            if available == 0 {
+                continue;
+            }
+
+            let mut nanoseconds = 0;
+            gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanoseconds);
+            self.results[index] = Some(Duration::from_nanos(nanoseconds));
+        }
+
+        self.frame += 1;
+    }
+
+    pub fn time(&self, pass: RenderPass) -> Option<Duration> {
+        self.results[pass.index()]
+    }
+
+    pub unsafe fn dispose(mut self) {
+        self.end_pass();
+        for slot in self.queries.iter() {
+            gl::DeleteQueries(PASS_COUNT as i32, slot.as_ptr());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries_are_read_two_frames_after_they_are_written() {
+        for frame in 0..10 {
+            assert_eq!(read_slot(frame + GPU_TIMER_LATENCY), write_slot(frame));
+            //Note(teddy) Read at the end of the frame, just before the next frame writes over it
+            assert_eq!(read_slot(frame), write_slot(frame + 1));
+        }
+    }
+}
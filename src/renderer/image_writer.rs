@@ -0,0 +1,459 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::utils::crc32_update;
+
+pub const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PixelFormat {
+    Rgb8,
+    Rgba8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 => 4,
+        }
+    }
+
+    fn png_color_type(&self) -> u8 {
+        match self {
+            PixelFormat::Rgb8 => 2,
+            PixelFormat::Rgba8 => 6,
+        }
+    }
+}
+
+///Pixels read back from GL, written as a PNG on the writer thread
+#[derive(Debug)]
+pub struct ImageJob {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    ///GL reads rows bottom up, images are stored top down
+    pub flip_y: bool,
+    pub path: String,
+    pub format: PixelFormat,
+    ///Box filtered down to this size before encoding, used for thumbnails
+    pub resize: Option<(u32, u32)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ImageWriterError {
+    ///The queue is full, the caller should drop or retry the job later
+    Busy,
+    ShutDown,
+}
+
+#[derive(Debug)]
+pub struct ImageWriteResult {
+    pub path: String,
+    pub result: Result<(), String>,
+}
+
+///What happens to the jobs still queued when the writer shuts down
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShutdownPolicy {
+    Drain,
+    Discard,
+}
+
+type WriteFn = dyn Fn(&ImageJob) -> Result<(), String> + Send;
+
+///Background thread encoding PNGs so screenshots and thumbnails don't stall the frame.
+///The main thread only pays for the pixel copy and the channel send.
+pub struct ImageWriter {
+    sender: Option<SyncSender<ImageJob>>,
+    results: Receiver<ImageWriteResult>,
+    worker: Option<JoinHandle<()>>,
+    discard: Arc<AtomicBool>,
+    policy: ShutdownPolicy,
+}
+
+impl ImageWriter {
+    pub fn new(capacity: usize, policy: ShutdownPolicy) -> Self {
+        Self::with_writer(capacity, policy, Box::new(write_png_job))
+    }
+
+    pub fn with_writer(capacity: usize, policy: ShutdownPolicy, write: Box<WriteFn>) -> Self {
+        let (sender, receiver) = sync_channel::<ImageJob>(capacity);
+        let (result_sender, results) = channel();
+        let discard = Arc::new(AtomicBool::new(false));
+        let discard_ref = Arc::clone(&discard);
+
+        let worker = thread::spawn(move || run_worker(receiver, result_sender, discard_ref, write));
+
+        Self {
+            sender: Some(sender),
+            results,
+            worker: Some(worker),
+            discard,
+            policy,
+        }
+    }
+
+    ///Never blocks, a full queue is reported as Busy
+    pub fn submit(&self, job: ImageJob) -> Result<(), ImageWriterError> {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return Err(ImageWriterError::ShutDown),
+        };
+
+        match sender.try_send(job) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(ImageWriterError::Busy),
+            Err(TrySendError::Disconnected(_)) => Err(ImageWriterError::ShutDown),
+        }
+    }
+
+    ///Completed jobs since the last poll
+    pub fn poll_results(&self) -> Vec<ImageWriteResult> {
+        let mut output = vec![];
+        loop {
+            match self.results.try_recv() {
+                Ok(result) => output.push(result),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        output
+    }
+
+    pub fn shutdown(&mut self) {
+        if self.policy == ShutdownPolicy::Discard {
+            self.discard.store(true, Ordering::SeqCst);
+        }
+
+        //Note(teddy) Dropping the sender ends the worker loop once the queue is empty
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for ImageWriter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run_worker(
+    receiver: Receiver<ImageJob>,
+    results: Sender<ImageWriteResult>,
+    discard: Arc<AtomicBool>,
+    write: Box<WriteFn>,
+) {
+    while let Ok(job) = receiver.recv() {
+        if discard.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let result = write(&job);
+        let _ = results.send(ImageWriteResult { path: job.path, result });
+    }
+}
+
+fn write_png_job(job: &ImageJob) -> Result<(), String> {
+    let pixels = if job.flip_y {
+        flip_rows(&job.pixels, job.width, job.height, job.format)
+    } else {
+        job.pixels.clone()
+    };
+
+    let (pixels, width, height) = match job.resize {
+        Some((width, height)) => (
+            downscale(&pixels, job.width, job.height, job.format, width, height),
+            width,
+            height,
+        ),
+        None => (pixels, job.width, job.height),
+    };
+
+    let png = encode_png(&pixels, width, height, job.format)?;
+
+    if let Some(parent) = Path::new(&job.path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| format!("{}", e))?;
+        }
+    }
+
+    let mut file = File::create(&job.path).map_err(|e| format!("{}", e))?;
+    file.write_all(&png).map_err(|e| format!("{}", e))
+}
+
+pub fn flip_rows(pixels: &[u8], width: u32, height: u32, format: PixelFormat) -> Vec<u8> {
+    let row_length = width as usize * format.bytes_per_pixel();
+    let mut output = Vec::with_capacity(pixels.len());
+
+    for row in (0..height as usize).rev() {
+        output.extend_from_slice(&pixels[row * row_length..(row + 1) * row_length]);
+    }
+
+    output
+}
+
+///Averages the source pixels covered by each destination pixel
+pub fn downscale(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    new_width: u32,
+    new_height: u32,
+) -> Vec<u8> {
+    let channels = format.bytes_per_pixel();
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = (new_width as usize, new_height as usize);
+    let mut output = Vec::with_capacity(new_width * new_height * channels);
+
+    for y in 0..new_height {
+        let y_start = y * height / new_height;
+        let y_end = ((y + 1) * height / new_height).max(y_start + 1).min(height);
+
+        for x in 0..new_width {
+            let x_start = x * width / new_width;
+            let x_end = ((x + 1) * width / new_width).max(x_start + 1).min(width);
+            let count = ((y_end - y_start) * (x_end - x_start)).max(1);
+
+            for channel in 0..channels {
+                let mut sum = 0usize;
+                for source_y in y_start..y_end {
+                    for source_x in x_start..x_end {
+                        sum += pixels[(source_y * width + source_x) * channels + channel] as usize;
+                    }
+                }
+                output.push((sum / count) as u8);
+            }
+        }
+    }
+
+    output
+}
+
+///Minimal PNG encoder, the image data is stored in uncompressed deflate blocks.
+///The files are bigger than they need to be but it keeps the engine free of an image dependency
+pub fn encode_png(pixels: &[u8], width: u32, height: u32, format: PixelFormat) -> Result<Vec<u8>, String> {
+    let row_length = width as usize * format.bytes_per_pixel();
+    if pixels.len() != row_length * height as usize {
+        return Err(format!(
+            "Expected {} bytes for a {}x{} image, got {}",
+            row_length * height as usize,
+            width,
+            height,
+            pixels.len()
+        ));
+    }
+
+    //Note(teddy) Every scanline starts with its filter type, 0 = None
+    let mut raw = Vec::with_capacity((row_length + 1) * height as usize);
+    for row in pixels.chunks(row_length.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, format.png_color_type(), 0, 0, 0]);
+
+    let mut output = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", &header);
+    write_chunk(&mut output, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut output, b"IEND", &[]);
+    Ok(output)
+}
+
+fn write_chunk(output: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    output.extend_from_slice(chunk_type);
+    output.extend_from_slice(data);
+
+    let crc = crc32_update(crc32_update(0xFFFF_FFFF, chunk_type), data) ^ 0xFFFF_FFFF;
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(MAX_STORED_BLOCK).collect()
+    };
+
+    for (i, block) in blocks.iter().enumerate() {
+        let is_final = (i + 1 == blocks.len()) as u8;
+        let length = block.len() as u16;
+
+        output.push(is_final);
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn job(path: &str) -> ImageJob {
+        ImageJob {
+            pixels: vec![0; 12],
+            width: 2,
+            height: 2,
+            flip_y: false,
+            path: String::from(path),
+            format: PixelFormat::Rgb8,
+            resize: None,
+        }
+    }
+
+    //Note(teddy) Reads the stored blocks back out of the IDAT chunk
+    fn decode_stored_png(png: &[u8]) -> (u32, u32, u8, Vec<u8>) {
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+        let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+        let color_type = png[25];
+
+        let idat_length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let zlib = &png[41..41 + idat_length];
+
+        let mut raw = vec![];
+        let mut offset = 2;
+        loop {
+            let is_final = zlib[offset] & 1 == 1;
+            let length = u16::from_le_bytes([zlib[offset + 1], zlib[offset + 2]]) as usize;
+            raw.extend_from_slice(&zlib[offset + 5..offset + 5 + length]);
+            offset += 5 + length;
+            if is_final {
+                break;
+            }
+        }
+
+        let adler = u32::from_be_bytes([zlib[offset], zlib[offset + 1], zlib[offset + 2], zlib[offset + 3]]);
+        assert_eq!(adler, adler32(&raw));
+        (width, height, color_type, raw)
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_flip_rows() {
+        let pixels: Vec<u8> = (0..12).collect();
+        let flipped = flip_rows(&pixels, 2, 2, PixelFormat::Rgb8);
+        assert_eq!(flipped, vec![6, 7, 8, 9, 10, 11, 0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_downscale_averages_blocks() {
+        let pixels: Vec<u8> = (0..16).map(|i| i * 10).collect();
+        let gray: Vec<u8> = pixels.iter().flat_map(|v| vec![*v, *v, *v]).collect();
+        let scaled = downscale(&gray, 4, 4, PixelFormat::Rgb8, 2, 2);
+        assert_eq!(&scaled[..3], &[25, 25, 25]);
+        assert_eq!(scaled.len(), 2 * 2 * 3);
+        assert_eq!(&scaled[9..], &[125, 125, 125]);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let pixels: Vec<u8> = (0..16).collect();
+        let png = encode_png(&pixels, 2, 2, PixelFormat::Rgba8).unwrap();
+        let (width, height, color_type, raw) = decode_stored_png(&png);
+
+        assert_eq!((width, height, color_type), (2, 2, 6));
+        assert_eq!(raw, vec![0, 0, 1, 2, 3, 4, 5, 6, 7, 0, 8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        assert!(encode_png(&pixels, 3, 2, PixelFormat::Rgba8).is_err());
+    }
+
+    #[test]
+    fn test_encode_large_png_splits_blocks() {
+        let pixels = vec![7u8; 300 * 100 * 3];
+        let png = encode_png(&pixels, 300, 100, PixelFormat::Rgb8).unwrap();
+        let (_, _, _, raw) = decode_stored_png(&png);
+        assert_eq!(raw.len(), (300 * 3 + 1) * 100);
+    }
+
+    #[test]
+    fn test_full_queue_reports_busy() {
+        let (gate_sender, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let (taken_sender, taken) = channel::<String>();
+
+        let mut writer = ImageWriter::with_writer(
+            1,
+            ShutdownPolicy::Drain,
+            Box::new(move |job| {
+                taken_sender.send(job.path.clone()).unwrap();
+                gate.lock().unwrap().recv().unwrap();
+                Ok(())
+            }),
+        );
+
+        //Note(teddy) Once the worker has taken a and blocks on the gate, b fills the queue
+        writer.submit(job("a.png")).unwrap();
+        assert_eq!(taken.recv().unwrap(), "a.png");
+        writer.submit(job("b.png")).unwrap();
+        assert_eq!(writer.submit(job("c.png")), Err(ImageWriterError::Busy));
+
+        let queued = 2;
+        for _ in 0..queued {
+            gate_sender.send(()).unwrap();
+        }
+        writer.shutdown();
+
+        let results = writer.poll_results();
+        assert_eq!(results.len(), queued);
+        assert!(results.iter().all(|r| r.result.is_ok()));
+        assert_eq!(writer.submit(job("d.png")), Err(ImageWriterError::ShutDown));
+    }
+
+    #[test]
+    fn test_discard_policy_drops_pending_jobs() {
+        let (gate_sender, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+
+        let mut writer = ImageWriter::with_writer(
+            4,
+            ShutdownPolicy::Discard,
+            Box::new(move |_job| {
+                gate.lock().unwrap().recv().unwrap();
+                Ok(())
+            }),
+        );
+
+        for name in &["a.png", "b.png", "c.png"] {
+            writer.submit(job(name)).unwrap();
+        }
+
+        gate_sender.send(()).unwrap();
+        writer.shutdown();
+
+        assert!(writer.poll_results().len() <= 1);
+    }
+}
@@ -1,2 +1,8 @@
+pub mod debug_draw;
 pub mod draw;
+pub mod gpu_timer;
+pub mod image_writer;
+pub mod png_decoder;
 pub mod shaders;
+pub mod stats;
+pub mod textures;
@@ -0,0 +1,492 @@
+use crate::renderer::image_writer::{adler32, PNG_SIGNATURE};
+use crate::utils::crc32;
+
+//Note(teddy) Minimal PNG decoder for textures, the counterpart of encode_png. It handles every color type
+//and bit depth but not interlaced images, those are rare for textures and can be saved again without it.
+
+///Past this the image is more likely a broken header than a texture
+const MAX_IMAGE_SIDE: u32 = 16384;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+///Order the code length code lengths of a dynamic block come in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+///RGBA8 pixels, the top row first
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PngHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl PngHeader {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    fn row_length(&self) -> usize {
+        (self.width as usize * self.bits_per_pixel() + 7) / 8
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let depths: &[u8] = match self.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            color_type => return Err(format!("Unknown PNG color type {}", color_type)),
+        };
+
+        if !depths.contains(&self.bit_depth) {
+            return Err(format!("Bit depth {} is not allowed for color type {}", self.bit_depth, self.color_type));
+        }
+
+        if self.width == 0 || self.height == 0 || self.width > MAX_IMAGE_SIDE || self.height > MAX_IMAGE_SIDE {
+            return Err(format!("Unsupported PNG size {}x{}", self.width, self.height));
+        }
+
+        Ok(())
+    }
+}
+
+///Width and height from the header without decoding the pixels, IHDR is always the first chunk
+pub fn png_size(bytes: &[u8]) -> Result<(u32, u32), String> {
+    if !bytes.starts_with(&PNG_SIGNATURE) {
+        return Err(String::from("Not a PNG file"));
+    }
+
+    let offset = PNG_SIGNATURE.len();
+    match bytes.get(offset + 4..offset + 16) {
+        Some(chunk) if &chunk[..4] == b"IHDR" => Ok((
+            u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+            u32::from_be_bytes([chunk[8], chunk[9], chunk[10], chunk[11]]),
+        )),
+        _ => Err(String::from("The PNG doesn't start with its IHDR chunk")),
+    }
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<DecodedImage, String> {
+    if !bytes.starts_with(&PNG_SIGNATURE) {
+        return Err(String::from("Not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = vec![];
+    let mut offset = PNG_SIGNATURE.len();
+
+    loop {
+        let length = bytes.get(offset..offset + 4).ok_or("The PNG ends before its IEND chunk")?;
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        let chunk = bytes.get(offset + 4..offset + 8 + length + 4).ok_or("A PNG chunk runs past the end of the file")?;
+        let (typed_data, crc) = chunk.split_at(4 + length);
+        if crc32(typed_data) != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err(format!("Bad checksum on the {} chunk", String::from_utf8_lossy(&typed_data[..4])));
+        }
+
+        let data = &typed_data[4..];
+        match &typed_data[..4] {
+            b"IHDR" if data.len() == 13 => {
+                if data[10] != 0 || data[11] != 0 {
+                    return Err(String::from("Unknown PNG compression or filter method"));
+                }
+                if data[12] != 0 {
+                    return Err(String::from("Interlaced PNGs are not supported, save the texture without interlacing"));
+                }
+
+                let parsed = PngHeader {
+                    width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+                    height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+                    bit_depth: data[8],
+                    color_type: data[9],
+                };
+                parsed.validate()?;
+                header = Some(parsed);
+            }
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => (),
+        }
+
+        offset += 12 + length;
+    }
+
+    let header = header.ok_or("The PNG has no header")?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(String::from("The PNG is indexed but has no palette"));
+    }
+
+    let mut raw = zlib_decompress(&compressed)?;
+    unfilter(&mut raw, header.row_length(), (header.bits_per_pixel() / 8).max(1), header.height as usize)?;
+
+    Ok(DecodedImage { width: header.width, height: header.height, pixels: to_rgba(&header, &raw, palette, transparency) })
+}
+
+///Undoes the per scanline filters in place, the filter bytes are left at the start of each row
+fn unfilter(raw: &mut [u8], row_length: usize, pixel_bytes: usize, rows: usize) -> Result<(), String> {
+    let stride = row_length + 1;
+    if raw.len() < stride * rows {
+        return Err(String::from("The PNG has less image data than its size needs"));
+    }
+
+    for row in 0..rows {
+        let (previous, current) = raw.split_at_mut(row * stride);
+        let previous = if row > 0 { &previous[previous.len() - row_length..] } else { &[][..] };
+        let filter = current[0];
+        let line = &mut current[1..stride];
+
+        for x in 0..row_length {
+            let left = if x >= pixel_bytes { line[x - pixel_bytes] } else { 0 };
+            let up = previous.get(x).copied().unwrap_or(0);
+            let up_left = if x >= pixel_bytes { previous.get(x - pixel_bytes).copied().unwrap_or(0) } else { 0 };
+
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                filter => return Err(format!("Unknown PNG filter {} on row {}", filter, row)),
+            };
+            line[x] = line[x].wrapping_add(predicted);
+        }
+    }
+
+    Ok(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - up_left as i16).abs());
+
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+///Expands the unfiltered scanlines of any color type and depth to RGBA8
+fn to_rgba(header: &PngHeader, raw: &[u8], palette: &[u8], transparency: &[u8]) -> Vec<u8> {
+    let (width, channels, depth) = (header.width as usize, header.channels(), header.bit_depth as usize);
+    let stride = header.row_length() + 1;
+    let max = (1u32 << depth.min(8)) - 1;
+    let mut pixels = Vec::with_capacity(width * header.height as usize * 4);
+
+    for line in raw.chunks(stride).take(header.height as usize) {
+        let line = &line[1..];
+        //Note(teddy) 16 bit samples keep their high byte, smaller ones are scaled up to 8 bits
+        let sample = |index: usize| -> u32 {
+            match depth {
+                16 => line[index * 2] as u32,
+                8 => line[index] as u32,
+                _ => (line[index * depth / 8] as u32 >> (8 - depth - (index * depth) % 8)) & max,
+            }
+        };
+        let scaled = |index: usize| if depth < 8 { sample(index) * 255 / max } else { sample(index) };
+
+        for x in 0..width {
+            let first = x * channels;
+            match header.color_type {
+                0 => {
+                    let gray = scaled(first) as u8;
+                    pixels.extend_from_slice(&[gray, gray, gray, 255]);
+                }
+                2 => pixels.extend_from_slice(&[scaled(first) as u8, scaled(first + 1) as u8, scaled(first + 2) as u8, 255]),
+                3 => {
+                    let index = sample(first) as usize;
+                    let color = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0, 0, 0]);
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    pixels.extend_from_slice(&[color[0], color[1], color[2], alpha]);
+                }
+                4 => {
+                    let gray = scaled(first) as u8;
+                    pixels.extend_from_slice(&[gray, gray, gray, scaled(first + 1) as u8]);
+                }
+                _ => pixels.extend_from_slice(&[scaled(first) as u8, scaled(first + 1) as u8, scaled(first + 2) as u8, scaled(first + 3) as u8]),
+            }
+        }
+    }
+
+    pixels
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    ///In bits
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, count: usize) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.data.get(self.position / 8).ok_or("The compressed PNG data ends early")?;
+            value |= ((*byte as u32 >> (self.position % 8)) & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = (self.position + 7) / 8 * 8;
+    }
+}
+
+///Canonical Huffman code, decoded a bit at a time
+struct Huffman {
+    ///Codes of each length, 1 to 15
+    counts: [u16; 16],
+    ///Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|symbol| lengths[*symbol as usize] != 0).collect();
+        symbols.sort_by_key(|symbol| lengths[*symbol as usize]);
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(String::from("Invalid Huffman code in the compressed PNG data"))
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].iter_mut().for_each(|length| *length = 9);
+    lengths[256..280].iter_mut().for_each(|length| *length = 7);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literals = reader.bits(5)? as usize + 257;
+    let distances = reader.bits(5)? as usize + 1;
+    let code_lengths = reader.bits(4)? as usize + 4;
+
+    let mut length_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+        length_lengths[*index] = reader.bits(3)? as u8;
+    }
+    let length_code = Huffman::new(&length_lengths);
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("A repeated code length has nothing before it")?, 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        lengths.extend(std::iter::repeat(value).take(repeat));
+    }
+
+    if lengths.len() != literals + distances {
+        return Err(String::from("The code lengths run past the end of the table"));
+    }
+
+    Ok((Huffman::new(&lengths[..literals]), Huffman::new(&lengths[literals..])))
+}
+
+fn inflate_block(reader: &mut BitReader, output: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(format!("Invalid length symbol {}", symbol));
+                }
+                let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as usize)? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(format!("Invalid distance symbol {}", index));
+                }
+                let distance = DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index] as usize)? as usize;
+                if distance > output.len() {
+                    return Err(String::from("A back reference points before the start of the data"));
+                }
+
+                //Note(teddy) The copy can overlap what it writes, it goes a byte at a time
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+}
+
+fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut output = vec![];
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let start = reader.position / 8;
+                let header = data.get(start..start + 4).ok_or("A stored block ends early")?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(String::from("A stored block's length doesn't match its complement"));
+                }
+                let block = data.get(start + 4..start + 4 + length as usize).ok_or("A stored block ends early")?;
+                output.extend_from_slice(block);
+                reader.position = (start + 4 + length as usize) * 8;
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(String::from("Invalid deflate block type")),
+        }
+
+        if is_final {
+            reader.align_to_byte();
+            return Ok((output, reader.position / 8));
+        }
+    }
+}
+
+fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 || data[0] & 0x0F != 8 || (data[0] as u16 * 256 + data[1] as u16) % 31 != 0 {
+        return Err(String::from("The PNG data is not a zlib stream"));
+    }
+    if data[1] & 0x20 != 0 {
+        return Err(String::from("zlib preset dictionaries are not supported"));
+    }
+
+    let (output, used) = inflate(&data[2..])?;
+    let checksum = data.get(2 + used..2 + used + 4).ok_or("The zlib stream has no checksum")?;
+    if adler32(&output) != u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) {
+        return Err(String::from("Bad checksum on the PNG image data"));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::image_writer::{encode_png, PixelFormat};
+
+    #[test]
+    fn test_decodes_what_the_encoder_writes() {
+        let rgb: Vec<u8> = (0..2 * 3 * 3).collect();
+        let image = decode_png(&encode_png(&rgb, 3, 2, PixelFormat::Rgb8).unwrap()).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(&image.pixels[..8], &[0, 1, 2, 255, 3, 4, 5, 255]);
+
+        let rgba: Vec<u8> = (0..300 * 100 * 4).map(|i| (i % 251) as u8).collect();
+        let image = decode_png(&encode_png(&rgba, 300, 100, PixelFormat::Rgba8).unwrap()).unwrap();
+        assert_eq!(image.pixels, rgba);
+
+        let mut broken = encode_png(&rgb, 3, 2, PixelFormat::Rgb8).unwrap();
+        let last = broken.len() - 20;
+        broken[last] ^= 1;
+        assert!(decode_png(&broken).is_err());
+        assert!(decode_png(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_inflates_huffman_blocks() {
+        //Note(teddy) From zlib at level 9, short text gets the fixed codes and longer text dynamic ones
+        let fixed = [120, 218, 75, 76, 132, 129, 36, 108, 32, 153, 8, 144, 130, 2, 82, 137, 4, 0, 31, 27, 47, 1];
+        let expected: Vec<u8> = [(b'a', 10), (b'b', 21), (b'c', 36), (b'd', 14), (b'e', 40)]
+            .iter()
+            .flat_map(|(c, count)| std::iter::repeat(*c).take(*count))
+            .collect();
+        assert_eq!(zlib_decompress(&fixed).unwrap(), expected);
+
+        let dynamic = [
+            120, 218, 53, 202, 209, 9, 128, 48, 12, 69, 209, 85, 222, 0, 226, 52, 46, 16, 154, 103, 45, 104, 82, 98, 11, 142, 111, 21, 253, 187, 92, 206, 194,
+            171, 245, 224, 9, 9, 66, 153, 92, 169, 112, 67, 219, 136, 221, 69, 139, 229, 209, 65, 81, 136, 41, 122, 125, 230, 32, 205, 95, 146, 107, 255, 245,
+            33, 197, 62, 58, 141, 151, 8, 193, 26, 114, 112, 190, 1, 47, 227, 35, 176,
+        ];
+        assert_eq!(
+            zlib_decompress(&dynamic).unwrap(),
+            b"Textures are decoded on the loading thread and uploaded to the gpu on the main thread, once a frame.".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_unfilters_every_filter_type() {
+        //Note(teddy) One byte pixels, the rows use None, Sub, Up, Average and Paeth in that order
+        let mut raw = vec![0, 10, 20, 30, 1, 5, 5, 5, 2, 1, 1, 1, 3, 5, 5, 5, 4, 1, 1, 1];
+        unfilter(&mut raw, 3, 1, 5).unwrap();
+
+        assert_eq!(&raw[5..8], &[5, 10, 15]);
+        assert_eq!(&raw[9..12], &[6, 11, 16]);
+        assert_eq!(&raw[13..16], &[8, 14, 20]);
+        assert_eq!(&raw[17..20], &[9, 15, 21]);
+        assert!(unfilter(&mut vec![5, 0], 1, 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_palette_and_small_depths_expand_to_rgba() {
+        let header = PngHeader { width: 3, height: 1, bit_depth: 2, color_type: 3 };
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+        //Note(teddy) Indices 2, 0, 1 packed in one byte
+        let pixels = to_rgba(&header, &[0, 0b1000_0100], &palette, &[128]);
+        assert_eq!(pixels, vec![0, 0, 255, 255, 255, 0, 0, 128, 0, 255, 0, 255]);
+
+        let gray = PngHeader { width: 2, height: 1, bit_depth: 1, color_type: 0 };
+        assert_eq!(to_rgba(&gray, &[0, 0b0100_0000], &[], &[]), vec![0, 0, 0, 255, 255, 255, 255, 255]);
+        assert!(PngHeader { width: 1, height: 1, bit_depth: 4, color_type: 6 }.validate().is_err());
+    }
+}
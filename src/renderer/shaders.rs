@@ -1,7 +1,11 @@
-use std::ffi::CString;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io::Read;
 use std::ptr::null;
+use std::sync::Mutex;
+
+use nalgebra::Matrix4;
 
 #[derive(Debug)]
 pub enum ShaderError {
@@ -10,6 +14,54 @@ pub enum ShaderError {
     GeometryError(String),
 }
 
+///A linked program and the locations of the uniforms it has been asked for, each name is only looked up once
+#[derive(Debug)]
+pub struct ShaderProgram {
+    pub id: u32,
+    //Note(teddy) Behind a mutex since the programs live in the shared resources and the setters only get &self
+    locations: Mutex<HashMap<&'static str, i32>>,
+}
+
+impl ShaderProgram {
+    pub fn new(id: u32) -> Self {
+        Self { id, locations: Mutex::new(HashMap::new()) }
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::UseProgram(self.id);
+    }
+
+    ///-1 for a uniform the program doesn't have, setting it is then a no-op
+    pub fn location(&self, name: &'static str) -> i32 {
+        self.location_with(name, |name| unsafe { gl::GetUniformLocation(self.id, name.as_ptr()) })
+    }
+
+    fn location_with<F: FnOnce(&CStr) -> i32>(&self, name: &'static str, lookup: F) -> i32 {
+        let mut locations = self.locations.lock().unwrap();
+        *locations.entry(name).or_insert_with(|| lookup(&CString::new(name).unwrap()))
+    }
+
+    pub unsafe fn set_mat4(&self, name: &'static str, value: &Matrix4<f32>) {
+        gl::UniformMatrix4fv(self.location(name), 1, gl::FALSE, value.as_slice().as_ptr());
+    }
+
+    pub unsafe fn set_vec3(&self, name: &'static str, value: &[f32; 3]) {
+        gl::Uniform3fv(self.location(name), 1, value.as_ptr());
+    }
+
+    pub unsafe fn set_vec4(&self, name: &'static str, value: &[f32; 4]) {
+        gl::Uniform4fv(self.location(name), 1, value.as_ptr());
+    }
+
+    pub unsafe fn set_f32(&self, name: &'static str, value: f32) {
+        gl::Uniform1f(self.location(name), value);
+    }
+
+    pub unsafe fn set_i32(&self, name: &'static str, value: i32) {
+        gl::Uniform1i(self.location(name), value);
+    }
+}
+
 pub unsafe fn create_shader(
     vertex: String,
     fragment: String,
@@ -29,8 +81,8 @@ pub unsafe fn create_shader(
         Ok(CString::new(contents).expect("Unable to load C String"))
     };
 
-    let vertex_string = get_contents(vertex).unwrap();
-    let fragment_string = get_contents(fragment).unwrap();
+    let vertex_string = get_contents(vertex).map_err(|err| ShaderError::VertexError(err.to_string()))?;
+    let fragment_string = get_contents(fragment).map_err(|err| ShaderError::FragmentError(err.to_string()))?;
 
     let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
     gl::ShaderSource(
@@ -89,7 +141,7 @@ pub unsafe fn create_shader(
 
     let geo_shader = match geometric {
         Some(source) => {
-            let geo_string = get_contents(source).unwrap();
+            let geo_string = get_contents(source).map_err(|err| ShaderError::GeometryError(err.to_string()))?;
             let geo_shader = gl::CreateShader(gl::GEOMETRY_SHADER);
             gl::ShaderSource(
                 geo_shader,
@@ -139,3 +191,24 @@ pub unsafe fn create_shader(
 
     Ok(shader_program)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_locations_are_looked_up_once() {
+        let program = ShaderProgram::new(3);
+        let mut lookups = vec![];
+
+        for name in ["view", "model", "view"].iter() {
+            let location = program.location_with(name, |name| {
+                lookups.push(name.to_str().unwrap().to_string());
+                lookups.len() as i32
+            });
+            assert_eq!(location, if *name == "view" { 1 } else { 2 });
+        }
+
+        assert_eq!(lookups, vec![String::from("view"), String::from("model")]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::logs::Logable;
+
+//Note(teddy) The draw helpers are free functions called from all over the ui and the renderer, they count
+//into the frame being recorded here instead of having the stats threaded through every call
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderPass {
+    Scene,
+    Ui,
+    ///The screen quad putting the scene and ui textures together
+    Composite,
+}
+
+impl RenderPass {
+    pub const ALL: [RenderPass; 3] = [RenderPass::Scene, RenderPass::Ui, RenderPass::Composite];
+
+    pub fn index(&self) -> usize {
+        match self {
+            RenderPass::Scene => 0,
+            RenderPass::Ui => 1,
+            RenderPass::Composite => 2,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PassStats {
+    pub draw_calls: u32,
+    pub triangles: u64,
+    pub texture_binds: u32,
+    pub time: Duration,
+    ///Measured by a timer query a couple of frames back, None while the result isn't in or can't be measured
+    pub gpu_time: Option<Duration>,
+}
+
+///What the last frame cost, split by pass so the ui shows up apart from the scene
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RenderStats {
+    pub scene: PassStats,
+    pub ui: PassStats,
+    pub composite: PassStats,
+    pub entities_drawn: u32,
+    pub entities_culled: u32,
+    ///Whether the gpu times were asked for, they're left out of the summary otherwise
+    pub gpu_timing: bool,
+}
+
+impl RenderStats {
+    pub fn pass_mut(&mut self, pass: RenderPass) -> &mut PassStats {
+        match pass {
+            RenderPass::Scene => &mut self.scene,
+            RenderPass::Ui => &mut self.ui,
+            RenderPass::Composite => &mut self.composite,
+        }
+    }
+
+    pub fn draw_calls(&self) -> u32 {
+        self.scene.draw_calls + self.ui.draw_calls + self.composite.draw_calls
+    }
+
+    pub fn triangles(&self) -> u64 {
+        self.scene.triangles + self.ui.triangles + self.composite.triangles
+    }
+
+    pub fn time(&self) -> Duration {
+        self.scene.time + self.ui.time + self.composite.time
+    }
+}
+
+#[derive(Default)]
+struct StatsRecorder {
+    frame: RenderStats,
+    ///Pass the counts go to and when it started, draws outside a pass aren't counted
+    pass: Option<(RenderPass, Instant)>,
+}
+
+impl StatsRecorder {
+    fn current(&mut self) -> Option<&mut PassStats> {
+        let pass = self.pass?.0;
+        Some(self.frame.pass_mut(pass))
+    }
+
+    fn end_pass(&mut self) {
+        if let Some((pass, started)) = self.pass.take() {
+            self.frame.pass_mut(pass).time += started.elapsed();
+        }
+    }
+}
+
+thread_local! {
+    //Note(teddy) Everything is drawn on the gl thread
+    static RECORDER: RefCell<StatsRecorder> = RefCell::new(StatsRecorder::default());
+}
+
+///Zeroes the counts, called once before anything of the frame is drawn
+pub fn begin_frame() {
+    RECORDER.with(|recorder| *recorder.borrow_mut() = StatsRecorder::default());
+}
+
+///Draws from here on count towards the pass and its time starts, ending the pass before it
+pub fn begin_pass(pass: RenderPass) {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        recorder.end_pass();
+        recorder.pass = Some((pass, Instant::now()));
+    });
+}
+
+pub fn end_pass() {
+    RECORDER.with(|recorder| recorder.borrow_mut().end_pass());
+}
+
+pub fn record_draw(triangles: u64) {
+    RECORDER.with(|recorder| {
+        if let Some(stats) = recorder.borrow_mut().current() {
+            stats.draw_calls += 1;
+            stats.triangles += triangles;
+        }
+    });
+}
+
+pub fn record_texture_bind() {
+    RECORDER.with(|recorder| {
+        if let Some(stats) = recorder.borrow_mut().current() {
+            stats.texture_binds += 1;
+        }
+    });
+}
+
+pub fn record_entities(drawn: u32, culled: u32) {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        recorder.frame.entities_drawn = drawn;
+        recorder.frame.entities_culled = culled;
+    });
+}
+
+///The counts so far, the whole frame once the last pass has ended
+pub fn frame_stats() -> RenderStats {
+    RECORDER.with(|recorder| recorder.borrow().frame)
+}
+
+pub struct RenderStatsLogObject {
+    pub stats: RenderStats,
+}
+
+impl Logable for RenderStatsLogObject {
+    fn to_string(&self) -> String {
+        let stats = &self.stats;
+        let pass_line = |name: &str, pass: &PassStats| {
+            let gpu = match (stats.gpu_timing, pass.gpu_time) {
+                (false, _) => String::new(),
+                (true, Some(time)) => format!(" gpu {:.2} ms", time.as_secs_f32() * 1000.0),
+                (true, None) => String::from(" gpu N/A"),
+            };
+
+            format!(
+                "{}: {:.2} ms{} {} draws {} tris {} binds",
+                name,
+                pass.time.as_secs_f32() * 1000.0,
+                gpu,
+                pass.draw_calls,
+                pass.triangles,
+                pass.texture_binds
+            )
+        };
+
+        [
+            format!(
+                "FRAME: {:.2} ms {} draws {} tris, {} drawn {} culled",
+                stats.time().as_secs_f32() * 1000.0,
+                stats.draw_calls(),
+                stats.triangles(),
+                stats.entities_drawn,
+                stats.entities_culled
+            ),
+            pass_line("SCENE", &stats.scene),
+            pass_line("UI", &stats.ui),
+            pass_line("COMPOSITE", &stats.composite),
+        ]
+        .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_go_to_the_pass_being_drawn() {
+        begin_frame();
+        record_draw(12);
+
+        begin_pass(RenderPass::Scene);
+        record_draw(12);
+        record_draw(100);
+        record_texture_bind();
+        begin_pass(RenderPass::Ui);
+        record_draw(2);
+        end_pass();
+        record_draw(2);
+        record_entities(2, 5);
+
+        let stats = frame_stats();
+        //Note(teddy) The draw before the first pass and the one after the last aren't counted
+        assert_eq!((stats.scene.draw_calls, stats.scene.triangles, stats.scene.texture_binds), (2, 112, 1));
+        assert_eq!((stats.ui.draw_calls, stats.ui.triangles), (1, 2));
+        assert_eq!(stats.composite, PassStats::default());
+        assert_eq!((stats.draw_calls(), stats.entities_drawn, stats.entities_culled), (3, 2, 5));
+        assert_eq!(RenderStatsLogObject { stats }.to_string().lines().count(), 4);
+
+        begin_frame();
+        assert_eq!(frame_stats(), RenderStats::default());
+    }
+}
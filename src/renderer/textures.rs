@@ -0,0 +1,96 @@
+use std::ffi::c_void;
+
+use crate::renderer::png_decoder::DecodedImage;
+use crate::renderer::shaders::ShaderProgram;
+use crate::renderer::stats;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl TextureWrap {
+    fn gl_enum(&self) -> u32 {
+        match self {
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+///How a texture is sampled, set when it's queued for loading
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureParams {
+    pub wrap: TextureWrap,
+    pub filter: TextureFilter,
+    pub mipmaps: bool,
+}
+
+impl Default for TextureParams {
+    fn default() -> Self {
+        Self { wrap: TextureWrap::Repeat, filter: TextureFilter::Linear, mipmaps: true }
+    }
+}
+
+impl TextureParams {
+    ///Minification and magnification filters, mipmaps only change how the texture shrinks
+    fn gl_filters(&self) -> (u32, u32) {
+        match (self.filter, self.mipmaps) {
+            (TextureFilter::Nearest, false) => (gl::NEAREST, gl::NEAREST),
+            (TextureFilter::Nearest, true) => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
+            (TextureFilter::Linear, false) => (gl::LINEAR, gl::LINEAR),
+            (TextureFilter::Linear, true) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+        }
+    }
+}
+
+///Uploads decoded RGBA pixels into a new texture, has to run on the thread with the gl context
+pub unsafe fn upload_texture(image: &DecodedImage, params: &TextureParams) -> u32 {
+    let mut texture = 0;
+    gl::GenTextures(1, &mut texture);
+    gl::BindTexture(gl::TEXTURE_2D, texture);
+
+    let wrap = params.wrap.gl_enum() as i32;
+    let (min_filter, mag_filter) = params.gl_filters();
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+
+    //Note(teddy) RGBA rows are always 4 byte aligned, the default unpack alignment is fine
+    gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RGBA as i32,
+        image.width as i32,
+        image.height as i32,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        image.pixels.as_ptr() as *const c_void,
+    );
+
+    if params.mipmaps {
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+    }
+
+    gl::BindTexture(gl::TEXTURE_2D, 0);
+    texture
+}
+
+///Binds the texture to unit 0 as the program's diffuse_map, the program has to be in use
+pub unsafe fn bind_diffuse_map(program: &ShaderProgram, texture: u32) {
+    program.set_i32("diffuse_map", 0);
+    gl::ActiveTexture(gl::TEXTURE0);
+    gl::BindTexture(gl::TEXTURE_2D, texture);
+    stats::record_texture_bind();
+}
@@ -0,0 +1,239 @@
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+use crate::core::Light;
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsError {
+    UnknownFlag(String),
+    UnknownCommand(String),
+    MissingValue(String),
+    InvalidNumber(String),
+    ZeroDirection,
+}
+
+///Commands accepted by the console, mirrors the startup flags
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConsoleCommand {
+    LightDirection([f32; 3]),
+    LightColor([f32; 3]),
+    Ambient(f32),
+}
+
+///Lighting overrides collected from the command line.
+///Values that are present override whatever the engine or the level sets up.
+#[derive(Debug, Default, PartialEq)]
+pub struct LightSettings {
+    pub direction: Option<[f32; 3]>,
+    pub color: Option<[f32; 3]>,
+    pub ambient: Option<f32>,
+}
+
+impl LightSettings {
+    pub fn apply(&self, light: &mut Light) {
+        if let Some(direction) = self.direction {
+            light.direction = direction;
+        }
+
+        if let Some(color) = self.color {
+            light.color = color;
+        }
+
+        if let Some(ambient) = self.ambient {
+            light.ambient = ambient;
+        }
+    }
+
+    fn set(&mut self, command: ConsoleCommand) {
+        match command {
+            ConsoleCommand::LightDirection(direction) => self.direction = Some(direction),
+            ConsoleCommand::LightColor(color) => self.color = Some(color),
+            ConsoleCommand::Ambient(ambient) => self.ambient = Some(ambient),
+        }
+    }
+}
+
+impl ConsoleCommand {
+    pub fn apply(&self, light: &mut Light) {
+        let mut settings = LightSettings::default();
+        settings.set(*self);
+        settings.apply(light);
+    }
+}
+
+///Parses `--light-dir x y z`, `--light-color r g b` and `--ambient a`.
+///The iterator should not contain the program name.
+pub fn parse_cli_args<I: Iterator<Item = String>>(args: I) -> Result<LightSettings, SettingsError> {
+    let args: Vec<String> = args.collect();
+    let mut settings = LightSettings::default();
+    let mut i = 0;
+
+    while i < args.len() {
+        let (command, consumed) = match args[i].as_str() {
+            "--light-dir" => (ConsoleCommand::LightDirection(parse_direction(&args[i + 1..], &args[i])?), 3),
+            "--light-color" => (ConsoleCommand::LightColor(parse_color(&args[i + 1..], &args[i])?), 3),
+            "--ambient" => (ConsoleCommand::Ambient(parse_ambient(&args[i + 1..], &args[i])?), 1),
+            flag => return Err(SettingsError::UnknownFlag(String::from(flag))),
+        };
+
+        settings.set(command);
+        i += consumed + 1;
+    }
+
+    Ok(settings)
+}
+
+///Parses console lines such as `light dir 0.5 -1 0.2`, `light color 1 0.9 0.8` or `ambient 0.2`
+pub fn parse_console_command(line: &str) -> Result<ConsoleCommand, SettingsError> {
+    let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+
+    match words.iter().map(|w| w.as_str()).collect::<Vec<&str>>().as_slice() {
+        ["light", "dir", ..] => Ok(ConsoleCommand::LightDirection(parse_direction(&words[2..], line)?)),
+        ["light", "color", ..] => Ok(ConsoleCommand::LightColor(parse_color(&words[2..], line)?)),
+        ["ambient", ..] => Ok(ConsoleCommand::Ambient(parse_ambient(&words[1..], line)?)),
+        _ => Err(SettingsError::UnknownCommand(String::from(line.trim()))),
+    }
+}
+
+fn parse_numbers(values: &[String], count: usize, context: &str) -> Result<Vec<f32>, SettingsError> {
+    if values.len() < count {
+        return Err(SettingsError::MissingValue(String::from(context)));
+    }
+
+    values[..count]
+        .iter()
+        .map(|value| match value.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(SettingsError::InvalidNumber(value.clone())),
+        })
+        .collect()
+}
+
+fn parse_direction(values: &[String], context: &str) -> Result<[f32; 3], SettingsError> {
+    let numbers = parse_numbers(values, 3, context)?;
+    let length = (numbers[0] * numbers[0] + numbers[1] * numbers[1] + numbers[2] * numbers[2]).sqrt();
+
+    if length <= std::f32::EPSILON {
+        return Err(SettingsError::ZeroDirection);
+    }
+
+    Ok([numbers[0] / length, numbers[1] / length, numbers[2] / length])
+}
+
+fn parse_color(values: &[String], context: &str) -> Result<[f32; 3], SettingsError> {
+    let numbers = parse_numbers(values, 3, context)?;
+    Ok([clamp_unit(numbers[0]), clamp_unit(numbers[1]), clamp_unit(numbers[2])])
+}
+
+fn parse_ambient(values: &[String], context: &str) -> Result<f32, SettingsError> {
+    let numbers = parse_numbers(values, 1, context)?;
+    Ok(clamp_unit(numbers[0]))
+}
+
+#[inline]
+fn clamp_unit(value: f32) -> f32 {
+    value.max(0.0).min(1.0)
+}
+
+///Reads commands from stdin on a separate thread so the main loop never blocks on input
+pub struct Console {
+    receiver: Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let stdin = std::io::stdin();
+            let mut line = String::new();
+
+            while let Ok(read) = stdin.read_line(&mut line) {
+                if read == 0 || sender.send(line.clone()).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        Self { receiver }
+    }
+
+    pub fn poll(&self) -> Vec<Result<ConsoleCommand, SettingsError>> {
+        let mut commands = vec![];
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => commands.push(parse_console_command(&line)),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> {
+        line.split_whitespace().map(String::from).collect::<Vec<String>>().into_iter()
+    }
+
+    #[test]
+    fn test_cli_flags_are_validated() {
+        let settings = parse_cli_args(args("--light-dir 0 -2 0 --light-color 2 0.5 -1 --ambient 0.25")).unwrap();
+
+        assert_eq!(settings.direction, Some([0.0, -1.0, 0.0]));
+        assert_eq!(settings.color, Some([1.0, 0.5, 0.0]));
+        assert_eq!(settings.ambient, Some(0.25));
+        assert_eq!(parse_cli_args(args("")).unwrap(), LightSettings::default());
+    }
+
+    #[test]
+    fn test_cli_flag_errors() {
+        assert_eq!(parse_cli_args(args("--light-dir 0 0 0")), Err(SettingsError::ZeroDirection));
+        assert_eq!(
+            parse_cli_args(args("--light-color 1 1")),
+            Err(SettingsError::MissingValue(String::from("--light-color")))
+        );
+        assert_eq!(
+            parse_cli_args(args("--ambient bright")),
+            Err(SettingsError::InvalidNumber(String::from("bright")))
+        );
+        assert_eq!(parse_cli_args(args("--fullscreen")), Err(SettingsError::UnknownFlag(String::from("--fullscreen"))));
+    }
+
+    #[test]
+    fn test_console_commands() {
+        assert_eq!(parse_console_command("light color 1 0.9 0.8"), Ok(ConsoleCommand::LightColor([1.0, 0.9, 0.8])));
+        assert_eq!(parse_console_command("ambient 3\n"), Ok(ConsoleCommand::Ambient(1.0)));
+        assert!(matches!(parse_console_command("fog 1"), Err(SettingsError::UnknownCommand(_))));
+
+        match parse_console_command("light dir 3 4 0") {
+            Ok(ConsoleCommand::LightDirection(direction)) => {
+                assert!((direction[0] - 0.6).abs() < 1e-6 && (direction[1] - 0.8).abs() < 1e-6)
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_settings_override_light() {
+        let mut light = Light {
+            color: [1.0, 1.0, 1.0],
+            direction: [10.0, 30.0, 0.0],
+            ambient: 0.4,
+        };
+
+        let settings = parse_cli_args(args("--ambient 0.1")).unwrap();
+        settings.apply(&mut light);
+        assert_eq!(light.ambient, 0.1);
+        assert_eq!(light.color, [1.0, 1.0, 1.0]);
+
+        ConsoleCommand::LightColor([0.5, 0.5, 0.5]).apply(&mut light);
+        assert_eq!(light.color, [0.5, 0.5, 0.5]);
+    }
+}
@@ -0,0 +1,176 @@
+use nalgebra::{Isometry3, Vector3};
+use ncollide3d::bounding_volume::BoundingVolume;
+use ncollide3d::pipeline::object::CollisionGroups;
+use ncollide3d::query::{self, DefaultTOIDispatcher};
+use ncollide3d::shape::Shape;
+use nphysics3d::object::{DefaultColliderHandle, DefaultColliderSet};
+use nphysics3d::world::DefaultGeometricalWorld;
+
+use crate::game_world::components::CharacterController;
+
+///Gap kept between the capsule and whatever it walks into or stands on
+const SKIN: f32 = 0.01;
+//Note(teddy) Enough to slide along one wall into a corner and out along the other
+const MAX_SLIDES: usize = 4;
+///Ground this far below the capsule still counts as standing on it
+const GROUND_PROBE: f32 = 0.05;
+
+///Sweeps a character's shape through the physics system's colliders
+pub struct CharacterQuery<'a> {
+    pub geometrical_world: &'a DefaultGeometricalWorld<f32>,
+    pub colliders: &'a DefaultColliderSet<f32>,
+    pub shape: &'a dyn Shape<f32>,
+    ///The character's own collider, never in its way
+    pub own: DefaultColliderHandle,
+    pub groups: CollisionGroups,
+}
+
+impl<'a> CharacterQuery<'a> {
+    ///How much of the motion the shape travels before it comes within SKIN of something, and that thing's normal
+    fn cast(&self, from: &Isometry3<f32>, motion: &Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+        let mut to = *from;
+        to.translation.vector += motion;
+        let swept = self.shape.aabb(from).merged(&self.shape.aabb(&to)).loosened(SKIN * 2.0);
+
+        let mut closest: Option<(f32, Vector3<f32>)> = None;
+        for (handle, collider) in self.geometrical_world.interferences_with_aabb(self.colliders, &swept, &self.groups) {
+            if handle == self.own || collider.is_sensor() {
+                continue;
+            }
+
+            let toi = match query::time_of_impact(
+                &DefaultTOIDispatcher,
+                from,
+                motion,
+                self.shape,
+                collider.position(),
+                &Vector3::zeros(),
+                collider.shape(),
+                1.0,
+                SKIN,
+            ) {
+                Ok(Some(toi)) => toi,
+                _ => continue,
+            };
+
+            let normal = -(from.rotation * toi.normal1.into_inner());
+            //Note(teddy) Resting on the floor it reports a hit right away, only what the motion pushes into is in the way
+            if motion.dot(&normal) >= 0.0 || !normal.iter().all(|x| x.is_finite()) {
+                continue;
+            }
+
+            if closest.map_or(true, |(best, _)| toi.toi < best) {
+                closest = Some((toi.toi, normal));
+            }
+        }
+
+        closest
+    }
+
+    ///Moves as far along the motion as it can, sliding along what it hits. Walking, walls too steep to
+    ///stand on are flattened so the slide can't carry the character up them. Falling stops on walkable ground.
+    fn slide(&self, position: &mut Isometry3<f32>, motion: Vector3<f32>, walkable: f32, falling: bool) -> Vec<Vector3<f32>> {
+        let mut hits = vec![];
+        let mut remaining = motion;
+
+        for _ in 0..MAX_SLIDES {
+            if remaining.norm() <= std::f32::EPSILON {
+                break;
+            }
+
+            let (toi, normal) = match self.cast(position, &remaining) {
+                Some(hit) => hit,
+                None => {
+                    position.translation.vector += remaining;
+                    break;
+                }
+            };
+
+            position.translation.vector += remaining * toi;
+            hits.push(normal);
+            if falling && normal.y >= walkable {
+                break;
+            }
+
+            let flat = Vector3::new(normal.x, 0.0, normal.z);
+            let normal = if !falling && normal.y < walkable && flat.norm() > std::f32::EPSILON {
+                flat.normalize()
+            } else {
+                normal
+            };
+
+            let rest = remaining * (1.0 - toi);
+            remaining = rest - normal * rest.dot(&normal);
+        }
+
+        hits
+    }
+
+    fn ground_below(&self, position: &Isometry3<f32>, distance: f32, walkable: f32) -> Option<f32> {
+        match self.cast(position, &(-Vector3::y() * distance)) {
+            Some((toi, normal)) if normal.y >= walkable => Some(toi * distance),
+            _ => None,
+        }
+    }
+}
+
+///Walks the shape at position with the horizontal velocity for dt seconds, climbing ledges up to the step
+///height and falling when there's no ground. Keeps the controller's vertical speed and is_grounded up to date.
+pub fn move_character(
+    query: &CharacterQuery,
+    controller: &mut CharacterController,
+    position: &mut Isometry3<f32>,
+    velocity: Vector3<f32>,
+    dt: f32,
+) {
+    let walkable = controller.max_slope.cos();
+    let walk = Vector3::new(velocity.x, 0.0, velocity.z) * dt;
+    let progress = |to: &Isometry3<f32>| (to.translation.vector - position.translation.vector).dot(&walk);
+
+    let mut moved = *position;
+    query.slide(&mut moved, walk, walkable, false);
+
+    //Note(teddy) Blocked while standing, try again from step height and come back down on top of the ledge
+    let wanted = walk.norm_squared();
+    if controller.is_grounded && controller.step_height > 0.0 && progress(&moved) < wanted * 0.99 {
+        let rise = Vector3::y() * controller.step_height;
+        let mut stepped = *position;
+        stepped.translation.vector += rise * query.cast(&stepped, &rise).map_or(1.0, |(toi, _)| toi);
+        query.slide(&mut stepped, walk, walkable, false);
+
+        let height = stepped.translation.vector.y - position.translation.vector.y;
+        if let Some(drop) = query.ground_below(&stepped, height, walkable).filter(|_| height > SKIN) {
+            stepped.translation.vector.y -= drop;
+            if progress(&stepped) > progress(&moved) + std::f32::EPSILON {
+                moved = stepped;
+            }
+        }
+    }
+
+    //Note(teddy) Stick to the ground walking down slopes and stairs instead of hopping off every edge
+    let snap = match controller.is_grounded && controller.vertical_speed <= 0.0 {
+        true => query.ground_below(&moved, controller.step_height.max(GROUND_PROBE), walkable),
+        false => None,
+    };
+
+    match snap {
+        Some(drop) => {
+            moved.translation.vector.y -= drop;
+            controller.vertical_speed = 0.0;
+        }
+        None => {
+            controller.vertical_speed -= controller.gravity * dt;
+            let hits = query.slide(&mut moved, Vector3::y() * controller.vertical_speed * dt, walkable, true);
+            if controller.vertical_speed > 0.0 && hits.iter().any(|normal| normal.y < 0.0) {
+                controller.vertical_speed = 0.0;
+            }
+        }
+    }
+
+    controller.is_grounded = controller.vertical_speed <= 0.0 && query.ground_below(&moved, GROUND_PROBE, walkable).is_some();
+    if controller.is_grounded {
+        controller.vertical_speed = 0.0;
+    }
+
+    *position = moved;
+}
@@ -1,3 +1,5 @@
+pub mod character;
 pub mod physics;
 pub mod render_system;
+pub mod rope;
 pub mod system;
@@ -1,17 +1,169 @@
-use nalgebra::{Point3, Vector3};
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::{Isometry3, Matrix3, Point3, Unit, UnitQuaternion, Vector3};
+use ncollide3d::pipeline::narrow_phase::ContactEvent;
 use ncollide3d::pipeline::object::CollisionGroups;
-use ncollide3d::shape::{Ball, ShapeHandle, TriMesh};
+use ncollide3d::bounding_volume::BoundingVolume;
+use ncollide3d::query::{self, DefaultTOIDispatcher, Ray};
+use ncollide3d::shape::{Ball, Capsule, ConvexHull, Cuboid, HeightField, Shape, ShapeHandle, TriMesh};
 
 use nphysics3d::force_generator::DefaultForceGeneratorSet;
-use nphysics3d::joint::DefaultJointConstraintSet;
+use nphysics3d::joint::{
+    BallConstraint, DefaultJointConstraintHandle, DefaultJointConstraintSet, FixedConstraint, RevoluteConstraint,
+};
+use nphysics3d::math::{Force, ForceType, Velocity};
 use nphysics3d::object::{
-    BodyPartHandle, ColliderDesc, DefaultBodySet, DefaultColliderSet, RigidBodyDesc,
+    ActivationStatus, Body, BodyPartHandle, BodyStatus, ColliderDesc, DefaultBodyHandle, DefaultBodySet, DefaultColliderHandle,
+    DefaultColliderSet, RigidBodyDesc,
 };
 use nphysics3d::world::{DefaultGeometricalWorld, DefaultMechanicalWorld};
 
+use super::character::{move_character, CharacterQuery};
 use super::system::{System, SystemType};
-use crate::core::{CastedRay, Engine, Event, EventManager, EventType};
-use crate::game_world::world::{MeshType, World};
+use crate::core::{CastedRay, Engine, Event, EventManager, EventType, PhysicsCommand, RayFilter};
+use crate::game_world::components::{
+    ColliderShape, CollisionLayer, Components, Joint, JointKind, PhysicsComponent, TransformComponent,
+};
+use crate::game_world::world::{EntityID, JointID, MeshType, World};
+use crate::logs::Logable;
+use crate::renderer::debug_draw::DebugLines;
+use crate::utils::Aabb;
+
+//Note(teddy) Collision group ids, every collider is a member of exactly one of them
+pub const STATIC_GROUP: usize = 0;
+pub const DYNAMIC_GROUP: usize = 1;
+pub const TRIGGER_GROUP: usize = 2;
+pub const EDITOR_HELPER_GROUP: usize = 3;
+pub const DEBRIS_GROUP: usize = 4;
+
+///What a character walks on and bumps into, triggers and editor helpers are walked through
+const CHARACTER_BLOCKERS: u32 = (1 << STATIC_GROUP) | (1 << DYNAMIC_GROUP) | (1 << DEBRIS_GROUP);
+//Note(teddy) ncollide only has 30 groups, the upper bits are reserved
+const ALL_GROUPS_MASK: u32 = (1 << 30) - 1;
+const MAX_RAY_DISTANCE: f32 = 10000.0;
+const CONTACT_COLOR: [f32; 3] = [1.0, 0.3, 0.1];
+///Half the width of the cross drawn on contact points
+const CONTACT_POINT_SIZE: f32 = 0.05;
+const CONTACT_NORMAL_LENGTH: f32 = 0.3;
+///Flat meshes still get a box with some thickness
+const MIN_HALF_EXTENT: f32 = 0.01;
+
+pub fn collision_group(component: &PhysicsComponent) -> usize {
+    match (component.layer, component.status) {
+        (CollisionLayer::Trigger, _) => TRIGGER_GROUP,
+        (CollisionLayer::EditorHelper, _) => EDITOR_HELPER_GROUP,
+        //Note(teddy) A sensor in the world is a trigger whatever its layer says
+        (CollisionLayer::World, _) | (CollisionLayer::Debris, _) if component.is_sensor => TRIGGER_GROUP,
+        (CollisionLayer::Debris, _) => DEBRIS_GROUP,
+        (CollisionLayer::World, BodyStatus::Static) => STATIC_GROUP,
+        (CollisionLayer::World, _) => DYNAMIC_GROUP,
+    }
+}
+
+///Groups handed to interferences_with_ray, only colliders in whitelisted groups are reported
+pub fn ray_filter_groups(filter: RayFilter) -> CollisionGroups {
+    let mask = match filter {
+        RayFilter::All => ALL_GROUPS_MASK,
+        RayFilter::StaticOnly => 1 << STATIC_GROUP,
+        RayFilter::ExcludeHelpers => ALL_GROUPS_MASK & !(1 << EDITOR_HELPER_GROUP) & !(1 << TRIGGER_GROUP),
+        RayFilter::Groups(mask) => mask & ALL_GROUPS_MASK,
+    };
+
+    CollisionGroups::new().with_whitelist_by_mask(mask)
+}
+
+///Membership in the entity's group, touching only what its mask lets through
+pub fn collision_groups(component: &PhysicsComponent) -> CollisionGroups {
+    let group = collision_group(component);
+    let mut mask = component.mask & ALL_GROUPS_MASK;
+    //Note(teddy) Debris piles up in the hundreds, contacts between the pieces aren't worth the solver time
+    if group == DEBRIS_GROUP {
+        mask &= !(1 << DEBRIS_GROUP);
+    }
+
+    CollisionGroups::new().with_membership(&[group]).with_whitelist_by_mask(mask)
+}
+
+fn same_groups(a: &CollisionGroups, b: &CollisionGroups) -> bool {
+    (0..30).all(|group| a.is_member_of(group) == b.is_member_of(group) && a.is_group_whitelisted(group) == b.is_group_whitelisted(group))
+}
+
+fn collider_outline_color(status: BodyStatus, sleeping: bool) -> [f32; 3] {
+    match status {
+        BodyStatus::Static | BodyStatus::Disabled => [0.6, 0.6, 0.6],
+        BodyStatus::Kinematic => [0.9, 0.8, 0.2],
+        BodyStatus::Dynamic if sleeping => [0.3, 0.5, 1.0],
+        BodyStatus::Dynamic => [0.3, 0.9, 0.3],
+    }
+}
+
+///Dynamic bodies the solver still steps and the ones it has put to sleep
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PhysicsStats {
+    pub active_bodies: u32,
+    pub sleeping_bodies: u32,
+}
+
+pub struct PhysicsStatsLogObject {
+    pub stats: PhysicsStats,
+}
+
+impl Logable for PhysicsStatsLogObject {
+    fn to_string(&self) -> String {
+        format!("PHYSICS: {} active {} sleeping", self.stats.active_bodies, self.stats.sleeping_bodies)
+    }
+}
+
+///Closest collider a cast ray or shape reached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub entity: EntityID,
+    pub point: Point3<f32>,
+    ///Points out of the collider that was hit
+    pub normal: Vector3<f32>,
+    ///Distance along the ray in multiples of its direction
+    pub toi: f32,
+}
+
+///The physics system's queries for code outside of it, handed to World::physics when the system starts.
+///The answers are for the colliders as of the last step.
+pub struct PhysicsQueries {
+    physics: *const Physics,
+}
+
+impl PhysicsQueries {
+    ///The physics system has to stay where it is, and alive, for as long as the queries are used.
+    ///The one in the systems list is boxed and lives as long as the world, init hands that one out.
+    ///Nothing else may hold it mutably while a query runs.
+    pub unsafe fn new(physics: *const Physics) -> Self {
+        Self { physics }
+    }
+
+    fn physics(&self) -> &Physics {
+        unsafe { self.physics.as_ref().unwrap() }
+    }
+
+    pub fn shape_cast(
+        &self,
+        shape: &dyn Shape<f32>,
+        from: &Isometry3<f32>,
+        direction: &Vector3<f32>,
+        max_toi: f32,
+        filter: RayFilter,
+    ) -> Option<RayHit> {
+        self.physics().shape_cast(shape, from, direction, max_toi, filter)
+    }
+}
+
+fn casted_ray(id: usize, hit: Option<RayHit>) -> CastedRay {
+    CastedRay {
+        id,
+        entity: hit.map(|hit| hit.entity),
+        point: hit.map(|hit| hit.point),
+        normal: hit.map(|hit| hit.normal),
+        toi: hit.map_or(std::f32::INFINITY, |hit| hit.toi),
+    }
+}
 
 pub struct Physics {
     mechanical_world: DefaultMechanicalWorld<f32>,
@@ -20,6 +172,12 @@ pub struct Physics {
     colliders: DefaultColliderSet<f32>,
     joint_constraints: DefaultJointConstraintSet<f32>,
     force_generators: DefaultForceGeneratorSet<f32>,
+    ///Entity and body behind every collider, filled in as the bodies are inserted
+    collider_entities: HashMap<DefaultColliderHandle, (EntityID, DefaultBodyHandle)>,
+    ///Constraint built for each of the World's joints
+    joints: HashMap<JointID, DefaultJointConstraintHandle>,
+    ///Given to every body, follows Engine::sleep_threshold
+    sleep_threshold: Option<f32>,
 }
 
 impl Physics {
@@ -31,13 +189,16 @@ impl Physics {
             colliders: DefaultColliderSet::new(),
             joint_constraints: DefaultJointConstraintSet::new(),
             force_generators: DefaultForceGeneratorSet::new(),
+            collider_entities: HashMap::new(),
+            joints: HashMap::new(),
+            sleep_threshold: Some(ActivationStatus::default_threshold()),
         }
     }
 
     #[inline]
     fn handle_physics_events(&mut self, world: &mut World, _event_manager: &mut EventManager) {
         for entity in world.entities.iter() {
-            let physics_component = match world.components.physics[*entity].as_ref() {
+            let physics_component = match world.components.physics[*entity].as_mut() {
                 Some(component) => component,
                 None => continue,
             };
@@ -47,10 +208,19 @@ impl Physics {
                 None => continue,
             };
 
-            let rigid_body = self
-                .bodies
-                .rigid_body(physics_component.rigid_handle.unwrap())
-                .unwrap();
+            //Note(teddy) No body while the mesh it's built from is loading
+            let rigid_body = match physics_component.rigid_handle.and_then(|handle| self.bodies.rigid_body_mut(handle)) {
+                Some(body) => body,
+                None => continue,
+            };
+
+            //Note(teddy) The simulation never moves static bodies, the editor does through the transform
+            if physics_component.status == BodyStatus::Static {
+                if *rigid_body.position() != transform_component.position {
+                    rigid_body.set_position(transform_component.position);
+                }
+                continue;
+            }
 
             // let collider = self
             //     .colliders
@@ -58,12 +228,485 @@ impl Physics {
             //     .unwrap();
 
             transform_component.position = rigid_body.position().clone();
+            physics_component.velocity = rigid_body.velocity().linear;
+            physics_component.angular_velocity = rigid_body.velocity().angular;
             //dbg!(&rigid_body.position());
             //dbg!(&collider.position());
             //panic!();
         }
     }
 
+    ///Status and layer can change after the collider was built, keep the body and its group in step
+    #[inline]
+    fn sync_collision_groups(&mut self, world: &World) {
+        for entity in world.entities.iter() {
+            let component = match world.components.physics[*entity].as_ref() {
+                Some(component) => component,
+                None => continue,
+            };
+
+            if let Some(body) = component.rigid_handle.and_then(|handle| self.bodies.rigid_body_mut(handle)) {
+                if body.status() != component.status {
+                    body.set_status(component.status);
+                }
+            }
+
+            let groups = collision_groups(component);
+            if let Some(collider) = component.collider_handle.and_then(|handle| self.colliders.get_mut(handle)) {
+                if !same_groups(collider.collision_groups(), &groups) {
+                    collider.set_collision_groups(groups);
+                }
+            }
+        }
+    }
+
+    ///Outlines every collider, balls as spheres, boxes as their edges and the rest as their bounds so big
+    ///trimeshes stay cheap. Contacts get a cross at the point and a line along the normal.
+    fn queue_collider_outlines(&self, world: &World, lines: &mut DebugLines) {
+        for entity in world.entities.iter() {
+            let component = match world.components.physics[*entity].as_ref() {
+                Some(component) => component,
+                None => continue,
+            };
+
+            let collider = match component.collider_handle.and_then(|handle| self.colliders.get(handle)) {
+                Some(collider) => collider,
+                None => continue,
+            };
+
+            let color = collider_outline_color(component.status, self.is_sleeping(&world.components, *entity));
+            let shape = collider.shape();
+            if let Some(ball) = shape.as_shape::<Ball<f32>>() {
+                lines.queue_sphere(Point3::from(collider.position().translation.vector), ball.radius, color);
+            } else if let Some(cuboid) = shape.as_shape::<Cuboid<f32>>() {
+                lines.queue_box(collider.position(), cuboid.half_extents, color);
+            } else {
+                let bounds = shape.aabb(collider.position());
+                lines.queue_aabb(bounds.mins, bounds.maxs, color);
+            }
+        }
+
+        for (_, _, _, _, _, manifold) in self.geometrical_world.contact_pairs(&self.colliders, true) {
+            for tracked in manifold.contacts() {
+                let contact = &tracked.contact;
+                for axis in [Vector3::x(), Vector3::y(), Vector3::z()].iter() {
+                    let offset = axis * CONTACT_POINT_SIZE;
+                    lines.queue_line(contact.world1 - offset, contact.world1 + offset, CONTACT_COLOR);
+                }
+                lines.queue_line(contact.world1, contact.world1 + contact.normal.into_inner() * CONTACT_NORMAL_LENGTH, CONTACT_COLOR);
+            }
+        }
+    }
+
+    fn insert_body(
+        &mut self,
+        entity: EntityID,
+        physics_component: &mut PhysicsComponent,
+        transform_component: &TransformComponent,
+        shape: ShapeHandle<f32>,
+        offset: Isometry3<f32>,
+    ) {
+        //Note(teddy) Inertia of a solid ball the size of the shape's bounding sphere, without any the body can't turn
+        //and a joint would hold it as stiff as a fixed one
+        let radius = shape.local_bounding_sphere().radius() + offset.translation.vector.norm();
+        let inertia = 0.4 * physics_component.mass * radius * radius;
+
+        //Note(teddy) Creating rigid body object
+        let rigid_body = RigidBodyDesc::new()
+            .position(transform_component.position)
+            .mass(physics_component.mass)
+            .angular_inertia(Matrix3::from_diagonal_element(inertia))
+            .velocity(Velocity::new(physics_component.velocity, physics_component.angular_velocity))
+            .sleep_threshold(self.sleep_threshold)
+            .gravity_enabled(physics_component.gravity)
+            .status(physics_component.status)
+            .build();
+
+        let rigid_body_handle = self.bodies.insert(rigid_body);
+
+        let collider_body = ColliderDesc::new(shape)
+            .position(offset)
+            .sensor(physics_component.is_sensor)
+            //.ccd_enabled(true)
+            .margin(0.2)
+            .material(physics_component.material_handle.clone())
+            .collision_groups(collision_groups(physics_component))
+            .build(BodyPartHandle(rigid_body_handle, 0));
+
+        let collider_handle = self.colliders.insert(collider_body);
+        self.collider_entities.insert(collider_handle, (entity, rigid_body_handle));
+
+        physics_component.rigid_handle = Some(rigid_body_handle);
+        physics_component.collider_handle = Some(collider_handle);
+    }
+
+    ///Walks a character entity's capsule with the horizontal velocity, see CharacterController. Returns false
+    ///when the entity has no controller or its body isn't built yet
+    pub fn move_character(&mut self, components: &mut Components, entity: EntityID, velocity: Vector3<f32>, dt: f32) -> bool {
+        let (controller, physics) = match (components.characters[entity].as_mut(), components.physics[entity].as_ref()) {
+            (Some(controller), Some(physics)) => (controller, physics),
+            _ => return false,
+        };
+        let (body, collider) = match (physics.rigid_handle, physics.collider_handle) {
+            (Some(body), Some(collider)) => (body, collider),
+            _ => return false,
+        };
+        let (rigid_body, capsule) = match (self.bodies.rigid_body_mut(body), self.colliders.get(collider)) {
+            (Some(rigid_body), Some(capsule)) => (rigid_body, capsule),
+            _ => return false,
+        };
+
+        let query = CharacterQuery {
+            geometrical_world: &self.geometrical_world,
+            colliders: &self.colliders,
+            shape: capsule.shape(),
+            own: collider,
+            groups: ray_filter_groups(RayFilter::Groups(CHARACTER_BLOCKERS)),
+        };
+
+        let mut position = *rigid_body.position() * capsule.position_wrt_body();
+        move_character(&query, controller, &mut position, velocity, dt);
+        rigid_body.set_position(position * capsule.position_wrt_body().inverse());
+        true
+    }
+
+    ///Energy under which bodies fall asleep, None wakes them all and keeps them awake
+    pub fn set_sleep_threshold(&mut self, threshold: Option<f32>) {
+        if self.sleep_threshold == threshold {
+            return;
+        }
+
+        self.sleep_threshold = threshold;
+        for (_, body) in self.bodies.iter_mut() {
+            //Note(teddy) Woken with the old threshold, nphysics wakes a body with energy relative to it
+            if threshold.is_none() {
+                body.activate();
+            }
+            body.set_deactivation_threshold(threshold);
+        }
+    }
+
+    ///Whether the entity's body has been put to sleep, false while it has none
+    pub fn is_sleeping(&self, components: &Components, entity: EntityID) -> bool {
+        components
+            .physics
+            .get(entity)
+            .and_then(|component| component.as_ref()?.rigid_handle)
+            .and_then(|handle| self.bodies.rigid_body(handle))
+            .map_or(false, |body| body.status() == BodyStatus::Dynamic && !body.is_active())
+    }
+
+    pub fn stats(&self) -> PhysicsStats {
+        let mut stats = PhysicsStats::default();
+        for (_, body) in self.bodies.iter().filter(|(_, body)| body.status() == BodyStatus::Dynamic) {
+            match body.is_active() {
+                true => stats.active_bodies += 1,
+                false => stats.sleeping_bodies += 1,
+            }
+        }
+        stats
+    }
+
+    ///First collider the shape runs into moving from its position along the direction, toi is in multiples of the
+    ///direction up to max_toi. Colliders the shape already overlaps are hit at 0.
+    pub fn shape_cast(
+        &self,
+        shape: &dyn Shape<f32>,
+        from: &Isometry3<f32>,
+        direction: &Vector3<f32>,
+        max_toi: f32,
+        filter: RayFilter,
+    ) -> Option<RayHit> {
+        let mut to = *from;
+        to.translation.vector += direction * max_toi;
+        let swept = shape.aabb(from).merged(&shape.aabb(&to));
+
+        let mut closest: Option<RayHit> = None;
+        for (handle, collider) in self.geometrical_world.interferences_with_aabb(&self.colliders, &swept, &ray_filter_groups(filter)) {
+            let entity = match self.collider_entities.get(&handle) {
+                Some((entity, _)) => *entity,
+                None => continue,
+            };
+
+            let hit = match query::time_of_impact(
+                &DefaultTOIDispatcher,
+                from,
+                direction,
+                shape,
+                collider.position(),
+                &Vector3::zeros(),
+                collider.shape(),
+                max_toi,
+                0.0,
+            ) {
+                Ok(Some(hit)) => hit,
+                _ => continue,
+            };
+
+            if closest.map_or(true, |closest| hit.toi < closest.toi) {
+                closest = Some(RayHit {
+                    entity,
+                    point: collider.position() * hit.witness2,
+                    normal: collider.position().rotation * hit.normal2.into_inner(),
+                    toi: hit.toi,
+                });
+            }
+        }
+
+        closest
+    }
+
+    fn move_characters(&mut self, world: &mut World, delta_time: f32) {
+        for entity in world.entities.iter() {
+            if let Some(controller) = world.components.characters[*entity].as_ref() {
+                let velocity = controller.desired_velocity;
+                self.move_character(&mut world.components, *entity, velocity, delta_time);
+            }
+        }
+    }
+
+    ///Closest entity along the ray that passes the filter, along with the hit point
+    pub fn cast_ray(&self, world: &World, ray: &Ray<f32>, filter: RayFilter) -> Option<RayHit> {
+        let collider_groups = ray_filter_groups(filter);
+        let interferences = self.geometrical_world.interferences_with_ray(
+            &self.colliders,
+            ray,
+            MAX_RAY_DISTANCE,
+            &collider_groups,
+        );
+
+        let mut closest: Option<RayHit> = None;
+
+        for (handle, _collider, intersection) in interferences {
+            let entity = match self.collider_entities.get(&handle) {
+                Some((entity, _)) => *entity,
+                None => continue,
+            };
+
+            //Note(teddy) Editor helpers and other unpickable geometry let the ray through
+            match world.components.renderables.get(entity) {
+                Some(Some(render_component)) if !render_component.is_pickable() => continue,
+                _ => (),
+            }
+
+            if closest.map_or(true, |hit| intersection.toi < hit.toi) {
+                closest = Some(RayHit {
+                    entity,
+                    point: ray.point_at(intersection.toi),
+                    normal: intersection.normal,
+                    toi: intersection.toi,
+                });
+            }
+        }
+
+        closest
+    }
+
+    ///Contacts and proximities the last step started or ended, as entity events. Each pair comes once with
+    ///the smaller id first, colliders of removed entities are skipped.
+    fn collision_events(&self) -> Vec<EventType> {
+        let pair = |collider1, collider2| {
+            let (a, _) = self.collider_entities.get(&collider1)?;
+            let (b, _) = self.collider_entities.get(&collider2)?;
+            Some((*a.min(b), *a.max(b)))
+        };
+
+        let contacts = self.geometrical_world.contact_events().iter().filter_map(|event| match *event {
+            ContactEvent::Started(collider1, collider2) => pair(collider1, collider2).map(|(a, b)| (a, b, EventType::CollisionStarted { a, b })),
+            ContactEvent::Stopped(collider1, collider2) => pair(collider1, collider2).map(|(a, b)| (a, b, EventType::CollisionEnded { a, b })),
+        });
+        let proximities = self.geometrical_world.proximity_events().iter().filter_map(|event| {
+            pair(event.collider1, event.collider2).map(|(a, b)| (a, b, EventType::ProximityChanged { a, b, state: event.new_status }))
+        });
+
+        //Note(teddy) An entity with more than one collider shows up once per collider pair
+        let mut seen = HashSet::new();
+        contacts
+            .chain(proximities)
+            .filter(|(a, b, event)| seen.insert((std::mem::discriminant(event), *a, *b)))
+            .map(|(_, _, event)| event)
+            .collect()
+    }
+
+    ///Takes the entity's body and collider out of the simulation and clears the handles on its component
+    fn remove_body(&mut self, components: &mut Components, id: EntityID) {
+        let mut handles = vec![];
+        if let Some(component) = components.physics.get_mut(id).and_then(Option::as_mut) {
+            if let (Some(collider), Some(body)) = (component.collider_handle.take(), component.rigid_handle.take()) {
+                handles.push((collider, body));
+            }
+        }
+
+        //Note(teddy) The component can be gone before the event comes through, the handles are still in the map
+        if handles.is_empty() {
+            handles.extend(
+                self.collider_entities
+                    .iter()
+                    .filter(|(_, (entity, _))| *entity == id)
+                    .map(|(collider, (_, body))| (*collider, *body)),
+            );
+        }
+
+        for (collider, body) in handles {
+            self.collider_entities.remove(&collider);
+            self.colliders.remove(collider);
+            self.bodies.remove(body);
+        }
+    }
+
+    ///Push rope particles that moved into a collider back out along the hit normal
+    #[inline]
+    fn resolve_rope_collisions(&self, world: &mut World) {
+        let collider_groups = ray_filter_groups(RayFilter::Groups((1 << STATIC_GROUP) | (1 << DYNAMIC_GROUP)));
+
+        for entity in world.entities.iter() {
+            let rope = match world.components.ropes[*entity].as_mut() {
+                Some(rope) if rope.collide => rope,
+                _ => continue,
+            };
+
+            let radius = rope.thickness * 0.5;
+            let last = rope.particles.len().saturating_sub(1);
+
+            for i in 1..last {
+                let from = rope.previous[i];
+                let motion = rope.particles[i] - from;
+                let distance = motion.norm();
+                if distance <= std::f32::EPSILON {
+                    continue;
+                }
+
+                let ray = Ray::new(from, motion / distance);
+                let closest = self
+                    .geometrical_world
+                    .interferences_with_ray(&self.colliders, &ray, distance + radius, &collider_groups)
+                    .map(|(_, _, intersection)| intersection)
+                    .min_by(|a, b| a.toi.partial_cmp(&b.toi).unwrap());
+
+                if let Some(intersection) = closest {
+                    //Note(teddy) Killing the velocity on contact doubles as friction
+                    let hit_point = ray.point_at(intersection.toi);
+                    rope.particles[i] = hit_point + intersection.normal * radius;
+                    rope.previous[i] = rope.particles[i];
+                }
+            }
+        }
+    }
+
+    fn handle_command(
+        &mut self,
+        components: &Components,
+        event_manager: &mut EventManager,
+        event: Event,
+        entity: EntityID,
+        command: PhysicsCommand,
+    ) {
+        let component = components.physics.get(entity).and_then(|component| component.as_ref());
+
+        //Note(teddy) The body shows up once the mesh loads, a deleted entity never gets one
+        if let Some(None) = component.map(|component| component.rigid_handle) {
+            if !event.is_pending_for(SystemType::PhysicsSystem) {
+                event_manager.add_pending(event, SystemType::PhysicsSystem);
+            }
+            return;
+        }
+
+        if event.is_pending_for(SystemType::PhysicsSystem) {
+            event_manager.remove_pending(event.id, SystemType::PhysicsSystem);
+        }
+
+        if let Some(handle) = component.and_then(|component| component.rigid_handle) {
+            self.apply_command(handle, command);
+        }
+    }
+
+    fn handle_joint_added(&mut self, world: &World, event_manager: &mut EventManager, event: Event, id: JointID) {
+        let joint = world.joints.get(id).copied().flatten();
+        let handle = |entity: EntityID| {
+            world.components.physics.get(entity).and_then(|component| component.as_ref()).map(|component| component.rigid_handle)
+        };
+
+        //Note(teddy) Waits while either body is still loading, gives up if the joint or a physics component is gone
+        let bodies = joint.and_then(|joint| handle(joint.a).zip(handle(joint.b)));
+        if let Some((None, _)) | Some((_, None)) = bodies {
+            if !event.is_pending_for(SystemType::PhysicsSystem) {
+                event_manager.add_pending(event, SystemType::PhysicsSystem);
+            }
+            return;
+        }
+
+        if event.is_pending_for(SystemType::PhysicsSystem) {
+            event_manager.remove_pending(event.id, SystemType::PhysicsSystem);
+        }
+
+        if let (Some(joint), Some((Some(a), Some(b)))) = (joint, bodies) {
+            self.insert_joint(id, joint, a, b);
+        }
+    }
+
+    fn insert_joint(&mut self, id: JointID, joint: Joint, a: DefaultBodyHandle, b: DefaultBodyHandle) {
+        let rotation = |handle| self.bodies.rigid_body(handle).map_or(UnitQuaternion::identity(), |body| body.position().rotation);
+        //Note(teddy) How body a is turned seen from body b, so the joint keeps the pose they were in when it was made
+        let relative = rotation(b).inverse() * rotation(a);
+        let (part_a, part_b) = (BodyPartHandle(a, 0), BodyPartHandle(b, 0));
+        let spec = joint.spec;
+
+        let handle = match spec.kind {
+            JointKind::Fixed => self.joint_constraints.insert(FixedConstraint::new(
+                part_a,
+                part_b,
+                spec.anchor_a,
+                UnitQuaternion::identity(),
+                spec.anchor_b,
+                relative,
+            )),
+            JointKind::Ball => self.joint_constraints.insert(BallConstraint::new(part_a, part_b, spec.anchor_a, spec.anchor_b)),
+            JointKind::Revolute { axis } => {
+                let axis = Unit::new_normalize(axis);
+                self.joint_constraints.insert(RevoluteConstraint::new(
+                    part_a,
+                    part_b,
+                    spec.anchor_a,
+                    axis,
+                    spec.anchor_b,
+                    relative * axis,
+                ))
+            }
+        };
+
+        if let Some(previous) = self.joints.insert(id, handle) {
+            self.joint_constraints.remove(previous);
+        }
+    }
+
+    ///nphysics drops the constraints of removed bodies by itself, this covers joints removed on their own
+    fn remove_joint(&mut self, id: JointID) {
+        if let Some(handle) = self.joints.remove(&id) {
+            self.joint_constraints.remove(handle);
+        }
+    }
+
+    fn apply_command(&mut self, handle: DefaultBodyHandle, command: PhysicsCommand) {
+        let body = match self.bodies.rigid_body_mut(handle) {
+            Some(body) => body,
+            None => return,
+        };
+
+        match command {
+            PhysicsCommand::ApplyImpulse(impulse) => body.apply_force(0, &Force::linear(impulse), ForceType::Impulse, true),
+            PhysicsCommand::ApplyForce(force) => body.apply_force(0, &Force::linear(force), ForceType::Force, true),
+            PhysicsCommand::SetLinearVelocity(velocity) => {
+                body.set_linear_velocity(velocity);
+                body.activate();
+            }
+            PhysicsCommand::SetAngularVelocity(velocity) => {
+                body.set_angular_velocity(velocity);
+                body.activate();
+            }
+            PhysicsCommand::WakeUp => body.activate(),
+        }
+    }
+
     #[inline]
     fn handle_world_events(
         &mut self,
@@ -72,7 +715,6 @@ impl Physics {
         event_manager: *mut EventManager,
     ) -> Result<(), ()> {
         let resources_lock = world.resources.read().unwrap();
-        let mesh_data = &resources_lock.mesh_data;
         for event in unsafe { &mut *event_manager }.get_engine_events() {
             //TODO(teddy) Integrate with pending events
             match event.event_type {
@@ -86,115 +728,51 @@ impl Physics {
                         None => continue,
                     };
 
-                    //Note(teddy) Creating rigid body object
-                    let rigid_body = RigidBodyDesc::new()
-                        .position(transform_component.position)
-                        .mass(physics_component.mass)
-                        .gravity_enabled(physics_component.gravity)
-                        .status(physics_component.status)
-                        .build();
-
-                    let rigid_body_handle = self.bodies.insert(rigid_body);
-                    physics_component.rigid_handle = Some(rigid_body_handle);
-
-                    let shape = if let Some(render_component) = &world.components.renderables[id] {
-                        // construct a trimesh
-                        let mesh_label = &render_component.mesh_label;
-
-                        //We only process already loaded mesh data
-                        //When the data is not loaded i.e. `None` we append the event to pending events and Skip
-                        //FIXME(teddy): This might cause a bug
-                        if let Some(mesh) = &(mesh_data.get(mesh_label).unwrap()).mesh_type {
-                            //Note(teddy) Thread this operation
-                            let trimesh = match mesh {
-                                MeshType::Normal(obj) => {
-                                    if event.is_pending_for(SystemType::PhysicsSystem) {
-                                        unsafe {
-                                            &mut (*event_manager)
-                                                .remove_pending(event.id, SystemType::PhysicsSystem)
-                                        };
+                    //We only process already loaded mesh data
+                    //When the data is not loaded i.e. `None` we append the event to pending events and Skip
+                    let mesh = match &world.components.renderables[id] {
+                        Some(render_component) if physics_component.shape.needs_mesh() => {
+                            match resources_lock.mesh(&render_component.mesh_label).filter(|mesh| mesh.mesh_type.is_some()) {
+                                Some(mesh) => Some(mesh),
+                                None => {
+                                    if !event.is_pending_for(SystemType::PhysicsSystem) {
+                                        unsafe { (*event_manager).add_pending(event, SystemType::PhysicsSystem) };
                                     }
-                                    TriMesh::new(
-                                        obj.vertices.iter().map(|p| p.xyz()).collect(),
-                                        divide_indices(&obj.indices),
-                                        None,
-                                    )
-                                }
-
-                                MeshType::Textured(_obj) => {
-                                    unimplemented!();
+                                    continue;
                                 }
-                            };
-                            ShapeHandle::new(trimesh)
-                        } else {
-                            if !event.is_pending_for(SystemType::PhysicsSystem) {
-                                unsafe {
-                                    &mut (*event_manager)
-                                        .add_pending(event, SystemType::PhysicsSystem)
-                                };
                             }
-
-                            continue;
                         }
-                    } else {
-                        //Construct a ball, this entity could be a sensor
-
-                        ShapeHandle::new(Ball::new(1.5))
+                        _ => None,
                     };
 
-                    let collider_body = ColliderDesc::new(shape)
-                        //.ccd_enabled(true)
-                        .margin(0.2)
-                        .material(physics_component.material_handle.clone())
-                        .build(BodyPartHandle(rigid_body_handle, 0));
-
-                    let collider_handle = self.colliders.insert(collider_body);
+                    if event.is_pending_for(SystemType::PhysicsSystem) {
+                        unsafe { (*event_manager).remove_pending(event.id, SystemType::PhysicsSystem) };
+                    }
 
-                    physics_component.rigid_handle = Some(rigid_body_handle);
-                    physics_component.collider_handle = Some(collider_handle);
+                    //Note(teddy) Thread this operation
+                    let (shape, offset) = build_collider_shape(
+                        physics_component.shape,
+                        mesh.and_then(|mesh| mesh.mesh_type.as_ref()),
+                        mesh.and_then(|mesh| mesh.heightfield.as_ref()),
+                    );
+                    self.insert_body(id, physics_component, transform_component, shape, offset);
                 }
 
-                EventType::EntityRemoved(_id) => {}
+                EventType::EntityRemoved(id) => self.remove_body(&mut world.components, id),
 
                 EventType::CastRay(data) => {
-                    let collider_groups = CollisionGroups::new();
-                    let interferences = self.geometrical_world.interferences_with_ray(
-                        &self.colliders,
-                        &data.ray,
-                        10000.0,
-                        &collider_groups,
-                    );
-
-                    let mut ray_casted_event = CastedRay {
-                        id: data.id,
-                        entity: None,
-                    };
-
-                    let mut min = 100000.0;
-
-                    for (id, _collider, intersection) in interferences {
-                        for (entity_id, physics_component) in
-                            world.components.physics.iter().enumerate()
-                        {
-                            //Note(teddy) This might result in undefined behaviour sooner or later
-                            if let Some(component) = physics_component {
-                                dbg!(&intersection);
-                                match component.collider_handle {
-                                    Some(handle) if handle == id => {
-                                        if intersection.toi < min {
-                                            ray_casted_event.entity = Some(entity_id);
-                                            min = intersection.toi;
-                                        }
-                                    }
+                    let ray_casted_event = casted_ray(data.id, self.cast_ray(world, &data.ray, data.filter));
 
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
                     unsafe { &mut *event_manager }
                         .add_engine_event(Event::new(EventType::RayCasted(ray_casted_event)));
                 }
+
+                EventType::Physics { entity, command } => {
+                    self.handle_command(&world.components, unsafe { &mut *event_manager }, event, entity, command)
+                }
+
+                EventType::JointAdded(id) => self.handle_joint_added(world, unsafe { &mut *event_manager }, event, id),
+                EventType::JointRemoved(id) => self.remove_joint(id),
                 _ => (),
             };
         }
@@ -207,14 +785,28 @@ impl System for Physics {
         String::from("Physics")
     }
 
+    fn init(&mut self, world: &mut World, _engine: &mut Engine) -> Result<(), String> {
+        //Note(teddy) Called once the system is boxed in the systems list, it won't move from here
+        world.physics = Some(unsafe { PhysicsQueries::new(self) });
+        Ok(())
+    }
+
     fn update(
         &mut self,
         world: &mut World,
         event_manager: &mut EventManager,
         engine: &mut Engine,
-        _delta_time: f32,
+        delta_time: f32,
     ) {
         self.handle_world_events(engine, world, event_manager);
+        self.sync_collision_groups(world);
+        self.set_sleep_threshold(engine.sleep_threshold);
+
+        //Note(teddy) The bodies move as far as the frame took, a fixed step would keep the solver steadier
+        if delta_time > 0.0 {
+            self.mechanical_world.set_timestep(delta_time);
+        }
+        self.move_characters(world, delta_time);
 
         self.mechanical_world.step(
             &mut self.geometrical_world,
@@ -224,12 +816,80 @@ impl System for Physics {
             &mut self.force_generators,
         );
 
+        for event in self.collision_events() {
+            event_manager.add_engine_event(Event::new(event));
+        }
+
         self.handle_physics_events(world, event_manager);
+        self.resolve_rope_collisions(world);
+
+        engine.physics_stats = self.stats();
+        engine.log_manager.add_log((
+            String::from("physics_stats"),
+            Box::new(PhysicsStatsLogObject { stats: engine.physics_stats }),
+        ));
+
+        if engine.show_colliders {
+            self.queue_collider_outlines(world, &mut engine.debug_draw.scene);
+        }
 
         //Check is object has intersected with the camera view direction
     }
 }
 
+///Points and triangles of a loaded mesh, either kind
+fn mesh_geometry(mesh: &MeshType) -> (Vec<Point3<f32>>, &Vec<u32>) {
+    let (vertices, indices) = match mesh {
+        MeshType::Normal(obj) => (&obj.vertices, &obj.indices),
+        MeshType::Textured(obj) => (&obj.vertices, &obj.indices),
+    };
+
+    (vertices.iter().map(|point| point.xyz()).collect(), indices)
+}
+
+///The ncollide shape for the component and where it sits on the body. Mesh shapes without a mesh to build
+///from fall back to a ball, the entity could be a sensor. A heightfield without one is a trimesh of the mesh.
+fn build_collider_shape(
+    shape: ColliderShape,
+    mesh: Option<&MeshType>,
+    heightfield: Option<&HeightField<f32>>,
+) -> (ShapeHandle<f32>, Isometry3<f32>) {
+    let centered = |shape: ShapeHandle<f32>| (shape, Isometry3::identity());
+
+    if let (ColliderShape::HeightField, Some(heightfield)) = (shape, heightfield) {
+        return centered(ShapeHandle::new(heightfield.clone()));
+    }
+
+    let mesh = match (shape, mesh) {
+        (ColliderShape::Box { half_extents }, _) => return centered(ShapeHandle::new(Cuboid::new(half_extents))),
+        (ColliderShape::Sphere { radius }, _) => return centered(ShapeHandle::new(Ball::new(radius))),
+        (ColliderShape::Capsule { half_height, radius }, _) => return centered(ShapeHandle::new(Capsule::new(half_height, radius))),
+        (_, None) => return centered(ShapeHandle::new(Ball::new(1.5))),
+        (_, Some(mesh)) => mesh,
+    };
+
+    let (points, indices) = mesh_geometry(mesh);
+    let trimesh = |points: Vec<Point3<f32>>| ShapeHandle::new(TriMesh::new(points, divide_indices(indices), None));
+
+    match shape {
+        ColliderShape::ConvexHull => match ConvexHull::try_from_points(&points) {
+            Some(hull) => centered(ShapeHandle::new(hull)),
+            //Note(teddy) Flat or degenerate meshes have no hull
+            None => centered(trimesh(points)),
+        },
+
+        ColliderShape::AutoFromAabb => match Aabb::from_points(points.iter().map(|point| point.coords)) {
+            Some(bounds) => {
+                let half_extents = ((bounds.max - bounds.min) * 0.5).map(|extent| extent.max(MIN_HALF_EXTENT));
+                (ShapeHandle::new(Cuboid::new(half_extents)), Isometry3::translation(bounds.center().x, bounds.center().y, bounds.center().z))
+            }
+            None => centered(ShapeHandle::new(Ball::new(1.5))),
+        },
+
+        _ => centered(trimesh(points)),
+    }
+}
+
 fn divide_indices(ind: &Vec<u32>) -> Vec<Point3<usize>> {
     let collected_indices: Vec<Point3<usize>> = ind
         .chunks(3)
@@ -265,8 +925,14 @@ fn divide_indices(ind: &Vec<u32>) -> Vec<Point3<usize>> {
     // }
 }
 
+#[cfg(test)]
 mod tests {
-    use super::divide_indices;
+    use super::*;
+    use nalgebra::Vector3;
+    use crate::game_world::components::{CharacterController, JointSpec};
+    use crate::game_world::terrain::Heightmap;
+    use ncollide3d::query::Proximity;
+    use nphysics3d::material::{BasicMaterial, MaterialHandle};
 
     #[test]
     fn test_divide_indices() {
@@ -277,4 +943,695 @@ mod tests {
         println!("{:?}", result);
         assert!(result.len() == 3, true);
     }
+
+    fn add_body(
+        physics: &mut Physics,
+        world: &mut World,
+        position: Vector3<f32>,
+        status: BodyStatus,
+        layer: CollisionLayer,
+        shape: ShapeHandle<f32>,
+    ) -> EntityID {
+        let mut component = PhysicsComponent::new(
+            1.0,
+            false,
+            status,
+            Vector3::zeros(),
+            MaterialHandle::new(BasicMaterial::new(0.0, 0.0)),
+        );
+        component.layer = layer;
+        add_component(physics, world, position, component, shape)
+    }
+
+    fn add_component(
+        physics: &mut Physics,
+        world: &mut World,
+        position: Vector3<f32>,
+        mut component: PhysicsComponent,
+        shape: ShapeHandle<f32>,
+    ) -> EntityID {
+        world.components.create_entry();
+        let id = world.components.physics.len() - 1;
+        world.entities.push_back(id);
+
+        let transform = TransformComponent::new(position, Vector3::zeros(), 1.0);
+        physics.insert_body(id, &mut component, &transform, shape, Isometry3::identity());
+        world.components.physics[id] = Some(component);
+        world.components.positionable[id] = Some(transform);
+        id
+    }
+
+    fn step(physics: &mut Physics) {
+        physics.mechanical_world.step(
+            &mut physics.geometrical_world,
+            &mut physics.bodies,
+            &mut physics.colliders,
+            &mut physics.joint_constraints,
+            &mut physics.force_generators,
+        );
+    }
+
+    #[test]
+    fn test_ray_filters_select_expected_hit() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        let floor = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::zeros(),
+            BodyStatus::Static,
+            CollisionLayer::World,
+            ShapeHandle::new(Cuboid::new(Vector3::new(10.0, 0.5, 10.0))),
+        );
+        let ball = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::new(0.0, 2.0, 0.0),
+            BodyStatus::Dynamic,
+            CollisionLayer::World,
+            ShapeHandle::new(Ball::new(1.0)),
+        );
+        let helper = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::new(0.0, 5.0, 0.0),
+            BodyStatus::Static,
+            CollisionLayer::EditorHelper,
+            ShapeHandle::new(Ball::new(1.0)),
+        );
+        step(&mut physics);
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), -Vector3::y());
+        let hit = |filter| physics.cast_ray(&world, &ray, filter).map(|hit| hit.entity);
+
+        assert_eq!(hit(RayFilter::All), Some(helper));
+        assert_eq!(hit(RayFilter::ExcludeHelpers), Some(ball));
+        assert_eq!(hit(RayFilter::StaticOnly), Some(floor));
+        assert_eq!(hit(RayFilter::Groups(1 << DYNAMIC_GROUP)), Some(ball));
+        assert_eq!(hit(RayFilter::Groups(1 << TRIGGER_GROUP)), None);
+
+        let point = physics.cast_ray(&world, &ray, RayFilter::StaticOnly).unwrap().point;
+        assert!((point.y - 0.5).abs() < 0.3, "{:?}", point);
+    }
+
+    #[test]
+    fn test_group_follows_status_changes() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        let crate_entity = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::zeros(),
+            BodyStatus::Dynamic,
+            CollisionLayer::World,
+            ShapeHandle::new(Ball::new(1.0)),
+        );
+        step(&mut physics);
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), -Vector3::y());
+        assert_eq!(physics.cast_ray(&world, &ray, RayFilter::StaticOnly), None);
+
+        world.components.physics[crate_entity].as_mut().unwrap().status = BodyStatus::Static;
+        physics.sync_collision_groups(&world);
+        step(&mut physics);
+
+        assert_eq!(
+            physics.cast_ray(&world, &ray, RayFilter::StaticOnly).map(|hit| hit.entity),
+            Some(crate_entity)
+        );
+    }
+
+    #[test]
+    fn test_removed_bodies_are_not_hit() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        let shape = || ShapeHandle::new(Ball::new(1.0));
+        let top = add_body(&mut physics, &mut world, Vector3::new(0.0, 4.0, 0.0), BodyStatus::Static, CollisionLayer::World, shape());
+        let bottom = add_body(&mut physics, &mut world, Vector3::zeros(), BodyStatus::Static, CollisionLayer::World, shape());
+        step(&mut physics);
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), -Vector3::y());
+        let hit = |physics: &Physics, world: &World| physics.cast_ray(world, &ray, RayFilter::All).map(|hit| hit.entity);
+        assert_eq!(hit(&physics, &world), Some(top));
+
+        physics.remove_body(&mut world.components, top);
+        step(&mut physics);
+        assert_eq!(hit(&physics, &world), Some(bottom));
+        assert_eq!(world.components.physics[top].as_ref().unwrap().collider_handle, None);
+
+        //Note(teddy) Found through the map once the component has been cleared
+        world.components.physics[bottom] = None;
+        physics.remove_body(&mut world.components, bottom);
+        step(&mut physics);
+        assert_eq!(hit(&physics, &world), None);
+        assert!(physics.collider_entities.is_empty());
+        assert_eq!(physics.bodies.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_collider_shapes_from_mesh() {
+        use crate::obj_parser::NormalObj;
+        use nalgebra::Point4;
+
+        //Note(teddy) A box from (1, -1, -1) to (3, 1, 1), off the model origin
+        let vertices = (0..8)
+            .map(|i| Point4::new(if i & 1 == 0 { 1.0 } else { 3.0 }, if i & 2 == 0 { -1.0 } else { 1.0 }, if i & 4 == 0 { -1.0 } else { 1.0 }, 1.0))
+            .collect();
+        let indices = vec![0, 1, 3, 0, 3, 2, 4, 5, 7, 4, 7, 6, 0, 1, 5, 0, 5, 4, 2, 3, 7, 2, 7, 6];
+        let mesh = MeshType::Normal(NormalObj { vertices, normals: vec![], indices });
+
+        let (shape, offset) = build_collider_shape(ColliderShape::AutoFromAabb, Some(&mesh), None);
+        assert_eq!(shape.as_shape::<Cuboid<f32>>().unwrap().half_extents, Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(offset.translation.vector, Vector3::new(2.0, 0.0, 0.0));
+
+        let (shape, offset) = build_collider_shape(ColliderShape::ConvexHull, Some(&mesh), None);
+        assert_eq!(shape.as_shape::<ConvexHull<f32>>().unwrap().points().len(), 8);
+        assert_eq!(offset, Isometry3::identity());
+        assert!(build_collider_shape(ColliderShape::TriMesh, Some(&mesh), None).0.as_shape::<TriMesh<f32>>().is_some());
+
+        //Note(teddy) Primitives keep their own size, mesh shapes without a mesh become the sensor ball
+        let (shape, _) = build_collider_shape(ColliderShape::Capsule { half_height: 2.0, radius: 0.5 }, Some(&mesh), None);
+        assert_eq!(shape.as_shape::<Capsule<f32>>().unwrap().radius, 0.5);
+        let (shape, _) = build_collider_shape(ColliderShape::TriMesh, None, None);
+        assert_eq!(shape.as_shape::<Ball<f32>>().unwrap().radius, 1.5);
+    }
+
+    #[test]
+    fn test_touching_balls_send_collision_events() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        let shape = || ShapeHandle::new(Ball::new(1.0));
+        let first = add_body(&mut physics, &mut world, Vector3::zeros(), BodyStatus::Dynamic, CollisionLayer::World, shape());
+        let second = add_body(&mut physics, &mut world, Vector3::new(1.5, 0.0, 0.0), BodyStatus::Dynamic, CollisionLayer::World, shape());
+        let pair = (first.min(second), first.max(second));
+
+        step(&mut physics);
+        let started = physics.collision_events();
+        assert_eq!(started.len(), 1, "{:?}", started);
+        assert!(matches!(started[0], EventType::CollisionStarted { a, b } if (a, b) == pair));
+
+        let handle = world.components.physics[second].as_ref().unwrap().rigid_handle.unwrap();
+        physics.bodies.rigid_body_mut(handle).unwrap().set_position(Isometry3::translation(50.0, 0.0, 0.0));
+        step(&mut physics);
+        let ended = physics.collision_events();
+        assert!(ended.iter().any(|event| matches!(event, EventType::CollisionEnded { a, b } if (*a, *b) == pair)), "{:?}", ended);
+
+        step(&mut physics);
+        assert!(physics.collision_events().is_empty());
+    }
+
+    #[test]
+    fn test_balls_fall_through_sensors() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let material = || MaterialHandle::new(BasicMaterial::new(0.0, 0.0));
+
+        let mut zone = PhysicsComponent::new(1.0, false, BodyStatus::Static, Vector3::zeros(), material());
+        zone.is_sensor = true;
+        let zone = add_component(&mut physics, &mut world, Vector3::zeros(), zone, ShapeHandle::new(Cuboid::new(Vector3::new(2.0, 0.5, 2.0))));
+        let ball = PhysicsComponent::new(1.0, true, BodyStatus::Dynamic, Vector3::zeros(), material());
+        let ball = add_component(&mut physics, &mut world, Vector3::new(0.0, 3.0, 0.0), ball, ShapeHandle::new(Ball::new(0.5)));
+
+        let mut entered = false;
+        for _ in 0..120 {
+            step(&mut physics);
+            entered |= physics.collision_events().iter().any(|event| match event {
+                EventType::ProximityChanged { a, b, state: Proximity::Intersecting } => (*a, *b) == (zone.min(ball), zone.max(ball)),
+                EventType::CollisionStarted { .. } => panic!("A sensor pushed back"),
+                _ => false,
+            });
+        }
+
+        let handle = world.components.physics[ball].as_ref().unwrap().rigid_handle.unwrap();
+        assert!(physics.bodies.rigid_body(handle).unwrap().position().translation.y < -2.0);
+        assert!(entered);
+
+        //Note(teddy) Picking looks through the sensor to what's behind it
+        let ray = Ray::new(Point3::new(1.5, 10.0, 0.0), -Vector3::y());
+        assert_eq!(physics.cast_ray(&world, &ray, RayFilter::ExcludeHelpers), None);
+        assert_eq!(physics.cast_ray(&world, &ray, RayFilter::All).map(|hit| hit.entity), Some(zone));
+    }
+
+    #[test]
+    fn test_commands_wait_for_the_body() {
+        let mut physics = Physics::new();
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+
+        world.components.create_entry();
+        let id = world.components.physics.len() - 1;
+        world.entities.push_back(id);
+        world.components.physics[id] = Some(PhysicsComponent::new(
+            1.0,
+            false,
+            BodyStatus::Dynamic,
+            Vector3::zeros(),
+            MaterialHandle::new(BasicMaterial::new(0.0, 0.0)),
+        ));
+
+        let run_commands = |physics: &mut Physics, world: &World, event_manager: &mut EventManager| {
+            for event in event_manager.get_engine_events() {
+                if let EventType::Physics { entity, command } = event.event_type {
+                    physics.handle_command(&world.components, event_manager, event, entity, command);
+                }
+            }
+            event_manager.clear();
+        };
+
+        //Note(teddy) No body yet, the impulse has to wait
+        world.apply_impulse(id, Vector3::new(0.0, 5.0, 0.0));
+        run_commands(&mut physics, &world, &mut event_manager);
+
+        let transform = TransformComponent::new(Vector3::zeros(), Vector3::zeros(), 1.0);
+        let component = world.components.physics[id].as_mut().unwrap();
+        physics.insert_body(id, component, &transform, ShapeHandle::new(Ball::new(0.5)), Isometry3::identity());
+        run_commands(&mut physics, &world, &mut event_manager);
+        step(&mut physics);
+
+        let handle = world.components.physics[id].as_ref().unwrap().rigid_handle.unwrap();
+        let velocity = physics.bodies.rigid_body(handle).unwrap().velocity().linear;
+        assert!((velocity.y - 5.0).abs() < 1e-4, "{:?}", velocity);
+
+        run_commands(&mut physics, &world, &mut event_manager);
+        assert!(event_manager.get_engine_events().is_empty());
+    }
+
+    #[test]
+    fn test_masks_filter_rays_and_contacts() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let shape = || ShapeHandle::new(Ball::new(1.0));
+
+        let mut zone = PhysicsComponent::new(1.0, false, BodyStatus::Static, Vector3::zeros(), MaterialHandle::new(BasicMaterial::new(0.0, 0.0)));
+        zone.is_sensor = true;
+        let zone = add_component(&mut physics, &mut world, Vector3::zeros(), zone, shape());
+        let first = add_body(&mut physics, &mut world, Vector3::new(10.0, 0.0, 0.0), BodyStatus::Dynamic, CollisionLayer::Debris, shape());
+        let second = add_body(&mut physics, &mut world, Vector3::new(11.5, 0.0, 0.0), BodyStatus::Dynamic, CollisionLayer::Debris, shape());
+        step(&mut physics);
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), -Vector3::y());
+        let hit = |mask| physics.cast_ray(&world, &ray, RayFilter::Groups(mask)).map(|hit| hit.entity);
+        assert_eq!(hit(ALL_GROUPS_MASK), Some(zone));
+        assert_eq!(hit(ALL_GROUPS_MASK & !(1 << TRIGGER_GROUP)), None);
+
+        //Note(teddy) Overlapping debris never reports a contact
+        let events = physics.collision_events();
+        assert!(!events.iter().any(|event| matches!(event, EventType::CollisionStarted { a, b } if (*a, *b) == (first, second))), "{:?}", events);
+
+        //Note(teddy) A prop in the world still bumps into the debris, until the debris masks props out
+        world.components.physics[second].as_mut().unwrap().layer = CollisionLayer::World;
+        physics.sync_collision_groups(&world);
+        step(&mut physics);
+        let events = physics.collision_events();
+        assert!(events.iter().any(|event| matches!(event, EventType::CollisionStarted { a, b } if (*a, *b) == (first, second))), "{:?}", events);
+
+        world.components.physics[first].as_mut().unwrap().mask = ALL_GROUPS_MASK & !(1 << DYNAMIC_GROUP);
+        physics.sync_collision_groups(&world);
+        step(&mut physics);
+        let events = physics.collision_events();
+        assert!(events.iter().any(|event| matches!(event, EventType::CollisionEnded { a, b } if (*a, *b) == (first, second))), "{:?}", events);
+    }
+
+    ///Static trimesh following the (x, y) profile, stretched 10 units along z
+    fn add_terrain(physics: &mut Physics, world: &mut World, profile: &[(f32, f32)]) -> EntityID {
+        let points = profile.iter().flat_map(|(x, y)| vec![Point3::new(*x, *y, -5.0), Point3::new(*x, *y, 5.0)]).collect();
+        let indices = (0..profile.len() - 1)
+            .flat_map(|i| vec![Point3::new(2 * i, 2 * i + 1, 2 * i + 3), Point3::new(2 * i, 2 * i + 3, 2 * i + 2)])
+            .collect();
+        let shape = ShapeHandle::new(TriMesh::new(points, indices, None));
+        add_body(physics, world, Vector3::zeros(), BodyStatus::Static, CollisionLayer::World, shape)
+    }
+
+    ///Capsule one unit tall standing at x = -3 on the ground, walks along x for the given time
+    fn walk_along_x(profile: &[(f32, f32)], seconds: f32) -> (Vector3<f32>, bool) {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        add_terrain(&mut physics, &mut world, profile);
+
+        let mut component = PhysicsComponent::new(1.0, false, BodyStatus::Kinematic, Vector3::zeros(), MaterialHandle::new(BasicMaterial::new(0.0, 0.0)));
+        component.shape = ColliderShape::Capsule { half_height: 0.5, radius: 0.5 };
+        let (shape, _) = build_collider_shape(component.shape, None, None);
+        let character = add_component(&mut physics, &mut world, Vector3::new(-3.0, 1.05, 0.0), component, shape);
+        world.components.characters[character] = Some(CharacterController::new(0.3, 45.0f32.to_radians()));
+        step(&mut physics);
+
+        let dt = 1.0 / 60.0;
+        for _ in 0..(seconds / dt) as usize {
+            assert!(physics.move_character(&mut world.components, character, Vector3::new(2.0, 0.0, 0.0), dt));
+        }
+
+        let handle = world.components.physics[character].as_ref().unwrap().rigid_handle.unwrap();
+        let position = physics.bodies.rigid_body(handle).unwrap().position().translation.vector;
+        (position, world.components.characters[character].unwrap().is_grounded)
+    }
+
+    #[test]
+    fn test_characters_walk_up_gentle_slopes_only() {
+        let (flat, grounded) = walk_along_x(&[(-10.0, 0.0), (10.0, 0.0)], 1.0);
+        assert!(grounded);
+        assert!((flat.x + 1.0).abs() < 0.05 && (flat.y - 1.0).abs() < 0.05, "{:?}", flat);
+
+        let gentle = 20.0f32.to_radians().tan();
+        let (climbed, grounded) = walk_along_x(&[(-10.0, 0.0), (0.0, 0.0), (10.0, 10.0 * gentle)], 3.0);
+        assert!(grounded);
+        assert!(climbed.x > 1.5 && climbed.y > 1.0 + climbed.x * gentle * 0.8, "{:?}", climbed);
+
+        let steep = 60.0f32.to_radians().tan();
+        let (blocked, grounded) = walk_along_x(&[(-10.0, 0.0), (0.0, 0.0), (5.0, 5.0 * steep)], 3.0);
+        assert!(grounded);
+        assert!(blocked.x < 0.0 && blocked.y < 1.1, "{:?}", blocked);
+    }
+
+    #[test]
+    fn test_characters_climb_steps_up_to_step_height() {
+        let (climbed, grounded) = walk_along_x(&[(-10.0, 0.0), (0.0, 0.0), (0.0, 0.2), (10.0, 0.2)], 3.0);
+        assert!(grounded);
+        assert!(climbed.x > 2.0 && (climbed.y - 1.2).abs() < 0.05, "{:?}", climbed);
+
+        let (blocked, grounded) = walk_along_x(&[(-10.0, 0.0), (0.0, 0.0), (0.0, 0.6), (10.0, 0.6)], 3.0);
+        assert!(grounded);
+        assert!(blocked.x < 0.0 && (blocked.y - 1.0).abs() < 0.05, "{:?}", blocked);
+
+        //Note(teddy) Walking off the top of a ledge falls back down to the floor below
+        let (fell, grounded) = walk_along_x(&[(-10.0, 0.0), (-2.0, 0.0), (-2.0, -2.0), (10.0, -2.0)], 3.0);
+        assert!(grounded);
+        assert!(fell.x > 2.0 && (fell.y + 1.0).abs() < 0.05, "{:?}", fell);
+    }
+
+    #[test]
+    fn test_ray_hits_report_point_and_normal() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let cube = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::zeros(),
+            BodyStatus::Static,
+            CollisionLayer::World,
+            ShapeHandle::new(Cuboid::new(Vector3::new(0.5, 0.5, 0.5))),
+        );
+        step(&mut physics);
+
+        let ray = Ray::new(Point3::new(0.2, 5.0, -0.1), -Vector3::y());
+        let casted = casted_ray(7, physics.cast_ray(&world, &ray, RayFilter::All));
+        assert_eq!((casted.id, casted.entity), (7, Some(cube)));
+        assert!((casted.point.unwrap() - Point3::new(0.2, 0.5, -0.1)).norm() < 1e-4, "{:?}", casted.point);
+        assert!((casted.normal.unwrap() - Vector3::y()).norm() < 1e-4, "{:?}", casted.normal);
+        assert!((casted.toi - 4.5).abs() < 1e-4);
+
+        let missed = casted_ray(7, physics.cast_ray(&world, &Ray::new(Point3::new(2.0, 5.0, 0.0), -Vector3::y()), RayFilter::All));
+        assert_eq!((missed.entity, missed.point, missed.normal), (None, None, None));
+        assert!(missed.toi.is_infinite());
+    }
+
+    #[test]
+    fn test_outlines_follow_shapes_and_contacts() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let floor = add_body(
+            &mut physics,
+            &mut world,
+            Vector3::zeros(),
+            BodyStatus::Static,
+            CollisionLayer::World,
+            ShapeHandle::new(Cuboid::new(Vector3::new(2.0, 0.5, 2.0))),
+        );
+        let ball = add_component(
+            &mut physics,
+            &mut world,
+            Vector3::new(0.0, 1.0, 0.0),
+            PhysicsComponent::new(1.0, true, BodyStatus::Dynamic, Vector3::zeros(), MaterialHandle::new(BasicMaterial::new(0.0, 0.0))),
+            ShapeHandle::new(Ball::new(0.5)),
+        );
+
+        //Note(teddy) Turned a quarter of the way round, the bounds would be wider than the box
+        let handle = world.components.physics[floor].as_ref().unwrap().rigid_handle.unwrap();
+        physics.bodies.rigid_body_mut(handle).unwrap().set_position(Isometry3::new(Vector3::zeros(), Vector3::y() * std::f32::consts::FRAC_PI_4));
+        world.components.positionable[floor].as_mut().unwrap().position = *physics.bodies.rigid_body(handle).unwrap().position();
+        for _ in 0..10 {
+            step(&mut physics);
+        }
+
+        let mut lines = DebugLines::default();
+        physics.queue_collider_outlines(&world, &mut lines);
+        let colored = |color: [f32; 3]| lines.vertices().iter().filter(|vertex| vertex.color == color).map(|vertex| Vector3::from(vertex.position)).collect::<Vec<_>>();
+
+        let edges = colored(collider_outline_color(BodyStatus::Static, false));
+        assert_eq!(edges.len(), 12 * 2);
+        let reach = 2.0 * std::f32::consts::SQRT_2;
+        assert!(edges.iter().all(|corner| (corner.xz().norm() - reach).abs() < 1e-3), "{:?}", edges);
+
+        let ball_position = physics.bodies.rigid_body(world.components.physics[ball].as_ref().unwrap().rigid_handle.unwrap()).unwrap().position().translation.vector;
+        let circles = colored(collider_outline_color(BodyStatus::Dynamic, false));
+        assert!(!circles.is_empty() && circles.iter().all(|point| ((point - ball_position).norm() - 0.5).abs() < 1e-3));
+
+        //Note(teddy) A cross and a normal for each contact between the ball and the floor
+        let contacts = colored(CONTACT_COLOR);
+        assert!(!contacts.is_empty() && contacts.len() % 8 == 0, "{:?}", contacts);
+        assert!(contacts.iter().all(|point| point.y > 0.3 && point.y < 0.9), "{:?}", contacts);
+    }
+
+    #[test]
+    fn test_hanging_chain_holds_together() {
+        let mut physics = Physics::new();
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        let material = || MaterialHandle::new(BasicMaterial::new(0.0, 0.0));
+
+        //Note(teddy) Held out sideways so the chain swings down, every link a unit apart
+        let anchor = add_component(
+            &mut physics,
+            &mut world,
+            Vector3::new(0.0, 10.0, 0.0),
+            PhysicsComponent::new(1.0, false, BodyStatus::Static, Vector3::zeros(), material()),
+            ShapeHandle::new(Ball::new(0.2)),
+        );
+        let links: Vec<EntityID> = (1..=5)
+            .map(|i| {
+                world.components.create_entry();
+                let id = world.components.physics.len() - 1;
+                world.entities.push_back(id);
+                world.components.physics[id] = Some(PhysicsComponent::new(1.0, true, BodyStatus::Dynamic, Vector3::zeros(), material()));
+                world.components.positionable[id] = Some(TransformComponent::new(Vector3::new(i as f32, 10.0, 0.0), Vector3::zeros(), 1.0));
+                id
+            })
+            .collect();
+
+        let spec = JointSpec { kind: JointKind::Ball, anchor_a: Point3::new(0.5, 0.0, 0.0), anchor_b: Point3::new(-0.5, 0.0, 0.0) };
+        let chain: Vec<EntityID> = std::iter::once(anchor).chain(links.iter().copied()).collect();
+        for pair in chain.windows(2) {
+            let spec = JointSpec { anchor_a: if pair[0] == anchor { Point3::new(0.5, 0.0, 0.0) } else { spec.anchor_a }, ..spec };
+            world.add_joint(pair[0], pair[1], spec);
+        }
+
+        let run_joints = |physics: &mut Physics, world: &mut World, event_manager: &mut EventManager| {
+            for event in event_manager.get_engine_events() {
+                match event.event_type {
+                    EventType::JointAdded(id) => physics.handle_joint_added(world, event_manager, event, id),
+                    EventType::EntityRemoved(id) => physics.remove_body(&mut world.components, id),
+                    EventType::JointRemoved(id) => physics.remove_joint(id),
+                    _ => (),
+                }
+            }
+            event_manager.clear();
+        };
+
+        //Note(teddy) The links have no bodies yet, the joints wait for them
+        run_joints(&mut physics, &mut world, &mut event_manager);
+        assert!(physics.joints.is_empty());
+
+        for id in links.iter().copied() {
+            let transform = TransformComponent::new(Vector3::new(id as f32, 10.0, 0.0), Vector3::zeros(), 1.0);
+            let component = world.components.physics[id].as_mut().unwrap();
+            physics.insert_body(id, component, &transform, ShapeHandle::new(Ball::new(0.2)), Isometry3::identity());
+        }
+        run_joints(&mut physics, &mut world, &mut event_manager);
+        assert_eq!(physics.joints.len(), 5);
+        assert!(event_manager.get_engine_events().is_empty());
+
+        physics.mechanical_world.set_timestep(1.0 / 60.0);
+        for _ in 0..300 {
+            step(&mut physics);
+        }
+
+        let position = |id: EntityID| {
+            let handle = world.components.physics[id].as_ref().unwrap().rigid_handle.unwrap();
+            *physics.bodies.rigid_body(handle).unwrap().position()
+        };
+        let positions: Vec<Isometry3<f32>> = chain.iter().map(|id| position(*id)).collect();
+        assert!(positions.iter().all(|position| position.translation.vector.iter().all(|x| x.is_finite())), "{:?}", positions);
+        assert!(positions[5].translation.vector.y < 10.0 - 2.0, "{:?}", positions);
+        for (i, joint) in world.joints.iter().flatten().enumerate() {
+            let gap = positions[i] * joint.spec.anchor_a - positions[i + 1] * joint.spec.anchor_b;
+            assert!(gap.norm() < 0.05, "{} {:?}", i, gap);
+        }
+
+        //Note(teddy) Both joints on the deleted link go with it
+        world.delete_entity(links[2]);
+        run_joints(&mut physics, &mut world, &mut event_manager);
+        assert_eq!(physics.joints.len(), 3);
+        assert_eq!(world.joints.iter().filter(|joint| joint.is_some()).count(), 3);
+        for _ in 0..10 {
+            step(&mut physics);
+        }
+        assert_eq!(physics.joint_constraints.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_balls_roll_down_heightfield_terrain() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+        let material = || MaterialHandle::new(BasicMaterial::new(0.0, 0.5));
+
+        //Note(teddy) A 256x256 ramp rising along x, 12 units over the 128 it spans
+        let heightmap = Heightmap {
+            width: 256,
+            depth: 256,
+            heights: (0..256 * 256).map(|i| 0.5 + 0.3 * (i % 256) as f32 / 255.0).collect(),
+            scale: Vector3::new(128.0, 40.0, 128.0),
+        };
+        let surface = |x: f32| 20.0 + 12.0 * (x + 64.0) / 128.0;
+
+        let chunks = heightmap.chunks("ramp");
+        assert_eq!(chunks.len(), 16);
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let terrain = PhysicsComponent::new(0.0, false, BodyStatus::Static, Vector3::zeros(), material());
+            let (shape, _) = build_collider_shape(ColliderShape::HeightField, Some(&MeshType::Normal(chunk.mesh)), Some(&chunk.heightfield));
+            add_component(&mut physics, &mut world, heightmap.chunk_offset(i % 4, i / 4), terrain, shape);
+        }
+
+        //Note(teddy) Started just uphill of a chunk edge, the balls have to roll across it
+        let starts = [Vector3::new(4.0, 0.0, -20.0), Vector3::new(4.0, 0.0, 0.0), Vector3::new(6.0, 0.0, 33.0)];
+        let balls: Vec<EntityID> = starts
+            .iter()
+            .map(|start| {
+                let ball = PhysicsComponent::new(1.0, true, BodyStatus::Dynamic, Vector3::zeros(), material());
+                let position = Vector3::new(start.x, surface(start.x) + 1.0, start.z);
+                add_component(&mut physics, &mut world, position, ball, ShapeHandle::new(Ball::new(0.5)))
+            })
+            .collect();
+
+        physics.mechanical_world.set_timestep(1.0 / 60.0);
+        for _ in 0..300 {
+            step(&mut physics);
+        }
+
+        for (ball, start) in balls.iter().zip(starts.iter()) {
+            let handle = world.components.physics[*ball].as_ref().unwrap().rigid_handle.unwrap();
+            let body = physics.bodies.rigid_body(handle).unwrap();
+            let position = body.position().translation.vector;
+
+            assert!(position.x < start.x - 2.0 && position.x < 0.0, "{:?}", position);
+            assert!((position.z - start.z).abs() < 0.5, "{:?}", position);
+            //Note(teddy) Resting on the radius plus both colliders' margins
+            let above = position.y - surface(position.x);
+            assert!(above > 0.4 && above < 1.0, "{} above the surface at {:?}", above, position);
+            assert!(body.velocity().angular.norm() > 0.5, "{:?}", body.velocity());
+        }
+    }
+
+    #[test]
+    fn test_moving_bodies_reload_with_their_velocity() {
+        let mut physics = Physics::new();
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        let build = |physics: &mut Physics, world: &mut World, id: EntityID| {
+            let transform = TransformComponent::new(world.components.positionable[id].as_ref().unwrap().position.translation.vector, Vector3::zeros(), 1.0);
+            let component = world.components.physics[id].as_mut().unwrap();
+            physics.insert_body(id, component, &transform, ShapeHandle::new(Ball::new(0.5)), Isometry3::identity());
+            physics.bodies.rigid_body(component.rigid_handle.unwrap()).unwrap().velocity().clone()
+        };
+
+        let id = world.create_entity();
+        let mut component = PhysicsComponent::new(1.0, false, BodyStatus::Dynamic, Vector3::new(1.0, 2.0, 0.0), MaterialHandle::new(BasicMaterial::new(0.0, 0.0)));
+        component.angular_velocity = Vector3::new(0.0, 0.0, 3.0);
+        world.components.physics[id] = Some(component);
+        world.components.positionable[id] = Some(TransformComponent::new(Vector3::zeros(), Vector3::zeros(), 1.0));
+
+        let initial = build(&mut physics, &mut world, id);
+        assert_eq!((initial.linear, initial.angular), (Vector3::new(1.0, 2.0, 0.0), Vector3::new(0.0, 0.0, 3.0)));
+
+        //Note(teddy) Pushed around at runtime, the component follows the body
+        let handle = world.components.physics[id].as_ref().unwrap().rigid_handle.unwrap();
+        physics.bodies.rigid_body_mut(handle).unwrap().set_velocity(Velocity::new(Vector3::new(-4.0, 0.0, 0.5), Vector3::new(1.0, 0.0, 0.0)));
+        step(&mut physics);
+        physics.handle_physics_events(&mut world, &mut event_manager);
+        let moving = physics.bodies.rigid_body(handle).unwrap().velocity().clone();
+        assert_eq!(world.components.physics[id].as_ref().unwrap().velocity, moving.linear);
+
+        let mut loaded_events = EventManager::new();
+        let mut loaded = World::new(&mut loaded_events, std::ptr::null_mut());
+        loaded.load_level_bytes(&world.level_bytes()).unwrap();
+        let loaded_id = *loaded.entities.front().unwrap();
+
+        let reloaded = build(&mut Physics::new(), &mut loaded, loaded_id);
+        assert_eq!((reloaded.linear, reloaded.angular), (moving.linear, moving.angular));
+    }
+
+    #[test]
+    fn test_resting_bodies_sleep_until_pushed() {
+        let mut physics = Physics::new();
+        let mut event_manager = EventManager::new();
+        let mut world = World::new(&mut event_manager, std::ptr::null_mut());
+        physics.mechanical_world.set_timestep(1.0 / 60.0);
+
+        add_body(&mut physics, &mut world, Vector3::zeros(), BodyStatus::Static, CollisionLayer::World, ShapeHandle::new(Cuboid::new(Vector3::new(20.0, 0.5, 20.0))));
+        let balls: Vec<EntityID> = (0..10)
+            .map(|i| {
+                let ball = PhysicsComponent::new(1.0, true, BodyStatus::Dynamic, Vector3::zeros(), MaterialHandle::new(BasicMaterial::new(0.0, 0.5)));
+                add_component(&mut physics, &mut world, Vector3::new(i as f32 * 2.0 - 9.0, 1.5, 0.0), ball, ShapeHandle::new(Ball::new(0.5)))
+            })
+            .collect();
+
+        assert_eq!(physics.stats(), PhysicsStats { active_bodies: 10, sleeping_bodies: 0 });
+        for _ in 0..600 {
+            step(&mut physics);
+        }
+        assert_eq!(physics.stats(), PhysicsStats { active_bodies: 0, sleeping_bodies: 10 });
+        assert!(balls.iter().all(|ball| physics.is_sleeping(&world.components, *ball)));
+
+        //Note(teddy) Commands wake the body they're sent to and nothing else
+        let event = Event::new(EventType::Physics { entity: balls[3], command: PhysicsCommand::ApplyImpulse(Vector3::y() * 2.0) });
+        physics.handle_command(&world.components, &mut event_manager, event, balls[3], PhysicsCommand::ApplyImpulse(Vector3::y() * 2.0));
+        assert!(!physics.is_sleeping(&world.components, balls[3]));
+        assert_eq!(physics.stats(), PhysicsStats { active_bodies: 1, sleeping_bodies: 9 });
+
+        physics.set_sleep_threshold(None);
+        for _ in 0..600 {
+            step(&mut physics);
+        }
+        assert_eq!(physics.stats(), PhysicsStats { active_bodies: 10, sleeping_bodies: 0 });
+    }
+
+    #[test]
+    fn test_shape_casts() {
+        let mut physics = Physics::new();
+        let mut world = World::new(std::ptr::null_mut(), std::ptr::null_mut());
+
+        let floor = add_body(&mut physics, &mut world, Vector3::zeros(), BodyStatus::Static, CollisionLayer::World, ShapeHandle::new(Cuboid::new(Vector3::new(10.0, 0.5, 10.0))));
+        let ball = add_body(&mut physics, &mut world, Vector3::new(3.0, 2.0, 0.0), BodyStatus::Dynamic, CollisionLayer::World, ShapeHandle::new(Ball::new(0.5)));
+        step(&mut physics);
+
+        //Note(teddy) physics isn't touched again until the end of the test
+        world.physics = Some(unsafe { PhysicsQueries::new(&physics) });
+        let queries = world.physics.as_ref().unwrap();
+
+        let probe = Ball::new(0.5);
+        let above_ball = Isometry3::translation(3.0, 5.0, 0.0);
+        let hit = queries.shape_cast(&probe, &above_ball, &-Vector3::y(), 10.0, RayFilter::All).unwrap();
+        assert_eq!(hit.entity, ball);
+        assert!((hit.toi - 2.0).abs() < 1e-3 && (hit.point - Point3::new(3.0, 2.5, 0.0)).norm() < 1e-3, "{:?}", hit);
+        assert!((hit.normal - Vector3::y()).norm() < 1e-3, "{:?}", hit);
+
+        let hit = queries.shape_cast(&probe, &above_ball, &-Vector3::y(), 10.0, RayFilter::StaticOnly).unwrap();
+        assert_eq!(hit.entity, floor);
+        assert!((hit.toi - 4.0).abs() < 1e-3 && (hit.point.y - 0.5).abs() < 1e-3, "{:?}", hit);
+
+        assert_eq!(queries.shape_cast(&probe, &above_ball, &-Vector3::y(), 1.5, RayFilter::All), None);
+        assert_eq!(queries.shape_cast(&probe, &above_ball, &Vector3::x(), 10.0, RayFilter::All), None);
+    }
 }
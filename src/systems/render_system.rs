@@ -1,16 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use std::convert::TryInto;
 use std::ffi::{c_void, CString};
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use nalgebra::Vector3;
+use nalgebra::{Point3, Vector3, Vector4};
 
 use super::system::{System, SystemType};
 use crate::core::{Engine, EventManager, Camera, EventType, Light, ViewPortDimensions, bind_texture, Event};
-use crate::game_world::components::{TransformComponent, RenderComponent};
+use crate::game_world::components::{DebugViewMode, Material, RenderComponent, RenderFlags, TransformComponent};
+use crate::game_world::level_meta::{THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
 use crate::game_world::world::{EntityID, MeshType, World};
+use crate::obj_parser::NormalObj;
 use crate::logs::{LogManager, Logable};
+use crate::renderer::debug_draw::{draw_debug_lines, ground_grid, init_debug_lines_object, DebugLinesObject};
 use crate::renderer::draw::*;
+use crate::renderer::gpu_timer::GpuTimer;
+use crate::renderer::image_writer::{ImageJob, PixelFormat};
+use crate::renderer::stats::{self, RenderPass, RenderStatsLogObject};
+use crate::systems::rope::build_rope_mesh;
+use crate::utils::{aabb_outside_frustum, frustum_planes, Aabb};
+use crate::ui::ui::ui_cull_stats;
+
+///Half the width of the ground grid, the fade in grid_frag.glsl ends just inside it
+const GRID_EXTENT: f32 = 50.0;
+const GRID_SPACING: f32 = 1.0;
+///Normal lines are this much of the mesh's bounds diagonal, meshes without bounds get the default
+const NORMAL_LENGTH_FRACTION: f32 = 0.05;
+const DEFAULT_NORMAL_LENGTH: f32 = 0.1;
 
 #[macro_export]
 macro_rules! border_shader {
@@ -26,6 +42,34 @@ macro_rules! SCREEN_SHADER {
     };
 }
 
+#[macro_export]
+macro_rules! TEXTURED_SHADER {
+    () => {
+        String::from("textured_shader")
+    };
+}
+
+#[macro_export]
+macro_rules! DEBUG_LINE_SHADER {
+    () => {
+        String::from("debug_line_shader")
+    };
+}
+
+#[macro_export]
+macro_rules! NORMALS_SHADER {
+    () => {
+        String::from("normals_shader")
+    };
+}
+
+#[macro_export]
+macro_rules! GRID_SHADER {
+    () => {
+        String::from("grid_shader")
+    };
+}
+
 struct RenderSystemLogObject { 
     text: String
 }
@@ -38,6 +82,86 @@ impl Logable for RenderSystemLogObject {
 
 type ComponentsForRender<'a> = (EntityID, &'a RenderComponent, &'a TransformComponent);
 
+///Name the instanced variant of a shader is registered under, shaders without one draw an entity at a time
+pub fn instanced_shader_label(shader_label: &str) -> String {
+    format!("{}_instanced", shader_label)
+}
+
+///Drops the entities whose mesh bounds are completely outside the frustum and counts them, entities
+///without a render object yet have no bounds and are kept
+fn cull_entities<'a, F>(entities: Vec<ComponentsForRender<'a>>, planes: &[Vector4<f32>; 6], bounds: F) -> (Vec<ComponentsForRender<'a>>, usize)
+where
+    F: Fn(EntityID) -> Option<Aabb>,
+{
+    let count = entities.len();
+
+    let visible: Vec<ComponentsForRender> = entities
+        .into_iter()
+        .filter(|(id, _, transform_component)| {
+            match bounds(*id) {
+                Some(bounds) => !aabb_outside_frustum(&bounds.transformed(&model_matrix(transform_component)), planes),
+                None => true,
+            }
+        })
+        .collect();
+
+    let culled = count - visible.len();
+    (visible, culled)
+}
+
+///Nearest first lets the depth test throw away more of the opaque pass, blending needs the farthest first
+fn sort_by_camera_distance(entities: &mut Vec<ComponentsForRender>, camera_position: &Point3<f32>, back_to_front: bool) {
+    let distance = |(_, _, transform): &ComponentsForRender| (Point3::from(transform.position.translation.vector) - camera_position).norm_squared();
+
+    entities.sort_by(|a, b| {
+        let order = distance(a).partial_cmp(&distance(b)).unwrap_or(std::cmp::Ordering::Equal);
+        if back_to_front { order.reverse() } else { order }
+    });
+}
+
+///Highlights, textures, wireframe and the debug views are set per entity, those entities keep their own draw call
+fn can_instance(render_component: &RenderComponent) -> bool {
+    render_component.highlight.is_none()
+        && render_component.material.textures().is_empty()
+        && !render_component.is_wireframe()
+        && render_component.debug_view == DebugViewMode::Default
+}
+
+fn same_instance_group(a: &RenderComponent, b: &RenderComponent) -> bool {
+    a.shader_label == b.shader_label && a.material == b.material
+}
+
+///Groups the entities sharing a mesh, shader and material, entities that can't be instanced or have no company come back on their own.
+///`mesh_of` gives the canonical label of the mesh uploaded for an entity, aliased labels share a group with their canonical one.
+fn group_instances<'a, 'm, F>(entities: Vec<ComponentsForRender<'a>>, mesh_of: F) -> (Vec<Vec<ComponentsForRender<'a>>>, Vec<ComponentsForRender<'a>>)
+where
+    F: Fn(EntityID) -> Option<&'m str>,
+{
+    let mut groups: Vec<(&'m str, Vec<ComponentsForRender>)> = vec![];
+    let mut singles = vec![];
+
+    for entity in entities {
+        let (id, render_component, _) = entity;
+        let mesh = match mesh_of(id) {
+            Some(mesh) if can_instance(render_component) => mesh,
+            _ => {
+                singles.push(entity);
+                continue;
+            }
+        };
+
+        //Note(teddy) Materials hold floats so there's no hashing them, there are only ever a few groups to look through
+        match groups.iter_mut().find(|(group_mesh, group)| *group_mesh == mesh && same_instance_group(group[0].1, render_component)) {
+            Some((_, group)) => group.push(entity),
+            None => groups.push((mesh, vec![entity])),
+        }
+    }
+
+    let (groups, lone): (Vec<_>, Vec<_>) = groups.into_iter().map(|(_, group)| group).partition(|group| group.len() > 1);
+    singles.extend(lone.into_iter().flatten());
+    (groups, singles)
+}
+
 impl World {
     //TODO(teddy) construct an iterator
     fn get_render_components(&self) -> Vec<ComponentsForRender> {
@@ -72,7 +196,10 @@ struct HighlightReferences<'a> {
     camera: &'a Camera,
     transform: &'a TransformComponent,
     light: &'a Light,
-    object: &'a RenderObject
+    object: &'a RenderObject,
+    material: &'a Material,
+    ///Set for textured objects, they're drawn with the textured shader under the border
+    diffuse_map: Option<u32>,
 }
 
 #[inline]
@@ -90,16 +217,30 @@ unsafe fn draw_with_highlight(data: HighlightReferences) {
     gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
     gl::StencilMask(0xFF);
 
-    draw_normal_object(
-        data.world,
-        data.shader_label,
-        data.camera,
-        data.object,
-        data.transform,
-        data.light,
-        draw_params,
-        )
-        .unwrap();
+    match data.diffuse_map {
+        Some(texture) => draw_textured_object(
+            data.world,
+            &TEXTURED_SHADER!(),
+            data.camera,
+            data.object,
+            data.transform,
+            data.light,
+            data.material,
+            texture,
+            draw_params,
+            ),
+        None => draw_normal_object(
+            data.world,
+            data.shader_label,
+            data.camera,
+            data.object,
+            data.transform,
+            data.light,
+            data.material,
+            draw_params,
+            ),
+    }
+    .unwrap();
 
     let scaled_transform = TransformComponent::new(
         data.transform.position.translation.vector,
@@ -125,14 +266,36 @@ unsafe fn draw_with_highlight(data: HighlightReferences) {
         data.object,
         &data.transform,
         &data.light,
+        data.material,
         scaled_params,
         )
         .unwrap();
 }
 
+///A mesh uploaded once and drawn for every entity whose label resolves to it
+struct SharedObject {
+    object: RenderObject,
+    users: usize,
+}
+
 pub struct Renderer {
-    normal_objects: HashMap<EntityID, RenderObject>,
-    textured_objects: HashMap<EntityID, RenderObject>,
+    ///Keyed by canonical mesh label, aliased meshes share the object of their canonical one
+    normal_objects: HashMap<String, SharedObject>,
+    textured_objects: HashMap<String, SharedObject>,
+    ///Canonical mesh label of every entity with a render object
+    entity_meshes: HashMap<EntityID, String>,
+    rope_objects: HashMap<EntityID, RenderObject>,
+    overlay_lines_object: Option<RenderObject>,
+    debug_lines_object: Option<DebugLinesObject>,
+    grid_object: Option<DebugLinesObject>,
+    ///Model matrices of the group being instanced, refilled for every group
+    instance_buffer: Option<u32>,
+    ///Entities drawn and culled in the last frame
+    cull_counts: (usize, usize),
+    ///Made when engine.gpu_timing is turned on and dropped when it's turned off
+    gpu_timer: Option<GpuTimer>,
+    ///Set once making the timer failed, it isn't tried again
+    gpu_timer_unsupported: bool,
     screen_vao: Option<u32>,
     screen_shader_program: Option<u32>,
 }
@@ -142,6 +305,15 @@ impl Renderer {
         Self {
             normal_objects: HashMap::new(),
             textured_objects: HashMap::new(),
+            entity_meshes: HashMap::new(),
+            rope_objects: HashMap::new(),
+            overlay_lines_object: None,
+            debug_lines_object: None,
+            grid_object: None,
+            instance_buffer: None,
+            cull_counts: (0, 0),
+            gpu_timer: None,
+            gpu_timer_unsupported: false,
             screen_vao: None,
             screen_shader_program: None
         }
@@ -150,57 +322,458 @@ impl Renderer {
     unsafe fn draw_entities(&mut self, engine_ptr: *mut Engine, world: &mut World) {
         let engine = engine_ptr.as_mut().unwrap();
 
-        if world.entities.len() == 0 {
-            return;
+        if engine.scene_render_object.requested_samples() != engine.msaa_samples {
+            self.apply_msaa_samples(engine);
         }
 
-        gl::BindFramebuffer(gl::FRAMEBUFFER, engine.scene_render_object.frame_buffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, engine.scene_render_object.draw_frame_buffer());
         //gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         gl::ClearColor(0.1, 0.1, 0.1, 1.0);
         gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         gl::Enable(gl::DEPTH_TEST);
 
-        for (i, render_component, transform_component) in world.get_render_components() {
-            let render_object = match self.normal_objects.get(&i) {
-                Some(object) => object,
-                None => continue,
-            };
+        //Note(teddy) The scene only covers the area not taken by docked panels
+        let (viewport_x, viewport_y, viewport_width, viewport_height) = engine.scene_viewport_pixels();
+        gl::Viewport(viewport_x, viewport_y, viewport_width, viewport_height);
 
-            if render_component.highlight.is_none() {
-                let draw_params = || {
-                    gl::Enable(gl::CULL_FACE);
-                    gl::Enable(gl::DEPTH_TEST);
-                    gl::DepthFunc(gl::LESS);
-                };
+        let mut draw_calls = 0;
+        if engine.show_grid {
+            draw_calls += self.draw_grid(engine, world) as u32;
+        }
+
+        let planes = frustum_planes(&(engine.camera.perspective() * engine.camera.view()));
+        //Note(teddy) The bounds come with the render objects, the resources lock may be held by the loading thread
+        let object_bounds = |id: EntityID| self.render_object(id).and_then(|(object, _)| object.bounds);
+        let (visible, culled) = cull_entities(world.get_render_components(), &planes, object_bounds);
+        self.cull_counts = (visible.len(), culled);
+        stats::record_entities(visible.len() as u32, culled as u32);
+
+        //Note(teddy) Overlay entities are drawn last, after the depth buffer is cleared
+        let (overlay, scene): (Vec<ComponentsForRender>, Vec<ComponentsForRender>) = visible
+            .into_iter()
+            .partition(|(_, render_component, _)| render_component.flags.contains(RenderFlags::OVERLAY));
+
+        let (mut transparent, mut scene): (Vec<ComponentsForRender>, Vec<ComponentsForRender>) = scene
+            .into_iter()
+            .partition(|(_, render_component, _)| render_component.material.is_transparent());
+
+        let camera_position = Point3::from(engine.camera.position);
+        sort_by_camera_distance(&mut scene, &camera_position, false);
+        sort_by_camera_distance(&mut transparent, &camera_position, true);
+
+        //Note(teddy) Wireframe is set per draw call, the whole scene goes through the single draws while it's on
+        let entity_meshes = &self.entity_meshes;
+        let (groups, singles) = match engine.wireframe {
+            true => (vec![], scene),
+            false => group_instances(scene, |id| entity_meshes.get(&id).map(String::as_str)),
+        };
+
+        for group in groups {
+            if self.draw_instances(engine, world, &group) {
+                draw_calls += 1;
+                continue;
+            }
+
+            for (i, render_component, transform_component) in group {
+                draw_calls += self.draw_entity(engine, world, i, render_component, transform_component) as u32;
+            }
+        }
+
+        for (i, render_component, transform_component) in singles {
+            draw_calls += self.draw_entity(engine, world, i, render_component, transform_component) as u32;
+        }
+
+        draw_calls += self.draw_ropes(engine, world);
+
+        //Note(teddy) Still tested against the opaque depth but not written, the ones behind blend in.
+        //Stencil writes don't follow the depth mask so highlighted ones keep their outline.
+        if !transparent.is_empty() {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+
+            for (i, render_component, transform_component) in transparent {
+                draw_calls += self.draw_entity(engine, world, i, render_component, transform_component) as u32;
+            }
+
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+
+        if !overlay.is_empty() || engine.overlay_lines.is_some() {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            for (i, render_component, transform_component) in overlay {
+                draw_calls += self.draw_entity(engine, world, i, render_component, transform_component) as u32;
+            }
+            draw_calls += self.draw_overlay_lines(engine, world) as u32;
+        }
+
+        draw_calls += self.draw_debug_lines(engine, world);
+        engine.frame_draw_calls = draw_calls;
+
+        //Note(teddy) The screen pass and the captures below read the single sampled texture
+        engine.scene_render_object.resolve();
+
+        let ViewPortDimensions {width, height} = engine.framebuffer_size;
+        gl::Viewport(0, 0, width, height);
+
+        if engine.screenshot_requested {
+            engine.screenshot_requested = false;
+            self.capture_screenshot(engine);
+        }
+
+        if let Some(path) = engine.thumbnail_request.take() {
+            self.capture_scene(engine, path, Some((THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)));
+        }
+    }
+
+    unsafe fn apply_msaa_samples(&mut self, engine: &mut Engine) {
+        let requested = engine.msaa_samples;
+        engine.scene_render_object.set_samples(requested);
+
+        let samples = engine.scene_render_object.samples();
+        if samples < requested {
+            engine.log_manager.add_log((
+                String::from("msaa"),
+                Box::new(RenderSystemLogObject { text: format!("MSAA: {}x isn't supported, drawing with {}x", requested, samples) }),
+            ));
+        }
+    }
+
+    unsafe fn apply_gpu_timing(&mut self, engine: &mut Engine) {
+        if !engine.gpu_timing {
+            if let Some(timer) = self.gpu_timer.take() {
+                timer.dispose();
+            }
+            return;
+        }
+
+        if self.gpu_timer.is_some() || self.gpu_timer_unsupported {
+            return;
+        }
+
+        self.gpu_timer = GpuTimer::new();
+        if self.gpu_timer.is_none() {
+            self.gpu_timer_unsupported = true;
+            engine.log_manager.add_log((
+                String::from("gpu_timing"),
+                Box::new(RenderSystemLogObject { text: format!("GPU timing: timer queries aren't supported") }),
+            ));
+        }
+    }
+
+    unsafe fn begin_pass(&mut self, pass: RenderPass) {
+        stats::begin_pass(pass);
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin_pass(pass);
+        }
+    }
+
+    ///Ends the last pass and hands the frame's stats to the engine, the gpu times are from a couple of frames back
+    unsafe fn end_frame(&mut self, engine: &mut Engine) {
+        stats::end_pass();
+
+        let mut frame = stats::frame_stats();
+        frame.gpu_timing = engine.gpu_timing;
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.end_frame();
+            for pass in RenderPass::ALL.iter() {
+                frame.pass_mut(*pass).gpu_time = timer.time(*pass);
+            }
+        }
+
+        engine.render_stats = frame;
+    }
+
+    unsafe fn capture_screenshot(&self, engine: &mut Engine) {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+
+        self.capture_scene(engine, format!("screenshots/screenshot_{}.png", seconds), None);
+    }
+
+    ///Copies the scene viewport and hands it to the image writer, the encode happens off the main thread
+    unsafe fn capture_scene(&self, engine: &mut Engine, path: String, resize: Option<(u32, u32)>) {
+        let (x, y, width, height) = engine.scene_viewport_pixels();
+        let mut pixels: Vec<u8> = vec![0; (width * height * 3) as usize];
+
+        //Note(teddy) Rows are tightly packed, the default alignment pads RGB rows to 4 bytes
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(x, y, width, height, gl::RGB, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut c_void);
+
+        let job = ImageJob {
+            pixels,
+            width: width as u32,
+            height: height as u32,
+            flip_y: true,
+            path,
+            format: PixelFormat::Rgb8,
+            resize,
+        };
+
+        if let Err(error) = engine.image_writer.submit(job) {
+            engine.log_manager.add_log((
+                String::from("screenshot"),
+                Box::new(RenderSystemLogObject { text: format!("Capture skipped: {:?}", error) }),
+            ));
+        }
+    }
+
+    ///One instanced draw for the group, false when the shader has no instanced variant yet
+    unsafe fn draw_instances(&mut self, engine: &Engine, world: &World, group: &[ComponentsForRender]) -> bool {
+        let (_, render_component, _) = group[0];
+        let shader_label = instanced_shader_label(&render_component.shader_label);
+        if world.resources.read().unwrap().shaders.get(&shader_label).map_or(true, Option::is_none) {
+            return false;
+        }
+
+        let instance_buffer = *self.instance_buffer.get_or_insert_with(|| {
+            let mut buffer = 0;
+            gl::GenBuffers(1, &mut buffer);
+            buffer
+        });
+
+        //Note(teddy) The group shares one uploaded mesh, the textured ones never make it into a group
+        let object = match self.render_object(group[0].0) {
+            Some((object, false)) => object,
+            _ => return false,
+        };
+        let transforms: Vec<&TransformComponent> = group.iter().map(|(_, _, transform)| *transform).collect();
+
+        let draw_params = || {
+            gl::Enable(gl::CULL_FACE);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+        };
+
+        if let Err(error) = draw_instanced_objects(
+            world,
+            &shader_label,
+            &engine.camera,
+            object,
+            instance_buffer,
+            &transforms,
+            &engine.dir_lights,
+            &render_component.material,
+            draw_params,
+        ) {
+            println!("Failed to draw {} instances: {:?}", render_component.mesh_label, error);
+        }
+        true
+    }
+
+    unsafe fn draw_entity(
+        &self,
+        engine: &Engine,
+        world: &World,
+        id: EntityID,
+        render_component: &RenderComponent,
+        transform_component: &TransformComponent,
+    ) -> bool {
+        //Note(teddy) Textured meshes only draw once their texture is uploaded, until then they're skipped
+        let diffuse_map = render_component.material.diffuse_map.as_ref().and_then(|name| world.resources.read().unwrap().texture(name));
+        let (render_object, diffuse_map) = match self.render_object(id) {
+            Some((object, false)) => (object, None),
+            Some((object, true)) if diffuse_map.is_some() => (object, diffuse_map),
+            _ => return false,
+        };
+
+        //Note(teddy) Wireframe is scoped to this draw call, restore whatever mode was active
+        let wireframe = engine.wireframe || render_component.is_wireframe();
+        let mut previous_polygon_mode = [gl::FILL as i32; 2];
+        if wireframe {
+            gl::GetIntegerv(gl::POLYGON_MODE, previous_polygon_mode.as_mut_ptr());
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+        }
 
-                draw_normal_object(
+        if render_component.highlight.is_none() {
+            let draw_params = || {
+                gl::Enable(gl::CULL_FACE);
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+            };
+
+            match diffuse_map {
+                Some(texture) => draw_textured_object(
+                    &world,
+                    &TEXTURED_SHADER!(),
+                    &engine.camera,
+                    render_object,
+                    &transform_component,
+                    &engine.dir_lights,
+                    &render_component.material,
+                    texture,
+                    draw_params,
+                ),
+                None => draw_normal_object(
                     &world,
                     &render_component.shader_label,
                     &engine.camera      ,
                     render_object,
                     &transform_component,
                     &engine.dir_lights,
+                    &render_component.material,
                     draw_params,
-                )
-                .unwrap();
-                continue;
+                ),
             }
-
+            .unwrap();
+        } else {
             draw_with_highlight(HighlightReferences { 
                 world: &world, 
                 shader_label: &render_component.shader_label, 
                 camera: &engine.camera, 
                 transform: &transform_component, 
                 light: &engine.dir_lights, 
-                object: &render_object
+                object: &render_object,
+                material: &render_component.material,
+                diffuse_map,
             });
         }
 
-        let ViewPortDimensions {width, height} = engine.camera.view_port;
+        if wireframe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, previous_polygon_mode[0] as u32);
+        }
+
+        if render_component.debug_view == DebugViewMode::Normals {
+            let length = render_object.bounds.map_or(DEFAULT_NORMAL_LENGTH, |bounds| bounds.diagonal() * NORMAL_LENGTH_FRACTION)
+                * transform_component.scale;
+            if let Err(error) = draw_normals(world, &NORMALS_SHADER!(), &engine.camera, render_object, transform_component, length) {
+                println!("Failed to draw the normals of {}: {:?}", render_component.mesh_label, error);
+            }
+        }
+        true
+    }
+
+    //Note(teddy) Rope meshes are rebuilt every frame from the simulated particles
+    unsafe fn draw_ropes(&mut self, engine: &Engine, world: &World) -> u32 {
+        let mut draw_calls = 0;
+        let world_transform = TransformComponent::new(Vector3::zeros(), Vector3::zeros(), 1.0);
+
+        for entity in world.entities.iter() {
+            let rope = match world.components.ropes[*entity].as_ref() {
+                Some(rope) if rope.particles.len() > 1 => rope,
+                _ => continue,
+            };
+
+            let mesh = build_rope_mesh(&rope.particles, rope.thickness);
+            //Note(teddy) A new rope is uploaded by init, only existing ones need the update
+            let render_object = match self.rope_objects.entry(*entity) {
+                Entry::Occupied(entry) => {
+                    let render_object = entry.into_mut();
+                    update_normal_object(render_object, &mesh);
+                    render_object
+                }
+                Entry::Vacant(entry) => entry.insert(init_normal_object(&mesh)),
+            };
+
+            let draw_params = || {
+                gl::Disable(gl::CULL_FACE);
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+            };
+
+            if let Err(error) = draw_normal_object(
+                world,
+                &rope.shader_label,
+                &engine.camera,
+                render_object,
+                &world_transform,
+                &engine.dir_lights,
+                &Material::default(),
+                draw_params,
+            ) {
+                println!("Failed to draw rope {}: {:?}", entity, error);
+            }
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+
+    //Note(teddy) Every segment becomes a thin tube, same mesh builder as the ropes
+    unsafe fn draw_overlay_lines(&mut self, engine: &Engine, world: &World) -> bool {
+        let lines = match engine.overlay_lines.as_ref() {
+            Some(lines) if !lines.segments.is_empty() => lines,
+            _ => return false,
+        };
+
+        let mut mesh = NormalObj { vertices: vec![], normals: vec![], indices: vec![] };
+        for (start, end) in lines.segments.iter() {
+            let segment = build_rope_mesh(&[*start, *end], lines.thickness);
+            let offset = mesh.vertices.len() as u32;
+
+            mesh.vertices.extend(segment.vertices);
+            mesh.normals.extend(segment.normals);
+            mesh.indices.extend(segment.indices.iter().map(|index| index + offset));
+        }
+
+        let render_object = self.overlay_lines_object.get_or_insert_with(|| init_normal_object(&mesh));
+        update_normal_object(render_object, &mesh);
+
+        let world_transform = TransformComponent::new(Vector3::zeros(), Vector3::zeros(), 1.0);
+        let draw_params = || {
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+        };
+
+        if let Err(error) = draw_normal_object(
+            world,
+            &lines.shader_label,
+            &engine.camera,
+            render_object,
+            &world_transform,
+            &engine.dir_lights,
+            &Material::default(),
+            draw_params,
+        ) {
+            println!("Failed to draw overlay lines: {:?}", error);
+        }
+        true
+    }
+
+    ///Ground grid under the scene, depth tested so entities cover it
+    unsafe fn draw_grid(&mut self, engine: &Engine, world: &World) -> bool {
+        let object = self.grid_object.get_or_insert_with(|| init_debug_lines_object());
+        let lines = ground_grid(Point3::from(engine.camera.position), GRID_EXTENT, GRID_SPACING);
+
+        match draw_debug_lines(world, &GRID_SHADER!(), &engine.camera, object, &lines, true) {
+            Ok(()) => true,
+            Err(error) => {
+                println!("Failed to draw the grid: {:?}", error);
+                false
+            }
+        }
+    }
+
+    ///Flushes what was queued on the engine's debug draw, the scene lines first then the overlay ones
+    unsafe fn draw_debug_lines(&mut self, engine: &Engine, world: &World) -> u32 {
+        let debug_draw = &engine.debug_draw;
+        let object = self.debug_lines_object.get_or_insert_with(|| init_debug_lines_object());
 
-        let mut texture_data: Vec<u8> = Vec::with_capacity((width * height * 1000).try_into().unwrap());
-        gl::ReadPixels(0, 0, width, height, gl::RGB, gl::UNSIGNED_BYTE, texture_data.as_mut_ptr() as *mut c_void);
-        //println!("{:?}", texture_data.len());
+        let mut draw_calls = 0;
+        for (lines, depth_test) in [(&debug_draw.scene, true), (&debug_draw.overlay, false)].iter() {
+            if lines.is_empty() {
+                continue;
+            }
+
+            match draw_debug_lines(world, &DEBUG_LINE_SHADER!(), &engine.camera, object, lines, *depth_test) {
+                Ok(()) => draw_calls += 1,
+                Err(error) => println!("Failed to draw debug lines: {:?}", error),
+            }
+        }
+        draw_calls
+    }
+
+    ///The entity's render object and whether it's the textured kind
+    fn render_object(&self, id: EntityID) -> Option<(&RenderObject, bool)> {
+        let label = self.entity_meshes.get(&id)?;
+        match (self.normal_objects.get(label), self.textured_objects.get(label)) {
+            (Some(shared), _) => Some((&shared.object, false)),
+            (None, Some(shared)) => Some((&shared.object, true)),
+            (None, None) => None,
+        }
     }
 
     fn allocate_entity(
@@ -208,6 +781,7 @@ impl Renderer {
         event: Event, 
         id: EntityID, 
         event_manager: &mut EventManager, 
+        label: &str,
         mesh: &Option<MeshType>
     ) -> Result<(), String>{
         let mesh_type = match mesh {
@@ -220,23 +794,28 @@ impl Renderer {
             }
         };
 
-        match mesh_type {
-            MeshType::Textured(obj) => {
-                let _render_object = unsafe { init_textured_object(&obj) };
-            },
-            MeshType::Normal(obj) => {
-                let render_object = unsafe { init_normal_object(&obj) };
-
-                if let Some(_) = self.normal_objects.insert(id, render_object) {
-                    panic!("Weird, looks render object for this entity exists.")
-                };
+        if let Some(_) = self.entity_meshes.insert(id, label.to_string()) {
+            panic!("Weird, looks render object for this entity exists.")
+        };
 
-                if event.is_pending_for(SystemType::RenderSystem) {
-                    event_manager
-                        .remove_pending(event.id, SystemType::RenderSystem);
-                }
-            }
+        //Note(teddy) Only the first entity with the mesh uploads it, the rest share its object
+        let objects = match mesh_type {
+            MeshType::Textured(_) => &mut self.textured_objects,
+            MeshType::Normal(_) => &mut self.normal_objects,
         };
+        let shared = objects.entry(label.to_string()).or_insert_with(|| {
+            let object = match mesh_type {
+                MeshType::Textured(obj) => unsafe { init_textured_object(&obj) },
+                MeshType::Normal(obj) => unsafe { init_normal_object(&obj) },
+            };
+            SharedObject { object, users: 0 }
+        });
+        shared.users += 1;
+
+        if event.is_pending_for(SystemType::RenderSystem) {
+            event_manager
+                .remove_pending(event.id, SystemType::RenderSystem);
+        }
         Ok(())
 
     }
@@ -248,7 +827,7 @@ impl Renderer {
         event_manager: &mut EventManager, 
         world: &mut World
     ) -> Result<(), String> {
-        if (self.normal_objects.contains_key(&id) || self.textured_objects.contains_key(&id)) 
+        if self.entity_meshes.contains_key(&id) 
             && event.is_pending_for(SystemType::RenderSystem) {
             return Err(format!(""));
         }
@@ -258,8 +837,10 @@ impl Renderer {
         };
 
         match world.resources.try_read() {
-            Ok(res) if res.mesh_data.contains_key(mesh_label) =>
-                self.allocate_entity(event, id, event_manager, &res.mesh_data[mesh_label].mesh_type),
+            Ok(res) if res.mesh_data.contains_key(mesh_label) => {
+                let canonical = res.mesh_aliases.canonical(mesh_label);
+                self.allocate_entity(event, id, event_manager, canonical, &res.mesh(mesh_label).unwrap().mesh_type)
+            }
             Err(_) => {
                 if !event.is_pending_for(SystemType::RenderSystem) {
                     event_manager.add_pending(event, SystemType::RenderSystem);
@@ -270,43 +851,18 @@ impl Renderer {
         }
     }
 
-    fn free_objects(&mut self, id: EntityID, mesh: &Option<MeshType>) -> Result <(), String> {
-        if let Some(mesh_type) = mesh {
-            match mesh_type {
-                MeshType::Textured(_obj) => {
-                    remove_textured_object(
-                        id,
-                        self.textured_objects.remove(&id).unwrap(),
-                    );
-                }
-
-                MeshType::Normal(_obj) => {
-                    remove_normal_object(
-                        id,
-                        self.normal_objects.remove(&id).unwrap(),
-                    );
-                }
+    //Note(teddy) The components are already cleared by the time the event comes through, the objects are found by id
+    fn remove_entity(&mut self, id: EntityID) {
+        if let Some(label) = self.entity_meshes.remove(&id) {
+            if let Some(object) = release_shared(&mut self.normal_objects, &label) {
+                remove_normal_object(id, object);
             }
-
-            Ok(())
-        } else {
-            Err("".to_string())
-        }
-    }
-
-    fn remove_entity(&mut self, id: EntityID, event: Event, event_manager: &mut EventManager, world: &mut World) -> Result<(), String> {
-        let mesh_label = match world.components.renderables[id].as_mut() {
-            Some(comp) => &comp.mesh_label,
-            None => return Err("".to_string()),
-        };
-
-        match world.resources.try_read() {
-            Ok(res) if res.mesh_data.contains_key(mesh_label) => self.free_objects(id, &res.mesh_data[mesh_label].mesh_type),
-            Err(_) => {
-                event_manager.add_pending(event, SystemType::RenderSystem);
-                Err("".to_string())
+            if let Some(object) = release_shared(&mut self.textured_objects, &label) {
+                remove_textured_object(id, object);
             }
-            _ => Err("".to_string())
+        }
+        if let Some(object) = self.rope_objects.remove(&id) {
+            remove_normal_object(id, object);
         }
     }
 
@@ -318,9 +874,7 @@ impl Renderer {
                     self.handle_entity_creation(id, event, event_manager, world);
                 }
 
-                EventType::EntityRemoved(id) => {
-                    self.remove_entity(id, event, event_manager, world);
-                }
+                EventType::EntityRemoved(id) => self.remove_entity(id),
 
                 _ => (),
             }
@@ -328,6 +882,16 @@ impl Renderer {
     }
 }
 
+///Drops one user of the mesh, the object comes back once nothing draws it anymore
+fn release_shared(objects: &mut HashMap<String, SharedObject>, label: &str) -> Option<RenderObject> {
+    let shared = objects.get_mut(label)?;
+    shared.users -= 1;
+    match shared.users {
+        0 => objects.remove(label).map(|shared| shared.object),
+        _ => None,
+    }
+}
+
 impl System for Renderer {
     fn name(&self) -> String {
         String::from("Renderer")
@@ -341,7 +905,7 @@ impl System for Renderer {
 
             Some(id) => {
                 if let Some(shader_id) = id {
-                    *shader_id
+                    shader_id.id
                 } else {
                     return Err(String::from("Failed to load the screen shader"));
                 }
@@ -398,23 +962,53 @@ impl System for Renderer {
         world: &mut World,
         event_manager: &mut EventManager,
         engine: &mut Engine,
-        _delta_time: f32,
+        delta_time: f32,
     ) {
 
 
         self.handle_system_events(event_manager, world);
 
+        if let Some(size) = engine.pending_resize.take() {
+            unsafe {
+                engine.scene_render_object.resize(size);
+                //Note(teddy) None until init_ui, it's created at the size of the time
+                if let Some(ui_render_object) = engine.ui_render_object.as_mut() {
+                    ui_render_object.resize(size);
+                }
+            }
+        }
+
+        //Note(teddy) The loading thread holds the lock while it works, the uploads wait for a frame it doesn't
+        if let Ok(mut resources) = world.resources.try_write() {
+            unsafe { resources.upload_pending_textures() };
+
+            //Note(teddy) A hot reload swaps the screen shader for a new program
+            if let Some(Some(program)) = resources.shaders.get(&SCREEN_SHADER!()) {
+                self.screen_shader_program = Some(program.id);
+            }
+        }
+
         unsafe {
+            self.apply_gpu_timing(engine);
+            stats::begin_frame();
+            self.begin_pass(RenderPass::Scene);
             let instant = Instant::now();
             self.draw_entities(engine, world);
-            draw_ui(engine, &mut engine.log_manager);
+            engine.debug_draw.clear();
+            self.begin_pass(RenderPass::Ui);
+            draw_ui(engine, &mut engine.log_manager, delta_time);
             let time = instant.elapsed().as_millis();
 
             let log_manager = &mut engine.log_manager;
             log_manager.add_log((
                 format!("render_system"), 
-                Box::new(RenderSystemLogObject{text: format!("RENDER_SYSTEM: {} ms", time)})
+                Box::new(RenderSystemLogObject{text: format!(
+                    "RENDER_SYSTEM: {} ms {} draw calls, {} drawn {} culled, {}x msaa",
+                    time, engine.frame_draw_calls, self.cull_counts.0, self.cull_counts.1, engine.scene_render_object.samples()
+                )})
             ));
+
+            self.begin_pass(RenderPass::Composite);
             //Note(teddy) I guess the texturing is not working
             //Note(teddy) Drawing the screen shadee
             //Using the sceen texture
@@ -442,10 +1036,16 @@ impl System for Renderer {
                 bind_texture(&engine.scene_render_object, 0, program, "scene_shader");
                 bind_texture(engine.ui_render_object.as_ref().unwrap(), 1, program, "ui_texture");
                 gl::DrawArrays(gl::TRIANGLES, 0, 6);
+                stats::record_draw(2);
 
 
                 gl::BindVertexArray(0);
             }
+            self.end_frame(engine);
+            engine.log_manager.add_log((
+                String::from("render_stats"),
+                Box::new(RenderStatsLogObject { stats: engine.render_stats }),
+            ));
         }
     }
 }
@@ -454,22 +1054,127 @@ impl System for Renderer {
 
 
 //TODO(teddy) Draw on a seperate frame buffer
-unsafe fn draw_ui(engine: *mut Engine, log_manager: *mut LogManager) {
+//Note(teddy) delta_time is in seconds, the time the last frame took
+unsafe fn draw_ui(engine: *mut Engine, log_manager: *mut LogManager, delta_time: f32) {
     let eng = engine.as_mut().unwrap();
     let ui_frame_buffer = eng.ui_render_object.as_ref().unwrap().frame_buffer;
 
     gl::BindFramebuffer(gl::FRAMEBUFFER, ui_frame_buffer);
-    gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+    //Note(teddy) Transparent, wherever nothing is drawn the screen shader shows the scene
+    gl::ClearColor(0.0, 0.0, 0.0, 0.0);
     gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
     gl::Enable(gl::DEPTH_TEST);
 
-    log_manager.as_ref().unwrap().update_ui_logs_view(eng);
+    let resources = eng.ui.clone();
+    log_manager.as_ref().unwrap().update_ui_logs_view(eng.get_ui_tree().unwrap(), &resources);
     //TODO(Teddy) Do all the buffer clearing operations
 
+    eng.get_ui_tree().unwrap().begin_frame();
+    eng.get_ui_tree().unwrap().update_layout();
+
     if let Some(view) = &mut eng.get_ui_tree().unwrap().root {
         match view.update(engine.as_ref().unwrap()) {
             Ok(_) => (),
             Err(_) => println!("A view failed to update"),
         }
     }
+
+    for overlay in eng.get_ui_tree().unwrap().overlays.iter_mut().filter(|overlay| overlay.visible) {
+        if overlay.view.update(engine.as_ref().unwrap()).is_err() {
+            println!("Overlay {} failed to update", overlay.view.get_id());
+        }
+    }
+
+    //Note(teddy) Popups go over everything drawn so far, clearing depth is what puts them on top
+    if let Some(view) = &mut eng.get_ui_tree().unwrap().root {
+        gl::Clear(gl::DEPTH_BUFFER_BIT);
+        if view.draw_popups(engine.as_ref().unwrap()).is_err() {
+            println!("A popup failed to draw");
+        }
+    }
+
+    if eng.get_ui_tree().unwrap().draw_modals(engine.as_ref().unwrap()).is_err() {
+        println!("A modal failed to draw");
+    }
+
+    let tree = eng.get_ui_tree().unwrap();
+    tree.update(delta_time);
+    //Note(teddy) Nothing is walking the tree anymore, the queued changes show from the next frame
+    tree.flush_commands();
+    if tree.draw_drag_ghost(engine.as_ref().unwrap()).is_err() {
+        println!("The drag ghost failed to draw");
+    }
+    if tree.draw_context_menu(engine.as_ref().unwrap()).is_err() {
+        println!("The context menu failed to draw");
+    }
+    if tree.draw_tooltip(engine.as_ref().unwrap()).is_err() {
+        println!("The tooltip failed to draw");
+    }
+
+    let stats = ui_cull_stats();
+    log_manager.as_mut().unwrap().add_log((
+        format!("ui_culling"),
+        Box::new(RenderSystemLogObject { text: format!("UI: {} drawn {} culled", stats.drawn, stats.culled) }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities_sharing_mesh_and_shader_are_grouped() {
+        let cube = RenderComponent::new(String::from("cube.obj"), String::from("default"));
+        let sphere = RenderComponent::new(String::from("sphere.obj"), String::from("default"));
+        let mut highlighted = RenderComponent::new(String::from("cube.obj"), String::from("default"));
+        highlighted.highlight = Some([1.0, 0.0, 0.0]);
+        let mut red = RenderComponent::new(String::from("cube.obj"), String::from("default"));
+        red.material.base_color = [1.0, 0.0, 0.0];
+        let mut normals = RenderComponent::new(String::from("cube.obj"), String::from("default"));
+        normals.debug_view = DebugViewMode::Normals;
+        let transform = TransformComponent::new(Vector3::zeros(), Vector3::zeros(), 1.0);
+
+        let entities = vec![
+            (0, &cube, &transform),
+            (1, &sphere, &transform),
+            (2, &cube, &transform),
+            (3, &highlighted, &transform),
+            (4, &red, &transform),
+            (5, &red, &transform),
+            (6, &normals, &transform),
+        ];
+        let (groups, singles) = group_instances(entities.clone(), |id| entities.iter().find(|(e, _, _)| *e == id).map(|(_, c, _)| c.mesh_label.as_str()));
+
+        let ids = |entities: &Vec<ComponentsForRender>| entities.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+        assert_eq!(groups.iter().map(ids).collect::<Vec<_>>(), vec![vec![0, 2], vec![4, 5]]);
+        assert_eq!(ids(&singles), vec![3, 6, 1]);
+
+        //Note(teddy) crate.obj is an alias of cube.obj and draws with it, 8 has no render object yet
+        let crate_mesh = RenderComponent::new(String::from("crate.obj"), String::from("default"));
+        let entities = vec![(0, &cube, &transform), (7, &crate_mesh, &transform), (8, &cube, &transform), (2, &cube, &transform)];
+        let mesh_of = |id: EntityID| match id {
+            0 | 2 | 7 => Some("cube.obj"),
+            _ => None,
+        };
+        let (groups, singles) = group_instances(entities, mesh_of);
+        assert_eq!(groups.iter().map(ids).collect::<Vec<_>>(), vec![vec![0, 7, 2]]);
+        assert_eq!(ids(&singles), vec![8]);
+    }
+
+    #[test]
+    fn test_sorting_by_camera_distance() {
+        let cube = RenderComponent::new(String::from("cube.obj"), String::from("default"));
+        let near = TransformComponent::new(Vector3::new(0.0, 0.0, 2.0), Vector3::zeros(), 1.0);
+        let middle = TransformComponent::new(Vector3::new(0.0, 0.0, -5.0), Vector3::zeros(), 1.0);
+        let far = TransformComponent::new(Vector3::new(10.0, 0.0, 0.0), Vector3::zeros(), 1.0);
+        let camera = Point3::new(0.0, 0.0, 3.0);
+
+        let mut entities = vec![(0, &cube, &middle), (1, &cube, &far), (2, &cube, &near)];
+        let ids = |entities: &Vec<ComponentsForRender>| entities.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+
+        sort_by_camera_distance(&mut entities, &camera, false);
+        assert_eq!(ids(&entities), vec![2, 0, 1]);
+        sort_by_camera_distance(&mut entities, &camera, true);
+        assert_eq!(ids(&entities), vec![1, 0, 2]);
+    }
 }
@@ -0,0 +1,270 @@
+use nalgebra::{Point3, Point4, Vector3};
+
+use super::system::System;
+use crate::core::{Engine, EventManager};
+use crate::game_world::components::{RopeAnchor, RopeComponent};
+use crate::game_world::world::World;
+use crate::obj_parser::NormalObj;
+
+//Note(teddy) Verlet needs a steady timestep, the frame time is collected and the ropes
+//take as many fixed steps as fit in it
+pub const ROPE_TIMESTEP: f32 = 1.0 / 60.0;
+///A long frame, a hitch or a breakpoint, drops whatever doesn't fit in this many steps
+const MAX_ROPE_STEPS: usize = 5;
+const ROPE_GRAVITY: f32 = -9.81;
+const ROPE_DAMPING: f32 = 0.98;
+const ROPE_TUBE_SIDES: usize = 6;
+
+pub struct RopeSystem {
+    ///Frame time not stepped yet, less than a ROPE_TIMESTEP
+    accumulator: f32,
+}
+
+impl RopeSystem {
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+}
+
+///Fixed steps to take for the frame, the remainder carries over to the next one
+fn rope_steps(accumulator: &mut f32, delta_time: f32) -> usize {
+    *accumulator += delta_time.max(0.0);
+    let steps = (*accumulator / ROPE_TIMESTEP) as usize;
+    *accumulator -= steps as f32 * ROPE_TIMESTEP;
+
+    if steps > MAX_ROPE_STEPS {
+        *accumulator = 0.0;
+    }
+    steps.min(MAX_ROPE_STEPS)
+}
+
+impl System for RopeSystem {
+    fn name(&self) -> String {
+        String::from("Ropes")
+    }
+
+    fn update(
+        &mut self,
+        world: &mut World,
+        _event_manager: &mut EventManager,
+        _engine: &mut Engine,
+        delta_time: f32,
+    ) {
+        let steps = rope_steps(&mut self.accumulator, delta_time);
+        if steps == 0 {
+            return;
+        }
+
+        for entity in world.entities.iter() {
+            let (start, end) = match world.components.ropes[*entity].as_ref() {
+                Some(rope) => (
+                    resolve_anchor(world, &rope.start),
+                    resolve_anchor(world, &rope.end),
+                ),
+                None => continue,
+            };
+
+            //Note(teddy) Anchor entity was deleted or lost its transform, leave the rope where it is
+            let (start, end) = match (start, end) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+
+            let rope = world.components.ropes[*entity].as_mut().unwrap();
+            for _ in 0..steps {
+                simulate_rope(rope, start, end, ROPE_TIMESTEP);
+            }
+        }
+    }
+}
+
+fn resolve_anchor(world: &World, anchor: &RopeAnchor) -> Option<Point3<f32>> {
+    match anchor {
+        RopeAnchor::Point(point) => Some(*point),
+        RopeAnchor::Entity(id) => match world.components.positionable.get(*id) {
+            Some(Some(transform)) => Some(Point3::from(transform.position.translation.vector)),
+            _ => None,
+        },
+    }
+}
+
+///Lay the particles out on a straight line between the anchors
+pub fn reset_rope(rope: &mut RopeComponent, start: Point3<f32>, end: Point3<f32>) {
+    let segments = rope.segments.max(1);
+    rope.particles = (0..=segments)
+        .map(|i| start + (end - start) * (i as f32 / segments as f32))
+        .collect();
+    rope.previous = rope.particles.clone();
+}
+
+///Verlet integration followed by a few relaxation passes over the distance constraints.
+///The first and last particles are pinned to the anchors.
+pub fn simulate_rope(rope: &mut RopeComponent, start: Point3<f32>, end: Point3<f32>, dt: f32) {
+    if rope.particles.len() != rope.segments.max(1) + 1 {
+        reset_rope(rope, start, end);
+    }
+
+    let gravity = Vector3::new(0.0, ROPE_GRAVITY, 0.0) * dt * dt;
+    let last = rope.particles.len() - 1;
+
+    for i in 1..last {
+        let current = rope.particles[i];
+        let velocity = (current - rope.previous[i]) * ROPE_DAMPING;
+        rope.previous[i] = current;
+        rope.particles[i] = current + velocity + gravity;
+    }
+
+    rope.previous[0] = rope.particles[0];
+    rope.previous[last] = rope.particles[last];
+    rope.particles[0] = start;
+    rope.particles[last] = end;
+
+    let rest_length = rope.segment_length();
+    for _ in 0..rope.iterations.max(1) {
+        for i in 0..last {
+            let delta = rope.particles[i + 1] - rope.particles[i];
+            let distance = delta.norm();
+            if distance <= std::f32::EPSILON {
+                continue;
+            }
+
+            let correction = delta * ((distance - rest_length) / distance);
+            let (first_weight, second_weight) = match (i == 0, i + 1 == last) {
+                (true, true) => continue,
+                (true, false) => (0.0, 1.0),
+                (false, true) => (1.0, 0.0),
+                (false, false) => (0.5, 0.5),
+            };
+
+            rope.particles[i] += correction * first_weight;
+            rope.particles[i + 1] -= correction * second_weight;
+        }
+    }
+}
+
+///Build a tube around the particle chain, the vertices are already in world space
+pub fn build_rope_mesh(particles: &[Point3<f32>], thickness: f32) -> NormalObj {
+    let mut vertices = vec![];
+    let mut normals = vec![];
+    let mut indices = vec![];
+
+    if particles.len() < 2 {
+        return NormalObj { vertices, normals, indices };
+    }
+
+    let radius = thickness * 0.5;
+    let mut reference = Vector3::y();
+
+    for (i, particle) in particles.iter().enumerate() {
+        let previous = particles[i.saturating_sub(1)];
+        let next = particles[std::cmp::min(i + 1, particles.len() - 1)];
+
+        let tangent = match (next - previous).try_normalize(std::f32::EPSILON) {
+            Some(tangent) => tangent,
+            None => Vector3::x(),
+        };
+
+        //Note(teddy) Switch the reference axis when the rope runs parallel to it
+        if tangent.dot(&reference).abs() > 0.99 {
+            reference = Vector3::x();
+        }
+
+        let u = tangent.cross(&reference).normalize();
+        let v = tangent.cross(&u);
+
+        for side in 0..ROPE_TUBE_SIDES {
+            let angle = (side as f32 / ROPE_TUBE_SIDES as f32) * std::f32::consts::PI * 2.0;
+            let normal = u * angle.cos() + v * angle.sin();
+            let position = particle + normal * radius;
+
+            vertices.push(Point4::new(position.x, position.y, position.z, 1.0));
+            normals.push(Point3::from(normal));
+        }
+    }
+
+    for ring in 0..(particles.len() - 1) as u32 {
+        let sides = ROPE_TUBE_SIDES as u32;
+        for side in 0..sides {
+            let current = ring * sides + side;
+            let next = ring * sides + (side + 1) % sides;
+
+            indices.extend_from_slice(&[current, current + sides, next]);
+            indices.extend_from_slice(&[next, current + sides, next + sides]);
+        }
+    }
+
+    NormalObj { vertices, normals, indices }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rope_length(rope: &RopeComponent) -> f32 {
+        rope.particles
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).norm())
+            .sum()
+    }
+
+    #[test]
+    fn test_rope_settles_within_length_bounds() {
+        let start = Point3::new(0.0, 5.0, 0.0);
+        let end = Point3::new(4.0, 5.0, 0.0);
+        let mut rope = RopeComponent::new(
+            RopeAnchor::Point(start),
+            RopeAnchor::Point(end),
+            12,
+            5.0,
+            8,
+            0.05,
+            String::from("default"),
+        );
+
+        for _ in 0..2000 {
+            simulate_rope(&mut rope, start, end, ROPE_TIMESTEP);
+        }
+
+        let max_motion = rope
+            .particles
+            .iter()
+            .zip(rope.previous.iter())
+            .map(|(current, previous)| (current - previous).norm())
+            .fold(0.0, f32::max);
+        assert!(max_motion < 1e-3, "rope still moving {}", max_motion);
+
+        let length = rope_length(&rope);
+        assert!(length >= 4.0 && length <= 5.0 * 1.05, "length {}", length);
+
+        let middle = rope.particles[rope.particles.len() / 2];
+        assert!(middle.y < start.y - 1.0, "rope did not sag {:?}", middle);
+        assert_eq!(rope.particles[0], start);
+        assert_eq!(rope.particles[rope.particles.len() - 1], end);
+    }
+
+    #[test]
+    fn test_ropes_step_with_the_frame_time() {
+        let mut accumulator = 0.0;
+
+        //Note(teddy) A second is sixty steps at 30, 60 or 144 frames a second
+        for fps in [30.0, 60.0, 144.0] {
+            let frame = 1.0 / fps;
+            let steps: usize = (0..fps as usize).map(|_| rope_steps(&mut accumulator, frame)).sum();
+            assert!((59..=61).contains(&steps), "{} steps at {} fps", steps, fps);
+        }
+
+        assert_eq!(rope_steps(&mut accumulator, 2.0), MAX_ROPE_STEPS);
+        assert_eq!(accumulator, 0.0);
+        assert_eq!(rope_steps(&mut accumulator, ROPE_TIMESTEP * 0.5), 0);
+    }
+
+    #[test]
+    fn test_rope_mesh_is_a_closed_tube() {
+        let particles = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, -1.0, 0.0), Point3::new(0.0, -2.0, 0.0)];
+        let mesh = build_rope_mesh(&particles, 0.1);
+
+        assert_eq!(mesh.vertices.len(), particles.len() * ROPE_TUBE_SIDES);
+        assert_eq!(mesh.indices.len(), (particles.len() - 1) * ROPE_TUBE_SIDES * 6);
+        assert!(mesh.indices.iter().all(|i| (*i as usize) < mesh.vertices.len()));
+    }
+}
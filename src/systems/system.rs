@@ -10,6 +10,7 @@ pub enum SystemType {
 
 pub trait System {
     //WE might do some event subscriptions
+    ///delta_time is the real time the last frame took in seconds, clamped after a stall
     fn update(
         &mut self,
         world: &mut World,
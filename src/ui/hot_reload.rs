@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::core::Engine;
+use crate::game_world::world::{ShaderSource, World, SHADER_ASSETS_DIR};
+use crate::renderer::shaders::ShaderProgram;
+
+//Note(teddy) Polling the modified times is enough for the handful of ui and shader files, a change
+//shows up at most one interval after the file is saved
+
+pub const UI_LAYOUT_DIR: &'static str = "./assets/ui/";
+const UI_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct FileWatcher {
+    ///Directory and the extension of the files in it that are watched, files added later are picked up
+    dirs: Vec<(PathBuf, &'static str)>,
+    modified: HashMap<PathBuf, SystemTime>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self { dirs: vec![], modified: HashMap::new(), interval, last_poll: Instant::now() }
+    }
+
+    pub fn watch_dir(&mut self, dir: PathBuf, extension: &'static str) {
+        self.dirs.push((dir, extension));
+    }
+
+    ///Files modified since the last poll, empty until the interval has passed
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return vec![];
+        }
+
+        self.last_poll = Instant::now();
+        self.scan()
+    }
+
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+
+        for path in self.watched_files() {
+            //Note(teddy) A file that can't be read right now, like one half way through a save, keeps its last time
+            if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                if self.record(&path, modified) {
+                    changed.push(path);
+                }
+            }
+        }
+
+        changed
+    }
+
+    fn watched_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![];
+
+        for (dir, extension) in &self.dirs {
+            if let Ok(entries) = fs::read_dir(dir) {
+                files.extend(
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| path.extension().map_or(false, |ext| ext == *extension)),
+                );
+            }
+        }
+
+        files
+    }
+
+    ///Stores the time and says whether it moved, the first time a file is seen isn't a change
+    fn record(&mut self, path: &Path, modified: SystemTime) -> bool {
+        match self.modified.insert(path.to_path_buf(), modified) {
+            Some(previous) => previous != modified,
+            None => false,
+        }
+    }
+}
+
+///Watches the json layouts and every shader source
+pub fn asset_file_watcher() -> FileWatcher {
+    let mut watcher = FileWatcher::new(UI_WATCH_INTERVAL);
+    watcher.watch_dir(PathBuf::from(UI_LAYOUT_DIR), "json");
+    watcher.watch_dir(PathBuf::from(SHADER_ASSETS_DIR), "glsl");
+
+    //Note(teddy) Takes the starting times so the first poll only reports real edits
+    watcher.scan();
+    watcher
+}
+
+pub fn is_shader_source(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "glsl")
+}
+
+///Recompiles the shaders built from the source file, see reload_shaders_where
+pub fn reload_shaders(source: &Path, engine: &mut Engine, world: &World) -> Result<Vec<String>, String> {
+    let file_name = source.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    reload_shaders_where(engine, world, |shader| shader.uses(file_name))
+}
+
+pub fn reload_all_shaders(engine: &mut Engine, world: &World) -> Result<Vec<String>, String> {
+    reload_shaders_where(engine, world, |_| true)
+}
+
+///Recompiles the matching shaders and swaps the new programs in, the next draw uses them. A shader that
+///fails to compile keeps drawing with its old program, the errors come back together.
+fn reload_shaders_where<F: Fn(&ShaderSource) -> bool>(engine: &mut Engine, world: &World, matches: F) -> Result<Vec<String>, String> {
+    let sources: Vec<(String, ShaderSource)> = world
+        .resources
+        .read()
+        .unwrap()
+        .shader_sources
+        .iter()
+        .filter(|(_, source)| matches(source))
+        .map(|(name, source)| (name.clone(), source.clone()))
+        .collect();
+
+    let mut reloaded = vec![];
+    let mut errors = vec![];
+    for (name, source) in sources {
+        let program = match unsafe { source.compile() } {
+            Ok(program) => program,
+            Err(error) => {
+                errors.push(format!("{} failed to compile: {:?}", name, error));
+                continue;
+            }
+        };
+
+        let previous = world.resources.write().unwrap().shaders.insert(name.clone(), Some(ShaderProgram::new(program)));
+        engine.ui.set_shader(&name, program);
+
+        if let Some(Some(previous)) = previous {
+            unsafe { gl::DeleteProgram(previous.id) };
+        }
+        reloaded.push(name);
+    }
+
+    if errors.is_empty() {
+        Ok(reloaded)
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_a_new_modified_time_is_a_change() {
+        let mut watcher = FileWatcher::new(Duration::from_secs(60));
+        let path = Path::new("./assets/ui/editor.json");
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+
+        assert!(!watcher.record(path, saved));
+        assert!(!watcher.record(path, saved));
+        assert!(watcher.record(path, saved + Duration::from_secs(1)));
+
+        //Note(teddy) Nothing is read before the interval is up
+        assert!(watcher.poll().is_empty());
+    }
+}
@@ -0,0 +1,545 @@
+use std::fs;
+use std::path::Path;
+
+use nalgebra::Vector3;
+use serde_json::{Map, Value};
+
+use crate::core::Engine;
+use crate::ui::ui::{
+    Anchor, CheckboxView, CollapsibleSection, ColorPickerView, DropdownView, GridContainer, Margin, Orientation, ScrollView, SimpleUIContainer,
+    SliderView, SplitPane, TabView, TextAlign, TextInputView, TextOverflow, TextView, TreeView, VerticalAlign, View, ViewContainer, ViewPosition,
+};
+
+//Note(teddy) The document is read into nodes before anything is built, a bad file is reported
+//before any view makes its gl buffers
+
+const ROOT_PATH: &str = "$";
+
+#[derive(Debug)]
+pub enum LoaderError {
+    Io(String),
+    Parse(String),
+    ///Path of the type field and the type that was asked for
+    UnknownNodeType { path: String, node_type: String },
+    MissingField { path: String },
+    InvalidField { path: String, expected: &'static str },
+}
+
+impl std::fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoaderError::Io(error) => write!(f, "{}", error),
+            LoaderError::Parse(error) => write!(f, "not valid json, {}", error),
+            LoaderError::UnknownNodeType { path, node_type } => write!(f, "{} has the unknown type {}", path, node_type),
+            LoaderError::MissingField { path } => write!(f, "{} is missing", path),
+            LoaderError::InvalidField { path, expected } => write!(f, "{} should be {}", path, expected),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Container { orientation: Orientation, spacing: i32 },
+    Scroll { max_height: i32, spacing: i32 },
+    Grid { columns: usize, spacing: i32 },
+    Section { title: String },
+    Text { text: String },
+    Checkbox { label: Option<String>, checked: bool },
+    Slider { width: i32, range: (f32, f32, f32), value: f32 },
+    Dropdown { options: Vec<String> },
+    Input { width: i32 },
+    ///Starts empty, the nodes are set from code
+    Tree,
+    ///Starts at the node's color, white without one
+    ColorPicker,
+    ///Exactly two children, the first goes on the left or on top
+    Split { orientation: Orientation, ratio: f32, min_sizes: (i32, i32) },
+    ///A tab for each child, titled by the child's id when there's no title for it
+    Tabs { titles: Vec<String> },
+}
+
+impl NodeKind {
+    fn is_container(&self) -> bool {
+        match self {
+            NodeKind::Container { .. }
+            | NodeKind::Scroll { .. }
+            | NodeKind::Grid { .. }
+            | NodeKind::Section { .. }
+            | NodeKind::Split { .. }
+            | NodeKind::Tabs { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+///One view of the document and its children, nothing is built until it's inflated
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiNode {
+    pub kind: NodeKind,
+    pub id: String,
+    pub position: ViewPosition,
+    pub padding: i32,
+    pub margin: Option<Margin>,
+    ///RGBA, a section's background goes to its header
+    pub background: Option<[f32; 4]>,
+    ///Color and width in pixels, like the background it goes to a section's header
+    pub border: Option<([f32; 3], i32)>,
+    pub corner_radius: Option<i32>,
+    //Note(teddy) The text style is used by text nodes and section headers
+    pub color: Option<[f32; 3]>,
+    pub font_size: Option<u32>,
+    pub align: Option<TextAlign>,
+    pub vertical_align: Option<VerticalAlign>,
+    pub max_width: Option<(i32, TextOverflow)>,
+    pub anchor: Option<Anchor>,
+    pub tooltip: Option<String>,
+    ///Used by containers and sections
+    pub clip_children: bool,
+    ///Rows of a container or scroll view can be picked with the arrow keys
+    pub selectable: bool,
+    pub children: Vec<UiNode>,
+}
+
+pub fn load_ui(path: &Path, engine: &Engine) -> Result<Box<dyn View>, LoaderError> {
+    let source = fs::read_to_string(path).map_err(|e| LoaderError::Io(format!("{}: {}", path.display(), e)))?;
+    Ok(inflate(&parse_ui(&source)?, engine))
+}
+
+pub fn parse_ui(source: &str) -> Result<UiNode, LoaderError> {
+    let document: Value = serde_json::from_str(source).map_err(|e| LoaderError::Parse(format!("{}", e)))?;
+    parse_node(&document, ROOT_PATH.to_string())
+}
+
+///The fields of one json object, errors name them by their path in the document
+struct Fields<'a> {
+    object: &'a Map<String, Value>,
+    path: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn path_of(&self, field: &str) -> String {
+        format!("{}.{}", self.path, field)
+    }
+
+    fn invalid(&self, field: &str, expected: &'static str) -> LoaderError {
+        LoaderError::InvalidField { path: self.path_of(field), expected }
+    }
+
+    fn required<T>(&self, field: &str, value: Option<T>) -> Result<T, LoaderError> {
+        value.ok_or(LoaderError::MissingField { path: self.path_of(field) })
+    }
+
+    ///None when the field is left out, an error when it's there with the wrong type
+    fn optional<T, F>(&self, field: &str, expected: &'static str, read: F) -> Result<Option<T>, LoaderError>
+    where
+        F: FnOnce(&'a Value) -> Option<T>,
+    {
+        match self.object.get(field) {
+            Some(value) => read(value).map(Some).ok_or_else(|| self.invalid(field, expected)),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, field: &str) -> Result<Option<String>, LoaderError> {
+        self.optional(field, "a string", |value| value.as_str().map(String::from))
+    }
+
+    fn int(&self, field: &str) -> Result<Option<i32>, LoaderError> {
+        self.optional(field, "an integer", |value| value.as_i64().map(|value| value as i32))
+    }
+
+    fn float(&self, field: &str) -> Result<Option<f32>, LoaderError> {
+        self.optional(field, "a number", |value| value.as_f64().map(|value| value as f32))
+    }
+
+    fn boolean(&self, field: &str) -> Result<Option<bool>, LoaderError> {
+        self.optional(field, "true or false", Value::as_bool)
+    }
+
+    fn floats<const N: usize>(&self, field: &str, expected: &'static str) -> Result<Option<[f32; N]>, LoaderError> {
+        self.optional(field, expected, |value| {
+            let items = value.as_array().filter(|items| items.len() == N)?;
+            let mut floats = [0.0; N];
+            for (float, item) in floats.iter_mut().zip(items) {
+                *float = item.as_f64()? as f32;
+            }
+            Some(floats)
+        })
+    }
+
+    ///Alpha is opaque when only [r, g, b] is given
+    fn rgba(&self, field: &str) -> Result<Option<[f32; 4]>, LoaderError> {
+        self.optional(field, "[r, g, b] or [r, g, b, a]", |value| {
+            let items = value.as_array().filter(|items| items.len() == 3 || items.len() == 4)?;
+            let mut rgba = [1.0; 4];
+            for (float, item) in rgba.iter_mut().zip(items) {
+                *float = item.as_f64()? as f32;
+            }
+            Some(rgba)
+        })
+    }
+
+    fn strings(&self, field: &str) -> Result<Option<Vec<String>>, LoaderError> {
+        self.optional(field, "an array of strings", |value| {
+            value.as_array()?.iter().map(|item| item.as_str().map(String::from)).collect()
+        })
+    }
+
+    fn named<T>(&self, field: &str, expected: &'static str, parse: fn(&str) -> Option<T>) -> Result<Option<T>, LoaderError> {
+        self.optional(field, expected, |value| value.as_str().and_then(parse))
+    }
+}
+
+fn parse_orientation(name: &str) -> Option<Orientation> {
+    match name {
+        "vertical" => Some(Orientation::Vertical),
+        "horizontal" => Some(Orientation::Horizontal),
+        _ => None,
+    }
+}
+
+fn parse_align(name: &str) -> Option<TextAlign> {
+    match name {
+        "left" => Some(TextAlign::Left),
+        "center" => Some(TextAlign::Center),
+        "right" => Some(TextAlign::Right),
+        _ => None,
+    }
+}
+
+fn parse_vertical_align(name: &str) -> Option<VerticalAlign> {
+    match name {
+        "top" => Some(VerticalAlign::Top),
+        "center" => Some(VerticalAlign::Center),
+        "bottom" => Some(VerticalAlign::Bottom),
+        _ => None,
+    }
+}
+
+fn parse_overflow(name: &str) -> Option<TextOverflow> {
+    match name {
+        "wrap" => Some(TextOverflow::Wrap),
+        "ellipsis" => Some(TextOverflow::Ellipsis),
+        _ => None,
+    }
+}
+
+fn parse_anchor(name: &str) -> Option<Anchor> {
+    match name {
+        "top_left" => Some(Anchor::TopLeft),
+        "top_right" => Some(Anchor::TopRight),
+        "bottom_left" => Some(Anchor::BottomLeft),
+        "bottom_right" => Some(Anchor::BottomRight),
+        "center" => Some(Anchor::Center),
+        "stretch_vertical" => Some(Anchor::StretchVertical),
+        "stretch_horizontal" => Some(Anchor::StretchHorizontal),
+        "fill" => Some(Anchor::Fill),
+        _ => None,
+    }
+}
+
+///One number for every side, or [top, right, bottom, left]
+fn parse_margin(value: &Value) -> Option<Margin> {
+    if let Some(margin) = value.as_i64() {
+        return Some(Margin::all(margin as i32));
+    }
+
+    let sides = value.as_array().filter(|sides| sides.len() == 4)?;
+    let side = |index: usize| sides[index].as_i64().map(|side| side as i32);
+    Some(Margin::new(side(0)?, side(1)?, side(2)?, side(3)?))
+}
+
+fn parse_node(value: &Value, path: String) -> Result<UiNode, LoaderError> {
+    let object = value.as_object().ok_or(LoaderError::InvalidField { path: path.clone(), expected: "an object" })?;
+    let fields = Fields { object, path: &path };
+
+    let node_type = fields.required("type", fields.string("type")?)?;
+    let spacing = fields.int("spacing")?.unwrap_or(0);
+
+    let kind = match node_type.as_str() {
+        "container" => NodeKind::Container {
+            orientation: fields.named("orientation", "vertical or horizontal", parse_orientation)?.unwrap_or(Orientation::Vertical),
+            spacing,
+        },
+        "scroll" => NodeKind::Scroll { max_height: fields.required("max_height", fields.int("max_height")?)?, spacing },
+        "grid" => {
+            let columns = fields.required("columns", fields.int("columns")?)?;
+            if columns < 1 {
+                return Err(fields.invalid("columns", "at least one column"));
+            }
+            NodeKind::Grid { columns: columns as usize, spacing }
+        }
+        "section" => NodeKind::Section { title: fields.required("title", fields.string("title")?)? },
+        "text" => NodeKind::Text { text: fields.required("text", fields.string("text")?)? },
+        "checkbox" => NodeKind::Checkbox { label: fields.string("label")?, checked: fields.boolean("checked")?.unwrap_or(false) },
+        "slider" => {
+            let [min, max, step] = fields.required("range", fields.floats("range", "[min, max, step]")?)?;
+            NodeKind::Slider {
+                width: fields.required("width", fields.int("width")?)?,
+                range: (min, max, step),
+                value: fields.float("value")?.unwrap_or(min),
+            }
+        }
+        "dropdown" => NodeKind::Dropdown { options: fields.strings("options")?.unwrap_or_default() },
+        "input" => NodeKind::Input { width: fields.required("width", fields.int("width")?)? },
+        "tree" => NodeKind::Tree,
+        "color_picker" => NodeKind::ColorPicker,
+        "split" => {
+            let [min_first, min_second] = fields.floats("min_sizes", "[first, second]")?.unwrap_or([0.0, 0.0]);
+            NodeKind::Split {
+                orientation: fields.named("orientation", "vertical or horizontal", parse_orientation)?.unwrap_or(Orientation::Horizontal),
+                ratio: fields.float("ratio")?.unwrap_or(0.5),
+                min_sizes: (min_first as i32, min_second as i32),
+            }
+        }
+        "tabs" => NodeKind::Tabs { titles: fields.strings("titles")?.unwrap_or_default() },
+        _ => return Err(LoaderError::UnknownNodeType { path: fields.path_of("type"), node_type }),
+    };
+
+    let mut children = vec![];
+    if let Some(value) = object.get("children") {
+        if !kind.is_container() {
+            return Err(fields.invalid("children", "no children, only containers and sections have them"));
+        }
+
+        let items = value.as_array().ok_or_else(|| fields.invalid("children", "an array of nodes"))?;
+        for (index, child) in items.iter().enumerate() {
+            children.push(parse_node(child, format!("{}.children[{}]", path, index))?);
+        }
+    }
+
+    if matches!(kind, NodeKind::Split { .. }) && children.len() != 2 {
+        return Err(fields.invalid("children", "two children, one for each side of the split"));
+    }
+
+    if let NodeKind::Tabs { titles } = &kind {
+        if titles.len() > children.len() {
+            return Err(fields.invalid("titles", "no more titles than there are children"));
+        }
+    }
+
+    let [x, y] = fields.floats("position", "[x, y]")?.unwrap_or([0.0, 0.0]);
+    let border = match fields.floats("border", "[r, g, b]")? {
+        Some(color) => Some((color, fields.int("border_width")?.unwrap_or(1))),
+        None => None,
+    };
+    let max_width = match fields.int("max_width")? {
+        Some(width) => Some((width, fields.named("overflow", "wrap or ellipsis", parse_overflow)?.unwrap_or(TextOverflow::Wrap))),
+        None => None,
+    };
+
+    Ok(UiNode {
+        kind,
+        id: fields.required("id", fields.string("id")?)?,
+        position: ViewPosition::new(x as i32, y as i32),
+        padding: fields.int("padding")?.unwrap_or(0),
+        margin: fields.optional("margin", "an integer or [top, right, bottom, left]", parse_margin)?,
+        background: fields.rgba("background")?,
+        border,
+        corner_radius: fields.int("corner_radius")?,
+        color: fields.floats("color", "[r, g, b]")?,
+        font_size: fields.int("font_size")?.map(|size| size.max(1) as u32),
+        align: fields.named("align", "left, center or right", parse_align)?,
+        vertical_align: fields.named("vertical_align", "top, center or bottom", parse_vertical_align)?,
+        max_width,
+        anchor: fields.named("anchor", "an anchor name", parse_anchor)?,
+        tooltip: fields.string("tooltip")?,
+        clip_children: fields.boolean("clip_children")?.unwrap_or(false),
+        selectable: fields.boolean("selectable")?.unwrap_or(false),
+        children,
+    })
+}
+
+fn style_text(text: &mut TextView, node: &UiNode, engine: &Engine) {
+    if node.font_size.is_some() {
+        text.set_font_size(node.font_size, engine);
+    }
+    if let Some(align) = node.align {
+        text.align = align;
+    }
+    if let Some(vertical_align) = node.vertical_align {
+        text.vertical_align = vertical_align;
+    }
+    if let Some((width, overflow)) = node.max_width {
+        text.set_max_width(Some(width), overflow);
+    }
+    if let Some([r, g, b]) = node.color {
+        text.color = Some(Vector3::new(r, g, b));
+    }
+}
+
+fn style_background(view: &mut dyn View, node: &UiNode) {
+    if node.background.is_some() {
+        view.set_background(node.background);
+    }
+    if let Some((color, width)) = node.border {
+        view.set_border(color, width);
+    }
+    if let Some(radius) = node.corner_radius {
+        view.set_corner_radius(radius);
+    }
+}
+
+fn with_children<C: ViewContainer + 'static>(mut container: C, node: &UiNode, engine: &Engine) -> Box<dyn View> {
+    for child in node.children.iter() {
+        container.add_child(inflate(child, engine));
+    }
+    Box::new(container)
+}
+
+///Builds the views for a node and everything under it
+pub fn inflate(node: &UiNode, engine: &Engine) -> Box<dyn View> {
+    let id = node.id.clone().into_boxed_str();
+
+    let mut view = match &node.kind {
+        NodeKind::Container { orientation, spacing } => {
+            let mut container = SimpleUIContainer::new(id, None, node.position, *orientation, node.padding, 1.0, node.clip_children);
+            container.set_spacing(*spacing);
+            container.set_selectable(node.selectable);
+            with_children(container, node, engine)
+        }
+
+        NodeKind::Scroll { max_height, spacing } => {
+            let mut scroll = ScrollView::new(id, *max_height, node.position, node.padding);
+            scroll.set_spacing(*spacing);
+            scroll.set_selectable(node.selectable);
+            with_children(scroll, node, engine)
+        }
+
+        NodeKind::Grid { columns, spacing } => {
+            let mut grid = GridContainer::new(id, *columns, node.position, node.padding);
+            grid.set_spacing(*spacing);
+            with_children(grid, node, engine)
+        }
+
+        NodeKind::Section { title } => {
+            let mut section = CollapsibleSection::new(id, title.clone(), node.position, node.padding, &engine.ui);
+            style_text(section.header_mut(), node, engine);
+            style_background(section.header_mut(), node);
+            section.set_clip_children(node.clip_children);
+            with_children(section, node, engine)
+        }
+
+        NodeKind::Text { text } => {
+            let mut view = TextView::new(id, text.clone(), node.position, 1.0, node.padding, &engine.ui);
+            style_text(&mut view, node, engine);
+            Box::new(view)
+        }
+
+        NodeKind::Checkbox { label, checked } => Box::new(CheckboxView::new(id, label.clone(), *checked, node.position, node.padding, &engine.ui)),
+        NodeKind::Slider { width, range, value } => Box::new(SliderView::new(id, *width, *range, *value, node.position, node.padding, &engine.ui)),
+        NodeKind::Dropdown { options } => Box::new(DropdownView::new(id, options.clone(), None, node.position, node.padding, &engine.ui)),
+        NodeKind::Input { width } => Box::new(TextInputView::new(id, *width, node.position, node.padding, &engine.ui)),
+        NodeKind::Tree => Box::new(TreeView::new(id, node.position, node.padding, &engine.ui)),
+        NodeKind::ColorPicker => Box::new(ColorPickerView::new(id, node.color.unwrap_or([1.0; 3]), node.position, node.padding, &engine.ui)),
+
+        NodeKind::Split { orientation, ratio, min_sizes: (min_first, min_second) } => {
+            let mut children = node.children.iter().map(|child| inflate(child, engine));
+            let (first, second) = (children.next().unwrap(), children.next().unwrap());
+
+            let mut split = SplitPane::new(id, *orientation, first, second, node.position);
+            split.set_ratio(*ratio);
+            split.set_min_sizes(*min_first, *min_second);
+            Box::new(split)
+        }
+
+        NodeKind::Tabs { titles } => {
+            let mut tabs = TabView::new(id, node.position, node.padding, &engine.ui);
+            for (index, child) in node.children.iter().enumerate() {
+                let title = titles.get(index).unwrap_or(&child.id).clone();
+                tabs.add_tab(title, inflate(child, engine));
+            }
+            Box::new(tabs)
+        }
+    };
+
+    if !matches!(node.kind, NodeKind::Section { .. }) {
+        style_background(view.as_mut(), node);
+    }
+
+    view.get_view_object_mut().anchor = node.anchor;
+    if let Some(margin) = node.margin {
+        view.set_margin(margin);
+    }
+    if let Some(tooltip) = &node.tooltip {
+        view.set_tooltip(tooltip.clone());
+    }
+
+    view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_a_tree() {
+        let root = parse_ui(
+            r#"{
+                "type": "container", "id": "panel", "padding": 10, "anchor": "stretch_vertical",
+                "children": [
+                    {"type": "section", "id": "assets", "title": "Objects", "font_size": 16, "background": [0.6, 0.2, 0.2, 0.8],
+                     "children": [{"type": "scroll", "id": "asset_list", "max_height": 400, "spacing": 4, "selectable": true}]},
+                    {"type": "text", "id": "save", "text": "Save world", "max_width": 360, "overflow": "ellipsis", "border": [1, 1, 1], "corner_radius": 4, "margin": 6, "vertical_align": "bottom"},
+                    {"type": "slider", "id": "ambient", "width": 200, "range": [0, 1, 0.05], "margin": [8, 0, 0, 2]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.kind, NodeKind::Container { orientation: Orientation::Vertical, spacing: 0 });
+        assert_eq!((root.padding, root.anchor), (10, Some(Anchor::StretchVertical)));
+        assert_eq!(root.children.len(), 3);
+
+        let assets = &root.children[0];
+        assert_eq!(assets.kind, NodeKind::Section { title: String::from("Objects") });
+        assert_eq!((assets.font_size, assets.background), (Some(16), Some([0.6, 0.2, 0.2, 0.8])));
+        assert_eq!(assets.children[0].kind, NodeKind::Scroll { max_height: 400, spacing: 4 });
+        assert!(assets.children[0].selectable);
+
+        assert_eq!(root.children[1].max_width, Some((360, TextOverflow::Ellipsis)));
+        assert_eq!((root.children[1].border, root.children[1].corner_radius), (Some(([1.0, 1.0, 1.0], 1)), Some(4)));
+        assert_eq!(root.children[2].kind, NodeKind::Slider { width: 200, range: (0.0, 1.0, 0.05), value: 0.0 });
+        assert_eq!((root.children[1].margin, root.children[2].margin), (Some(Margin::all(6)), Some(Margin::new(8, 0, 0, 2))));
+        assert_eq!(root.margin, None);
+        assert_eq!(root.children[1].vertical_align, Some(VerticalAlign::Bottom));
+    }
+
+    #[test]
+    fn test_errors_carry_the_json_path() {
+        let error = parse_ui(r#"{"type": "container", "id": "panel", "children": [{"type": "text", "id": "a", "text": "a"}, {"type": "button", "id": "b"}]}"#);
+        assert!(matches!(error, Err(LoaderError::UnknownNodeType { path, node_type }) if path == "$.children[1].type" && node_type == "button"));
+
+        let error = parse_ui(r#"{"type": "container", "id": "panel", "children": [{"type": "section", "id": "logs"}]}"#);
+        assert!(matches!(error, Err(LoaderError::MissingField { path }) if path == "$.children[0].title"));
+
+        let error = parse_ui(r#"{"type": "text", "id": "a", "text": "a", "color": [1, 0]}"#);
+        assert!(matches!(error, Err(LoaderError::InvalidField { path, .. }) if path == "$.color"));
+
+        let error = parse_ui(r#"{"type": "text", "id": "a", "text": "a", "children": []}"#);
+        assert!(matches!(error, Err(LoaderError::InvalidField { path, .. }) if path == "$.children"));
+
+        let error = parse_ui(r#"{"type": "split", "id": "split", "children": [{"type": "text", "id": "a", "text": "a"}]}"#);
+        assert!(matches!(error, Err(LoaderError::InvalidField { path, .. }) if path == "$.children"));
+
+        let error = parse_ui(r#"{"type": "tabs", "id": "tabs", "titles": ["A", "B"], "children": [{"type": "text", "id": "a", "text": "a"}]}"#);
+        assert!(matches!(error, Err(LoaderError::InvalidField { path, .. }) if path == "$.titles"));
+
+        assert!(matches!(parse_ui("{"), Err(LoaderError::Parse(_))));
+    }
+
+    #[test]
+    fn test_the_editor_ui_parses() {
+        let source = fs::read_to_string("./assets/ui/editor.json").unwrap();
+        let root = parse_ui(&source).unwrap();
+        assert!(matches!(root.kind, NodeKind::Split { orientation: Orientation::Horizontal, .. }));
+
+        //Note(teddy) The panel is on the left of the split, the scene is drawn on the right
+        let panel = &root.children[0];
+        let ids: Vec<&str> = panel.children.iter().map(|child| child.id.as_str()).collect();
+        assert!(["assets", "save", "wireframe", "console_input", "recovery", "levels", "ui_log"].iter().all(|id| ids.contains(id)));
+
+        let light_color = panel.children.iter().find(|child| child.id == "light_color").unwrap();
+        assert_eq!(light_color.kind, NodeKind::ColorPicker);
+    }
+}
@@ -1 +1,4 @@
+pub mod hot_reload;
+pub mod loader;
+pub mod tween;
 pub mod ui;
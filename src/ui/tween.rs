@@ -0,0 +1,161 @@
+use crate::ui::ui::{UiContext, View, ViewPosition};
+
+//Note(teddy) Tweens live on the UITree and find their view by id every frame, a view removed
+//while it's animated just ends the tween
+
+///What a tween animates, the values have to match it: colors for BackgroundColor, numbers for the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenProperty {
+    PositionX,
+    PositionY,
+    BackgroundColor,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenValue {
+    Number(f32),
+    Color([f32; 3]),
+}
+
+impl TweenValue {
+    fn lerp(&self, to: &TweenValue, t: f32) -> Option<TweenValue> {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+
+        match (self, to) {
+            (TweenValue::Number(a), TweenValue::Number(b)) => Some(TweenValue::Number(mix(*a, *b))),
+            (TweenValue::Color(a), TweenValue::Color(b)) => {
+                Some(TweenValue::Color([mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    ///Starts fast and settles, the default for things coming into view
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    ///Maps the linear progress t in [0, 1] to the eased one
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+pub struct Tween {
+    pub target_view_id: Box<str>,
+    pub property: TweenProperty,
+    pub from: TweenValue,
+    pub to: TweenValue,
+    ///Seconds
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+    ///Runs in the dispatch phase after the tween's last frame
+    pub on_complete: Option<Box<dyn FnOnce(&mut UiContext)>>,
+}
+
+impl Tween {
+    pub fn new(target_view_id: &str, property: TweenProperty, from: TweenValue, to: TweenValue, duration: f32) -> Self {
+        Self {
+            target_view_id: Box::from(target_view_id),
+            property,
+            from,
+            to,
+            duration,
+            easing: Easing::EaseOut,
+            elapsed: 0.0,
+            on_complete: None,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn on_complete<F: FnOnce(&mut UiContext) + 'static>(mut self, callback: F) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    ///Moves the tween on by dt seconds and returns the value for this frame
+    pub fn advance(&mut self, dt: f32) -> Option<TweenValue> {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+
+        self.from.lerp(&self.to, self.easing.apply(t))
+    }
+}
+
+///Sets the animated property on the view
+pub fn apply_tween_value(view: &mut dyn View, property: TweenProperty, value: TweenValue) {
+    let position = view.get_view_object().position;
+
+    match (property, value) {
+        (TweenProperty::PositionX, TweenValue::Number(x)) => view.set_position(ViewPosition::new(x.round() as i32, position.y)),
+        (TweenProperty::PositionY, TweenValue::Number(y)) => view.set_position(ViewPosition::new(position.x, y.round() as i32)),
+        (TweenProperty::BackgroundColor, TweenValue::Color(color)) => view.set_background_color(color),
+        (TweenProperty::Scale, TweenValue::Number(scale)) => view.set_scale(scale),
+        (property, value) => println!("A {:?} tween can't take {:?}", property, value),
+    }
+}
+
+///Moves the view along x from from_x to its place at to_x
+pub fn slide_in(view_id: &str, from_x: i32, to_x: i32, duration: f32) -> Tween {
+    Tween::new(view_id, TweenProperty::PositionX, TweenValue::Number(from_x as f32), TweenValue::Number(to_x as f32), duration)
+}
+
+///Blends the view's background from one color to another
+pub fn fade(view_id: &str, from: [f32; 3], to: [f32; 3], duration: f32) -> Tween {
+    Tween::new(view_id, TweenProperty::BackgroundColor, TweenValue::Color(from), TweenValue::Color(to), duration)
+        .with_easing(Easing::Linear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_ends_where_it_should() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut].iter() {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn test_tween_advances_to_its_end() {
+        let mut tween = slide_in("panel", -300, 0, 1.0).with_easing(Easing::Linear);
+
+        assert_eq!(tween.advance(0.25), Some(TweenValue::Number(-225.0)));
+        assert!(!tween.is_finished());
+        assert_eq!(tween.advance(2.0), Some(TweenValue::Number(0.0)));
+        assert!(tween.is_finished());
+
+        let mut mismatched = Tween::new("panel", TweenProperty::Scale, TweenValue::Number(1.0), TweenValue::Color([0.0; 3]), 1.0);
+        assert_eq!(mismatched.advance(0.5), None);
+    }
+}
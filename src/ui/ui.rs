@@ -1,21 +1,24 @@
 use std::any::Any;
+use std::cell::RefCell;
 use std::ffi::c_void;
 use std::ptr::null;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use glfw::MouseButton;
+use glfw::{Action, Key, MouseButton};
 use nalgebra::Vector3;
 use nphysics3d::utils::UserData;
 
-use crate::core::{Engine, FontFace, FrameRenderObject};
+use crate::core::{Engine, Event, EventManager, EventType, FontFace, FrameRenderObject, UiEvent};
 use crate::game_world::world::AssetSource;
 use crate::game_world::world::World;
-use crate::renderer::draw::{draw_quad_with_default_shader, draw_text};
-use crate::utils::{get_at_index, Cords};
-
-static mut SHADER_TEXT_ID: u32 = 0;
-pub static mut UI_QUAD_SHADER_ID: u32 = 0;
-static mut ENGINE_PTR: *const Engine = null();
+use crate::ui::tween::{apply_tween_value, Tween};
+use crate::renderer::draw::{
+    draw_quad_with_default_shader, draw_styled_quad, draw_text, draw_text_vertices, draw_textured_quad, draw_translucent_quad, glyph_quads,
+    upload_text_vertices, QuadStyle,
+};
+use crate::renderer::shaders::ShaderProgram;
+use crate::utils::{get_at_index, Cords, ViewportRect};
 
 macro_rules! font_shader {
     () => {
@@ -29,7 +32,42 @@ macro_rules! quad_shader {
     };
 }
 
-#[derive(Copy, Clone, Debug)]
+macro_rules! image_shader {
+    () => {
+        String::from("ui_image")
+    };
+}
+
+///The font views are measured with and the shaders they are drawn with. The engine keeps one,
+///init_ui fills in the shaders once they are compiled, until then they are 0.
+#[derive(Clone)]
+pub struct UiResources {
+    pub font_face: Rc<FontFace>,
+    pub text_shader: Rc<ShaderProgram>,
+    pub quad_shader: Rc<ShaderProgram>,
+    pub image_shader: Rc<ShaderProgram>,
+}
+
+impl UiResources {
+    pub fn new(font_face: Rc<FontFace>) -> Self {
+        let unloaded = || Rc::new(ShaderProgram::new(0));
+        Self { font_face, text_shader: unloaded(), quad_shader: unloaded(), image_shader: unloaded() }
+    }
+
+    ///Points the field for the named ui shader at the program
+    pub fn set_shader(&mut self, name: &str, program: u32) {
+        let program = Rc::new(ShaderProgram::new(program));
+        if name == font_shader!() {
+            self.text_shader = program;
+        } else if name == quad_shader!() {
+            self.quad_shader = program;
+        } else if name == image_shader!() {
+            self.image_shader = program;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Dimensions<T> {
     pub x: T,
     pub y: T,
@@ -53,23 +91,31 @@ pub type ViewPosition = Dimensions<i32>;
 pub trait View {
     fn get_id(&self) -> &str;
     fn update(&mut self, engine: &Engine) -> UIResult;
-    fn compute_intersect_with_cursor_cords(&mut self, engine: &Engine, cords: &Cords<f32>) {
-        let id = String::from(self.get_id());
-        let view_object = self.get_view_object_mut();
-        if does_cursor_intersect(
-            cords,
-            view_object.position,
-            view_object.size.unwrap_or(ViewDimens::zerod()),
-            view_object.padding,
-        ) {
-            view_object.cursor_hover_state = CursorState::Hover;
-        } else {
-            if view_object.cursor_hover_state != CursorState::Neither {
-                view_object.cursor_hover_state = CursorState::Leave;
-            }
+    fn compute_intersect_with_cursor_cords(&mut self, _engine: &Engine, cords: &Cords<f32>) {
+        self.update_hover(cords);
+    }
+
+    ///Tracks the cursor against the view's rect and fires the enter and exit hooks on the transitions
+    fn update_hover(&mut self, cords: &Cords<f32>) {
+        let inside = self.bounds().contains(cords);
+
+        match self.get_view_object_mut().set_hovered(inside) {
+            Some(CursorEvent::Enter) => self.on_cursor_enter(),
+            Some(CursorEvent::Exit) => self.on_cursor_exit(),
+            None => (),
         }
     }
 
+    ///Called once when the cursor moves onto the view
+    fn on_cursor_enter(&mut self) {}
+
+    ///Called once when the cursor moves off the view
+    fn on_cursor_exit(&mut self) {}
+
+    fn is_hovered(&self) -> bool {
+        self.get_view_object().cursor_hover_state == CursorState::Hover
+    }
+
     fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
         self.compute_intersect_with_cursor_cords(&engine, &cords);
     }
@@ -77,21 +123,187 @@ pub trait View {
     fn get_view_object(&self) -> &ViewObject;
     fn get_view_object_mut(&mut self) -> &mut ViewObject;
 
-    fn handle_button_click(
-        &mut self,
-        engine: &Engine,
-        clicked_buttons: &Vec<MouseButton>,
-        cords: Cords<f32>,
-    ) -> bool;
+    ///Returns true only when the click landed on this view, containers return true when one of their children took it
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool;
 
+    ///Space the parent container gives the view, padding included. Containers call it every frame before update.
     fn update_dimensions(&mut self, _dimensions: ViewDimens) {}
+
+    ///Space the parent container keeps around the view, it doesn't grow the view's hit box
+    fn set_margin(&mut self, margin: Margin) {
+        self.get_view_object_mut().margin = margin;
+    }
+
+    fn get_margin(&self) -> Margin {
+        self.get_view_object().margin
+    }
+
+    fn is_visible(&self) -> bool {
+        self.get_view_object().visible
+    }
+
+    ///Containers notice the change on their next update and stack their children again
+    fn set_visible(&mut self, visible: bool) {
+        self.get_view_object_mut().visible = visible;
+    }
+
+    fn set_background_color(&mut self, color: [f32; 3]) {
+        self.get_view_object_mut().set_background_color(color);
+    }
+
+    ///RGBA, None leaves the view transparent
+    fn set_background(&mut self, background: Option<[f32; 4]>) {
+        self.get_view_object_mut().background = background;
+    }
+
+    ///Only text is drawn scaled, the layout keeps the unscaled size
+    fn set_scale(&mut self, scale: f32) {
+        self.get_view_object_mut().scale = scale;
+    }
+
+    ///Drawn inside the background's edges, a width of 0 removes it
+    fn set_border(&mut self, color: [f32; 3], width: i32) {
+        self.get_view_object_mut().style.border = if width > 0 { Some((color, width as f32)) } else { None };
+    }
+
+    ///Keeps the width, so hover states can recolor the border and leave the fill alone
+    fn set_border_color(&mut self, color: [f32; 3]) {
+        if let Some((border_color, _)) = &mut self.get_view_object_mut().style.border {
+            *border_color = color;
+        }
+    }
+
+    fn set_corner_radius(&mut self, radius: i32) {
+        self.get_view_object_mut().style.corner_radius = radius.max(0) as f32;
+    }
+
+    ///Text shown near the cursor after hovering the view for a moment
+    fn set_tooltip(&mut self, tooltip: String) {
+        self.get_view_object_mut().tooltip = Some(tooltip);
+    }
+
+    ///Dragging the view carries the payload, dropped over the scene it becomes a UiEvent::DroppedOnWorld
+    fn set_draggable(&mut self, payload: String) {
+        self.get_view_object_mut().drag_payload = Some(payload);
+    }
+
+    ///The innermost view under the cursor whose ViewObject passes accept, containers look through their children first
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        own_object(self, cords, accept)
+    }
+
+    ///Id and tooltip of the innermost view under the cursor that has one
+    fn tooltip_at(&self, cords: &Cords<f32>) -> Option<(&str, &str)> {
+        let object = self.object_at(cords, &|object| object.tooltip.is_some())?;
+        Some((&object.id, object.tooltip.as_deref()?))
+    }
+
+    ///Calls the callbacks for what happened to the view since the last dispatch, containers pass it down.
+    ///Input only records events, the callbacks run here once the frame is drawn.
+    fn dispatch(&mut self, _ctx: &mut UiContext) {}
+
+    ///Modals return true once they are done, the tree then pops them
+    fn is_closed(&self) -> bool {
+        false
+    }
+
+    ///Resolves the view's anchor against the rect of its parent, containers then lay out their children.
+    ///Runs when the window is resized, not every frame.
+    fn layout(&mut self, parent: ViewRect) {
+        apply_anchor(self, parent);
+    }
+
+    ///Screen space rect the view is drawn in and hit tested against
+    fn bounds(&self) -> ViewRect {
+        let view = self.get_view_object();
+        ViewRect::new(view.position, view.size.unwrap_or(ViewDimens::zerod()), view.padding)
+    }
     fn get_view_dimensions(&self) -> Option<ViewDimens> {
         None
     }
+
+    ///Containers size themselves to their children, deepest first. The tree runs it every frame before drawing
+    ///so a child that grew resizes every container above it in the same frame.
+    fn update_layout(&mut self) {}
     fn set_position(&mut self, _position: ViewPosition);
     fn get_position(&self) -> Option<ViewPosition>;
     fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>>;
     fn as_any(&mut self) -> Box<&mut dyn Any>;
+
+    ///Composed text from the window's char callback, returns true when a focused view consumed it
+    fn receive_char(&mut self, _engine: &Engine, _c: char) -> bool {
+        false
+    }
+
+    ///Key presses while a view has keyboard focus, returns true when the key should not reach shortcuts
+    fn receive_key(&mut self, _engine: &Engine, _key: Key) -> bool {
+        false
+    }
+
+    ///Enter on a selected list row, views with an on_click fire it in the next dispatch as if clicked
+    fn activate(&mut self) -> bool {
+        false
+    }
+
+    ///True while a drag started on this view is still held, containers then keep sending it
+    ///clicks and cursor positions even when the cursor leaves their bounds
+    fn captures_cursor(&self) -> bool {
+        false
+    }
+
+    ///Containers return themselves so lookups can recurse into them
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        None
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        None
+    }
+
+    ///Drawn after the whole tree over a cleared depth buffer, for views like an open dropdown
+    ///that have to sit above their later siblings
+    fn draw_popups(&mut self, _engine: &Engine) -> UIResult {
+        Ok(())
+    }
+
+    ///Mouse wheel, returns true when a view scrolled so nothing else reacts to the wheel
+    fn receive_scroll(&mut self, _engine: &Engine, _cords: Cords<f32>, _delta: f32) -> bool {
+        false
+    }
+
+    ///Views that take keyboard focus when clicked or reached with Tab
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    ///Called by the UITree when focus moves to or away from this view
+    fn set_focused(&mut self, _focused: bool) {}
+
+    ///Ids of the focusable views in tab order, containers add their children's
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        if self.focusable() {
+            ids.push(Box::from(self.get_id()));
+        }
+    }
+
+    ///Id of the focusable view under the cursor
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        if self.focusable() && self.bounds().contains(cords) {
+            Some(Box::from(self.get_id()))
+        } else {
+            None
+        }
+    }
+
+    ///Cursor shown while the view is hovered, None leaves it to the view under it
+    fn cursor_icon(&self) -> Option<CursorIcon> {
+        None
+    }
+
+    ///Icon of the innermost view under the cursor that has one, containers look through their children first
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        self.cursor_icon().filter(|_| self.bounds().contains(cords))
+    }
 }
 ///Note(teddy) Container specific methods.
 ///Container is also a view so each container
@@ -102,6 +314,61 @@ pub trait ViewContainer: View {
 
     ///Note(teddy) Iterate throught the entire container children to find the view id
     fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError>;
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError>;
+
+    ///Removes a child, or a nested container's child, with the id of the container that held it and its index there
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError>;
+    ///Puts a child back at index, past the last child it goes at the end
+    fn insert_child(&mut self, index: usize, child: Box<dyn View>);
+}
+
+///Direct children first, then the children of nested containers
+fn find_child<'a>(children: &'a [Box<dyn View>], child_id: &str) -> Result<&'a Box<dyn View>, UIError> {
+    if let Some(child) = children.iter().find(|child| child.get_id() == child_id) {
+        return Ok(child);
+    }
+
+    children
+        .iter()
+        .filter_map(|child| child.as_container())
+        .find_map(|container| container.get_view_by_id(child_id).ok())
+        .ok_or(UIError::ViewNotFound)
+}
+
+fn find_child_mut<'a>(children: &'a mut [Box<dyn View>], child_id: &str) -> Result<&'a mut Box<dyn View>, UIError> {
+    //Note(teddy) Find the owning child with shared borrows first, the mutable walk then never has to back out
+    let index = children
+        .iter()
+        .position(|child| child.get_id() == child_id)
+        .or_else(|| {
+            children.iter().position(|child| {
+                child.as_container().map_or(false, |container| container.get_view_by_id(child_id).is_ok())
+            })
+        })
+        .ok_or(UIError::ViewNotFound)?;
+
+    let child = &mut children[index];
+    if child.get_id() == child_id {
+        return Ok(child);
+    }
+
+    child.as_container_mut().ok_or(UIError::ViewNotFound)?.get_view_by_id_mut(child_id)
+}
+
+fn take_from_children(
+    owner: &str,
+    children: &mut Vec<Box<dyn View>>,
+    child_id: &str,
+) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+    if let Some(index) = children.iter().position(|child| child.get_id() == child_id) {
+        return Ok((Box::from(owner), index, children.remove(index)));
+    }
+
+    children
+        .iter_mut()
+        .filter_map(|child| child.as_container_mut())
+        .find_map(|container| container.take_child(child_id).ok())
+        .ok_or(UIError::ViewNotFound)
 }
 
 #[inline(always)]
@@ -113,722 +380,7442 @@ pub fn cast_view<'a, 'b, T: View + 'b + 'static>(
 }
 
 pub struct UITree {
-    ///Keystrokes will be sent this view
-    pub focused_view: Option<Box<dyn View>>,
+    ///Id of the view keystrokes are sent to
+    pub focused_view: Option<Box<str>>,
     pub root: Option<Box<dyn View>>,
+    pub overlays: Vec<Overlay>,
+    ///Dialogs drawn centered over everything else, the last one gets all the input
+    modals: Vec<Box<dyn View>>,
+    ///The rect the last layout ran against, modals are centered in it
+    viewport: ViewRect,
+    ///Buffers of the quad that dims everything under the modal, made on the first modal drawn
+    backdrop_buffers: Option<(i32, i32)>,
+    tooltip: TooltipState,
+    ///Made the first time a tooltip shows
+    tooltip_view: Option<TextView>,
+    ///Changes queued while the tree is being walked, flush_commands applies them
+    commands: Vec<UiCommand>,
+    last_press: Option<LastPress>,
+    context_menu: Option<ContextMenu>,
+    ///The press that closed a context menu, its release doesn't reach the scene either
+    swallowed_release: Option<MouseButton>,
+    drag: Option<DragState>,
+    ///Target ids and payloads of the drops onto views, their on_drop runs in dispatch
+    drops: Vec<(Box<str>, String)>,
+    ///Made the first time a drag starts
+    drag_ghost: Option<TextView>,
+    tweens: Vec<Tween>,
+    ///Callbacks of the tweens that finished, they run in the next dispatch
+    finished_tweens: Vec<Box<dyn FnOnce(&mut UiContext)>>,
 }
 
-impl UITree {
-    pub fn new() -> Self {
-        UITree {
-            root: None,
-            focused_view: None,
+///Seconds the cursor has to stay on a view before its tooltip shows
+const TOOLTIP_DELAY: f32 = 0.5;
+///How far down and right of the cursor the tooltip is drawn
+const TOOLTIP_CURSOR_OFFSET: i32 = 16;
+const TOOLTIP_MAX_WIDTH: i32 = 480;
+const TOOLTIP_COLOR: [f32; 3] = [0.05, 0.05, 0.05];
+
+///Which view's tooltip the cursor is on and for how long
+#[derive(Debug)]
+struct TooltipState {
+    view: Option<Box<str>>,
+    text: String,
+    cursor: Cords<f32>,
+    hovered_for: f32,
+    ///A click hides the tooltip until the cursor moves onto another view
+    dismissed: bool,
+}
+
+impl TooltipState {
+    fn new() -> Self {
+        Self { view: None, text: String::new(), cursor: OUTSIDE_CORDS, hovered_for: 0.0, dismissed: false }
+    }
+
+    ///The view with a tooltip under the cursor, moving onto another view starts the delay over
+    fn hover(&mut self, hovered: Option<(&str, &str)>, cursor: Cords<f32>) {
+        self.cursor = cursor;
+        if self.view.as_deref() == hovered.map(|(id, _)| id) {
+            return;
         }
+
+        self.view = hovered.map(|(id, _)| Box::from(id));
+        self.text = hovered.map_or(String::new(), |(_, text)| text.to_string());
+        self.hovered_for = 0.0;
+        self.dismissed = false;
     }
 
-    pub fn find_element(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
-        if let Some(root) = &mut self.root {
-            return root.get_element_by_id(id);
+    fn tick(&mut self, dt: f32) {
+        if self.view.is_some() {
+            self.hovered_for += dt;
         }
+    }
 
-        None
+    fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    fn visible(&self) -> Option<&str> {
+        match self.view {
+            Some(_) if !self.dismissed && self.hovered_for >= TOOLTIP_DELAY => Some(&self.text),
+            _ => None,
+        }
+    }
+}
+
+///Longest gap between two presses of a double click
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(300);
+///How far apart the presses of a double click can be
+const DOUBLE_CLICK_DISTANCE: f32 = 4.0;
+
+///A mouse button going down or up over the ui
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClickInfo {
+    pub button: MouseButton,
+    ///Presses in a row on the same view, 2 for a double click. 0 when the button went up.
+    pub count: u32,
+    pub cords: Cords<f32>,
+}
+
+impl ClickInfo {
+    pub fn press(button: MouseButton, count: u32, cords: Cords<f32>) -> Self {
+        Self { button, count: count.max(1), cords }
+    }
+
+    pub fn release(button: MouseButton, cords: Cords<f32>) -> Self {
+        Self { button, count: 0, cords }
+    }
+
+    pub fn is_release(&self) -> bool {
+        self.count == 0
+    }
+
+    ///True when this is the button going down
+    pub fn pressed(&self, button: MouseButton) -> bool {
+        self.button == button && !self.is_release()
     }
 }
 
+///The last press, the next one counts on from it when it's quick, close and on the same view
 #[derive(Debug)]
-pub enum UIError {
-    UnableToInitializeFramebuffer,
-    ViewNotFound,
+struct LastPress {
+    button: MouseButton,
+    view: Option<Box<str>>,
+    cords: Cords<f32>,
+    at: Instant,
+    count: u32,
 }
 
-#[derive(PartialEq)]
-enum CursorState {
-    Hover,
-    Leave,
-    Neither,
+impl LastPress {
+    fn continued_by(&self, button: MouseButton, view: &Option<Box<str>>, cords: Cords<f32>, at: Instant) -> bool {
+        let (dx, dy) = (cords.x - self.cords.x, cords.y - self.cords.y);
+
+        self.button == button
+            && view.is_some()
+            && self.view == *view
+            && at.saturating_duration_since(self.at) <= DOUBLE_CLICK_TIME
+            && dx * dx + dy * dy <= DOUBLE_CLICK_DISTANCE * DOUBLE_CLICK_DISTANCE
+    }
 }
 
-pub struct ViewObject {
-    cursor_hover_state: CursorState,
-    pub id: Box<str>,
-    pub background_vao: i32,
-    pub background_vbo: i32,
-    pub size: Option<ViewDimens>,
-    pub background_color: Box<[f32; 3]>,
-    pub padding: i32,
-    pub scale: f32,
-    pub position: ViewDimens,
-    pub z_index: Option<u32>,
+///How far the cursor moves with Button1 down on a draggable view before it becomes a drag
+const DRAG_THRESHOLD: f32 = 6.0;
+const DRAG_GHOST_COLOR: [f32; 3] = [0.15, 0.15, 0.15];
+const DRAG_GHOST_BORDER: [f32; 3] = [0.0, 0.7, 0.0];
+
+///Button1 went down on a draggable view, the press only turns into a drag once the cursor moves away
+#[derive(Debug)]
+struct DragState {
+    payload: String,
+    ///The press that started it, it goes to the view if no drag starts
+    press: ClickInfo,
+    cursor: Cords<f32>,
+    started: bool,
 }
 
-impl ViewObject {
-    fn new(
-        id: Box<str>,
-        position: ViewDimens,
-        size: Option<ViewDimens>,
-        padding: i32,
-        scale: f32,
-        background_color: Box<[f32; 3]>,
-        z_index: Option<u32>,
-    ) -> Self {
-        unsafe {
-            let (background_vao, background_vbo) = initialize_background_buffers();
+impl DragState {
+    fn new(payload: String, press: ClickInfo) -> Self {
+        Self { payload, press, cursor: press.cords, started: false }
+    }
 
-            Self {
-                id,
-                size,
-                padding,
-                scale,
-                position,
-                background_vao,
-                background_vbo,
-                background_color,
-                cursor_hover_state: CursorState::Neither,
-                z_index,
-            }
-        }
+    fn move_to(&mut self, cursor: Cords<f32>) {
+        self.cursor = cursor;
+        let (dx, dy) = (cursor.x - self.press.cords.x, cursor.y - self.press.cords.y);
+        self.started |= dx * dx + dy * dy >= DRAG_THRESHOLD * DRAG_THRESHOLD;
     }
 }
 
-pub struct TextView {
-    text_vao: i32,
-    text_vbo: i32,
-    text_shader_id: u32,
-    text_length: u32,
-    text_height: u32,
-    cursor_hover: CursorState,
-    view: ViewObject,
+///Pushes a rect of that size back inside the viewport when it would go past an edge,
+///too big to fit it keeps to the top left
+fn clamp_to_viewport(position: ViewPosition, size: ViewDimens, viewport: ViewRect) -> ViewPosition {
+    let clamp = |start: i32, extent: i32, min: f32, max: f32| start.min(max as i32 - extent).max(min as i32);
 
-    text: String,
-    pub color: Option<Vector3<f32>>,
-    //Note(teddy) Incase the size is not passed, use the fonts width and heights and update this value
-    pub on_hover: Option<Box<dyn FnMut(*mut Self)>>,
-    pub on_mouse_leave: Option<Box<dyn FnMut(*mut Self)>>,
-    pub on_click: Option<Box<dyn Fn(*mut Self)>>,
-    pub on_right_click: Option<Box<dyn Fn(*mut Self)>>,
-    pub on_middle_click: Option<Box<dyn Fn(*mut Self)>>,
+    ViewPosition::new(
+        clamp(position.x, size.x, viewport.min.x, viewport.max.x),
+        clamp(position.y, size.y, viewport.min.y, viewport.max.y),
+    )
 }
 
-pub type UIResult = Result<(), UIError>;
+///Below and right of the cursor, kept inside the viewport
+fn tooltip_position(cursor: Cords<f32>, size: ViewDimens, viewport: ViewRect) -> ViewPosition {
+    let position = ViewPosition::new(cursor.x as i32 + TOOLTIP_CURSOR_OFFSET, cursor.y as i32 + TOOLTIP_CURSOR_OFFSET);
+    clamp_to_viewport(position, size, viewport)
+}
 
-#[inline]
-unsafe fn initialize_background_buffers() -> (i32, i32) {
-    let mut vao: u32 = 0;
-    let mut vbo: u32 = 0;
+///Change to the tree that can't be made while it's being walked, callbacks push them through the UiContext
+pub enum UiCommand {
+    AddChild { parent_id: Box<str>, view: Box<dyn View> },
+    RemoveView { id: Box<str> },
+    ///Only text and text input views take it
+    SetText { id: Box<str>, text: String },
+    ///Toggles an overlay or the view's visible flag
+    SetVisible { id: Box<str>, visible: bool },
+}
 
-    gl::GenVertexArrays(1, &mut vao);
-    gl::GenBuffers(1, &mut vbo);
+const MODAL_BACKDROP_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+const MODAL_BACKDROP_ALPHA: f32 = 0.5;
 
-    gl::BindVertexArray(vao);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        (6 * 3 * std::mem::size_of::<f32>()) as isize,
-        null(),
-        gl::DYNAMIC_DRAW,
-    );
+///Runs once when its context menu row is clicked
+pub type MenuAction = Box<dyn FnOnce(&mut UiContext)>;
 
-    gl::EnableVertexAttribArray(0);
-    gl::VertexAttribPointer(
-        0,
-        3, //Using vec3 when drawing quads inside the shader
-        gl::FLOAT,
-        gl::FALSE,
-        (3 * std::mem::size_of::<f32>()) as i32,
-        0 as *const c_void,
-    );
+const CONTEXT_MENU_ID: &'static str = "context_menu";
+const CONTEXT_MENU_COLOR: [f32; 3] = [0.12, 0.12, 0.12];
+const CONTEXT_MENU_HOVER_COLOR: [f32; 3] = [0.25, 0.35, 0.25];
+const CONTEXT_MENU_ROW_PADDING: i32 = 6;
 
-    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-    gl::BindVertexArray(0);
+///Rows of actions opened at the cursor and drawn over everything, the next press anywhere closes it
+struct ContextMenu {
+    view: Box<dyn View>,
+    ///Set by the press that closed it, the menu goes once that press is dispatched
+    closed: bool,
+}
 
-    (vao as i32, vbo as i32)
+///View drawn over the scene at its own position, it takes no part in the layout or in input
+pub struct Overlay {
+    pub visible: bool,
+    pub view: Box<dyn View>,
 }
 
-impl TextView {
-    pub fn new(
-        id: Box<str>,
-        text: String,
-        position: ViewPosition,
-        scale: f32,
-        padding: i32,
-    ) -> Self {
-        let mut vbo: u32 = 0;
-        let mut vao: u32 = 0;
+impl UITree {
+    pub fn new() -> Self {
+        UITree {
+            root: None,
+            focused_view: None,
+            overlays: vec![],
+            modals: vec![],
+            viewport: ViewRect::new(ViewPosition::zerod(), ViewDimens::zerod(), 0),
+            backdrop_buffers: None,
+            tooltip: TooltipState::new(),
+            tooltip_view: None,
+            commands: vec![],
+            last_press: None,
+            context_menu: None,
+            swallowed_release: None,
+            drag: None,
+            drops: vec![],
+            drag_ghost: None,
+            tweens: vec![],
+            finished_tweens: vec![],
+        }
+    }
 
-        let engine = unsafe { ENGINE_PTR.as_ref().unwrap() };
-        let length_of_text = get_the_length_of_text(&text, &engine.font_face);
+    ///Per frame bookkeeping, dt is in seconds
+    pub fn update(&mut self, dt: f32) {
+        self.tooltip.tick(dt);
+        self.advance_tweens(dt);
+    }
 
-        let size = Some(Dimensions::new(
-            length_of_text as i32,
-            engine.font_face.font_size as i32,
-        ));
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::GenBuffers(1, &mut vbo);
-
-            gl::BindVertexArray(vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (std::mem::size_of::<f32>() * 6 * 4) as isize,
-                null(),
-                gl::DYNAMIC_DRAW,
-            );
+    ///Starts the tween, one already running on the same view and property is replaced.
+    ///The view takes the from value right away so it isn't drawn where it ends up first.
+    pub fn animate(&mut self, tween: Tween) {
+        self.tweens.retain(|running| running.target_view_id != tween.target_view_id || running.property != tween.property);
+        if let Ok(view) = self.find_view_mut(&tween.target_view_id) {
+            apply_tween_value(view.as_mut(), tween.property, tween.from);
+        }
 
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(
-                0,
-                4,
-                gl::FLOAT,
-                gl::FALSE,
-                (4 * std::mem::size_of::<f32>()) as i32,
-                0 as *const c_void,
-            );
+        self.tweens.push(tween);
+    }
 
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
+    pub fn is_animating(&self, id: &str) -> bool {
+        self.tweens.iter().any(|tween| &*tween.target_view_id == id)
+    }
 
-            let (background_vao, background_vbo) = initialize_background_buffers();
+    fn advance_tweens(&mut self, dt: f32) {
+        for mut tween in std::mem::take(&mut self.tweens) {
+            let value = tween.advance(dt);
+            let applied = match (value, self.find_view_mut(&tween.target_view_id)) {
+                (Some(value), Ok(view)) => {
+                    apply_tween_value(view.as_mut(), tween.property, value);
+                    true
+                }
+                (None, _) => {
+                    println!("The {:?} tween of {} mixes two kinds of values", tween.property, tween.target_view_id);
+                    false
+                }
+                (_, Err(_)) => false,
+            };
 
-            Self {
-                view: ViewObject::new(
+            match (applied && !tween.is_finished(), tween.on_complete.take()) {
+                (true, on_complete) => {
+                    tween.on_complete = on_complete;
+                    self.tweens.push(tween);
+                }
+                (false, Some(on_complete)) => self.finished_tweens.push(on_complete),
+                (false, None) => (),
+            }
+        }
+    }
+
+    pub fn push_command(&mut self, command: UiCommand) {
+        self.commands.push(command);
+    }
+
+    ///Applies the queued commands in the order they were pushed, run once the frame's update is done
+    pub fn flush_commands(&mut self) {
+        let mut moved = false;
+
+        for command in std::mem::take(&mut self.commands) {
+            moved |= !matches!(command, UiCommand::SetText { .. });
+            if let Err(error) = self.apply_command(command) {
+                println!("A ui command failed: {:?}", error);
+            }
+        }
+
+        //Note(teddy) Adding or removing a view moves its siblings, the anchored ones are placed again
+        if moved {
+            self.layout(self.viewport);
+        }
+    }
+
+    fn apply_command(&mut self, command: UiCommand) -> UIResult {
+        match command {
+            UiCommand::AddChild { parent_id, view } => {
+                self.find_view_mut(&parent_id)?.as_container_mut().ok_or(UIError::NotAContainer)?.add_child(view);
+            }
+
+            UiCommand::RemoveView { id } => {
+                if self.focused_view.as_deref() == Some(&*id) {
+                    self.focus(None);
+                }
+
+                self.take_view(&id)?;
+            }
+
+            UiCommand::SetText { id, text } => {
+                let any = self.find_view_mut(&id)?.as_any();
+                if let Some(text_view) = any.downcast_mut::<TextView>() {
+                    text_view.set_text(text);
+                } else if let Some(input) = any.downcast_mut::<TextInputView>() {
+                    input.state.set_text(text);
+                } else {
+                    return Err(UIError::NotText);
+                }
+            }
+
+            UiCommand::SetVisible { id, visible } => {
+                if let Some(overlay) = self.find_overlay(&id) {
+                    overlay.visible = visible;
+                    return Ok(());
+                }
+
+                if !visible && self.focused_view.as_deref() == Some(&*id) {
+                    self.focus(None);
+                }
+
+                self.find_view_mut(&id)?.set_visible(visible);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn take_view(&mut self, id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        let root = self.root.as_mut().ok_or(UIError::ViewNotFound)?;
+        root.as_container_mut().ok_or(UIError::ViewNotFound)?.take_child(id)
+    }
+
+    ///Draws the tooltip of the hovered view over everything, modals included
+    pub fn draw_tooltip(&mut self, engine: &Engine) -> UIResult {
+        let text = match self.tooltip.visible() {
+            Some(text) => text,
+            None => return Ok(()),
+        };
+
+        let view = self.tooltip_view.get_or_insert_with(|| {
+            let mut view = TextView::new(Box::from("tooltip"), String::new(), ViewPosition::zerod(), 1.0, 4, &engine.ui);
+            view.set_max_width(Some(TOOLTIP_MAX_WIDTH), TextOverflow::Wrap);
+            view.view.set_background_color(TOOLTIP_COLOR);
+            view
+        });
+
+        if view.text() != text {
+            view.set_text(text.to_string());
+        }
+
+        let size = view.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+        view.set_position(tooltip_position(self.tooltip.cursor, size, self.viewport));
+
+        unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
+        view.update(engine)
+    }
+
+    ///A button went down at the time the engine saw it, counts it as a double click when the
+    ///last press was on the same view a moment ago
+    fn count_press(&mut self, button: MouseButton, cords: Cords<f32>, at: Instant) -> ClickInfo {
+        let view = self
+            .input_root()
+            .and_then(|root| root.object_at(&cords, &|_| true))
+            .map(|object| object.id.clone());
+
+        let count = match &self.last_press {
+            Some(last) if last.continued_by(button, &view, cords, at) => last.count + 1,
+            _ => 1,
+        };
+
+        self.last_press = Some(LastPress { button, view, cords, at, count });
+        ClickInfo::press(button, count, cords)
+    }
+
+    ///Button1 went down, starts tracking a drag when it landed on a draggable view.
+    ///Returns true when it did, the view then gets the click on release if the cursor stays put.
+    fn press_draggable(&mut self, press: &ClickInfo) -> bool {
+        let root = match &self.root {
+            Some(root) if self.modals.is_empty() => root,
+            _ => return false,
+        };
+
+        match root.object_at(&press.cords, &|object| object.drag_payload.is_some()) {
+            Some(object) => {
+                self.drag = Some(DragState::new(object.drag_payload.clone().unwrap(), *press));
+                true
+            }
+            None => false,
+        }
+    }
+
+    ///Button1 went up after a drag started. A drop target under the cursor gets the payload,
+    ///over no view at all the drop is returned for the world.
+    fn release_drag(&mut self, drag: DragState, cords: Cords<f32>) -> Option<UiEvent> {
+        let root = self.root.as_ref()?;
+
+        if let Some(target) = root.object_at(&cords, &|object| object.on_drop.is_some()) {
+            self.drops.push((target.id.clone(), drag.payload));
+            return None;
+        }
+
+        match root.object_at(&cords, &|_| true) {
+            Some(_) => None,
+            None => Some(UiEvent::DroppedOnWorld { payload: drag.payload, cords }),
+        }
+    }
+
+    ///Draws the payload next to the cursor while a drag is going on
+    pub fn draw_drag_ghost(&mut self, engine: &Engine) -> UIResult {
+        let drag = match &self.drag {
+            Some(drag) if drag.started => drag,
+            _ => return Ok(()),
+        };
+
+        let view = self.drag_ghost.get_or_insert_with(|| {
+            let mut view = TextView::new(Box::from("drag_ghost"), String::new(), ViewPosition::zerod(), 1.0, 4, &engine.ui);
+            view.view.set_background_color(DRAG_GHOST_COLOR);
+            view.set_border(DRAG_GHOST_BORDER, 1);
+            view
+        });
+
+        //Note(teddy) Asset payloads are paths, the file name is enough
+        let label = drag.payload.rsplit('/').next().unwrap_or(&drag.payload);
+        if view.text() != label {
+            view.set_text(label.to_string());
+        }
+
+        let size = view.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+        view.set_position(tooltip_position(drag.cursor, size, self.viewport));
+
+        unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
+        view.update(engine)
+    }
+
+    ///Centers the view over the ui, input goes only to it until it's popped or closes itself
+    pub fn push_modal(&mut self, mut view: Box<dyn View>) {
+        view.get_view_object_mut().anchor = Some(Anchor::Center);
+        view.layout(self.viewport);
+
+        self.focus(None);
+        self.modals.push(view);
+    }
+
+    pub fn pop_modal(&mut self) -> Option<Box<dyn View>> {
+        self.focus(None);
+        self.modals.pop()
+    }
+
+    pub fn has_modal(&self) -> bool {
+        !self.modals.is_empty()
+    }
+
+    ///Opens a menu with a row per item at position, moved back on screen when it would go past an edge.
+    ///A click on a row runs its action in dispatch, a menu already open is replaced.
+    pub fn open_context_menu(&mut self, position: ViewPosition, items: Vec<(String, MenuAction)>, resources: &UiResources) {
+        let mut menu = SimpleUIContainer::new(Box::from(CONTEXT_MENU_ID), None, position, Orientation::Vertical, 0, 1.0, false);
+        menu.set_background_color(CONTEXT_MENU_COLOR);
+
+        for (index, (label, action)) in items.into_iter().enumerate() {
+            let id = format!("{}_{}", CONTEXT_MENU_ID, index).into_boxed_str();
+            let mut row = TextView::new(id, label, ViewPosition::zerod(), 1.0, CONTEXT_MENU_ROW_PADDING, resources);
+            row.set_background_color(CONTEXT_MENU_COLOR);
+
+            row.on_hover = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+                view.set_background_color(CONTEXT_MENU_HOVER_COLOR);
+            }));
+            row.on_mouse_leave = Some(Box::new(|_ctx: &mut UiContext, view: &mut TextView| {
+                view.set_background_color(CONTEXT_MENU_COLOR);
+            }));
+
+            let mut action = Some(action);
+            row.on_click = Some(Box::new(move |ctx: &mut UiContext, _view: &mut TextView| {
+                if let Some(action) = action.take() {
+                    action(ctx);
+                }
+            }));
+
+            menu.add_child(Box::new(row));
+        }
+
+        let size = menu.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+        menu.set_position(clamp_to_viewport(position, size, self.viewport));
+
+        self.tooltip.dismiss();
+        self.context_menu = Some(ContextMenu { view: Box::new(menu), closed: false });
+    }
+
+    pub fn has_context_menu(&self) -> bool {
+        self.context_menu.as_ref().map_or(false, |menu| !menu.closed)
+    }
+
+    pub fn close_context_menu(&mut self) {
+        self.context_menu = None;
+    }
+
+    ///Icon of the view under the cursor, an open menu is above the modals and the last modal above the rest
+    pub fn cursor_icon_at(&self, cords: &Cords<f32>) -> CursorIcon {
+        let menu = self.context_menu.as_ref().filter(|menu| !menu.closed && menu.view.bounds().contains(cords));
+        let top = match menu {
+            Some(menu) => Some(&menu.view),
+            None => self.modals.last().or(self.root.as_ref()),
+        };
+
+        top.and_then(|view| view.cursor_icon_at(cords)).unwrap_or(CursorIcon::Arrow)
+    }
+
+    ///Draws the open context menu over the ui and its modals
+    pub fn draw_context_menu(&mut self, engine: &Engine) -> UIResult {
+        match &mut self.context_menu {
+            Some(menu) if !menu.closed => {
+                unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
+                menu.view.update(engine)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    ///Runs every view's pending callbacks, then pops the modals that closed.
+    ///The UiCommands the callbacks pushed wait for flush_commands.
+    pub fn dispatch(&mut self, ctx: &mut UiContext) {
+        if let Some(root) = &mut self.root {
+            root.dispatch(ctx);
+        }
+
+        for overlay in self.overlays.iter_mut() {
+            overlay.view.dispatch(ctx);
+        }
+
+        for modal in self.modals.iter_mut() {
+            modal.dispatch(ctx);
+        }
+
+        if let Some(menu) = &mut self.context_menu {
+            menu.view.dispatch(ctx);
+            if menu.closed {
+                self.context_menu = None;
+            }
+        }
+
+        for on_complete in std::mem::take(&mut self.finished_tweens) {
+            on_complete(ctx);
+        }
+
+        for (id, payload) in std::mem::take(&mut self.drops) {
+            if let Some(mut view) = self.find_element(&id) {
+                let object = Rc::get_mut(&mut view).unwrap().get_view_object_mut();
+                if let Some(mut on_drop) = object.on_drop.take() {
+                    on_drop(ctx, payload);
+                    object.on_drop.get_or_insert(on_drop);
+                }
+            }
+        }
+
+        self.commands.extend(ctx.commands.drain::<UiCommand>());
+        self.pop_closed_modals();
+    }
+
+    ///Removes the modals that closed themselves, dispatch runs it once their callbacks fired
+    pub fn pop_closed_modals(&mut self) {
+        if self.modals.iter().any(|modal| modal.is_closed()) {
+            self.focus(None);
+            self.modals.retain(|modal| !modal.is_closed());
+        }
+    }
+
+    ///The view input is routed to, the topmost modal while there is one
+    pub fn input_root(&mut self) -> Option<&mut Box<dyn View>> {
+        match self.modals.last_mut() {
+            Some(modal) => Some(modal),
+            None => self.root.as_mut(),
+        }
+    }
+
+    ///Dims everything drawn so far and draws the topmost modal over it
+    pub fn draw_modals(&mut self, engine: &Engine) -> UIResult {
+        if self.modals.is_empty() {
+            return Ok(());
+        }
+
+        let (vao, vbo) = *self.backdrop_buffers.get_or_insert_with(|| unsafe { initialize_background_buffers() });
+        let (quad_position, quad_size) = self.viewport.quad_args();
+
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            draw_translucent_quad(
+                engine,
+                vao as u32,
+                vbo as u32,
+                quad_depth(None, QuadLayer::Container),
+                quad_position,
+                quad_size,
+                &MODAL_BACKDROP_COLOR,
+                MODAL_BACKDROP_ALPHA,
+            );
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+
+        let modal = self.modals.last_mut().unwrap();
+        modal.update(engine)?;
+
+        unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
+        modal.draw_popups(engine)
+    }
+
+    ///Resolves anchored views against the viewport, run when the window changes size
+    pub fn layout(&mut self, viewport: ViewRect) {
+        self.viewport = viewport;
+        if let Some(root) = &mut self.root {
+            root.layout(viewport);
+        }
+
+        for modal in self.modals.iter_mut() {
+            modal.layout(viewport);
+        }
+    }
+
+    ///Starts a ui frame, views outside the viewport are culled until the next one
+    pub fn begin_frame(&self) {
+        //Note(teddy) Before the first layout the viewport is empty and would cull everything
+        let laid_out = self.viewport.width() > 0.0 && self.viewport.height() > 0.0;
+        begin_ui_frame(Some(self.viewport).filter(|_| laid_out));
+    }
+
+    ///Sizes every container to its children before anything is drawn, culled views included
+    pub fn update_layout(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.update_layout();
+        }
+
+        for overlay in self.overlays.iter_mut() {
+            overlay.view.update_layout();
+        }
+
+        for modal in self.modals.iter_mut() {
+            modal.update_layout();
+        }
+    }
+
+    pub fn find_overlay(&mut self, id: &str) -> Option<&mut Overlay> {
+        self.overlays.iter_mut().find(|overlay| overlay.view.get_id() == id)
+    }
+
+    ///Searches from the root down through every nested container
+    pub fn find_view(&self, id: &str) -> Result<&Box<dyn View>, UIError> {
+        let root = self.root.as_ref().ok_or(UIError::ViewNotFound)?;
+        if root.get_id() == id {
+            return Ok(root);
+        }
+
+        root.as_container().ok_or(UIError::ViewNotFound)?.get_view_by_id(id)
+    }
+
+    pub fn find_view_mut(&mut self, id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        let root = self.root.as_mut().ok_or(UIError::ViewNotFound)?;
+        if root.get_id() == id {
+            return Ok(root);
+        }
+
+        root.as_container_mut().ok_or(UIError::ViewNotFound)?.get_view_by_id_mut(id)
+    }
+
+    ///find_view_mut for a view of a known type, None when the id is missing or the view is of another type
+    pub fn find_view_as<T: View + 'static>(&mut self, id: &str) -> Option<&mut T> {
+        let view = self.find_view_mut(id).ok()?;
+        let any: &mut dyn Any = *view.as_any();
+        any.downcast_mut::<T>()
+    }
+
+    pub fn find_element(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if let Some(view) = self.modals.iter_mut().rev().find_map(|modal| modal.get_element_by_id(id)) {
+            return Some(view);
+        }
+
+        if let Some(root) = &mut self.root {
+            return root.get_element_by_id(id);
+        }
+
+        None
+    }
+
+    ///Moves keyboard focus, None clears it
+    pub fn focus(&mut self, id: Option<Box<str>>) {
+        if let Some(previous) = self.focused_view.take() {
+            if let Some(mut view) = self.find_element(&previous) {
+                Rc::get_mut(&mut view).unwrap().set_focused(false);
+            }
+        }
+
+        if let Some(id) = id {
+            if let Some(mut view) = self.find_element(&id) {
+                Rc::get_mut(&mut view).unwrap().set_focused(true);
+                self.focused_view = Some(id);
+            }
+        }
+    }
+
+    ///Tab, cycles through the focusable views of the root container, or of the modal while one is open
+    pub fn focus_next(&mut self) {
+        let mut ids = vec![];
+        if let Some(root) = self.input_root() {
+            root.collect_focusable(&mut ids);
+        }
+
+        let next = next_focus(&ids, self.focused_view.as_deref());
+        self.focus(next);
+    }
+}
+
+fn next_focus(ids: &[Box<str>], current: Option<&str>) -> Option<Box<str>> {
+    if ids.is_empty() {
+        return None;
+    }
+
+    let next = match current.and_then(|current| ids.iter().position(|id| &**id == current)) {
+        Some(index) => (index + 1) % ids.len(),
+        None => 0,
+    };
+
+    Some(ids[next].clone())
+}
+
+///Where a quad sits within its view, every view's quads stay between its container and the next z_index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuadLayer {
+    Container,
+    Background,
+    ///Parts drawn over the view's own background, like the caret or the checkbox mark
+    Detail,
+}
+
+const UI_BASE_Z: f32 = -0.9;
+const UI_Z_STEP: f32 = 0.01;
+///Keeps every quad behind the text, which the font shader draws at z 1.0
+const MAX_Z_INDEX: u32 = 180;
+
+///The z handed to draw_quad. The ui projection puts a higher z nearer, so a higher z_index wins the depth test
+///the same way it wins the draw order.
+pub fn quad_depth(z_index: Option<u32>, layer: QuadLayer) -> f32 {
+    let z = UI_BASE_Z + z_index.unwrap_or(0).min(MAX_Z_INDEX) as f32 * UI_Z_STEP;
+
+    match layer {
+        QuadLayer::Container => z - UI_Z_STEP * 0.5,
+        QuadLayer::Background => z,
+        QuadLayer::Detail => z + UI_Z_STEP * 0.25,
+    }
+}
+
+///Child indices sorted by z_index, children with the same index keep their insertion order
+fn draw_order(children: &[Box<dyn View>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..children.len()).filter(|index| children[*index].is_visible()).collect();
+    order.sort_by_key(|index| children[*index].get_view_object().z_index.unwrap_or(0));
+    order
+}
+
+///Offers a click to the children topmost first and stops at the first one that takes it.
+///While a drag or an open dropdown captures the cursor only the capturing children are asked.
+fn offer_click<F>(children: &mut [Box<dyn View>], mut offer: F) -> bool
+where
+    F: FnMut(&mut Box<dyn View>) -> bool,
+{
+    let capturing = children.iter().any(|view| view.captures_cursor());
+
+    draw_order(children)
+        .into_iter()
+        .rev()
+        .any(|index| {
+            let view = &mut children[index];
+            //Note(teddy) The same check as drawing, a view culled off screen can't take the click
+            let reachable = if capturing { view.captures_cursor() } else { on_screen(view.as_ref()) };
+            reachable && offer(view)
+        })
+}
+
+#[derive(Debug)]
+pub enum UIError {
+    UnableToInitializeFramebuffer,
+    ViewNotFound,
+    NotAContainer,
+    NotText,
+}
+
+///Shape of the mouse cursor, the engine shows the one of the view under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    Arrow,
+    IBeam,
+    Hand,
+    HResize,
+    VResize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CursorState {
+    Hover,
+    Neither,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorEvent {
+    Enter,
+    Exit,
+}
+
+pub struct ViewObject {
+    cursor_hover_state: CursorState,
+    pub id: Box<str>,
+    pub background_vao: i32,
+    pub background_vbo: i32,
+    pub size: Option<ViewDimens>,
+    ///RGBA, a view without one draws no background quad
+    pub background: Option<[f32; 4]>,
+    pub padding: i32,
+    pub scale: f32,
+    pub position: ViewDimens,
+    pub z_index: Option<u32>,
+    pub margin: Margin,
+    ///Set to position the view against its parent's edges instead of where the parent's layout puts it
+    pub anchor: Option<Anchor>,
+    ///Distance from the anchored edges, towards the inside of the parent
+    pub anchor_offset: ViewPosition,
+    ///Containers never shrink below it, stretch anchors set it to the parent's size
+    pub min_size: Option<ViewDimens>,
+    pub tooltip: Option<String>,
+    ///Hidden views aren't drawn or hit tested, and their container gives them no space
+    pub visible: bool,
+    ///Border and corner radius of the background quad
+    pub style: QuadStyle,
+    ///Set by set_draggable, what a drag from the view carries
+    pub drag_payload: Option<String>,
+    ///Makes the view a drop target, runs in the dispatch phase with the dropped payload
+    pub on_drop: Option<Box<dyn FnMut(&mut UiContext, String)>>,
+    ///The size the view's container last gave it, padding included
+    pub allocated: Option<ViewDimens>,
+    resized: bool,
+    ///Runs in the dispatch phase after the container gave the view a new size
+    pub on_resize: Option<Box<dyn FnMut(&mut UiContext, ViewDimens)>>,
+}
+
+impl ViewObject {
+    fn new(
+        id: Box<str>,
+        position: ViewDimens,
+        size: Option<ViewDimens>,
+        padding: i32,
+        scale: f32,
+        background: Option<[f32; 4]>,
+        z_index: Option<u32>,
+    ) -> Self {
+        unsafe {
+            let (background_vao, background_vbo) = initialize_background_buffers();
+
+            Self {
+                id,
+                size,
+                padding,
+                scale,
+                position,
+                background_vao,
+                background_vbo,
+                background,
+                cursor_hover_state: CursorState::Neither,
+                z_index,
+                margin: Margin::zerod(),
+                anchor: None,
+                anchor_offset: ViewPosition::zerod(),
+                min_size: None,
+                tooltip: None,
+                visible: true,
+                style: QuadStyle::default(),
+                drag_payload: None,
+                on_drop: None,
+                allocated: None,
+                resized: false,
+                on_resize: None,
+            }
+        }
+    }
+
+    ///Keeps the alpha of the background, a transparent view becomes opaque
+    pub fn set_background_color(&mut self, [r, g, b]: [f32; 3]) {
+        let alpha = self.background.map_or(1.0, |color| color[3]);
+        self.background = Some([r, g, b, alpha]);
+    }
+
+    ///Fills the rect with the background and the style's border, nothing without a background
+    unsafe fn draw_background(&self, engine: &Engine, layer: QuadLayer, rect: ViewRect) {
+        let color = match &self.background {
+            Some(color) => color,
+            None => return,
+        };
+
+        let (position, size) = rect.quad_args();
+        draw_styled_quad(
+            engine,
+            self.background_vao as u32,
+            self.background_vbo as u32,
+            quad_depth(self.z_index, layer),
+            position,
+            size,
+            color,
+            &self.style,
+        );
+    }
+
+    ///Returns the transition when the hover state changes, nothing while it stays the same
+    fn set_hovered(&mut self, inside: bool) -> Option<CursorEvent> {
+        let state = if inside { CursorState::Hover } else { CursorState::Neither };
+        if state == self.cursor_hover_state {
+            return None;
+        }
+
+        self.cursor_hover_state = state;
+        Some(if inside { CursorEvent::Enter } else { CursorEvent::Exit })
+    }
+}
+
+///Where a TextView's glyph quads were built for, the buffer is rebuilt when any of it changes
+#[derive(Debug, Clone, PartialEq)]
+struct TextPlacement {
+    ///Top left of every line in window cords
+    lines: Vec<(f32, f32)>,
+    scale: f32,
+    viewport_height: i32,
+    ///The atlas grew since when the uvs in the buffer are stale
+    atlas_generation: u32,
+}
+
+pub struct TextView {
+    text_vao: i32,
+    text_vbo: i32,
+    ///Vertices in text_vbo, drawn as they are until the text or its placement changes
+    text_vertex_count: i32,
+    text_placement: Option<TextPlacement>,
+    ///Set when the lines change, the quads are rebuilt on the next draw
+    text_dirty: bool,
+    text_length: u32,
+    text_height: u32,
+    view: ViewObject,
+    ///Picked from the engine's FontCache, measuring and drawing both use it
+    font: Rc<FontFace>,
+    font_size: Option<u32>,
+
+    text: String,
+    ///What is drawn, one entry per line
+    lines: Vec<String>,
+    max_width: Option<i32>,
+    overflow: TextOverflow,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    ///Set by the parent container, Center and Right fill it to have room to align in
+    assigned: Option<ViewDimens>,
+    pub color: Option<Vector3<f32>>,
+    //Note(teddy) Incase the size is not passed, use the fonts width and heights and update this value
+    pub on_hover: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    pub on_mouse_leave: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    ///Cursor transitions since the last dispatch, the hover callbacks fire for them there
+    hover_events: Vec<CursorEvent>,
+    pub on_click: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    ///Second left press within DOUBLE_CLICK_TIME, without it the press is a plain click
+    pub on_double_click: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    pub on_right_click: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    pub on_middle_click: Option<Box<dyn FnMut(&mut UiContext, &mut TextView)>>,
+    ///Clicks on the view since the last dispatch
+    clicks: Vec<ClickInfo>,
+    ///Keys while the view is focused, setting it makes the view focusable and it takes every key
+    pub on_key_press: Option<Box<dyn FnMut(&mut UiContext, &mut TextView, Key)>>,
+    ///Keys since the last dispatch
+    keys: Vec<Key>,
+    focused: bool,
+}
+
+pub type UIResult = Result<(), UIError>;
+
+///Type erased queue of commands for the owner of the tree, for state the ui doesn't know about like the editor's
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Box<dyn Any>>,
+}
+
+impl CommandQueue {
+    pub fn push<T: Any>(&mut self, command: T) {
+        self.commands.push(Box::new(command));
+    }
+
+    ///Takes the commands of type T out in the order they were pushed, the rest stay queued
+    pub fn drain<T: Any>(&mut self) -> Vec<T> {
+        let (matching, rest): (Vec<_>, Vec<_>) = self.commands.drain(..).partition(|command| command.is::<T>());
+        self.commands = rest;
+        matching.into_iter().map(|command| *command.downcast::<T>().unwrap()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+///Handed to the callbacks in the dispatch phase, after the frame is drawn and no view is being traversed
+pub struct UiContext<'a> {
+    pub world: &'a mut World,
+    pub engine: &'a mut Engine,
+    pub commands: CommandQueue,
+}
+
+impl<'a> UiContext<'a> {
+    pub fn new(world: &'a mut World, engine: &'a mut Engine) -> Self {
+        Self { world, engine, commands: CommandQueue::default() }
+    }
+}
+
+#[inline]
+unsafe fn initialize_background_buffers() -> (i32, i32) {
+    let mut vao: u32 = 0;
+    let mut vbo: u32 = 0;
+
+    //Note(teddy) Without a gl context, as in the tests, views are still built and measured but draw nothing
+    if !gl::GenVertexArrays::is_loaded() {
+        return (0, 0);
+    }
+
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (6 * 3 * std::mem::size_of::<f32>()) as isize,
+        null(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(
+        0,
+        3, //Using vec3 when drawing quads inside the shader
+        gl::FLOAT,
+        gl::FALSE,
+        (3 * std::mem::size_of::<f32>()) as i32,
+        0 as *const c_void,
+    );
+
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    gl::BindVertexArray(0);
+
+    (vao as i32, vbo as i32)
+}
+
+///Buffers for draw_text, one glyph quad at a time
+#[inline]
+unsafe fn initialize_text_buffers() -> (i32, i32) {
+    let mut vbo: u32 = 0;
+    let mut vao: u32 = 0;
+
+    if !gl::GenVertexArrays::is_loaded() {
+        return (0, 0);
+    }
+
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (std::mem::size_of::<f32>() * 6 * 4) as isize,
+        null(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(
+        0,
+        4,
+        gl::FLOAT,
+        gl::FALSE,
+        (4 * std::mem::size_of::<f32>()) as i32,
+        0 as *const c_void,
+    );
+
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    gl::BindVertexArray(0);
+
+    (vao as i32, vbo as i32)
+}
+
+///Buffers for draw_textured_quad, a vec3 position and a vec2 uv per vertex
+#[inline]
+unsafe fn initialize_image_buffers() -> (i32, i32) {
+    let mut vbo: u32 = 0;
+    let mut vao: u32 = 0;
+    let stride = (5 * std::mem::size_of::<f32>()) as i32;
+
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (std::mem::size_of::<f32>() * 6 * 5) as isize,
+        null(),
+        gl::DYNAMIC_DRAW,
+    );
+
+    gl::EnableVertexAttribArray(0);
+    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, 0 as *const c_void);
+    gl::EnableVertexAttribArray(1);
+    gl::VertexAttribPointer(
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        stride,
+        (3 * std::mem::size_of::<f32>()) as *const c_void,
+    );
+
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    gl::BindVertexArray(0);
+
+    (vao as i32, vbo as i32)
+}
+
+impl TextView {
+    pub fn new(
+        id: Box<str>,
+        text: String,
+        position: ViewPosition,
+        scale: f32,
+        padding: i32,
+        resources: &UiResources,
+    ) -> Self {
+        let font = resources.font_face.clone();
+        let length_of_text = get_the_length_of_text(&text, &font);
+
+        let size = Some(Dimensions::new(
+            length_of_text as i32,
+            font.font_size as i32,
+        ));
+        unsafe {
+            let (vao, vbo) = initialize_text_buffers();
+
+            Self {
+                view: ViewObject::new(
+                    id,
+                    position,
+                    size,
+                    padding,
+                    scale,
+                    Some([0.4, 0.4, 0.4, 1.0]),
+                    None,
+                ),
+                lines: vec![text.clone()],
+                max_width: None,
+                overflow: TextOverflow::Wrap,
+                align: TextAlign::Left,
+                vertical_align: VerticalAlign::Top,
+                assigned: None,
+                text,
+                text_height: font.font_size as u32,
+                font,
+                font_size: None,
+                text_length: length_of_text,
+                text_vao: vao,
+                text_vbo: vbo,
+                text_vertex_count: 0,
+                text_placement: None,
+                text_dirty: true,
+                color: None,
+
+                on_hover: None,
+                on_mouse_leave: None,
+                hover_events: vec![],
+                on_click: None,
+                on_double_click: None,
+                on_right_click: None,
+                on_middle_click: None,
+                clicks: vec![],
+                on_key_press: None,
+                keys: vec![],
+                focused: false,
+            }
+        }
+    }
+
+    pub fn set_text(&mut self, new_text: String) {
+        self.text = new_text;
+        self.relayout();
+    }
+
+    ///Limits the width of the text, past it the text wraps or is cut off depending on overflow
+    pub fn set_max_width(&mut self, max_width: Option<i32>, overflow: TextOverflow) {
+        self.max_width = max_width;
+        self.overflow = overflow;
+        self.relayout();
+    }
+
+    ///Pixel size of the text, None goes back to the engine's default face
+    pub fn set_font_size(&mut self, font_size: Option<u32>, engine: &Engine) {
+        self.font_size = font_size;
+        self.font = engine.font(font_size);
+        self.relayout();
+    }
+
+    pub fn font(&self) -> &FontFace {
+        &self.font
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    //Note(teddy) The size follows the wrapped lines so containers stack the view at its real height
+    fn relayout(&mut self) {
+        let font_face: &FontFace = &self.font;
+        self.lines = layout_text(&self.text, self.max_width, self.overflow, font_face);
+        self.text_dirty = true;
+        self.text_length = get_the_length_of_text(&self.text, font_face);
+
+        let width = self.lines.iter().map(|line| get_the_length_of_text(line, font_face)).max().unwrap_or(0);
+        self.text_height = font_face.font_size * self.lines.len().max(1) as u32;
+        self.view.size = Some(Dimensions::new(width as i32, self.text_height as i32));
+    }
+}
+
+///Calls a callback with the context and the view itself. It's taken out of the view for the call,
+///a callback the call replaced is kept.
+macro_rules! fire_callback {
+    ($view:expr, $callback:ident, $ctx:expr $(, $arg:expr)*) => {
+        if let Some(mut func) = $view.$callback.take() {
+            func($ctx, $view $(, $arg)*);
+            if $view.$callback.is_none() {
+                $view.$callback = Some(func);
+            }
+        }
+    };
+}
+
+//Note(teddy) Only views with a hover callback keep the transitions, the rest would never drain them
+macro_rules! queue_hover {
+    ($view:expr, $event:expr) => {
+        if $view.on_hover.is_some() || $view.on_mouse_leave.is_some() {
+            $view.hover_events.push($event);
+        }
+    };
+}
+
+macro_rules! fire_hover_callbacks {
+    ($view:expr, $ctx:expr) => {
+        for event in std::mem::take(&mut $view.hover_events) {
+            match event {
+                CursorEvent::Enter => fire_callback!($view, on_hover, $ctx),
+                CursorEvent::Exit => fire_callback!($view, on_mouse_leave, $ctx),
+            }
+        }
+    };
+}
+
+impl View for TextView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+    fn get_id(&self) -> &str {
+        &(*self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn on_cursor_enter(&mut self) {
+        queue_hover!(self, CursorEvent::Enter);
+    }
+
+    fn on_cursor_exit(&mut self) {
+        queue_hover!(self, CursorEvent::Exit);
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let default_text_color: Vector3<f32> = Vector3::new(1.0, 1.0, 1.0);
+        let color = match &self.color {
+            Some(color) => color,
+            None => &default_text_color,
+        };
+
+        unsafe {
+            let rect = self.bounds();
+            let padding = self.view.padding as f32;
+            let content = (rect.width() - padding * 2.0, rect.height() - padding * 2.0);
+            let text_top = rect.min.y + padding + align_offset(content.1, self.text_height as f32, self.vertical_align.factor());
+
+            let placement = TextPlacement {
+                lines: self
+                    .lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let line_width = get_the_length_of_text(line, &self.font) as f32;
+                        (
+                            rect.min.x + padding + align_offset(content.0, line_width, self.align.factor()),
+                            text_top + (i as u32 * self.font.font_size) as f32,
+                        )
+                    })
+                    .collect(),
+                scale: self.view.scale,
+                viewport_height: engine.ui_size().height,
+                atlas_generation: self.font.atlas_generation(),
+            };
+
+            //Note(teddy) Containers set the position every frame, only a real move rebuilds the quads
+            if self.text_dirty || self.text_placement.as_ref() != Some(&placement) {
+                let vertices: Vec<[f32; 4]> = self
+                    .lines
+                    .iter()
+                    .zip(placement.lines.iter())
+                    .flat_map(|(line, (x, y))| glyph_quads(&self.font, line, *x, *y, placement.scale, placement.viewport_height))
+                    .collect();
+
+                upload_text_vertices(self.text_vbo as u32, &vertices);
+                self.text_vertex_count = vertices.len() as i32;
+                //Note(teddy) Building the quads can load glyphs and grow the atlas, the uvs are current as of now
+                self.text_placement = Some(TextPlacement { atlas_generation: self.font.atlas_generation(), ..placement });
+                self.text_dirty = false;
+            }
+
+            let atlas = self.font.atlas_texture();
+            draw_text_vertices(self.text_vao as u32, engine, &engine.ui.text_shader, atlas, self.text_vertex_count, color);
+            self.view.draw_background(engine, QuadLayer::Background, rect);
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.compute_intersect_with_cursor_cords(&engine, &cords);
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        //dbg!(&click.cords);
+
+        if self.bounds().contains(&click.cords) {
+            if !click.is_release() {
+                self.clicks.push(*click);
+            }
+            return true;
+        }
+
+        false
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        fire_hover_callbacks!(self, ctx);
+        for key in std::mem::take(&mut self.keys) {
+            fire_callback!(self, on_key_press, ctx, key);
+        }
+
+        for click in std::mem::take(&mut self.clicks) {
+            match click.button {
+                //Note(teddy) The first press of a double click already fired on_click
+                MouseButton::Button1 if click.count == 2 && self.on_double_click.is_some() => {
+                    fire_callback!(self, on_double_click, ctx)
+                }
+                //Note(teddy) Left Click
+                MouseButton::Button1 => fire_callback!(self, on_click, ctx),
+                //Right Click
+                MouseButton::Button2 => fire_callback!(self, on_right_click, ctx),
+                //Middleclick
+                MouseButton::Button3 => fire_callback!(self, on_middle_click, ctx),
+                _ => (),
+            }
+        }
+    }
+
+    fn activate(&mut self) -> bool {
+        let bounds = self.bounds();
+        let center = Cords { x: (bounds.min.x + bounds.max.x) / 2.0, y: (bounds.min.y + bounds.max.y) / 2.0 };
+        self.clicks.push(ClickInfo::press(MouseButton::Button1, 1, center));
+        true
+    }
+
+    fn update_dimensions(&mut self, dimensions: ViewDimens) {
+        self.assigned = Some(dimensions);
+    }
+
+    fn bounds(&self) -> ViewRect {
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let size = match self.assigned {
+            Some(assigned) => aligned_size(size, assigned, self.view.padding, self.align, self.vertical_align),
+            None => size,
+        };
+
+        ViewRect::new(self.view.position, size, self.view.padding)
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        match self.view.size {
+            Some(size) => Some(ViewDimens::new(
+                size.x + (self.view.padding << 1),
+                size.y + (self.view.padding << 1),
+            )),
+
+            None => None,
+        }
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+
+    fn receive_key(&mut self, _engine: &Engine, key: Key) -> bool {
+        if !self.focused || self.on_key_press.is_none() {
+            return false;
+        }
+
+        self.keys.push(key);
+        true
+    }
+
+    fn focusable(&self) -> bool {
+        self.on_key_press.is_some()
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+}
+
+///Editing state of a single line text field.
+///The cursor is a byte offset that always sits on a char boundary so multi-byte characters stay whole.
+#[derive(Debug, Default)]
+pub struct TextInputState {
+    text: String,
+    cursor: usize,
+}
+
+impl TextInputState {
+    pub fn new(text: String) -> Self {
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        //Note(teddy) Control characters come through key events, not the char callback
+        if c.is_control() {
+            return;
+        }
+
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor].char_indices().next_back().map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..].chars().next().map(|c| self.cursor + c.len_utf8())
+    }
+
+    ///Handles the editing and navigation keys, returns false for keys a text field doesn't use
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Backspace => {
+                if let Some(previous) = self.previous_boundary() {
+                    self.text.replace_range(previous..self.cursor, "");
+                    self.cursor = previous;
+                }
+            }
+            Key::Delete => {
+                if let Some(next) = self.next_boundary() {
+                    self.text.replace_range(self.cursor..next, "");
+                }
+            }
+            Key::Left => self.cursor = self.previous_boundary().unwrap_or(self.cursor),
+            Key::Right => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    ///Width of the whole text in pixels
+    pub fn measure(&self, font_face: &FontFace) -> u32 {
+        font_face.measure_text(&self.text)
+    }
+
+    ///Horizontal offset of the cursor from the start of the text
+    pub fn cursor_offset(&self, font_face: &FontFace) -> u32 {
+        font_face.measure_text(&self.text[..self.cursor])
+    }
+}
+
+const CARET_WIDTH: i32 = 2;
+const CARET_BLINK_MS: u128 = 530;
+
+///The caret stays solid right after focusing or typing, then blinks
+fn caret_visible(since_last_edit: Duration) -> bool {
+    (since_last_edit.as_millis() / CARET_BLINK_MS) % 2 == 0
+}
+
+pub struct TextInputView {
+    text_view: TextView,
+    caret_vao: i32,
+    caret_vbo: i32,
+    ///The background never shrinks below this, it grows with the text past it
+    min_width: i32,
+    last_edit: Instant,
+    pub state: TextInputState,
+    pub focused: bool,
+    pub on_submit: Option<Box<dyn FnMut(&mut UiContext, &mut TextInputView)>>,
+    submitted: bool,
+}
+
+impl TextInputView {
+    pub fn new(id: Box<str>, width: i32, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let mut text_view = TextView::new(id, String::new(), position, 1.0, padding, resources);
+        text_view.get_view_object_mut().size = Some(ViewDimens::new(width, resources.font_face.font_size as i32));
+        text_view.get_view_object_mut().set_background_color([0.15, 0.15, 0.15]);
+
+        let (caret_vao, caret_vbo) = unsafe { initialize_background_buffers() };
+
+        Self {
+            text_view,
+            caret_vao,
+            caret_vbo,
+            min_width: width,
+            last_edit: Instant::now(),
+            state: TextInputState::default(),
+            focused: false,
+            on_submit: None,
+            submitted: false,
+        }
+    }
+}
+
+impl View for TextInputView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if std::mem::take(&mut self.submitted) {
+            fire_callback!(self, on_submit, ctx);
+        }
+    }
+
+    fn get_id(&self) -> &str {
+        self.text_view.get_id()
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        self.text_view.get_view_object()
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        self.text_view.get_view_object_mut()
+    }
+
+    fn cursor_icon(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::IBeam)
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        if self.text_view.text != self.state.text() {
+            self.text_view.set_text(String::from(self.state.text()));
+
+            let width = (get_the_length_of_text(&self.text_view.text, self.text_view.font()) as i32 + CARET_WIDTH).max(self.min_width);
+            let view = self.text_view.get_view_object_mut();
+            view.size = view.size.map(|size| ViewDimens::new(width, size.y));
+        }
+
+        self.text_view.set_background_color(if self.focused { [0.25, 0.25, 0.3] } else { [0.15, 0.15, 0.15] });
+        self.text_view.update(engine)?;
+
+        if self.focused && caret_visible(self.last_edit.elapsed()) {
+            let view = self.text_view.get_view_object();
+            let caret_x = view.position.x + view.padding + self.state.cursor_offset(self.text_view.font()) as i32;
+
+            unsafe {
+                draw_quad_with_default_shader(
+                    engine,
+                    self.caret_vao as u32,
+                    self.caret_vbo as u32,
+                    quad_depth(self.text_view.get_view_object().z_index, QuadLayer::Detail),
+                    (caret_x as f32, (view.position.y + view.padding + self.text_view.font().font_size as i32) as f32),
+                    (self.text_view.font().font_size as f32, CARET_WIDTH as f32),
+                    &[1.0, 1.0, 1.0],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.text_view.receive_cursor_cords(engine, cords);
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        //Note(teddy) Focus on click is handled by the UITree
+        let view = self.text_view.get_view_object();
+        does_cursor_intersect(&click.cords, view.position, view.size.unwrap_or(ViewDimens::zerod()), view.padding)
+    }
+
+    fn receive_char(&mut self, _engine: &Engine, c: char) -> bool {
+        if !self.focused {
+            return false;
+        }
+
+        self.state.insert_char(c);
+        self.last_edit = Instant::now();
+        true
+    }
+
+    fn receive_key(&mut self, _engine: &Engine, key: Key) -> bool {
+        if !self.focused {
+            return false;
+        }
+
+        match key {
+            Key::Enter | Key::KpEnter => self.submitted = true,
+            key => {
+                if self.state.handle_key(key) {
+                    self.last_edit = Instant::now();
+                }
+            }
+        }
+
+        //Note(teddy) Every key belongs to the field while it's focused, typing 'w' must not move the camera
+        true
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.last_edit = Instant::now();
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.text_view.get_view_dimensions()
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.text_view.set_position(position);
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        self.text_view.get_position()
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const BUTTON_COLOR: [f32; 3] = [0.25, 0.25, 0.3];
+const BUTTON_HOVER_COLOR: [f32; 3] = [0.35, 0.35, 0.45];
+
+///Label with a background that lights up under the cursor, on_click fires on a left click
+pub struct ButtonView {
+    label: TextView,
+    clicked: bool,
+    pub on_click: Option<Box<dyn FnMut(&mut UiContext, &mut ButtonView)>>,
+}
+
+impl ButtonView {
+    pub fn new(id: Box<str>, label: String, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let mut label = TextView::new(id, label, position, 1.0, padding, resources);
+        label.align = TextAlign::Center;
+        label.view.set_background_color(BUTTON_COLOR);
+
+        Self { label, clicked: false, on_click: None }
+    }
+}
+
+impl View for ButtonView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        self.label.get_id()
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        self.label.get_view_object()
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        self.label.get_view_object_mut()
+    }
+
+    fn cursor_icon(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.label.update(engine)
+    }
+
+    fn on_cursor_enter(&mut self) {
+        self.label.view.set_background_color(BUTTON_HOVER_COLOR);
+    }
+
+    fn on_cursor_exit(&mut self) {
+        self.label.view.set_background_color(BUTTON_COLOR);
+    }
+
+    fn receive_cursor_cords(&mut self, _engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        if !self.bounds().contains(&click.cords) {
+            return false;
+        }
+
+        if click.pressed(MouseButton::Button1) {
+            self.clicked = true;
+        }
+
+        true
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if std::mem::take(&mut self.clicked) {
+            fire_callback!(self, on_click, ctx);
+        }
+    }
+
+    fn activate(&mut self) -> bool {
+        self.clicked = true;
+        true
+    }
+
+    fn update_dimensions(&mut self, dimensions: ViewDimens) {
+        self.label.update_dimensions(dimensions);
+    }
+
+    fn bounds(&self) -> ViewRect {
+        self.label.bounds()
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.label.get_view_dimensions()
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.label.set_position(position);
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        self.label.get_position()
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const CONFIRM_DIALOG_PADDING: i32 = 16;
+const CONFIRM_DIALOG_GAP: i32 = 12;
+const CONFIRM_DIALOG_MAX_WIDTH: i32 = 360;
+const CONFIRM_DIALOG_COLOR: [f32; 3] = [0.15, 0.15, 0.15];
+
+///The message on top and the buttons in a row under it, confirm against the right edge with cancel to its left.
+///Returns the content size and the positions of the message, the confirm and the cancel button.
+fn confirm_dialog_layout(
+    origin: ViewPosition,
+    message: ViewDimens,
+    confirm: ViewDimens,
+    cancel: ViewDimens,
+) -> (ViewDimens, [ViewPosition; 3]) {
+    let width = message.x.max(cancel.x + CONFIRM_DIALOG_GAP + confirm.x);
+    let row_y = origin.y + message.y + CONFIRM_DIALOG_GAP;
+    let height = message.y + CONFIRM_DIALOG_GAP + confirm.y.max(cancel.y);
+
+    let confirm_x = origin.x + width - confirm.x;
+    let cancel_x = confirm_x - CONFIRM_DIALOG_GAP - cancel.x;
+
+    (
+        ViewDimens::new(width, height),
+        [origin, ViewPosition::new(confirm_x, row_y), ViewPosition::new(cancel_x, row_y)],
+    )
+}
+
+///Asks before something that can't be undone, meant to be pushed with UITree::push_modal.
+///Either button, Enter or Escape closes it and the tree pops it.
+pub struct ConfirmDialog {
+    view: ViewObject,
+    message: TextView,
+    confirm: ButtonView,
+    cancel: ButtonView,
+    ///Set when a button or key answers the dialog, true for confirm
+    answer: Option<bool>,
+    closed: bool,
+    pub on_confirm: Option<Box<dyn FnMut(&mut UiContext, &mut ConfirmDialog)>>,
+    pub on_cancel: Option<Box<dyn FnMut(&mut UiContext, &mut ConfirmDialog)>>,
+}
+
+impl ConfirmDialog {
+    pub fn new(id: Box<str>, message: String, confirm_label: String, cancel_label: String, resources: &UiResources) -> Self {
+        let mut message = TextView::new(format!("{}_message", id).into_boxed_str(), message, ViewPosition::zerod(), 1.0, 0, resources);
+        message.set_max_width(Some(CONFIRM_DIALOG_MAX_WIDTH), TextOverflow::Wrap);
+        message.view.set_background_color(CONFIRM_DIALOG_COLOR);
+
+        let confirm = ButtonView::new(format!("{}_confirm", id).into_boxed_str(), confirm_label, ViewPosition::zerod(), 6, resources);
+        let cancel = ButtonView::new(format!("{}_cancel", id).into_boxed_str(), cancel_label, ViewPosition::zerod(), 6, resources);
+
+        let mut dialog = Self {
+            view: ViewObject::new(
+                id,
+                ViewPosition::zerod(),
+                None,
+                CONFIRM_DIALOG_PADDING,
+                1.0,
+                None,
+                None,
+            ),
+            message,
+            confirm,
+            cancel,
+            answer: None,
+            closed: false,
+            on_confirm: None,
+            on_cancel: None,
+        };
+        dialog.view.set_background_color(CONFIRM_DIALOG_COLOR);
+        dialog.arrange();
+        dialog
+    }
+
+    //Note(teddy) Only the first answer counts, the dialog stays up until the dispatch pops it
+    pub fn confirm(&mut self) {
+        self.answer.get_or_insert(true);
+    }
+
+    pub fn cancel(&mut self) {
+        self.answer.get_or_insert(false);
+    }
+
+    fn arrange(&mut self) {
+        let padding = self.view.padding;
+        let origin = ViewPosition::new(self.view.position.x + padding, self.view.position.y + padding);
+        let dimensions = |view: &dyn View| view.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+
+        let (size, [message, confirm, cancel]) =
+            confirm_dialog_layout(origin, dimensions(&self.message), dimensions(&self.confirm), dimensions(&self.cancel));
+
+        self.view.size = Some(size);
+        self.message.set_position(message);
+        self.confirm.set_position(confirm);
+        self.cancel.set_position(cancel);
+    }
+}
+
+impl View for ConfirmDialog {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &self.view.id
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        self.message.update(engine)?;
+        self.confirm.update(engine)?;
+        self.cancel.update(engine)?;
+
+        unsafe { self.view.draw_background(engine, QuadLayer::Container, self.bounds()) };
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+        self.confirm.receive_cursor_cords(engine, cords);
+        self.cancel.receive_cursor_cords(engine, cords);
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        self.confirm.cursor_icon_at(cords).or_else(|| self.cancel.cursor_icon_at(cords))
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        if click.pressed(MouseButton::Button1) {
+            if self.confirm.bounds().contains(&click.cords) {
+                self.confirm();
+            } else if self.cancel.bounds().contains(&click.cords) {
+                self.cancel();
+            }
+        }
+
+        self.bounds().contains(&click.cords)
+    }
+
+    fn receive_key(&mut self, _engine: &Engine, key: Key) -> bool {
+        match key {
+            Key::Escape => self.cancel(),
+            Key::Enter | Key::KpEnter => self.confirm(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        match self.answer {
+            Some(_) if self.closed => (),
+            Some(true) => fire_callback!(self, on_confirm, ctx),
+            Some(false) => fire_callback!(self, on_cancel, ctx),
+            None => return,
+        }
+
+        self.closed = true;
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+        self.arrange();
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const CHECKBOX_LABEL_GAP: i32 = 8;
+const CHECKBOX_MARK_INSET: i32 = 3;
+
+///Box plus the label to its right, without padding
+fn checkbox_dimensions(box_size: i32, label_width: Option<u32>) -> ViewDimens {
+    match label_width {
+        Some(width) => ViewDimens::new(box_size + CHECKBOX_LABEL_GAP + width as i32, box_size),
+        None => ViewDimens::new(box_size, box_size),
+    }
+}
+
+///Box that toggles on click, the label is optional and drawn to the right of the box
+pub struct CheckboxView {
+    view: ViewObject,
+    mark_vao: i32,
+    mark_vbo: i32,
+    text_vao: i32,
+    text_vbo: i32,
+    box_size: i32,
+    label: Option<String>,
+    checked: bool,
+    changed: bool,
+    pub on_change: Option<Box<dyn FnMut(&mut UiContext, &mut CheckboxView, bool)>>,
+}
+
+impl CheckboxView {
+    pub fn new(id: Box<str>, label: Option<String>, checked: bool, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let box_size = resources.font_face.font_size as i32;
+        let label_width = label.as_ref().map(|label| get_the_length_of_text(label, &resources.font_face));
+
+        unsafe {
+            let (mark_vao, mark_vbo) = initialize_background_buffers();
+            let (text_vao, text_vbo) = initialize_text_buffers();
+
+            Self {
+                view: ViewObject::new(
                     id,
                     position,
+                    Some(checkbox_dimensions(box_size, label_width)),
+                    padding,
+                    1.0,
+                    Some([0.2, 0.2, 0.2, 1.0]),
+                    None,
+                ),
+                mark_vao,
+                mark_vbo,
+                text_vao,
+                text_vbo,
+                box_size,
+                label,
+                checked,
+                changed: false,
+                on_change: None,
+            }
+        }
+    }
+
+    ///Shorthand for on_change when the view itself isn't needed
+    pub fn on_toggle<F: FnMut(&mut UiContext, bool) + 'static>(&mut self, mut func: F) {
+        self.on_change = Some(Box::new(move |ctx: &mut UiContext, _view: &mut Self, checked: bool| func(ctx, checked)));
+    }
+
+    ///Sets the state without calling on_change, for syncing with a value changed elsewhere
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    pub fn toggle(&mut self) {
+        self.checked = !self.checked;
+        self.changed = true;
+    }
+}
+
+impl View for CheckboxView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if std::mem::take(&mut self.changed) {
+            let checked = self.checked;
+            fire_callback!(self, on_change, ctx, checked);
+        }
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let box_x = (self.view.position.x + self.view.padding) as f32;
+        let box_y = (self.view.position.y + self.view.padding) as f32;
+        let box_size = self.box_size as f32;
+        let inset = CHECKBOX_MARK_INSET as f32;
+
+        unsafe {
+            if self.checked {
+                draw_quad_with_default_shader(
+                    engine,
+                    self.mark_vao as u32,
+                    self.mark_vbo as u32,
+                    quad_depth(self.view.z_index, QuadLayer::Detail),
+                    (box_x + inset, box_y + box_size - inset),
+                    (box_size - inset * 2.0, box_size - inset * 2.0),
+                    &[0.3, 0.7, 0.3],
+                );
+            }
+
+            self.view.draw_background(engine, QuadLayer::Background, ViewRect::at(box_x, box_y, box_size, box_size));
+
+            if let Some(label) = &self.label {
+                draw_text(
+                    self.text_vao as u32,
+                    self.text_vbo as u32,
+                    &engine,
+                    &engine.font_face,
+                    &engine.ui.text_shader,
+                    label.as_str(),
+                    box_x + (self.box_size + CHECKBOX_LABEL_GAP) as f32,
+                    box_y,
+                    1.0,
+                    &Vector3::new(1.0, 1.0, 1.0),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        let inside = does_cursor_intersect(
+            &click.cords,
+            self.view.position,
+            self.view.size.unwrap_or(ViewDimens::zerod()),
+            self.view.padding,
+        );
+
+        if inside && click.pressed(MouseButton::Button1) {
+            self.toggle();
+        }
+
+        inside
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const SLIDER_TRACK_HEIGHT: f32 = 4.0;
+const SLIDER_HANDLE_WIDTH: f32 = 8.0;
+const SLIDER_VALUE_GAP: i32 = 8;
+
+///Value under the cursor, clamped to the track and rounded to the step
+fn slider_value(cursor_x: f32, track_x: f32, track_width: f32, min: f32, max: f32, step: f32) -> f32 {
+    let t = ((cursor_x - track_x) / track_width.max(1.0)).max(0.0).min(1.0);
+    let value = min + t * (max - min);
+
+    if step > 0.0 {
+        (min + ((value - min) / step).round() * step).min(max)
+    } else {
+        value
+    }
+}
+
+fn format_slider_value(value: f32, step: f32) -> String {
+    if step >= 1.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+///Horizontal slider, the value is set by clicking the track and follows the cursor while Button1 is held
+pub struct SliderView {
+    view: ViewObject,
+    handle_vao: i32,
+    handle_vbo: i32,
+    text_vao: i32,
+    text_vbo: i32,
+    track_width: i32,
+    min: f32,
+    max: f32,
+    step: f32,
+    value: f32,
+    dragging: bool,
+    ///The value moved since the last dispatch, on_change only gets the latest
+    changed: bool,
+    pub on_change: Option<Box<dyn FnMut(&mut UiContext, &mut SliderView, f32)>>,
+}
+
+impl SliderView {
+    pub fn new(id: Box<str>, track_width: i32, (min, max, step): (f32, f32, f32), value: f32, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+
+        //Note(teddy) Room for the widest value so the layout doesn't shift while dragging
+        let value_width = std::cmp::max(
+            get_the_length_of_text(&format_slider_value(min, step), &resources.font_face),
+            get_the_length_of_text(&format_slider_value(max, step), &resources.font_face),
+        ) as i32;
+        let size = ViewDimens::new(track_width + SLIDER_VALUE_GAP + value_width, resources.font_face.font_size as i32);
+
+        unsafe {
+            let (handle_vao, handle_vbo) = initialize_background_buffers();
+            let (text_vao, text_vbo) = initialize_text_buffers();
+
+            Self {
+                view: ViewObject::new(id, position, Some(size), padding, 1.0, Some([0.3, 0.3, 0.3, 1.0]), None),
+                handle_vao,
+                handle_vbo,
+                text_vao,
+                text_vbo,
+                track_width,
+                min,
+                max,
+                step,
+                value: value.max(min).min(max),
+                dragging: false,
+                changed: false,
+                on_change: None,
+            }
+        }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    ///Sets the value without calling on_change, for syncing with a value changed elsewhere
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.max(self.min).min(self.max);
+    }
+
+    fn track_x(&self) -> f32 {
+        (self.view.position.x + self.view.padding) as f32
+    }
+
+    fn drag_to(&mut self, cursor_x: f32) {
+        let value = slider_value(cursor_x, self.track_x(), self.track_width as f32, self.min, self.max, self.step);
+        if value == self.value {
+            return;
+        }
+
+        self.value = value;
+        self.changed = true;
+    }
+}
+
+impl View for SliderView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if std::mem::take(&mut self.changed) {
+            let value = self.value;
+            fire_callback!(self, on_change, ctx, value);
+        }
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let track_x = self.track_x();
+        let top = (self.view.position.y + self.view.padding) as f32;
+        let height = engine.font_face.font_size as f32;
+        let t = if self.max > self.min { (self.value - self.min) / (self.max - self.min) } else { 0.0 };
+        let handle_x = track_x + t * self.track_width as f32 - SLIDER_HANDLE_WIDTH * 0.5;
+
+        unsafe {
+            draw_quad_with_default_shader(
+                engine,
+                self.handle_vao as u32,
+                self.handle_vbo as u32,
+                quad_depth(self.view.z_index, QuadLayer::Detail),
+                (handle_x, top + height),
+                (height, SLIDER_HANDLE_WIDTH),
+                if self.dragging { &[0.9, 0.9, 0.9] } else { &[0.7, 0.7, 0.7] },
+            );
+
+            self.view.draw_background(engine, QuadLayer::Background, ViewRect::at(track_x, top + (height - SLIDER_TRACK_HEIGHT) * 0.5, self.track_width as f32, SLIDER_TRACK_HEIGHT));
+
+            draw_text(
+                self.text_vao as u32,
+                self.text_vbo as u32,
+                &engine,
+                &engine.font_face,
+                &engine.ui.text_shader,
+                &format_slider_value(self.value, self.step),
+                track_x + (self.track_width + SLIDER_VALUE_GAP) as f32,
+                top,
+                1.0,
+                &Vector3::new(1.0, 1.0, 1.0),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, _engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+
+        if self.dragging {
+            self.drag_to(cords.x);
+        }
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        if click.button != MouseButton::Button1 {
+            return self.dragging;
+        }
+
+        if click.is_release() {
+            return std::mem::replace(&mut self.dragging, false);
+        }
+
+        if self.dragging {
+            return true;
+        }
+
+        let on_track = does_cursor_intersect(
+            &click.cords,
+            self.view.position,
+            ViewDimens::new(self.track_width, self.view.size.unwrap_or(ViewDimens::zerod()).y),
+            self.view.padding,
+        );
+
+        if on_track {
+            self.dragging = true;
+            self.drag_to(click.cords.x);
+        }
+
+        on_track
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.dragging
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const COLOR_CHANNEL_RANGE: (f32, f32, f32) = (0.0, 1.0, 0.01);
+const COLOR_SLIDER_WIDTH: i32 = 120;
+const COLOR_PREVIEW_GAP: i32 = 8;
+
+///Sliders for red, green and blue stacked next to a square of the color, on_change fires when any channel moves
+pub struct ColorPickerView {
+    view: ViewObject,
+    preview_vao: i32,
+    preview_vbo: i32,
+    channels: [SliderView; 3],
+    color: [f32; 3],
+    pub on_change: Option<Box<dyn FnMut(&mut UiContext, &mut ColorPickerView, [f32; 3])>>,
+}
+
+impl ColorPickerView {
+    pub fn new(id: Box<str>, color: [f32; 3], position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let channel = |suffix: &str, value: f32| {
+            SliderView::new(format!("{}_{}", id, suffix).into_boxed_str(), COLOR_SLIDER_WIDTH, COLOR_CHANNEL_RANGE, value, ViewPosition::zerod(), 2, resources)
+        };
+        let channels = [channel("r", color[0]), channel("g", color[1]), channel("b", color[2])];
+
+        unsafe {
+            let (preview_vao, preview_vbo) = initialize_background_buffers();
+
+            let mut picker = Self {
+                view: ViewObject::new(id, position, None, padding, 1.0, None, None),
+                preview_vao,
+                preview_vbo,
+                channels,
+                color: [0.0; 3],
+                on_change: None,
+            };
+            picker.set_color(color);
+            picker.arrange();
+            picker
+        }
+    }
+
+    ///Sets the color without calling on_change, for syncing with a color changed elsewhere
+    pub fn set_color(&mut self, color: [f32; 3]) {
+        for (slider, value) in self.channels.iter_mut().zip(color.iter()) {
+            slider.set_value(*value);
+        }
+        self.color = self.channel_values();
+    }
+
+    fn channel_values(&self) -> [f32; 3] {
+        [self.channels[0].value(), self.channels[1].value(), self.channels[2].value()]
+    }
+
+    ///Square as tall as the three sliders, right of them
+    fn preview_rect(&self) -> ViewRect {
+        let padding = self.view.padding;
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        ViewRect::at((self.view.position.x + padding + size.x - size.y) as f32, (self.view.position.y + padding) as f32, size.y as f32, size.y as f32)
+    }
+
+    fn arrange(&mut self) {
+        let padding = self.view.padding;
+        let mut y = self.view.position.y + padding;
+        let mut width = 0;
+
+        for slider in self.channels.iter_mut() {
+            slider.set_position(ViewPosition::new(self.view.position.x + padding, y));
+            let dimensions = slider.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+            y += dimensions.y;
+            width = width.max(dimensions.x);
+        }
+
+        let height = y - self.view.position.y - padding;
+        self.view.size = Some(ViewDimens::new(width + COLOR_PREVIEW_GAP + height, height));
+    }
+}
+
+impl View for ColorPickerView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &self.view.id
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        for slider in self.channels.iter_mut() {
+            slider.update(engine)?;
+        }
+
+        //Note(teddy) The preview follows the sliders while they are dragged, before on_change has run
+        let (position, size) = self.preview_rect().quad_args();
+        unsafe {
+            draw_quad_with_default_shader(
+                engine,
+                self.preview_vao as u32,
+                self.preview_vbo as u32,
+                quad_depth(self.view.z_index, QuadLayer::Detail),
+                position,
+                size,
+                &self.channel_values(),
+            );
+            self.view.draw_background(engine, QuadLayer::Background, self.bounds());
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+        for slider in self.channels.iter_mut() {
+            slider.receive_cursor_cords(engine, cords);
+        }
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        let taken = self.channels.iter_mut().fold(false, |taken, slider| slider.handle_button_click(engine, click) || taken);
+        taken || self.bounds().contains(&click.cords)
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.channels.iter().any(|slider| slider.captures_cursor())
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        let color = self.channel_values();
+        if color != self.color {
+            self.color = color;
+            fire_callback!(self, on_change, ctx, color);
+        }
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+        self.arrange();
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+///How quickly the fill closes in on the target fraction, the gap shrinks by e every 1/speed seconds
+const PROGRESS_FILL_SPEED: f32 = 8.0;
+const PROGRESS_FILL_COLOR: [f32; 3] = [0.2, 0.6, 0.2];
+
+///Fraction the fill shows after dt seconds of easing from shown toward target
+fn approach_fraction(shown: f32, target: f32, dt: f32) -> f32 {
+    let next = shown + (target - shown) * (1.0 - (-dt * PROGRESS_FILL_SPEED).exp());
+
+    //Note(teddy) Snap once the gap can't be seen, the fill would otherwise creep forever
+    if (target - next).abs() < 0.001 {
+        target
+    } else {
+        next
+    }
+}
+
+///Track with a fill as wide as the fraction, the fill slides toward a new fraction instead of jumping
+pub struct ProgressBarView {
+    view: ViewObject,
+    fill_vao: i32,
+    fill_vbo: i32,
+    track_width: i32,
+    ///Where the fill is heading, set_fraction moves it
+    fraction: f32,
+    ///What the fill shows this frame
+    shown: f32,
+    last_frame: Option<Instant>,
+    ///Centered over the track when the percentage is shown
+    label: Option<TextView>,
+}
+
+impl ProgressBarView {
+    pub fn new(id: Box<str>, track_width: i32, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let size = ViewDimens::new(track_width, resources.font_face.font_size as i32);
+
+        unsafe {
+            let (fill_vao, fill_vbo) = initialize_background_buffers();
+
+            Self {
+                view: ViewObject::new(id, position, Some(size), padding, 1.0, Some([0.25, 0.25, 0.25, 1.0]), None),
+                fill_vao,
+                fill_vbo,
+                track_width,
+                fraction: 0.0,
+                shown: 0.0,
+                last_frame: None,
+                label: None,
+            }
+        }
+    }
+
+    ///Clamped to 0..1. The fill only eases forward, a lower fraction is a new run and the fill starts over there.
+    pub fn set_fraction(&mut self, fraction: f32) {
+        let fraction = fraction.max(0.0).min(1.0);
+        if fraction < self.fraction {
+            self.shown = fraction;
+        }
+
+        self.fraction = fraction;
+        if let Some(label) = &mut self.label {
+            label.set_text(format!("{:.0}%", self.fraction * 100.0));
+        }
+    }
+
+    ///Shows the percentage over the track
+    pub fn set_show_percentage(&mut self, show: bool, resources: &UiResources) {
+        if !show {
+            self.label = None;
+            return;
+        }
+
+        let mut label = TextView::new(format!("{}_percentage", self.view.id).into_boxed_str(), String::new(), self.view.position, 1.0, 0, resources);
+        label.set_background(None);
+        label.align = TextAlign::Center;
+        label.vertical_align = VerticalAlign::Center;
+        self.label = Some(label);
+        self.set_fraction(self.fraction);
+    }
+
+    fn track_rect(&self) -> ViewRect {
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let position = ViewPosition::new(self.view.position.x + self.view.padding, self.view.position.y + self.view.padding);
+        ViewRect::new(position, ViewDimens::new(self.track_width, size.y), 0)
+    }
+}
+
+impl View for ProgressBarView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let now = Instant::now();
+        let dt = self.last_frame.map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_frame = Some(now);
+        self.shown = approach_fraction(self.shown, self.fraction, dt);
+
+        let track = self.track_rect();
+
+        if let Some(label) = &mut self.label {
+            label.set_position(ViewPosition::new(track.min.x as i32, track.min.y as i32));
+            label.update_dimensions(ViewDimens::new(track.width() as i32, track.height() as i32));
+            label.update(engine)?;
+        }
+
+        unsafe {
+            if self.shown > 0.0 {
+                let (position, size) = ViewRect::at(track.min.x, track.min.y, track.width() * self.shown, track.height()).quad_args();
+                draw_quad_with_default_shader(
+                    engine,
+                    self.fill_vao as u32,
+                    self.fill_vbo as u32,
+                    quad_depth(self.view.z_index, QuadLayer::Detail),
+                    position,
                     size,
-                    padding,
-                    scale,
-                    Box::new([0.4, 0.4, 0.4]),
-                    None,
-                ),
-                text,
-                text_height: engine.font_face.font_size as u32,
-                text_length: length_of_text,
-                text_vao: vao as i32,
-                text_vbo: vbo as i32,
-                cursor_hover: CursorState::Neither,
-                text_shader_id: SHADER_TEXT_ID,
-                color: None,
+                    &PROGRESS_FILL_COLOR,
+                );
+            }
+
+            self.view.draw_background(engine, QuadLayer::Background, track);
+        }
+
+        Ok(())
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        self.bounds().contains(&click.cords)
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+const DROPDOWN_MARKER: &'static str = " v";
+
+///Index of the option row under the cursor, the rows are stacked below the collapsed header
+fn dropdown_option_at(cords: &Cords<f32>, list_position: ViewPosition, row: ViewDimens, count: usize) -> Option<usize> {
+    let x = cords.x - list_position.x as f32;
+    let y = cords.y - list_position.y as f32;
+
+    if x < 0.0 || x >= row.x as f32 || y < 0.0 || row.y <= 0 {
+        return None;
+    }
+
+    let index = (y / row.y as f32) as usize;
+    if index < count {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+///Collapsed it shows the selected option, clicking it lists every option below it
+pub struct DropdownView {
+    view: ViewObject,
+    row_vao: i32,
+    row_vbo: i32,
+    text_vao: i32,
+    text_vbo: i32,
+    ///Measures the options
+    font: Rc<FontFace>,
+    options: Vec<String>,
+    selected: Option<usize>,
+    expanded: bool,
+    hovered: Option<usize>,
+    ///Row picked since the last dispatch
+    picked: Option<usize>,
+    pub on_select: Option<Box<dyn FnMut(&mut UiContext, &mut DropdownView, usize)>>,
+}
+
+impl DropdownView {
+    pub fn new(id: Box<str>, options: Vec<String>, selected: Option<usize>, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        unsafe {
+            let (row_vao, row_vbo) = initialize_background_buffers();
+            let (text_vao, text_vbo) = initialize_text_buffers();
+
+            let mut dropdown = Self {
+                view: ViewObject::new(id, position, None, padding, 1.0, Some([0.2, 0.2, 0.2, 1.0]), None),
+                row_vao,
+                row_vbo,
+                text_vao,
+                text_vbo,
+                font: resources.font_face.clone(),
+                options: vec![],
+                selected: None,
+                expanded: false,
+                hovered: None,
+                picked: None,
+                on_select: None,
+            };
+            dropdown.set_options(options);
+            dropdown.selected = selected.filter(|index| *index < dropdown.options.len());
+            dropdown
+        }
+    }
+
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    ///Selects without calling on_select, for syncing with a value changed elsewhere
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected.filter(|index| *index < self.options.len());
+    }
+
+    ///Replaces the options, the selection follows its option by name and is cleared when it is gone
+    pub fn set_options(&mut self, options: Vec<String>) {
+        let selected_name = self.selected.map(|index| self.options[index].clone());
+
+        let width = options
+            .iter()
+            .map(|option| get_the_length_of_text(&format!("{}{}", option, DROPDOWN_MARKER), &self.font))
+            .max()
+            .unwrap_or(0);
+
+        self.selected = selected_name.and_then(|name| options.iter().position(|option| *option == name));
+        self.options = options;
+        self.hovered = None;
+        self.view.size = Some(ViewDimens::new(width as i32, self.font.font_size as i32));
+    }
+
+    fn row_dimensions(&self) -> ViewDimens {
+        self.get_view_dimensions().unwrap_or(ViewDimens::zerod())
+    }
+
+    fn list_position(&self) -> ViewPosition {
+        ViewPosition::new(self.view.position.x, self.view.position.y + self.row_dimensions().y)
+    }
+
+    unsafe fn draw_row(&self, engine: &Engine, text: &str, top: i32, color: &[f32; 3]) {
+        let row = self.row_dimensions();
+
+        draw_text(
+            self.text_vao as u32,
+            self.text_vbo as u32,
+            &engine,
+            &engine.font_face,
+            &engine.ui.text_shader,
+            text,
+            (self.view.position.x + self.view.padding) as f32,
+            (top + self.view.padding) as f32,
+            1.0,
+            &Vector3::new(1.0, 1.0, 1.0),
+        );
+        draw_quad_with_default_shader(
+            engine,
+            self.row_vao as u32,
+            self.row_vbo as u32,
+            quad_depth(self.view.z_index, QuadLayer::Background),
+            (self.view.position.x as f32, (top + row.y) as f32),
+            (row.y as f32, row.x as f32),
+            color,
+        );
+    }
+}
+
+impl View for DropdownView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if let Some(index) = self.picked.take() {
+            fire_callback!(self, on_select, ctx, index);
+        }
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let header = match self.selected {
+            Some(index) => format!("{}{}", self.options[index], DROPDOWN_MARKER),
+            None => String::from(DROPDOWN_MARKER),
+        };
+
+        //Note(teddy) Rows are drawn opaque with the default shader, only the background's color is used
+        let [r, g, b, _] = self.view.background.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        unsafe { self.draw_row(engine, &header, self.view.position.y, &[r, g, b]) };
+        Ok(())
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        if !self.expanded {
+            return Ok(());
+        }
+
+        let list = self.list_position();
+        let row_height = self.row_dimensions().y;
+
+        for (index, option) in self.options.iter().enumerate() {
+            let color = if self.hovered == Some(index) {
+                [0.0, 0.4, 0.0]
+            } else if self.selected == Some(index) {
+                [0.3, 0.3, 0.3]
+            } else {
+                [0.15, 0.15, 0.15]
+            };
+
+            unsafe { self.draw_row(engine, option, list.y + row_height * index as i32, &color) };
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, _engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+
+        if self.expanded {
+            self.hovered = dropdown_option_at(&cords, self.list_position(), self.row_dimensions(), self.options.len());
+        }
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        let on_header = does_cursor_intersect(
+            &click.cords,
+            self.view.position,
+            self.view.size.unwrap_or(ViewDimens::zerod()),
+            self.view.padding,
+        );
+
+        if !click.pressed(MouseButton::Button1) {
+            return on_header;
+        }
+
+        if self.expanded {
+            //Note(teddy) Any click while open closes the list, only a click on a row selects
+            self.expanded = false;
+            self.hovered = None;
+
+            if let Some(index) = dropdown_option_at(&click.cords, self.list_position(), self.row_dimensions(), self.options.len()) {
+                self.selected = Some(index);
+                self.picked = Some(index);
+            }
+            //Note(teddy) The click that closes the list never reaches the views under it
+            return true;
+        } else if on_header && !self.options.is_empty() {
+            self.expanded = true;
+        }
+
+        on_header
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.expanded
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+///Indent of each level of a TreeView, in pixels
+const TREE_INDENT: i32 = 16;
+const TREE_SELECTED_COLOR: [f32; 3] = [0.25, 0.35, 0.25];
+
+///A node of a TreeView, the id is the caller's own, the outliner uses entity ids
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    pub label: String,
+    pub id: usize,
+    pub expanded: bool,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    pub fn new(label: String, id: usize) -> Self {
+        Self { label, id, expanded: false, children: vec![] }
+    }
+}
+
+///A node that is shown, the rows of a tree are its nodes flattened in drawing order
+#[derive(Debug, Clone, PartialEq)]
+struct TreeRow {
+    id: usize,
+    depth: i32,
+    ///None for a leaf, otherwise whether the node is expanded
+    toggle: Option<bool>,
+    label: String,
+}
+
+///Appends the nodes and the children of the expanded ones
+fn flatten_tree(nodes: &[TreeNode], depth: i32, rows: &mut Vec<TreeRow>) {
+    for node in nodes.iter() {
+        let toggle = Some(node.expanded).filter(|_| !node.children.is_empty());
+        rows.push(TreeRow { id: node.id, depth, toggle, label: node.label.clone() });
+
+        if node.expanded {
+            flatten_tree(&node.children, depth + 1, rows);
+        }
+    }
+}
+
+fn find_node_mut(nodes: &mut [TreeNode], id: usize) -> Option<&mut TreeNode> {
+    for node in nodes.iter_mut() {
+        if node.id == id {
+            return Some(node);
+        }
+
+        if let Some(found) = find_node_mut(&mut node.children, id) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+///Expands every ancestor of the node, true when the node is in the tree
+fn expand_path(nodes: &mut [TreeNode], id: usize) -> bool {
+    for node in nodes.iter_mut() {
+        if node.id == id {
+            return true;
+        }
+
+        if expand_path(&mut node.children, id) {
+            node.expanded = true;
+            return true;
+        }
+    }
+
+    false
+}
+
+fn expanded_ids(nodes: &[TreeNode], ids: &mut Vec<usize>) {
+    for node in nodes.iter().filter(|node| node.expanded) {
+        ids.push(node.id);
+        expanded_ids(&node.children, ids);
+    }
+}
+
+///Rows of expandable nodes, indented by depth. Clicking the marker in front of a node expands or collapses it,
+///clicking the label selects the node.
+pub struct TreeView {
+    view: ViewObject,
+    row_vao: i32,
+    row_vbo: i32,
+    text_vao: i32,
+    text_vbo: i32,
+    ///Measures the rows
+    font: Rc<FontFace>,
+    nodes: Vec<TreeNode>,
+    ///The shown nodes as of the last arrange
+    rows: Vec<TreeRow>,
+    row_height: i32,
+    selected: Option<usize>,
+    ///Node picked since the last dispatch
+    picked: Option<usize>,
+    pub on_node_selected: Option<Box<dyn FnMut(&mut UiContext, &mut TreeView, usize)>>,
+}
+
+impl TreeView {
+    pub fn new(id: Box<str>, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        unsafe {
+            let (row_vao, row_vbo) = initialize_background_buffers();
+            let (text_vao, text_vbo) = initialize_text_buffers();
+
+            Self {
+                view: ViewObject::new(id, position, Some(ViewDimens::zerod()), padding, 1.0, None, None),
+                row_vao,
+                row_vbo,
+                text_vao,
+                text_vbo,
+                font: resources.font_face.clone(),
+                nodes: vec![],
+                rows: vec![],
+                row_height: 0,
+                selected: None,
+                picked: None,
+                on_node_selected: None,
+            }
+        }
+    }
+
+    ///Replaces the nodes, nodes that were expanded stay expanded when their id is still there
+    pub fn set_nodes(&mut self, mut nodes: Vec<TreeNode>) {
+        let mut expanded = vec![];
+        expanded_ids(&self.nodes, &mut expanded);
+
+        for id in expanded {
+            if let Some(node) = find_node_mut(&mut nodes, id) {
+                node.expanded = true;
+            }
+        }
+
+        self.nodes = nodes;
+        self.arrange();
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    ///Selects without calling on_node_selected, for syncing with a selection made elsewhere
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+    }
+
+    pub fn set_expanded(&mut self, id: usize, expanded: bool) {
+        if let Some(node) = find_node_mut(&mut self.nodes, id) {
+            node.expanded = expanded;
+            self.arrange();
+        }
+    }
+
+    ///Expands the node's ancestors so it's shown, false when no node has the id
+    pub fn expand_to(&mut self, id: usize) -> bool {
+        let found = expand_path(&mut self.nodes, id);
+        self.arrange();
+        found
+    }
+
+    ///Flattens the shown nodes and sizes the view to them
+    fn arrange(&mut self) {
+        let font_face = &self.font;
+
+        self.rows.clear();
+        flatten_tree(&self.nodes, 0, &mut self.rows);
+        self.row_height = font_face.font_size as i32;
+
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.depth * TREE_INDENT + self.row_height + get_the_length_of_text(&row.label, font_face) as i32)
+            .max()
+            .unwrap_or(0);
+
+        self.view.size = Some(ViewDimens::new(width, self.row_height * self.rows.len() as i32));
+    }
+
+    ///Left edge of the row's marker, the label starts a row height after it
+    fn marker_x(&self, row: &TreeRow) -> i32 {
+        self.view.position.x + self.view.padding + row.depth * TREE_INDENT
+    }
+
+    fn rows_position(&self) -> ViewPosition {
+        ViewPosition::new(self.view.position.x, self.view.position.y + self.view.padding)
+    }
+}
+
+impl View for TreeView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if let Some(id) = self.picked.take() {
+            fire_callback!(self, on_node_selected, ctx, id);
+        }
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update_layout(&mut self) {
+        self.arrange();
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        let width = self.get_view_dimensions().unwrap_or(ViewDimens::zerod()).x;
+        let top = self.rows_position().y;
+
+        for (index, row) in self.rows.iter().enumerate() {
+            let y = top + self.row_height * index as i32;
+            let x = self.marker_x(row);
+
+            unsafe {
+                if let Some(expanded) = row.toggle {
+                    let marker = expand_marker(expanded, &engine.font_face).to_string();
+                    draw_text(self.text_vao as u32, self.text_vbo as u32, &engine, &engine.font_face, &engine.ui.text_shader, &marker, x as f32, y as f32, 1.0, &Vector3::new(1.0, 1.0, 1.0));
+                }
+
+                draw_text(
+                    self.text_vao as u32,
+                    self.text_vbo as u32,
+                    &engine,
+                    &engine.font_face,
+                    &engine.ui.text_shader,
+                    &row.label,
+                    (x + self.row_height) as f32,
+                    y as f32,
+                    1.0,
+                    &Vector3::new(1.0, 1.0, 1.0),
+                );
+
+                if self.selected == Some(row.id) {
+                    let (position, size) = ViewRect::at(self.view.position.x as f32, y as f32, width as f32, self.row_height as f32).quad_args();
+                    draw_quad_with_default_shader(engine, self.row_vao as u32, self.row_vbo as u32, quad_depth(self.view.z_index, QuadLayer::Detail), position, size, &TREE_SELECTED_COLOR);
+                }
+            }
+        }
+
+        let rect = ViewRect::new(self.view.position, self.get_view_dimensions().unwrap_or(ViewDimens::zerod()), 0);
+        unsafe { self.view.draw_background(engine, QuadLayer::Background, rect) };
+        Ok(())
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        if !self.bounds().contains(&click.cords) {
+            return false;
+        }
+
+        if !click.pressed(MouseButton::Button1) {
+            return true;
+        }
+
+        let width = self.get_view_dimensions().unwrap_or(ViewDimens::zerod()).x;
+        let row = ViewDimens::new(width, self.row_height);
+        let row = match dropdown_option_at(&click.cords, self.rows_position(), row, self.rows.len()) {
+            Some(index) => self.rows[index].clone(),
+            None => return true,
+        };
+
+        //Note(teddy) The marker is a row height wide, clicks on it toggle and clicks past it select
+        let marker_x = self.marker_x(&row) as f32;
+        let on_marker = click.cords.x >= marker_x && click.cords.x < marker_x + self.row_height as f32;
+
+        match row.toggle {
+            Some(expanded) if on_marker => self.set_expanded(row.id, !expanded),
+            _ => {
+                self.selected = Some(row.id);
+                self.picked = Some(row.id);
+            }
+        }
+
+        true
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageScaling {
+    ///Fills the view, the texture is squashed to its size
+    Stretch,
+    ///Largest size that fits the view at the texture's aspect ratio, centered
+    PreserveAspect,
+}
+
+///Offset from the view's top left and the size the texture is drawn at
+fn fit_image(native: ViewDimens, bounds: ViewDimens, scaling: ImageScaling) -> (ViewPosition, ViewDimens) {
+    if scaling == ImageScaling::Stretch || native.x <= 0 || native.y <= 0 {
+        return (ViewPosition::zerod(), bounds);
+    }
+
+    let scale = (bounds.x as f32 / native.x as f32).min(bounds.y as f32 / native.y as f32);
+    let size = ViewDimens::new((native.x as f32 * scale).round() as i32, (native.y as f32 * scale).round() as i32);
+
+    (ViewPosition::new((bounds.x - size.x) / 2, (bounds.y - size.y) / 2), size)
+}
+
+unsafe fn texture_dimensions(texture: u32) -> ViewDimens {
+    let (mut width, mut height) = (0, 0);
+
+    gl::BindTexture(gl::TEXTURE_2D, texture);
+    gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut width);
+    gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut height);
+    gl::BindTexture(gl::TEXTURE_2D, 0);
+
+    ViewDimens::new(width, height)
+}
+
+///Draws a gl texture at its own size, the texture's native size only matters for PreserveAspect.
+//TODO(teddy) Take an asset name once Resources loads textures
+pub struct ImageView {
+    view: ViewObject,
+    image_vao: i32,
+    image_vbo: i32,
+    texture: u32,
+    native_size: ViewDimens,
+    pub scaling: ImageScaling,
+    ///For textures uploaded top row first, framebuffer textures are already the right way up
+    pub flip_vertical: bool,
+    clicked: bool,
+    pub on_click: Option<Box<dyn FnMut(&mut UiContext, &mut ImageView)>>,
+}
+
+impl ImageView {
+    pub fn new(id: Box<str>, texture: u32, size: ViewDimens, position: ViewPosition, padding: i32) -> Self {
+        unsafe {
+            let (image_vao, image_vbo) = initialize_image_buffers();
+
+            Self {
+                view: ViewObject::new(id, position, Some(size), padding, 1.0, Some([0.1, 0.1, 0.1, 1.0]), None),
+                image_vao,
+                image_vbo,
+                texture,
+                native_size: texture_dimensions(texture),
+                scaling: ImageScaling::PreserveAspect,
+                flip_vertical: false,
+                clicked: false,
+                on_click: None,
+            }
+        }
+    }
+}
+
+impl View for ImageView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let (offset, image_size) = fit_image(self.native_size, size, self.scaling);
+        let image_position = ViewPosition::new(
+            self.view.position.x + self.view.padding + offset.x,
+            self.view.position.y + self.view.padding + offset.y,
+        );
+        let (quad_position, quad_size) = ViewRect::new(image_position, image_size, 0).quad_args();
+
+        unsafe {
+            draw_textured_quad(
+                engine,
+                self.image_vao as u32,
+                self.image_vbo as u32,
+                self.texture,
+                quad_depth(self.view.z_index, QuadLayer::Detail),
+                quad_position,
+                quad_size,
+                self.flip_vertical,
+            );
+
+            self.view.draw_background(engine, QuadLayer::Background, ViewRect::new(self.view.position, size, self.view.padding));
+        }
+
+        Ok(())
+    }
+
+    fn handle_button_click(&mut self, _engine: &Engine, click: &ClickInfo) -> bool {
+        let inside = does_cursor_intersect(
+            &click.cords,
+            self.view.position,
+            self.view.size.unwrap_or(ViewDimens::zerod()),
+            self.view.padding,
+        );
+
+        if inside && click.pressed(MouseButton::Button1) {
+            self.clicked = true;
+        }
+
+        inside
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if std::mem::take(&mut self.clicked) {
+            fire_callback!(self, on_click, ctx);
+        }
+    }
+
+    fn activate(&mut self) -> bool {
+        self.clicked = true;
+        true
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if id == self.get_id() {
+            Some(Rc::new(self))
+        } else {
+            None
+        }
+    }
+}
+
+///Screen space rect of a view, position to position + size + padding on both sides.
+///Drawing and hit testing both go through this so what is clicked is what is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewRect {
+    pub min: Cords<f32>,
+    pub max: Cords<f32>,
+}
+
+impl ViewRect {
+    pub fn new(position: ViewPosition, size: ViewDimens, padding: i32) -> Self {
+        let min = Cords { x: position.x as f32, y: position.y as f32 };
+        let max = Cords {
+            x: min.x + (size.x + (padding << 1)) as f32,
+            y: min.y + (size.y + (padding << 1)) as f32,
+        };
+
+        Self { min, max }
+    }
+
+    ///A rect from its top left corner, for the parts of a view drawn at their own place
+    pub fn at(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { min: Cords { x, y }, max: Cords { x: x + width, y: y + height } }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    ///The overlap of both rects, empty rects keep a width or height of 0
+    pub fn intersect(&self, other: &ViewRect) -> ViewRect {
+        let min = Cords { x: self.min.x.max(other.min.x), y: self.min.y.max(other.min.y) };
+        let max = Cords { x: self.max.x.min(other.max.x).max(min.x), y: self.max.y.min(other.max.y).max(min.y) };
+
+        Self { min, max }
+    }
+
+    ///False only when the rects are fully apart, touching edges and empty rects inside still count
+    pub fn overlaps(&self, other: &ViewRect) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    ///Min edges are inside, max edges belong to the next view over
+    pub fn contains(&self, cords: &Cords<f32>) -> bool {
+        (cords.x >= self.min.x && cords.x < self.max.x) && (cords.y >= self.min.y && cords.y < self.max.y)
+    }
+
+    ///Position and (height, width) for draw_quad_with_default_shader.
+    //Note(teddy) The quad grows upwards from the position it's given so it gets the bottom edge
+    pub fn quad_args(&self) -> ((f32, f32), (f32, f32)) {
+        ((self.min.x, self.max.y), (self.height(), self.width()))
+    }
+}
+
+fn does_cursor_intersect(
+    cords: &Cords<f32>,
+    position: ViewDimens,
+    size: ViewDimens,
+    padding: i32,
+) -> bool {
+    ViewRect::new(position, size, padding).contains(cords)
+}
+
+///Width of the text, or of any slice of it, in pixels
+fn get_the_length_of_text(text: &str, font_face: &FontFace) -> u32 {
+    font_face.measure_text(text)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextOverflow {
+    ///Breaks on spaces into as many lines as needed, words wider than the limit are broken anywhere
+    Wrap,
+    ///Keeps one line and cuts it off with an ellipsis
+    Ellipsis,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    fn factor(self) -> f32 {
+        match self {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => 0.5,
+            TextAlign::Right => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl VerticalAlign {
+    fn factor(self) -> f32 {
+        match self {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Center => 0.5,
+            VerticalAlign::Bottom => 1.0,
+        }
+    }
+}
+
+///How far content is pushed into the available space, factor 0 is flush with the start and 1 with the end
+fn align_offset(available: f32, content: f32, factor: f32) -> f32 {
+    ((available - content) * factor).max(0.0)
+}
+
+///Size a text view draws at inside the space its container assigned, padding excluded.
+///Only an axis that is aligned away from the start grows to fill the space, Left and Top keep the text's own size.
+fn aligned_size(size: ViewDimens, assigned: ViewDimens, padding: i32, align: TextAlign, vertical_align: VerticalAlign) -> ViewDimens {
+    let inner = ViewDimens::new(assigned.x - (padding << 1), assigned.y - (padding << 1));
+
+    ViewDimens::new(
+        if align == TextAlign::Left { size.x } else { size.x.max(inner.x) },
+        if vertical_align == VerticalAlign::Top { size.y } else { size.y.max(inner.y) },
+    )
+}
+
+///The lines a TextView draws, the text as one line when there is no max width
+fn layout_text(text: &str, max_width: Option<i32>, overflow: TextOverflow, font_face: &FontFace) -> Vec<String> {
+    let max_width = match max_width {
+        Some(max_width) => max_width.max(0) as u32,
+        None => return vec![text.to_string()],
+    };
+    let fits = |line: &str| get_the_length_of_text(line, font_face) <= max_width;
+
+    if overflow == TextOverflow::Ellipsis {
+        if fits(text) {
+            return vec![text.to_string()];
+        }
+
+        //Note(teddy) Fall back to dots when the font has no ellipsis glyph
+        let ellipsis = if font_face.has_glyph('\u{2026}') { "\u{2026}" } else { "..." };
+        let mut line = String::new();
+        for c in text.chars() {
+            if !fits(&format!("{}{}{}", line, c, ellipsis)) {
+                break;
+            }
+            line.push(c);
+        }
+
+        return vec![format!("{}{}", line.trim_end(), ellipsis)];
+    }
+
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if fits(&candidate) {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            for c in word.chars() {
+                line.push(c);
+                if !fits(&line) && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+///Name, vertex and fragment source of each shader the ui draws with
+pub fn ui_shader_sources() -> [(String, &'static str, &'static str); 3] {
+    [
+        (font_shader!(), "font_vert.glsl", "font_frag.glsl"),
+        (quad_shader!(), "ui_quad_vert.glsl", "ui_quad_frag.glsl"),
+        (image_shader!(), "ui_image_vert.glsl", "ui_image_frag.glsl"),
+    ]
+}
+
+///Create framebuffer
+/// Create shader id
+pub fn init_ui(engine: &mut Engine, world: &mut World) -> UIResult {
+    let mut fbo: u32 = 0;
+
+    unsafe {
+        engine.ui_render_object = Some(FrameRenderObject::new(engine.framebuffer_size, true));
+    }
+
+    for (name, vertex, fragment) in ui_shader_sources().iter() {
+        let _ = world.add_resource(AssetSource::Shader(name.clone(), vertex.to_string(), fragment.to_string(), None));
+    }
+
+    let shader_container_ref = &world.resources.read().unwrap().shaders;
+
+    loop {
+        let programs: Option<Vec<u32>> =
+            ui_shader_sources().iter().map(|(name, _, _)| shader_container_ref.get(name).and_then(|program| program.as_ref().map(|program| program.id))).collect();
+
+        if let Some(programs) = programs {
+            for ((name, _, _), program) in ui_shader_sources().iter().zip(programs) {
+                engine.ui.set_shader(name, program);
+            }
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn propagate_cursor_pos_to_ui(engine: *mut Engine, cords: Cords<f32>) {
+    unsafe {
+        let tree = (&mut *engine).get_ui_tree().unwrap();
+        if let Some(drag) = &mut tree.drag {
+            drag.move_to(cords);
+        }
+
+        //Note(teddy) Under a modal the rest of the ui is told the cursor left so nothing stays hovered
+        let root_cords = if tree.has_modal() { OUTSIDE_CORDS } else { cords };
+        if let Some(view) = &mut tree.root {
+            view.receive_cursor_cords(&mut *engine, root_cords);
+        }
+
+        if let Some(modal) = tree.modals.last_mut() {
+            modal.receive_cursor_cords(&mut *engine, cords);
+        }
+
+        if let Some(menu) = tree.context_menu.as_mut().filter(|menu| !menu.closed) {
+            menu.view.receive_cursor_cords(&mut *engine, cords);
+        }
+
+        let hovered = match tree.input_root() {
+            Some(view) => view.tooltip_at(&cords).map(|(id, text)| (Box::<str>::from(id), text.to_string())),
+            None => None,
+        };
+        tree.tooltip.hover(hovered.as_ref().map(|(id, text)| (&**id, text.as_str())), cords);
+    }
+}
+
+///Returns true when a view took the click, the engine then doesn't cast a picking ray through it.
+///at is when the engine saw the button go down, presses close together count as a double click.
+pub fn propagate_button_click(
+    engine: *mut Engine,
+    button: MouseButton,
+    action: Action,
+    at: Instant,
+    cords: Cords<f32>,
+    event_manager: &mut EventManager,
+) -> bool {
+    let mut result = false;
+    let eng_ref = unsafe { engine.as_mut().unwrap() };
+    let ref_for_view = unsafe { engine.as_mut().unwrap() };
+
+    let tree = eng_ref.get_ui_tree().unwrap();
+    //Note(teddy) A modal takes every click, even the ones that miss it, so none reach the scene
+    let modal_open = tree.has_modal();
+
+    let click = match action {
+        Action::Release => ClickInfo::release(button, cords),
+        _ => tree.count_press(button, cords, at),
+    };
+
+    //Note(teddy) An open context menu takes the next press wherever it lands, only one on a row runs an action
+    if let Some(menu) = tree.context_menu.as_mut().filter(|menu| !menu.closed) {
+        if !click.is_release() {
+            menu.view.handle_button_click(ref_for_view, &click);
+            menu.closed = true;
+            tree.swallowed_release = Some(click.button);
+        }
+
+        tree.tooltip.dismiss();
+        return true;
+    }
+
+    if click.is_release() && tree.swallowed_release == Some(click.button) {
+        tree.swallowed_release = None;
+        return true;
+    }
+
+    //Note(teddy) Draggable views get their click on release, a press that turns into a drag never clicks them
+    let button1 = click.button == MouseButton::Button1;
+    match tree.drag.take() {
+        None if click.pressed(MouseButton::Button1) && tree.press_draggable(&click) => {
+            tree.tooltip.dismiss();
+            return true;
+        }
+        Some(drag) if !(button1 && click.is_release()) => tree.drag = Some(drag),
+        Some(drag) if drag.started => {
+            if let Some(event) = tree.release_drag(drag, cords) {
+                event_manager.add_engine_event(Event::new(EventType::Ui(event)));
+            }
+            return true;
+        }
+        Some(drag) => {
+            if let Some(view) = tree.input_root() {
+                view.handle_button_click(ref_for_view, &drag.press);
+            }
+        }
+        None => (),
+    }
+
+    if let Some(view) = tree.input_root() {
+        result = view.handle_button_click(ref_for_view, &click) || modal_open;
+
+        //Note(teddy) Clicking outside every focusable view clears the focus
+        if click.pressed(MouseButton::Button1) {
+            let target = view.focusable_at(&cords);
+            tree.focus(target);
+        }
+    }
+
+    if !result && !click.is_release() {
+        event_manager.add_engine_event(Event::new(EventType::Ui(UiEvent::ClickedWorld { button, cords })));
+    }
+
+    tree.tooltip.dismiss();
+    result
+}
+
+///Returns true when the UI took the key, the engine then keeps it out of pressed_keys.
+///Tab cycles focus and Escape clears it, every other key goes to the focused view.
+pub fn propagate_key_stroke(engine: *mut Engine, key: glfw::Key) -> bool {
+    let eng_ref = unsafe { engine.as_mut().unwrap() };
+    let ref_for_view = unsafe { engine.as_mut().unwrap() };
+
+    let tree = match eng_ref.get_ui_tree() {
+        Some(tree) => tree,
+        None => return false,
+    };
+
+    if key == Key::Escape && tree.has_context_menu() {
+        tree.close_context_menu();
+        return true;
+    }
+
+    //Note(teddy) The modal sees every key first, Escape dismisses it and nothing reaches the editor under it
+    if let Some(modal) = tree.modals.last_mut() {
+        let taken = match tree.focused_view.clone().and_then(|id| modal.get_element_by_id(&id)) {
+            Some(mut view) => Rc::get_mut(&mut view).unwrap().receive_key(ref_for_view, key),
+            None => false,
+        };
+
+        if !taken {
+            modal.receive_key(ref_for_view, key);
+        }
+
+        return true;
+    }
+
+    match (key, tree.focused_view.clone()) {
+        (Key::Tab, _) => {
+            tree.focus_next();
+            tree.focused_view.is_some()
+        }
+        (Key::Escape, Some(_)) => {
+            tree.focus(None);
+            true
+        }
+        (key, Some(id)) => match tree.find_element(&id) {
+            Some(mut view) => Rc::get_mut(&mut view).unwrap().receive_key(ref_for_view, key),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+///Returns true when a scroll view under the cursor took the wheel
+pub fn propagate_scroll(engine: *mut Engine, cords: Cords<f32>, delta: f32) -> bool {
+    let eng_ref = unsafe { engine.as_mut().unwrap() };
+    let ref_for_view = unsafe { engine.as_mut().unwrap() };
+
+    match eng_ref.get_ui_tree().and_then(|tree| tree.input_root()) {
+        Some(view) => view.receive_scroll(ref_for_view, cords, delta),
+        None => false,
+    }
+}
+
+pub fn propagate_char(engine: *mut Engine, c: char) -> bool {
+    let eng_ref = unsafe { engine.as_mut().unwrap() };
+    let ref_for_view = unsafe { engine.as_mut().unwrap() };
+
+    let tree = match eng_ref.get_ui_tree() {
+        Some(tree) => tree,
+        None => return false,
+    };
+
+    match tree.focused_view.clone() {
+        Some(id) => match tree.find_element(&id) {
+            Some(mut view) => Rc::get_mut(&mut view).unwrap().receive_char(ref_for_view, c),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margin {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+impl Margin {
+    pub fn new(top: i32, right: i32, bottom: i32, left: i32) -> Self {
+        Self { top, right, bottom, left }
+    }
+
+    pub fn all(margin: i32) -> Self {
+        Self::new(margin, margin, margin, margin)
+    }
+
+    pub fn zerod() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+    ///Against the left edge, as tall as the parent
+    StretchVertical,
+    ///Against the top edge, as wide as the parent
+    StretchHorizontal,
+    ///Covers the whole parent
+    Fill,
+}
+
+///Position and outer size of an anchored view inside its parent's rect.
+///The size only changes on the axes a stretch anchor covers.
+fn resolve_anchor(anchor: Anchor, offset: ViewPosition, size: ViewDimens, parent: ViewRect) -> (ViewPosition, ViewDimens) {
+    let (left, top) = (parent.min.x as i32, parent.min.y as i32);
+    let (right, bottom) = (parent.max.x as i32, parent.max.y as i32);
+    let stretched_width = right - left - (offset.x << 1);
+    let stretched_height = bottom - top - (offset.y << 1);
+
+    match anchor {
+        Anchor::TopLeft => (ViewPosition::new(left + offset.x, top + offset.y), size),
+        Anchor::TopRight => (ViewPosition::new(right - offset.x - size.x, top + offset.y), size),
+        Anchor::BottomLeft => (ViewPosition::new(left + offset.x, bottom - offset.y - size.y), size),
+        Anchor::BottomRight => (ViewPosition::new(right - offset.x - size.x, bottom - offset.y - size.y), size),
+        Anchor::Center => (
+            ViewPosition::new(
+                left + (right - left - size.x) / 2 + offset.x,
+                top + (bottom - top - size.y) / 2 + offset.y,
+            ),
+            size,
+        ),
+        Anchor::StretchVertical => (
+            ViewPosition::new(left + offset.x, top + offset.y),
+            ViewDimens::new(size.x, stretched_height),
+        ),
+        Anchor::StretchHorizontal => (
+            ViewPosition::new(left + offset.x, top + offset.y),
+            ViewDimens::new(stretched_width, size.y),
+        ),
+        Anchor::Fill => (
+            ViewPosition::new(left + offset.x, top + offset.y),
+            ViewDimens::new(stretched_width, stretched_height),
+        ),
+    }
+}
+
+//Note(teddy) Free function so containers can run the default layout for themselves before recursing
+fn apply_anchor<V: View + ?Sized>(view: &mut V, parent: ViewRect) {
+    let anchor = match view.get_view_object().anchor {
+        Some(anchor) => anchor,
+        None => return,
+    };
+
+    let bounds = view.bounds();
+    let size = ViewDimens::new(bounds.width() as i32, bounds.height() as i32);
+    let (position, stretched) = resolve_anchor(anchor, view.get_view_object().anchor_offset, size, parent);
+    view.set_position(position);
+
+    if stretched != size {
+        let object = view.get_view_object_mut();
+        let padding = object.padding << 1;
+        let inner = ViewDimens::new(stretched.x - padding, stretched.y - padding);
+
+        //Note(teddy) The stretched axes follow the parent both ways so the view shrinks with the window too,
+        //the other axis keeps whatever the view had
+        let (along_x, along_y) = (stretched.x != size.x, stretched.y != size.y);
+        let resolve = |current: ViewDimens| {
+            ViewDimens::new(if along_x { inner.x } else { current.x }, if along_y { inner.y } else { current.y })
+        };
+
+        object.min_size = Some(resolve(object.min_size.unwrap_or(ViewDimens::zerod())));
+        object.size = object.size.map(resolve);
+    }
+}
+
+fn own_object<'a, V: View + ?Sized>(view: &'a V, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&'a ViewObject> {
+    let object = view.get_view_object();
+    match accept(object) && view.bounds().contains(cords) {
+        true => Some(object),
+        false => None,
+    }
+}
+
+fn is_anchored(view: &Box<dyn View>) -> bool {
+    view.get_view_object().anchor.is_some()
+}
+
+///Children the container stacks, anchored and hidden ones take no space
+fn in_flow(view: &Box<dyn View>) -> bool {
+    view.is_visible() && !is_anchored(view)
+}
+
+fn shown(children: &[Box<dyn View>]) -> impl Iterator<Item = &Box<dyn View>> {
+    children.iter().filter(|child| child.is_visible())
+}
+
+fn shown_mut(children: &mut [Box<dyn View>]) -> impl Iterator<Item = &mut Box<dyn View>> {
+    children.iter_mut().filter(|child| child.is_visible())
+}
+
+///Records the size of every child, None for hidden ones, true when any changed since the last call
+fn children_changed(children: &[Box<dyn View>], sizes: &mut Vec<Option<ViewDimens>>) -> bool {
+    let now: Vec<Option<ViewDimens>> = children
+        .iter()
+        .map(|child| Some(child.get_view_dimensions().unwrap_or(ViewDimens::zerod())).filter(|_| child.is_visible()))
+        .collect();
+    if now == *sizes {
+        return false;
+    }
+
+    *sizes = now;
+    true
+}
+
+///Gives a child its place and the size its container allocated, a new size fires on_resize in the next dispatch
+fn allocate(view: &mut Box<dyn View>, position: ViewPosition, dimensions: ViewDimens) {
+    view.set_position(position);
+    view.update_dimensions(dimensions);
+
+    let object = view.get_view_object_mut();
+    if object.allocated != Some(dimensions) {
+        object.allocated = Some(dimensions);
+        object.resized = true;
+    }
+}
+
+///Fires on_resize for the children that got a new size, then lets them dispatch their own callbacks
+fn dispatch_children(children: &mut [Box<dyn View>], ctx: &mut UiContext) {
+    for view in children.iter_mut() {
+        let object = view.get_view_object_mut();
+        if std::mem::take(&mut object.resized) {
+            if let (Some(mut on_resize), Some(size)) = (object.on_resize.take(), object.allocated) {
+                on_resize(ctx, size);
+                object.on_resize.get_or_insert(on_resize);
+            }
+        }
+
+        view.dispatch(ctx);
+    }
+}
+
+///Lays out every child against the parent's rect, anchored children are placed and the rest pass it down
+fn layout_children(children: &mut [Box<dyn View>], parent: ViewRect) {
+    for child in children.iter_mut() {
+        child.layout(parent);
+    }
+}
+
+///Where each child goes when stacked from origin, and the size of the whole stack.
+///Children are given as their dimensions and margin, spacing only goes between children.
+fn stack_children(
+    origin: ViewPosition,
+    orientation: Orientation,
+    spacing: i32,
+    children: &[(ViewDimens, Margin)],
+) -> (Vec<ViewPosition>, ViewDimens) {
+    let mut positions = Vec::with_capacity(children.len());
+    let mut along = 0;
+    let mut across = 0;
+
+    for (i, (dimensions, margin)) in children.iter().enumerate() {
+        if i > 0 {
+            along += spacing;
+        }
+
+        match orientation {
+            Orientation::Vertical => {
+                along += margin.top;
+                positions.push(ViewPosition::new(origin.x + margin.left, origin.y + along));
+                along += dimensions.y + margin.bottom;
+                across = across.max(margin.left + dimensions.x + margin.right);
+            }
+
+            Orientation::Horizontal => {
+                along += margin.left;
+                positions.push(ViewPosition::new(origin.x + along, origin.y + margin.top));
+                along += dimensions.x + margin.right;
+                across = across.max(margin.top + dimensions.y + margin.bottom);
+            }
+        }
+    }
+
+    let size = match orientation {
+        Orientation::Vertical => ViewDimens::new(across, along),
+        Orientation::Horizontal => ViewDimens::new(along, across),
+    };
+
+    (positions, size)
+}
+
+///Dimensions and margin of every child the container stacks, anchored children place themselves
+fn child_extents(children: &[Box<dyn View>]) -> Vec<(ViewDimens, Margin)> {
+    children
+        .iter()
+        .filter(|child| in_flow(child))
+        .map(|child| (child.get_view_dimensions().unwrap_or(ViewDimens::zerod()), child.get_margin()))
+        .collect()
+}
+
+///Row highlight of a selectable list
+const SELECTION_COLOR: [f32; 4] = [0.2, 0.35, 0.6, 1.0];
+
+///The row after current for a navigation key, rows are the indices of the shown children.
+///Up and Down step and wrap past the ends when asked to, Home and End jump, other keys change nothing.
+fn step_selection(rows: &[usize], current: Option<usize>, key: Key, wrap: bool) -> Option<usize> {
+    let last = rows.len().checked_sub(1)?;
+    let position = current.and_then(|current| rows.iter().position(|&row| row == current));
+
+    let next = match (key, position) {
+        (Key::Home, _) | (Key::Down, None) => 0,
+        (Key::End, _) | (Key::Up, None) => last,
+        (Key::Down, Some(position)) if position == last => {
+            if wrap {
+                0
+            } else {
+                last
+            }
+        }
+        (Key::Down, Some(position)) => position + 1,
+        (Key::Up, Some(0)) => {
+            if wrap {
+                last
+            } else {
+                0
+            }
+        }
+        (Key::Up, Some(position)) => position - 1,
+        _ => return current,
+    };
+
+    Some(rows[next])
+}
+
+///Keyboard selection of a container's rows. The list takes focus like an input, Up/Down/Home/End move
+///the highlight and Enter activates the selected row.
+pub struct ListSelection {
+    pub selectable: bool,
+    ///Up on the first row goes to the last and Down on the last to the first
+    pub wrap: bool,
+    ///Drawn in place of the selected row's background
+    pub highlight: [f32; 4],
+    ///Index into the container's children
+    selected_index: Option<usize>,
+    changed: bool,
+    ///Runs in the dispatch phase after the selection moved, with the new index
+    pub on_selection_changed: Option<Box<dyn FnMut(&mut UiContext, usize)>>,
+}
+
+impl Default for ListSelection {
+    fn default() -> Self {
+        Self { selectable: false, wrap: false, highlight: SELECTION_COLOR, selected_index: None, changed: false, on_selection_changed: None }
+    }
+}
+
+impl ListSelection {
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected_index
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        if self.selected_index != index {
+            self.selected_index = index;
+            self.changed = index.is_some();
+        }
+    }
+
+    ///Moves the selection or activates the selected row, true when the key was used
+    fn receive_key(&mut self, children: &mut [Box<dyn View>], key: Key) -> bool {
+        if !self.selectable {
+            return false;
+        }
+
+        if key == Key::Enter || key == Key::KpEnter {
+            return match self.selected_index.and_then(|index| children.get_mut(index)) {
+                Some(row) => row.activate(),
+                None => false,
+            };
+        }
+
+        let rows: Vec<usize> = (0..children.len()).filter(|&index| children[index].is_visible()).collect();
+        match step_selection(&rows, self.selected_index, key, self.wrap) {
+            Some(next) if matches!(key, Key::Up | Key::Down | Key::Home | Key::End) => {
+                self.select(Some(next));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    ///A left click on a row selects it, so the keys carry on from there
+    fn receive_click(&mut self, children: &[Box<dyn View>], click: &ClickInfo) {
+        if self.selectable && click.pressed(MouseButton::Button1) {
+            if let Some(index) = children.iter().position(|child| child.is_visible() && child.bounds().contains(&click.cords)) {
+                self.select(Some(index));
+            }
+        }
+    }
+
+    fn highlight_for(&self, index: usize) -> Option<[f32; 4]> {
+        Some(self.highlight).filter(|_| self.selectable && self.selected_index == Some(index))
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        if !std::mem::take(&mut self.changed) {
+            return;
+        }
+
+        if let (Some(mut callback), Some(index)) = (self.on_selection_changed.take(), self.selected_index) {
+            callback(ctx, index);
+            self.on_selection_changed.get_or_insert(callback);
+        }
+    }
+}
+
+///Draws a child, with its background swapped for the highlight while it's the selected row
+fn draw_row(view: &mut Box<dyn View>, engine: &Engine, highlight: Option<[f32; 4]>) -> UIResult {
+    let background = view.get_view_object().background;
+    if highlight.is_some() {
+        view.set_background(highlight);
+    }
+
+    let result = draw_child(view, engine);
+    view.set_background(background);
+    result
+}
+
+pub struct SimpleUIContainer {
+    children: Vec<Box<dyn View>>,
+    orientation: Orientation,
+    ///Gap between children, on top of their margins
+    spacing: i32,
+    ///Which children were visible at the last update, the size is recalculated when it changes
+    ///Child sizes as of the last recalculate_dimensions, hidden children are None
+    child_sizes: Vec<Option<ViewDimens>>,
+    ///Children are cut off at the container's edges, both when drawn and for the cursor
+    clip_children: bool,
+    ///Space the parent gave it, padding included. It fills it but still reports its own size to the parent.
+    assigned: Option<ViewDimens>,
+    view: ViewObject,
+    pub selection: ListSelection,
+    pub on_hover: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    pub on_mouse_leave: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    ///Cursor transitions since the last dispatch
+    hover_events: Vec<CursorEvent>,
+}
+
+impl SimpleUIContainer {
+    pub fn new(
+        id: Box<str>,
+        dimensions: Option<ViewDimens>,
+        position: ViewPosition,
+        orientation: Orientation,
+        padding: i32,
+        scale: f32,
+        clip_children: bool,
+    ) -> Self {
+        Self {
+            view: ViewObject::new(
+                id,
+                position,
+                dimensions,
+                padding,
+                scale,
+                Some([0.1, 0.1, 0.1, 1.0]),
+                None,
+            ),
+            children: vec![],
+            orientation,
+            spacing: 0,
+            child_sizes: vec![],
+            clip_children,
+            assigned: None,
+            selection: ListSelection::default(),
+            on_hover: None,
+            on_mouse_leave: None,
+            hover_events: vec![],
+        }
+    }
+
+    pub fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+        self.recalculate_dimensions();
+    }
+
+    fn recalculate_dimensions(&mut self) {
+        let (_, new_dimensions) =
+            stack_children(self.view.position, self.orientation, self.spacing, &child_extents(&self.children));
+        let min_size = self.view.min_size.unwrap_or(ViewDimens::zerod());
+
+        self.view.size = Some(ViewDimens::new(new_dimensions.x.max(min_size.x), new_dimensions.y.max(min_size.y)));
+
+        // //Flip the y for this quad
+        // if let Some(position) = self.view.position {
+        //     self.view.position = Some(ViewDimens::new(position.x, position.y));
+        // }
+    }
+
+    pub fn set_clip_children(&mut self, clip_children: bool) {
+        self.clip_children = clip_children;
+    }
+
+    ///Rows can be picked with the arrow keys once the container has focus
+    pub fn set_selectable(&mut self, selectable: bool) {
+        self.selection.selectable = selectable;
+    }
+
+    ///The size of the children, grown to the space the parent gave it
+    fn area(&self) -> ViewDimens {
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let padding = self.view.padding << 1;
+
+        match self.assigned {
+            Some(assigned) => ViewDimens::new(size.x.max(assigned.x - padding), size.y.max(assigned.y - padding)),
+            None => size,
+        }
+    }
+
+    fn clip_rect(&self) -> ViewRect {
+        ViewRect::new(self.view.position, self.area(), 0)
+    }
+
+    ///Whether the cursor can reach the children there, parts cut off by the clip can't be hovered or clicked
+    fn reaches_children(&self, cords: &Cords<f32>) -> bool {
+        !self.clip_children || self.clip_rect().contains(cords)
+    }
+
+    ///Updates the container's hover and passes the cursor on to the children under it
+    fn track_cursor(&mut self, cords: Cords<f32>, send: &mut dyn FnMut(&mut Box<dyn View>, Cords<f32>)) {
+        let was_hovered = self.is_hovered();
+        self.update_hover(&cords);
+        let inside = self.is_hovered();
+
+        //Note(teddy) The children sit inside the container, once they've been told the cursor left
+        //there's nothing new for them until it comes back or one of them holds on to it
+        if !inside && !was_hovered && !self.captures_cursor() {
+            return;
+        }
+
+        let reaches = inside && self.reaches_children(&cords);
+        for view in self.children.iter_mut() {
+            let reached = view.is_visible() && (reaches || view.captures_cursor());
+            send(view, if reached { cords } else { OUTSIDE_CORDS });
+        }
+    }
+
+    ///Resizes to the shown children and stacks them, drawn or not
+    fn arrange(&mut self) {
+        if children_changed(&self.children, &mut self.child_sizes) {
+            self.recalculate_dimensions();
+        }
+
+        //TODO(teddy) optimize this to prevent recalculations
+        let size = self.area();
+        let extents = child_extents(&self.children);
+        let (positions, _) = stack_children(self.view.position, self.orientation, self.spacing, &extents);
+
+        let stacked = self.children.iter_mut().filter(|child| in_flow(child));
+        for ((view, position), (dimensions, margin)) in stacked.zip(positions).zip(extents) {
+            //Note(teddy) Children get the container's full width, or height in a row, less their margin
+            let allocated = match self.orientation {
+                Orientation::Vertical => ViewDimens::new(size.x - margin.left - margin.right, dimensions.y),
+                Orientation::Horizontal => ViewDimens::new(dimensions.x, size.y - margin.top - margin.bottom),
+            };
+            allocate(view, position, allocated);
+        }
+    }
+}
+
+impl View for SimpleUIContainer {
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    //Note(teddy) Nested containers take the room of their children in the parent's stack
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size
+    }
+
+    fn layout(&mut self, parent: ViewRect) {
+        apply_anchor(self, parent);
+        let bounds = self.bounds();
+        layout_children(&mut self.children, bounds);
+    }
+
+    fn update_dimensions(&mut self, dimensions: ViewDimens) {
+        self.assigned = Some(dimensions);
+    }
+
+    fn bounds(&self) -> ViewRect {
+        ViewRect::new(self.view.position, self.area(), self.view.padding)
+    }
+
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        let container_position = self.view.position;
+
+        // println!(self.get_view_object().)
+        let inside = does_cursor_intersect(
+            &click.cords,
+            //self.position.unwrap_or(ViewDimens::zerod()),
+            container_position,
+            self.area(),
+            0,
+        );
+
+        if inside || self.captures_cursor() {
+            self.selection.receive_click(&self.children, click);
+            return offer_click(&mut self.children, |view| view.handle_button_click(engine, click));
+        }
+
+        false
+    }
+
+    fn update_layout(&mut self) {
+        self.children.iter_mut().for_each(|child| child.update_layout());
+        self.arrange();
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        //Note(teddy) A container that was never sized still shows up as a small square
+        let rect = ViewRect::new(self.view.position, self.view.size.map_or(ViewDimens::new(10, 10), |_| self.area()), 0);
+
+        if self.clip_children {
+            unsafe { push_clip(engine, self.clip_rect()) };
+        }
+
+        for index in draw_order(&self.children) {
+            draw_row(&mut self.children[index], engine, self.selection.highlight_for(index)).unwrap();
+        }
+
+        if self.clip_children {
+            unsafe { pop_clip(engine) };
+        }
+
+        unsafe { self.view.draw_background(engine, QuadLayer::Container, rect) };
+
+        Ok(())
+    }
+
+    fn on_cursor_enter(&mut self) {
+        queue_hover!(self, CursorEvent::Enter);
+    }
+
+    fn on_cursor_exit(&mut self) {
+        queue_hover!(self, CursorEvent::Exit);
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.track_cursor(cords, &mut |view, cords| view.receive_cursor_cords(engine, cords));
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        None
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.children.iter_mut().any(|view| view.receive_char(engine, c))
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        self.children.iter_mut().any(|view| view.receive_key(engine, key)) || self.selection.receive_key(&mut self.children, key)
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        if !self.reaches_children(&cords) {
+            return false;
+        }
+
+        shown_mut(&mut self.children).any(|view| view.receive_scroll(engine, cords, delta))
+    }
+
+    fn captures_cursor(&self) -> bool {
+        shown(&self.children).any(|view| view.captures_cursor())
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        shown_mut(&mut self.children).try_for_each(|view| view.draw_popups(engine))
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        fire_hover_callbacks!(self, ctx);
+        dispatch_children(&mut self.children, ctx);
+        self.selection.dispatch(ctx);
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn focusable(&self) -> bool {
+        self.selection.selectable
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        if self.focusable() {
+            ids.push(Box::from(self.get_id()));
+        }
+
+        for view in shown(&self.children) {
+            view.collect_focusable(ids);
+        }
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        if !self.reaches_children(cords) {
+            return None;
+        }
+
+        //Note(teddy) A selectable list takes focus when a row that doesn't take it is clicked
+        shown(&self.children)
+            .find_map(|view| view.focusable_at(cords))
+            .or_else(|| Some(Box::from(self.get_id())).filter(|_| self.focusable() && self.bounds().contains(cords)))
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        let own = self.cursor_icon().filter(|_| self.bounds().contains(cords));
+        if !self.reaches_children(cords) {
+            return own;
+        }
+
+        shown(&self.children).find_map(|view| view.cursor_icon_at(cords)).or(own)
+    }
+
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        if !self.reaches_children(cords) {
+            return own_object(self, cords, accept);
+        }
+
+        shown(&self.children).find_map(|view| view.object_at(cords, accept)).or_else(|| own_object(self, cords, accept))
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        for element in &mut self.children {
+            if let Some(el) = element.get_element_by_id(id) {
+                return Some(el);
+            }
+        }
+
+        None
+    }
+}
+
+impl ViewContainer for SimpleUIContainer {
+    fn add_child(&mut self, child: Box<dyn View>) {
+        self.children.push(child);
+        self.recalculate_dimensions();
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        find_child(&self.children, child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        find_child_mut(&mut self.children, child_id)
+    }
+
+    fn remove_child(&mut self, child_id: &str) -> UIResult {
+        if let Some(index) = self
+            .children
+            .iter()
+            .position(|child| child.get_id() == child_id)
+        {
+            self.children.remove(index);
+            Ok(())
+        } else {
+            Err(UIError::ViewNotFound)
+        }
+    }
+
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        let taken = take_from_children(&self.view.id, &mut self.children, child_id)?;
+        self.recalculate_dimensions();
+        Ok(taken)
+    }
+
+    fn insert_child(&mut self, index: usize, child: Box<dyn View>) {
+        self.children.insert(index.min(self.children.len()), child);
+        self.recalculate_dimensions();
+    }
+}
+
+///Views drawn and views skipped for being off screen in the last ui frame
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CullStats {
+    pub drawn: u32,
+    pub culled: u32,
+}
+
+///Scissor rects of the clipping views being drawn, the innermost is last.
+///Each one is already cut to the ones before it so nested clips only ever shrink.
+#[derive(Debug, Default)]
+struct ClipStack {
+    rects: Vec<ViewRect>,
+    ///The window's rect for this frame, None until the first frame starts
+    viewport: Option<ViewRect>,
+    stats: CullStats,
+}
+
+impl ClipStack {
+    const fn new() -> Self {
+        Self { rects: Vec::new(), viewport: None, stats: CullStats { drawn: 0, culled: 0 } }
+    }
+
+    fn begin_frame(&mut self, viewport: Option<ViewRect>) {
+        self.rects.clear();
+        self.viewport = viewport;
+        self.stats = CullStats::default();
+    }
+
+    ///What can still be seen of the screen, the innermost clip or else the viewport
+    fn visible_rect(&self) -> Option<ViewRect> {
+        self.rects.last().copied().or(self.viewport)
+    }
+
+    ///Whether a view with these bounds gets drawn, counting it either way
+    fn cull(&mut self, bounds: &ViewRect) -> bool {
+        let visible = self.visible_rect().map_or(true, |rect| rect.overlaps(bounds));
+
+        if visible {
+            self.stats.drawn += 1;
+        } else {
+            self.stats.culled += 1;
+        }
+
+        visible
+    }
+
+    ///Returns the rect to scissor to, the part of rect inside the current clip
+    fn push(&mut self, rect: ViewRect) -> ViewRect {
+        let clipped = match self.rects.last() {
+            Some(current) => current.intersect(&rect),
+            None => rect,
+        };
+
+        self.rects.push(clipped);
+        clipped
+    }
+
+    ///Returns the clip to go back to, None once nothing clips anymore
+    fn pop(&mut self) -> Option<ViewRect> {
+        self.rects.pop();
+        self.rects.last().copied()
+    }
+}
+
+thread_local! {
+    //Note(teddy) The ui is drawn on the gl thread only
+    static CLIP_STACK: RefCell<ClipStack> = RefCell::new(ClipStack::new());
+}
+
+//Note(teddy) glScissor wants framebuffer pixels with a bottom left origin, the ui works in ui pixels
+unsafe fn scissor(engine: &Engine, rect: ViewRect) {
+    let (x, y, width, height) = ViewportRect::new(rect.min.x, rect.min.y, rect.width(), rect.height())
+        .to_framebuffer_pixels(engine.ui_size(), engine.framebuffer_size);
+
+    gl::Enable(gl::SCISSOR_TEST);
+    gl::Scissor(x, y, width, height);
+}
+
+///Starts counting drawn and culled views again and culls against the new viewport
+fn begin_ui_frame(viewport: Option<ViewRect>) {
+    CLIP_STACK.with(|stack| stack.borrow_mut().begin_frame(viewport));
+}
+
+pub fn ui_cull_stats() -> CullStats {
+    CLIP_STACK.with(|stack| stack.borrow().stats)
+}
+
+///Whether any of the bounds are inside the last frame's viewport, views off screen can't be hit
+fn on_screen(view: &dyn View) -> bool {
+    CLIP_STACK.with(|stack| stack.borrow().viewport.map_or(true, |viewport| viewport.overlaps(&view.bounds())))
+}
+
+///Draws a child that can be seen, the layout pass already sized the ones that can't
+fn draw_child(view: &mut Box<dyn View>, engine: &Engine) -> UIResult {
+    if CLIP_STACK.with(|stack| stack.borrow_mut().cull(&view.bounds())) {
+        return view.update(engine);
+    }
+
+    Ok(())
+}
+
+///Every push_clip needs a pop_clip once the clipped views are drawn
+unsafe fn push_clip(engine: &Engine, rect: ViewRect) {
+    let clip = CLIP_STACK.with(|stack| stack.borrow_mut().push(rect));
+    scissor(engine, clip);
+}
+
+unsafe fn pop_clip(engine: &Engine) {
+    match CLIP_STACK.with(|stack| stack.borrow_mut().pop()) {
+        Some(clip) => scissor(engine, clip),
+        None => gl::Disable(gl::SCISSOR_TEST),
+    }
+}
+
+///Pixels moved per notch of the mouse wheel
+const SCROLL_STEP: f32 = 30.0;
+///Cursor position handed to hidden children so their hover state leaves
+const OUTSIDE_CORDS: Cords<f32> = Cords { x: -1.0, y: -1.0 };
+
+///Keeps the content from scrolling past its first or last child
+fn clamp_scroll(scroll: f32, content_height: i32, view_height: i32) -> f32 {
+    scroll.max(0.0).min((content_height - view_height).max(0) as f32)
+}
+
+///Scroll that shows a row whole, the row's top is measured from the top of the content
+fn scroll_to_show(scroll: f32, row_top: i32, row_height: i32, view_height: i32) -> f32 {
+    if (row_top as f32) < scroll {
+        row_top as f32
+    } else if (row_top + row_height) as f32 > scroll + view_height as f32 {
+        (row_top + row_height - view_height) as f32
+    } else {
+        scroll
+    }
+}
+
+///Whether any part of a child lies inside the visible band of the scroll view
+fn is_in_view(child_y: i32, child_height: i32, top: i32, height: i32) -> bool {
+    child_y + child_height > top && child_y < top + height
+}
+
+///Vertical container that clips its children to its size and scrolls them with the mouse wheel.
+///It grows with its children up to max_height, past that the rest is reached by scrolling.
+pub struct ScrollView {
+    children: Vec<Box<dyn View>>,
+    view: ViewObject,
+    max_height: i32,
+    ///Gap between children, on top of their margins
+    spacing: i32,
+    ///Which children were visible at the last update, the size is recalculated when it changes
+    ///Child sizes as of the last recalculate_dimensions, hidden children are None
+    child_sizes: Vec<Option<ViewDimens>>,
+    content_height: i32,
+    ///How far the content is moved up, in pixels
+    scroll: f32,
+    pub selection: ListSelection,
+    pub on_hover: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    pub on_mouse_leave: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    ///Cursor transitions since the last dispatch
+    hover_events: Vec<CursorEvent>,
+}
+
+impl ScrollView {
+    pub fn new(id: Box<str>, max_height: i32, position: ViewPosition, padding: i32) -> Self {
+        Self {
+            view: ViewObject::new(
+                id,
+                position,
+                Some(ViewDimens::zerod()),
+                padding,
+                1.0,
+                Some([0.1, 0.1, 0.1, 1.0]),
+                None,
+            ),
+            children: vec![],
+            max_height,
+            spacing: 0,
+            child_sizes: vec![],
+            content_height: 0,
+            scroll: 0.0,
+            selection: ListSelection::default(),
+            on_hover: None,
+            on_mouse_leave: None,
+            hover_events: vec![],
+        }
+    }
+
+    pub fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+        self.recalculate_dimensions();
+    }
+
+    ///Resizes to the shown children and stacks them from the scroll offset, drawn or not
+    fn arrange(&mut self) {
+        if children_changed(&self.children, &mut self.child_sizes) {
+            self.recalculate_dimensions();
+        }
+
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let extents = child_extents(&self.children);
+        let origin = ViewPosition::new(self.view.position.x, self.view.position.y - self.scroll as i32);
+        let (positions, _) = stack_children(origin, Orientation::Vertical, self.spacing, &extents);
+
+        let stacked = self.children.iter_mut().filter(|child| in_flow(child));
+        for ((view, position), (dimensions, margin)) in stacked.zip(positions).zip(extents) {
+            allocate(view, position, ViewDimens::new(size.x - margin.left - margin.right, dimensions.y));
+        }
+    }
+
+    fn recalculate_dimensions(&mut self) {
+        let (_, mut size) =
+            stack_children(ViewPosition::zerod(), Orientation::Vertical, self.spacing, &child_extents(&self.children));
+
+        self.content_height = size.y;
+        size.y = std::cmp::min(size.y, self.max_height);
+        self.view.size = Some(size);
+        self.scroll = clamp_scroll(self.scroll, self.content_height, size.y);
+    }
+
+    fn contains(&self, cords: &Cords<f32>) -> bool {
+        does_cursor_intersect(cords, self.view.position, self.view.size.unwrap_or(ViewDimens::zerod()), 0)
+    }
+
+    ///Rows can be picked with the arrow keys once the scroll view has focus, the selected row is scrolled into view
+    pub fn set_selectable(&mut self, selectable: bool) {
+        self.selection.selectable = selectable;
+    }
+
+    fn scroll_to_selection(&mut self) {
+        let row = match self.selection.selected_index().and_then(|index| self.children.get(index)) {
+            Some(row) => row,
+            None => return,
+        };
+
+        //Note(teddy) Rows sit where the last arrange put them, shifted up by the current scroll
+        let top = row.get_view_object().position.y - self.view.position.y + self.scroll as i32;
+        let row_height = row.get_view_dimensions().unwrap_or(ViewDimens::zerod()).y;
+        let height = self.view.size.unwrap_or(ViewDimens::zerod()).y;
+
+        self.scroll = clamp_scroll(scroll_to_show(self.scroll, top, row_height, height), self.content_height, height);
+    }
+}
+
+impl View for ScrollView {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update_layout(&mut self) {
+        self.children.iter_mut().for_each(|child| child.update_layout());
+        self.arrange();
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        unsafe { push_clip(engine, ViewRect::new(self.view.position, size, 0)) };
+
+        //Note(teddy) The clip is the scroll view's rect, children scrolled out of it are culled
+        for index in draw_order(&self.children) {
+            draw_row(&mut self.children[index], engine, self.selection.highlight_for(index))?;
+        }
+
+        unsafe {
+            self.view.draw_background(engine, QuadLayer::Container, ViewRect::new(self.view.position, size, 0));
+
+            pop_clip(engine);
+        }
+
+        Ok(())
+    }
+
+    fn on_cursor_enter(&mut self) {
+        queue_hover!(self, CursorEvent::Enter);
+    }
+
+    fn on_cursor_exit(&mut self) {
+        queue_hover!(self, CursorEvent::Exit);
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+
+        //Note(teddy) Children scrolled out of view sit under other views, they must not hover
+        let inside = self.contains(&cords);
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+
+        for view in self.children.iter_mut() {
+            let child_y = view.get_view_object().position.y;
+            let child_height = view.get_view_dimensions().unwrap_or(ViewDimens::zerod()).y;
+            let in_view = is_in_view(child_y, child_height, self.view.position.y, size.y);
+
+            let send = view.is_visible() && ((inside && in_view) || view.captures_cursor());
+            view.receive_cursor_cords(engine, if send { cords } else { OUTSIDE_CORDS });
+        }
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        //Note(teddy) A hidden asset entry must not spawn when the click lands on empty space outside the view
+        if self.contains(&click.cords) || self.captures_cursor() {
+            self.selection.receive_click(&self.children, click);
+            return offer_click(&mut self.children, |view| view.handle_button_click(engine, click));
+        }
+
+        false
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        if !self.contains(&cords) {
+            return false;
+        }
+
+        if shown_mut(&mut self.children).any(|view| view.receive_scroll(engine, cords, delta)) {
+            return true;
+        }
+
+        //Note(teddy) Wheel up is a positive delta and moves the content down
+        let height = self.view.size.unwrap_or(ViewDimens::zerod()).y;
+        self.scroll = clamp_scroll(self.scroll - delta * SCROLL_STEP, self.content_height, height);
+        true
+    }
+
+    fn captures_cursor(&self) -> bool {
+        shown(&self.children).any(|view| view.captures_cursor())
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        shown_mut(&mut self.children).try_for_each(|view| view.draw_popups(engine))
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        fire_hover_callbacks!(self, ctx);
+        dispatch_children(&mut self.children, ctx);
+        self.selection.dispatch(ctx);
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn layout(&mut self, parent: ViewRect) {
+        apply_anchor(self, parent);
+        let bounds = self.bounds();
+        layout_children(&mut self.children, bounds);
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.children.iter_mut().any(|view| view.receive_char(engine, c))
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        if self.children.iter_mut().any(|view| view.receive_key(engine, key)) {
+            return true;
+        }
+
+        if self.selection.receive_key(&mut self.children, key) {
+            self.scroll_to_selection();
+            return true;
+        }
+
+        false
+    }
+
+    fn focusable(&self) -> bool {
+        self.selection.selectable
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        if self.focusable() {
+            ids.push(Box::from(self.get_id()));
+        }
+
+        for view in shown(&self.children) {
+            view.collect_focusable(ids);
+        }
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        if !self.contains(cords) {
+            return None;
+        }
+
+        shown(&self.children)
+            .find_map(|view| view.focusable_at(cords))
+            .or_else(|| Some(Box::from(self.get_id())).filter(|_| self.focusable() && self.bounds().contains(cords)))
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        if !self.contains(cords) {
+            return None;
+        }
+
+        shown(&self.children).find_map(|view| view.cursor_icon_at(cords)).or(self.cursor_icon())
+    }
+
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        //Note(teddy) Entries scrolled out of view are still positioned, only look at them inside the view
+        if !self.contains(cords) {
+            return None;
+        }
+
+        shown(&self.children).find_map(|view| view.object_at(cords, accept)).or_else(|| own_object(self, cords, accept))
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        for element in &mut self.children {
+            if let Some(el) = element.get_element_by_id(id) {
+                return Some(el);
+            }
+        }
+
+        None
+    }
+}
+
+impl ViewContainer for ScrollView {
+    fn add_child(&mut self, child: Box<dyn View>) {
+        self.children.push(child);
+        self.recalculate_dimensions();
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        find_child(&self.children, child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        find_child_mut(&mut self.children, child_id)
+    }
+
+    fn remove_child(&mut self, child_id: &str) -> UIResult {
+        match self.children.iter().position(|child| child.get_id() == child_id) {
+            Some(index) => {
+                self.children.remove(index);
+                self.recalculate_dimensions();
+                Ok(())
+            }
+            None => Err(UIError::ViewNotFound),
+        }
+    }
+
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        let taken = take_from_children(&self.view.id, &mut self.children, child_id)?;
+        self.recalculate_dimensions();
+        Ok(taken)
+    }
+
+    fn insert_child(&mut self, index: usize, child: Box<dyn View>) {
+        self.children.insert(index.min(self.children.len()), child);
+        self.recalculate_dimensions();
+    }
+}
+
+///Cell rects of a grid filled row-major, and the size of the whole grid.
+///Columns are as wide as their widest child and rows as tall as their tallest, margins included.
+fn grid_cells(origin: ViewPosition, columns: usize, spacing: i32, children: &[(ViewDimens, Margin)]) -> (Vec<ViewRect>, ViewDimens) {
+    if children.is_empty() {
+        return (vec![], ViewDimens::zerod());
+    }
+
+    let columns = columns.max(1).min(children.len());
+    let rows = (children.len() + columns - 1) / columns;
+    let mut widths = vec![0; columns];
+    let mut heights = vec![0; rows];
+
+    for (index, (dimensions, margin)) in children.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        widths[column] = widths[column].max(dimensions.x + margin.left + margin.right);
+        heights[row] = heights[row].max(dimensions.y + margin.top + margin.bottom);
+    }
+
+    //Note(teddy) Offsets of each column and row from the origin, the spacing only sits between them
+    let offsets = |extents: &[i32]| -> Vec<i32> {
+        extents.iter().scan(0, |offset, extent| {
+            let start = *offset;
+            *offset += extent + spacing;
+            Some(start)
+        }).collect()
+    };
+    let (column_offsets, row_offsets) = (offsets(&widths), offsets(&heights));
+
+    let cells = (0..children.len())
+        .map(|index| {
+            let (column, row) = (index % columns, index / columns);
+            ViewRect::new(
+                ViewPosition::new(origin.x + column_offsets[column], origin.y + row_offsets[row]),
+                ViewDimens::new(widths[column], heights[row]),
+                0,
+            )
+        })
+        .collect();
+
+    let size = ViewDimens::new(
+        widths.iter().sum::<i32>() + spacing * (columns as i32 - 1),
+        heights.iter().sum::<i32>() + spacing * (rows as i32 - 1),
+    );
+
+    (cells, size)
+}
+
+///Index of the cell the cursor is over, the gaps between cells belong to none
+fn cell_at(cells: &[ViewRect], cords: &Cords<f32>) -> Option<usize> {
+    cells.iter().position(|cell| cell.contains(cords))
+}
+
+///Lays its children out in a fixed number of columns, filled a row at a time.
+///Cursor and click events only go to the child whose cell is under the cursor.
+pub struct GridContainer {
+    children: Vec<Box<dyn View>>,
+    columns: usize,
+    ///Gap between columns and between rows
+    spacing: i32,
+    ///Screen space rect of every visible child's cell, in the same order as the children
+    cells: Vec<ViewRect>,
+    ///Index of the child in each cell, hidden children get no cell
+    cell_owners: Vec<usize>,
+    view: ViewObject,
+    pub on_hover: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    pub on_mouse_leave: Option<Box<dyn FnMut(&mut UiContext, &mut Self)>>,
+    ///Cursor transitions since the last dispatch
+    hover_events: Vec<CursorEvent>,
+}
+
+impl GridContainer {
+    pub fn new(id: Box<str>, columns: usize, position: ViewPosition, padding: i32) -> Self {
+        Self {
+            view: ViewObject::new(
+                id,
+                position,
+                Some(ViewDimens::zerod()),
+                padding,
+                1.0,
+                Some([0.1, 0.1, 0.1, 1.0]),
+                None,
+            ),
+            children: vec![],
+            columns,
+            spacing: 0,
+            cells: vec![],
+            cell_owners: vec![],
+            on_hover: None,
+            on_mouse_leave: None,
+            hover_events: vec![],
+        }
+    }
+
+    pub fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+        self.recalculate_dimensions();
+    }
+
+    ///Places every child in its cell, drawn or not
+    fn arrange(&mut self) {
+        //Note(teddy) The grid moves with its parent, the cells follow it
+        self.recalculate_dimensions();
+
+        for (index, cell) in self.cell_owners.iter().zip(self.cells.iter()) {
+            let view = &mut self.children[*index];
+            let margin = view.get_margin();
+            let height = view.get_view_dimensions().unwrap_or(ViewDimens::zerod()).y;
+
+            let position = ViewPosition::new(cell.min.x as i32 + margin.left, cell.min.y as i32 + margin.top);
+            allocate(view, position, ViewDimens::new(cell.width() as i32 - margin.left - margin.right, height));
+        }
+    }
+
+    fn recalculate_dimensions(&mut self) {
+        let padding = self.view.padding;
+        let origin = ViewPosition::new(self.view.position.x + padding, self.view.position.y + padding);
+        let owners: Vec<usize> = (0..self.children.len()).filter(|index| self.children[*index].is_visible()).collect();
+        let extents: Vec<(ViewDimens, Margin)> = owners
+            .iter()
+            .map(|index| &self.children[*index])
+            .map(|child| (child.get_view_dimensions().unwrap_or(ViewDimens::zerod()), child.get_margin()))
+            .collect();
+
+        let (cells, size) = grid_cells(origin, self.columns, self.spacing, &extents);
+        let min_size = self.view.min_size.unwrap_or(ViewDimens::zerod());
+
+        self.cells = cells;
+        self.cell_owners = owners;
+        self.view.size = Some(ViewDimens::new(size.x.max(min_size.x), size.y.max(min_size.y)));
+    }
+
+    ///Index of the child whose cell is under the cursor
+    fn cell_under(&self, cords: &Cords<f32>) -> Option<usize> {
+        cell_at(&self.cells, cords).map(|cell| self.cell_owners[cell])
+    }
+}
+
+impl View for GridContainer {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn update_layout(&mut self) {
+        self.children.iter_mut().for_each(|child| child.update_layout());
+        self.arrange();
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        for index in draw_order(&self.children) {
+            draw_child(&mut self.children[index], engine)?;
+        }
+
+        unsafe { self.view.draw_background(engine, QuadLayer::Container, self.bounds()) };
+
+        Ok(())
+    }
+
+    fn on_cursor_enter(&mut self) {
+        queue_hover!(self, CursorEvent::Enter);
+    }
+
+    fn on_cursor_exit(&mut self) {
+        queue_hover!(self, CursorEvent::Exit);
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+
+        //Note(teddy) Only the cell under the cursor hovers, the rest are told the cursor left
+        let target = self.cell_under(&cords);
+        for (index, view) in self.children.iter_mut().enumerate() {
+            let send = target == Some(index) || (view.is_visible() && view.captures_cursor());
+            view.receive_cursor_cords(engine, if send { cords } else { OUTSIDE_CORDS });
+        }
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        if self.captures_cursor() {
+            return offer_click(&mut self.children, |view| view.handle_button_click(engine, click));
+        }
+
+        match self.cell_under(&click.cords) {
+            Some(index) => self.children[index].handle_button_click(engine, click),
+            None => false,
+        }
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        match self.cell_under(&cords) {
+            Some(index) => self.children[index].receive_scroll(engine, cords, delta),
+            None => false,
+        }
+    }
+
+    fn captures_cursor(&self) -> bool {
+        shown(&self.children).any(|view| view.captures_cursor())
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        shown_mut(&mut self.children).try_for_each(|view| view.draw_popups(engine))
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        fire_hover_callbacks!(self, ctx);
+        dispatch_children(&mut self.children, ctx);
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size.map(|size| ViewDimens::new(size.x + (self.view.padding << 1), size.y + (self.view.padding << 1)))
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.children.iter_mut().any(|view| view.receive_char(engine, c))
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        self.children.iter_mut().any(|view| view.receive_key(engine, key))
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        for view in shown(&self.children) {
+            view.collect_focusable(ids);
+        }
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        self.cell_under(cords).and_then(|index| self.children[index].focusable_at(cords))
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        self.cell_under(cords)
+            .and_then(|index| self.children[index].cursor_icon_at(cords))
+            .or_else(|| self.cursor_icon().filter(|_| self.bounds().contains(cords)))
+    }
+
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        self.cell_under(cords)
+            .and_then(|index| self.children[index].object_at(cords, accept))
+            .or_else(|| own_object(self, cords, accept))
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        for element in &mut self.children {
+            if let Some(el) = element.get_element_by_id(id) {
+                return Some(el);
+            }
+        }
+
+        None
+    }
+}
+
+impl ViewContainer for GridContainer {
+    fn add_child(&mut self, child: Box<dyn View>) {
+        self.children.push(child);
+        self.recalculate_dimensions();
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        find_child(&self.children, child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        find_child_mut(&mut self.children, child_id)
+    }
+
+    fn remove_child(&mut self, child_id: &str) -> UIResult {
+        match self.children.iter().position(|child| child.get_id() == child_id) {
+            Some(index) => {
+                self.children.remove(index);
+                self.recalculate_dimensions();
+                Ok(())
+            }
+            None => Err(UIError::ViewNotFound),
+        }
+    }
+
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        let taken = take_from_children(&self.view.id, &mut self.children, child_id)?;
+        self.recalculate_dimensions();
+        Ok(taken)
+    }
+
+    fn insert_child(&mut self, index: usize, child: Box<dyn View>) {
+        self.children.insert(index.min(self.children.len()), child);
+        self.recalculate_dimensions();
+    }
+}
+
+const SPLIT_DIVIDER_WIDTH: i32 = 4;
+///Pixels either side of the divider that still grab it, ahead of the children under them
+const SPLIT_DIVIDER_GRAB: f32 = 3.0;
+const SPLIT_DIVIDER_COLOR: [f32; 3] = [0.3, 0.3, 0.3];
+const SPLIT_DIVIDER_DRAG_COLOR: [f32; 3] = [0.55, 0.55, 0.55];
+
+///Sizes along the split of the first and second pane, the divider is taken out of the middle.
+///When both minimums don't fit the first pane gives way.
+fn split_sizes(total: i32, ratio: f32, divider: i32, (min_first, min_second): (i32, i32)) -> (i32, i32) {
+    let available = (total - divider).max(0);
+    let first = ((available as f32 * ratio).round() as i32).max(min_first).min(available - min_second).max(0);
+    (first, available - first)
+}
+
+///Ratio that puts the middle of the divider offset pixels into the pane, kept to the minimum sizes
+fn split_ratio_at(offset: f32, total: i32, divider: i32, min_sizes: (i32, i32)) -> f32 {
+    let available = (total - divider).max(1) as f32;
+    let (first, _) = split_sizes(total, (offset - divider as f32 * 0.5) / available, divider, min_sizes);
+    first as f32 / available
+}
+
+///Two children side by side, or one over the other when vertical, with a divider between them that is dragged
+///to share out the space. It fills the rect it's laid out in and gives each child its pane through update_dimensions.
+pub struct SplitPane {
+    view: ViewObject,
+    orientation: Orientation,
+    ///Always two, the first one is on the left or on top
+    children: Vec<Box<dyn View>>,
+    ///Share of the space, less the divider, that goes to the first child
+    ratio: f32,
+    ///Smallest size along the split of the first and the second child
+    min_sizes: (i32, i32),
+    divider_vao: i32,
+    divider_vbo: i32,
+    dragging: bool,
+    ///The ratio moved since the last dispatch
+    changed: bool,
+    ///Panes the children were last laid out in, they are laid out again when the divider moves
+    panes: Option<[ViewRect; 2]>,
+    pub on_ratio_changed: Option<Box<dyn FnMut(&mut UiContext, &mut SplitPane, f32)>>,
+}
+
+impl SplitPane {
+    pub fn new(id: Box<str>, orientation: Orientation, first: Box<dyn View>, second: Box<dyn View>, position: ViewPosition) -> Self {
+        let (divider_vao, divider_vbo) = unsafe { initialize_background_buffers() };
+
+        Self {
+            view: ViewObject::new(id, position, Some(ViewDimens::zerod()), 0, 1.0, None, None),
+            orientation,
+            children: vec![first, second],
+            ratio: 0.5,
+            min_sizes: (0, 0),
+            divider_vao,
+            divider_vbo,
+            dragging: false,
+            changed: false,
+            panes: None,
+            on_ratio_changed: None,
+        }
+    }
+
+    ///Sets the ratio without calling on_ratio_changed
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(0.0).min(1.0);
+    }
+
+    pub fn set_min_sizes(&mut self, first: i32, second: i32) {
+        self.min_sizes = (first.max(0), second.max(0));
+    }
+
+    fn along(&self, dimensions: ViewDimens) -> i32 {
+        match self.orientation {
+            Orientation::Horizontal => dimensions.x,
+            Orientation::Vertical => dimensions.y,
+        }
+    }
+
+    ///Size along the split of the first and the second child
+    pub fn pane_sizes(&self) -> (i32, i32) {
+        split_sizes(self.along(self.view.size.unwrap_or(ViewDimens::zerod())), self.ratio, SPLIT_DIVIDER_WIDTH, self.min_sizes)
+    }
+
+    ///Rects of the first child, the divider and the second child
+    fn rects(&self) -> [ViewRect; 3] {
+        let position = self.view.position;
+        let size = self.view.size.unwrap_or(ViewDimens::zerod());
+        let (first, second) = self.pane_sizes();
+
+        match self.orientation {
+            Orientation::Horizontal => [
+                ViewRect::new(position, ViewDimens::new(first, size.y), 0),
+                ViewRect::new(ViewPosition::new(position.x + first, position.y), ViewDimens::new(SPLIT_DIVIDER_WIDTH, size.y), 0),
+                ViewRect::new(ViewPosition::new(position.x + first + SPLIT_DIVIDER_WIDTH, position.y), ViewDimens::new(second, size.y), 0),
+            ],
+            Orientation::Vertical => [
+                ViewRect::new(position, ViewDimens::new(size.x, first), 0),
+                ViewRect::new(ViewPosition::new(position.x, position.y + first), ViewDimens::new(size.x, SPLIT_DIVIDER_WIDTH), 0),
+                ViewRect::new(ViewPosition::new(position.x, position.y + first + SPLIT_DIVIDER_WIDTH), ViewDimens::new(size.x, second), 0),
+            ],
+        }
+    }
+
+    fn panes(&self) -> [ViewRect; 2] {
+        let [first, _, second] = self.rects();
+        [first, second]
+    }
+
+    ///The divider wins over the children within a few pixels of it
+    fn on_divider(&self, cords: &Cords<f32>) -> bool {
+        let divider = self.rects()[1];
+        let grab = match self.orientation {
+            Orientation::Horizontal => Cords { x: SPLIT_DIVIDER_GRAB, y: 0.0 },
+            Orientation::Vertical => Cords { x: 0.0, y: SPLIT_DIVIDER_GRAB },
+        };
+
+        ViewRect { min: Cords { x: divider.min.x - grab.x, y: divider.min.y - grab.y }, max: Cords { x: divider.max.x + grab.x, y: divider.max.y + grab.y } }
+            .contains(cords)
+    }
+
+    ///Index of the shown child whose pane is under the cursor, or the one holding on to the cursor
+    fn child_under(&self, cords: &Cords<f32>) -> Option<usize> {
+        let panes = self.panes();
+        let capturing = self.children.iter().position(|view| view.is_visible() && view.captures_cursor());
+
+        capturing.or_else(|| (0..2).find(|index| self.children[*index].is_visible() && panes[*index].contains(cords)))
+    }
+
+    fn drag_to(&mut self, cords: &Cords<f32>) {
+        let offset = match self.orientation {
+            Orientation::Horizontal => cords.x - self.view.position.x as f32,
+            Orientation::Vertical => cords.y - self.view.position.y as f32,
+        };
+
+        let ratio = split_ratio_at(offset, self.along(self.view.size.unwrap_or(ViewDimens::zerod())), SPLIT_DIVIDER_WIDTH, self.min_sizes);
+        if ratio != self.ratio {
+            self.ratio = ratio;
+            self.changed = true;
+        }
+    }
+
+    ///Gives each child its pane, drawn or not
+    fn arrange(&mut self) {
+        let panes = self.panes();
+
+        for (view, pane) in self.children.iter_mut().zip(panes.iter()) {
+            allocate(view, ViewPosition::new(pane.min.x as i32, pane.min.y as i32), ViewDimens::new(pane.width() as i32, pane.height() as i32));
+        }
+
+        //Note(teddy) Anchored views in a pane follow it when the divider moves
+        if self.panes != Some(panes) {
+            self.panes = Some(panes);
+            for (view, pane) in self.children.iter_mut().zip(panes.iter()) {
+                view.layout(*pane);
+            }
+        }
+    }
+}
+
+impl View for SplitPane {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        &(self.view.id)
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.view
+    }
+
+    fn layout(&mut self, parent: ViewRect) {
+        self.view.position = ViewPosition::new(parent.min.x as i32, parent.min.y as i32);
+        self.view.size = Some(ViewDimens::new(parent.width() as i32, parent.height() as i32));
+        self.panes = None;
+        self.arrange();
+    }
+
+    fn update_layout(&mut self) {
+        self.children.iter_mut().for_each(|child| child.update_layout());
+        self.arrange();
+    }
+
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.arrange();
+
+        let panes = self.panes();
+        for (view, pane) in self.children.iter_mut().zip(panes.iter()).filter(|(view, _)| view.is_visible()) {
+            unsafe { push_clip(engine, *pane) };
+            let result = draw_child(view, engine);
+            unsafe { pop_clip(engine) };
+            result?;
+        }
+
+        let (position, size) = self.rects()[1].quad_args();
+        unsafe {
+            draw_quad_with_default_shader(
+                engine,
+                self.divider_vao as u32,
+                self.divider_vbo as u32,
+                quad_depth(self.view.z_index, QuadLayer::Detail),
+                position,
+                size,
+                if self.dragging { &SPLIT_DIVIDER_DRAG_COLOR } else { &SPLIT_DIVIDER_COLOR },
+            );
+            self.view.draw_background(engine, QuadLayer::Container, self.bounds());
+        }
+
+        Ok(())
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.update_hover(&cords);
+
+        if self.dragging {
+            self.drag_to(&cords);
+        }
+
+        //Note(teddy) Nothing under the divider hovers while it's grabbed or the cursor is on it
+        let target = if self.dragging || self.on_divider(&cords) { None } else { self.child_under(&cords) };
+        for (index, view) in self.children.iter_mut().enumerate() {
+            view.receive_cursor_cords(engine, if target == Some(index) { cords } else { OUTSIDE_CORDS });
+        }
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        if self.dragging {
+            if click.button == MouseButton::Button1 && click.is_release() {
+                self.dragging = false;
+            }
+            return true;
+        }
+
+        let capturing = self.children.iter().any(|view| view.captures_cursor());
+        if click.pressed(MouseButton::Button1) && !capturing && self.on_divider(&click.cords) {
+            self.dragging = true;
+            return true;
+        }
+
+        match self.child_under(&click.cords) {
+            Some(index) => self.children[index].handle_button_click(engine, click),
+            None => false,
+        }
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        match self.child_under(&cords) {
+            Some(index) => self.children[index].receive_scroll(engine, cords, delta),
+            None => false,
+        }
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.dragging || shown(&self.children).any(|view| view.captures_cursor())
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        shown_mut(&mut self.children).try_for_each(|view| view.draw_popups(engine))
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        dispatch_children(&mut self.children, ctx);
+
+        if std::mem::take(&mut self.changed) {
+            let ratio = self.ratio;
+            fire_callback!(self, on_ratio_changed, ctx, ratio);
+        }
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.view.size
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.view.position = position;
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.view.position)
+    }
+
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.children.iter_mut().any(|view| view.receive_char(engine, c))
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        self.children.iter_mut().any(|view| view.receive_key(engine, key))
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        for view in shown(&self.children) {
+            view.collect_focusable(ids);
+        }
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        if self.on_divider(cords) {
+            return None;
+        }
+
+        self.child_under(cords).and_then(|index| self.children[index].focusable_at(cords))
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        if self.dragging || self.on_divider(cords) {
+            return match self.orientation {
+                Orientation::Horizontal => Some(CursorIcon::HResize),
+                Orientation::Vertical => Some(CursorIcon::VResize),
+            };
+        }
+
+        self.child_under(cords).and_then(|index| self.children[index].cursor_icon_at(cords))
+    }
+
+    //Note(teddy) Only the divider is the pane's own, the rest of it is its children
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        if self.on_divider(cords) {
+            return Some(&self.view).filter(|object| accept(object));
+        }
+
+        self.child_under(cords).and_then(|index| self.children[index].object_at(cords, accept))
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        self.children.iter_mut().find_map(|element| element.get_element_by_id(id))
+    }
+}
+
+//Note(teddy) The pane is built with both its children, they can't be added, removed or taken out of it,
+//only views further down can
+impl ViewContainer for SplitPane {
+    fn add_child(&mut self, child: Box<dyn View>) {
+        println!("{} already has its two children, {} was not added", self.view.id, child.get_id());
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        find_child(&self.children, child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        find_child_mut(&mut self.children, child_id)
+    }
+
+    fn remove_child(&mut self, _child_id: &str) -> UIResult {
+        Err(UIError::ViewNotFound)
+    }
+
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        self.children
+            .iter_mut()
+            .filter_map(|child| child.as_container_mut())
+            .find_map(|container| container.take_child(child_id).ok())
+            .ok_or(UIError::ViewNotFound)
+    }
+
+    fn insert_child(&mut self, _index: usize, child: Box<dyn View>) {
+        println!("{} already has its two children, {} was not added", self.view.id, child.get_id());
+    }
+}
+
+const SECTION_EXPANDED_MARKER: char = '\u{25be}';
+const SECTION_COLLAPSED_MARKER: char = '\u{25b8}';
+
+///Header text with the marker for the section's state
+fn section_header(title: &str, expanded: bool, font_face: &FontFace) -> String {
+    format!("{} {}", expand_marker(expanded, font_face), title)
+}
+
+///Triangle showing whether a section or tree node is open, plain ascii when the font has no triangles
+fn expand_marker(expanded: bool, font_face: &FontFace) -> char {
+    let marker = if expanded { SECTION_EXPANDED_MARKER } else { SECTION_COLLAPSED_MARKER };
+    match (font_face.has_glyph(marker), expanded) {
+        (true, _) => marker,
+        (false, true) => 'v',
+        (false, false) => '>',
+    }
+}
+
+///Vertical container under a header, clicking the header hides or shows the rest of its children
+pub struct CollapsibleSection {
+    title: String,
+    expanded: bool,
+    ///The header is the first child, the body follows it
+    container: SimpleUIContainer,
+}
+
+impl CollapsibleSection {
+    pub fn new(id: Box<str>, title: String, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let header = TextView::new(
+            format!("{}_header", id).into_boxed_str(),
+            section_header(&title, true, &resources.font_face),
+            ViewPosition::zerod(),
+            1.0,
+            padding,
+            resources,
+        );
+
+        let mut container = SimpleUIContainer::new(id, None, position, Orientation::Vertical, 0, 1.0, false);
+        container.add_child(Box::new(header));
+
+        Self { title, expanded: true, container }
+    }
+
+    pub fn header_mut(&mut self) -> &mut TextView {
+        self.container.children[0].as_any().downcast_mut::<TextView>().unwrap()
+    }
+
+    pub fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+        for child in self.container.children.iter_mut().skip(1) {
+            child.set_visible(expanded);
+        }
+
+        let title = self.title.clone();
+        let header = self.header_mut();
+        let text = section_header(&title, expanded, header.font());
+        header.set_text(text);
+        self.container.recalculate_dimensions();
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_expanded(!self.expanded);
+    }
+
+    pub fn set_clip_children(&mut self, clip_children: bool) {
+        self.container.set_clip_children(clip_children);
+    }
+}
+
+impl View for CollapsibleSection {
+    fn as_any(&mut self) -> Box<&mut dyn Any> {
+        Box::new(self)
+    }
+
+    fn get_id(&self) -> &str {
+        self.container.get_id()
+    }
+
+    fn get_view_object(&self) -> &ViewObject {
+        &self.container.view
+    }
+
+    fn get_view_object_mut(&mut self) -> &mut ViewObject {
+        &mut self.container.view
+    }
+
+    fn update_layout(&mut self) {
+        self.container.update_layout();
+    }
 
-                on_hover: None,
-                on_mouse_leave: None,
-                on_click: None,
-                on_right_click: None,
-                on_middle_click: None,
-            }
+    fn update(&mut self, engine: &Engine) -> UIResult {
+        self.container.update(engine)
+    }
+
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.container.receive_cursor_cords(engine, cords);
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        let on_header = self.container.children[0].bounds().contains(&click.cords);
+        if on_header && click.pressed(MouseButton::Button1) {
+            self.toggle();
+            return true;
         }
+
+        self.container.handle_button_click(engine, click)
     }
 
-    pub fn set_text(&mut self, new_text: String, font_face: &FontFace) {
-        self.text = new_text;
-        self.text_length = get_the_length_of_text(&self.text, &font_face);
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.container.view.size
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.container.set_position(position);
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.container.view.position)
+    }
+
+    fn layout(&mut self, parent: ViewRect) {
+        self.container.layout(parent);
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        self.container.dispatch(ctx);
+    }
+
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.container.receive_char(engine, c)
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        self.container.receive_key(engine, key)
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        self.container.receive_scroll(engine, cords, delta)
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.container.captures_cursor()
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        self.container.draw_popups(engine)
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        self.container.collect_focusable(ids);
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        self.container.focusable_at(cords)
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        self.container.cursor_icon_at(cords)
+    }
+
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        self.container.object_at(cords, accept)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        self.container.children.iter_mut().find_map(|element| element.get_element_by_id(id))
     }
 }
 
-macro_rules! button_clicked {
-    ($container:expr, $butt:expr) => {
-        $container.iter().find(|b: &&MouseButton| **b == $butt)
-    };
+impl ViewContainer for CollapsibleSection {
+    //Note(teddy) Children added to a collapsed section stay hidden until it's expanded
+    fn add_child(&mut self, mut child: Box<dyn View>) {
+        child.set_visible(self.expanded);
+        self.container.add_child(child);
+    }
+
+    fn remove_child(&mut self, child_id: &str) -> UIResult {
+        self.container.remove_child(child_id)
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        self.container.get_view_by_id(child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        self.container.get_view_by_id_mut(child_id)
+    }
+
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        self.container.take_child(child_id)
+    }
+
+    fn insert_child(&mut self, index: usize, mut child: Box<dyn View>) {
+        child.set_visible(self.expanded);
+        self.container.insert_child(index.max(1), child);
+    }
 }
 
-impl View for TextView {
+const TAB_ACTIVE_COLOR: [f32; 3] = [0.25, 0.25, 0.35];
+const TAB_INACTIVE_COLOR: [f32; 3] = [0.12, 0.12, 0.12];
+
+///A strip of tab headers over the content of the active tab. The other tabs are hidden, so they
+///aren't drawn and get no cursor or clicks, but they keep their state for when they're picked again.
+pub struct TabView {
+    ///The header strip is the first child, the tab contents follow it in the order of the headers
+    container: SimpleUIContainer,
+    active: usize,
+    ///Ids of the headers in the strip, numbered so they stay unique after tabs are removed
+    headers: Vec<Box<str>>,
+    next_header: usize,
+    ///The active tab moved since the last dispatch
+    changed: bool,
+    resources: UiResources,
+    padding: i32,
+    pub on_tab_changed: Option<Box<dyn FnMut(&mut UiContext, &mut TabView, usize)>>,
+}
+
+impl TabView {
+    pub fn new(id: Box<str>, position: ViewPosition, padding: i32, resources: &UiResources) -> Self {
+        let strip = SimpleUIContainer::new(format!("{}_tabs", id).into_boxed_str(), None, ViewPosition::zerod(), Orientation::Horizontal, 0, 1.0, false);
+
+        let mut container = SimpleUIContainer::new(id, None, position, Orientation::Vertical, 0, 1.0, false);
+        container.add_child(Box::new(strip));
+
+        Self { container, active: 0, headers: vec![], next_header: 0, changed: false, resources: resources.clone(), padding, on_tab_changed: None }
+    }
+
+    fn strip_mut(&mut self) -> &mut SimpleUIContainer {
+        self.container.children[0].as_any().downcast_mut::<SimpleUIContainer>().unwrap()
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.container.children.len() - 1
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.active
+    }
+
+    pub fn add_tab(&mut self, title: String, view: Box<dyn View>) {
+        let index = self.tab_count();
+        self.insert_tab(index, title, view);
+    }
+
+    pub fn insert_tab(&mut self, index: usize, title: String, mut view: Box<dyn View>) {
+        let index = index.min(self.tab_count());
+        let header_id: Box<str> = format!("{}_tab_{}", self.container.view.id, self.next_header).into_boxed_str();
+        self.next_header += 1;
+        self.headers.insert(index, header_id.clone());
+
+        let header = TextView::new(header_id, title, ViewPosition::zerod(), 1.0, self.padding, &self.resources);
+        self.strip_mut().insert_child(index, Box::new(header));
+
+        //Note(teddy) The first tab added is the one shown, the rest wait behind it
+        let was_empty = self.tab_count() == 0;
+        if !was_empty && index <= self.active {
+            self.active += 1;
+        }
+
+        view.set_visible(was_empty);
+        self.container.insert_child(index + 1, view);
+        self.show_active();
+    }
+
+    ///Takes the tab out and returns its content, the tab after it is shown when it was the active one
+    pub fn remove_tab(&mut self, index: usize) -> Option<Box<dyn View>> {
+        if index >= self.tab_count() {
+            return None;
+        }
+
+        let header_id = self.headers.remove(index);
+        let _ = self.strip_mut().remove_child(&header_id);
+        let mut view = self.container.children.remove(index + 1);
+        view.set_visible(true);
+
+        if index < self.active {
+            self.active -= 1;
+        } else if index == self.active {
+            self.active = self.active.min(self.tab_count().saturating_sub(1));
+            self.changed |= self.tab_count() > 0;
+        }
+
+        self.show_active();
+        Some(view)
+    }
+
+    ///Shows the tab, on_tab_changed only runs for tabs picked from the header strip
+    pub fn set_active_tab(&mut self, index: usize) {
+        if index < self.tab_count() {
+            self.active = index;
+            self.show_active();
+        }
+    }
+
+    fn show_active(&mut self) {
+        let active = self.active;
+
+        for (index, view) in self.container.children.iter_mut().skip(1).enumerate() {
+            view.set_visible(index == active);
+        }
+
+        for (index, header) in self.strip_mut().children.iter_mut().enumerate() {
+            header.set_background_color(if index == active { TAB_ACTIVE_COLOR } else { TAB_INACTIVE_COLOR });
+        }
+
+        self.container.recalculate_dimensions();
+    }
+
+    fn header_at(&self, cords: &Cords<f32>) -> Option<usize> {
+        let strip = self.container.children[0].as_container()?;
+        self.headers.iter().position(|id| strip.get_view_by_id(id).map_or(false, |header| header.bounds().contains(cords)))
+    }
+
+    fn active_view_mut(&mut self) -> Option<&mut Box<dyn View>> {
+        self.container.children.get_mut(self.active + 1)
+    }
+}
+
+impl View for TabView {
     fn as_any(&mut self) -> Box<&mut dyn Any> {
         Box::new(self)
     }
+
     fn get_id(&self) -> &str {
-        &(*self.view.id)
+        self.container.get_id()
     }
 
     fn get_view_object(&self) -> &ViewObject {
-        &self.view
+        &self.container.view
     }
+
     fn get_view_object_mut(&mut self) -> &mut ViewObject {
-        &mut self.view
+        &mut self.container.view
+    }
+
+    fn update_layout(&mut self) {
+        self.container.update_layout();
     }
 
     fn update(&mut self, engine: &Engine) -> UIResult {
-        let view: *mut TextView = self;
+        self.container.update(engine)
+    }
 
-        match self.cursor_hover {
-            CursorState::Hover => {
-                if let Some(func) = &mut self.on_hover {
-                    func(view);
+    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
+        self.container.receive_cursor_cords(engine, cords);
+    }
+
+    fn handle_button_click(&mut self, engine: &Engine, click: &ClickInfo) -> bool {
+        if !self.captures_cursor() && click.pressed(MouseButton::Button1) {
+            if let Some(index) = self.header_at(&click.cords) {
+                if index != self.active {
+                    self.set_active_tab(index);
+                    self.changed = true;
                 }
+                return true;
             }
+        }
 
-            CursorState::Leave => {
-                if let Some(func) = &mut self.on_mouse_leave {
-                    func(view);
-                    self.cursor_hover = CursorState::Neither;
-                }
+        self.container.handle_button_click(engine, click)
+    }
+
+    fn update_dimensions(&mut self, dimensions: ViewDimens) {
+        self.container.update_dimensions(dimensions);
+    }
+
+    fn bounds(&self) -> ViewRect {
+        self.container.bounds()
+    }
+
+    fn get_view_dimensions(&self) -> Option<ViewDimens> {
+        self.container.view.size
+    }
+
+    fn set_position(&mut self, position: ViewPosition) {
+        self.container.set_position(position);
+    }
+
+    fn get_position(&self) -> Option<ViewPosition> {
+        Some(self.container.view.position)
+    }
+
+    fn layout(&mut self, parent: ViewRect) {
+        self.container.layout(parent);
+    }
+
+    fn dispatch(&mut self, ctx: &mut UiContext) {
+        self.container.dispatch(ctx);
+
+        if std::mem::take(&mut self.changed) {
+            let active = self.active;
+            fire_callback!(self, on_tab_changed, ctx, active);
+        }
+    }
+
+    //Note(teddy) Hidden tabs keep their text inputs, the keys only go to the one shown
+    fn receive_char(&mut self, engine: &Engine, c: char) -> bool {
+        self.active_view_mut().map_or(false, |view| view.receive_char(engine, c))
+    }
+
+    fn receive_key(&mut self, engine: &Engine, key: Key) -> bool {
+        self.active_view_mut().map_or(false, |view| view.receive_key(engine, key))
+    }
+
+    fn receive_scroll(&mut self, engine: &Engine, cords: Cords<f32>, delta: f32) -> bool {
+        self.container.receive_scroll(engine, cords, delta)
+    }
+
+    fn captures_cursor(&self) -> bool {
+        self.container.captures_cursor()
+    }
+
+    fn draw_popups(&mut self, engine: &Engine) -> UIResult {
+        self.container.draw_popups(engine)
+    }
+
+    fn as_container(&self) -> Option<&dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+        Some(self)
+    }
+
+    fn collect_focusable(&self, ids: &mut Vec<Box<str>>) {
+        self.container.collect_focusable(ids);
+    }
+
+    fn focusable_at(&self, cords: &Cords<f32>) -> Option<Box<str>> {
+        self.container.focusable_at(cords)
+    }
+
+    fn cursor_icon_at(&self, cords: &Cords<f32>) -> Option<CursorIcon> {
+        if self.header_at(cords).is_some() {
+            return Some(CursorIcon::Hand);
+        }
+
+        self.container.cursor_icon_at(cords)
+    }
+
+    fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+        self.container.object_at(cords, accept)
+    }
+
+    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
+        if self.get_id() == id {
+            return Some(Rc::new(self));
+        }
+
+        self.container.children.iter_mut().find_map(|element| element.get_element_by_id(id))
+    }
+}
+
+//Note(teddy) Children added as plain views become tabs titled with their id
+impl ViewContainer for TabView {
+    fn add_child(&mut self, child: Box<dyn View>) {
+        let title = child.get_id().to_string();
+        self.add_tab(title, child);
+    }
+
+    fn remove_child(&mut self, child_id: &str) -> UIResult {
+        match self.container.children.iter().skip(1).position(|view| view.get_id() == child_id) {
+            Some(index) => {
+                self.remove_tab(index);
+                Ok(())
+            }
+            None => Err(UIError::ViewNotFound),
+        }
+    }
+
+    fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+        self.container.get_view_by_id(child_id)
+    }
+
+    fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+        self.container.get_view_by_id_mut(child_id)
+    }
+
+    //Note(teddy) Tabs and headers come and go together, only views inside the tabs can be taken out
+    fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+        self.container
+            .children
+            .iter_mut()
+            .skip(1)
+            .filter_map(|child| child.as_container_mut())
+            .find_map(|container| container.take_child(child_id).ok())
+            .ok_or(UIError::ViewNotFound)
+    }
+
+    fn insert_child(&mut self, index: usize, child: Box<dyn View>) {
+        let title = child.get_id().to_string();
+        self.insert_tab(index, title, child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FontChar;
+    use nalgebra::Point2;
+    use std::collections::HashMap;
+
+    fn font_face() -> FontFace {
+        let mut chars = HashMap::new();
+        for (c, advance) in [('a', 8), ('e', 8), ('?', 7), ('é', 9)].iter() {
+            chars.insert(
+                *c,
+                FontChar {
+                    uv: [0.0; 4],
+                    size: Point2::new(*advance, 12),
+                    bearing: Point2::new(0, 12),
+                    advance: advance << 6,
+                },
+            );
+        }
+        FontFace::new(String::from("test"), 12, chars)
+    }
+
+    #[test]
+    fn test_text_input_with_multi_byte_chars() {
+        let font_face = font_face();
+        let mut state = TextInputState::default();
+
+        for c in "aé日".chars() {
+            state.insert_char(c);
+        }
+        assert_eq!(state.text(), "aé日");
+        assert_eq!(state.cursor(), 1 + 2 + 3);
+        //Note(teddy) 日 is not in the atlas and is measured with the fallback glyph
+        assert_eq!(state.measure(&font_face), 8 + 9 + 7);
+
+        assert!(state.handle_key(Key::Left));
+        assert_eq!(state.cursor(), 3);
+        assert_eq!(state.cursor_offset(&font_face), 17);
+
+        state.insert_char('e');
+        state.insert_char('\u{8}');
+        assert_eq!(state.text(), "aée日");
+
+        assert!(state.handle_key(Key::Backspace));
+        assert!(state.handle_key(Key::Backspace));
+        assert_eq!(state.text(), "a日");
+        assert_eq!(state.cursor(), 1);
+
+        assert!(state.handle_key(Key::Delete));
+        assert_eq!(state.text(), "a");
+        assert!(state.handle_key(Key::Home));
+        assert!(state.handle_key(Key::Backspace));
+        assert_eq!((state.text(), state.cursor()), ("a", 0));
+        assert!(state.handle_key(Key::End));
+        assert_eq!(state.cursor(), 1);
+        assert!(!state.handle_key(Key::W));
+    }
+
+    #[test]
+    fn test_caret_blinks_after_edit() {
+        assert!(caret_visible(Duration::from_millis(0)));
+        assert!(caret_visible(Duration::from_millis(CARET_BLINK_MS as u64 - 1)));
+        assert!(!caret_visible(Duration::from_millis(CARET_BLINK_MS as u64)));
+        assert!(caret_visible(Duration::from_millis(CARET_BLINK_MS as u64 * 2)));
+    }
+
+    #[test]
+    fn test_scroll_is_clamped_to_the_content() {
+        //Note(teddy) 30 asset entries of 32 pixels in a 400 pixel tall view
+        let (content, height) = (30 * 32, 400);
+
+        assert_eq!(clamp_scroll(-SCROLL_STEP, content, height), 0.0);
+        assert_eq!(clamp_scroll(10_000.0, content, height), 560.0);
+        assert_eq!(clamp_scroll(90.0, content, height), 90.0);
+        //Note(teddy) Content that fits never scrolls
+        assert_eq!(clamp_scroll(90.0, 300, height), 0.0);
+
+        assert!(is_in_view(390, 32, 0, height));
+        assert!(!is_in_view(400, 32, 0, height));
+        assert!(!is_in_view(-32, 32, 0, height));
+        assert!(is_in_view(-31, 32, 0, height));
+    }
+
+    #[test]
+    fn test_checkbox_dimensions_include_the_label() {
+        let font_face = font_face();
+        let label_width = get_the_length_of_text(&String::from("ae"), &font_face);
+
+        assert_eq!(checkbox_dimensions(12, None), ViewDimens::new(12, 12));
+        assert_eq!(checkbox_dimensions(12, Some(label_width)), ViewDimens::new(12 + CHECKBOX_LABEL_GAP + 16, 12));
+    }
+
+    #[test]
+    fn test_slider_value_follows_the_cursor() {
+        //Note(teddy) 200 pixel track starting at x 10, light direction range
+        let value = |cursor_x| slider_value(cursor_x, 10.0, 200.0, -50.0, 50.0, 1.0);
+
+        assert_eq!(value(10.0), -50.0);
+        assert_eq!(value(110.0), 0.0);
+        assert_eq!(value(111.0), 1.0);
+        //Note(teddy) A drag that leaves the track keeps the value at the end it left from
+        assert_eq!(value(-300.0), -50.0);
+        assert_eq!(value(900.0), 50.0);
+
+        assert!((slider_value(60.0, 10.0, 200.0, 0.0, 1.0, 0.05) - 0.25).abs() < 1e-5);
+        assert!((slider_value(210.0, 10.0, 200.0, 0.0, 1.0, 0.3) - 0.9).abs() < 1e-5);
+        assert_eq!(format_slider_value(0.25, 0.05), "0.25");
+        assert_eq!(format_slider_value(-12.0, 1.0), "-12");
+    }
+
+    #[test]
+    fn test_dropdown_rows_under_the_cursor() {
+        let list = ViewPosition::new(10, 40);
+        let row = ViewDimens::new(100, 20);
+        let at = |x, y| dropdown_option_at(&Cords { x, y }, list, row, 3);
+
+        assert_eq!(at(15.0, 41.0), Some(0));
+        assert_eq!(at(109.0, 65.0), Some(1));
+        assert_eq!(at(50.0, 99.0), Some(2));
+        //Note(teddy) Past the last row, above the list or beside it collapses without a selection
+        assert_eq!(at(50.0, 100.0), None);
+        assert_eq!(at(50.0, 39.0), None);
+        assert_eq!(at(110.0, 50.0), None);
+    }
+
+    //Note(teddy) Containers and text views need a gl context, the lookup only needs ids and children
+    struct StubView {
+        view: ViewObject,
+        children: Option<Vec<Box<dyn View>>>,
+        events: Vec<CursorEvent>,
+    }
+
+    impl StubView {
+        fn new(id: &str, children: Option<Vec<Box<dyn View>>>) -> Box<dyn View> {
+            Box::new(Self::sized(id, ViewDimens::zerod(), None, children))
+        }
+
+        fn sized(id: &str, position: ViewPosition, size: Option<ViewDimens>, children: Option<Vec<Box<dyn View>>>) -> Self {
+            let view = ViewObject {
+                cursor_hover_state: CursorState::Neither,
+                id: Box::from(id),
+                background_vao: 0,
+                background_vbo: 0,
+                size,
+                background: None,
+                padding: 0,
+                scale: 1.0,
+                position,
+                z_index: None,
+                margin: Margin::zerod(),
+                anchor: None,
+                anchor_offset: ViewPosition::zerod(),
+                min_size: None,
+                tooltip: None,
+                visible: true,
+                style: QuadStyle::default(),
+                drag_payload: None,
+                on_drop: None,
+                allocated: None,
+                resized: false,
+                on_resize: None,
+            };
+            Self { view, children, events: vec![] }
+        }
+    }
+
+    impl View for StubView {
+        fn get_id(&self) -> &str {
+            &self.view.id
+        }
+        fn update(&mut self, _engine: &Engine) -> UIResult {
+            Ok(())
+        }
+        fn get_view_object(&self) -> &ViewObject {
+            &self.view
+        }
+        fn get_view_object_mut(&mut self) -> &mut ViewObject {
+            &mut self.view
+        }
+        fn handle_button_click(&mut self, _engine: &Engine, _click: &ClickInfo) -> bool {
+            true
+        }
+        fn set_position(&mut self, position: ViewPosition) {
+            self.view.position = position;
+        }
+        fn get_position(&self) -> Option<ViewPosition> {
+            None
+        }
+        fn get_view_dimensions(&self) -> Option<ViewDimens> {
+            self.view.size
+        }
+        fn layout(&mut self, parent: ViewRect) {
+            apply_anchor(self, parent);
+            let bounds = self.bounds();
+            if let Some(children) = &mut self.children {
+                layout_children(children, bounds);
+            }
+        }
+        fn object_at(&self, cords: &Cords<f32>, accept: &dyn Fn(&ViewObject) -> bool) -> Option<&ViewObject> {
+            let children = self.children.iter().flatten();
+            children.filter_map(|view| view.object_at(cords, accept)).next().or_else(|| own_object(self, cords, accept))
+        }
+        fn get_element_by_id(&mut self, _id: &str) -> Option<Rc<&mut dyn View>> {
+            None
+        }
+        fn as_any(&mut self) -> Box<&mut dyn Any> {
+            Box::new(self)
+        }
+        fn on_cursor_enter(&mut self) {
+            self.events.push(CursorEvent::Enter);
+        }
+        fn on_cursor_exit(&mut self) {
+            self.events.push(CursorEvent::Exit);
+        }
+        fn as_container(&self) -> Option<&dyn ViewContainer> {
+            self.children.as_ref().map(|_| self as &dyn ViewContainer)
+        }
+        fn as_container_mut(&mut self) -> Option<&mut dyn ViewContainer> {
+            match self.children {
+                Some(_) => Some(self),
+                None => None,
             }
+        }
+    }
+
+    impl ViewContainer for StubView {
+        fn add_child(&mut self, child: Box<dyn View>) {
+            self.children.get_or_insert_with(Vec::new).push(child);
+        }
+        fn remove_child(&mut self, _child_id: &str) -> UIResult {
+            Ok(())
+        }
+        fn get_view_by_id(&self, child_id: &str) -> Result<&Box<dyn View>, UIError> {
+            find_child(self.children.as_deref().unwrap_or(&[]), child_id)
+        }
+        fn get_view_by_id_mut(&mut self, child_id: &str) -> Result<&mut Box<dyn View>, UIError> {
+            find_child_mut(self.children.as_deref_mut().unwrap_or(&mut []), child_id)
+        }
+        fn take_child(&mut self, child_id: &str) -> Result<(Box<str>, usize, Box<dyn View>), UIError> {
+            take_from_children(&self.view.id, self.children.get_or_insert_with(Vec::new), child_id)
+        }
+        fn insert_child(&mut self, index: usize, child: Box<dyn View>) {
+            let children = self.children.get_or_insert_with(Vec::new);
+            children.insert(index.min(children.len()), child);
+        }
+    }
 
-            _ => (),
-        };
+    #[test]
+    fn test_find_view_recurses_into_containers() {
+        let log = StubView::new("ui_log", Some(vec![StubView::new("screenshot", None)]));
+        let assets = StubView::new("asset_list", Some(vec![StubView::new("text_crate.obj", None)]));
 
-        let default_text_color: Vector3<f32> = Vector3::new(1.0, 1.0, 1.0);
-        let color = match &self.color {
-            Some(color) => color,
-            None => &default_text_color,
-        };
+        let mut tree = UITree::new();
+        tree.root = Some(StubView::new("simple_container", Some(vec![StubView::new("save", None), assets, log])));
 
-        unsafe {
-            let size = self.view.size.unwrap();
+        assert_eq!(tree.find_view("simple_container").unwrap().get_id(), "simple_container");
+        assert_eq!(tree.find_view("save").unwrap().get_id(), "save");
+        assert_eq!(tree.find_view("screenshot").unwrap().get_id(), "screenshot");
+        assert!(matches!(tree.find_view("missing"), Err(UIError::ViewNotFound)));
 
-            let text_position = (
-                (self.view.position.x + self.view.padding) as f32,
-                (self.view.position.y + engine.font_face.font_size as i32 - self.view.padding)
-                    as f32,
-            );
+        let log = tree.find_view_mut("ui_log").unwrap().as_container_mut().unwrap();
+        log.add_child(StubView::new("physics", None));
+        assert_eq!(tree.find_view_mut("physics").unwrap().get_id(), "physics");
+        assert_eq!(tree.find_view_mut("text_crate.obj").unwrap().get_id(), "text_crate.obj");
+    }
 
-            let quad_size = (
-                (size.y + (self.view.padding << 1)) as f32,
-                (size.x + (self.view.padding << 1)) as f32,
-            );
+    #[test]
+    fn test_z_index_orders_drawing_and_depth() {
+        let mut children = vec![StubView::new("panel", None), StubView::new("dialog", None), StubView::new("label", None)];
+        children[1].get_view_object_mut().z_index = Some(2);
+        children[2].get_view_object_mut().z_index = Some(0);
+
+        //Note(teddy) Equal indices keep insertion order, the dialog draws last and is clicked first
+        assert_eq!(draw_order(&children), vec![0, 2, 1]);
+
+        let depths = [
+            quad_depth(None, QuadLayer::Container),
+            quad_depth(None, QuadLayer::Background),
+            quad_depth(None, QuadLayer::Detail),
+            quad_depth(Some(1), QuadLayer::Container),
+            quad_depth(Some(1), QuadLayer::Background),
+        ];
+        assert!(depths.windows(2).all(|pair| pair[0] < pair[1]));
+        //Note(teddy) Even the highest index stays behind the text
+        assert!(quad_depth(Some(u32::MAX), QuadLayer::Detail) < 1.0);
+    }
 
-            draw_text(
-                self.text_vao as u32,
-                self.text_vbo as u32,
-                &engine,
-                self.text_shader_id,
-                self.text.as_str(),
-                text_position.0,
-                text_position.1,
-                1.0,
-                color,
-            );
-            draw_quad_with_default_shader(
-                engine,
-                self.view.background_vao as u32,
-                self.view.background_vbo as u32,
-                -0.9,
-                (self.view.position.x as f32, self.view.position.y as f32),
-                quad_size,
-                // &[0.2, 0.2, 0.2],
-                &self.view.background_color,
-            );
+    #[test]
+    fn test_consumed_click_stops_propagation() {
+        let mut children = vec![StubView::new("save", None), StubView::new("load", None), StubView::new("dialog", None)];
+        for (index, view) in children.iter_mut().enumerate() {
+            let view = view.get_view_object_mut();
+            view.position = ViewPosition::new(0, index as i32 * 20);
+            view.size = Some(ViewDimens::new(100, 20));
         }
 
-        Ok(())
+        //Note(teddy) The dialog sits on top of load and covers the same rows
+        children[2].get_view_object_mut().position = ViewPosition::new(0, 20);
+        children[2].get_view_object_mut().z_index = Some(1);
+
+        let click = |children: &mut Vec<Box<dyn View>>, cords: Cords<f32>| {
+            let mut offered = vec![];
+            let consumed = offer_click(children, |view| {
+                offered.push(String::from(view.get_id()));
+                let object = view.get_view_object();
+                does_cursor_intersect(&cords, object.position, object.size.unwrap(), object.padding)
+            });
+            (consumed, offered)
+        };
+
+        assert_eq!(click(&mut children, Cords { x: 50.0, y: 30.0 }), (true, vec![String::from("dialog")]));
+
+        let (consumed, offered) = click(&mut children, Cords { x: 50.0, y: 10.0 });
+        assert!(consumed);
+        assert_eq!(offered, vec!["dialog", "load", "save"]);
+
+        //Note(teddy) Nothing under the cursor, the engine casts its picking ray
+        let (consumed, offered) = click(&mut children, Cords { x: 500.0, y: 10.0 });
+        assert!(!consumed);
+        assert_eq!(offered.len(), 3);
     }
 
-    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
-        self.compute_intersect_with_cursor_cords(&engine, &cords);
+    #[test]
+    fn test_tab_cycles_focus() {
+        let ids: Vec<Box<str>> = vec![Box::from("console_input"), Box::from("level_author"), Box::from("level_description")];
+
+        assert_eq!(next_focus(&ids, None).as_deref(), Some("console_input"));
+        assert_eq!(next_focus(&ids, Some("console_input")).as_deref(), Some("level_author"));
+        assert_eq!(next_focus(&ids, Some("level_description")).as_deref(), Some("console_input"));
+        //Note(teddy) A focused view that is gone from the tree starts the cycle over
+        assert_eq!(next_focus(&ids, Some("removed")).as_deref(), Some("console_input"));
+        assert_eq!(next_focus(&[], Some("console_input")), None);
     }
 
-    fn handle_button_click(
-        &mut self,
-        engine: &Engine,
-        clicked_buttons: &Vec<MouseButton>,
-        cords: Cords<f32>,
-    ) -> bool {
-        //dbg!(&cords);
+    #[test]
+    fn test_hit_test_matches_drawn_quad() {
+        use crate::renderer::draw::{quad_vertices, window_to_gl_y};
+
+        const VIEWPORT_HEIGHT: f32 = 600.0;
+        let rect = ViewRect::new(ViewPosition::new(100, 200), ViewDimens::new(50, 20), 4);
+        assert_eq!(rect.min, Cords { x: 100.0, y: 200.0 });
+        assert_eq!(rect.max, Cords { x: 158.0, y: 228.0 });
+
+        //Note(teddy) Run the quad through the same flip the renderer does and map it back to window cords
+        let (position, size) = rect.quad_args();
+        let vertices = quad_vertices(0.0, (position.0, window_to_gl_y(VIEWPORT_HEIGHT, position.1)), size);
+        let xs = vertices.iter().map(|vertex| vertex[0]);
+        let ys = vertices.iter().map(|vertex| VIEWPORT_HEIGHT - vertex[1]);
+        let fold = |values: &mut dyn Iterator<Item = f32>| values.fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        assert_eq!(fold(&mut xs.clone()), (rect.min.x, rect.max.x));
+        assert_eq!(fold(&mut ys.clone()), (rect.min.y, rect.max.y));
+
+        let hit = |x: f32, y: f32| does_cursor_intersect(&Cords { x, y }, ViewPosition::new(100, 200), ViewDimens::new(50, 20), 4);
+        assert!(hit(125.0, 214.0));
+        //Note(teddy) The padding is part of the view, both the min corner and the far padding edge hit
+        assert!(hit(100.0, 200.0));
+        assert!(hit(157.5, 227.5));
+        assert!(!hit(99.5, 214.0));
+        assert!(!hit(158.0, 214.0));
+        assert!(!hit(125.0, 228.0));
+        assert!(!hit(125.0, 199.5));
+        //Note(teddy) Without padding the same cursor is past the edge
+        assert!(!does_cursor_intersect(&Cords { x: 152.0, y: 214.0 }, ViewPosition::new(100, 200), ViewDimens::new(50, 20), 0));
+    }
 
-        if does_cursor_intersect(
-            &cords,
-            self.get_position().unwrap(),
-            self.view.size.unwrap_or(ViewDimens::zerod()),
-            self.view.padding,
-        ) {
-            //Note(teddy) Left Click
-            let self_ptr: *mut TextView = self;
-            if let Some(_) = button_clicked!(clicked_buttons, MouseButton::Button1) {
-                if let Some(func) = &self.on_click {
-                    func(self_ptr);
-                }
-            }
-            if let Some(_) = button_clicked!(clicked_buttons, MouseButton::Button2) {
-                //Right Click
-                if let Some(func) = &self.on_right_click {
-                    func(self_ptr);
-                }
-            }
-            if let Some(_) = button_clicked!(clicked_buttons, MouseButton::Button3) {
-                //Middleclick
-                println!("Middle click was clicked");
-                if let Some(func) = &self.on_middle_click {
-                    func(self_ptr);
-                }
-            }
+    #[test]
+    fn test_cursor_enter_and_exit_fire_once() {
+        let mut panel = StubView::sized("panel", ViewPosition::new(0, 0), Some(ViewDimens::new(200, 100)), Some(vec![]));
+        let mut button = StubView::sized("button", ViewPosition::new(10, 10), Some(ViewDimens::new(50, 20)), None);
+
+        //Note(teddy) The cursor moves into the panel, across the button, stays on it, then leaves both
+        let path = [(300.0, 300.0), (150.0, 50.0), (20.0, 15.0), (30.0, 20.0), (40.0, 25.0), (150.0, 50.0), (300.0, 50.0), (400.0, 50.0)];
+        for (x, y) in path.iter() {
+            let cords = Cords { x: *x, y: *y };
+            panel.update_hover(&cords);
+            button.update_hover(&cords);
         }
 
-        true
-    }
+        assert_eq!(button.events, vec![CursorEvent::Enter, CursorEvent::Exit]);
+        assert_eq!(panel.events, vec![CursorEvent::Enter, CursorEvent::Exit]);
+        assert!(!panel.is_hovered());
 
-    fn get_view_dimensions(&self) -> Option<ViewDimens> {
-        match self.view.size {
-            Some(size) => Some(ViewDimens::new(
-                size.x + (self.view.padding << 1),
-                size.y + (self.view.padding << 1),
-            )),
+        panel.update_hover(&Cords { x: 1.0, y: 1.0 });
+        assert!(panel.is_hovered());
+        assert_eq!(panel.events.len(), 3);
+    }
 
-            None => None,
+    fn move_cursor(view: &mut Box<dyn View>, cords: Cords<f32>) {
+        match (*view.as_any()).downcast_mut::<SimpleUIContainer>() {
+            Some(container) => container.track_cursor(cords, &mut move_cursor),
+            None => view.update_hover(&cords),
         }
     }
 
-    fn set_position(&mut self, position: ViewPosition) {
-        self.view.position = position;
+    #[test]
+    fn test_cursor_outside_a_container_skips_its_children() {
+        //Note(teddy) The inner child was never stacked, it's still where it was built, past the containers' edges
+        let mut inner = SimpleUIContainer::new(Box::from("inner"), None, ViewPosition::zerod(), Orientation::Vertical, 0, 1.0, false);
+        inner.add_child(Box::new(StubView::sized("deep", ViewPosition::new(500, 500), Some(ViewDimens::new(40, 40)), None)));
+        let mut outer = SimpleUIContainer::new(Box::from("outer"), None, ViewPosition::zerod(), Orientation::Vertical, 0, 1.0, false);
+        outer.add_child(Box::new(inner));
+        let mut root: Box<dyn View> = Box::new(outer);
+
+        move_cursor(&mut root, Cords { x: 510.0, y: 510.0 });
+        let tree = root.as_container().unwrap();
+        assert!(!root.is_hovered());
+        assert!(!tree.get_view_by_id("inner").unwrap().is_hovered());
+        assert!(!tree.get_view_by_id("deep").unwrap().is_hovered());
+
+        move_cursor(&mut root, Cords { x: 5.0, y: 5.0 });
+        assert!(root.is_hovered());
+        assert!(root.as_container().unwrap().get_view_by_id("inner").unwrap().is_hovered());
     }
 
-    fn get_position(&self) -> Option<ViewPosition> {
-        Some(self.view.position)
-    }
+    #[test]
+    fn test_image_fits_its_view() {
+        let bounds = ViewDimens::new(128, 72);
 
-    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
-        if id == self.get_id() {
-            Some(Rc::new(self))
-        } else {
-            None
-        }
+        assert_eq!(fit_image(ViewDimens::new(64, 64), bounds, ImageScaling::Stretch), (ViewPosition::zerod(), bounds));
+        //Note(teddy) A square texture in a wide view is pillarboxed, a wide one in a square view letterboxed
+        assert_eq!(
+            fit_image(ViewDimens::new(64, 64), bounds, ImageScaling::PreserveAspect),
+            (ViewPosition::new(28, 0), ViewDimens::new(72, 72))
+        );
+        assert_eq!(
+            fit_image(ViewDimens::new(256, 144), ViewDimens::new(64, 64), ImageScaling::PreserveAspect),
+            (ViewPosition::new(0, 14), ViewDimens::new(64, 36))
+        );
+        //Note(teddy) Without a native size there is no aspect to keep
+        assert_eq!(fit_image(ViewDimens::zerod(), bounds, ImageScaling::PreserveAspect), (ViewPosition::zerod(), bounds));
     }
-}
 
-fn does_cursor_intersect(
-    cords: &Cords<f32>,
-    position: ViewDimens,
-    size: ViewDimens,
-    padding: i32,
-) -> bool {
-    let quad_size = (
-        (size.x + (padding << 1)) as f32,
-        (size.y + (padding << 1)) as f32,
-    );
+    #[test]
+    fn test_text_wraps_and_truncates() {
+        let font_face = font_face();
+        //Note(teddy) a and e are 8 wide, spaces and dots fall back to the 7 wide glyph
+        assert_eq!(get_the_length_of_text("aa ee", &font_face), 39);
+
+        assert_eq!(layout_text("aa ee aaa", None, TextOverflow::Wrap, &font_face), vec!["aa ee aaa"]);
+        assert_eq!(layout_text("aa ee aaa", Some(40), TextOverflow::Wrap, &font_face), vec!["aa ee", "aaa"]);
+        assert_eq!(layout_text("aa\nee", Some(100), TextOverflow::Wrap, &font_face), vec!["aa", "ee"]);
+        //Note(teddy) A word wider than the limit is broken so no line runs past it
+        assert_eq!(layout_text("aaaaa ee", Some(24), TextOverflow::Wrap, &font_face), vec!["aaa", "aa", "ee"]);
+        assert_eq!(layout_text("", Some(24), TextOverflow::Wrap, &font_face), vec![""]);
+
+        assert_eq!(layout_text("aa ee", Some(40), TextOverflow::Ellipsis, &font_face), vec!["aa ee"]);
+        let truncated = layout_text("aaaaaaaa", Some(40), TextOverflow::Ellipsis, &font_face);
+        assert_eq!(truncated, vec!["aa..."]);
+        assert!(get_the_length_of_text(&truncated[0], &font_face) <= 40);
+    }
 
-    let quad_position = (position.x as f32, position.y as f32);
+    #[test]
+    fn test_text_alignment_offsets() {
+        assert_eq!(align_offset(100.0, 40.0, TextAlign::Left.factor()), 0.0);
+        assert_eq!(align_offset(100.0, 40.0, TextAlign::Center.factor()), 30.0);
+        assert_eq!(align_offset(100.0, 40.0, TextAlign::Right.factor()), 60.0);
+        assert_eq!(align_offset(32.0, 12.0, VerticalAlign::Bottom.factor()), 20.0);
+        //Note(teddy) Text wider than its space stays flush left instead of running off the start
+        assert_eq!(align_offset(20.0, 40.0, TextAlign::Right.factor()), 0.0);
+
+        let text = ViewDimens::new(40, 12);
+        let assigned = ViewDimens::new(200, 52);
+        assert_eq!(aligned_size(text, assigned, 10, TextAlign::Left, VerticalAlign::Top), text);
+        assert_eq!(aligned_size(text, assigned, 10, TextAlign::Center, VerticalAlign::Top), ViewDimens::new(180, 12));
+        assert_eq!(aligned_size(text, assigned, 10, TextAlign::Left, VerticalAlign::Center), ViewDimens::new(40, 32));
+        //Note(teddy) A container narrower than the text never shrinks it
+        assert_eq!(aligned_size(text, ViewDimens::new(30, 20), 10, TextAlign::Right, VerticalAlign::Bottom), text);
+    }
 
-    //dbg!(&cords);
-    //dbg!(&quad_position);
-    let min_x = quad_position.0;
-    let min_y = quad_position.1;
+    #[test]
+    fn test_font_cache_reuses_loaded_sizes() {
+        use crate::core::FontCache;
 
-    //FIXME(teddy): The length doesn't seem to match the actual screen length
-    let max_x = min_x + quad_size.0;
-    let max_y = min_y + quad_size.1;
+        let default = Rc::new(font_face());
+        let fonts = FontCache::new(default.clone());
 
-    (cords.x > min_x as f32 && cords.x < max_x as f32)
-        && (cords.y > min_y as f32 && cords.y < max_y as f32)
-}
+        //Note(teddy) A size that is already loaded comes back without touching freetype or gl
+        let face = fonts.get(12).unwrap();
+        assert!(Rc::ptr_eq(&face, &default));
+        assert_eq!(get_the_length_of_text("ae", &face), 16);
+    }
 
-fn get_the_length_of_text(text: &String, font_face: &FontFace) -> u32 {
-    let mut length = 0;
-    for c in text.chars() {
-        let font_char = &font_face.chars[&c];
+    #[test]
+    fn test_measuring_characters_outside_the_atlas() {
+        let font_face = font_face();
 
-        length += (font_char.advance >> 6) as u32;
+        //Note(teddy) é has its own glyph, the arrow isn't in the face and is measured as the fallback glyph
+        assert_eq!(get_the_length_of_text("é\u{2192}", &font_face), 9 + 7);
+        assert!(font_face.has_glyph('é'));
+        assert!(!font_face.has_glyph('\u{2192}'));
+        assert_eq!(font_face.glyph('\u{2192}').unwrap().advance, font_face.glyph('?').unwrap().advance);
+        assert_eq!(layout_text("ae \u{2192} ae", Some(20), TextOverflow::Wrap, &font_face), vec!["ae", "\u{2192}", "ae"]);
     }
 
-    length
-}
-
-///Create framebuffer
-/// Create shader id
-pub fn init_ui(engine: &mut Engine, world: &mut World) -> UIResult {
-    let mut fbo: u32 = 0;
+    #[test]
+    fn test_stacking_with_margin_and_spacing() {
+        let rows = [
+            (ViewDimens::new(100, 32), Margin::zerod()),
+            (ViewDimens::new(60, 32), Margin::new(2, 0, 6, 10)),
+            (ViewDimens::new(80, 20), Margin::zerod()),
+        ];
+
+        let (positions, size) = stack_children(ViewPosition::new(5, 50), Orientation::Vertical, 4, &rows);
+        assert_eq!(positions, vec![ViewPosition::new(5, 50), ViewPosition::new(15, 88), ViewPosition::new(5, 130)]);
+        //Note(teddy) Two gaps between three rows, margins count towards the size but spacing never trails
+        assert_eq!(size, ViewDimens::new(100, 32 + 4 + 2 + 32 + 6 + 4 + 20));
+
+        let (positions, size) = stack_children(ViewPosition::zerod(), Orientation::Horizontal, 4, &rows);
+        assert_eq!(positions, vec![ViewPosition::new(0, 0), ViewPosition::new(114, 2), ViewPosition::new(178, 0)]);
+        assert_eq!(size, ViewDimens::new(258, 40));
+
+        assert_eq!(stack_children(ViewPosition::zerod(), Orientation::Vertical, 4, &[]), (vec![], ViewDimens::zerod()));
+
+        //Note(teddy) The margin keeps the row apart without growing the box its hover is tested against
+        let mut row = StubView::sized("row", ViewPosition::zerod(), Some(ViewDimens::new(60, 32)), None);
+        row.set_margin(Margin::all(4));
+        assert_eq!(row.bounds(), ViewRect::new(ViewPosition::zerod(), ViewDimens::new(60, 32), 0));
+    }
 
-    unsafe {
-        ENGINE_PTR = engine;
+    #[test]
+    fn test_resolving_anchors() {
+        let parent = ViewRect::new(ViewPosition::new(10, 20), ViewDimens::new(200, 100), 0);
+        let size = ViewDimens::new(50, 30);
+        let offset = ViewPosition::new(4, 6);
+
+        assert_eq!(resolve_anchor(Anchor::TopLeft, offset, size, parent), (ViewPosition::new(14, 26), size));
+        assert_eq!(resolve_anchor(Anchor::TopRight, offset, size, parent), (ViewPosition::new(156, 26), size));
+        assert_eq!(resolve_anchor(Anchor::BottomLeft, offset, size, parent), (ViewPosition::new(14, 84), size));
+        assert_eq!(resolve_anchor(Anchor::BottomRight, offset, size, parent), (ViewPosition::new(156, 84), size));
+        assert_eq!(resolve_anchor(Anchor::Center, ViewPosition::zerod(), size, parent), (ViewPosition::new(85, 55), size));
+        assert_eq!(
+            resolve_anchor(Anchor::StretchVertical, offset, size, parent),
+            (ViewPosition::new(14, 26), ViewDimens::new(50, 88))
+        );
+        assert_eq!(
+            resolve_anchor(Anchor::StretchHorizontal, offset, size, parent),
+            (ViewPosition::new(14, 26), ViewDimens::new(192, 30))
+        );
+        assert_eq!(resolve_anchor(Anchor::Fill, offset, size, parent), (ViewPosition::new(14, 26), ViewDimens::new(192, 88)));
     }
 
-    unsafe {
-        engine.ui_render_object = Some(FrameRenderObject::new(engine.camera.view_port, true));
+    #[test]
+    fn test_right_anchored_panel_tracks_resize() {
+        let mut panel = StubView::sized("panel", ViewPosition::zerod(), Some(ViewDimens::new(120, 300)), None);
+        panel.get_view_object_mut().anchor = Some(Anchor::TopRight);
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(panel));
+
+        for width in [800, 1280, 640] {
+            tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(width, 600), 0));
+            let bounds = tree.root.as_ref().unwrap().bounds();
+            assert_eq!(bounds.max.x, width as f32);
+            assert_eq!(bounds.width(), 120.0);
+        }
     }
 
-    let _ = world.add_resource(AssetSource::Shader(
-        font_shader!(),
-        String::from("font_vert.glsl"),
-        String::from("font_frag.glsl"),
-        None,
-    ));
+    #[test]
+    fn test_nested_anchors_resolve_against_the_parent() {
+        let mut badge = StubView::sized("badge", ViewPosition::zerod(), Some(ViewDimens::new(10, 10)), None);
+        badge.get_view_object_mut().anchor = Some(Anchor::BottomRight);
+        badge.get_view_object_mut().anchor_offset = ViewPosition::new(2, 2);
 
-    let _ = world.add_resource(AssetSource::Shader(
-        quad_shader!(),
-        String::from("ui_quad_vert.glsl"),
-        String::from("ui_quad_frag.glsl"),
-        None,
-    ));
+        let mut panel = StubView::sized("panel", ViewPosition::zerod(), Some(ViewDimens::new(100, 50)), Some(vec![Box::new(badge)]));
+        panel.get_view_object_mut().anchor = Some(Anchor::StretchVertical);
+        panel.get_view_object_mut().anchor_offset = ViewPosition::new(20, 0);
 
-    let shader_container_ref = &world.resources.read().unwrap().shaders;
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(panel));
+        tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(800, 600), 0));
 
-    loop {
-        if shader_container_ref.get(&font_shader!()).is_some()
-            && shader_container_ref.get(&quad_shader!()).is_some()
-        {
-            let shader_id = &shader_container_ref[&font_shader!()].unwrap();
-            let quad_shader_id = &shader_container_ref[&quad_shader!()].unwrap();
-            unsafe {
-                SHADER_TEXT_ID = *shader_id;
-                UI_QUAD_SHADER_ID = *quad_shader_id;
-            };
-            break;
-        }
+        //Note(teddy) The panel is stretched to the window's height first, the badge sits in its bottom right corner
+        let panel_bounds = tree.root.as_ref().unwrap().bounds();
+        assert_eq!(panel_bounds, ViewRect::new(ViewPosition::new(20, 0), ViewDimens::new(100, 600), 0));
+        assert_eq!(tree.find_view("badge").unwrap().get_view_object().position, ViewPosition::new(108, 588));
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_stretched_views_shrink_with_the_parent() {
+        let child = StubView::sized("child", ViewPosition::zerod(), Some(ViewDimens::new(80, 40)), None);
+        let mut panel = SimpleUIContainer::new(Box::from("panel"), None, ViewPosition::zerod(), Orientation::Vertical, 10, 1.0, false);
+        panel.add_child(Box::new(child));
+        panel.get_view_object_mut().anchor = Some(Anchor::StretchVertical);
+
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(panel));
+
+        for (width, height) in [(800, 600), (800, 400), (640, 700)] {
+            tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(width, height), 0));
+            let bounds = tree.root.as_ref().unwrap().bounds();
+            assert_eq!(bounds.height(), height as f32);
+            assert_eq!(bounds.width(), 100.0);
+        }
 
-pub fn propagate_cursor_pos_to_ui(engine: *mut Engine, cords: Cords<f32>) {
-    unsafe {
-        if let Some(view) = &mut (&mut *engine).get_ui_tree().unwrap().root {
-            view.receive_cursor_cords(&mut *engine, cords);
+        let mut overlay = StubView::sized("overlay", ViewPosition::zerod(), Some(ViewDimens::new(10, 10)), None);
+        overlay.get_view_object_mut().anchor = Some(Anchor::Fill);
+        tree.root = Some(Box::new(overlay));
+
+        for (width, height) in [(800, 600), (320, 200)] {
+            tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(width, height), 0));
+            let bounds = tree.root.as_ref().unwrap().bounds();
+            assert_eq!((bounds.width(), bounds.height()), (width as f32, height as f32));
         }
     }
-}
 
-///Mouse click propagated and received by the ui will return false;
-///Incase a ui element receives and process the event, it should return a false
-pub fn propagate_button_click(
-    engine: *mut Engine,
-    button: &Vec<MouseButton>,
-    cords: Cords<f32>,
-) -> bool {
-    let mut result = true;
-    let eng_ref = unsafe { engine.as_mut().unwrap() };
-    let ref_for_view = unsafe { engine.as_mut().unwrap() };
+    #[test]
+    fn test_grid_cells_fill_row_major() {
+        //Note(teddy) Two inspector rows, a label column and an input column
+        let children = [
+            (ViewDimens::new(80, 20), Margin::zerod()),
+            (ViewDimens::new(120, 30), Margin::zerod()),
+            (ViewDimens::new(100, 20), Margin::new(0, 0, 4, 0)),
+            (ViewDimens::new(60, 30), Margin::zerod()),
+            (ViewDimens::new(40, 10), Margin::zerod()),
+        ];
+
+        let (cells, size) = grid_cells(ViewPosition::new(10, 10), 2, 5, &children);
+        let cell = |x, y, width, height| ViewRect::new(ViewPosition::new(x, y), ViewDimens::new(width, height), 0);
+
+        assert_eq!(
+            cells,
+            vec![
+                cell(10, 10, 100, 30),
+                cell(115, 10, 120, 30),
+                cell(10, 45, 100, 30),
+                cell(115, 45, 120, 30),
+                cell(10, 80, 100, 10),
+            ]
+        );
+        assert_eq!(size, ViewDimens::new(100 + 5 + 120, 30 + 5 + 30 + 5 + 10));
 
-    if let Some(view) = &mut eng_ref.get_ui_tree().unwrap().root {
-        result = view.handle_button_click(ref_for_view, button, cords);
+        //Note(teddy) Fewer children than columns leaves no empty columns behind
+        assert_eq!(grid_cells(ViewPosition::zerod(), 4, 5, &children[..1]).1, ViewDimens::new(80, 20));
+        assert_eq!(grid_cells(ViewPosition::zerod(), 2, 5, &[]), (vec![], ViewDimens::zerod()));
+
+        assert_eq!(cell_at(&cells, &Cords { x: 120.0, y: 50.0 }), Some(3));
+        assert_eq!(cell_at(&cells, &Cords { x: 112.0, y: 50.0 }), None);
+        assert_eq!(cell_at(&cells, &Cords { x: 150.0, y: 85.0 }), None);
     }
 
-    // result
-    false
-}
+    #[test]
+    fn test_confirm_dialog_layout() {
+        let (size, [message, confirm, cancel]) = confirm_dialog_layout(
+            ViewPosition::new(16, 16),
+            ViewDimens::new(200, 24),
+            ViewDimens::new(50, 20),
+            ViewDimens::new(60, 30),
+        );
 
-pub fn propagate_key_stroke(engine: *mut Engine, key: glfw::Key) -> bool {
-    unimplemented!()
-}
+        assert_eq!(size, ViewDimens::new(200, 24 + CONFIRM_DIALOG_GAP + 30));
+        assert_eq!(message, ViewPosition::new(16, 16));
+        //Note(teddy) Confirm sits against the right edge and cancel to its left
+        assert_eq!(confirm, ViewPosition::new(16 + 200 - 50, 16 + 24 + CONFIRM_DIALOG_GAP));
+        assert_eq!(cancel, ViewPosition::new(confirm.x - CONFIRM_DIALOG_GAP - 60, confirm.y));
 
-pub enum Orientation {
-    Vertical,
-    Horizontal,
-}
+        //Note(teddy) A short message leaves the buttons to set the width
+        let (size, _) =
+            confirm_dialog_layout(ViewPosition::zerod(), ViewDimens::new(40, 12), ViewDimens::new(50, 20), ViewDimens::new(60, 20));
+        assert_eq!(size.x, 50 + CONFIRM_DIALOG_GAP + 60);
+    }
 
-pub struct SimpleUIContainer {
-    children: Vec<Box<dyn View>>,
-    orientation: Orientation,
-    view: ViewObject,
-}
+    #[test]
+    fn test_modal_takes_the_input() {
+        let mut tree = UITree::new();
+        tree.root = Some(StubView::new("root", None));
+        tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(800, 600), 0));
+        assert_eq!(tree.input_root().unwrap().get_id(), "root");
+
+        tree.push_modal(Box::new(StubView::sized("dialog", ViewPosition::zerod(), Some(ViewDimens::new(200, 100)), None)));
+        assert!(tree.has_modal());
+        assert_eq!(tree.input_root().unwrap().get_id(), "dialog");
+        //Note(teddy) Centered in the viewport the tree was last laid out against
+        assert_eq!(tree.input_root().unwrap().get_view_object().position, ViewPosition::new(300, 250));
+
+        tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(1000, 600), 0));
+        assert_eq!(tree.input_root().unwrap().get_view_object().position, ViewPosition::new(400, 250));
+
+        //Note(teddy) The stub never closes itself, only popping removes it
+        tree.pop_closed_modals();
+        assert!(tree.has_modal());
+        assert_eq!(tree.pop_modal().unwrap().get_id(), "dialog");
+        assert_eq!(tree.input_root().unwrap().get_id(), "root");
+    }
 
-impl SimpleUIContainer {
-    pub fn new(
-        id: Box<str>,
-        dimensions: Option<ViewDimens>,
-        position: ViewPosition,
-        orientation: Orientation,
-        padding: i32,
-        scale: f32,
-    ) -> Self {
-        Self {
-            view: ViewObject::new(
-                id,
-                position,
-                dimensions,
-                padding,
-                scale,
-                Box::new([1.0, 1.0, 1.0]),
-                None,
-            ),
-            children: vec![],
-            orientation,
-        }
+    #[test]
+    fn test_tooltip_shows_after_the_delay() {
+        let mut asset = StubView::sized("asset", ViewPosition::zerod(), Some(ViewDimens::new(100, 20)), None);
+        asset.set_tooltip(String::from("./assets/objects/a_long_name.obj"));
+        let list = StubView::sized("list", ViewPosition::zerod(), Some(ViewDimens::new(100, 200)), Some(vec![Box::new(asset)]));
+
+        let over = Cords { x: 50.0, y: 10.0 };
+        assert_eq!(list.tooltip_at(&over), Some(("asset", "./assets/objects/a_long_name.obj")));
+        assert_eq!(list.tooltip_at(&Cords { x: 50.0, y: 100.0 }), None);
+
+        let mut state = TooltipState::new();
+        state.hover(list.tooltip_at(&over), over);
+        state.tick(0.3);
+        assert_eq!(state.visible(), None);
+        //Note(teddy) Moving within the same view keeps the time hovered
+        state.hover(list.tooltip_at(&Cords { x: 60.0, y: 10.0 }), over);
+        state.tick(0.3);
+        assert_eq!(state.visible(), Some("./assets/objects/a_long_name.obj"));
+
+        state.dismiss();
+        assert_eq!(state.visible(), None);
+
+        state.hover(None, OUTSIDE_CORDS);
+        state.tick(1.0);
+        assert_eq!(state.visible(), None);
+        state.hover(list.tooltip_at(&over), over);
+        assert_eq!(state.visible(), None);
     }
 
-    fn recalculate_dimensions(&mut self) {
-        let mut new_dimensions = ViewDimens::zerod();
+    #[test]
+    fn test_tooltip_stays_inside_the_viewport() {
+        let viewport = ViewRect::new(ViewPosition::zerod(), ViewDimens::new(800, 600), 0);
+        let size = ViewDimens::new(200, 30);
 
-        //Note(teddy) Updating the length and height based on the orientation of the container
-        match self.orientation {
-            Orientation::Vertical => {
-                let mut height: i32 = 0;
-                let mut view_dimens = ViewDimens::zerod();
+        assert_eq!(tooltip_position(Cords { x: 100.0, y: 100.0 }, size, viewport), ViewPosition::new(116, 116));
+        assert_eq!(tooltip_position(Cords { x: 790.0, y: 590.0 }, size, viewport), ViewPosition::new(600, 570));
+        //Note(teddy) Wider than the viewport, the start of the text stays on screen
+        assert_eq!(tooltip_position(Cords { x: 10.0, y: 10.0 }, ViewDimens::new(900, 30), viewport), ViewPosition::new(0, 26));
+    }
 
-                for child in self.children.iter() {
-                    view_dimens = child.get_view_dimensions().unwrap_or(ViewDimens::zerod());
-                    new_dimensions.x = std::cmp::max(view_dimens.x, new_dimensions.x);
-                    height += view_dimens.y;
-                }
+    #[test]
+    fn test_command_queue_drains_by_type() {
+        let mut commands = CommandQueue::default();
+        commands.push(1usize);
+        commands.push(String::from("select"));
+        commands.push(2usize);
+
+        assert_eq!(commands.drain::<usize>(), vec![1, 2]);
+        //Note(teddy) Commands of another type wait for their own drain
+        assert!(!commands.is_empty());
+        assert_eq!(commands.drain::<String>(), vec![String::from("select")]);
+        assert!(commands.is_empty());
+    }
 
-                new_dimensions.y = height;
-            }
+    #[test]
+    fn test_flushing_ui_commands() {
+        let mut tree = UITree::new();
+        tree.root = Some(StubView::new(
+            "root",
+            Some(vec![StubView::new("save", None), StubView::new("ui_log", Some(vec![])), StubView::new("load", None)]),
+        ));
 
-            Orientation::Horizontal => {
-                let mut width: i32 = 0;
-                let mut view_dimens = ViewDimens::zerod();
+        tree.push_command(UiCommand::AddChild { parent_id: Box::from("ui_log"), view: StubView::new("physics", None) });
+        tree.push_command(UiCommand::SetVisible { id: Box::from("save"), visible: false });
+        //Note(teddy) Nothing changes until the flush
+        assert!(tree.find_view("physics").is_err());
+        assert!(tree.find_view("save").is_ok());
+
+        tree.flush_commands();
+        let log = tree.find_view("ui_log").unwrap().as_container().unwrap();
+        assert_eq!(log.get_view_by_id("physics").unwrap().get_id(), "physics");
+        assert!(!tree.find_view("save").unwrap().is_visible());
+
+        tree.push_command(UiCommand::SetVisible { id: Box::from("save"), visible: true });
+        tree.push_command(UiCommand::RemoveView { id: Box::from("load") });
+        //Note(teddy) A failed command is reported and the rest still apply
+        tree.push_command(UiCommand::RemoveView { id: Box::from("missing") });
+        tree.push_command(UiCommand::RemoveView { id: Box::from("physics") });
+        tree.flush_commands();
+        assert!(tree.find_view("load").is_err());
+        assert!(tree.find_view("physics").is_err());
+        assert!(tree.find_view("save").unwrap().is_visible());
+    }
 
-                for child in self.children.iter() {
-                    view_dimens = child.get_view_dimensions().unwrap_or(ViewDimens::zerod());
-                    new_dimensions.y = std::cmp::max(view_dimens.y, new_dimensions.y);
-                    width += view_dimens.x;
-                }
+    #[test]
+    fn test_new_sizes_are_noticed() {
+        let mut children = vec![StubView::new("log", None), Box::new(StubView::sized("row", ViewPosition::zerod(), Some(ViewDimens::new(100, 20)), None))];
+        let mut sizes = vec![];
+        assert!(children_changed(&children, &mut sizes));
 
-                new_dimensions.x = width;
-            }
-        }
+        //Note(teddy) A child that grew makes its container stack everything again
+        children[1].get_view_object_mut().size = Some(ViewDimens::new(100, 40));
+        assert!(children_changed(&children, &mut sizes));
+        assert!(!children_changed(&children, &mut sizes));
 
-        self.view.size = Some(new_dimensions);
+        allocate(&mut children[1], ViewPosition::new(0, 30), ViewDimens::new(200, 40));
+        assert!(children[1].get_view_object().resized);
+        children[1].get_view_object_mut().resized = false;
 
-        // //Flip the y for this quad
-        // if let Some(position) = self.view.position {
-        //     self.view.position = Some(ViewDimens::new(position.x, position.y));
-        // }
+        allocate(&mut children[1], ViewPosition::new(0, 60), ViewDimens::new(200, 40));
+        assert!(!children[1].get_view_object().resized);
+        assert_eq!(children[1].get_view_object().position, ViewPosition::new(0, 60));
     }
-}
 
-impl View for SimpleUIContainer {
-    fn set_position(&mut self, position: ViewPosition) {
-        self.view.position = position;
+    #[test]
+    fn test_hidden_children_take_no_space() {
+        let mut children = vec![StubView::new("header", None), StubView::new("body", None), StubView::new("footer", None)];
+        let mut sizes = vec![];
+        assert!(children_changed(&children, &mut sizes));
+        assert!(!children_changed(&children, &mut sizes));
+
+        children[1].set_visible(false);
+        assert!(children_changed(&children, &mut sizes));
+
+        //Note(teddy) The hidden body gets no slot, so no spacing either
+        let (positions, _) = stack_children(ViewPosition::zerod(), Orientation::Vertical, 5, &child_extents(&children));
+        assert_eq!(positions.len(), 2);
+        assert!(children.iter().filter(|child| in_flow(child)).map(|child| child.get_id()).eq(["header", "footer"]));
+        //Note(teddy) Hidden children are neither drawn nor offered clicks
+        assert_eq!(draw_order(&children), vec![0, 2]);
     }
 
-    fn as_any(&mut self) -> Box<&mut dyn Any> {
-        Box::new(self)
+    #[test]
+    fn test_section_header_marker() {
+        let font_face = font_face();
+        assert_eq!(section_header("Logs", true, &font_face), "v Logs");
+        assert_eq!(section_header("Logs", false, &font_face), "> Logs");
     }
 
-    fn get_id(&self) -> &str {
-        &(self.view.id)
+    #[test]
+    fn test_border_color_keeps_the_width() {
+        let mut view = StubView::new("asset", None);
+        view.set_border_color([1.0, 0.0, 0.0]);
+        assert_eq!(view.get_view_object().style.border, None);
+
+        view.set_border([0.3, 0.3, 0.3], 2);
+        view.set_border_color([0.0, 0.7, 0.0]);
+        assert_eq!(view.get_view_object().style.border, Some(([0.0, 0.7, 0.0], 2.0)));
+
+        view.set_border([0.3, 0.3, 0.3], 0);
+        assert_eq!(view.get_view_object().style.border, None);
     }
 
-    fn handle_button_click(
-        &mut self,
-        engine: &Engine,
-        button: &Vec<MouseButton>,
-        cords: Cords<f32>,
-    ) -> bool {
-        let container_position = self.view.position;
+    #[test]
+    fn test_background_color_keeps_the_alpha() {
+        let mut view = StubView::new("log", None);
+        assert_eq!(view.get_view_object().background, None);
 
-        // println!(self.get_view_object().)
-        if does_cursor_intersect(
-            &cords,
-            //self.position.unwrap_or(ViewDimens::zerod()),
-            container_position,
-            self.view.size.unwrap_or(ViewDimens::zerod()),
-            0,
-        ) {
-            for view in &mut self.children {
-                view.handle_button_click(engine, button, cords);
-            }
-        }
+        view.set_background_color([0.2, 0.2, 0.2]);
+        assert_eq!(view.get_view_object().background, Some([0.2, 0.2, 0.2, 1.0]));
 
-        true
+        view.set_background(Some([0.1, 0.1, 0.1, 0.5]));
+        view.set_background_color([0.0, 0.4, 0.0]);
+        assert_eq!(view.get_view_object().background, Some([0.0, 0.4, 0.0, 0.5]));
     }
 
-    fn update(&mut self, engine: &Engine) -> UIResult {
-        let quad_size = self.view.size;
-        let default_dimensions = ViewDimens::new(10, 10);
+    #[test]
+    fn test_nested_clips_only_shrink() {
+        let mut stack = ClipStack::new();
+        let panel = ViewRect::new(ViewPosition::new(0, 0), ViewDimens::new(100, 100), 0);
+        let log = ViewRect::new(ViewPosition::new(50, 80), ViewDimens::new(100, 100), 0);
 
-        let quad_size = (
-            (quad_size.unwrap_or(default_dimensions).y) as f32,
-            (quad_size.unwrap_or(default_dimensions).x) as f32,
-        );
+        assert_eq!(stack.push(panel), panel);
+        let inner = stack.push(log);
+        assert_eq!((inner.min, inner.max), (Cords { x: 50.0, y: 80.0 }, Cords { x: 100.0, y: 100.0 }));
 
-        let quad_position = (
-            self.view.position.x as f32,
-            self.view.position.y as f32 + quad_size.0,
-        );
+        //Note(teddy) Popping the log goes back to the panel, popping the panel turns clipping off
+        assert_eq!(stack.pop(), Some(panel));
+        assert_eq!(stack.pop(), None);
+    }
 
-        //TODO(teddy) This initial position will be the position of the container
-        //TODO(teddy) optimize this to prevent recalculations
-        match self.orientation {
-            Orientation::Vertical => {
-                let mut initial_y_position = self.view.position.y;
+    #[test]
+    fn test_views_off_screen_are_culled() {
+        let mut stack = ClipStack::new();
+        let row = |y| ViewRect::new(ViewPosition::new(0, y), ViewDimens::new(200, 20), 0);
 
-                for view in self.children.iter_mut() {
-                    let view_dimensions = view.get_view_dimensions().unwrap_or(ViewDimens::zerod());
-                    view.set_position(ViewPosition::new(self.view.position.x, initial_y_position));
+        //Note(teddy) Nothing is culled until a frame has a viewport
+        assert!(stack.cull(&row(5000)));
 
-                    initial_y_position += view_dimensions.y;
-                    view.update(engine).unwrap();
-                }
-            }
+        stack.begin_frame(Some(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(800, 600), 0)));
+        assert!(stack.cull(&row(590)));
+        assert!(!stack.cull(&row(700)));
 
-            Orientation::Horizontal => {
-                let mut intial_x_position = self.view.position.x;
+        stack.push(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(200, 100), 0));
+        assert!(!stack.cull(&row(300)));
+        stack.pop();
+        assert!(stack.cull(&row(300)));
 
-                for view in self.children.iter_mut() {
-                    let view_dimensions = view.get_view_dimensions().unwrap_or(ViewDimens::zerod());
+        assert_eq!(stack.stats, CullStats { drawn: 2, culled: 2 });
+    }
 
-                    view.set_position(ViewPosition::new(
-                        intial_x_position,
-                        self.view.position.y + view_dimensions.y,
-                    ));
-                    intial_x_position += view_dimensions.x;
-                    view.update(engine).unwrap();
-                }
-            }
-        }
+    #[test]
+    fn test_drags_drop_on_targets_or_the_world() {
+        let at = |x: f32, y: f32| Cords { x, y };
+        let payload = String::from("./assets/objects/cube.obj");
+
+        let mut asset = StubView::sized("asset", ViewPosition::new(0, 0), Some(ViewDimens::new(100, 20)), None);
+        asset.set_draggable(payload.clone());
+        let mut bin = StubView::sized("bin", ViewPosition::new(0, 40), Some(ViewDimens::new(100, 20)), None);
+        bin.get_view_object_mut().on_drop = Some(Box::new(|_, _| ()));
+        let children: Vec<Box<dyn View>> = vec![Box::new(asset), Box::new(bin)];
+
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(StubView::sized("panel", ViewPosition::new(0, 0), Some(ViewDimens::new(100, 100)), Some(children))));
+        let press = |cords| ClickInfo::press(MouseButton::Button1, 1, cords);
+
+        assert!(!tree.press_draggable(&press(at(50.0, 90.0))));
+        assert!(tree.press_draggable(&press(at(10.0, 10.0))));
+
+        //Note(teddy) A small wobble is still a click
+        let mut drag = tree.drag.take().unwrap();
+        drag.move_to(at(12.0, 12.0));
+        assert!(!drag.started);
+        drag.move_to(at(10.0, 50.0));
+        assert!(drag.started);
+
+        assert_eq!(tree.release_drag(drag, at(10.0, 50.0)), None);
+        assert_eq!(tree.drops, vec![(Box::from("bin"), payload.clone())]);
+
+        //Note(teddy) Over the panel but on no target nothing happens, past the panel it's the world's
+        let drag = DragState::new(payload.clone(), press(at(10.0, 10.0)));
+        assert_eq!(tree.release_drag(drag, at(50.0, 90.0)), None);
+        let drag = DragState::new(payload.clone(), press(at(10.0, 10.0)));
+        assert_eq!(tree.release_drag(drag, at(300.0, 50.0)), Some(UiEvent::DroppedOnWorld { payload, cords: at(300.0, 50.0) }));
+    }
 
-        unsafe {
-            draw_quad_with_default_shader(
-                engine,
-                self.view.background_vao as u32,
-                self.view.background_vbo as u32,
-                0.99,
-                quad_position,
-                quad_size,
-                &[0.1, 0.1, 0.1],
-                // &[0.6, 0.3, 0.3],
-            );
-        }
+    #[test]
+    fn test_tweens_move_the_view_and_finish() {
+        use crate::ui::tween::{slide_in, Easing};
 
-        Ok(())
-    }
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(StubView::sized("panel", ViewPosition::new(0, 0), Some(ViewDimens::new(100, 100)), None)));
 
-    fn compute_intersect_with_cursor_cords(&mut self, _engine: &Engine, cords: &Cords<f32>) {
-        //TODO(teddy) implement a simple ui container
+        tree.animate(slide_in("panel", -100, 0, 1.0).with_easing(Easing::Linear).on_complete(|_| ()));
+        assert_eq!(tree.find_view("panel").unwrap().get_view_object().position.x, -100);
+
+        tree.update(0.5);
+        assert_eq!(tree.find_view("panel").unwrap().get_view_object().position.x, -50);
+        assert!(tree.is_animating("panel"));
+
+        //Note(teddy) The last frame lands on the end value, the callback waits for dispatch
+        tree.update(0.75);
+        assert_eq!(tree.find_view("panel").unwrap().get_view_object().position.x, 0);
+        assert!(!tree.is_animating("panel"));
+        assert_eq!(tree.finished_tweens.len(), 1);
     }
 
-    fn receive_cursor_cords(&mut self, engine: &Engine, cords: Cords<f32>) {
-        self.compute_intersect_with_cursor_cords(&engine, &cords);
+    #[test]
+    fn test_hover_transitions_wait_for_dispatch() {
+        let at = |x: f32, y: f32| Cords { x, y };
+        let mut plain = GridContainer::new(Box::from("plain"), 1, ViewPosition::zerod(), 0);
+        let mut grid = GridContainer::new(Box::from("grid"), 1, ViewPosition::zerod(), 0);
+        for view in [&mut plain, &mut grid] {
+            view.get_view_object_mut().size = Some(ViewDimens::new(100, 100));
+        }
+        grid.on_hover = Some(Box::new(|_ctx: &mut UiContext, view: &mut GridContainer| view.set_background_color([0.0, 0.4, 0.0])));
 
-        for view in self.children.iter_mut() {
-            view.receive_cursor_cords(engine, cords);
+        //Note(teddy) The callbacks need the UiContext, until dispatch the transitions are only queued
+        for cords in [at(10.0, 10.0), at(20.0, 20.0), at(200.0, 10.0), at(50.0, 50.0)] {
+            plain.update_hover(&cords);
+            grid.update_hover(&cords);
         }
+        assert!(plain.hover_events.is_empty());
+        assert_eq!(grid.hover_events, vec![CursorEvent::Enter, CursorEvent::Exit, CursorEvent::Enter]);
     }
 
-    fn get_position(&self) -> Option<ViewPosition> {
-        None
+    #[test]
+    fn test_quick_presses_on_one_view_double_click() {
+        let at = |x: f32, y: f32| Cords { x, y };
+        let children: Vec<Box<dyn View>> = vec![
+            Box::new(StubView::sized("save", ViewPosition::new(0, 0), Some(ViewDimens::new(100, 20)), None)),
+            Box::new(StubView::sized("load", ViewPosition::new(0, 20), Some(ViewDimens::new(100, 20)), None)),
+        ];
+
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(StubView::sized("panel", ViewPosition::new(0, 0), Some(ViewDimens::new(100, 100)), Some(children))));
+        let start = Instant::now();
+        let later = |ms: u64| start + Duration::from_millis(ms);
+
+        assert_eq!(tree.count_press(MouseButton::Button1, at(10.0, 10.0), start).count, 1);
+        assert_eq!(tree.count_press(MouseButton::Button1, at(12.0, 11.0), later(200)).count, 2);
+
+        //Note(teddy) Too slow, too far, another button or another view all start over
+        assert_eq!(tree.count_press(MouseButton::Button1, at(12.0, 11.0), later(600)).count, 1);
+        assert_eq!(tree.count_press(MouseButton::Button1, at(30.0, 11.0), later(700)).count, 1);
+        assert_eq!(tree.count_press(MouseButton::Button2, at(30.0, 11.0), later(800)).count, 1);
+        assert_eq!(tree.count_press(MouseButton::Button1, at(30.0, 18.0), later(850)).count, 1);
+        assert_eq!(tree.count_press(MouseButton::Button1, at(30.0, 21.0), later(900)).count, 1);
     }
 
-    fn get_view_object(&self) -> &ViewObject {
-        &self.view
+    #[test]
+    fn test_menus_open_inside_the_viewport() {
+        let viewport = ViewRect::new(ViewPosition::new(0, 0), ViewDimens::new(800, 600), 0);
+        let menu = ViewDimens::new(120, 90);
+
+        assert_eq!(clamp_to_viewport(ViewPosition::new(100, 100), menu, viewport), ViewPosition::new(100, 100));
+        assert_eq!(clamp_to_viewport(ViewPosition::new(750, 580), menu, viewport), ViewPosition::new(680, 510));
+        assert_eq!(clamp_to_viewport(ViewPosition::new(-20, -5), menu, viewport), ViewPosition::new(0, 0));
+
+        //Note(teddy) A menu bigger than the window keeps its first rows on screen
+        let tall = ViewDimens::new(120, 900);
+        assert_eq!(clamp_to_viewport(ViewPosition::new(100, 300), tall, viewport), ViewPosition::new(100, 0));
     }
 
-    fn get_view_object_mut(&mut self) -> &mut ViewObject {
-        &mut self.view
+    #[test]
+    fn test_arrow_keys_move_the_selection() {
+        let mut rows = vec![StubView::new("first", None), StubView::new("hidden", None), StubView::new("last", None)];
+        rows[1].set_visible(false);
+
+        let mut selection = ListSelection::default();
+        assert!(!selection.receive_key(&mut rows, Key::Down));
+
+        selection.selectable = true;
+        assert!(selection.receive_key(&mut rows, Key::Down));
+        assert_eq!(selection.selected_index(), Some(0));
+        //Note(teddy) The hidden row is stepped over
+        selection.receive_key(&mut rows, Key::Down);
+        assert_eq!(selection.selected_index(), Some(2));
+        selection.receive_key(&mut rows, Key::Down);
+        assert_eq!(selection.selected_index(), Some(2));
+
+        selection.wrap = true;
+        selection.receive_key(&mut rows, Key::Down);
+        assert_eq!(selection.selected_index(), Some(0));
+        selection.receive_key(&mut rows, Key::End);
+        assert_eq!(selection.selected_index(), Some(2));
+        assert!(!selection.receive_key(&mut rows, Key::A));
+
+        //Note(teddy) A row 40 pixels tall at 180 in a 100 pixel view scrolls so its bottom is at the edge
+        assert_eq!(scroll_to_show(0.0, 180, 40, 100), 120.0);
+        assert_eq!(scroll_to_show(120.0, 20, 40, 100), 20.0);
+        assert_eq!(scroll_to_show(50.0, 60, 40, 100), 50.0);
     }
 
-    fn get_element_by_id(&mut self, id: &str) -> Option<Rc<&mut dyn View>> {
-        if self.get_id() == id {
-            return Some(Rc::new(self));
-        }
+    #[test]
+    fn test_tree_rows_follow_the_expanded_nodes() {
+        let node = |label: &str, id: usize, children: Vec<TreeNode>| TreeNode { children, ..TreeNode::new(label.to_string(), id) };
+        let mut nodes = vec![
+            node("crate.obj", 100, vec![node("Entity 1", 1, vec![]), node("Entity 2", 2, vec![node("Entity 3", 3, vec![])])]),
+            node("Ropes", 99, vec![]),
+        ];
+
+        let rows = |nodes: &[TreeNode]| {
+            let mut rows = vec![];
+            flatten_tree(nodes, 0, &mut rows);
+            rows.iter().map(|row| (row.id, row.depth, row.toggle)).collect::<Vec<_>>()
+        };
+        assert_eq!(rows(&nodes), vec![(100, 0, Some(false)), (99, 0, None)]);
 
-        for element in &mut self.children {
-            if let Some(el) = element.get_element_by_id(id) {
-                return Some(el);
-            }
+        assert!(expand_path(&mut nodes, 3));
+        assert_eq!(rows(&nodes), vec![(100, 0, Some(true)), (1, 1, None), (2, 1, Some(true)), (3, 2, None), (99, 0, None)]);
+
+        assert!(!expand_path(&mut nodes, 42));
+        find_node_mut(&mut nodes, 100).unwrap().expanded = false;
+        assert_eq!(rows(&nodes).len(), 2);
+    }
+
+    #[test]
+    fn test_progress_fill_eases_to_the_target() {
+        let mut shown = 0.0;
+        let mut previous = shown;
+        for _ in 0..10 {
+            shown = approach_fraction(shown, 0.5, 0.05);
+            assert!(shown > previous && shown <= 0.5);
+            previous = shown;
         }
 
-        None
+        //Note(teddy) A frame with no time passed leaves it, a long one lands on the target
+        assert_eq!(approach_fraction(0.2, 0.5, 0.0), 0.2);
+        assert_eq!(approach_fraction(0.2, 0.5, 5.0), 0.5);
     }
-}
 
-impl ViewContainer for SimpleUIContainer {
-    fn add_child(&mut self, child: Box<dyn View>) {
-        self.children.push(child);
-        self.recalculate_dimensions();
+    #[test]
+    fn test_cursor_icon_of_the_view_under_it() {
+        let resources = UiResources::new(Rc::new(font_face()));
+        let mut panel = SimpleUIContainer::new(Box::from("panel"), None, ViewPosition::zerod(), Orientation::Vertical, 0, 1.0, false);
+        panel.add_child(Box::new(TextView::new(Box::from("label"), String::from("ae"), ViewPosition::zerod(), 1.0, 0, &resources)));
+        panel.add_child(Box::new(TextInputView::new(Box::from("input"), 100, ViewPosition::zerod(), 0, &resources)));
+
+        let mut tree = UITree::new();
+        tree.root = Some(Box::new(panel));
+        tree.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(800, 600), 0));
+        tree.update_layout();
+
+        let center = |id: &str| {
+            let bounds = tree.find_view(id).unwrap().bounds();
+            Cords { x: (bounds.min.x + bounds.max.x) / 2.0, y: (bounds.min.y + bounds.max.y) / 2.0 }
+        };
+        assert_eq!(tree.cursor_icon_at(&center("input")), CursorIcon::IBeam);
+        assert_eq!(tree.cursor_icon_at(&center("label")), CursorIcon::Arrow);
+
+        //Note(teddy) The modal takes the cursor even where it doesn't cover the input
+        let input = center("input");
+        let mut dialog = ConfirmDialog::new(Box::from("dialog"), String::from("ae"), String::from("a"), String::from("e"), &resources);
+        dialog.arrange();
+        let button = dialog.confirm.bounds().min;
+        tree.modals.push(Box::new(dialog));
+        assert_eq!(tree.cursor_icon_at(&Cords { x: button.x + 1.0, y: button.y + 1.0 }), CursorIcon::Hand);
+        assert_eq!(tree.cursor_icon_at(&input), CursorIcon::Arrow);
     }
 
-    fn get_view_by_id(&self, _child_id: &str) -> Result<&Box<dyn View>, UIError> {
-        unimplemented!();
+    #[test]
+    fn test_split_pane_shares_out_its_space() {
+        assert_eq!(split_sizes(404, 0.5, 4, (0, 0)), (200, 200));
+        assert_eq!(split_sizes(404, 0.1, 4, (100, 0)), (100, 300));
+        assert_eq!(split_sizes(404, 0.9, 4, (0, 100)), (300, 100));
+        //Note(teddy) When both minimums don't fit the first pane gives way
+        assert_eq!(split_sizes(204, 0.5, 4, (150, 150)), (50, 150));
+        assert_eq!(split_ratio_at(302.0, 404, 4, (0, 0)), 0.75);
+        assert_eq!(split_ratio_at(0.0, 404, 4, (100, 0)), 0.25);
+
+        let second = Box::new(StubView::sized("second", ViewPosition::zerod(), Some(ViewDimens::new(200, 300)), None));
+        let mut split = SplitPane::new(Box::from("split"), Orientation::Horizontal, StubView::new("first", None), second, ViewPosition::zerod());
+        split.layout(ViewRect::new(ViewPosition::zerod(), ViewDimens::new(404, 300), 0));
+        assert_eq!(split.children[1].get_view_object().position, ViewPosition::new(204, 0));
+        assert_eq!(split.children[1].get_view_object().allocated, Some(ViewDimens::new(200, 300)));
+
+        //Note(teddy) A few pixels off the divider still grab it rather than the pane under the cursor
+        let near_divider = Cords { x: 199.0, y: 150.0 };
+        assert!(split.on_divider(&near_divider));
+        assert_eq!(split.cursor_icon_at(&near_divider), Some(CursorIcon::HResize));
+        assert!(split.object_at(&near_divider, &|_| true).map_or(false, |object| &*object.id == "split"));
+        assert!(split.object_at(&Cords { x: 300.0, y: 150.0 }, &|_| true).map_or(false, |object| &*object.id == "second"));
+
+        split.set_min_sizes(0, 150);
+        split.drag_to(&Cords { x: 380.0, y: 0.0 });
+        assert_eq!(split.pane_sizes(), (250, 150));
+        split.update_layout();
+        assert_eq!(split.children[1].get_view_object().position, ViewPosition::new(254, 0));
     }
 
-    fn remove_child(&mut self, child_id: &str) -> UIResult {
-        if let Some(index) = self
-            .children
-            .iter()
-            .position(|child| child.get_id() == child_id)
-        {
-            self.children.remove(index);
-            Ok(())
-        } else {
-            Err(UIError::ViewNotFound)
-        }
+    #[test]
+    fn test_tab_view_shows_only_the_active_tab() {
+        let resources = UiResources::new(Rc::new(font_face()));
+        let mut tabs = TabView::new(Box::from("tabs"), ViewPosition::zerod(), 0, &resources);
+        tabs.add_tab(String::from("aa"), Box::new(StubView::sized("assets", ViewPosition::zerod(), Some(ViewDimens::new(100, 50)), None)));
+        tabs.add_tab(String::from("ee"), Box::new(StubView::sized("scene", ViewPosition::zerod(), Some(ViewDimens::new(100, 50)), None)));
+        tabs.update_layout();
+
+        let visible = |tabs: &TabView, id: &str| tabs.get_view_by_id(id).unwrap().is_visible();
+        assert_eq!(tabs.active_tab(), 0);
+        assert!(visible(&tabs, "assets") && !visible(&tabs, "scene"));
+
+        //Note(teddy) The hidden tab sits under the shown one and still can't be reached
+        let content = Cords { x: 50.0, y: 30.0 };
+        assert!(tabs.object_at(&content, &|_| true).map_or(false, |object| &*object.id == "assets"));
+
+        let second_header = tabs.container.children[0].as_container().unwrap().get_view_by_id("tabs_tab_1").unwrap().bounds().min;
+        let header_cords = Cords { x: second_header.x + 1.0, y: second_header.y + 1.0 };
+        assert_eq!(tabs.header_at(&header_cords), Some(1));
+        assert_eq!(tabs.cursor_icon_at(&header_cords), Some(CursorIcon::Hand));
+
+        tabs.set_active_tab(1);
+        assert!(!visible(&tabs, "assets") && visible(&tabs, "scene"));
+
+        //Note(teddy) Removing the shown tab shows the one that takes its place
+        tabs.add_tab(String::from("a"), StubView::new("logs", None));
+        assert!(tabs.remove_tab(1).map_or(false, |view| view.get_id() == "scene"));
+        assert_eq!((tabs.tab_count(), tabs.active_tab()), (2, 1));
+        assert!(visible(&tabs, "logs"));
+        assert!(tabs.remove_tab(5).is_none());
+    }
+
+    #[test]
+    fn test_views_size_to_the_font_they_are_built_with() {
+        let resources = UiResources::new(Rc::new(font_face()));
+
+        let mut text = TextView::new(Box::from("label"), String::from("aa ee"), ViewPosition::zerod(), 1.0, 4, &resources);
+        assert_eq!(text.get_view_dimensions(), Some(ViewDimens::new(39 + 8, 12 + 8)));
+
+        text.set_max_width(Some(40), TextOverflow::Wrap);
+        text.set_text(String::from("aa ee aaa"));
+        assert_eq!(text.get_view_dimensions(), Some(ViewDimens::new(39 + 8, 24 + 8)));
+
+        let checkbox = CheckboxView::new(Box::from("check"), Some(String::from("ae")), false, ViewPosition::zerod(), 0, &resources);
+        assert_eq!(checkbox.get_view_dimensions(), Some(checkbox_dimensions(12, Some(16))));
     }
 }
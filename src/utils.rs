@@ -1,6 +1,9 @@
 use nalgebra::{Matrix4, Vector3, Vector4};
 use std::collections::LinkedList;
 use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use crate::core::ViewPortDimensions;
 
@@ -13,17 +16,148 @@ pub fn get_at_index<T>(list: &LinkedList<T>, index: usize) -> Option<&T> {
     None
 }
 
-#[derive(Debug, Copy, Clone)]
+///CRC-32 (IEEE), the checksum used by PNG chunks
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0xFFFF_FFFF, bytes) ^ 0xFFFF_FFFF
+}
+
+///Continue a running CRC-32 so checksums can be computed over several slices
+pub fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
+
+pub fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+///Writes next to the target and renames over it, a crash mid write leaves the old file intact
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| format!("{}", e))?;
+        }
+    }
+
+    let temporary = temporary_path(path);
+    let mut file = File::create(&temporary).map_err(|e| format!("{}: {}", temporary.display(), e))?;
+    file.write_all(bytes).map_err(|e| format!("{}: {}", temporary.display(), e))?;
+    file.sync_all().map_err(|e| format!("{}: {}", temporary.display(), e))?;
+
+    fs::rename(&temporary, path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+///SplitMix64, small and seedable so generated content is identical on every run
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    ///Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    pub fn index(&mut self, length: usize) -> usize {
+        (self.next_u64() % length as u64) as usize
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Cords<T: fmt::Debug> {
     pub(crate) x: T,
     pub(crate) y: T,
 }
 
+///Rectangle in window cordinates (origin at the top left, same space as the cursor)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ViewportRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ViewportRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, cords: &Cords<f32>) -> bool {
+        cords.x >= self.x
+            && cords.x <= self.x + self.width
+            && cords.y >= self.y
+            && cords.y <= self.y + self.height
+    }
+
+    ///Maps window cordinates to normalized device cordinates of this rect.
+    ///Cordinates outside the rect don't belong to it and return None
+    pub fn to_ndc(&self, cords: &Cords<f32>) -> Option<Cords<f32>> {
+        if !self.contains(cords) || self.width <= 0.0 || self.height <= 0.0 {
+            return None;
+        }
+
+        Some(Cords {
+            x: (2.0 * (cords.x - self.x)) / self.width - 1.0,
+            y: 1.0 - (2.0 * (cords.y - self.y)) / self.height,
+        })
+    }
+
+    pub fn from_ndc(&self, ndc: &Cords<f32>) -> Cords<f32> {
+        Cords {
+            x: self.x + (ndc.x + 1.0) * (self.width / 2.0),
+            y: self.y + (1.0 - ndc.y) * (self.height / 2.0),
+        }
+    }
+
+    ///Converts the rect into framebuffer pixels with OpenGL's bottom left origin, ready for glViewport
+    pub fn to_framebuffer_pixels(
+        &self,
+        window: ViewPortDimensions,
+        framebuffer: ViewPortDimensions,
+    ) -> (i32, i32, i32, i32) {
+        let scale_x = framebuffer.width as f32 / window.width.max(1) as f32;
+        let scale_y = framebuffer.height as f32 / window.height.max(1) as f32;
+
+        let x = (self.x * scale_x).round() as i32;
+        let width = (self.width * scale_x).round() as i32;
+        let height = (self.height * scale_y).round() as i32;
+        let y = framebuffer.height - (self.y * scale_y).round() as i32 - height;
+
+        (x, y, width, height)
+    }
+}
+
 //TODO(Teddy) Maybe will include the object's local vector space for rotation of markers
 //This function will be used to generate cordinates for screen markers
 #[inline]
 pub fn compute_world_space_to_screen_space(
-    screen_dimensions: ViewPortDimensions,
+    viewport: ViewportRect,
     object_world_position: &Vector3<f32>,
     view_matrix: &Matrix4<f32>,
     perspective_matrix: &Matrix4<f32>,
@@ -35,23 +169,192 @@ pub fn compute_world_space_to_screen_space(
         1.0,
     );
 
-    let mut world_position_mapped_to_screen_position: Vector4<f32> =
+    let world_position_mapped_to_screen_position: Vector4<f32> =
         (perspective_matrix * view_matrix) * position_to_vec4;
-    world_position_mapped_to_screen_position = world_position_mapped_to_screen_position;
 
+    //Note(teddy) Perspective divide is by w, dividing by z skews points away from the center
     let screen_cords =
-        world_position_mapped_to_screen_position.xy() / world_position_mapped_to_screen_position.z;
+        world_position_mapped_to_screen_position.xy() / world_position_mapped_to_screen_position.w;
+
+    viewport.from_ndc(&Cords {
+        x: screen_cords.x,
+        y: screen_cords.y,
+    })
+}
 
-    let ViewPortDimensions { width, height } = screen_dimensions;
+///Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    ///Smallest box around the points, None when there are none
+    pub fn from_points<I: IntoIterator<Item = Vector3<f32>>>(points: I) -> Option<Self> {
+        points.into_iter().fold(None, |aabb: Option<Aabb>, point| match aabb {
+            Some(aabb) => Some(Aabb { min: aabb.min.inf(&point), max: aabb.max.sup(&point) }),
+            None => Some(Aabb { min: point, max: point }),
+        })
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn diagonal(&self) -> f32 {
+        (self.max - self.min).norm()
+    }
+
+    ///Box around this one once it's transformed, rotations make it grow instead of turn
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Aabb {
+        let corners = (0..8).map(|i| {
+            let corner = Vector4::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+                1.0,
+            );
+            (matrix * corner).xyz()
+        });
+
+        Aabb::from_points(corners).unwrap()
+    }
+}
+
+///The six planes of the frustum as (a, b, c, d), a point is on the inside of a plane when a*x + b*y + c*z + d >= 0
+pub fn frustum_planes(view_projection: &Matrix4<f32>) -> [Vector4<f32>; 6] {
+    let row = |i: usize| view_projection.row(i).transpose();
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+    [w + x, w - x, w + y, w - y, w + z, w - z]
+}
+
+///True when the box is completely on the outside of one of the planes
+pub fn aabb_outside_frustum(aabb: &Aabb, planes: &[Vector4<f32>; 6]) -> bool {
+    planes.iter().any(|plane| {
+        //Note(teddy) The corner furthest along the normal, when even it is outside the whole box is
+        let corner = Vector3::new(
+            if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+            if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+            if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+        );
+
+        plane.xyz().dot(&corner) + plane.w < 0.0
+    })
+}
+
+///How far from a sphere of the radius a camera has to be for all of it to fit in view, the narrower of
+///the vertical fov and the horizontal one the aspect ratio gives decides
+pub fn framing_distance(radius: f32, fov: f32, aspect: f32) -> f32 {
+    let half_vertical = fov / 2.0;
+    let half_horizontal = (half_vertical.tan() * aspect).atan();
+
+    radius / half_vertical.min(half_horizontal).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    //Note(teddy) HiDPI window with a 250px panel docked on the left
+    fn docked_viewport() -> (ViewportRect, ViewPortDimensions, ViewPortDimensions) {
+        (
+            ViewportRect::new(250.0, 0.0, 750.0, 600.0),
+            ViewPortDimensions { width: 1000, height: 600 },
+            ViewPortDimensions { width: 2000, height: 1200 },
+        )
+    }
+
+    #[test]
+    fn test_cursor_to_ndc_in_docked_viewport() {
+        let (rect, _, _) = docked_viewport();
 
-    let x = screen_cords.x;
-    let y = screen_cords.y;
+        let center = rect.to_ndc(&Cords { x: 625.0, y: 300.0 }).unwrap();
+        assert!(center.x.abs() < 1e-6 && center.y.abs() < 1e-6);
+
+        let top_left = rect.to_ndc(&Cords { x: 250.0, y: 0.0 }).unwrap();
+        assert_eq!((top_left.x, top_left.y), (-1.0, 1.0));
+
+        let bottom_right = rect.to_ndc(&Cords { x: 1000.0, y: 600.0 }).unwrap();
+        assert_eq!((bottom_right.x, bottom_right.y), (1.0, -1.0));
+
+        let quarter = rect.to_ndc(&Cords { x: 437.5, y: 450.0 }).unwrap();
+        assert!((quarter.x + 0.5).abs() < 1e-6 && (quarter.y + 0.5).abs() < 1e-6);
+
+        //Note(teddy) Clicks on the panel belong to the UI
+        assert!(rect.to_ndc(&Cords { x: 100.0, y: 300.0 }).is_none());
+    }
+
+    #[test]
+    fn test_ndc_round_trip() {
+        let (rect, _, _) = docked_viewport();
+        let cords = Cords { x: 812.0, y: 123.0 };
+        let back = rect.from_ndc(&rect.to_ndc(&cords).unwrap());
+
+        assert!((back.x - cords.x).abs() < 1e-3 && (back.y - cords.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_seeded_rng_is_repeatable() {
+        let mut a = SeededRng::new(42);
+        let mut b = SeededRng::new(42);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..8).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(first[0], SeededRng::new(43).next_u64());
+
+        for _ in 0..1000 {
+            let value = a.range(-0.5, 0.5);
+            assert!(value >= -0.5 && value < 0.5);
+            assert!(a.index(3) < 3);
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32_update(crc32_update(0xFFFF_FFFF, b"1234"), b"56789") ^ 0xFFFF_FFFF, 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn test_viewport_in_framebuffer_pixels() {
+        let (rect, window, framebuffer) = docked_viewport();
+        assert_eq!(rect.to_framebuffer_pixels(window, framebuffer), (500, 0, 1500, 1200));
+
+        let top_half = ViewportRect::new(0.0, 0.0, 1000.0, 300.0);
+        assert_eq!(top_half.to_framebuffer_pixels(window, framebuffer), (0, 600, 2000, 600));
+    }
+
+    #[test]
+    fn test_aabb_against_the_frustum() {
+        let unit = Aabb::from_points(vec![Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0)]).unwrap();
+        let at = |x: f32, y: f32, z: f32| unit.transformed(&Matrix4::new_translation(&Vector3::new(x, y, z)));
+
+        //Note(teddy) Looking down -z from the origin with a 90 degree fov
+        let view = Matrix4::look_at_rh(&Point3::origin(), &Point3::new(0.0, 0.0, -1.0), &Vector3::y());
+        let planes = frustum_planes(&(Matrix4::new_perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0) * view));
+
+        assert!(!aabb_outside_frustum(&at(0.0, 0.0, -10.0), &planes));
+        assert!(aabb_outside_frustum(&at(0.0, 0.0, 10.0), &planes));
+        assert!(aabb_outside_frustum(&at(-20.0, 0.0, -10.0), &planes));
+        assert!(aabb_outside_frustum(&at(0.0, 0.0, -200.0), &planes));
+
+        //Note(teddy) Straddling the left plane still counts as visible
+        assert!(!aabb_outside_frustum(&at(-10.5, 0.0, -10.0), &planes));
+
+        let rotated = unit.transformed(&Matrix4::new_rotation(Vector3::new(0.0, std::f32::consts::FRAC_PI_4, 0.0)));
+        assert!((rotated.max.x - 2.0f32.sqrt()).abs() < 1e-5);
+    }
 
-    let cord_x = (x + 1.0) * (width as f32 / 2.0);
-    let cord_y = (y - 1.0) * (height as f32 / -2.0);
+    #[test]
+    fn test_framing_distance_fits_the_narrower_side() {
+        let fov = std::f32::consts::FRAC_PI_2;
 
-    Cords {
-        x: cord_x,
-        y: cord_y,
+        //Note(teddy) At a 90 degree fov a unit sphere touches the edges from sqrt(2) away
+        assert!((framing_distance(1.0, fov, 1.0) - 2.0f32.sqrt()).abs() < 1e-5);
+        assert!((framing_distance(1.0, fov, 2.0) - 2.0f32.sqrt()).abs() < 1e-5);
+        assert!(framing_distance(1.0, fov, 0.5) > framing_distance(1.0, fov, 1.0));
     }
 }